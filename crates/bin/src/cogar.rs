@@ -2,16 +2,23 @@
 
 use axum::{
     extract::{ws::{WebSocket, WebSocketUpgrade}, ConnectInfo, State},
-    http::{header, StatusCode, Uri},
+    http::{header, HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Response},
     routing::get,
+    serve::{Listener, ListenerExt},
     Router,
 };
-use futures_util::{SinkExt, StreamExt};
+use futures_util::StreamExt;
 use rust_embed::RustEmbed;
+use server::config::TlsConfig;
+use server::metrics::{Channel, HandshakeStage};
+use server::tls::{ReloadSignal, TlsAcceptor, TlsStream};
+use server::upgrade::UpgradePolicy;
 use std::net::SocketAddr;
 use std::sync::{Arc, OnceLock};
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, RwLock};
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tracing::{error, info, warn};
@@ -58,15 +65,104 @@ fn generate_skins_list() -> String {
 
 #[derive(Clone)]
 struct AppState {
-    game_state: Arc<RwLock<server::server::game::GameState>>,
-    chat_tx: broadcast::Sender<server::ChatBroadcast>,
-    lb_tx: broadcast::Sender<server::LeaderboardBroadcast>,
-    world_tx: broadcast::Sender<server::WorldUpdateBroadcast>,
-    targeted_tx: broadcast::Sender<server::TargetedMessage>,
+    rooms: Arc<server::RoomRegistry>,
+    upgrade_policy: Arc<UpgradePolicy>,
+}
+
+/// Hands axum the connections that finished the TLS handshake. Handshakes
+/// run in their own tasks so a slow peer can't hold up the accept loop;
+/// SIGHUP reloads the certificate.
+struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    fn spawn(
+        listener: TcpListener,
+        tls_config: TlsConfig,
+        mut acceptor: TlsAcceptor,
+        handshake_timeout: Duration,
+        rooms: Arc<server::RoomRegistry>,
+    ) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let mut reload = ReloadSignal::new()?;
+        let (tx, incoming) = mpsc::channel(64);
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            // Usually out of file descriptors; give it a moment
+                            warn!("Accept failed: {}", e);
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                    },
+                    _ = reload.recv() => {
+                        server::tls::reload_acceptor(&tls_config, &mut acceptor);
+                        continue;
+                    }
+                    _ = tx.closed() => return,
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                let rooms = Arc::clone(&rooms);
+                tokio::spawn(async move {
+                    match server::tls::accept(&acceptor, stream, handshake_timeout).await {
+                        Ok(Some(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(None) => {
+                            warn!("Dropping {}: no TLS handshake within {:?}", addr, handshake_timeout);
+                            rooms.get("/").metrics.handshake_timed_out(HandshakeStage::Upgrade);
+                        }
+                        Err(e) => warn!("TLS handshake with {} failed: {}", addr, e),
+                    }
+                });
+            }
+        });
+        Ok(Self { incoming, local_addr })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(accepted) => accepted,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+    info!("Shutting down");
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // Dump the effective config before logging is set up so stdout stays clean
+    if std::env::args().any(|arg| arg == "--write-config") {
+        let config = server::Config::load()?;
+        print!("{}", toml::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    // Summarize a replay recording and exit
+    let args: Vec<String> = std::env::args().collect();
+    if server::replay::ReplayInfo::inspect_from_args(&args)? {
+        return Ok(());
+    }
+
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -78,6 +174,13 @@ async fn main() -> anyhow::Result<()> {
 
     // Load server configuration
     let config = server::Config::load()?;
+    if let Err(errors) = config.validate() {
+        error!("Invalid configuration:");
+        for err in &errors {
+            error!("  {}", err);
+        }
+        anyhow::bail!("config.toml has {} invalid setting(s)", errors.len());
+    }
     info!("Loaded configuration");
     info!("  Port: {}", config.server.port);
     info!("  Border: {}x{}", config.border.width, config.border.height);
@@ -88,46 +191,44 @@ async fn main() -> anyhow::Result<()> {
     info!("Found {} skins: {}", skins_list.split(',').filter(|s| !s.is_empty()).count(), skins_list);
     SKINS_LIST.set(skins_list).ok();
 
-    // Create broadcast channels
-    let (chat_tx, _) = broadcast::channel::<server::ChatBroadcast>(100);
-    let (lb_tx, _) = broadcast::channel::<server::LeaderboardBroadcast>(10);
-    let (world_tx, _) = broadcast::channel::<server::WorldUpdateBroadcast>(5);
-    let (targeted_tx, _) = broadcast::channel::<server::TargetedMessage>(100);
-
-    // Create shared game state
-    let game_state = Arc::new(RwLock::new(server::server::game::GameState::new(
-        &config,
-        chat_tx.clone(),
-        lb_tx.clone(),
-        world_tx.clone(),
-        targeted_tx.clone(),
-    )));
-
-    // Start the game loop
-    let game_loop_state = Arc::clone(&game_state);
-    let tick_interval = config.server.tick_interval_ms;
-    tokio::spawn(async move {
-        server::server::game::run_game_loop(game_loop_state, tick_interval).await;
-    });
+    // Start the first room from a world dump when asked to
+    let dump = server::dump::WorldDump::from_args(&args)?;
+
+    // Create the game worlds, one per room
+    let rooms = Arc::new(server::RoomRegistry::start(&config, dump).await);
+
+    if let Some(port) = config.server.metrics_port {
+        let bind = config.server.bind.clone();
+        let rooms = Arc::clone(&rooms);
+        tokio::spawn(async move {
+            if let Err(e) = server::metrics::serve(&bind, port, rooms).await {
+                error!("Metrics endpoint failed: {}", e);
+            }
+        });
+    }
 
     // Create app state
     let state = AppState {
-        game_state,
-        chat_tx,
-        lb_tx,
-        world_tx,
-        targeted_tx,
+        rooms: Arc::clone(&rooms),
+        upgrade_policy: Arc::new(UpgradePolicy::new(&config.server)),
     };
 
     // Build the axum router
-    let app = Router::new()
-        // WebSocket game endpoint
+    let mut app = Router::new()
+        // WebSocket game endpoint (the first room)
         .route("/game", get(websocket_handler))
         // Static file serving (index.html, CSS, WASM, etc.)
         .route("/", get(serve_index))
         .route("/index.html", get(serve_index))
         .route("/main.js", get(serve_main_js))
-        .route("/skinList.txt", get(serve_skins_txt))
+        .route("/skinList.txt", get(serve_skins_txt));
+    // Each configured room on its own path
+    for room in rooms.rooms() {
+        if !["/", "/game", "/index.html", "/main.js", "/skinList.txt"].contains(&room.path.as_str()) {
+            app = app.route(&room.path, get(websocket_handler));
+        }
+    }
+    let app = app
         .fallback(static_handler)
         .layer(
             ServiceBuilder::new()
@@ -137,13 +238,40 @@ async fn main() -> anyhow::Result<()> {
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let listener = TcpListener::bind(&addr).await?;
+    let tls = config.server.tls.as_ref().map(server::tls::load_acceptor).transpose()?;
+    let (http, ws) = if tls.is_some() { ("https", "wss") } else { ("http", "ws") };
     
-    info!("Server running on http://{}", addr);
-    info!("Game WebSocket endpoint: ws://{}/game", addr);
+    info!("Server running on {}://{}", http, addr);
+    info!("Game WebSocket endpoint: {}://{}/game", ws, addr);
+    if rooms.rooms().len() > 1 {
+        for room in rooms.rooms() {
+            info!("  Room {}://{}{}", ws, addr, room.path);
+        }
+    }
 
-    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
-        .await?;
+    let tracker = if config.tracker.url.is_empty() {
+        None
+    } else {
+        Some(server::tracker::spawn(&config, addr, Arc::clone(&rooms))?)
+    };
+
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    match (tls, &config.server.tls) {
+        (Some(acceptor), Some(tls_config)) => {
+            let handshake_timeout = Duration::from_secs(config.server.handshake_timeout);
+            let listener = TlsListener::spawn(listener, tls_config.clone(), acceptor, handshake_timeout, Arc::clone(&rooms))?
+                // Connect info for a custom listener comes through TapIo
+                .tap_io(|_| {});
+            axum::serve(listener, service).with_graceful_shutdown(shutdown_signal()).await?;
+        }
+        _ => axum::serve(listener, service).with_graceful_shutdown(shutdown_signal()).await?,
+    }
+    if let Some(tracker) = tracker {
+        tracker.abort();
+    }
+    rooms.save_highscores().await;
+    rooms.save_heatmaps().await;
 
     Ok(())
 }
@@ -153,10 +281,18 @@ async fn websocket_handler(
     ws: WebSocketUpgrade,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    info!("WebSocket connection from {}", addr);
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let origin = headers.get(header::ORIGIN).and_then(|origin| origin.to_str().ok());
+    if let Err(reason) = state.upgrade_policy.check(origin, uri.query()) {
+        warn!("Refused upgrade from {} to {}: {:?} check failed", addr, uri.path(), reason);
+        state.rooms.get(uri.path()).metrics.upgrade_rejected(reason);
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    info!("WebSocket connection from {} on {}", addr, uri.path());
     
-    ws.on_upgrade(move |socket| handle_websocket(socket, addr, state))
+    ws.on_upgrade(move |socket| handle_websocket(socket, addr, state, uri.path().to_string()))
 }
 
 /// Handle individual WebSocket connections
@@ -164,63 +300,124 @@ async fn handle_websocket(
     socket: WebSocket,
     addr: SocketAddr,
     state: AppState,
+    path: String,
 ) {
-    info!("New game connection from {}", addr);
+    let room = state.rooms.get(&path);
+    info!("New game connection from {} to room {}", addr, room.path);
 
-    // Subscribe to broadcast channels
-    let chat_rx = state.chat_tx.subscribe();
-    let lb_rx = state.lb_tx.subscribe();
-    let world_rx = state.world_tx.subscribe();
-    let targeted_rx = state.targeted_tx.subscribe();
+    // Subscribe to the room's broadcast channels
+    let receivers = room.subscribe();
 
     // Handle the connection using server logic
     if let Err(e) = handle_game_connection(
         socket,
         addr,
-        state.game_state,
-        chat_rx,
-        lb_rx,
-        world_rx,
-        targeted_rx,
+        Arc::clone(&room.game_state),
+        Arc::clone(&room.metrics),
+        receivers.chat_rx,
+        receivers.lb_rx,
+        receivers.world_rx,
+        room.targeted_tx.clone(),
     ).await {
         error!("Connection error from {}: {}", addr, e);
     }
 }
 
+/// Encode an outbox frame as an Axum WebSocket message.
+fn websocket_message(frame: server::Frame) -> axum::extract::ws::Message {
+    use axum::extract::ws::{CloseFrame, Message};
+    match frame {
+        server::Frame::Binary(packet) => Message::Binary(packet),
+        server::Frame::Close { code, reason } => Message::Close(Some(CloseFrame { code, reason: reason.into() })),
+    }
+}
+
 /// Adapt Axum WebSocket to work with server's game connection handler
 async fn handle_game_connection(
     socket: WebSocket,
     addr: SocketAddr,
     game_state: Arc<RwLock<server::server::game::GameState>>,
+    metrics: Arc<server::metrics::Metrics>,
     mut chat_rx: broadcast::Receiver<server::ChatBroadcast>,
     mut lb_rx: broadcast::Receiver<server::LeaderboardBroadcast>,
     mut world_rx: broadcast::Receiver<server::WorldUpdateBroadcast>,
-    mut targeted_rx: broadcast::Receiver<server::TargetedMessage>,
+    targeted_tx: server::TargetedSender,
 ) -> anyhow::Result<()> {
-    use std::collections::HashSet;
-    
-    let (mut write, mut read) = socket.split();
+    let (write, mut read) = socket.split();
 
     // Create client
-    let client_id = {
+    let (client_id, mut targeted_rx, max_packet_bytes, send_timeout, batch_packets, handshake_timeout) = {
         let mut state = game_state.write().await;
-        state.add_client(addr)
+        let client_id = state.add_client(addr);
+        // Registered under the lock, so nothing addressed to the client is missed
+        let targeted_rx = targeted_tx.register(client_id);
+        let server = &state.config.server;
+        (
+            client_id,
+            targeted_rx,
+            server.max_packet_bytes,
+            Duration::from_secs(server.send_timeout),
+            server.batch_packets,
+            Duration::from_secs(server.handshake_timeout),
+        )
     };
+    let mut outbox = server::Outbox::spawn(write, websocket_message, client_id, addr, send_timeout, batch_packets, Arc::clone(&metrics));
 
-    // Track which nodes this client has seen (for delta updates)
-    let mut client_nodes: HashSet<u32> = HashSet::new();
+    // Negotiated protocol, learned from the first world update
+    let mut client_protocol = 0;
+    let mut lag = server::LagTracker::default();
+    let handshake_deadline = tokio::time::sleep(handshake_timeout);
+    tokio::pin!(handshake_deadline);
+    let mut awaiting_handshake = true;
 
     // Message loop - handle both incoming messages and broadcasts
     loop {
         tokio::select! {
+            // The writer gave up on a stalled or broken socket
+            _ = outbox.closed() => {
+                break;
+            }
+            // Upgraded, but never sent 0xFE / 0xFF
+            _ = &mut handshake_deadline, if awaiting_handshake => {
+                awaiting_handshake = false;
+                if !game_state.read().await.handshake_complete(client_id) {
+                    warn!("Dropping {}: no protocol handshake within {:?}", addr, handshake_timeout);
+                    metrics.handshake_timed_out(HandshakeStage::Protocol);
+                    break;
+                }
+            }
             // Handle incoming WebSocket messages
             msg = read.next() => {
                 match msg {
                     Some(Ok(axum::extract::ws::Message::Binary(data))) => {
-                        let mut state = game_state.write().await;
-                        if let Err(e) = state.handle_packet(client_id, &data) {
+                        if data.len() > max_packet_bytes {
+                            warn!("Disconnecting {}: {}-byte packet exceeds the {}-byte limit", addr, data.len(), max_packet_bytes);
+                            break;
+                        }
+                        let (result, negotiated) = {
+                            let mut state = game_state.write().await;
+                            let result = state.handle_packet(client_id, &data);
+                            (result, state.negotiated_protocol(client_id))
+                        };
+                        if let Err(e) = result {
+                            // A refused handshake closes the socket with its code
+                            if let Some(rejected) = e.downcast_ref::<server::server::game::HandshakeRejected>() {
+                                info!("Disconnecting {}: {}", addr, rejected.reason);
+                                let _ = outbox.close(rejected.code as u16, rejected.reason.clone()).await;
+                                break;
+                            }
                             warn!("Packet error from {}: {}", addr, e);
                         }
+                        // The writer learns the protocol before the handshake's replies
+                        if client_protocol == 0
+                            && let Some(protocol) = negotiated
+                        {
+                            client_protocol = protocol;
+                            if let Err(e) = outbox.set_protocol(protocol).await {
+                                warn!("Failed to pass the protocol to the writer for {}: {}", addr, e);
+                                break;
+                            }
+                        }
                     }
                     Some(Ok(axum::extract::ws::Message::Close(_))) => {
                         info!("Client {} disconnected", addr);
@@ -238,6 +435,7 @@ async fn handle_game_connection(
             }
             // Handle chat broadcasts
             chat_msg = chat_rx.recv() => {
+                lag.observe(Channel::Chat, &chat_msg, &metrics, addr);
                 if let Ok(chat) = chat_msg {
                     let packet = protocol::packets::build_chat_message(
                         chat.color,
@@ -248,7 +446,7 @@ async fn handle_game_connection(
                         false,
                     );
                     let bytes = packet.finish();
-                    if let Err(e) = send_binary(&mut write, bytes).await {
+                    if let Err(e) = outbox.send(bytes).await {
                         warn!("Failed to send chat to {}: {}", addr, e);
                         break;
                     }
@@ -256,7 +454,28 @@ async fn handle_game_connection(
             }
             // Handle leaderboard broadcasts
             lb_msg = lb_rx.recv() => {
+                lag.observe(Channel::Leaderboard, &lb_msg, &metrics, addr);
                 if let Ok(lb) = lb_msg {
+                    // Only cogar clients know the Roster opcode
+                    if let Some(entries) = &lb.roster
+                        && client_protocol >= protocol::packets::COGAR_PROTOCOL
+                    {
+                        let packet = protocol::packets::build_roster(entries);
+                        let bytes = packet.finish();
+                        if let Err(e) = outbox.send(bytes).await {
+                            warn!("Failed to send Roster to {}: {}", addr, e);
+                            break;
+                        }
+                    }
+                    if let Some(lines) = &lb.text {
+                        let packet = protocol::packets::build_leaderboard_text(lines);
+                        if let Err(e) = outbox.send(packet.finish()).await {
+                            warn!("Failed to send text leaderboard to {}: {}", addr, e);
+                            break;
+                        }
+                        // A text board replaces the ranked list
+                        continue;
+                    }
                     match lb.gamemode_id {
                         1 => {
                             let team_scores: Vec<f32> = lb.entries.iter()
@@ -264,20 +483,20 @@ async fn handle_game_connection(
                                 .collect();
                             let packet = protocol::packets::build_leaderboard_pie(&team_scores);
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send pie leaderboard to {}: {}", addr, e);
                                 break;
                             }
                         }
                         _ => {
-                            let entries: Vec<(bool, &str)> = lb.entries.iter()
+                            let entries: Vec<(bool, u32, &str)> = lb.entries.iter()
                                 .take(10)
-                                .map(|e| (e.client_id == client_id, e.name.as_str()))
+                                .map(|e| (e.client_id == client_id, e.client_id, e.name.as_str()))
                                 .collect();
 
-                            let packet = protocol::packets::build_leaderboard_ffa(&entries);
+                            let packet = protocol::packets::build_leaderboard_ffa(client_protocol, &entries);
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send ffa leaderboard to {}: {}", addr, e);
                                 break;
                             }
@@ -287,128 +506,25 @@ async fn handle_game_connection(
             }
             // Handle world update broadcasts
             world_msg = world_rx.recv() => {
+                lag.observe(Channel::World, &world_msg, &metrics, addr);
                 if let Ok(world) = world_msg {
                     let client_view = match world.client_data.get(&client_id) {
                         Some(v) => v,
                         None => continue,
                     };
-
-                    let scale = client_view.scale.max(0.15);
-                    let view_half_w = (1920.0 / scale) / 2.0;
-                    let view_half_h = (1080.0 / scale) / 2.0;
-                    let view_min_x = client_view.center_x - view_half_w;
-                    let view_min_y = client_view.center_y - view_half_h;
-                    let view_max_x = client_view.center_x + view_half_w;
-                    let view_max_y = client_view.center_y + view_half_h;
-
-                    let mut view_nodes: HashSet<u32> = HashSet::new();
-                    for cell in &world.cells {
-                        let margin = cell.size;
-                        if cell.x + margin >= view_min_x
-                            && cell.x - margin <= view_max_x
-                            && cell.y + margin >= view_min_y
-                            && cell.y - margin <= view_max_y
-                        {
-                            view_nodes.insert(cell.node_id);
-                        }
-                    }
-
-                    for &cell_id in &client_view.cell_ids {
-                        view_nodes.insert(cell_id);
-                    }
-
-                    // Force-include all minion cells (always visible to owner)
-                    for &minion_id in &client_view.minion_ids {
-                        for cell in &world.cells {
-                            if cell.owner_id == Some(minion_id) {
-                                view_nodes.insert(cell.node_id);
-                            }
-                        }
-                    }
-
-                    let mut add_nodes = Vec::new();
-                    let mut upd_nodes = Vec::new();
-                    let mut del_nodes = Vec::new();
-
-                    for cell in &world.cells {
-                        if view_nodes.contains(&cell.node_id) {
-                            let is_new = !client_nodes.contains(&cell.node_id);
-
-                            let update_cell = protocol::packets::UpdateCell {
-                                node_id: cell.node_id,
-                                x: cell.x as i32,
-                                y: cell.y as i32,
-                                size: cell.size as u16,
-                                color: cell.color,
-                                flags: protocol::packets::CellFlags {
-                                    is_spiked: cell.cell_type == 2,
-                                    is_player: true,
-                                    has_skin: is_new && cell.skin.is_some(),
-                                    has_name: is_new && cell.name.is_some(),
-                                    is_agitated: false,
-                                    is_ejected: cell.cell_type == 3,
-                                    is_food: cell.cell_type == 1,
-                                },
-                                skin: if is_new { cell.skin.clone() } else { None },
-                                name: if is_new { cell.name.clone() } else { None },
-                            };
-
-                            if is_new {
-                                add_nodes.push(update_cell);
-                            } else {
-                                upd_nodes.push(update_cell);
-                            }
-                        }
-                    }
-
-                    for &node_id in &client_nodes {
-                        if !view_nodes.contains(&node_id) {
-                            del_nodes.push(node_id);
-                        }
-                    }
-
-                    let eat_records: Vec<protocol::packets::EatRecord> = world.eaten.iter()
-                        .filter(|(eaten_id, eater_id)| {
-                            view_nodes.contains(eaten_id)
-                                || view_nodes.contains(eater_id)
-                                || client_nodes.contains(eaten_id)
-                                || client_nodes.contains(eater_id)
-                        })
-                        .map(|&(eaten_id, eater_id)| protocol::packets::EatRecord { eaten_id, eater_id })
-                        .collect();
-
-                    client_nodes = view_nodes;
-
-                    let packet = protocol::packets::build_update_nodes(
-                        client_view.protocol,
-                        client_view.scramble_id,
-                        client_view.scramble_x,
-                        client_view.scramble_y,
-                        &add_nodes,
-                        &upd_nodes,
-                        &eat_records,
-                        &del_nodes,
-                    );
-                    let bytes = packet.finish();
-
-                    if let Err(e) = send_binary(&mut write, bytes).await {
-                        warn!("Failed to send world update to {}: {}", addr, e);
-                        break;
-                    }
+                    client_protocol = client_view.protocol;
+                    outbox.send_world(world, lag.take_resync());
                 }
             }
             // Handle targeted messages
             targeted_msg = targeted_rx.recv() => {
+                lag.observe(Channel::Targeted, &targeted_msg, &metrics, addr);
                 if let Ok(msg) = targeted_msg {
-                    if msg.client_id != client_id {
-                        continue;
-                    }
-
                     match msg.message {
                         server::TargetedMessageType::AddNode { node_id, scramble_id } => {
                             let packet = protocol::packets::build_add_node(node_id, scramble_id);
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send AddNode to {}: {}", addr, e);
                                 break;
                             }
@@ -416,30 +532,55 @@ async fn handle_game_connection(
                         server::TargetedMessageType::ClearAll => {
                             let packet = protocol::packets::build_clear_all();
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send ClearAll to {}: {}", addr, e);
                                 break;
                             }
                         }
-                        server::TargetedMessageType::SetBorder { min_x, min_y, max_x, max_y, scramble_x, scramble_y, game_type, server_name } => {
+                        server::TargetedMessageType::ClearOwned => {
+                            let packet = protocol::packets::build_clear_owned();
+                            let bytes = packet.finish();
+                            if let Err(e) = outbox.send(bytes).await {
+                                warn!("Failed to send ClearOwned to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        server::TargetedMessageType::SetBorder { min_x, min_y, max_x, max_y, scramble_x, scramble_y, game_type, server_name, unlimited_zoom } => {
                             let packet = protocol::packets::build_set_border(
                                 min_x + scramble_x as f64,
                                 min_y + scramble_y as f64,
                                 max_x + scramble_x as f64,
                                 max_y + scramble_y as f64,
                                 game_type,
-                                &server_name
+                                &server_name,
+                                unlimited_zoom,
                             );
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send SetBorder to {}: {}", addr, e);
                                 break;
                             }
                         }
+                        server::TargetedMessageType::MapObjects { shapes, scramble_x, scramble_y } => {
+                            let packet = protocol::packets::build_map_objects(scramble_x, scramble_y, &shapes);
+                            let bytes = packet.finish();
+                            if let Err(e) = outbox.send(bytes).await {
+                                warn!("Failed to send MapObjects to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        server::TargetedMessageType::DrawLine { x, y, scramble_x, scramble_y } => {
+                            let packet = protocol::packets::build_draw_line(scramble_x, scramble_y, x, y);
+                            let bytes = packet.finish();
+                            if let Err(e) = outbox.send(bytes).await {
+                                warn!("Failed to send DrawLine to {}: {}", addr, e);
+                                break;
+                            }
+                        }
                         server::TargetedMessageType::ServerStat { json } => {
                             let packet = protocol::packets::build_server_stat(&json);
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send ServerStat to {}: {}", addr, e);
                                 break;
                             }
@@ -454,7 +595,7 @@ async fn handle_game_connection(
                                 false,
                             );
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send ChatMessage to {}: {}", addr, e);
                                 break;
                             }
@@ -467,11 +608,32 @@ async fn handle_game_connection(
                                 &player_cells,
                             );
                             let bytes = packet.finish();
-                            if let Err(e) = send_binary(&mut write, bytes).await {
+                            if let Err(e) = outbox.send(bytes).await {
                                 warn!("Failed to send XrayData to {}: {}", addr, e);
                                 break;
                             }
                         }
+                        server::TargetedMessageType::MergeTimer { timers, scramble_id } => {
+                            let packet = protocol::packets::build_merge_timer(scramble_id, &timers);
+                            let bytes = packet.finish();
+                            if let Err(e) = outbox.send(bytes).await {
+                                warn!("Failed to send MergeTimer to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        server::TargetedMessageType::DeathInfo { cause, killer_name, killer_mass, killer_id } => {
+                            let packet = protocol::packets::build_death_info(cause, &killer_name, killer_mass, killer_id);
+                            let bytes = packet.finish();
+                            if let Err(e) = outbox.send(bytes).await {
+                                warn!("Failed to send DeathInfo to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        server::TargetedMessageType::Disconnect { code, reason } => {
+                            info!("Disconnecting {}: {}", addr, reason);
+                            let _ = outbox.close(code as u16, reason).await;
+                            break;
+                        }
                     }
                 }
             }
@@ -481,7 +643,7 @@ async fn handle_game_connection(
     // Remove client
     {
         let mut state = game_state.write().await;
-        state.remove_client(client_id);
+        state.disconnect_client(client_id);
     }
 
     Ok(())
@@ -592,12 +754,3 @@ async fn serve_static_file_with_host(
         }
     }
 }
-
-async fn send_binary(
-    write: &mut futures_util::stream::SplitSink<WebSocket, axum::extract::ws::Message>,
-    bytes: bytes::Bytes,
-) -> anyhow::Result<()> {
-    // Convert Bytes to the format axum expects (matching tokio-tungstenite behavior)
-    write.send(axum::extract::ws::Message::Binary(bytes.to_vec().into())).await?;
-    Ok(())
-}
\ No newline at end of file
@@ -0,0 +1,425 @@
+//! Cogar loadtest - Headless bots for stress-testing a running server.
+//!
+//! Usage: `cogar-loadtest [--url ws://127.0.0.1:11443] [--clients 50] [--duration 60] [--protocol 6]`
+//!
+//! Every bot completes the handshake, joins and wanders around with the
+//! occasional split and eject, respawning after death. A separate monitor
+//! connection polls server stats and reports how the server's tick time grows
+//! under load. Raise `server.max_connections` and `server.ip_limit` on the
+//! target beforehand, since every bot connects from the same address.
+//!
+//! Bots count WebSocket frames apart from the packets in them: run once with
+//! `--protocol 6` and once with `--protocol 18` against a server with
+//! `server.batch_packets` on to see how many frames batching saves.
+//!
+//! Bots also track every cell they've been sent and count how many enter
+//! and leave their view per world update, to compare culling settings such
+//! as `server.view_margin`.
+
+use futures_util::{SinkExt, StreamExt};
+use protocol::packets::{self, ClientOpcode, ServerPacket};
+use protocol::BinaryWriter;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+/// Protocol the bots speak by default, same as the web client.
+const DEFAULT_PROTOCOL: u32 = 6;
+/// Protocol of the stats monitor, which never receives batches.
+const MONITOR_PROTOCOL: u32 = 6;
+/// How often each bot runs its script and sends its mouse position.
+const STEP_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a dead bot waits before joining again.
+const RESPAWN_DELAY: Duration = Duration::from_secs(1);
+/// How often the monitor asks for server stats (the server rate-limits these).
+const STATS_INTERVAL: Duration = Duration::from_secs(2);
+/// Delay between bot connections, so they don't arrive as a single burst.
+const CONNECT_STAGGER: Duration = Duration::from_millis(10);
+
+/// Command-line options.
+struct Options {
+    url: String,
+    clients: usize,
+    duration: Duration,
+    protocol: u32,
+}
+
+fn parse_args() -> anyhow::Result<Options> {
+    let mut options = Options {
+        url: "ws://127.0.0.1:11443".to_string(),
+        clients: 50,
+        duration: Duration::from_secs(60),
+        protocol: DEFAULT_PROTOCOL,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow::anyhow!("{} needs a value", arg));
+        match arg.as_str() {
+            "--url" => options.url = value()?,
+            "--clients" => options.clients = value()?.parse()?,
+            "--duration" => options.duration = Duration::from_secs(value()?.parse()?),
+            "--protocol" => options.protocol = value()?.parse()?,
+            _ => anyhow::bail!("unknown argument {} (expected --url, --clients, --duration or --protocol)", arg),
+        }
+    }
+    Ok(options)
+}
+
+/// Counters shared by all bots.
+#[derive(Default)]
+struct LoadStats {
+    connected: AtomicUsize,
+    alive: AtomicUsize,
+    failed: AtomicUsize,
+    frames: AtomicUsize,
+    packets: AtomicUsize,
+    bytes: AtomicUsize,
+    /// World updates received and the cells they added to or culled from
+    /// the bots' views (eaten cells aren't counted as culled).
+    updates: AtomicUsize,
+    added: AtomicUsize,
+    culled: AtomicUsize,
+}
+
+/// A scripted headless player that tracks its own cells from server packets.
+struct LoadBot {
+    name: String,
+    protocol: u32,
+    /// Own cells by node id, with their last known position (scrambled space).
+    cells: HashMap<u32, (i32, i32)>,
+    /// Every cell the server has sent and not yet removed.
+    known: HashSet<u32>,
+    /// World border (min_x, min_y, max_x, max_y) from SetBorder.
+    border: Option<(f64, f64, f64, f64)>,
+    /// Where the bot is heading.
+    target: (i32, i32),
+}
+
+impl LoadBot {
+    fn new(name: String, protocol: u32) -> Self {
+        Self {
+            name,
+            protocol,
+            cells: HashMap::new(),
+            known: HashSet::new(),
+            border: None,
+            target: (0, 0),
+        }
+    }
+
+    /// Packets that join the game: protocol version, handshake key, then Join.
+    fn handshake(&self) -> [BinaryWriter; 3] {
+        [
+            packets::build_protocol_version(self.protocol),
+            // Newer protocols expect a zero key
+            packets::build_handshake_key(if self.protocol > 6 { 0 } else { 1 }),
+            packets::build_join(self.protocol, &self.name),
+        ]
+    }
+
+    /// Apply one server packet to the bot's view of itself; returns how many
+    /// packets it held (more than one for a batch).
+    fn handle_packet(&mut self, data: &[u8], stats: &LoadStats) -> usize {
+        let packet = match ServerPacket::parse(data, self.protocol) {
+            Ok(packet) => packet,
+            Err(e) => {
+                warn!("{}: bad packet: {}", self.name, e);
+                return 1;
+            }
+        };
+        match packet {
+            // Follow own cells, drop removed ones
+            ServerPacket::UpdateNodes(update) => {
+                let added = update.nodes.iter().filter(|node| self.known.insert(node.node_id)).count();
+                let culled = update.removed.iter()
+                    .filter(|&&id| self.known.remove(&id) && !update.eats.iter().any(|eat| eat.eaten_id == id))
+                    .count();
+                stats.updates.fetch_add(1, Ordering::Relaxed);
+                stats.added.fetch_add(added, Ordering::Relaxed);
+                stats.culled.fetch_add(culled, Ordering::Relaxed);
+                for node in &update.nodes {
+                    if let Some(pos) = self.cells.get_mut(&node.node_id) {
+                        *pos = (node.x as i32, node.y as i32);
+                    }
+                }
+                for id in &update.removed {
+                    self.cells.remove(id);
+                }
+            }
+            ServerPacket::ClearAll => {
+                self.cells.clear();
+                self.known.clear();
+            }
+            ServerPacket::ClearOwned => self.cells.clear(),
+            ServerPacket::AddNode { node_id } => {
+                let pos = self.center().unwrap_or(self.target);
+                self.cells.insert(node_id, pos);
+            }
+            ServerPacket::SetBorder(border) => {
+                self.border = Some((border.min_x, border.min_y, border.max_x, border.max_y));
+                self.target = (((border.min_x + border.max_x) / 2.0) as i32, ((border.min_y + border.max_y) / 2.0) as i32);
+            }
+            ServerPacket::Batch { packets } => {
+                return packets.iter().map(|packet| self.handle_packet(packet, stats)).sum();
+            }
+            _ => {}
+        }
+        1
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.cells.is_empty()
+    }
+
+    /// Average position of the bot's cells.
+    fn center(&self) -> Option<(i32, i32)> {
+        if self.cells.is_empty() {
+            return None;
+        }
+        let n = self.cells.len() as i64;
+        let (sx, sy) = self.cells.values().fold((0i64, 0i64), |(sx, sy), &(x, y)| (sx + x as i64, sy + y as i64));
+        Some(((sx / n) as i32, (sy / n) as i32))
+    }
+
+    /// One step of the script: wander between random points, splitting and
+    /// ejecting now and then.
+    fn step(&mut self) -> Vec<BinaryWriter> {
+        let mut rng = rand::rng();
+        let Some((x, y)) = self.center() else {
+            return Vec::new();
+        };
+        let (dx, dy) = ((self.target.0 - x) as f64, (self.target.1 - y) as f64);
+        if dx.hypot(dy) < 200.0 || rng.random_bool(0.01) {
+            if let Some((min_x, min_y, max_x, max_y)) = self.border {
+                self.target = (rng.random_range(min_x..max_x) as i32, rng.random_range(min_y..max_y) as i32);
+            }
+        }
+
+        let mut out = vec![packets::build_mouse(self.target.0, self.target.1)];
+        if rng.random_bool(0.005) {
+            out.push(packets::build_opcode(ClientOpcode::Split));
+        }
+        if rng.random_bool(0.02) {
+            out.push(packets::build_opcode(ClientOpcode::Eject));
+        }
+        out
+    }
+}
+
+/// Run one bot until the deadline or until the server drops it.
+async fn run_bot(index: usize, url: String, protocol: u32, deadline: Instant, stats: Arc<LoadStats>) -> anyhow::Result<()> {
+    let (ws, _) = connect_async(&url).await?;
+    let (mut write, mut read) = ws.split();
+    stats.connected.fetch_add(1, Ordering::Relaxed);
+
+    let mut bot = LoadBot::new(format!("loadbot-{}", index), protocol);
+    for packet in bot.handshake() {
+        write.send(Message::Binary(packet.finish())).await?;
+    }
+
+    let mut ticker = tokio::time::interval(STEP_INTERVAL);
+    let mut was_alive = false;
+    let mut died_at = Instant::now();
+    let result = loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break Ok(()),
+            msg = read.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    stats.frames.fetch_add(1, Ordering::Relaxed);
+                    stats.bytes.fetch_add(data.len(), Ordering::Relaxed);
+                    let packets = bot.handle_packet(&data, &stats);
+                    stats.packets.fetch_add(packets, Ordering::Relaxed);
+                }
+                Some(Ok(Message::Close(_))) | None => break Err(anyhow::anyhow!("closed by server")),
+                Some(Err(e)) => break Err(e.into()),
+                Some(Ok(_)) => {}
+            },
+            _ = ticker.tick() => {
+                let alive = bot.is_alive();
+                if alive != was_alive {
+                    if alive {
+                        stats.alive.fetch_add(1, Ordering::Relaxed);
+                    } else {
+                        stats.alive.fetch_sub(1, Ordering::Relaxed);
+                        died_at = Instant::now();
+                    }
+                    was_alive = alive;
+                }
+
+                let packets = if alive {
+                    bot.step()
+                } else if died_at.elapsed() >= RESPAWN_DELAY {
+                    died_at = Instant::now();
+                    vec![packets::build_join(protocol, &bot.name)]
+                } else {
+                    Vec::new()
+                };
+                for packet in packets {
+                    if let Err(e) = write.send(Message::Binary(packet.finish())).await {
+                        warn!("{}: send failed: {}", bot.name, e);
+                    }
+                }
+            }
+        }
+    };
+
+    if was_alive {
+        stats.alive.fetch_sub(1, Ordering::Relaxed);
+    }
+    stats.connected.fetch_sub(1, Ordering::Relaxed);
+    let _ = write.close().await;
+    result
+}
+
+/// Server tick time reported at one point during the run.
+struct Sample {
+    elapsed: Duration,
+    connected: usize,
+    tick_ms: f64,
+}
+
+/// Poll server stats over a connection that never joins, logging each sample.
+async fn run_monitor(url: String, deadline: Instant, stats: Arc<LoadStats>) -> anyhow::Result<Vec<Sample>> {
+    let (ws, _) = connect_async(&url).await?;
+    let (mut write, mut read) = ws.split();
+    write.send(Message::Binary(packets::build_protocol_version(MONITOR_PROTOCOL).finish())).await?;
+    write.send(Message::Binary(packets::build_handshake_key(1).finish())).await?;
+
+    let start = Instant::now();
+    let mut samples = Vec::new();
+    let mut ticker = tokio::time::interval(STATS_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break,
+            _ = ticker.tick() => {
+                write.send(Message::Binary(packets::build_stats_request().finish())).await?;
+            }
+            msg = read.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    let Ok(ServerPacket::ServerStat { json }) = ServerPacket::parse(&data, MONITOR_PROTOCOL) else {
+                        continue;
+                    };
+                    let Some(tick_ms) = stat_field(&json, "update").and_then(|v| v.parse::<f64>().ok()) else {
+                        continue;
+                    };
+                    let sample = Sample {
+                        elapsed: start.elapsed(),
+                        connected: stats.connected.load(Ordering::Relaxed),
+                        tick_ms,
+                    };
+                    info!(
+                        "[{:>4}s] {} bots connected, {} alive, {} failed | {} packets in {} frames, {} KiB received | tick {:.2} ms",
+                        sample.elapsed.as_secs(),
+                        sample.connected,
+                        stats.alive.load(Ordering::Relaxed),
+                        stats.failed.load(Ordering::Relaxed),
+                        stats.packets.load(Ordering::Relaxed),
+                        stats.frames.load(Ordering::Relaxed),
+                        stats.bytes.load(Ordering::Relaxed) / 1024,
+                        sample.tick_ms,
+                    );
+                    samples.push(sample);
+                }
+                Some(Ok(Message::Close(_))) | None => anyhow::bail!("monitor connection closed by server"),
+                Some(Err(e)) => return Err(e.into()),
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    Ok(samples)
+}
+
+/// Raw value of a top-level field in the flat ServerStat JSON, unquoted.
+fn stat_field<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!("\"{}\":", key))? + key.len() + 3;
+    let rest = &json[start..];
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next(),
+        None => rest.split([',', '}']).next(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let options = parse_args()?;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    info!(
+        "Cogar loadtest: {} protocol {} bots against {} for {}s",
+        options.clients,
+        options.protocol,
+        options.url,
+        options.duration.as_secs()
+    );
+
+    let stats = Arc::new(LoadStats::default());
+    let start = Instant::now();
+    let deadline = start + options.duration;
+
+    // Start the monitor first so the first sample is an unloaded baseline
+    let monitor = tokio::spawn(run_monitor(options.url.clone(), deadline, Arc::clone(&stats)));
+    tokio::time::sleep(STATS_INTERVAL + STATS_INTERVAL / 4).await;
+
+    let mut bots = Vec::with_capacity(options.clients);
+    for index in 0..options.clients {
+        let url = options.url.clone();
+        let stats = Arc::clone(&stats);
+        let protocol = options.protocol;
+        bots.push(tokio::spawn(async move {
+            if let Err(e) = run_bot(index, url, protocol, deadline, Arc::clone(&stats)).await {
+                stats.failed.fetch_add(1, Ordering::Relaxed);
+                warn!("loadbot-{}: {}", index, e);
+            }
+        }));
+        tokio::time::sleep(CONNECT_STAGGER).await;
+    }
+    for bot in bots {
+        let _ = bot.await;
+    }
+
+    let samples = monitor.await??;
+    let Some(baseline) = samples.first() else {
+        anyhow::bail!("the server never answered a stats request");
+    };
+    let loaded: Vec<&Sample> = samples.iter().filter(|s| s.connected > 0).collect();
+    if loaded.is_empty() {
+        anyhow::bail!("no stats were sampled while bots were connected");
+    }
+    let mean = loaded.iter().map(|s| s.tick_ms).sum::<f64>() / loaded.len() as f64;
+    let peak = loaded.iter().map(|s| s.tick_ms).fold(0.0, f64::max);
+    let most = loaded.iter().map(|s| s.connected).max().unwrap_or(0);
+
+    info!("Tick time with no bots: {:.2} ms", baseline.tick_ms);
+    info!("Tick time with up to {} bots: mean {:.2} ms, peak {:.2} ms", most, mean, peak);
+    if baseline.tick_ms > 0.0 {
+        info!("Degradation: {:.1}x mean, {:.1}x peak", mean / baseline.tick_ms, peak / baseline.tick_ms);
+    }
+    let secs = start.elapsed().as_secs_f64();
+    let frames = stats.frames.load(Ordering::Relaxed);
+    let packets = stats.packets.load(Ordering::Relaxed);
+    info!(
+        "Received {:.0} frames/s carrying {:.0} packets/s ({:.2} packets per frame)",
+        frames as f64 / secs,
+        packets as f64 / secs,
+        packets as f64 / frames.max(1) as f64,
+    );
+    let updates = stats.updates.load(Ordering::Relaxed).max(1) as f64;
+    info!(
+        "View churn: {:.2} cells added and {:.2} culled per bot per world update",
+        stats.added.load(Ordering::Relaxed) as f64 / updates,
+        stats.culled.load(Ordering::Relaxed) as f64 / updates,
+    );
+    info!("{} of {} bots failed to connect or were dropped", stats.failed.load(Ordering::Relaxed), options.clients);
+    Ok(())
+}
@@ -1,10 +1,23 @@
 //! Ogar - Pure game server binary
 
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // Dump the effective config before logging is set up so stdout stays clean
+    if std::env::args().any(|arg| arg == "--write-config") {
+        let config = server::Config::load()?;
+        print!("{}", toml::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    // Summarize a replay recording and exit
+    let args: Vec<String> = std::env::args().collect();
+    if server::replay::ReplayInfo::inspect_from_args(&args)? {
+        return Ok(());
+    }
+
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -16,13 +29,23 @@ async fn main() -> anyhow::Result<()> {
 
     // Load configuration
     let config = server::Config::load()?;
+    if let Err(errors) = config.validate() {
+        error!("Invalid configuration:");
+        for err in &errors {
+            error!("  {}", err);
+        }
+        anyhow::bail!("config.toml has {} invalid setting(s)", errors.len());
+    }
     info!("Loaded configuration");
     info!("  Port: {}", config.server.port);
     info!("  Border: {}x{}", config.border.width, config.border.height);
     info!("  Game mode: {}", config.server.gamemode);
 
+    // Start the first room from a world dump when asked to
+    let dump = server::dump::WorldDump::from_args(&args)?;
+
     // Start the pure game server (WebSocket only)
-    server::run(config).await?;
+    server::run(config, dump).await?;
 
     Ok(())
 }
\ No newline at end of file
@@ -1,13 +1,64 @@
 // Camera system - viewport, zoom, smooth follow
 //
-// Lerp behaviour is intentionally frame-rate-dependent to match the JS client:
+// The legacy lerp is frame-rate-dependent to match the JS client:
 //   position: camera.x = (camera.x + target.x) / 2          (50 % per frame, alive)
 //             camera.x += (target.x - camera.x) / 20        (5 %  per frame, spectating)
 //   zoom:     camera.scale += (target.scale - camera.scale) / 9
+// so a 144 Hz display follows much faster than a 60 Hz one. Exponential
+// smoothing moves `1 - exp(-k * dt)` of the way per frame instead, with k
+// chosen so one 60 Hz frame moves exactly as far as the legacy lerp:
+// k = -60 * ln(1 - fraction).
 //
 // Zoom formula (JS): Math.pow(Math.min(64 / totalSize, 1), 0.4)
 use glam::Vec2;
 
+/// How far past the world border the camera target may go.
+pub const BORDER_MARGIN: f32 = 500.0;
+
+/// Exponential rate while alive: -60 ln(1/2), the legacy 50 % at 60 Hz.
+const FOLLOW_RATE: f32 = 41.59;
+/// Exponential rate while spectating: -60 ln(19/20), the legacy 5 % at 60 Hz.
+const SPECTATE_RATE: f32 = 3.078;
+/// Exponential zoom rate: -60 ln(8/9), the legacy 1/9 at 60 Hz.
+const ZOOM_RATE: f32 = 7.067;
+/// Furthest the wheel can zoom in.
+const MAX_ZOOM_FACTOR: f32 = 2.5;
+/// Furthest the wheel can zoom out: for huge players, or always with
+/// unlimited zoom.
+const MIN_ZOOM_FACTOR: f32 = 0.25;
+/// Furthest a single starting cell can zoom out; the limit eases toward
+/// [`MIN_ZOOM_FACTOR`] as the size scale shrinks with mass.
+const SMALL_MIN_ZOOM_FACTOR: f32 = 0.7;
+
+/// How the camera eases toward its target.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraSmoothing {
+    /// A fixed fraction per frame, like the JS client.
+    #[default]
+    Legacy,
+    /// By elapsed time, the same at any frame rate.
+    Exponential,
+}
+
+impl CameraSmoothing {
+    /// Parse the value of the `settingCameraSmoothing` select.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "exponential" => Self::Exponential,
+            _ => Self::Legacy,
+        }
+    }
+
+    /// The `settingCameraSmoothing` option value for this mode.
+    pub fn as_setting(self) -> &'static str {
+        match self {
+            Self::Legacy => "legacy",
+            Self::Exponential => "exponential",
+        }
+    }
+}
+
 pub struct Camera {
     pub position: Vec2,
     pub target_position: Vec2,
@@ -15,6 +66,8 @@ pub struct Camera {
     pub target_zoom: f32,
     pub zoom_factor: f32,
     pub size_scale: f32,
+    /// Let the wheel zoom out to [`MIN_ZOOM_FACTOR`] at any mass.
+    pub unlimited_zoom: bool,
 }
 
 impl Camera {
@@ -26,52 +79,107 @@ impl Camera {
             target_zoom: 1.0,
             zoom_factor: 1.0,
             size_scale: 1.0,
+            unlimited_zoom: false,
         }
     }
 
-    /// Called once per animation frame. `has_cells` controls position-lerp speed.
-    /// Matches JS client behavior (frame-rate dependent smoothing).
-    pub fn update(&mut self, has_cells: bool) {
-        if has_cells {
-            // 50 % lerp per frame (alive)
-            self.position = (self.position + self.target_position) * 0.5;
-        } else {
-            // ~5 % lerp per frame (spectating / dead)
-            self.position += (self.target_position - self.position) / 20.0;
+    /// Called once per animation frame with the seconds since the last one.
+    /// `has_cells` controls position-lerp speed.
+    pub fn update(&mut self, has_cells: bool, dt: f32, smoothing: CameraSmoothing) {
+        match smoothing {
+            CameraSmoothing::Legacy => {
+                if has_cells {
+                    // 50 % lerp per frame (alive)
+                    self.position = (self.position + self.target_position) * 0.5;
+                } else {
+                    // ~5 % lerp per frame (spectating / dead)
+                    self.position += (self.target_position - self.position) / 20.0;
+                }
+                // Scale always lerps at 1/9 per frame
+                self.zoom += (self.target_zoom - self.zoom) / 9.0;
+            }
+            CameraSmoothing::Exponential => {
+                let rate = if has_cells { FOLLOW_RATE } else { SPECTATE_RATE };
+                self.position += (self.target_position - self.position) * (1.0 - (-rate * dt).exp());
+                self.zoom += (self.target_zoom - self.zoom) * (1.0 - (-ZOOM_RATE * dt).exp());
+            }
         }
-        // Scale always lerps at 1/9 per frame
-        self.zoom += (self.target_zoom - self.zoom) / 9.0;
     }
 
     /// Set camera targets from the player's interpolated cell positions and sizes.
+    /// `mass_weighted` centers on the size²-weighted centroid, so small split
+    /// pieces don't drag the view away from the main cell; otherwise every
+    /// cell counts the same (the JS client's behavior).
     /// Must be called every frame before `update`.
-    pub fn follow_cells(&mut self, cell_positions: &[Vec2], cell_sizes: &[f32]) {
+    pub fn follow_cells(&mut self, cell_positions: &[Vec2], cell_sizes: &[f32], mass_weighted: bool) {
         if cell_positions.is_empty() {
             return;
         }
 
-        // Target = average position of owned cells
-        let sum: Vec2 = cell_positions.iter().copied().sum();
-        self.target_position = sum / cell_positions.len() as f32;
+        let total_mass: f32 = cell_sizes.iter().map(|s| s * s).sum();
+        self.target_position = if mass_weighted && total_mass > 0.0 {
+            let sum: Vec2 = cell_positions.iter().zip(cell_sizes).map(|(&p, &s)| p * (s * s)).sum();
+            sum / total_mass
+        } else {
+            let sum: Vec2 = cell_positions.iter().copied().sum();
+            sum / cell_positions.len() as f32
+        };
 
         // JS: sizeScale = Math.pow(Math.min(64 / totalSize, 1), 0.4)
         let total_size: f32 = cell_sizes.iter().sum();
         let base_zoom = (64.0_f32 / total_size).min(1.0).powf(0.4);
         self.size_scale = base_zoom;
+        self.clamp_zoom_factor();
         self.target_zoom = base_zoom * self.zoom_factor;
     }
 
-    /// Adjust manual zoom factor (mouse wheel). Clamped to a safe range.
+    /// Keep the target within `border` (min_x, min_y, max_x, max_y) plus
+    /// [`BORDER_MARGIN`], so free-roam panning can't wander into the void.
+    pub fn clamp_to_border(&mut self, border: (f32, f32, f32, f32)) {
+        let (min_x, min_y, max_x, max_y) = border;
+        if min_x > max_x || min_y > max_y {
+            return;
+        }
+        self.target_position = self.target_position.clamp(
+            Vec2::new(min_x - BORDER_MARGIN, min_y - BORDER_MARGIN),
+            Vec2::new(max_x + BORDER_MARGIN, max_y + BORDER_MARGIN),
+        );
+    }
+
+    /// How far the wheel may zoom out at the current size scale: small
+    /// players see about as much as vanilla clients let them, big ones may
+    /// zoom out further.
+    pub fn min_zoom_factor(&self) -> f32 {
+        if self.unlimited_zoom {
+            return MIN_ZOOM_FACTOR;
+        }
+        MIN_ZOOM_FACTOR + (SMALL_MIN_ZOOM_FACTOR - MIN_ZOOM_FACTOR) * self.size_scale.clamp(0.0, 1.0)
+    }
+
+    /// Pull the zoom factor back within range after the size scale or the
+    /// unlimited zoom setting changed.
+    pub fn clamp_zoom_factor(&mut self) {
+        self.zoom_factor = self.zoom_factor.clamp(self.min_zoom_factor(), MAX_ZOOM_FACTOR);
+    }
+
+    /// Adjust manual zoom factor (mouse wheel), within
+    /// [`Self::min_zoom_factor`] and [`MAX_ZOOM_FACTOR`].
     pub fn adjust_zoom_factor(&mut self, delta: f32) {
-        let next = self.zoom_factor * delta;
-        self.zoom_factor = next.clamp(0.25, 2.5);
+        self.zoom_factor *= delta;
+        self.clamp_zoom_factor();
         // Keep target zoom consistent with current zoom (used when spectating)
         self.target_zoom = self.target_zoom.clamp(0.05, 5.0);
     }
 
+    /// Zoom the free-roam spectator camera, well past the normal limits.
+    pub fn adjust_free_zoom(&mut self, delta: f32) {
+        self.target_zoom = (self.target_zoom * delta).clamp(0.02, 8.0);
+    }
+
     /// Apply a new base zoom (e.g. spectator update), respecting zoom factor.
     pub fn set_base_zoom(&mut self, base_zoom: f32) {
         self.size_scale = base_zoom;
+        self.clamp_zoom_factor();
         self.target_zoom = base_zoom * self.zoom_factor;
     }
 
@@ -87,3 +195,64 @@ impl Default for Camera {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stepped(smoothing: CameraSmoothing, hz: f32, frames: usize, has_cells: bool) -> Camera {
+        let mut camera = Camera::new();
+        camera.target_position = Vec2::new(1000.0, 0.0);
+        camera.target_zoom = 2.0;
+        for _ in 0..frames {
+            camera.update(has_cells, 1.0 / hz, smoothing);
+        }
+        camera
+    }
+
+    #[test]
+    fn test_exponential_matches_legacy_at_60hz_at_any_rate() {
+        for has_cells in [true, false] {
+            // One 60 Hz frame moves as far as the legacy lerp
+            let legacy = stepped(CameraSmoothing::Legacy, 60.0, 1, has_cells);
+            let exp = stepped(CameraSmoothing::Exponential, 60.0, 1, has_cells);
+            assert!((legacy.position.x - exp.position.x).abs() < 0.1, "{} vs {}", legacy.position.x, exp.position.x);
+            assert!((legacy.zoom - exp.zoom).abs() < 1e-3);
+
+            // A third of a second ends up in the same place at 60 and 144 Hz,
+            // which the legacy lerp does not
+            let slow = stepped(CameraSmoothing::Exponential, 60.0, 20, has_cells);
+            let fast = stepped(CameraSmoothing::Exponential, 144.0, 48, has_cells);
+            assert!((slow.position.x - fast.position.x).abs() < 0.5);
+            assert!((slow.zoom - fast.zoom).abs() < 1e-3);
+            let legacy_fast = stepped(CameraSmoothing::Legacy, 144.0, 48, has_cells);
+            assert!((stepped(CameraSmoothing::Legacy, 60.0, 20, has_cells).zoom - legacy_fast.zoom).abs() > 0.05);
+        }
+    }
+
+    #[test]
+    fn test_zoom_out_limit_follows_mass() {
+        let mut camera = Camera::new();
+        // A lone start-size cell can barely zoom out
+        camera.follow_cells(&[Vec2::ZERO], &[32.0], true);
+        assert_eq!(camera.size_scale, 1.0);
+        camera.adjust_zoom_factor(0.01);
+        assert!((camera.zoom_factor - SMALL_MIN_ZOOM_FACTOR).abs() < 1e-6);
+
+        // Growing lets the wheel go further...
+        camera.follow_cells(&[Vec2::ZERO], &[2000.0], true);
+        camera.adjust_zoom_factor(0.01);
+        let big = camera.zoom_factor;
+        assert!(big < 0.4, "{}", big);
+        // ...and shrinking pulls it back without a wheel event
+        camera.follow_cells(&[Vec2::ZERO], &[40.0], true);
+        assert!((camera.zoom_factor - SMALL_MIN_ZOOM_FACTOR).abs() < 1e-6);
+        assert_eq!(camera.target_zoom, camera.zoom_factor);
+
+        camera.unlimited_zoom = true;
+        camera.adjust_zoom_factor(0.01);
+        assert_eq!(camera.zoom_factor, MIN_ZOOM_FACTOR);
+        camera.adjust_zoom_factor(100.0);
+        assert_eq!(camera.zoom_factor, MAX_ZOOM_FACTOR);
+    }
+}
@@ -0,0 +1,166 @@
+// Chat input helpers: sent-message history and slash-command completion
+use std::collections::VecDeque;
+
+/// Most sent messages remembered for ArrowUp / ArrowDown.
+const HISTORY_LEN: usize = 32;
+
+/// Commands offered by Tab completion: the server's public and operator
+/// commands, the Tournament mode's, and the client-side `/pos`.
+const CHAT_COMMANDS: &[&str] = &[
+    "/help", "/name", "/top", "/operator", "/pos",
+    "/list", "/addbot", "/kick", "/kill", "/killall", "/mass", "/merge", "/speed",
+    "/freeze", "/teleport", "/line", "/spawn", "/clear", "/gamemode", "/chat", "/rename",
+    "/color", "/minion", "/xray", "/status", "/dump", "/heatmap", "/pause", "/resume",
+    "/players", "/start", "/team", "/food", "/foodamount", "/virus", "/virusamount",
+];
+
+/// The last sent messages, browsed newest first.
+pub struct ChatHistory {
+    sent: VecDeque<String>,
+    /// Position while browsing (0 = newest); `None` when not browsing.
+    cursor: Option<usize>,
+    /// What was in the input when browsing started.
+    draft: String,
+}
+
+impl ChatHistory {
+    pub fn new() -> Self {
+        Self { sent: VecDeque::with_capacity(HISTORY_LEN), cursor: None, draft: String::new() }
+    }
+
+    /// Remember a sent message and stop browsing. Repeats of the newest
+    /// entry are stored once.
+    pub fn push(&mut self, message: &str) {
+        self.cursor = None;
+        self.draft.clear();
+        if self.sent.front().map(String::as_str) == Some(message) {
+            return;
+        }
+        if self.sent.len() == HISTORY_LEN {
+            self.sent.pop_back();
+        }
+        self.sent.push_front(message.to_string());
+    }
+
+    /// Step to an older message; `current` is kept as the draft when browsing
+    /// starts. `None` if there is nothing older.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let next = match self.cursor {
+            None => 0,
+            Some(i) => i + 1,
+        };
+        if next >= self.sent.len() {
+            return None;
+        }
+        if self.cursor.is_none() {
+            self.draft = current.to_string();
+        }
+        self.cursor = Some(next);
+        self.sent.get(next).map(String::as_str)
+    }
+
+    /// Step to a newer message, ending on the draft. `None` when not browsing.
+    pub fn newer(&mut self) -> Option<&str> {
+        match self.cursor? {
+            0 => {
+                self.cursor = None;
+                Some(&self.draft)
+            }
+            i => {
+                self.cursor = Some(i - 1);
+                self.sent.get(i - 1).map(String::as_str)
+            }
+        }
+    }
+
+    /// Stop browsing, returning the draft to put back. `None` when not browsing.
+    pub fn cancel(&mut self) -> Option<String> {
+        self.cursor.take()?;
+        Some(std::mem::take(&mut self.draft))
+    }
+}
+
+/// Result of completing a partly typed command.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Completion {
+    /// Replace the input with this.
+    Complete(String),
+    /// Nothing more can be filled in; these commands all match.
+    Ambiguous(Vec<&'static str>),
+    None,
+}
+
+/// Complete the command word of `input`. Only input that is still a bare
+/// `/word` is completed; a unique match gets a trailing space for its
+/// arguments.
+pub fn complete_command(input: &str) -> Completion {
+    if !input.starts_with('/') || input.contains(' ') {
+        return Completion::None;
+    }
+    let typed = input.to_lowercase();
+    let matches: Vec<&'static str> = CHAT_COMMANDS.iter()
+        .copied()
+        .filter(|cmd| cmd.starts_with(&typed))
+        .collect();
+    match matches.as_slice() {
+        [] => Completion::None,
+        [only] => Completion::Complete(format!("{} ", only)),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.len(), |len, cmd| {
+                first.bytes().zip(cmd.bytes()).take(len).take_while(|(a, b)| a == b).count()
+            });
+            if common > typed.len() {
+                Completion::Complete(first[..common].to_string())
+            } else {
+                Completion::Ambiguous(matches)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_browse_and_restore_draft() {
+        let mut history = ChatHistory::new();
+        assert_eq!(history.older("typing"), None);
+        history.push("first");
+        history.push("second");
+        history.push("second");
+
+        assert_eq!(history.older("draft"), Some("second"));
+        assert_eq!(history.older("second"), Some("first"));
+        assert_eq!(history.older("first"), None);
+        assert_eq!(history.newer(), Some("second"));
+        assert_eq!(history.newer(), Some("draft"));
+        assert_eq!(history.newer(), None);
+
+        history.older("draft again");
+        assert_eq!(history.cancel().as_deref(), Some("draft again"));
+        assert_eq!(history.cancel(), None);
+
+        for i in 0..HISTORY_LEN + 5 {
+            history.push(&i.to_string());
+        }
+        let mut browsed = Vec::new();
+        while let Some(msg) = history.older("") {
+            browsed.push(msg.to_string());
+        }
+        // Only the newest HISTORY_LEN are kept
+        assert_eq!(browsed.len(), HISTORY_LEN);
+        assert_eq!(browsed.last().map(String::as_str), Some("5"));
+    }
+
+    #[test]
+    fn test_complete_command() {
+        assert_eq!(complete_command("/xr"), Completion::Complete("/xray ".to_string()));
+        assert_eq!(complete_command("/KILLA"), Completion::Complete("/killall ".to_string()));
+        assert_eq!(complete_command("/kil"), Completion::Complete("/kill".to_string()));
+        assert_eq!(complete_command("/s"), Completion::Ambiguous(vec!["/speed", "/spawn", "/status", "/start"]));
+        assert_eq!(complete_command("/mass 100"), Completion::None);
+        assert_eq!(complete_command("hello"), Completion::None);
+        assert_eq!(complete_command("/nope"), Completion::None);
+    }
+}
@@ -0,0 +1,53 @@
+// Events reported to an embedding page through `GameClientWrapper::on_event`
+use serde::Serialize;
+
+/// Something the host page may want to react to. Serialized as a JSON
+/// object whose `type` names the event:
+///
+/// - `{"type":"connected","url":"wss://host:port"}`
+/// - `{"type":"disconnected"}`: the connection dropped or `disconnect()` was
+///   called; a reconnect may follow unless it was `disconnect()`.
+/// - `{"type":"died","cause":"Eaten by Bob"|null,"mass":1234.5,"seconds_alive":90,"cells_eaten":12}`
+/// - `{"type":"leaderboard","entries":[{"name":"Bob","id":7|null,"is_me":false}]}`,
+///   best first; `id` is only sent by protocol 11+ servers.
+/// - `{"type":"chat","name":"Bob","message":"hi","color":"#ff8000"}`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientEvent {
+    Connected { url: String },
+    Disconnected,
+    Died { cause: Option<String>, mass: f32, seconds_alive: u64, cells_eaten: u32 },
+    Leaderboard { entries: Vec<LeaderboardEntry> },
+    Chat { name: String, message: String, color: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub id: Option<u32>,
+    pub is_me: bool,
+}
+
+impl ClientEvent {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_payload_shapes() {
+        let url = ClientEvent::Connected { url: "ws://localhost:11443".to_string() };
+        assert_eq!(url.to_json(), r#"{"type":"connected","url":"ws://localhost:11443"}"#);
+        assert_eq!(ClientEvent::Disconnected.to_json(), r#"{"type":"disconnected"}"#);
+        let died = ClientEvent::Died { cause: None, mass: 10.5, seconds_alive: 3, cells_eaten: 2 };
+        assert_eq!(died.to_json(), r#"{"type":"died","cause":null,"mass":10.5,"seconds_alive":3,"cells_eaten":2}"#);
+        let leaderboard = ClientEvent::Leaderboard {
+            entries: vec![LeaderboardEntry { name: "Bob".to_string(), id: Some(7), is_me: false }],
+        };
+        assert_eq!(leaderboard.to_json(), r#"{"type":"leaderboard","entries":[{"name":"Bob","id":7,"is_me":false}]}"#);
+    }
+}
@@ -4,28 +4,66 @@ use glam::Vec2;
 use std::collections::HashMap;
 use std::cell::RefCell;
 use std::rc::Rc;
-use web_sys::{window, HtmlCanvasElement, HtmlImageElement};
+use web_sys::{window, HtmlCanvasElement};
 use js_sys::Math;
-use protocol::BinaryReader;
+use protocol::packets::{
+    self, CellFlags, ChatMessagePayload, DeathCause, EatRecord, LeaderboardFfaPayload, MapShape, ServerPacket, SetBorderPayload,
+    UpdateNodesPayload, XrayPayload,
+};
+use protocol::{Color, ProtocolError};
 
 use crate::network::Connection;
-use crate::camera::Camera;
+use crate::camera::{Camera, CameraSmoothing};
 use crate::input::Input;
-use crate::render::{Renderer, Minimap};
-use crate::ui::UI;
+use crate::render::{self, BorderColorMode, CellRelation, CellStyle, Renderer, Minimap, Particles, TeamPie};
+use crate::ui::{ConnectionStatus, UI};
 use crate::utils;
 
+mod chat;
+mod events;
+mod owned;
+mod perf;
+mod skins;
+pub(crate) use chat::ChatHistory;
+use chat::{complete_command, Completion};
+pub(crate) use events::ClientEvent;
+use owned::OwnedCells;
+pub(crate) use perf::{PerfHistory, PERF_SAMPLES};
+use skins::SkinCache;
+
 // Performance: Compile-time constants for hot paths
-const INTERPOLATION_DURATION_MS: f64 = 120.0;
+const DEFAULT_INTERPOLATION_MS: f64 = 120.0;
+pub(crate) const MIN_INTERPOLATION_MS: f64 = 60.0;
+pub(crate) const MAX_INTERPOLATION_MS: f64 = 300.0;
+/// Weight of each new 0x10 arrival gap in the running packet-interval average.
+const PACKET_INTERVAL_BLEND: f64 = 0.1;
+/// Arrival gaps longer than this (tab in background, reconnect) are ignored.
+const PACKET_INTERVAL_MAX_MS: f64 = 1000.0;
+/// Camera zoom below which `FoodVisibility::HideZoomedOut` stops drawing food.
+const FOOD_HIDE_ZOOM: f32 = 0.3;
 const MOUSE_SEND_INTERVAL_MS: f64 = 40.0;
 const FRAME_DT_MAX: f32 = 0.1;
 const FADE_DURATION_MS: f64 = 120.0;
+/// Cells smaller than this (food, most ejected mass) die without particles.
+const PARTICLE_MIN_SIZE: f32 = 40.0;
 const DEATH_REMOVE_MS: f64 = 200.0;
+/// Frame interval while the tab is hidden (4 fps).
+const HIDDEN_FRAME_MS: f64 = 250.0;
+/// A frame this close to its slot is drawn rather than pushed to the next
+/// display refresh; absorbs animation-frame jitter.
+const FRAME_SLACK_MS: f64 = 1.0;
+/// Free-roam camera pan speed, in screen pixels per second.
+const FREE_ROAM_PAN_SPEED: f32 = 1200.0;
+/// How long the "Connected" banner stays up after (re)connecting.
+const CONNECTED_BANNER_MS: f64 = 2000.0;
+/// How long a 0x15 DrawLine stays on screen, fading out.
+const DRAW_LINE_MS: f64 = 1000.0;
 
 /// Represents a cell in the game world.
 ///
-/// Interpolation mirrors the JS client exactly:
-///   dt  = clamp((now - update_time) / 120, 0, 1)
+/// Interpolation mirrors the JS client, with a variable window
+/// (see `GameClient::interpolation_window`, 120ms by default):
+///   dt  = clamp((now - update_time) / window, 0, 1)
 ///   pos = (ox, oy)  +  (target - (ox, oy)) * dt
 ///   size = os + (target_size - os) * dt
 #[derive(Clone)]
@@ -58,6 +96,11 @@ pub struct Cell {
     pub is_virus: bool,
     pub is_ejected: bool,
     pub is_food: bool,
+    /// Golden bonus food (food cell with the agitated flag).
+    pub is_bonus: bool,
+    /// Boosted by a split, shot or ejection (non-food cell with the
+    /// agitated flag); drawn with a wobble.
+    pub is_agitated: bool,
     /// Timestamp (ms) when the most recent server update was received.
     pub update_time: f64,
     /// Timestamp when cell was born (for fade-in effect).
@@ -68,6 +111,8 @@ pub struct Cell {
     pub killed_by: Option<u32>,
     /// Whether this cell has been destroyed and is animating out.
     pub is_destroyed: bool,
+    /// Remerge countdown from a 0x51 packet: `(deadline, total)` in ms.
+    pub merge_timer: Option<(f64, f64)>,
 }
 
 #[derive(Clone, serde::Deserialize)]
@@ -88,9 +133,113 @@ pub struct ServerStats {
     pub bots_total: u32,
     #[serde(rename = "playersLimit")]
     pub players_limit: u32,
+    /// The server is falling behind its tick rate.
+    #[serde(default)]
+    pub overloaded: bool,
 }
 
-#[derive(Clone, Copy)]
+/// When food pellets are drawn.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodVisibility {
+    Always,
+    /// Hidden once the camera zooms out past `FOOD_HIDE_ZOOM`.
+    HideZoomedOut,
+    Never,
+}
+
+impl FoodVisibility {
+    /// Parse the value of the `settingFoodVisibility` select.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "zoomed" => Self::HideZoomedOut,
+            "never" => Self::Never,
+            _ => Self::Always,
+        }
+    }
+
+    /// The `settingFoodVisibility` option value for this mode.
+    pub fn as_setting(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::HideZoomedOut => "zoomed",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// Frame rate limit, to save battery.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FpsCap {
+    Off,
+    Sixty,
+    Thirty,
+}
+
+impl FpsCap {
+    /// Parse the value of the `settingFpsCap` select.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "60" => Self::Sixty,
+            "30" => Self::Thirty,
+            _ => Self::Off,
+        }
+    }
+
+    /// The `settingFpsCap` option value for this cap.
+    pub fn as_setting(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Sixty => "60",
+            Self::Thirty => "30",
+        }
+    }
+
+    /// Minimum time between rendered frames, or `None` when uncapped.
+    pub fn frame_ms(self) -> Option<f64> {
+        match self {
+            Self::Off => None,
+            Self::Sixty => Some(1000.0 / 60.0),
+            Self::Thirty => Some(1000.0 / 30.0),
+        }
+    }
+}
+
+/// Where ejected mass gets its color from.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EjectColorMode {
+    /// Same as the cell it came from.
+    Cell,
+    /// `ClientSettings::eject_color`.
+    Custom,
+    /// A new hue for every pellet.
+    Rainbow,
+}
+
+impl EjectColorMode {
+    /// Parse the value of the `settingEjectColorMode` select.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "custom" => Self::Custom,
+            "rainbow" => Self::Rainbow,
+            _ => Self::Cell,
+        }
+    }
+
+    /// The `settingEjectColorMode` option value for this mode.
+    pub fn as_setting(self) -> &'static str {
+        match self {
+            Self::Cell => "cell",
+            Self::Custom => "custom",
+            Self::Rainbow => "rainbow",
+        }
+    }
+}
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct ClientSettings {
     pub show_skins: bool,
     pub show_names: bool,
@@ -98,8 +247,34 @@ pub struct ClientSettings {
     pub show_grid: bool,
     pub show_background_sectors: bool,
     pub show_minimap: bool,
+    /// Teams mode pie chart under the leaderboard.
+    pub show_team_pie: bool,
     pub dark_theme: bool,
     pub jelly_physics: bool,
+    /// Minimum lerp window in ms; stretched further when packets arrive slower.
+    pub interpolation_ms: f64,
+    pub food_visibility: FoodVisibility,
+    pub cell_style: CellStyle,
+    /// Particle bursts when non-food cells get eaten.
+    pub show_particles: bool,
+    /// Outline my cells, and enemies big enough to eat me in red.
+    pub show_threat_indicators: bool,
+    /// Center the camera on my cells' mass-weighted centroid rather than
+    /// their plain average.
+    pub mass_weighted_camera: bool,
+    /// Zoom out as far at any mass as a big player can, for private servers.
+    pub unlimited_zoom: bool,
+    /// Key that posts the current sector to chat (same as typing `/pos`).
+    pub share_position_key: char,
+    /// Key that toggles the performance graphs.
+    pub debug_hud_key: char,
+    /// Key that toggles the help overlay.
+    pub help_key: char,
+    pub fps_cap: FpsCap,
+    pub camera_smoothing: CameraSmoothing,
+    pub eject_color_mode: EjectColorMode,
+    /// Ejected-mass color in `Custom` mode.
+    pub eject_color: (u8, u8, u8),
 }
 
 impl Default for ClientSettings {
@@ -111,12 +286,69 @@ impl Default for ClientSettings {
             show_grid: true,
             show_background_sectors: true,
             show_minimap: true,
+            show_team_pie: true,
             dark_theme: true,
             jelly_physics: true,
+            interpolation_ms: DEFAULT_INTERPOLATION_MS,
+            food_visibility: FoodVisibility::Always,
+            cell_style: CellStyle::default(),
+            show_particles: true,
+            show_threat_indicators: true,
+            mass_weighted_camera: true,
+            unlimited_zoom: false,
+            share_position_key: 'l',
+            debug_hud_key: 'h',
+            help_key: '?',
+            fps_cap: FpsCap::Off,
+            camera_smoothing: CameraSmoothing::Legacy,
+            eject_color_mode: EjectColorMode::Cell,
+            eject_color: (255, 128, 0),
         }
     }
 }
 
+/// Hosts remote `https://` skins may be loaded from; a listed host also
+/// covers its subdomains.
+pub const SKIN_URL_HOSTS: &[&str] = &[
+    "imgur.com",
+    "raw.githubusercontent.com",
+    "cdn.discordapp.com",
+];
+
+/// `#rrggbb`, as a color input reports it.
+fn parse_hex_rgb(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// A key binding typed into a settings field: one printable character,
+/// lowercased.
+fn parse_key_binding(value: &str) -> Option<char> {
+    let mut chars = value.trim().chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_control() && !c.is_whitespace() => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// `localStorage` key holding [`StoredState`] as JSON.
+const STORAGE_KEY: &str = "cogar.settings";
+/// `localStorage` key set once the help overlay has been shown on its own.
+const HELP_SEEN_KEY: &str = "cogar.helpSeen";
+
+/// Everything the client remembers across page loads.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct StoredState {
+    settings: ClientSettings,
+    last_nick: String,
+    last_skin: Option<String>,
+}
+
 impl Cell {
     pub fn new(id: u32, x: f32, y: f32, size: f32, color: (u8, u8, u8)) -> Self {
         let pos = Vec2::new(x, y);
@@ -140,14 +372,34 @@ impl Cell {
             is_virus: false,
             is_ejected: false,
             is_food: false,
+            is_bonus: false,
+            is_agitated: false,
             update_time: now,
             born_time: now,
             death_time: None,
             killed_by: None,
             is_destroyed: false,
+            merge_timer: None,
         }
     }
 
+    /// Fraction of the remerge countdown still remaining (0 once mergeable).
+    pub fn merge_remaining(&self, now: f64) -> f32 {
+        match self.merge_timer {
+            Some((deadline, total)) if total > 0.0 => {
+                ((deadline - now) / total).clamp(0.0, 1.0) as f32
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Whether being eaten spawns death particles; food is skipped so eating
+    /// sprees stay cheap.
+    #[inline]
+    pub fn bursts_on_death(&self) -> bool {
+        !self.is_food && self.render_size >= PARTICLE_MIN_SIZE
+    }
+
     /// mass = size² / 100  (uses interpolated size, for on-cell label)
     #[inline]
     pub fn mass(&self) -> f32 {
@@ -199,30 +451,64 @@ pub struct GameClient {
     connection: Rc<RefCell<Connection>>,
     renderer: Renderer,
     minimap: Minimap,
+    team_pie: TeamPie,
     camera: Camera,
     input: Input,
     input_state: Rc<RefCell<Input>>,  // Shared with event handlers
     ui: UI,
 
     cells: HashMap<u32, Cell>,
-    my_cells: Vec<u32>,
+    my_cells: OwnedCells,
     border: (f32, f32, f32, f32), // min_x, min_y, max_x, max_y
+    /// Whether the server's SetBorder lets the Unlimited Zoom setting apply.
+    unlimited_zoom_allowed: bool,
+    /// Walls and speed pads from the last 0x54 MapObjects packet.
+    map_shapes: Vec<MapShape>,
+    /// Point of the last 0x15 DrawLine and when it arrived.
+    draw_line: Option<(Vec2, f64)>,
 
     mouse_world_pos: Vec2,
     last_mouse_send: f64,
     last_update: f64,
+    /// Arrival time of the last 0x10 packet.
+    last_nodes_packet: f64,
+    /// Smoothed gap between consecutive 0x10 packets (ms).
+    packet_interval_ms: f64,
 
     alive: bool,
     death_time: Option<f64>,  // When player died (for 250ms delay)
+    /// What the last 0x53 said killed us, for the death overlay.
+    death_info: Option<String>,
+    /// Player id of whoever ate us last, followed by the death screen's
+    /// Spectate button.
+    killer_id: Option<u32>,
+    /// Counters for the current (or last) life, for the death overlay.
+    life: LifeStats,
     pending_spawn_nick: Option<String>,
     pending_spawn: Rc<RefCell<Option<String>>>,  // Spawn request from button click
     last_nick: String,
     last_skin: Option<String>,
-
-    leaderboard: Vec<(bool, String)>,
-
-    /// Loaded skin images — key is the skin name, value is the (possibly still loading) Image element.
-    skins: HashMap<String, HtmlImageElement>,
+    /// Whether the player has spawned this session (gates the minimap).
+    has_spawned: bool,
+
+    /// (is_me, player id on protocol 11+, name)
+    leaderboard: Vec<(bool, Option<u32>, String)>,
+    /// Team sizes from the last 0x32; empty outside Teams mode.
+    team_sizes: Vec<f32>,
+    /// Spectator camera moved by the keyboard instead of the server's view.
+    free_roam: bool,
+    /// Player picked from the leaderboard to spectate, drained each frame.
+    pending_spectate: Rc<RefCell<Option<u32>>>,
+    roster: Vec<RosterEntry>,
+    /// Whether the scoreboard overlay is up, and whether it shows the latest roster.
+    scoreboard_shown: bool,
+    scoreboard_stale: bool,
+
+    /// Skin images by name, with their load state.
+    skins: SkinCache,
+    /// Messages sent from the chat input, for ArrowUp / ArrowDown.
+    chat_history: ChatHistory,
+    particles: Particles,
 
     // Packet queue - WebSocket handler pushes here, game loop processes
     packet_queue: Rc<RefCell<Vec<Vec<u8>>>>,
@@ -230,16 +516,40 @@ pub struct GameClient {
     // WebSocket event flags (to avoid borrow conflicts in event handlers)
     ws_open_flag: Rc<std::cell::Cell<bool>>,
     ws_close_flag: Rc<std::cell::Cell<bool>>,
+    /// When the next reconnect attempt fires (ms), set by the WebSocket
+    /// close handler; `None` while connected or when none is scheduled.
+    reconnect_at: Rc<std::cell::Cell<Option<f64>>>,
+    /// Why the server closed the connection for good, set by the WebSocket
+    /// close handler instead of scheduling a reconnect.
+    rejection: Rc<RefCell<Option<String>>>,
+    connection_status: ConnectionStatus,
+    /// When to hide the "Connected" banner.
+    status_banner_hide_at: Option<f64>,
+    /// Bumped whenever the page switches servers or disconnects, so a
+    /// reconnect scheduled for the old connection knows to stand down.
+    connection_epoch: Rc<std::cell::Cell<u32>>,
+
+    /// The host page's `on_event` callback.
+    event_listener: Option<js_sys::Function>,
+    /// Events for `event_listener`, dispatched once the client isn't borrowed.
+    events: Vec<ClientEvent>,
 
     // FPS tracking
     frame_count: u32,
     last_fps_time: f64,
     fps: u32,
+    /// Frame time, traffic and drawn cells for the debug HUD.
+    perf: PerfHistory,
+    show_debug_hud: bool,
+    /// When the FPS cap next lets a frame render.
+    next_frame_at: f64,
     saw_eat_record: bool,
     settings: ClientSettings,
 
     xray_players: Vec<XrayPlayer>,
     xray_last_update: f64,
+    /// Merge timers for split cells that haven't arrived in a 0x10 yet.
+    pending_merge_timers: HashMap<u32, (f64, f64)>,
 
     // Server stats
     server_stats: Option<ServerStats>,
@@ -254,6 +564,28 @@ pub struct RenderPoint {
     pub rl: f32,
 }
 
+/// What the death overlay reports about a life, counted by the client.
+#[derive(Debug, Clone, Copy, Default)]
+struct LifeStats {
+    /// When the life's first cell arrived (ms).
+    started_at: f64,
+    /// Total mass as of the last frame alive.
+    mass: f32,
+    /// Cells (food included) eaten by my cells, not counting my own merges.
+    cells_eaten: u32,
+}
+
+/// One row of the 0x52 roster: every alive player and bot, heaviest first.
+pub struct RosterEntry {
+    pub name: String,
+    pub mass: u32,
+    pub color: (u8, u8, u8),
+    pub is_bot: bool,
+}
+
+/// Most roster rows kept (the server caps at the same number).
+const ROSTER_MAX_ENTRIES: usize = 128;
+
 #[derive(Clone)]
 struct XrayPlayer {
     id: u32,
@@ -272,7 +604,7 @@ struct PointRef {
 
 #[wasm_bindgen]
 impl GameClient {
-    pub fn new(canvas_id: &str, server_url: &str) -> Result<GameClient, JsValue> {
+    pub fn new(canvas_id: &str, server_url: &str, cogar: bool) -> Result<GameClient, JsValue> {
         let window = window().ok_or("No window")?;
         let document = window.document().ok_or("No document")?;
         let canvas = document
@@ -280,52 +612,88 @@ impl GameClient {
             .ok_or("Canvas not found")?
             .dyn_into::<HtmlCanvasElement>()?;
 
-        // Set canvas size
-        canvas.set_width(window.inner_width()?.as_f64().unwrap() as u32);
-        canvas.set_height(window.inner_height()?.as_f64().unwrap() as u32);
+        let stored: StoredState = utils::load_json(STORAGE_KEY).unwrap_or_default();
 
-        let renderer = Renderer::new(canvas.clone())?;
+        let mut renderer = Renderer::new(canvas.clone())?;
+        renderer.set_cell_style(stored.settings.cell_style);
+        renderer.resize(window.inner_width()?.as_f64().unwrap(), window.inner_height()?.as_f64().unwrap());
         let minimap = Minimap::new()?;
-        let connection = Connection::new(server_url)?;
+        let team_pie = TeamPie::new()?;
+        let connection = Connection::new(server_url, cogar)?;
 
         let conn_rc = Rc::new(RefCell::new(connection));
 
-        let input_state = Rc::new(RefCell::new(Input::new()));
+        let mut input = Input::new();
+        input.share_position_key = stored.settings.share_position_key;
+        input.debug_hud_key = stored.settings.debug_hud_key;
+        input.help_key = stored.settings.help_key;
+        let input_state = Rc::new(RefCell::new(input));
         let now = utils::now();
         let ui = UI::new(document);
+        ui.prefill_login(&stored.last_nick, stored.last_skin.as_deref());
+        ui.set_connection_status(Some(&ConnectionStatus::Connecting));
 
         let client = Self {
             connection: conn_rc,
             renderer,
             minimap,
+            team_pie,
             camera: Camera::new(),
             input: Input::new(),
             input_state: input_state.clone(),
             ui,
             cells: HashMap::new(),
-            my_cells: Vec::new(),
+            my_cells: OwnedCells::new(),
             border: (0.0, 0.0, 11180.0, 11180.0),
+            unlimited_zoom_allowed: false,
+            map_shapes: Vec::new(),
+            draw_line: None,
             mouse_world_pos: Vec2::ZERO,
             last_mouse_send: 0.0,
             last_update: now,
+            last_nodes_packet: 0.0,
+            packet_interval_ms: DEFAULT_INTERPOLATION_MS,
             alive: false,
             death_time: None,
+            death_info: None,
+            killer_id: None,
+            life: LifeStats::default(),
             pending_spawn_nick: None,
             pending_spawn: Rc::new(RefCell::new(None)),
-            last_nick: String::new(),
-            last_skin: None,
+            last_nick: stored.last_nick,
+            last_skin: stored.last_skin,
+            has_spawned: false,
             leaderboard: Vec::new(),
-            skins: HashMap::new(),
+            team_sizes: Vec::new(),
+            free_roam: false,
+            pending_spectate: Rc::new(RefCell::new(None)),
+            roster: Vec::new(),
+            scoreboard_shown: false,
+            scoreboard_stale: false,
+            skins: SkinCache::new(SKIN_URL_HOSTS),
+            chat_history: ChatHistory::new(),
+            particles: Particles::new(),
             packet_queue: Rc::new(RefCell::new(Vec::new())),
             ws_open_flag: Rc::new(std::cell::Cell::new(false)),
             ws_close_flag: Rc::new(std::cell::Cell::new(false)),
+            reconnect_at: Rc::new(std::cell::Cell::new(None)),
+            rejection: Rc::new(RefCell::new(None)),
+            connection_status: ConnectionStatus::Connecting,
+            status_banner_hide_at: None,
+            connection_epoch: Rc::new(std::cell::Cell::new(0)),
+            event_listener: None,
+            events: Vec::new(),
             frame_count: 0,
             last_fps_time: now,
             fps: 0,
+            perf: PerfHistory::new(),
+            show_debug_hud: false,
+            next_frame_at: now,
             saw_eat_record: false,
-            settings: ClientSettings::default(),
+            settings: stored.settings,
             xray_players: Vec::new(),
             xray_last_update: 0.0,
+            pending_merge_timers: HashMap::new(),
             server_stats: None,
             last_stats_request: 0.0,
             latency: None,
@@ -335,9 +703,13 @@ impl GameClient {
     }
 
     pub fn spawn(&mut self, nick: &str) {
+        self.set_free_roam(false);
         let (skin, name) = Self::parse_spawn_name(nick);
         self.last_nick = name;
         self.last_skin = skin;
+        self.has_spawned = true;
+        self.save_settings();
+        self.ui.hide_death_overlay();
         let spawn_name = self.build_spawn_name();
         self.pending_spawn_nick = Some(spawn_name.clone());
         if let Err(e) = self.connection.borrow().send_spawn(&spawn_name) {
@@ -358,11 +730,55 @@ impl GameClient {
     }
 
     pub fn send_chat_message(&self, message: &str) {
-        if let Err(e) = self.connection.borrow().send_chat(message) {
+        let message = if message.trim() == "/pos" {
+            self.position_message()
+        } else {
+            message.to_string()
+        };
+        if let Err(e) = self.connection.borrow().send_chat(&message) {
             web_sys::console::error_1(&format!("Failed to send chat: {:?}", e).into());
         }
     }
 
+    /// Tab completion for the chat input: the completed text, if any. When
+    /// several commands match and none can be filled in, they are listed in
+    /// the chat box instead.
+    pub(crate) fn complete_chat_input(&self, input: &str) -> Option<String> {
+        match complete_command(input) {
+            Completion::Complete(text) => Some(text),
+            Completion::Ambiguous(commands) => {
+                self.ui.show_chat_message("Commands", &commands.join(" "), (150, 150, 150));
+                None
+            }
+            Completion::None => None,
+        }
+    }
+
+    /// Chat line announcing the camera's sector and coordinates; the camera
+    /// follows the spectated view too, so this works while spectating.
+    fn position_message(&self) -> String {
+        let pos = self.camera.position;
+        format!(
+            "I'm at {} (x: {:.0}, y: {:.0})",
+            render::sector_at(self.border, pos),
+            pos.x,
+            pos.y,
+        )
+    }
+
+    pub(crate) fn settings(&self) -> ClientSettings {
+        self.settings
+    }
+
+    /// Persist settings plus the last nick/skin to `localStorage`.
+    pub(crate) fn save_settings(&self) {
+        utils::save_json(STORAGE_KEY, &StoredState {
+            settings: self.settings,
+            last_nick: self.last_nick.clone(),
+            last_skin: self.last_skin.clone(),
+        });
+    }
+
     pub(crate) fn set_show_skins(&mut self, value: bool) {
         self.settings.show_skins = value;
     }
@@ -387,6 +803,11 @@ impl GameClient {
         self.settings.show_minimap = value;
     }
 
+    pub(crate) fn set_show_team_pie(&mut self, value: bool) {
+        self.settings.show_team_pie = value;
+        self.update_team_pie();
+    }
+
     pub(crate) fn set_dark_theme(&mut self, value: bool) {
         self.settings.dark_theme = value;
         if let Some(document) = window().and_then(|w| w.document()) {
@@ -395,10 +816,117 @@ impl GameClient {
                 let _ = root.set_attribute("data-theme", theme);
             }
         }
+        self.update_team_pie();
+    }
+
+    pub(crate) fn set_show_threat_indicators(&mut self, value: bool) {
+        self.settings.show_threat_indicators = value;
+    }
+
+    pub(crate) fn set_mass_weighted_camera(&mut self, value: bool) {
+        self.settings.mass_weighted_camera = value;
+    }
+
+    pub(crate) fn set_unlimited_zoom(&mut self, value: bool) {
+        self.settings.unlimited_zoom = value;
+        self.apply_unlimited_zoom();
+    }
+
+    /// Unlimited zoom only takes effect where the server allows it.
+    fn apply_unlimited_zoom(&mut self) {
+        self.camera.unlimited_zoom = self.settings.unlimited_zoom && self.unlimited_zoom_allowed;
+        self.camera.clamp_zoom_factor();
+    }
+
+    pub(crate) fn set_show_particles(&mut self, value: bool) {
+        self.settings.show_particles = value;
+        if !value {
+            self.particles.clear();
+        }
+    }
+
+    /// Rebind the share-position key; anything but a single printable
+    /// character is ignored.
+    pub(crate) fn set_share_position_key(&mut self, value: &str) {
+        if let Some(c) = parse_key_binding(value) {
+            self.settings.share_position_key = c;
+            self.input_state.borrow_mut().share_position_key = c;
+        }
+    }
+
+    /// Rebind the debug HUD key, like [`Self::set_share_position_key`].
+    pub(crate) fn set_debug_hud_key(&mut self, value: &str) {
+        if let Some(c) = parse_key_binding(value) {
+            self.settings.debug_hud_key = c;
+            self.input_state.borrow_mut().debug_hud_key = c;
+        }
+    }
+
+    /// Rebind the help key, like [`Self::set_share_position_key`].
+    pub(crate) fn set_help_key(&mut self, value: &str) {
+        if let Some(c) = parse_key_binding(value) {
+            self.settings.help_key = c;
+            self.input_state.borrow_mut().help_key = c;
+        }
+    }
+
+    pub(crate) fn set_food_visibility(&mut self, value: FoodVisibility) {
+        self.settings.food_visibility = value;
+    }
+
+    pub(crate) fn set_fps_cap(&mut self, value: FpsCap) {
+        self.settings.fps_cap = value;
+    }
+
+    /// Pick the ejected-mass color; `hex` is the `#rrggbb` of the color
+    /// input, used in `Custom` mode.
+    pub(crate) fn set_eject_color(&mut self, mode: EjectColorMode, hex: &str) {
+        self.settings.eject_color_mode = mode;
+        if let Some(rgb) = parse_hex_rgb(hex) {
+            self.settings.eject_color = rgb;
+        }
+        // Not connected yet is fine: handle_ws_open sends it
+        let _ = self.connection.borrow().send_eject_color(self.eject_color());
+    }
+
+    fn eject_color(&self) -> packets::EjectColor {
+        let (r, g, b) = self.settings.eject_color;
+        match self.settings.eject_color_mode {
+            EjectColorMode::Cell => packets::EjectColor::Cell,
+            EjectColorMode::Custom => packets::EjectColor::Solid(Color::new(r, g, b)),
+            EjectColorMode::Rainbow => packets::EjectColor::Rainbow,
+        }
+    }
+
+    pub(crate) fn set_camera_smoothing(&mut self, value: CameraSmoothing) {
+        self.settings.camera_smoothing = value;
+    }
+
+    pub(crate) fn set_border_width(&mut self, value: f32) {
+        self.settings.cell_style.border_width = value.max(0.0);
+        self.renderer.set_cell_style(self.settings.cell_style);
+    }
+
+    pub(crate) fn set_border_color(&mut self, value: BorderColorMode) {
+        self.settings.cell_style.border_color = value;
+        self.renderer.set_cell_style(self.settings.cell_style);
+    }
+
+    pub(crate) fn set_skin_only(&mut self, value: bool) {
+        self.settings.cell_style.skin_only = value;
+        self.renderer.set_cell_style(self.settings.cell_style);
+    }
+
+    pub(crate) fn set_interpolation_ms(&mut self, value: f64) {
+        self.settings.interpolation_ms = value.clamp(MIN_INTERPOLATION_MS, MAX_INTERPOLATION_MS);
     }
 
     pub(crate) fn adjust_zoom(&mut self, zoom_multiplier: f32) {
-        self.camera.adjust_zoom_factor(zoom_multiplier);
+        if self.free_roam {
+            self.camera.adjust_free_zoom(zoom_multiplier);
+        } else {
+            self.camera.adjust_zoom_factor(zoom_multiplier);
+        }
     }
 }
 
@@ -414,7 +942,10 @@ impl GameClient {
         self.input_state.clone()
     }
 
-    pub(crate) fn handle_ws_open(&self) {
+    pub(crate) fn handle_ws_open(&mut self) {
+        self.set_connection_status(ConnectionStatus::Connected);
+        let url = self.connection.borrow().url().to_string();
+        self.emit(ClientEvent::Connected { url });
         let conn = self.connection.borrow();
         if let Err(e) = conn.send_protocol_version() {
             web_sys::console::error_1(&format!("Failed to send protocol: {:?}", e).into());
@@ -422,28 +953,174 @@ impl GameClient {
         if let Err(e) = conn.send_handshake() {
             web_sys::console::error_1(&format!("Failed to send handshake: {:?}", e).into());
         }
+        if let Err(e) = conn.send_viewport(self.renderer.width(), self.renderer.height()) {
+            web_sys::console::error_1(&format!("Failed to send viewport: {:?}", e).into());
+        }
+        // The server defaults to the cell color; other servers never hear of it
+        if self.settings.eject_color_mode != EjectColorMode::Cell
+            && let Err(e) = conn.send_eject_color(self.eject_color())
+        {
+            web_sys::console::error_1(&format!("Failed to send eject color: {:?}", e).into());
+        }
         web_sys::console::log_1(&"WebSocket ready for spawn".into());
     }
 
     pub(crate) fn handle_disconnect(&mut self) {
+        if self.connection_status == ConnectionStatus::Connected {
+            self.emit(ClientEvent::Disconnected);
+        }
         self.cells.clear();
-        self.my_cells.clear();
+        self.particles.clear();
+        self.my_cells.reset();
+        self.pending_merge_timers.clear();
         self.alive = false;
         self.death_time = Some(utils::now());
         self.xray_players.clear();
         self.xray_last_update = 0.0;
+        self.last_nodes_packet = 0.0;
         
         // Immediately clear the canvas to remove old cells
         let background = if self.settings.dark_theme { "#111" } else { "#f2f2f2" };
         self.renderer.clear(background);
         
+        self.death_info = None;
+        self.killer_id = None;
         self.ui.show_login_overlay(&self.last_nick, self.last_skin.as_deref());
+        let status = match self.rejection.borrow_mut().take() {
+            Some(reason) => ConnectionStatus::Rejected(reason),
+            None => ConnectionStatus::Reconnecting { seconds: 0 },
+        };
+        self.set_connection_status(status);
+    }
+
+    /// Enter or leave the free-roam spectator camera. Leaving hands the
+    /// camera back to the server's spectate view at the normal zoom.
+    fn set_free_roam(&mut self, enabled: bool) {
+        if self.free_roam == enabled {
+            return;
+        }
+        self.free_roam = enabled;
+        if enabled {
+            self.camera.target_position = self.camera.position;
+            self.ui.show_chat_message("Spectate", "Free camera on: WASD / arrows to pan, wheel to zoom, F to leave", (150, 150, 150));
+        } else {
+            self.camera.set_base_zoom(self.camera.size_scale);
+        }
+    }
+
+    /// Track the connection state: the banner, and the play button, which
+    /// only works while connected.
+    fn set_connection_status(&mut self, status: ConnectionStatus) {
+        if status == self.connection_status {
+            return;
+        }
+        let connected = status == ConnectionStatus::Connected;
+        self.ui.set_connection_status(Some(&status));
+        self.ui.set_spawn_enabled(connected);
+        self.status_banner_hide_at = connected.then(|| utils::now() + CONNECTED_BANNER_MS);
+        self.connection_status = status;
+    }
+
+    /// Count down to the next reconnect attempt, or report that none is
+    /// coming, and hide the "Connected" banner once it has been seen.
+    fn update_connection_status(&mut self, now: f64) {
+        match self.connection_status {
+            ConnectionStatus::Connecting | ConnectionStatus::Disconnected | ConnectionStatus::Rejected(_) => {}
+            ConnectionStatus::Connected => {
+                if self.status_banner_hide_at.is_some_and(|at| now >= at) {
+                    self.status_banner_hide_at = None;
+                    self.ui.set_connection_status(None);
+                }
+            }
+            ConnectionStatus::Reconnecting { .. } | ConnectionStatus::Failed => {
+                let status = match self.reconnect_at.get() {
+                    Some(at) => ConnectionStatus::Reconnecting {
+                        seconds: ((at - now) / 1000.0).ceil().max(0.0) as u32,
+                    },
+                    None => ConnectionStatus::Failed,
+                };
+                self.set_connection_status(status);
+            }
+        }
+    }
+
+    /// Fit the canvases to the window and the current device pixel ratio.
+    pub(crate) fn resize(&mut self) {
+        if let Some(win) = window() {
+            let width = win.inner_width().ok().and_then(|w| w.as_f64()).unwrap_or(800.0);
+            let height = win.inner_height().ok().and_then(|h| h.as_f64()).unwrap_or(600.0);
+            self.renderer.resize(width, height);
+        }
+        self.minimap.resize();
+        self.team_pie.resize();
+        self.update_team_pie();
+        // Not connected yet is fine: handle_ws_open sends it
+        let _ = self.connection.borrow().send_viewport(self.renderer.width(), self.renderer.height());
     }
 
     pub(crate) fn reconnect(&mut self) -> Result<web_sys::WebSocket, JsValue> {
         self.connection.borrow_mut().reconnect()
     }
 
+    /// Drop the current connection and open one to `url` instead.
+    pub(crate) fn connect_to(&mut self, url: &str, cogar: bool) -> Result<web_sys::WebSocket, JsValue> {
+        self.reset_connection();
+        let ws = self.connection.borrow_mut().connect_to(url, cogar)?;
+        self.set_connection_status(ConnectionStatus::Connecting);
+        Ok(ws)
+    }
+
+    /// Close the connection for good; only `connect_to` opens another.
+    pub(crate) fn disconnect(&mut self) {
+        self.reset_connection();
+        self.connection.borrow().close();
+        self.set_connection_status(ConnectionStatus::Disconnected);
+    }
+
+    /// Forget the current connection: cancel its pending reconnect, drop
+    /// anything it queued and clear the world.
+    fn reset_connection(&mut self) {
+        self.connection_epoch.set(self.connection_epoch.get().wrapping_add(1));
+        self.reconnect_at.set(None);
+        self.ws_open_flag.set(false);
+        self.ws_close_flag.set(false);
+        self.rejection.borrow_mut().take();
+        self.packet_queue.borrow_mut().clear();
+        self.unlimited_zoom_allowed = false;
+        self.apply_unlimited_zoom();
+        self.handle_disconnect();
+    }
+
+    /// Current connection epoch (for the WebSocket handlers to check
+    /// before reconnecting).
+    pub(crate) fn connection_epoch(&self) -> Rc<std::cell::Cell<u32>> {
+        self.connection_epoch.clone()
+    }
+
+    /// Register (or with `None`, remove) the host page's event callback.
+    pub(crate) fn set_event_listener(&mut self, listener: Option<js_sys::Function>) {
+        if listener.is_none() {
+            self.events.clear();
+        }
+        self.event_listener = listener;
+    }
+
+    /// Queue an event for the host page; dropped when nobody listens.
+    fn emit(&mut self, event: ClientEvent) {
+        if self.event_listener.is_some() {
+            self.events.push(event);
+        }
+    }
+
+    /// The listener and the events queued for it since the last call.
+    pub(crate) fn take_events(&mut self) -> Option<(js_sys::Function, Vec<ClientEvent>)> {
+        if self.events.is_empty() {
+            return None;
+        }
+        let listener = self.event_listener.clone()?;
+        Some((listener, std::mem::take(&mut self.events)))
+    }
+
     /// Mother cell color (experimental mode).
     const MOTHER_COLOR: (u8, u8, u8) = (206, 99, 99);
 
@@ -577,6 +1254,58 @@ impl GameClient {
         self.pending_spawn.clone()
     }
 
+    /// Death screen "Respawn": spawn again under the last nick and skin.
+    pub(crate) fn queue_respawn(&self) {
+        *self.pending_spawn.borrow_mut() = Some(self.build_spawn_name());
+    }
+
+    /// Death screen "Spectate": watch the game without the overlay,
+    /// following whoever ate us. The server switches to the leader if they
+    /// have died since.
+    pub(crate) fn spectate_after_death(&self) {
+        self.ui.hide_death_overlay();
+        if let Err(e) = self.connection.borrow().send_spectate() {
+            web_sys::console::error_1(&format!("Failed to send spectate: {:?}", e).into());
+            return;
+        }
+        if let Some(killer_id) = self.killer_id {
+            if let Err(e) = self.connection.borrow().send_spectate_target(killer_id) {
+                web_sys::console::error_1(&format!("Failed to send spectate target: {:?}", e).into());
+            }
+        }
+    }
+
+    /// Death screen "Change name": back to the login overlay.
+    pub(crate) fn show_login(&self) {
+        self.ui.show_login_overlay(&self.last_nick, self.last_skin.as_deref());
+    }
+
+    /// Open the help overlay, listing the keys as currently bound.
+    pub(crate) fn show_help(&self) {
+        self.ui.show_help(&self.input_state.borrow().help_sections());
+    }
+
+    pub(crate) fn hide_help(&self) {
+        self.ui.hide_help();
+    }
+
+    /// Open the help overlay if this browser has never had it opened for it.
+    pub(crate) fn show_help_on_first_visit(&self) {
+        if utils::load_item(HELP_SEEN_KEY).is_none() {
+            utils::save_item(HELP_SEEN_KEY, "1");
+            self.show_help();
+        }
+    }
+
+    pub(crate) fn pending_spectate(&self) -> Rc<RefCell<Option<u32>>> {
+        self.pending_spectate.clone()
+    }
+
+    /// Sent-message history (for the chat input's key handler)
+    pub(crate) fn chat_history(&mut self) -> &mut ChatHistory {
+        &mut self.chat_history
+    }
+
     /// Get the WebSocket open flag (for WebSocket onopen handler)
     pub(crate) fn ws_open_flag(&self) -> Rc<std::cell::Cell<bool>> {
         self.ws_open_flag.clone()
@@ -587,28 +1316,44 @@ impl GameClient {
         self.ws_close_flag.clone()
     }
 
-    /// Start loading a skin image the first time it is encountered.
-    /// The Image element is created immediately; the browser fetches the PNG asynchronously.
-    /// Rendering checks `img.complete() && img.width() > 0` before drawing.
-    fn ensure_skin_loaded(&mut self, skin_name: &str) {
-        if self.skins.contains_key(skin_name) {
-            return;
-        }
-        if let Ok(img) = HtmlImageElement::new() {
-            img.set_src(&format!("./skins/{}.png", skin_name));
-            self.skins.insert(skin_name.to_string(), img);
+    /// Get the permanent-close reason slot (for the WebSocket onclose handler)
+    pub(crate) fn rejection(&self) -> Rc<RefCell<Option<String>>> {
+        self.rejection.clone()
+    }
+
+    /// Get the next reconnect time (for the WebSocket handlers to publish it)
+    pub(crate) fn reconnect_at(&self) -> Rc<std::cell::Cell<Option<f64>>> {
+        self.reconnect_at.clone()
+    }
+
+    /// Lerp window in ms: the user's setting, stretched to the measured
+    /// packet interval so cells don't stop and wait between slow updates.
+    fn interpolation_window(&self) -> f64 {
+        self.settings.interpolation_ms
+            .max(self.packet_interval_ms)
+            .min(MAX_INTERPOLATION_MS)
+    }
+
+    /// Blend the gap since the previous 0x10 packet into `packet_interval_ms`.
+    fn track_packet_interval(&mut self, now: f64) {
+        let gap = now - self.last_nodes_packet;
+        if self.last_nodes_packet > 0.0 && gap <= PACKET_INTERVAL_MAX_MS {
+            self.packet_interval_ms += (gap - self.packet_interval_ms) * PACKET_INTERVAL_BLEND;
         }
+        self.last_nodes_packet = now;
     }
 
     /// Normalize skin names from the protocol or nick format.
     /// JS uses a leading '%' to indicate skins in protocol >= 11.
+    /// `https://` URLs pass through as-is; local names may not contain path
+    /// separators since they become `./skins/{name}.png`.
     fn normalize_skin_name(raw: &str) -> Option<String> {
         let trimmed = raw.trim();
-        if trimmed.is_empty() {
-            return None;
+        if trimmed.starts_with("https://") {
+            return Some(trimmed.to_string());
         }
         let normalized = trimmed.strip_prefix('%').unwrap_or(trimmed);
-        if normalized.is_empty() {
+        if normalized.is_empty() || normalized.contains(['/', '\\']) || normalized.contains("..") {
             None
         } else {
             Some(normalized.to_string())
@@ -640,12 +1385,12 @@ impl GameClient {
         self.last_nick.clone()
     }
 
-    /// Main update method called from JavaScript animation frame
-    pub fn update(&mut self) -> Result<(), JsValue> {
+    /// Per-frame work that can't wait for the next rendered frame: WebSocket
+    /// events, key presses, queued packets and overlays. Runs on every
+    /// animation frame, even when the FPS cap skips `update`.
+    pub fn process_events(&mut self) {
         let now = utils::now();
-        let frame_dt = (((now - self.last_update) / 1000.0).max(0.0).min(FRAME_DT_MAX as f64)) as f32;
-        self.last_update = now;
-        
+
         // Process WebSocket event flags
         if self.ws_open_flag.get() {
             self.ws_open_flag.set(false);
@@ -656,9 +1401,10 @@ impl GameClient {
             self.ws_close_flag.set(false);
             self.handle_disconnect();
         }
+        self.update_connection_status(now);
         
         // Process key press events (only send on initial press, not while held)
-        let (should_split, should_eject, should_q, should_e, should_r, should_t, should_p, should_enter, should_escape) = {
+        let (should_split, should_eject, should_q, should_e, should_r, should_t, should_p, should_enter, should_escape, should_share_position, should_toggle_free_roam, should_toggle_debug_hud, should_toggle_help) = {
             let mut input = self.input_state.borrow_mut();
             
             let should_split = input.space_just_pressed();
@@ -670,12 +1416,34 @@ impl GameClient {
             let should_p = input.p_just_pressed();
             let should_enter = input.enter_just_pressed();
             let should_escape = input.escape_just_pressed();
+            let should_share_position = input.share_position_just_pressed();
+            let should_toggle_free_roam = input.f_just_pressed();
+            let should_toggle_debug_hud = input.debug_hud_just_pressed();
+            let should_toggle_help = input.help_just_pressed();
             
             // Update previous frame state for next frame's edge detection
             input.update_previous_state();
             
-            (should_split, should_eject, should_q, should_e, should_r, should_t, should_p, should_enter, should_escape)
+            (should_split, should_eject, should_q, should_e, should_r, should_t, should_p, should_enter, should_escape, should_share_position, should_toggle_free_roam, should_toggle_debug_hud, should_toggle_help)
         };
+        if should_toggle_debug_hud {
+            self.show_debug_hud = !self.show_debug_hud;
+        }
+        if should_toggle_help {
+            if self.ui.help_visible() {
+                self.ui.hide_help();
+            } else {
+                self.show_help();
+            }
+        }
+
+        // Free roam is for spectating only; W pans instead of ejecting
+        if self.alive {
+            self.set_free_roam(false);
+        } else if should_toggle_free_roam {
+            self.set_free_roam(!self.free_roam);
+        }
+        let should_eject = should_eject && !self.free_roam;
         
         // Check WebSocket state once for all actions
         let ws_open = {
@@ -727,6 +1495,10 @@ impl GameClient {
                     web_sys::console::error_1(&format!("Failed to send P: {:?}", e).into());
                 }
             }
+
+            if should_share_position {
+                self.send_chat_message("/pos");
+            }
         }
         
         if should_enter {
@@ -741,16 +1513,6 @@ impl GameClient {
             }
         }
 
-        // FPS tracking — update stats display once per second
-        self.frame_count += 1;
-        if now - self.last_fps_time >= 1000.0 {
-            self.fps = self.frame_count;
-            self.frame_count = 0;
-            self.last_fps_time = now;
-            let score = self.calculate_score();
-            self.ui.update_stats(self.fps, score, self.my_cells.len());
-        }
-
         // Send stats request every 2 seconds (matches JS implementation)
         if ws_open && now - self.last_stats_request >= 2000.0 {
             self.last_stats_request = now;
@@ -765,19 +1527,96 @@ impl GameClient {
             self.spawn(&nick);
         }
 
+        // Leaderboard click: only meaningful while spectating
+        let spectate_target = self.pending_spectate.borrow_mut().take();
+        if let Some(player_id) = spectate_target {
+            if ws_open && !self.alive {
+                if let Err(e) = self.connection.borrow().send_spectate_target(player_id) {
+                    web_sys::console::error_1(&format!("Failed to send spectate target: {:?}", e).into());
+                }
+            }
+        }
+
         // Process all queued packets from WebSocket
         let packets: Vec<Vec<u8>> = self.packet_queue.borrow_mut().drain(..).collect();
         for packet_data in packets {
+            self.perf.add_bytes(packet_data.len());
             self.handle_packet(packet_data);
         }
 
+        // Scoreboard overlay while Tab is held
+        let tab_held = self.input_state.borrow().tab_pressed;
+        if tab_held && (!self.scoreboard_shown || self.scoreboard_stale) {
+            self.ui.show_scoreboard(&self.roster);
+            self.scoreboard_shown = true;
+            self.scoreboard_stale = false;
+        } else if !tab_held && self.scoreboard_shown {
+            self.ui.hide_scoreboard();
+            self.scoreboard_shown = false;
+        }
+
         // Check for death overlay delay (250ms after death)
         if let Some(death_time) = self.death_time {
             if !self.alive && self.my_cells.is_empty() && now - death_time >= 250.0 {
-                self.ui.show_login_overlay(&self.last_nick, self.last_skin.as_deref());
+                let seconds_alive = ((death_time - self.life.started_at).max(0.0) / 1000.0) as u64;
+                let cause = self.death_info.take();
+                self.ui.show_death_overlay(cause.as_deref(), self.life.mass, seconds_alive, self.life.cells_eaten);
+                self.emit(ClientEvent::Died {
+                    cause,
+                    mass: self.life.mass,
+                    seconds_alive,
+                    cells_eaten: self.life.cells_eaten,
+                });
                 self.death_time = None; // Clear so we don't show repeatedly
             }
         }
+    }
+
+    /// Whether a frame should be simulated and rendered now. `hidden` (the
+    /// tab is in the background) drops to a maintenance rate regardless of
+    /// the FPS cap.
+    pub fn frame_due(&mut self, now: f64, hidden: bool) -> bool {
+        let budget = if hidden {
+            HIDDEN_FRAME_MS
+        } else {
+            match self.settings.fps_cap.frame_ms() {
+                Some(ms) => ms,
+                None => return true,
+            }
+        };
+        if now + FRAME_SLACK_MS < self.next_frame_at {
+            return false;
+        }
+        // Keep the cadence, but don't try to catch up after a long gap
+        self.next_frame_at = if now - self.next_frame_at > budget {
+            now + budget
+        } else {
+            self.next_frame_at + budget
+        };
+        true
+    }
+
+    /// Simulate and render one frame, called from the animation frame once
+    /// `frame_due` allows it.
+    pub fn update(&mut self) -> Result<(), JsValue> {
+        let now = utils::now();
+        let frame_ms = (now - self.last_update).max(0.0);
+        let frame_dt = ((frame_ms / 1000.0).min(FRAME_DT_MAX as f64)) as f32;
+        self.last_update = now;
+
+        // FPS tracking — update stats display once per second. Counts rendered
+        // frames, so it shows the capped rate
+        self.frame_count += 1;
+        if now - self.last_fps_time >= 1000.0 {
+            self.fps = self.frame_count;
+            self.frame_count = 0;
+            self.last_fps_time = now;
+            let score = self.calculate_score();
+            self.ui.update_stats(self.fps, score, self.my_cells.len());
+        }
+        if self.alive {
+            self.life.mass = self.calculate_score();
+        }
 
         // Clean up destroyed cells that have finished their fade-out animation
         let cells_to_remove: Vec<u32> = self.cells.iter()
@@ -794,6 +1633,8 @@ impl GameClient {
             self.cells.remove(&cell_id);
         }
 
+        self.particles.update(frame_dt, now);
+
         // Read input state and update mouse world position
         let (mouse_pos, space, w, q) = {
             let input = self.input_state.borrow();
@@ -809,7 +1650,8 @@ impl GameClient {
             screen_center
         );
 
-        // Interpolate all cells (JS behavior): dt = clamp((now - updated) / 120, 0, 1)
+        // Interpolate all cells (JS behavior): dt = clamp((now - updated) / window, 0, 1)
+        let window_ms = self.interpolation_window();
         // First pass: collect killer positions for destroyed cells
         let killer_positions: std::collections::HashMap<u32, Vec2> = self.cells.iter()
             .filter_map(|(id, cell)| {
@@ -858,7 +1700,7 @@ impl GameClient {
                 }
             }
             
-            let dt = (((now - cell.update_time) / INTERPOLATION_DURATION_MS).max(0.0).min(1.0)) as f32;
+            let dt = (((now - cell.update_time) / window_ms).max(0.0).min(1.0)) as f32;
             cell.position.x = cell.ox + (cell.target_position.x - cell.ox) * dt;
             cell.position.y = cell.oy + (cell.target_position.y - cell.oy) * dt;
             cell.size        = cell.os + (cell.target_size        - cell.os) * dt;
@@ -871,19 +1713,25 @@ impl GameClient {
         // Update camera to follow player cells (uses interpolated positions/sizes)
         let has_cells = !self.my_cells.is_empty();
         if has_cells {
-            let positions: Vec<Vec2> = self.my_cells.iter()
-                .filter_map(|&id| self.cells.get(&id).map(|c| c.render_position))
+            let positions: Vec<Vec2> = self.my_cells.ids()
+                .filter_map(|id| self.cells.get(&id).map(|c| c.render_position))
                 .collect();
-            let sizes: Vec<f32> = self.my_cells.iter()
-                .filter_map(|&id| self.cells.get(&id).map(|c| c.render_size))
+            let sizes: Vec<f32> = self.my_cells.ids()
+                .filter_map(|id| self.cells.get(&id).map(|c| c.render_size))
                 .collect();
 
             if !positions.is_empty() {
-                self.camera.follow_cells(&positions, &sizes);
+                self.camera.follow_cells(&positions, &sizes, self.settings.mass_weighted_camera);
             }
         }
 
-        self.camera.update(has_cells);
+        if self.free_roam {
+            let pan = self.input_state.borrow().pan_direction().normalize_or_zero();
+            self.camera.target_position += pan * FREE_ROAM_PAN_SPEED * frame_dt / self.camera.zoom;
+        }
+        self.camera.clamp_to_border(self.border);
+
+        self.camera.update(has_cells, frame_dt, self.settings.camera_smoothing);
 
         // Jelly physics with LOD (skips small cells)
         if self.settings.jelly_physics {
@@ -906,12 +1754,17 @@ impl GameClient {
         }
 
         // Render
-        self.render()?;
+        let cells_drawn = self.render()?;
+        self.perf.end_frame(frame_ms, cells_drawn);
+        if self.show_debug_hud {
+            self.renderer.draw_perf_hud(&self.perf, self.settings.show_minimap && self.has_spawned);
+        }
 
         Ok(())
     }
 
-    fn render(&self) -> Result<(), JsValue> {
+    /// Draw the frame; returns how many cells survived viewport culling.
+    fn render(&self) -> Result<usize, JsValue> {
         let background = if self.settings.dark_theme { "#111" } else { "#f2f2f2" };
         self.renderer.clear(background);
         if self.settings.show_grid {
@@ -925,6 +1778,7 @@ impl GameClient {
                 self.settings.dark_theme,
             );
         }
+        self.renderer.draw_map_shapes(&self.map_shapes, self.camera.position, self.camera.zoom);
         self.renderer.draw_border(self.border, self.camera.position, self.camera.zoom);
 
         // Calculate viewport bounds for culling
@@ -936,10 +1790,19 @@ impl GameClient {
         let view_min_y = self.camera.position.y - half_view_h - 100.0;
         let view_max_y = self.camera.position.y + half_view_h + 100.0;
 
+        let show_food = match self.settings.food_visibility {
+            FoodVisibility::Always => true,
+            FoodVisibility::HideZoomedOut => self.camera.zoom >= FOOD_HIDE_ZOOM,
+            FoodVisibility::Never => false,
+        };
+
         // Sort cells by size (draw larger cells first, smaller on top)
         // Only include cells that are potentially visible
         let mut cells_to_draw: Vec<&Cell> = self.cells.values()
             .filter(|cell| {
+                if cell.is_food && !show_food {
+                    return false;
+                }
                 let pos = cell.render_position;
                 let size = cell.render_size;
                 // Quick AABB check - cell is visible if it overlaps viewport
@@ -955,7 +1818,27 @@ impl GameClient {
             }
         });
 
+        // Plain, fully opaque food is drawn in per-color batches; fading,
+        // bonus and jelly food still goes through draw_cell.
+        let (batched_food, cells_to_draw): (Vec<&Cell>, Vec<&Cell>) = cells_to_draw
+            .into_iter()
+            .partition(|cell| {
+                cell.is_food && !cell.is_bonus && cell.points.is_empty() && cell.get_render_alpha() >= 1.0
+            });
+        self.renderer.draw_food_batch(&batched_food, self.camera.position, self.camera.zoom);
+        let cells_drawn = batched_food.len() + cells_to_draw.len();
+
+        let my_max_size = self.my_cells.ids()
+            .filter_map(|id| self.cells.get(&id))
+            .map(|cell| cell.render_size)
+            .reduce(f32::max);
+
         for cell in cells_to_draw {
+            let relation = if self.settings.show_threat_indicators {
+                CellRelation::classify(cell, self.my_cells.contains(cell.id), my_max_size)
+            } else {
+                CellRelation::Neutral
+            };
             let skin_img = if self.settings.show_skins {
                 cell.skin.as_ref().and_then(|s| self.skins.get(s))
             } else {
@@ -972,14 +1855,46 @@ impl GameClient {
                     self.settings.show_mass,
                     self.settings.jelly_physics,
                     alpha,
+                    relation,
                 );
             }
         }
 
+        let now = utils::now();
+        self.renderer.draw_particles(&self.particles, self.camera.position, self.camera.zoom, now);
+
+        // Line from my cells (or the view center) to the last DrawLine point
+        if let Some((to, at)) = self.draw_line {
+            let age = now - at;
+            if age < DRAW_LINE_MS {
+                let mine: Vec<Vec2> = self.my_cells.ids()
+                    .filter_map(|id| self.cells.get(&id))
+                    .map(|cell| cell.render_position)
+                    .collect();
+                let from = if mine.is_empty() {
+                    self.camera.position
+                } else {
+                    mine.iter().sum::<Vec2>() / mine.len() as f32
+                };
+                let alpha = 1.0 - age / DRAW_LINE_MS;
+                self.renderer.draw_line(from, to, self.camera.position, self.camera.zoom, alpha, self.settings.dark_theme);
+            }
+        }
+
+        // Remerge countdown arcs around my own split cells
+        for id in self.my_cells.ids() {
+            if let Some(cell) = self.cells.get(&id) {
+                let remaining = cell.merge_remaining(now);
+                if remaining > 0.0 && !cell.is_destroyed {
+                    self.renderer.draw_merge_arc(cell, self.camera.position, self.camera.zoom, remaining);
+                }
+            }
+        }
+
         // Minimap — visible once the player has spawned at least once
-        if self.settings.show_minimap && !self.last_nick.is_empty() {
-            let my_cell_data: Vec<(Vec2, f32, (u8, u8, u8))> = self.my_cells.iter()
-                .filter_map(|&id| self.cells.get(&id).map(|c| (c.render_position, c.render_size, c.color)))
+        if self.settings.show_minimap && self.has_spawned {
+            let my_cell_data: Vec<(Vec2, f32, (u8, u8, u8))> = self.my_cells.ids()
+                .filter_map(|id| self.cells.get(&id).map(|c| (c.render_position, c.render_size, c.color)))
                 .collect();
             let xray_recent = (utils::now() - self.xray_last_update) <= 5000.0;
             let xray_points: Vec<(u32, Vec2, f32, (u8, u8, u8), String)> = if xray_recent {
@@ -1002,12 +1917,12 @@ impl GameClient {
             );
         }
 
-        Ok(())
+        Ok(cells_drawn)
     }
 
     fn calculate_score(&self) -> f32 {
-        self.my_cells.iter()
-            .filter_map(|id| self.cells.get(id))
+        self.my_cells.ids()
+            .filter_map(|id| self.cells.get(&id))
             .map(|c| (c.target_size * c.target_size / 100.0).floor())
             .sum()
     }
@@ -1092,84 +2007,103 @@ impl GameClient {
 
     // Handle incoming packet
     pub fn handle_packet(&mut self, data: Vec<u8>) {
+        self.handle_packet_bytes(&data);
+    }
+
+    /// Handle one packet, logging what can't be parsed.
+    fn handle_packet_bytes(&mut self, data: &[u8]) {
         if data.is_empty() {
             return;
         }
 
-        let mut reader = BinaryReader::new(data);
-        if let Err(e) = self.try_handle_packet(&mut reader) {
-            web_sys::console::error_1(&format!("Packet parsing error: {:?}", e).into());
-        }
-    }
-
-    fn try_handle_packet(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
-        let opcode = match reader.try_get_u8() {
-            Some(op) => op,
-            None => return Err("Empty packet".to_string()),
-        };
-
-        match opcode {
-            0x10 => self.handle_update_nodes(reader),   // World update
-            0x11 => self.handle_update_position(reader), // Spectator position
-            0x12 => self.handle_clear_all(reader),       // Clear all cells
-            0x14 => self.handle_clear_owned(reader),     // Clear my cells
-            0x15 => self.handle_draw_line(reader),       // Draw line (experimental)
-            0x20 => self.handle_add_node(reader),        // Add my cell
-            0x31 => self.handle_leaderboard_ffa(reader), // FFA leaderboard
-            0x32 => self.handle_leaderboard_teams(reader), // Teams leaderboard
-            0x40 => self.handle_set_border(reader),      // Set border
-            0x50 => self.handle_xray_data(reader),       // Xray data
-            0x63 => self.handle_chat(reader),            // Chat message
-            0xFE => self.handle_server_stat(reader),     // Server stats
-            _ => {
+        match self.try_handle_packet(data) {
+            Ok(()) => {}
+            Err(ProtocolError::InvalidOpcode(opcode)) => {
                 web_sys::console::warn_1(&format!("Unknown opcode: 0x{:02X}", opcode).into());
-                Ok(())
+            }
+            Err(e) => {
+                web_sys::console::error_1(&format!("Packet parsing error: {:?}", e).into());
             }
         }
     }
 
-    fn handle_clear_all(&mut self, _reader: &mut BinaryReader) -> Result<(), String> {
-        let had_cells = !self.my_cells.is_empty();
-        self.cells.clear();
-        self.my_cells.clear();
-        self.alive = false;
-        if had_cells {
-            self.death_time = Some(utils::now());
+    fn try_handle_packet(&mut self, data: &[u8]) -> Result<(), ProtocolError> {
+        let protocol = self.connection.borrow().protocol_version() as u32;
+        match ServerPacket::parse(data, protocol)? {
+            ServerPacket::UpdateNodes(payload) => self.handle_update_nodes(payload),
+            ServerPacket::UpdatePosition { x, y, scale } => self.handle_update_position(x, y, scale),
+            ServerPacket::ClearAll => self.handle_clear_all(),
+            ServerPacket::ClearOwned => self.handle_clear_owned(),
+            ServerPacket::DrawLine { x, y } => self.handle_draw_line(x, y),
+            ServerPacket::AddNode { node_id } => self.handle_add_node(node_id),
+            ServerPacket::LeaderboardText { lines } => self.handle_leaderboard_text(&lines),
+            ServerPacket::LeaderboardFfa(payload) => self.handle_leaderboard_ffa(payload),
+            ServerPacket::LeaderboardPie { team_sizes } => self.handle_leaderboard_pie(team_sizes),
+            ServerPacket::SetBorder(border) => self.handle_set_border(&border),
+            ServerPacket::XrayData(payload) => self.handle_xray_data(payload),
+            ServerPacket::MergeTimer { timers } => self.handle_merge_timer(&timers),
+            ServerPacket::Roster { entries } => self.handle_roster(entries),
+            ServerPacket::DeathInfo { cause, killer_name, killer_mass, killer_id } => {
+                self.handle_death_info(cause, &killer_name, killer_mass, killer_id)
+            }
+            ServerPacket::MapObjects { shapes } => self.map_shapes = shapes,
+            ServerPacket::ChatMessage(chat) => self.handle_chat(&chat),
+            ServerPacket::ServerStat { json } => self.handle_server_stat(&json),
+            ServerPacket::Batch { packets } => {
+                for packet in &packets {
+                    self.handle_packet_bytes(packet);
+                }
+            }
         }
         Ok(())
     }
 
-    fn handle_clear_owned(&mut self, _reader: &mut BinaryReader) -> Result<(), String> {
-        let had_cells = !self.my_cells.is_empty();
-        self.my_cells.clear();
-        self.alive = false;
-        if had_cells {
-            self.death_time = Some(utils::now());
+    fn handle_clear_all(&mut self) {
+        // A cell that just respawned is kept; the next world update resends it
+        self.cells.clear();
+        // Sent on every handshake; a map with objects follows up with them
+        self.map_shapes.clear();
+        self.draw_line = None;
+        self.particles.clear();
+        self.pending_merge_timers.clear();
+        self.handle_clear_owned();
+    }
+
+    /// Drop my cells, unless the clear was overtaken by a respawn's AddNode.
+    fn handle_clear_owned(&mut self) {
+        let dropped = self.my_cells.clear(utils::now());
+        if self.my_cells.is_empty() {
+            self.alive = false;
+            if dropped {
+                self.death_time = Some(utils::now());
+            }
         }
-        Ok(())
     }
 
-    fn handle_add_node(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
+    fn handle_add_node(&mut self, node_id: u32) {
         // Node ID is already XOR'd with scramble_id on the wire — use as-is.
         // All packets use the same scramble_id, so IDs match consistently.
-        let node_id = reader.try_get_u32().ok_or("truncated add_node packet")?;
-        if !self.my_cells.contains(&node_id) {
-            self.my_cells.push(node_id);
+        if !self.alive {
+            self.life = LifeStats { started_at: utils::now(), ..LifeStats::default() };
         }
+        self.my_cells.add(node_id, utils::now());
         self.alive = true;
         self.death_time = None;
-        Ok(())
+        self.death_info = None;
+        self.killer_id = None;
     }
 
-    fn handle_set_border(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
+    fn handle_set_border(&mut self, border: &SetBorderPayload) {
         // Border coordinates already include scramble (server adds scramble_x/y).
         // Store as-is — all cell coords are in the same scrambled space.
-        let min_x = reader.try_get_f64().ok_or("truncated border packet")? as f32;
-        let min_y = reader.try_get_f64().ok_or("truncated border packet")? as f32;
-        let max_x = reader.try_get_f64().ok_or("truncated border packet")? as f32;
-        let max_y = reader.try_get_f64().ok_or("truncated border packet")? as f32;
+        let min_x = border.min_x as f32;
+        let min_y = border.min_y as f32;
+        let max_x = border.max_x as f32;
+        let max_y = border.max_y as f32;
 
         self.border = (min_x, min_y, max_x, max_y);
+        self.unlimited_zoom_allowed = border.unlimited_zoom.unwrap_or(false);
+        self.apply_unlimited_zoom();
 
         // Center camera on map when border is first received (for spectator view)
         if !self.alive && self.my_cells.is_empty() {
@@ -1178,100 +2112,120 @@ impl GameClient {
             self.camera.position = Vec2::new(center_x, center_y);
             self.camera.target_position = Vec2::new(center_x, center_y);
         }
+    }
 
-        // Optional trailing: game_type (u32) + server_name (utf8 string)
-        if reader.remaining() >= 4 {
-            let _game_type = reader.try_get_u32();
-            let _server_name = reader.get_string_utf8();
+    /// Apply 0x51 MergeTimer countdowns.
+    fn handle_merge_timer(&mut self, timers: &[(u32, u32)]) {
+        let now = utils::now();
+        for &(node_id, remaining_ms) in timers {
+            let remaining = remaining_ms as f64;
+            let timer = (now + remaining, remaining);
+            match self.cells.get_mut(&node_id) {
+                Some(cell) => cell.merge_timer = Some(timer),
+                // The new half of a split shows up in the next world update
+                None => {
+                    self.pending_merge_timers.insert(node_id, timer);
+                }
+            }
         }
-
-        Ok(())
     }
 
-    fn handle_xray_data(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
-        let player_count = reader.try_get_u16().ok_or("truncated xray count")?;
-        let mut players = Vec::with_capacity(player_count as usize);
-
-        for _ in 0..player_count {
-            let id = reader.try_get_u32().ok_or("truncated xray id")?;
-            let x_raw = reader.try_get_u32().ok_or("truncated xray x")?;
-            let y_raw = reader.try_get_u32().ok_or("truncated xray y")?;
+    /// Replace the scoreboard roster from a 0x52 Roster packet.
+    fn handle_roster(&mut self, entries: Vec<packets::RosterEntry>) {
+        self.roster = entries.into_iter()
+            .take(ROSTER_MAX_ENTRIES)
+            .map(|e| RosterEntry { name: e.name, mass: e.mass, color: (e.color.r, e.color.g, e.color.b), is_bot: e.is_bot })
+            .collect();
+        self.scoreboard_stale = true;
+    }
 
-            let x = if x_raw > i32::MAX as u32 {
-                (x_raw as i64 - 4294967296) as i32
-            } else {
-                x_raw as i32
-            } as f32;
-            let y = if y_raw > i32::MAX as u32 {
-                (y_raw as i64 - 4294967296) as i32
-            } else {
-                y_raw as i32
-            } as f32;
-
-            let size = reader.try_get_u16().ok_or("truncated xray size")? as f32;
-            let r = reader.try_get_u8().ok_or("truncated xray color r")?;
-            let g = reader.try_get_u8().ok_or("truncated xray color g")?;
-            let b = reader.try_get_u8().ok_or("truncated xray color b")?;
-            let name = reader.get_string_utf8();
-
-            players.push(XrayPlayer {
-                id,
-                position: Vec2::new(x, y),
-                size,
-                color: (r, g, b),
-                name,
-            });
-        }
+    /// Keep a 0x15 DrawLine's point, unwrapped near the camera, to draw for
+    /// [`DRAW_LINE_MS`].
+    fn handle_draw_line(&mut self, x: i16, y: i16) {
+        let point = Vec2::new(
+            packets::unwrap_draw_line(x, self.camera.position.x),
+            packets::unwrap_draw_line(y, self.camera.position.y),
+        );
+        self.draw_line = Some((point, utils::now()));
+    }
 
+    /// Keep a 0x53 DeathInfo for the death overlay.
+    fn handle_death_info(&mut self, cause: DeathCause, killer_name: &str, killer_mass: u32, killer_id: u32) {
+        let text = match cause {
+            DeathCause::Eaten if killer_name.is_empty() => format!("Eaten by an unnamed cell ({} mass)", killer_mass),
+            DeathCause::Eaten => format!("Eaten by {} ({} mass)", killer_name, killer_mass),
+            DeathCause::MotherCell => format!("Eaten by a mother cell ({} mass)", killer_mass),
+            DeathCause::Killed => "Killed by an operator".to_string(),
+        };
+        self.death_info = Some(text);
+        self.killer_id = Some(killer_id).filter(|&id| id != 0);
+    }
+
+    fn handle_xray_data(&mut self, payload: XrayPayload) {
+        let players = payload.cells.into_iter()
+            .map(|cell| XrayPlayer {
+                id: cell.node_id,
+                position: Vec2::new(cell.x as f32, cell.y as f32),
+                size: cell.size as f32,
+                color: (cell.color.r, cell.color.g, cell.color.b),
+                name: cell.name,
+            })
+            .collect();
         self.xray_players = players;
         self.xray_last_update = utils::now();
-        Ok(())
     }
 
-    /// Parse 0x10 UpdateNodes packet.
+    /// Apply a 0x10 UpdateNodes packet.
     ///
     /// Wire format (protocol >= 11, matching write_update_nodes_v11 in server):
     ///   u16  eat_count
     ///   [u32 eater_id, u32 eaten_id] × eat_count
     ///   loop (updates then adds, no distinction on wire):
     ///     u32  node_id          — 0 terminates the loop
-    ///     i32  x
+    ///     i32  x               — quarter units on COGAR_PROTOCOL, parsed to f32
     ///     i32  y
     ///     u16  size
     ///     u8   flags
-    ///     u8   extended        — only if flags & 0x80 (is_food)
+    ///     u8   extended        — only if flags & 0x80
     ///     [u8 r, u8 g, u8 b]  — only if flags & 0x02 (is_player / has color)
     ///     string_utf8 skin     — only if flags & 0x04 (has_skin); protocol 11+ prefixes with '%'
     ///     string_utf8 name     — only if flags & 0x08 (has_name)
     ///   u16  remove_count
     ///   [u32 node_id] × remove_count
     ///
-    /// Flag bits (CellFlags::encode_v6 / v11):
+    /// Flag bits (CellFlags::encode_v6 / v11, read back by CellFlags::decode):
     ///   0x01 is_spiked  (virus)
     ///   0x02 is_player  (color present)
     ///   0x04 has_skin
     ///   0x08 has_name
-    ///   0x10 is_agitated
+    ///   0x10 is_agitated (on food: golden bonus pellet; otherwise boosted)
     ///   0x20 is_ejected
-    ///   0x80 is_food
-    fn handle_update_nodes(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
+    ///   0x80 is_food below protocol 11; from 11 on, extended byte follows
+    ///        (0x01 food, 0x02 friend)
+    fn handle_update_nodes(&mut self, payload: UpdateNodesPayload) {
+        self.track_packet_interval(utils::now());
+        let window_ms = self.interpolation_window();
+
         // --- Eat events ---
-        let eat_count = reader.try_get_u16().ok_or("truncated eat_count")?;
-        if eat_count > 0 {
+        if !payload.eats.is_empty() {
             self.saw_eat_record = true;
         }
-        for _ in 0..eat_count {
-            let eater_id = reader.try_get_u32().ok_or("truncated eat eater_id")?;
-            let eaten_id  = reader.try_get_u32().ok_or("truncated eat eaten_id")?;
+        for &EatRecord { eater_id, eaten_id } in &payload.eats {
+            if self.my_cells.contains(eater_id) && !self.my_cells.contains(eaten_id) {
+                self.life.cells_eaten += 1;
+            }
 
             // Mark the eaten cell as destroyed for animation, don't remove immediately
             let eater_pos = self.cells.get(&eater_id).map(|c| c.position);
             if let Some(cell) = self.cells.get_mut(&eaten_id) {
                 cell.destroy(Some(eater_id));
+                if self.settings.show_particles && cell.bursts_on_death() {
+                    self.particles.burst(cell.render_position, cell.render_size, cell.color, utils::now());
+                }
                 if let Some(pos) = eater_pos {
                     // Seed target position so short-lived food/ejected anims are visible
                     let now = utils::now();
-                    let dt = (((now - cell.update_time) / 120.0).max(0.0).min(1.0)) as f32;
+                    let dt = (((now - cell.update_time) / window_ms).max(0.0).min(1.0)) as f32;
                     cell.position.x = cell.ox + (cell.target_position.x - cell.ox) * dt;
                     cell.position.y = cell.oy + (cell.target_position.y - cell.oy) * dt;
                     cell.size        = cell.os + (cell.target_size        - cell.os) * dt;
@@ -1284,7 +2238,7 @@ impl GameClient {
             }
             
             // Remove from my_cells list immediately if it's mine
-            self.my_cells.retain(|&id| id != eaten_id);
+            self.my_cells.remove(eaten_id);
         }
         
         // Check if player died (all cells eaten)
@@ -1293,59 +2247,42 @@ impl GameClient {
             self.death_time = Some(utils::now());
         }
 
-        // --- Node updates + adds (terminated by node_id == 0) ---
-        loop {
-            let node_id = reader.try_get_u32().ok_or("truncated node_id")?;
-            if node_id == 0 {
-                break;
-            }
-
-            let x    = reader.try_get_i32().ok_or("truncated x")?    as f32;
-            let y    = reader.try_get_i32().ok_or("truncated y")?    as f32;
-            let size = reader.try_get_u16().ok_or("truncated size")? as f32;
-            let flags = reader.try_get_u8().ok_or("truncated flags")?;
+        // --- Node updates + adds ---
+        let protocol = self.connection.borrow().protocol_version() as u32;
+        for node in payload.nodes {
+            let node_id = node.node_id;
+            let x    = node.x;
+            let y    = node.y;
+            let size = node.size as f32;
+            let flags = CellFlags::decode(node.flags, node.ext_flags, protocol);
 
             // Color — present when is_player flag is set (server always sets this)
-            let (r, g, b) = if flags & 0x02 != 0 {
-                let r = reader.try_get_u8().ok_or("truncated color r")?;
-                let g = reader.try_get_u8().ok_or("truncated color g")?;
-                let b = reader.try_get_u8().ok_or("truncated color b")?;
-                (r, g, b)
-            } else {
-                (200, 200, 200)
-            };
+            let (r, g, b) = node.color.map_or((200, 200, 200), |c| (c.r, c.g, c.b));
 
             // Skin — only on initial add (has_skin flag set)
-            let skin = if flags & 0x04 != 0 {
-                let s = reader.get_string_utf8();
-                Self::normalize_skin_name(&s)
-            } else {
-                None
-            };
+            let skin = node.skin.as_deref().and_then(Self::normalize_skin_name);
 
             // Kick off image fetch for any new skin we haven't seen yet
             if let Some(ref skin_name) = skin {
-                self.ensure_skin_loaded(skin_name);
+                self.skins.ensure_loaded(skin_name);
             }
 
             // Name — only on initial add (has_name flag set)
-            let name = if flags & 0x08 != 0 {
-                reader.get_string_utf8()
-            } else {
-                String::new()
-            };
+            let name = node.name.unwrap_or_default();
 
-            let is_virus   = (flags & 0x01) != 0;
-            let is_ejected = (flags & 0x20) != 0;
-            let is_food    = (flags & 0x80) != 0;
+            let is_virus   = flags.is_spiked;
+            let is_ejected = flags.is_ejected;
+            let is_food    = flags.is_food;
+            let is_bonus   = is_food && flags.is_agitated;
+            let is_agitated = !is_food && flags.is_agitated;
 
             // Coordinates are already in scrambled space (server added scramble_x/y).
             // Store directly — border is in the same space, camera operates here too.
-            let is_mine = self.my_cells.contains(&node_id);
+            let is_mine = self.my_cells.contains(node_id);
             if let Some(cell) = self.cells.get_mut(&node_id) {
                 // Snap interpolation to current time before resetting lerp (matches JS cell.update() call)
                 let now = utils::now();
-                let dt = (((now - cell.update_time) / 120.0).max(0.0).min(1.0)) as f32;
+                let dt = (((now - cell.update_time) / window_ms).max(0.0).min(1.0)) as f32;
                 cell.position.x = cell.ox + (cell.target_position.x - cell.ox) * dt;
                 cell.position.y = cell.oy + (cell.target_position.y - cell.oy) * dt;
                 cell.size        = cell.os + (cell.target_size        - cell.os) * dt;
@@ -1359,11 +2296,14 @@ impl GameClient {
                 cell.update_time     = now;
 
                 cell.color = (r, g, b);
-                if !name.is_empty()  { cell.name = name; }
-                if skin.is_some()    { cell.skin = skin; }
+                // A resent name/skin replaces the old one, even when empty
+                if flags.has_name { cell.name = name; }
+                if flags.has_skin { cell.skin = skin; }
                 cell.is_virus   = is_virus;
                 cell.is_ejected = is_ejected;
                 cell.is_food    = is_food;
+                cell.is_bonus   = is_bonus;
+                cell.is_agitated = is_agitated;
             } else {
                 let mut cell = Cell::new(node_id, x, y, size, (r, g, b));
                 cell.name        = name;
@@ -1371,15 +2311,15 @@ impl GameClient {
                 cell.is_virus    = is_virus;
                 cell.is_ejected  = is_ejected;
                 cell.is_food     = is_food;
+                cell.is_bonus    = is_bonus;
+                cell.is_agitated = is_agitated;
+                cell.merge_timer = self.pending_merge_timers.remove(&node_id);
                 self.cells.insert(node_id, cell);
             }
         }
 
         // --- Removed nodes ---
-        let remove_count = reader.try_get_u16().ok_or("truncated remove_count")?;
-        for _ in 0..remove_count {
-            let node_id = reader.try_get_u32().ok_or("truncated remove node_id")?;
-            
+        for node_id in payload.removed {
             // Find nearest viable target before getting mutable reference
             let nearest_id = if self.saw_eat_record {
                 None
@@ -1413,11 +2353,14 @@ impl GameClient {
                     }
                 } else {
                     cell.destroy(nearest_id);
+                    if nearest_id.is_some() && self.settings.show_particles && cell.bursts_on_death() {
+                        self.particles.burst(cell.render_position, cell.render_size, cell.color, utils::now());
+                    }
                 }
             }
             
             // Remove from my_cells list immediately if it's mine
-            self.my_cells.retain(|&id| id != node_id);
+            self.my_cells.remove(node_id);
         }
         
         // Check if player died (all cells removed)
@@ -1425,16 +2368,12 @@ impl GameClient {
             self.alive = false;
             self.death_time = Some(utils::now());
         }
-
-        Ok(())
     }
 
-    fn handle_update_position(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
-        // Spectator position update — use to drive camera when not alive
-        let x    = reader.try_get_f32().ok_or("truncated spectator x")?;
-        let y    = reader.try_get_f32().ok_or("truncated spectator y")?;
-        let zoom = reader.try_get_f32().ok_or("truncated spectator zoom")?;
-        if !self.alive {
+    fn handle_update_position(&mut self, x: f32, y: f32, zoom: f32) {
+        // Spectator position update — use to drive camera when not alive,
+        // unless the player is steering it in free roam
+        if !self.alive && !self.free_roam {
             if self.camera.position == Vec2::ZERO && self.camera.target_position == Vec2::ZERO {
                 self.camera.position = Vec2::new(x, y);
                 self.camera.zoom = zoom * self.camera.zoom_factor;
@@ -1442,42 +2381,56 @@ impl GameClient {
             self.camera.target_position = Vec2::new(x, y);
             self.camera.set_base_zoom(zoom);
         }
-        Ok(())
     }
 
-    fn handle_draw_line(&mut self, _reader: &mut BinaryReader) -> Result<(), String> {
-        // Experimental mode feature — not implemented
-        Ok(())
+    /// Show a 0x30 LeaderboardText packet, which replaces the ranked list.
+    fn handle_leaderboard_text(&mut self, lines: &[String]) {
+        self.leaderboard.clear();
+        self.clear_team_pie();
+        self.ui.update_leaderboard_text(lines);
     }
 
-    /// Parse 0x31 LeaderboardFFA.
-    /// Format: u32 count, then [u32 is_me, string_utf8 name] × count
-    fn handle_leaderboard_ffa(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
-        let count = reader.try_get_u32().ok_or("truncated leaderboard count")?;
+    /// Show a 0x31 LeaderboardFFA packet.
+    fn handle_leaderboard_ffa(&mut self, payload: LeaderboardFfaPayload) {
+        self.clear_team_pie();
+        self.leaderboard = payload.entries.into_iter()
+            .map(|e| (e.is_me, e.player_id, e.name))
+            .collect();
+        self.ui.update_leaderboard(&self.leaderboard);
+        let entries = self.leaderboard.iter()
+            .map(|(is_me, id, name)| events::LeaderboardEntry { name: name.clone(), id: *id, is_me: *is_me })
+            .collect();
+        self.emit(ClientEvent::Leaderboard { entries });
+    }
+
+    /// Show a 0x32 LeaderboardPie packet: the pie chart replaces the list.
+    fn handle_leaderboard_pie(&mut self, team_sizes: Vec<f32>) {
         self.leaderboard.clear();
-        for _ in 0..count {
-            let is_me = reader.try_get_u32().unwrap_or(0) != 0;
-            let name  = reader.get_string_utf8();
-            self.leaderboard.push((is_me, name));
-        }
         self.ui.update_leaderboard(&self.leaderboard);
-        Ok(())
+        self.team_sizes = team_sizes;
+        self.update_team_pie();
     }
 
-    fn handle_leaderboard_teams(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
-        // Pie-chart leaderboard: u32 count, then [f32 team_size] × count
-        let count = reader.try_get_u32().unwrap_or(0);
-        for _ in 0..count {
-            reader.try_get_f32(); // consume
+    /// Drop the pie chart once the server sends another kind of leaderboard.
+    fn clear_team_pie(&mut self) {
+        if !self.team_sizes.is_empty() {
+            self.team_sizes.clear();
+            self.update_team_pie();
+        }
+    }
+
+    /// Redraw the pie chart, or hide it when collapsed or not in Teams mode.
+    fn update_team_pie(&self) {
+        let visible = self.settings.show_team_pie && !self.team_sizes.is_empty();
+        self.ui.set_team_pie_visible(visible);
+        if visible {
+            self.team_pie.draw(&self.team_sizes, self.settings.dark_theme);
         }
-        Ok(())
     }
 
-    fn handle_server_stat(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
+    fn handle_server_stat(&mut self, json_str: &str) {
         // Parse server statistics JSON
-        let json_str = reader.get_string_utf8();
-        
-        match serde_json::from_str::<ServerStats>(&json_str) {
+        match serde_json::from_str::<ServerStats>(json_str) {
             Ok(stats) => {
                 // Calculate latency
                 let now = utils::now();
@@ -1491,21 +2444,17 @@ impl GameClient {
                 web_sys::console::warn_1(&format!("Failed to parse server stats: {:?}", e).into());
             }
         }
-        Ok(())
     }
 
-    /// Parse 0x63 ChatMessage.
-    /// Format: u8 flags, u8 r, u8 g, u8 b, string_utf8 name, string_utf8 message
-    fn handle_chat(&mut self, reader: &mut BinaryReader) -> Result<(), String> {
-        let _flags = reader.try_get_u8().ok_or("truncated chat flags")?;
-        let r = reader.try_get_u8().unwrap_or(255);
-        let g = reader.try_get_u8().unwrap_or(255);
-        let b = reader.try_get_u8().unwrap_or(255);
-        let name    = reader.get_string_utf8();
-        let message = reader.get_string_utf8();
-
-        self.ui.show_chat_message(&name, &message, (r, g, b));
-        Ok(())
+    /// Show a 0x63 ChatMessage.
+    fn handle_chat(&mut self, chat: &ChatMessagePayload) {
+        let Color { r, g, b } = chat.color;
+        self.ui.show_chat_message(&chat.name, &chat.message, (r, g, b));
+        self.emit(ClientEvent::Chat {
+            name: chat.name.clone(),
+            message: chat.message.clone(),
+            color: format!("#{:02x}{:02x}{:02x}", r, g, b),
+        });
     }
 
     fn update_jelly_physics(&mut self) {
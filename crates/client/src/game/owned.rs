@@ -0,0 +1,110 @@
+// Own-cell tracking that tolerates clear packets overtaking a respawn
+/// A clear (0x12 / 0x14) this soon after a cell's AddNode is taken to predate
+/// it: the server sends ClearOwned before the AddNode, so a clear arriving
+/// later was sent for the previous life.
+const STALE_CLEAR_MS: f64 = 500.0;
+
+/// Ids of the cells this client owns. Each AddNode starts a new spawn
+/// generation, stamped with its arrival time; clears only drop cells from
+/// generations older than [`STALE_CLEAR_MS`].
+pub struct OwnedCells {
+    /// (node id, when its AddNode arrived)
+    cells: Vec<(u32, f64)>,
+}
+
+impl OwnedCells {
+    pub fn new() -> Self {
+        Self { cells: Vec::new() }
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.cells.iter().map(|&(id, _)| id)
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.cells.iter().any(|&(owned, _)| owned == id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// AddNode: the cell is ours from `now`.
+    pub fn add(&mut self, id: u32, now: f64) {
+        if !self.contains(id) {
+            self.cells.push((id, now));
+        }
+    }
+
+    /// The cell was eaten or removed from view.
+    pub fn remove(&mut self, id: u32) {
+        self.cells.retain(|&(owned, _)| owned != id);
+    }
+
+    /// ClearOwned / ClearAll: drop every cell except those whose AddNode came
+    /// in less than [`STALE_CLEAR_MS`] ago. Returns whether any cells were
+    /// dropped.
+    pub fn clear(&mut self, now: f64) -> bool {
+        let before = self.cells.len();
+        self.cells.retain(|&(_, added)| now - added < STALE_CLEAR_MS);
+        self.cells.len() < before
+    }
+
+    /// Forget everything, e.g. on disconnect.
+    pub fn reset(&mut self) {
+        self.cells.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::packets::{self, ServerPacket};
+
+    /// Feed a raw packet through the parser, as the game client does.
+    fn apply(owned: &mut OwnedCells, data: &[u8], now: f64) {
+        match ServerPacket::parse(data, 6).unwrap() {
+            ServerPacket::AddNode { node_id } => owned.add(node_id, now),
+            ServerPacket::ClearAll | ServerPacket::ClearOwned => {
+                owned.clear(now);
+            }
+            other => panic!("unexpected packet {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stale_clear_keeps_respawned_cell() {
+        let add = |id| packets::build_add_node(id, 0).finish();
+        let clear_owned = packets::build_clear_owned().finish();
+        let clear_all = packets::build_clear_all().finish();
+
+        let mut owned = OwnedCells::new();
+        apply(&mut owned, &add(1), 0.0);
+        apply(&mut owned, &add(2), 100.0);
+
+        // Respawn in order: the clear drops the old life, then the new cell
+        apply(&mut owned, &clear_owned, 5000.0);
+        apply(&mut owned, &add(3), 5010.0);
+        assert_eq!(owned.ids().collect::<Vec<_>>(), vec![3]);
+
+        // Respawn with the clears overtaking the AddNode
+        owned.remove(3);
+        apply(&mut owned, &add(4), 9000.0);
+        apply(&mut owned, &clear_owned, 9020.0);
+        apply(&mut owned, &clear_all, 9040.0);
+        assert_eq!(owned.ids().collect::<Vec<_>>(), vec![4]);
+
+        // Stale cells of the previous life still go
+        owned.add(5, 2000.0);
+        assert!(owned.clear(9050.0));
+        assert_eq!(owned.ids().collect::<Vec<_>>(), vec![4]);
+
+        // A clear well after the spawn is real
+        apply(&mut owned, &clear_owned, 12000.0);
+        assert!(owned.is_empty());
+    }
+}
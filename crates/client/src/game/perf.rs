@@ -0,0 +1,98 @@
+// Rolling per-frame performance samples for the debug HUD
+/// Frames kept per series: three seconds at 60 fps.
+pub const PERF_SAMPLES: usize = 180;
+
+/// A fixed ring of the last [`PERF_SAMPLES`] values of one series.
+pub struct Samples {
+    values: [f32; PERF_SAMPLES],
+    /// Slot the next value goes into.
+    next: usize,
+    len: usize,
+}
+
+impl Samples {
+    fn new() -> Self {
+        Self { values: [0.0; PERF_SAMPLES], next: 0, len: 0 }
+    }
+
+    fn push(&mut self, value: f32) {
+        self.values[self.next] = value;
+        self.next = (self.next + 1) % PERF_SAMPLES;
+        self.len = (self.len + 1).min(PERF_SAMPLES);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Values oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        let start = (self.next + PERF_SAMPLES - self.len) % PERF_SAMPLES;
+        (0..self.len).map(move |i| self.values[(start + i) % PERF_SAMPLES])
+    }
+
+    /// Smallest and largest value kept; `None` while empty.
+    pub fn min_max(&self) -> Option<(f32, f32)> {
+        self.iter().fold(None, |acc, v| match acc {
+            None => Some((v, v)),
+            Some((min, max)) => Some((min.min(v), max.max(v))),
+        })
+    }
+}
+
+/// Frame time, bytes received and cells drawn, one sample per rendered frame.
+pub struct PerfHistory {
+    /// Milliseconds since the previous frame.
+    pub frame_ms: Samples,
+    /// Bytes of packets handled during the frame.
+    pub bytes: Samples,
+    /// Cells left to draw after viewport culling.
+    pub cells: Samples,
+    pending_bytes: usize,
+}
+
+impl PerfHistory {
+    pub fn new() -> Self {
+        Self { frame_ms: Samples::new(), bytes: Samples::new(), cells: Samples::new(), pending_bytes: 0 }
+    }
+
+    /// Count a received packet towards the current frame.
+    pub fn add_bytes(&mut self, len: usize) {
+        self.pending_bytes += len;
+    }
+
+    /// Close the current frame.
+    pub fn end_frame(&mut self, frame_ms: f64, cells: usize) {
+        self.frame_ms.push(frame_ms as f32);
+        self.bytes.push(std::mem::take(&mut self.pending_bytes) as f32);
+        self.cells.push(cells as f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_keep_the_newest_frames_in_order() {
+        let mut perf = PerfHistory::new();
+        assert_eq!(perf.bytes.min_max(), None);
+
+        perf.add_bytes(100);
+        perf.add_bytes(20);
+        perf.end_frame(16.0, 3);
+        perf.end_frame(17.0, 4);
+        assert_eq!(perf.bytes.iter().collect::<Vec<_>>(), vec![120.0, 0.0]);
+        assert_eq!(perf.frame_ms.min_max(), Some((16.0, 17.0)));
+
+        for i in 0..PERF_SAMPLES + 10 {
+            perf.end_frame(i as f64, i);
+        }
+        assert_eq!(perf.cells.len(), PERF_SAMPLES);
+        let cells: Vec<f32> = perf.cells.iter().collect();
+        assert_eq!(cells[0], 10.0);
+        assert_eq!(cells[PERF_SAMPLES - 1], (PERF_SAMPLES + 9) as f32);
+        assert!(cells.windows(2).all(|w| w[1] == w[0] + 1.0));
+        assert_eq!(perf.cells.min_max(), Some((10.0, (PERF_SAMPLES + 9) as f32)));
+    }
+}
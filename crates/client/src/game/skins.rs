@@ -0,0 +1,137 @@
+// Skin image cache: load tracking, failure memory and LRU eviction
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlImageElement;
+
+use crate::utils;
+
+/// Most skins kept at once; a server cycling names can't grow memory past this.
+const MAX_SKINS: usize = 256;
+
+/// Load state of a skin image, updated by the image's onload / onerror.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SkinStatus {
+    Loading,
+    Ready,
+    /// The fetch failed (404, bad image); never retried this session.
+    Failed,
+}
+
+struct SkinEntry {
+    img: HtmlImageElement,
+    status: Rc<Cell<SkinStatus>>,
+    /// Last time (ms) the skin was requested or drawn, for LRU eviction.
+    last_used: Cell<f64>,
+}
+
+/// Skin images by name, with load state so failed skins are not re-fetched
+/// or re-checked every frame.
+pub struct SkinCache {
+    entries: HashMap<String, SkinEntry>,
+    /// Hosts `https://` skins may be fetched from.
+    url_hosts: &'static [&'static str],
+}
+
+impl SkinCache {
+    pub fn new(url_hosts: &'static [&'static str]) -> Self {
+        Self {
+            entries: HashMap::new(),
+            url_hosts,
+        }
+    }
+
+    /// Start loading a skin the first time it is seen. Known names, including
+    /// failed ones, are only marked as recently used.
+    pub fn ensure_loaded(&mut self, name: &str) {
+        let now = utils::now();
+        if let Some(entry) = self.entries.get(name) {
+            entry.last_used.set(now);
+            return;
+        }
+        let Ok(img) = HtmlImageElement::new() else {
+            return;
+        };
+
+        let status = Rc::new(Cell::new(SkinStatus::Loading));
+        let on_load = status.clone();
+        img.set_onload(Some(
+            Closure::once_into_js(move || on_load.set(SkinStatus::Ready)).unchecked_ref(),
+        ));
+        let on_error = status.clone();
+        img.set_onerror(Some(
+            Closure::once_into_js(move || on_error.set(SkinStatus::Failed)).unchecked_ref(),
+        ));
+        if name.starts_with("https://") {
+            if self.host_allowed(name) {
+                // Anonymous CORS keeps the canvas untainted when drawing it
+                img.set_cross_origin(Some("anonymous"));
+                img.set_src(name);
+            } else {
+                // Never fetched; remembered as failed so it isn't re-checked
+                status.set(SkinStatus::Failed);
+            }
+        } else {
+            img.set_src(&format!("./skins/{}.png", name));
+        }
+
+        if self.entries.len() >= MAX_SKINS {
+            self.evict_oldest();
+        }
+        self.entries.insert(name.to_string(), SkinEntry {
+            img,
+            status,
+            last_used: Cell::new(now),
+        });
+    }
+
+    /// The skin image if it finished loading successfully.
+    pub fn get(&self, name: &str) -> Option<&HtmlImageElement> {
+        let entry = self.entries.get(name)?;
+        if entry.status.get() != SkinStatus::Ready {
+            return None;
+        }
+        entry.last_used.set(utils::now());
+        Some(&entry.img)
+    }
+
+    /// Whether a skin URL points at one of the allowed hosts (or a subdomain).
+    fn host_allowed(&self, url: &str) -> bool {
+        let Some(host) = url_host(url) else {
+            return false;
+        };
+        self.url_hosts.iter().any(|allowed| {
+            host == *allowed
+                || host.strip_suffix(allowed).is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self.entries.iter()
+            .min_by(|a, b| a.1.last_used.get().total_cmp(&b.1.last_used.get()))
+            .map(|(name, _)| name.clone());
+        if let Some(name) = oldest {
+            if let Some(entry) = self.entries.remove(&name) {
+                // Drop the pending fetch callbacks along with the image
+                entry.img.set_onload(None);
+                entry.img.set_onerror(None);
+            }
+        }
+    }
+}
+
+/// Lowercased host of an `https://` URL, without port. URLs carrying
+/// credentials (`user@host`) are rejected outright.
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("https://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    if authority.contains('@') {
+        return None;
+    }
+    let host = authority.split(':').next()?;
+    if host.is_empty() {
+        return None;
+    }
+    Some(host.to_ascii_lowercase())
+}
@@ -12,6 +12,18 @@ pub struct Input {
     pub p_pressed: bool,
     pub enter_pressed: bool,
     pub escape_pressed: bool,
+    pub share_position_pressed: bool,
+    pub debug_hud_pressed: bool,
+    pub help_pressed: bool,
+    /// Held state only (scoreboard overlay), no edge detection needed.
+    pub tab_pressed: bool,
+    /// Toggles the free-roam spectator camera.
+    pub f_pressed: bool,
+    /// Free-roam panning (WASD / arrow keys), held state only.
+    pub pan_up: bool,
+    pub pan_down: bool,
+    pub pan_left: bool,
+    pub pan_right: bool,
     // Previous frame states for edge detection
     pub prev_space_pressed: bool,
     pub prev_w_pressed: bool,
@@ -22,6 +34,23 @@ pub struct Input {
     pub prev_p_pressed: bool,
     pub prev_enter_pressed: bool,
     pub prev_escape_pressed: bool,
+    pub prev_share_position_pressed: bool,
+    pub prev_debug_hud_pressed: bool,
+    pub prev_help_pressed: bool,
+    pub prev_f_pressed: bool,
+    /// Key bound to sharing the current sector in chat.
+    pub share_position_key: char,
+    /// Key bound to toggling the debug HUD.
+    pub debug_hud_key: char,
+    /// Key bound to toggling the help overlay.
+    pub help_key: char,
+    /// Time of the last touch event; emulated mouse events right after a
+    /// touch are ignored so the two don't fight over `mouse_pos`.
+    pub last_touch_time: f64,
+    /// Distance between the two fingers of an ongoing pinch.
+    pub pinch_distance: Option<f32>,
+    /// Time of the last single-finger tap start (for double-tap split).
+    pub last_tap_time: f64,
 }
 
 impl Input {
@@ -37,6 +66,15 @@ impl Input {
             p_pressed: false,
             enter_pressed: false,
             escape_pressed: false,
+            share_position_pressed: false,
+            debug_hud_pressed: false,
+            help_pressed: false,
+            tab_pressed: false,
+            f_pressed: false,
+            pan_up: false,
+            pan_down: false,
+            pan_left: false,
+            pan_right: false,
             prev_space_pressed: false,
             prev_w_pressed: false,
             prev_q_pressed: false,
@@ -46,6 +84,16 @@ impl Input {
             prev_p_pressed: false,
             prev_enter_pressed: false,
             prev_escape_pressed: false,
+            prev_share_position_pressed: false,
+            prev_debug_hud_pressed: false,
+            prev_help_pressed: false,
+            prev_f_pressed: false,
+            share_position_key: 'l',
+            debug_hud_key: 'h',
+            help_key: '?',
+            last_touch_time: f64::NEG_INFINITY,
+            pinch_distance: None,
+            last_tap_time: f64::NEG_INFINITY,
         }
     }
     
@@ -60,6 +108,10 @@ impl Input {
         self.prev_p_pressed = self.p_pressed;
         self.prev_enter_pressed = self.enter_pressed;
         self.prev_escape_pressed = self.escape_pressed;
+        self.prev_share_position_pressed = self.share_position_pressed;
+        self.prev_debug_hud_pressed = self.debug_hud_pressed;
+        self.prev_help_pressed = self.help_pressed;
+        self.prev_f_pressed = self.f_pressed;
     }
     
     /// Check if key was just pressed (transition from not pressed to pressed)
@@ -98,6 +150,134 @@ impl Input {
     pub fn escape_just_pressed(&self) -> bool {
         self.escape_pressed && !self.prev_escape_pressed
     }
+
+    pub fn share_position_just_pressed(&self) -> bool {
+        self.share_position_pressed && !self.prev_share_position_pressed
+    }
+
+    pub fn debug_hud_just_pressed(&self) -> bool {
+        self.debug_hud_pressed && !self.prev_debug_hud_pressed
+    }
+
+    pub fn help_just_pressed(&self) -> bool {
+        self.help_pressed && !self.prev_help_pressed
+    }
+
+    pub fn f_just_pressed(&self) -> bool {
+        self.f_pressed && !self.prev_f_pressed
+    }
+
+    /// Held panning keys as a direction (not normalized; y grows downward).
+    pub fn pan_direction(&self) -> Vec2 {
+        let axis = |neg: bool, pos: bool| (pos as i8 - neg as i8) as f32;
+        Vec2::new(axis(self.pan_left, self.pan_right), axis(self.pan_up, self.pan_down))
+    }
+
+    /// Set the panning state for a `KeyboardEvent::key()` value; false if
+    /// the key doesn't pan.
+    pub fn set_pan_key(&mut self, key: &str, pressed: bool) -> bool {
+        let flag = match key {
+            "w" | "W" | "ArrowUp" => &mut self.pan_up,
+            "s" | "S" | "ArrowDown" => &mut self.pan_down,
+            "a" | "A" | "ArrowLeft" => &mut self.pan_left,
+            "d" | "D" | "ArrowRight" => &mut self.pan_right,
+            _ => return false,
+        };
+        *flag = pressed;
+        true
+    }
+
+    /// Whether a `KeyboardEvent::key()` value is the share-position key.
+    pub fn is_share_position_key(&self, key: &str) -> bool {
+        is_bound_key(key, self.share_position_key)
+    }
+
+    /// Whether a `KeyboardEvent::key()` value is the debug HUD key.
+    pub fn is_debug_hud_key(&self, key: &str) -> bool {
+        is_bound_key(key, self.debug_hud_key)
+    }
+
+    /// Whether a `KeyboardEvent::key()` value is the help key.
+    pub fn is_help_key(&self, key: &str) -> bool {
+        is_bound_key(key, self.help_key)
+    }
+
+    /// Every control, grouped for the help overlay, with the rebindable
+    /// keys as currently bound.
+    pub fn help_sections(&self) -> Vec<HelpSection> {
+        let bound = |c: char| c.to_uppercase().to_string();
+        let entry = |keys: &[&str], action| HelpEntry { keys: keys.iter().map(|k| k.to_string()).collect(), action };
+        vec![
+            HelpSection {
+                title: "Movement",
+                entries: vec![
+                    entry(&["Mouse"], "Steer your cells toward the cursor"),
+                    entry(&["Space"], "Split"),
+                    entry(&["W"], "Eject mass"),
+                ],
+            },
+            HelpSection {
+                title: "Minions",
+                entries: vec![
+                    entry(&["Q"], "Freeze your own cells"),
+                    entry(&["E"], "Make minions split"),
+                    entry(&["R"], "Make minions eject"),
+                    entry(&["T"], "Freeze minions"),
+                    entry(&["P"], "Toggle minion food collection"),
+                ],
+            },
+            HelpSection {
+                title: "Chat",
+                entries: vec![
+                    entry(&["Enter"], "Open chat"),
+                    HelpEntry { keys: vec![bound(self.share_position_key)], action: "Share your position in chat" },
+                    entry(&["/help"], "List chat commands"),
+                ],
+            },
+            HelpSection {
+                title: "View",
+                entries: vec![
+                    entry(&["Wheel", "Pinch"], "Zoom"),
+                    entry(&["Tab"], "Show all players while held"),
+                    HelpEntry { keys: vec![bound(self.debug_hud_key)], action: "Toggle the performance graphs" },
+                    HelpEntry { keys: vec![bound(self.help_key)], action: "Show or hide this help" },
+                ],
+            },
+            HelpSection {
+                title: "Spectating",
+                entries: vec![
+                    entry(&["Escape"], "Spectate"),
+                    entry(&["Click"], "Follow a player from the leaderboard"),
+                    entry(&["F"], "Toggle the free camera"),
+                    entry(&["WASD", "Arrows"], "Pan the free camera"),
+                ],
+            },
+        ]
+    }
+}
+
+/// A titled group of controls in the help overlay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelpSection {
+    pub title: &'static str,
+    pub entries: Vec<HelpEntry>,
+}
+
+/// One control: the keys (alternatives) that trigger it and what it does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelpEntry {
+    pub keys: Vec<String>,
+    pub action: &'static str,
+}
+
+/// Whether a `KeyboardEvent::key()` value is the single character `bound`,
+/// ignoring case.
+fn is_bound_key(key: &str, bound: char) -> bool {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.eq_ignore_ascii_case(&bound),
+        _ => false,
+    }
 }
 
 impl Default for Input {
@@ -105,3 +285,24 @@ impl Default for Input {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_lists_keys_as_bound() {
+        let mut input = Input::new();
+        input.share_position_key = 'k';
+        input.help_key = 'j';
+        let keys: Vec<String> = input.help_sections().into_iter()
+            .flat_map(|section| section.entries)
+            .flat_map(|entry| entry.keys)
+            .collect();
+        assert!(keys.contains(&"K".to_string()));
+        assert!(keys.contains(&"J".to_string()));
+        assert!(!keys.contains(&"L".to_string()));
+        assert!(keys.contains(&"H".to_string()));
+        assert!(input.is_help_key("J") && !input.is_help_key("?"));
+    }
+}
@@ -5,9 +5,10 @@ use wasm_bindgen::prelude::*;
 use std::rc::Rc;
 use std::rc::Weak;
 use std::cell::RefCell;
-use web_sys::{window, KeyboardEvent, MouseEvent, MessageEvent, HtmlCanvasElement, HtmlInputElement, HtmlButtonElement, Element, WheelEvent, WebSocket, CloseEvent};
+use web_sys::{window, KeyboardEvent, MouseEvent, TouchEvent, TouchList, MessageEvent, HtmlInputElement, HtmlSelectElement, HtmlButtonElement, Element, WheelEvent, WebSocket, CloseEvent};
 use js_sys::{ArrayBuffer, Uint8Array};
 use glam::Vec2;
+use protocol::CloseCode;
 
 // Module structure - each module handles a specific concern
 mod network;  // WebSocket connection, packet handling
@@ -20,6 +21,9 @@ mod utils;    // Helper functions, LERP, math utilities
 
 // Re-export the main entry point
 pub use game::GameClient;
+use camera::CameraSmoothing;
+use game::{EjectColorMode, FoodVisibility, FpsCap};
+use render::BorderColorMode;
 
 /// Initialize panic hook for better error messages in the browser console
 #[wasm_bindgen(start)]
@@ -35,12 +39,15 @@ pub struct GameClientWrapper {
 
 #[wasm_bindgen]
 impl GameClientWrapper {
-    /// Create a new game client
+    /// Create a new game client. Private servers (`server.connect_token`)
+    /// expect the token as a query parameter, e.g. `wss://host/game?token=abc`;
+    /// `server_url` is used as given, so include it there. Pass `cogar` as
+    /// true only for cogar servers; others are spoken to on protocol 6.
     #[wasm_bindgen(constructor)]
-    pub fn new(canvas_id: &str, server_url: &str) -> Result<GameClientWrapper, JsValue> {
+    pub fn new(canvas_id: &str, server_url: &str, cogar: Option<bool>) -> Result<GameClientWrapper, JsValue> {
         init();
 
-        let client = GameClient::new(canvas_id, server_url)?;
+        let client = GameClient::new(canvas_id, server_url, cogar.unwrap_or(false))?;
         let client_rc = Rc::new(RefCell::new(client));
 
         // Setup WebSocket message handler
@@ -52,9 +59,21 @@ impl GameClientWrapper {
         // Setup input handlers
         setup_input_handlers(client_rc.clone())?;
 
+        // Setup touch handlers (no-op on devices without touch)
+        setup_touch_handlers(client_rc.clone(), canvas_id)?;
+
         // Setup chat handlers
         setup_chat_handlers(client_rc.clone())?;
 
+        // Setup leaderboard click-to-spectate
+        setup_leaderboard_handlers(client_rc.clone())?;
+
+        // Setup death screen buttons
+        setup_death_overlay_handlers(client_rc.clone())?;
+
+        // Setup help overlay buttons
+        setup_help_handlers(client_rc.clone())?;
+
         // Setup zoom handlers
         setup_zoom_handlers(client_rc.clone())?;
 
@@ -62,7 +81,9 @@ impl GameClientWrapper {
         setup_settings_handlers(client_rc.clone())?;
 
         // Setup canvas resize handler
-        setup_resize_handler(canvas_id)?;
+        setup_resize_handler(client_rc.clone())?;
+
+        client_rc.borrow().show_help_on_first_visit();
 
         Ok(GameClientWrapper {
             client: client_rc,
@@ -94,6 +115,41 @@ impl GameClientWrapper {
     pub fn websocket(&self) -> web_sys::WebSocket {
         self.client.borrow().websocket()
     }
+
+    /// Leave the current server and connect to `url` instead, e.g. from a
+    /// server browser. Takes the same forms as the constructor's
+    /// `server_url` and `cogar`; dropped connections reconnect to the new URL.
+    pub fn connect(&self, url: &str, cogar: Option<bool>) -> Result<(), JsValue> {
+        let ws = self.client.borrow_mut().connect_to(url, cogar.unwrap_or(false))?;
+        attach_websocket_handlers(self.client.clone(), ws, new_reconnect_state())
+    }
+
+    /// Close the connection without reconnecting until `connect` is called.
+    pub fn disconnect(&self) {
+        self.client.borrow_mut().disconnect();
+    }
+
+    /// Call `callback` with a JSON string for each key event: connected,
+    /// disconnected, died, leaderboard and chat. See
+    /// `game::events::ClientEvent` for the payload shapes. Callbacks run
+    /// after the frame that produced the events, so they may call back into
+    /// this object. Pass `null` to stop.
+    pub fn on_event(&self, callback: Option<js_sys::Function>) {
+        self.client.borrow_mut().set_event_listener(callback);
+    }
+}
+
+/// Hand queued events to the host page's listener, with the client free
+/// to be called back into.
+fn dispatch_events(client: &RefCell<GameClient>) {
+    let Some((listener, events)) = client.borrow_mut().take_events() else {
+        return;
+    };
+    for event in events {
+        if let Err(e) = listener.call1(&JsValue::NULL, &JsValue::from_str(&event.to_json())) {
+            web_sys::console::error_1(&format!("Event listener failed: {:?}", e).into());
+        }
+    }
 }
 
 struct ReconnectState {
@@ -102,6 +158,22 @@ struct ReconnectState {
     scheduled: bool,
 }
 
+impl ReconnectState {
+    /// Whether a close with `code` is worth retrying. The server's permanent
+    /// codes (kicked, banned, refused handshake) would only be refused again.
+    fn retries(code: u16) -> bool {
+        CloseCode::from_u16(code).is_none_or(|code| !code.is_permanent())
+    }
+}
+
+fn new_reconnect_state() -> Rc<RefCell<ReconnectState>> {
+    Rc::new(RefCell::new(ReconnectState {
+        delay_ms: 1000,
+        max_delay_ms: 5000,
+        scheduled: false,
+    }))
+}
+
 fn attach_websocket_handlers(
     client: Rc<RefCell<GameClient>>,
     ws: WebSocket,
@@ -110,6 +182,10 @@ fn attach_websocket_handlers(
     // Get shared resources that don't require borrowing client
     let packet_queue = client.borrow().packet_queue();
     let ws_open_flag = client.borrow().ws_open_flag();
+    let reconnect_at = client.borrow().reconnect_at();
+    // The page may switch servers or disconnect while a reconnect waits
+    let connection_epoch = client.borrow().connection_epoch();
+    let epoch = connection_epoch.get();
 
     let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
         if let Ok(buffer) = event.data().dyn_into::<ArrayBuffer>() {
@@ -126,10 +202,12 @@ fn attach_websocket_handlers(
 
     // onopen - set flag and reset reconnect delay
     let onopen_state = reconnect_state.clone();
+    let onopen_reconnect_at = reconnect_at.clone();
     let onopen = Closure::wrap(Box::new(move |_event: JsValue| {
         web_sys::console::log_1(&"WebSocket connected".into());
         // Set flag for game loop to process
         ws_open_flag.set(true);
+        onopen_reconnect_at.set(None);
         // Reset reconnect state on successful connection
         if let Ok(mut state) = onopen_state.try_borrow_mut() {
             state.delay_ms = 1000;
@@ -149,9 +227,22 @@ fn attach_websocket_handlers(
     // onclose - schedule reconnect
     let client_weak: Weak<RefCell<GameClient>> = Rc::downgrade(&client);
     let ws_close_flag = client.borrow().ws_close_flag();
+    let rejection = client.borrow().rejection();
     let onclose_state = reconnect_state.clone();
     let onclose = Closure::wrap(Box::new(move |event: CloseEvent| {
-        web_sys::console::log_1(&format!("WebSocket closed: {}", event.code()).into());
+        let code = event.code();
+        web_sys::console::log_1(&format!("WebSocket closed: {} {}", code, event.reason()).into());
+
+        // The game loop reports the reason instead of counting down
+        if !ReconnectState::retries(code) {
+            let reason = match event.reason() {
+                reason if reason.is_empty() => CloseCode::from_u16(code).map(CloseCode::description).unwrap_or_default().to_string(),
+                reason => reason,
+            };
+            rejection.replace(Some(reason));
+            ws_close_flag.set(true);
+            return;
+        }
 
         // Set flag for game loop to process disconnect
         ws_close_flag.set(true);
@@ -166,11 +257,18 @@ fn attach_websocket_handlers(
             state.delay_ms = ((state.delay_ms as f64) * 1.5).min(state.max_delay_ms as f64) as i32;
             current
         };
+        // Published for the status banner's countdown
+        reconnect_at.set(Some(utils::now() + delay as f64));
 
         if let Some(window) = web_sys::window() {
             let attempt_client = client_weak.clone();
             let attempt_state = onclose_state.clone();
+            let attempt_reconnect_at = reconnect_at.clone();
+            let attempt_epoch = connection_epoch.clone();
             let callback = Closure::wrap(Box::new(move || {
+                if attempt_epoch.get() != epoch {
+                    return;
+                }
                 if let Some(client_rc) = attempt_client.upgrade() {
                     // Use try_borrow_mut to avoid panic if client is borrowed elsewhere
                     match client_rc.try_borrow_mut() {
@@ -194,6 +292,8 @@ fn attach_websocket_handlers(
                                     if let Ok(mut state) = attempt_state.try_borrow_mut() {
                                         state.scheduled = false;
                                     }
+                                    // Nothing else is scheduled; the banner reports the failure
+                                    attempt_reconnect_at.set(None);
                                 }
                             }
                         }
@@ -203,6 +303,7 @@ fn attach_websocket_handlers(
                             if let Ok(mut state) = attempt_state.try_borrow_mut() {
                                 state.scheduled = false;
                             }
+                            attempt_reconnect_at.set(None);
                         }
                     }
                 }
@@ -222,12 +323,7 @@ fn attach_websocket_handlers(
 
 fn setup_websocket_handler(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
     let ws = client.borrow().websocket().clone();
-    let reconnect_state = Rc::new(RefCell::new(ReconnectState {
-        delay_ms: 1000,
-        max_delay_ms: 5000,
-        scheduled: false,
-    }));
-    attach_websocket_handlers(client, ws, reconnect_state)
+    attach_websocket_handlers(client, ws, new_reconnect_state())
 }
 
 fn setup_animation_loop(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
@@ -240,9 +336,21 @@ fn setup_animation_loop(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
     let client_clone = client.clone();
     *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
         // Update and render - now safe to use borrow_mut since WebSocket only queues
-        if let Err(e) = client_clone.borrow_mut().update() {
-            web_sys::console::error_1(&format!("Update error: {:?}", e).into());
+        {
+            let mut client = client_clone.borrow_mut();
+            // Input and packets every frame; simulation and drawing only as
+            // often as the FPS cap (or a hidden tab) allows
+            client.process_events();
+            let hidden = web_sys::window()
+                .and_then(|w| w.document())
+                .is_some_and(|d| d.hidden());
+            if client.frame_due(utils::now(), hidden) {
+                if let Err(e) = client.update() {
+                    web_sys::console::error_1(&format!("Update error: {:?}", e).into());
+                }
+            }
         }
+        dispatch_events(&client_clone);
 
         // Request next frame
         if let Some(win) = web_sys::window() {
@@ -280,9 +388,14 @@ fn setup_input_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
     {
         let input_clone = input_state.clone();
         let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+            let mut input = input_clone.borrow_mut();
+            // Browsers emulate mouse events after touches; the touch handler owns mouse_pos then
+            if utils::now() - input.last_touch_time < TOUCH_MOUSE_SUPPRESS_MS {
+                return;
+            }
             let x = event.client_x() as f32;
             let y = event.client_y() as f32;
-            input_clone.borrow_mut().mouse_pos = Vec2::new(x, y);
+            input.mouse_pos = Vec2::new(x, y);
         }) as Box<dyn FnMut(_)>);
 
         document.add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())?;
@@ -298,6 +411,9 @@ fn setup_input_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
             }
             let key = event.key();
             let mut input = input_clone.borrow_mut();
+            if input.set_pan_key(&key, true) && key.starts_with("Arrow") {
+                event.prevent_default(); // Don't scroll the page
+            }
             match key.as_str() {
                 " " => { event.prevent_default(); input.space_pressed = true; }
                 "w" | "W" => input.w_pressed = true,
@@ -306,8 +422,14 @@ fn setup_input_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
                 "r" | "R" => input.r_pressed = true,
                 "t" | "T" => input.t_pressed = true,
                 "p" | "P" => input.p_pressed = true,
+                "f" | "F" => input.f_pressed = true,
                 "Enter" => input.enter_pressed = true,
                 "Escape" => input.escape_pressed = true,
+                // Alt+Tab never delivers the matching keyup
+                "Tab" if !event.alt_key() => { event.prevent_default(); input.tab_pressed = true; }
+                k if input.is_share_position_key(k) => input.share_position_pressed = true,
+                k if input.is_debug_hud_key(k) => input.debug_hud_pressed = true,
+                k if input.is_help_key(k) => input.help_pressed = true,
                 _ => {}
             }
         }) as Box<dyn FnMut(_)>);
@@ -325,6 +447,7 @@ fn setup_input_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
             }
             let key = event.key();
             let mut input = input_clone.borrow_mut();
+            input.set_pan_key(&key, false);
             match key.as_str() {
                 " " => input.space_pressed = false,
                 "w" | "W" => input.w_pressed = false,
@@ -333,8 +456,13 @@ fn setup_input_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
                 "r" | "R" => input.r_pressed = false,
                 "t" | "T" => input.t_pressed = false,
                 "p" | "P" => input.p_pressed = false,
+                "f" | "F" => input.f_pressed = false,
                 "Enter" => input.enter_pressed = false,
                 "Escape" => input.escape_pressed = false,
+                "Tab" => input.tab_pressed = false,
+                k if input.is_share_position_key(k) => input.share_position_pressed = false,
+                k if input.is_debug_hud_key(k) => input.debug_hud_pressed = false,
+                k if input.is_help_key(k) => input.help_pressed = false,
                 _ => {}
             }
         }) as Box<dyn FnMut(_)>);
@@ -346,6 +474,163 @@ fn setup_input_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue>
     Ok(())
 }
 
+/// Mouse events within this many ms of a touch are treated as emulated and ignored.
+const TOUCH_MOUSE_SUPPRESS_MS: f64 = 800.0;
+/// Two taps closer together than this split.
+const DOUBLE_TAP_MS: f64 = 300.0;
+
+/// Distance between the first two touches, if there are at least two.
+fn touch_distance(touches: &TouchList) -> Option<f32> {
+    let a = touches.get(0)?;
+    let b = touches.get(1)?;
+    let dx = (a.client_x() - b.client_x()) as f32;
+    let dy = (a.client_y() - b.client_y()) as f32;
+    Some((dx * dx + dy * dy).sqrt())
+}
+
+/// Touch controls: the primary finger steers, pinching zooms, double-tap
+/// splits, and on-screen buttons split / eject. Only installed on devices
+/// that support touch, so desktop input is untouched.
+fn setup_touch_handlers(client: Rc<RefCell<GameClient>>, canvas_id: &str) -> Result<(), JsValue> {
+    let window = window().ok_or("No window")?;
+    if !js_sys::Reflect::has(&window, &JsValue::from("ontouchstart")).unwrap_or(false) {
+        return Ok(());
+    }
+    let document = window.document().ok_or("No document")?;
+    let canvas = document
+        .get_element_by_id(canvas_id)
+        .ok_or("Canvas not found")?;
+
+    let input_state = client.borrow().input_state();
+    // Non-passive so prevent_default can stop scrolling and emulated mouse events
+    let options = web_sys::AddEventListenerOptions::new();
+    options.set_passive(false);
+
+    // Touch start: steer, arm pinch, detect double-tap
+    {
+        let input_clone = input_state.clone();
+        let closure = Closure::wrap(Box::new(move |event: TouchEvent| {
+            event.prevent_default();
+            let touches = event.touches();
+            let now = utils::now();
+            let mut input = input_clone.borrow_mut();
+            input.last_touch_time = now;
+            if touches.length() >= 2 {
+                input.pinch_distance = touch_distance(&touches);
+            } else if let Some(touch) = touches.get(0) {
+                input.mouse_pos = Vec2::new(touch.client_x() as f32, touch.client_y() as f32);
+                if now - input.last_tap_time < DOUBLE_TAP_MS {
+                    input.space_pressed = true;
+                    input.last_tap_time = f64::NEG_INFINITY;
+                } else {
+                    input.last_tap_time = now;
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas.add_event_listener_with_callback_and_add_event_listener_options(
+            "touchstart",
+            closure.as_ref().unchecked_ref(),
+            &options,
+        )?;
+        closure.forget();
+    }
+
+    // Touch move: steer with one finger, zoom with two
+    {
+        let input_clone = input_state.clone();
+        let client = client.clone();
+        let closure = Closure::wrap(Box::new(move |event: TouchEvent| {
+            event.prevent_default();
+            let touches = event.touches();
+            let zoom = {
+                let mut input = input_clone.borrow_mut();
+                input.last_touch_time = utils::now();
+                if touches.length() >= 2 {
+                    let distance = touch_distance(&touches);
+                    let factor = match (input.pinch_distance, distance) {
+                        (Some(prev), Some(cur)) if prev > 0.0 => Some(cur / prev),
+                        _ => None,
+                    };
+                    input.pinch_distance = distance;
+                    factor
+                } else {
+                    if let Some(touch) = touches.get(0) {
+                        input.mouse_pos = Vec2::new(touch.client_x() as f32, touch.client_y() as f32);
+                    }
+                    None
+                }
+            };
+            if let Some(factor) = zoom {
+                client.borrow_mut().adjust_zoom(factor);
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas.add_event_listener_with_callback_and_add_event_listener_options(
+            "touchmove",
+            closure.as_ref().unchecked_ref(),
+            &options,
+        )?;
+        closure.forget();
+    }
+
+    // Touch end / cancel: release double-tap split, end pinch
+    {
+        let input_clone = input_state.clone();
+        let closure = Closure::wrap(Box::new(move |event: TouchEvent| {
+            let mut input = input_clone.borrow_mut();
+            input.last_touch_time = utils::now();
+            if event.touches().length() < 2 {
+                input.pinch_distance = None;
+            }
+            if event.touches().length() == 0 {
+                input.space_pressed = false;
+            }
+        }) as Box<dyn FnMut(_)>);
+        canvas.add_event_listener_with_callback("touchend", closure.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("touchcancel", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+
+    // On-screen split / eject buttons
+    let ui = ui::UI::new(document);
+    if let Some((split_btn, eject_btn)) = ui.create_touch_buttons() {
+        let bind_button = |button: &Element, set: fn(&mut input::Input, bool)| -> Result<(), JsValue> {
+            let press_input = input_state.clone();
+            let press = Closure::wrap(Box::new(move |event: TouchEvent| {
+                event.prevent_default();
+                let mut input = press_input.borrow_mut();
+                input.last_touch_time = utils::now();
+                set(&mut input, true);
+            }) as Box<dyn FnMut(_)>);
+            button.add_event_listener_with_callback_and_add_event_listener_options(
+                "touchstart",
+                press.as_ref().unchecked_ref(),
+                &options,
+            )?;
+            press.forget();
+
+            let release_input = input_state.clone();
+            let release = Closure::wrap(Box::new(move |_event: TouchEvent| {
+                set(&mut release_input.borrow_mut(), false);
+            }) as Box<dyn FnMut(_)>);
+            button.add_event_listener_with_callback("touchend", release.as_ref().unchecked_ref())?;
+            button.add_event_listener_with_callback("touchcancel", release.as_ref().unchecked_ref())?;
+            release.forget();
+            Ok(())
+        };
+        bind_button(&split_btn, |input, pressed| input.space_pressed = pressed)?;
+        bind_button(&eject_btn, |input, pressed| input.w_pressed = pressed)?;
+    }
+
+    Ok(())
+}
+
+/// Send a message typed into the chat input and remember it in the history.
+fn send_chat(client: &Rc<RefCell<GameClient>>, message: &str) {
+    let mut client = client.borrow_mut();
+    client.chat_history().push(message);
+    client.send_chat_message(message);
+}
+
 fn setup_chat_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
     let window = window().ok_or("No window")?;
     let document = window.document().ok_or("No document")?;
@@ -383,17 +668,46 @@ fn setup_chat_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
         let client = client.clone();
         let closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
             let key = event.key();
-            if key == "Enter" {
-                event.prevent_default();
-                let msg = chat_input_inner.value().trim().to_string();
-                if !msg.is_empty() {
-                    client.borrow().send_chat_message(&msg);
+            match key.as_str() {
+                "Enter" => {
+                    event.prevent_default();
+                    let msg = chat_input_inner.value().trim().to_string();
+                    if !msg.is_empty() {
+                        send_chat(&client, &msg);
+                    }
+                    chat_input_inner.set_value("");
                 }
-                chat_input_inner.set_value("");
-            } else if key == "Escape" {
-                event.prevent_default();
-                chat_input_inner.set_value("");
-                let _ = chat_input_inner.blur();
+                "Escape" => {
+                    event.prevent_default();
+                    // Leaving history browsing puts back what was typed; otherwise dismiss
+                    match client.borrow_mut().chat_history().cancel() {
+                        Some(draft) => chat_input_inner.set_value(&draft),
+                        None => {
+                            chat_input_inner.set_value("");
+                            let _ = chat_input_inner.blur();
+                        }
+                    }
+                }
+                "ArrowUp" => {
+                    event.prevent_default();
+                    let current = chat_input_inner.value();
+                    if let Some(msg) = client.borrow_mut().chat_history().older(&current) {
+                        chat_input_inner.set_value(msg);
+                    }
+                }
+                "ArrowDown" => {
+                    event.prevent_default();
+                    if let Some(msg) = client.borrow_mut().chat_history().newer() {
+                        chat_input_inner.set_value(msg);
+                    }
+                }
+                "Tab" => {
+                    event.prevent_default();
+                    if let Some(text) = client.borrow().complete_chat_input(&chat_input_inner.value()) {
+                        chat_input_inner.set_value(&text);
+                    }
+                }
+                _ => {}
             }
         }) as Box<dyn FnMut(_)>);
 
@@ -409,7 +723,7 @@ fn setup_chat_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
         let closure = Closure::wrap(Box::new(move |_| {
             let msg = chat_input.value().trim().to_string();
             if !msg.is_empty() {
-                client.borrow().send_chat_message(&msg);
+                send_chat(&client, &msg);
             }
             chat_input.set_value("");
             let _ = chat_input.blur();
@@ -422,6 +736,97 @@ fn setup_chat_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Clicking a leaderboard row queues that player as the spectate target.
+/// Rows are re-rendered on every update, so one delegated listener on the
+/// list handles them all.
+fn setup_leaderboard_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
+    let window = window().ok_or("No window")?;
+    let document = window.document().ok_or("No document")?;
+    let list = document
+        .get_element_by_id("leaderboardList")
+        .ok_or("leaderboardList not found")?;
+
+    let pending = client.borrow().pending_spectate();
+    let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+        let row = event
+            .target()
+            .and_then(|t| t.dyn_into::<Element>().ok())
+            .and_then(|el| el.closest("li[data-player-id]").ok().flatten());
+        let player_id = row
+            .and_then(|row| row.get_attribute("data-player-id"))
+            .and_then(|id| id.parse::<u32>().ok());
+        if let Some(id) = player_id {
+            *pending.borrow_mut() = Some(id);
+        }
+    }) as Box<dyn FnMut(_)>);
+    list.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+    closure.forget();
+
+    Ok(())
+}
+
+/// Death screen: respawn under the last nick, spectate, or go back to the
+/// login overlay to change name.
+fn setup_death_overlay_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
+    let window = window().ok_or("No window")?;
+    let document = window.document().ok_or("No document")?;
+
+    let actions: [(&str, fn(&GameClient)); 3] = [
+        ("respawnButton", GameClient::queue_respawn),
+        ("spectateButton", GameClient::spectate_after_death),
+        ("changeNameLink", GameClient::show_login),
+    ];
+    for (id, action) in actions {
+        let el = document.get_element_by_id(id).ok_or_else(|| format!("{} not found", id))?;
+        let client = client.clone();
+        let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+            event.prevent_default();
+            action(&client.borrow());
+        }) as Box<dyn FnMut(_)>);
+        el.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+
+    Ok(())
+}
+
+/// Help overlay: the "?" buttons open it; its close button or a click on
+/// the backdrop closes it.
+fn setup_help_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
+    let window = window().ok_or("No window")?;
+    let document = window.document().ok_or("No document")?;
+
+    let actions: [(&str, fn(&GameClient)); 3] = [
+        ("helpBtn", GameClient::show_help),
+        ("hudHelpButton", GameClient::show_help),
+        ("helpClose", GameClient::hide_help),
+    ];
+    for (id, action) in actions {
+        let el = document.get_element_by_id(id).ok_or_else(|| format!("{} not found", id))?;
+        let client = client.clone();
+        let closure = Closure::wrap(Box::new(move |_: MouseEvent| {
+            action(&client.borrow());
+        }) as Box<dyn FnMut(_)>);
+        el.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+
+    let overlay = document.get_element_by_id("helpOverlay").ok_or("helpOverlay not found")?;
+    let backdrop = overlay.clone();
+    let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+        let on_backdrop = event.target()
+            .and_then(|t| t.dyn_into::<Element>().ok())
+            .is_some_and(|target| target == backdrop);
+        if on_backdrop {
+            client.borrow().hide_help();
+        }
+    }) as Box<dyn FnMut(_)>);
+    overlay.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+    closure.forget();
+
+    Ok(())
+}
+
 fn setup_zoom_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
     let window = window().ok_or("No window")?;
     let document = window.document().ok_or("No document")?;
@@ -471,10 +876,85 @@ fn setup_settings_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValu
         .get_element_by_id("settingShowMinimap")
         .ok_or("settingShowMinimap not found")?
         .dyn_into::<HtmlInputElement>()?;
+    let show_team_pie = document
+        .get_element_by_id("settingShowTeamPie")
+        .ok_or("settingShowTeamPie not found")?
+        .dyn_into::<HtmlInputElement>()?;
     let dark_theme = document
         .get_element_by_id("settingDarkTheme")
         .ok_or("settingDarkTheme not found")?
         .dyn_into::<HtmlInputElement>()?;
+    let show_particles = document
+        .get_element_by_id("settingShowParticles")
+        .ok_or("settingShowParticles not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let show_threat_indicators = document
+        .get_element_by_id("settingShowThreatIndicators")
+        .ok_or("settingShowThreatIndicators not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let mass_weighted_camera = document
+        .get_element_by_id("settingMassWeightedCamera")
+        .ok_or("settingMassWeightedCamera not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let unlimited_zoom = document
+        .get_element_by_id("settingUnlimitedZoom")
+        .ok_or("settingUnlimitedZoom not found")?
+        .dyn_into::<HtmlInputElement>()?;
+
+    let food_visibility = document
+        .get_element_by_id("settingFoodVisibility")
+        .ok_or("settingFoodVisibility not found")?
+        .dyn_into::<HtmlSelectElement>()?;
+    let fps_cap = document
+        .get_element_by_id("settingFpsCap")
+        .ok_or("settingFpsCap not found")?
+        .dyn_into::<HtmlSelectElement>()?;
+    let eject_color_mode = document
+        .get_element_by_id("settingEjectColorMode")
+        .ok_or("settingEjectColorMode not found")?
+        .dyn_into::<HtmlSelectElement>()?;
+    let eject_color = document
+        .get_element_by_id("settingEjectColor")
+        .ok_or("settingEjectColor not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let camera_smoothing = document
+        .get_element_by_id("settingCameraSmoothing")
+        .ok_or("settingCameraSmoothing not found")?
+        .dyn_into::<HtmlSelectElement>()?;
+    let skin_only = document
+        .get_element_by_id("settingSkinOnly")
+        .ok_or("settingSkinOnly not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let border_color = document
+        .get_element_by_id("settingBorderColor")
+        .ok_or("settingBorderColor not found")?
+        .dyn_into::<HtmlSelectElement>()?;
+    let border_width = document
+        .get_element_by_id("settingBorderWidth")
+        .ok_or("settingBorderWidth not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let border_width_label = document
+        .get_element_by_id("settingBorderWidthValue")
+        .ok_or("settingBorderWidthValue not found")?;
+    let interpolation = document
+        .get_element_by_id("settingInterpolation")
+        .ok_or("settingInterpolation not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let interpolation_label = document
+        .get_element_by_id("settingInterpolationValue")
+        .ok_or("settingInterpolationValue not found")?;
+    let share_position_key = document
+        .get_element_by_id("settingSharePositionKey")
+        .ok_or("settingSharePositionKey not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let debug_hud_key = document
+        .get_element_by_id("settingDebugHudKey")
+        .ok_or("settingDebugHudKey not found")?
+        .dyn_into::<HtmlInputElement>()?;
+    let help_key = document
+        .get_element_by_id("settingHelpKey")
+        .ok_or("settingHelpKey not found")?
+        .dyn_into::<HtmlInputElement>()?;
 
     let minimap_canvas = document
         .get_element_by_id("minimapCanvas")
@@ -483,7 +963,35 @@ fn setup_settings_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValu
 
     let hidden_bang = js_sys::Array::of1(&JsValue::from("hidden!"));
 
-    // Apply initial settings
+    // Reflect saved settings in the controls, then apply them from the controls
+    {
+        let saved = client.borrow().settings();
+        show_skins.set_checked(saved.show_skins);
+        show_names.set_checked(saved.show_names);
+        show_mass.set_checked(saved.show_mass);
+        show_grid.set_checked(saved.show_grid);
+        show_background_sectors.set_checked(saved.show_background_sectors);
+        show_minimap.set_checked(saved.show_minimap);
+        show_team_pie.set_checked(saved.show_team_pie);
+        dark_theme.set_checked(saved.dark_theme);
+        show_particles.set_checked(saved.show_particles);
+        show_threat_indicators.set_checked(saved.show_threat_indicators);
+        mass_weighted_camera.set_checked(saved.mass_weighted_camera);
+        unlimited_zoom.set_checked(saved.unlimited_zoom);
+        food_visibility.set_value(saved.food_visibility.as_setting());
+        fps_cap.set_value(saved.fps_cap.as_setting());
+        camera_smoothing.set_value(saved.camera_smoothing.as_setting());
+        eject_color_mode.set_value(saved.eject_color_mode.as_setting());
+        let (r, g, b) = saved.eject_color;
+        eject_color.set_value(&format!("#{:02x}{:02x}{:02x}", r, g, b));
+        skin_only.set_checked(saved.cell_style.skin_only);
+        border_color.set_value(saved.cell_style.border_color.as_setting());
+        border_width.set_value_as_number(saved.cell_style.border_width as f64);
+        interpolation.set_value_as_number(saved.interpolation_ms);
+        share_position_key.set_value(&saved.share_position_key.to_string());
+        debug_hud_key.set_value(&saved.debug_hud_key.to_string());
+        help_key.set_value(&saved.help_key.to_string());
+    }
     {
         let mut client = client.borrow_mut();
         client.set_show_skins(show_skins.checked());
@@ -492,7 +1000,28 @@ fn setup_settings_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValu
         client.set_show_grid(show_grid.checked());
         client.set_show_background_sectors(show_background_sectors.checked());
         client.set_show_minimap(show_minimap.checked());
+        client.set_show_team_pie(show_team_pie.checked());
         client.set_dark_theme(dark_theme.checked());
+        client.set_show_particles(show_particles.checked());
+        client.set_show_threat_indicators(show_threat_indicators.checked());
+        client.set_mass_weighted_camera(mass_weighted_camera.checked());
+        client.set_unlimited_zoom(unlimited_zoom.checked());
+        client.set_food_visibility(FoodVisibility::from_setting(&food_visibility.value()));
+        client.set_fps_cap(FpsCap::from_setting(&fps_cap.value()));
+        client.set_camera_smoothing(CameraSmoothing::from_setting(&camera_smoothing.value()));
+        client.set_eject_color(EjectColorMode::from_setting(&eject_color_mode.value()), &eject_color.value());
+        client.set_skin_only(skin_only.checked());
+        client.set_border_color(BorderColorMode::from_setting(&border_color.value()));
+        client.set_border_width(border_width.value_as_number() as f32);
+        client.set_share_position_key(&share_position_key.value());
+        client.set_debug_hud_key(&debug_hud_key.value());
+        client.set_help_key(&help_key.value());
+        border_width_label.set_text_content(Some(&format!("{}x", border_width.value_as_number())));
+    }
+    {
+        let ms = interpolation.value_as_number();
+        client.borrow_mut().set_interpolation_ms(ms);
+        interpolation_label.set_text_content(Some(&format!("{}ms", ms)));
     }
     if show_minimap.checked() {
         minimap_canvas.class_list().remove(&hidden_bang).ok();
@@ -502,8 +1031,10 @@ fn setup_settings_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValu
 
     let bind_checkbox = |input: HtmlInputElement, mut f: Box<dyn FnMut(bool)>| {
         let input_clone = input.clone();
+        let client = client.clone();
         let closure = Closure::wrap(Box::new(move |_| {
             f(input_clone.checked());
+            client.borrow().save_settings();
         }) as Box<dyn FnMut(JsValue)>);
         input.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref()).ok();
         closure.forget();
@@ -558,6 +1089,13 @@ fn setup_settings_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValu
             }
         }));
     }
+    // Show team pie chart
+    {
+        let client = client.clone();
+        bind_checkbox(show_team_pie.clone(), Box::new(move |v| {
+            client.borrow_mut().set_show_team_pie(v);
+        }));
+    }
     // Dark theme
     {
         let client = client.clone();
@@ -565,31 +1103,194 @@ fn setup_settings_handlers(client: Rc<RefCell<GameClient>>) -> Result<(), JsValu
             client.borrow_mut().set_dark_theme(v);
         }));
     }
+    // Death particles
+    {
+        let client = client.clone();
+        bind_checkbox(show_particles.clone(), Box::new(move |v| {
+            client.borrow_mut().set_show_particles(v);
+        }));
+    }
+    // Threat indicators
+    {
+        let client = client.clone();
+        bind_checkbox(show_threat_indicators.clone(), Box::new(move |v| {
+            client.borrow_mut().set_show_threat_indicators(v);
+        }));
+    }
+    // Mass-weighted camera
+    {
+        let client = client.clone();
+        bind_checkbox(mass_weighted_camera.clone(), Box::new(move |v| {
+            client.borrow_mut().set_mass_weighted_camera(v);
+        }));
+    }
+    // Unlimited zoom
+    {
+        let client = client.clone();
+        bind_checkbox(unlimited_zoom.clone(), Box::new(move |v| {
+            client.borrow_mut().set_unlimited_zoom(v);
+        }));
+    }
+    // Food visibility
+    {
+        let client = client.clone();
+        let select = food_visibility.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            client.borrow_mut().set_food_visibility(FoodVisibility::from_setting(&select.value()));
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        food_visibility.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // FPS cap
+    {
+        let client = client.clone();
+        let select = fps_cap.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            client.borrow_mut().set_fps_cap(FpsCap::from_setting(&select.value()));
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        fps_cap.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Eject color; picking a color while on "Cell" switches to it
+    for picks_color in [false, true] {
+        let client = client.clone();
+        let select = eject_color_mode.clone();
+        let input = eject_color.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            if picks_color && select.value() == EjectColorMode::Cell.as_setting() {
+                select.set_value(EjectColorMode::Custom.as_setting());
+            }
+            client.borrow_mut().set_eject_color(EjectColorMode::from_setting(&select.value()), &input.value());
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        let target: &Element = if picks_color { &eject_color } else { &eject_color_mode };
+        target.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Camera smoothing
+    {
+        let client = client.clone();
+        let select = camera_smoothing.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            client.borrow_mut().set_camera_smoothing(CameraSmoothing::from_setting(&select.value()));
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        camera_smoothing.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Skin only (no base fill under loaded skins)
+    {
+        let client = client.clone();
+        bind_checkbox(skin_only.clone(), Box::new(move |v| {
+            client.borrow_mut().set_skin_only(v);
+        }));
+    }
+    // Border color
+    {
+        let client = client.clone();
+        let select = border_color.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            client.borrow_mut().set_border_color(BorderColorMode::from_setting(&select.value()));
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        border_color.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Border width
+    {
+        let client = client.clone();
+        let input = border_width.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            let width = input.value_as_number();
+            client.borrow_mut().set_border_width(width as f32);
+            border_width_label.set_text_content(Some(&format!("{}x", width)));
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        border_width.add_event_listener_with_callback("input", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Interpolation window
+    {
+        let client = client.clone();
+        let input = interpolation.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            let ms = input.value_as_number();
+            client.borrow_mut().set_interpolation_ms(ms);
+            interpolation_label.set_text_content(Some(&format!("{}ms", ms)));
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        interpolation.add_event_listener_with_callback("input", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Share-position key
+    {
+        let client = client.clone();
+        let input = share_position_key.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            let key = {
+                let mut client = client.borrow_mut();
+                client.set_share_position_key(&input.value());
+                client.settings().share_position_key
+            };
+            // Show the key actually in use (an empty/invalid entry is ignored)
+            input.set_value(&key.to_string());
+            let _ = input.blur();
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        share_position_key.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Debug HUD key
+    {
+        let client = client.clone();
+        let input = debug_hud_key.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            let key = {
+                let mut client = client.borrow_mut();
+                client.set_debug_hud_key(&input.value());
+                client.settings().debug_hud_key
+            };
+            input.set_value(&key.to_string());
+            let _ = input.blur();
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        debug_hud_key.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    // Help key
+    {
+        let client = client.clone();
+        let input = help_key.clone();
+        let closure = Closure::wrap(Box::new(move |_| {
+            let key = {
+                let mut client = client.borrow_mut();
+                client.set_help_key(&input.value());
+                client.settings().help_key
+            };
+            input.set_value(&key.to_string());
+            let _ = input.blur();
+            client.borrow().save_settings();
+        }) as Box<dyn FnMut(JsValue)>);
+        help_key.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
 
     Ok(())
 }
 
-/// Resize the canvas when the browser window is resized.
-fn setup_resize_handler(canvas_id: &str) -> Result<(), JsValue> {
+/// Resize the canvases when the browser window is resized or the device
+/// pixel ratio changes (browser zoom, moving to another screen).
+fn setup_resize_handler(client: Rc<RefCell<GameClient>>) -> Result<(), JsValue> {
     let win = window().ok_or("No window")?;
-    let id = canvas_id.to_string();
 
     let closure = Closure::wrap(Box::new(move || {
-        if let (Some(win), Some(doc)) = (web_sys::window(), web_sys::window().and_then(|w| w.document())) {
-            if let Some(canvas_el) = doc.get_element_by_id(&id) {
-                if let Ok(canvas) = canvas_el.dyn_into::<HtmlCanvasElement>() {
-                    if let Ok(w) = win.inner_width() {
-                        canvas.set_width(w.as_f64().unwrap_or(800.0) as u32);
-                    }
-                    if let Ok(h) = win.inner_height() {
-                        canvas.set_height(h.as_f64().unwrap_or(600.0) as u32);
-                    }
-                }
-            }
-        }
+        client.borrow_mut().resize();
     }) as Box<dyn FnMut()>);
 
     win.add_event_listener_with_callback("resize", closure.as_ref().unchecked_ref())?;
+    win.add_event_listener_with_callback("orientationchange", closure.as_ref().unchecked_ref())?;
     closure.forget();
 
     Ok(())
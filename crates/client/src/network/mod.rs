@@ -2,8 +2,12 @@
 use wasm_bindgen::prelude::*;
 use web_sys::{WebSocket, BinaryType};
 use protocol::BinaryWriter;
+use protocol::packets::{self, ClientOpcode};
 use js_sys::Uint8Array;
 
+/// Protocol announced to servers that aren't cogar, as Cigar2 does.
+const VANILLA_PROTOCOL: u8 = 6;
+
 pub struct Connection {
     ws: WebSocket,
     url: String,
@@ -13,21 +17,31 @@ pub struct Connection {
     protocol_version: u8,
 }
 
+/// `url` as a WebSocket URL; a bare `host:port` gets `wss://` on an HTTPS
+/// page and `ws://` otherwise.
+fn websocket_url(url: &str) -> String {
+    if url.starts_with("ws://") || url.starts_with("wss://") {
+        url.to_string()
+    } else {
+        // Check if we're on HTTPS
+        let is_https = web_sys::window()
+            .and_then(|w| w.location().protocol().ok())
+            .map(|p| p == "https:")
+            .unwrap_or(false);
+
+        format!("ws{}://{}", if is_https { "s" } else { "" }, url)
+    }
+}
+
+/// Protocol to announce: cogar's own level, for sub-unit cell positions,
+/// only to servers known to be cogar.
+fn protocol_for(cogar: bool) -> u8 {
+    if cogar { packets::COGAR_PROTOCOL as u8 } else { VANILLA_PROTOCOL }
+}
+
 impl Connection {
-    pub fn new(url: &str) -> Result<Self, JsValue> {
-        // Construct WebSocket URL with proper protocol
-        let ws_url = if url.starts_with("ws://") || url.starts_with("wss://") {
-            url.to_string()
-        } else {
-            // Check if we're on HTTPS
-            let is_https = web_sys::window()
-                .and_then(|w| w.location().protocol().ok())
-                .map(|p| p == "https:")
-                .unwrap_or(false);
-            
-            format!("ws{}://{}", if is_https { "s" } else { "" }, url)
-        };
-        
+    pub fn new(url: &str, cogar: bool) -> Result<Self, JsValue> {
+        let ws_url = websocket_url(url);
         web_sys::console::log_1(&format!("Connecting to: {}", ws_url).into());
         let ws = WebSocket::new(&ws_url)?;
         ws.set_binary_type(BinaryType::Arraybuffer);
@@ -38,7 +52,7 @@ impl Connection {
             scramble_x: 0,
             scramble_y: 0,
             scramble_id: 0,
-            protocol_version: 6, // Match JS client (Cigar2)
+            protocol_version: protocol_for(cogar),
         })
     }
 
@@ -46,14 +60,12 @@ impl Connection {
         &self.ws
     }
 
-    pub fn reconnect(&mut self) -> Result<WebSocket, JsValue> {
-        // Clean up old websocket
-        self.ws.set_onopen(None);
-        self.ws.set_onmessage(None);
-        self.ws.set_onerror(None);
-        self.ws.set_onclose(None);
-        let _ = self.ws.close();
+    pub fn url(&self) -> &str {
+        &self.url
+    }
 
+    pub fn reconnect(&mut self) -> Result<WebSocket, JsValue> {
+        self.close();
         web_sys::console::log_1(&format!("Reconnecting to: {}", self.url).into());
         let ws = WebSocket::new(&self.url)?;
         ws.set_binary_type(BinaryType::Arraybuffer);
@@ -61,12 +73,34 @@ impl Connection {
         Ok(self.ws.clone())
     }
 
+    /// Open a fresh WebSocket to another server, closing the current one.
+    pub fn connect_to(&mut self, url: &str, cogar: bool) -> Result<WebSocket, JsValue> {
+        self.url = websocket_url(url);
+        self.protocol_version = protocol_for(cogar);
+        self.set_scramble(0, 0, 0);
+        self.reconnect()
+    }
+
+    /// Close the WebSocket without its close handler firing, so nothing
+    /// schedules a reconnect.
+    pub fn close(&self) {
+        self.ws.set_onopen(None);
+        self.ws.set_onmessage(None);
+        self.ws.set_onerror(None);
+        self.ws.set_onclose(None);
+        let _ = self.ws.close();
+    }
+
     pub fn set_scramble(&mut self, x: i32, y: i32, id: u32) {
         self.scramble_x = x;
         self.scramble_y = y;
         self.scramble_id = id;
     }
 
+    pub fn protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+
     pub fn scramble_x(&self) -> i32 {
         self.scramble_x
     }
@@ -89,58 +123,46 @@ impl Connection {
         self.ws.send_with_array_buffer(&array.buffer())
     }
 
-    /// Send handshake (0xFF + key 1 for protocol <= 6)
+    /// Send handshake (0xFF + key 1 for protocol <= 6, 0 above)
     pub fn send_handshake(&self) -> Result<(), JsValue> {
-        let mut writer = BinaryWriter::new();
-        writer.put_u8(0xFF); // HandshakeKey opcode
-        writer.put_u32(1);   // Key = 1 for protocol <= 6
-        self.send_bytes(writer.as_slice())
+        let key = if self.protocol_version <= 6 { 1 } else { 0 };
+        self.send_bytes(packets::build_handshake_key(key).as_slice())
     }
 
     /// Send protocol version (0xFE + version as u32 — server expects exactly 5 bytes)
     pub fn send_protocol_version(&self) -> Result<(), JsValue> {
-        let mut writer = BinaryWriter::new();
-        writer.put_u8(0xFE);
-        writer.put_u32(self.protocol_version as u32);
-        self.send_bytes(writer.as_slice())
+        self.send_bytes(packets::build_protocol_version(self.protocol_version as u32).as_slice())
     }
 
-    /// Send spawn request (0x00 + nick as UTF-8, protocol <= 6)
+    /// Send spawn request (0x00 + nick, UTF-16 above protocol 6)
     pub fn send_spawn(&self, nick: &str) -> Result<(), JsValue> {
-        let mut writer = BinaryWriter::new();
-        writer.put_u8(0x00); // Join opcode
-        writer.put_string_utf8(nick);
-        self.send_bytes(writer.as_slice())
+        self.send_bytes(packets::build_join(self.protocol_version as u32, nick).as_slice())
     }
 
     /// Send mouse position (0x10 + x + y)
     /// Coordinates are already in scrambled world space — server subtracts scramble on receipt.
     pub fn send_mouse(&self, x: f32, y: f32) -> Result<(), JsValue> {
-        let mut writer = BinaryWriter::new();
-        writer.put_u8(0x10);
-        writer.put_i32(x as i32);
-        writer.put_i32(y as i32);
+        self.send_bytes(packets::build_mouse(x as i32, y as i32).as_slice())
+    }
 
-        // Protocol >=6: 13-byte mouse packet (4 extra zero bytes)
-        if self.protocol_version >= 6 {
-            writer.put_u32(0);
-        }
+    /// Send the canvas size in CSS pixels (0x1A) so the server culls to our view
+    pub fn send_viewport(&self, width: f32, height: f32) -> Result<(), JsValue> {
+        self.send_bytes(packets::build_viewport(width as u16, height as u16).as_slice())
+    }
 
-        self.send_bytes(writer.as_slice())
+    /// Send the ejected-mass color (0x1B)
+    pub fn send_eject_color(&self, color: packets::EjectColor) -> Result<(), JsValue> {
+        self.send_bytes(packets::build_eject_color(color).as_slice())
     }
 
     /// Send split request (Space key, 0x11)
     pub fn send_split(&self) -> Result<(), JsValue> {
-        let mut writer = BinaryWriter::new();
-        writer.put_u8(0x11); // Split opcode
-        self.send_bytes(writer.as_slice())
+        self.send_bytes(packets::build_opcode(ClientOpcode::Split).as_slice())
     }
 
     /// Send eject request (W key, 0x15)
     pub fn send_eject(&self) -> Result<(), JsValue> {
-        let mut writer = BinaryWriter::new();
-        writer.put_u8(0x15); // Eject opcode
-        self.send_bytes(writer.as_slice())
+        self.send_bytes(packets::build_opcode(ClientOpcode::Eject).as_slice())
     }
 
     /// Send Q key (0x12)
@@ -185,12 +207,20 @@ impl Connection {
         self.send_bytes(writer.as_slice())
     }
 
+    /// Send spectate target (0x02 + u32 player id from the leaderboard)
+    pub fn send_spectate_target(&self, player_id: u32) -> Result<(), JsValue> {
+        let mut writer = BinaryWriter::new();
+        writer.put_u8(0x02);
+        writer.put_u32(player_id);
+        self.send_bytes(writer.as_slice())
+    }
+
     /// Send chat message (0x63 + flags + message as UTF-8 for protocol >= 6)
     pub fn send_chat(&self, message: &str) -> Result<(), JsValue> {
         let mut writer = BinaryWriter::new();
         writer.put_u8(0x63);
         writer.put_u8(0); // Flags (0 = no reserved bytes)
-        writer.put_string_utf8(message);
+        writer.put_string_utf8_null(message);
         self.send_bytes(writer.as_slice())
     }
 
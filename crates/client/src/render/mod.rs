@@ -1,17 +1,233 @@
 // Canvas rendering - grid, cells, skins, UI overlays
 use wasm_bindgen::prelude::*;
+use js_sys::Math;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
 use glam::Vec2;
-use crate::game::Cell;
+use protocol::packets::{MapShape, MapShapeKind};
+use crate::game::{Cell, PerfHistory, PERF_SAMPLES};
 use crate::utils;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::f32::consts::PI;
 use std::f64::consts::TAU;
 use std::cell::RefCell;
 
+/// How cell outlines are colored.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorderColorMode {
+    /// A darker shade of the fill color, like vanilla agar.
+    Darker,
+    Black,
+    None,
+}
+
+impl BorderColorMode {
+    /// Parse the value of the `settingBorderColor` select.
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "darker" => Self::Darker,
+            "none" => Self::None,
+            _ => Self::Black,
+        }
+    }
+
+    /// The `settingBorderColor` option value for this mode.
+    pub fn as_setting(self) -> &'static str {
+        match self {
+            Self::Darker => "darker",
+            Self::Black => "black",
+            Self::None => "none",
+        }
+    }
+}
+
+/// Outline and fill options applied to cells and viruses.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CellStyle {
+    /// Multiplier on the 2px base outline width.
+    pub border_width: f32,
+    pub border_color: BorderColorMode,
+    /// Skip the base color fill when a skin is drawn, so transparent skins show through.
+    pub skin_only: bool,
+}
+
+impl Default for CellStyle {
+    fn default() -> Self {
+        Self {
+            border_width: 1.0,
+            border_color: BorderColorMode::Black,
+            skin_only: false,
+        }
+    }
+}
+
+impl CellStyle {
+    /// Stroke color for a cell filled with `color`, or `None` when outlines are off.
+    fn stroke_color(&self, (r, g, b): (u8, u8, u8)) -> Option<String> {
+        if self.border_width <= 0.0 {
+            return None;
+        }
+        match self.border_color {
+            BorderColorMode::Darker => Some(format!(
+                "rgb({},{},{})",
+                (r as f32 * 0.8) as u8,
+                (g as f32 * 0.8) as u8,
+                (b as f32 * 0.8) as u8
+            )),
+            BorderColorMode::Black => Some("rgba(0,0,0,0.8)".to_string()),
+            BorderColorMode::None => None,
+        }
+    }
+
+    fn line_width(&self) -> f64 {
+        (2.0 * self.border_width) as f64
+    }
+}
+
+/// A cell this many times bigger than another can eat it.
+const EAT_SIZE_RATIO: f32 = 1.15;
+
+/// How a cell relates to the player, for the threat indicator outlines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellRelation {
+    Mine,
+    /// An enemy my biggest cell can eat.
+    Prey,
+    /// An enemy that can eat my biggest cell.
+    Threat,
+    /// Anything else: food, viruses, equals, or no cells of my own.
+    Neutral,
+}
+
+impl CellRelation {
+    /// Classify `cell` against `my_max_size`, the size of my biggest cell.
+    pub fn classify(cell: &Cell, mine: bool, my_max_size: Option<f32>) -> Self {
+        if mine {
+            return Self::Mine;
+        }
+        let Some(my_size) = my_max_size else {
+            return Self::Neutral;
+        };
+        if cell.is_food || cell.is_virus || cell.is_ejected {
+            return Self::Neutral;
+        }
+        if cell.render_size > my_size * EAT_SIZE_RATIO {
+            Self::Threat
+        } else if my_size > cell.render_size * EAT_SIZE_RATIO {
+            Self::Prey
+        } else {
+            Self::Neutral
+        }
+    }
+
+    /// Outline replacing the normal border, as (color, width).
+    fn stroke(self, style: &CellStyle) -> Option<(&'static str, f64)> {
+        match self {
+            Self::Mine => Some(("rgba(255,255,255,0.7)", style.line_width().max(2.0))),
+            Self::Threat => Some(("rgba(255,40,40,0.9)", (style.line_width() * 1.5).max(3.0))),
+            Self::Prey | Self::Neutral => None,
+        }
+    }
+}
+
+/// Sectors per side of the A1–E5 grid.
+pub const SECTOR_COUNT: usize = 5;
+
+/// Label of the sector in column `col`, row `row` ("A1" is top-left).
+pub fn sector_label(col: usize, row: usize) -> String {
+    format!("{}{}", (b'A' + col as u8) as char, row + 1)
+}
+
+/// Column and row of the sector containing `pos`, clamped to the grid.
+pub fn sector_index(border: (f32, f32, f32, f32), pos: Vec2) -> (usize, usize) {
+    let (min_x, min_y, max_x, max_y) = border;
+    let last = (SECTOR_COUNT - 1) as f32;
+    let col = ((pos.x - min_x) / (max_x - min_x) * SECTOR_COUNT as f32).floor();
+    let row = ((pos.y - min_y) / (max_y - min_y) * SECTOR_COUNT as f32).floor();
+    // NaN (empty border) clamps to 0 through the cast
+    (col.clamp(0.0, last) as usize, row.clamp(0.0, last) as usize)
+}
+
+/// Label of the sector containing world position `pos`.
+pub fn sector_at(border: (f32, f32, f32, f32), pos: Vec2) -> String {
+    let (col, row) = sector_index(border, pos);
+    sector_label(col, row)
+}
+
+/// World position where a sector's label is drawn (its center).
+fn sector_center(border: (f32, f32, f32, f32), col: usize, row: usize) -> Vec2 {
+    let (min_x, min_y, max_x, max_y) = border;
+    let sector_w = (max_x - min_x) / SECTOR_COUNT as f32;
+    let sector_h = (max_y - min_y) / SECTOR_COUNT as f32;
+    Vec2::new(
+        min_x + (col as f32 + 0.5) * sector_w,
+        min_y + (row as f32 + 0.5) * sector_h,
+    )
+}
+
+/// Smallest and largest cell name font, in screen pixels.
+const NAME_FONT_MIN: f32 = 10.0;
+const NAME_FONT_MAX: f32 = 96.0;
+/// Name font size per pixel of on-screen cell radius.
+const NAME_FONT_PER_RADIUS: f32 = 0.4;
+/// Mass text size relative to the name.
+const MASS_FONT_RATIO: f32 = 0.6;
+/// Share of the cell's diameter text may span.
+const TEXT_FIT_RATIO: f32 = 0.9;
+/// Widest any cell text is drawn, however big the cell.
+const TEXT_MAX_WIDTH: f32 = 1200.0;
+/// Text taller than this many times its font size (stacked combining
+/// marks) is clipped to that band.
+const TEXT_MAX_HEIGHT_RATIO: f32 = 1.5;
+/// Font sizes are rounded to steps of this many pixels, so a growing cell
+/// doesn't get a new font every frame and text caches can key on the size.
+const FONT_BUCKET_PX: f32 = 2.0;
+
+/// Radius swing of an agitated (boosted) cell, as a share of its radius.
+const AGITATED_WOBBLE: f32 = 0.04;
+/// Wobble phase speed, in radians per millisecond.
+const AGITATED_WOBBLE_SPEED: f64 = 0.06;
+
+/// Round a font size to its bucket.
+fn font_bucket(size: f32) -> f32 {
+    ((size / FONT_BUCKET_PX).round() * FONT_BUCKET_PX).max(FONT_BUCKET_PX)
+}
+
+/// Name font size for a cell with the given on-screen radius.
+fn name_font_size(radius: f32) -> f32 {
+    font_bucket((radius * NAME_FONT_PER_RADIUS).clamp(NAME_FONT_MIN, NAME_FONT_MAX))
+}
+
+/// Fit `text` into `max_width`: shrink the font, but not below
+/// `NAME_FONT_MIN` (or `font_size` if that is already smaller), then drop
+/// characters behind an ellipsis. `measure(text, size)` gives the drawn width.
+fn fit_text(text: &str, font_size: f32, max_width: f32, measure: impl Fn(&str, f32) -> f32) -> (std::borrow::Cow<'_, str>, f32) {
+    let width = measure(text, font_size);
+    if width <= max_width {
+        return (text.into(), font_size);
+    }
+    let min_size = NAME_FONT_MIN.min(font_size);
+    let shrunk = (font_size * max_width / width).floor();
+    if shrunk >= min_size {
+        return (text.into(), shrunk);
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while chars.pop().is_some() {
+        let candidate: String = chars.iter().chain(std::iter::once(&'\u{2026}')).collect();
+        if measure(&candidate, min_size) <= max_width {
+            return (candidate.into(), min_size);
+        }
+    }
+    ("".into(), min_size)
+}
+
 pub struct Renderer {
     canvas: HtmlCanvasElement,
     ctx: CanvasRenderingContext2d,
+    cell_style: CellStyle,
+    /// Device pixels per logical pixel of the backing store.
+    dpr: f64,
     // Offscreen canvases for caching static elements
     grid_cache: RefCell<Option<(HtmlCanvasElement, f32, f32, f32, bool)>>, // (canvas, zoom, cam_x, cam_y, dark_theme)
     bg_cache: RefCell<Option<(HtmlCanvasElement, f32, f32, f32, bool)>>, // (canvas, zoom, cam_x, cam_y, dark_theme)
@@ -27,27 +243,57 @@ impl Renderer {
         Ok(Self {
             canvas,
             ctx,
+            cell_style: CellStyle::default(),
+            dpr: 1.0,
             grid_cache: RefCell::new(None),
             bg_cache: RefCell::new(None),
         })
     }
 
+    pub fn set_cell_style(&mut self, style: CellStyle) {
+        self.cell_style = style;
+    }
+
+    /// Size the backing store for a `width` x `height` (CSS pixel) viewport
+    /// at the current device pixel ratio, so HiDPI screens render sharp.
+    /// Everything else keeps working in logical pixels.
+    pub fn resize(&mut self, width: f64, height: f64) {
+        self.dpr = utils::device_pixel_ratio();
+        self.canvas.set_width((width * self.dpr).round() as u32);
+        self.canvas.set_height((height * self.dpr).round() as u32);
+        // The cached layers were drawn for the old size
+        *self.grid_cache.borrow_mut() = None;
+        *self.bg_cache.borrow_mut() = None;
+    }
+
+    /// Viewport width in logical (CSS) pixels.
     #[inline(always)]
     pub fn width(&self) -> f32 {
-        self.canvas.width() as f32
+        (self.canvas.width() as f64 / self.dpr) as f32
     }
 
+    /// Viewport height in logical (CSS) pixels.
     #[inline(always)]
     pub fn height(&self) -> f32 {
-        self.canvas.height() as f32
+        (self.canvas.height() as f64 / self.dpr) as f32
     }
 
+    /// Start a frame: reset the transform to logical pixels and fill the background.
     #[inline]
     pub fn clear(&self, background: &str) {
+        let _ = self.ctx.set_transform(self.dpr, 0.0, 0.0, self.dpr, 0.0, 0.0);
         self.ctx.set_fill_style_str(background);
         self.ctx.fill_rect(0.0, 0.0, self.width() as f64, self.height() as f64);
     }
 
+    /// Draw a device-pixel cache canvas over the whole viewport.
+    #[inline]
+    fn blit_cache(&self, cache: &HtmlCanvasElement) {
+        let _ = self.ctx.draw_image_with_html_canvas_element_and_dw_and_dh(
+            cache, 0.0, 0.0, self.width() as f64, self.height() as f64,
+        );
+    }
+
     #[inline]
     pub fn draw_grid(&self, border: (f32, f32, f32, f32), camera_pos: Vec2, zoom: f32, dark_theme: bool) {
         // Check if we can use cached grid
@@ -59,7 +305,7 @@ impl Renderer {
             
             if zoom_match && pos_match && theme_match {
                 // Use cached grid - just blit it to the main canvas
-                let _ = self.ctx.draw_image_with_html_canvas_element(cached_canvas, 0.0, 0.0);
+                self.blit_cache(cached_canvas);
                 return;
             }
         }
@@ -80,12 +326,13 @@ impl Renderer {
             .get_context("2d").unwrap()
             .unwrap()
             .dyn_into::<CanvasRenderingContext2d>().unwrap();
+        let _ = cache_ctx.scale(self.dpr, self.dpr);
 
         // Render grid to cache canvas
         self.render_grid_to_context(&cache_ctx, self.width(), self.height(), border, camera_pos, zoom, dark_theme);
 
         // Blit cache to main canvas
-        let _ = self.ctx.draw_image_with_html_canvas_element(&cache_canvas, 0.0, 0.0);
+        self.blit_cache(&cache_canvas);
 
         // Update cache
         *self.grid_cache.borrow_mut() = Some((cache_canvas, zoom, camera_pos.x, camera_pos.y, dark_theme));
@@ -188,7 +435,7 @@ impl Renderer {
             
             if zoom_match && pos_match && theme_match {
                 // Use cached background - just blit it
-                let _ = self.ctx.draw_image_with_html_canvas_element(cached_canvas, 0.0, 0.0);
+                self.blit_cache(cached_canvas);
                 return;
             }
         }
@@ -209,12 +456,13 @@ impl Renderer {
             .get_context("2d").unwrap()
             .unwrap()
             .dyn_into::<CanvasRenderingContext2d>().unwrap();
+        let _ = cache_ctx.scale(self.dpr, self.dpr);
 
         // Render background sectors to cache canvas
         self.render_background_sectors_to_context(&cache_ctx, self.width(), self.height(), border, camera_pos, zoom, dark_theme);
 
         // Blit cache to main canvas
-        let _ = self.ctx.draw_image_with_html_canvas_element(&cache_canvas, 0.0, 0.0);
+        self.blit_cache(&cache_canvas);
 
         // Update cache
         *self.bg_cache.borrow_mut() = Some((cache_canvas, zoom, camera_pos.x, camera_pos.y, dark_theme));
@@ -238,9 +486,7 @@ impl Renderer {
             return;
         }
 
-        let sector_count = 5;
-        let sector_names_x = ["A", "B", "C", "D", "E"];
-        let sector_names_y = ["1", "2", "3", "4", "5"];
+        let sector_count = SECTOR_COUNT;
 
         let sector_w = world_width / sector_count as f32;
         let sector_h = world_height / sector_count as f32;
@@ -281,11 +527,8 @@ impl Renderer {
 
         for y in 0..sector_count {
             for x in 0..sector_count {
-                let label = format!("{}{}", sector_names_x[x], sector_names_y[y]);
-                let world_x = min_x + (x as f32 + 0.5) * sector_w;
-                let world_y = min_y + (y as f32 + 0.5) * sector_h;
-                let screen_pos = world_to_screen(Vec2::new(world_x, world_y));
-                ctx.fill_text(&label, snap(screen_pos.x) as f64, snap(screen_pos.y) as f64).ok();
+                let screen_pos = world_to_screen(sector_center(border, x, y));
+                ctx.fill_text(&sector_label(x, y), snap(screen_pos.x) as f64, snap(screen_pos.y) as f64).ok();
             }
         }
     }
@@ -301,10 +544,17 @@ impl Renderer {
         show_mass: bool,
         jelly_physics: bool,
         alpha: f32,
+        relation: CellRelation,
     ) {
         let screen_center = Vec2::new(self.width() / 2.0, self.height() / 2.0);
         let screen_pos = (cell.render_position - camera_pos) * zoom + screen_center;
-        let radius = cell.render_size * zoom; // size IS the visual radius in world units
+        let mut radius = cell.render_size * zoom; // size IS the visual radius in world units
+        if cell.is_agitated {
+            // Boosted cells shiver while in flight; the id offsets the phase
+            // so a split's pieces don't pulse in lockstep
+            let phase = utils::now() * AGITATED_WOBBLE_SPEED + cell.id as f64;
+            radius *= 1.0 + AGITATED_WOBBLE * phase.sin() as f32;
+        }
 
         if radius < 1.0 {
             return; // Too small to see
@@ -312,8 +562,8 @@ impl Renderer {
 
         let (r, g, b) = cell.color;
 
-        // LOD: Skip skins for small cells (< 30px radius)
-        let should_render_skin = skin.is_some() && radius >= 30.0;
+        // LOD: Skip skins for small cells (< 30px radius); only loaded images count
+        let skin = skin.filter(|img| radius >= 30.0 && img.complete() && img.width() > 0);
 
         if cell.is_virus && !(jelly_physics && !cell.points.is_empty()) {
             self.ctx.set_global_alpha(alpha as f64);
@@ -344,31 +594,41 @@ impl Renderer {
                 ).ok();
             }
 
-            // Fill cell body with its colour
-            self.ctx.set_fill_style_str(&format!("rgb({},{},{})", r, g, b));
-            self.ctx.fill();
+            // Fill cell body with its colour; bonus food gets a golden glow
+            if cell.is_bonus {
+                self.ctx.set_shadow_color("rgba(255,215,0,0.9)");
+                self.ctx.set_shadow_blur((radius * 1.5).max(8.0) as f64);
+            }
+            if skin.is_none() || !self.cell_style.skin_only {
+                self.ctx.set_fill_style_str(&format!("rgb({},{},{})", r, g, b));
+                self.ctx.fill();
+            }
+            if cell.is_bonus {
+                self.ctx.set_shadow_blur(0.0);
+            }
 
-            // Overlay skin image, clipped to the circle (only when loaded and large enough)
-            if should_render_skin {
-                if let Some(img) = skin {
-                    // Cache check: only render if image is complete
-                    if img.complete() && img.width() > 0 {
-                        self.ctx.save();
-                        self.ctx.clip(); // clip region = current path (the circle)
-                        // translate + scale so the basic draw_image fills the circle
-                        let _ = self.ctx.translate((screen_pos.x - radius) as f64, (screen_pos.y - radius) as f64);
-                        let scale = (radius * 2.0) as f64 / img.width() as f64;
-                        let _ = self.ctx.scale(scale, scale);
-                        self.ctx.draw_image_with_html_image_element(img, 0.0, 0.0).ok();
-                        self.ctx.restore(); // remove clip + transform; path still intact for stroke
-                    }
-                }
+            // Overlay skin image, clipped to the circle
+            if let Some(img) = skin {
+                self.ctx.save();
+                self.ctx.clip(); // clip region = current path (the circle)
+                // translate + scale so the basic draw_image fills the circle
+                let _ = self.ctx.translate((screen_pos.x - radius) as f64, (screen_pos.y - radius) as f64);
+                let scale = (radius * 2.0) as f64 / img.width() as f64;
+                let _ = self.ctx.scale(scale, scale);
+                self.ctx.draw_image_with_html_image_element(img, 0.0, 0.0).ok();
+                self.ctx.restore(); // remove clip + transform; path still intact for stroke
             }
 
             // Border stroke (path persists through save/restore)
-            self.ctx.set_stroke_style_str("rgba(0,0,0,0.8)");
-            self.ctx.set_line_width(2.0);
-            self.ctx.stroke();
+            if let Some((stroke, width)) = relation.stroke(&self.cell_style) {
+                self.ctx.set_stroke_style_str(stroke);
+                self.ctx.set_line_width(width);
+                self.ctx.stroke();
+            } else if let Some(stroke) = self.cell_style.stroke_color((r, g, b)) {
+                self.ctx.set_stroke_style_str(&stroke);
+                self.ctx.set_line_width(self.cell_style.line_width());
+                self.ctx.stroke();
+            }
             
             // Reset alpha
             self.ctx.set_global_alpha(1.0);
@@ -376,15 +636,139 @@ impl Renderer {
 
         // LOD: Only draw text for cells above 20px radius (names) or 30px (mass)
         if !cell.is_food {
+            let name_size = name_font_size(radius);
             if show_names && radius > 20.0 {
-                self.draw_text_centered(&cell.name, screen_pos, radius, 16.0);
+                self.draw_text_centered(&cell.name, screen_pos, radius * 2.0 * TEXT_FIT_RATIO, name_size);
             }
 
             if show_mass && radius > 30.0 {
                 let mass_text = format!("{:.0}", cell.mass());
-                self.draw_text_centered(&mass_text, screen_pos + Vec2::new(0.0, 16.0), radius, 14.0);
+                let mass_size = font_bucket(name_size * MASS_FONT_RATIO);
+                // Just under the name, however big it is drawn
+                let offset = (name_size + mass_size) * 0.5;
+                self.draw_text_centered(&mass_text, screen_pos + Vec2::new(0.0, offset), radius * 2.0 * TEXT_FIT_RATIO, mass_size);
+            }
+        }
+    }
+
+    /// Draw plain food pellets with one path and one fill per color,
+    /// instead of a full state change per pellet like `draw_cell`.
+    pub fn draw_food_batch(&self, food: &[&Cell], camera_pos: Vec2, zoom: f32) {
+        let screen_center = Vec2::new(self.width() / 2.0, self.height() / 2.0);
+        let mut by_color: HashMap<(u8, u8, u8), Vec<(Vec2, f32)>> = HashMap::new();
+        for cell in food {
+            let radius = cell.render_size * zoom;
+            if radius < 1.0 {
+                continue;
+            }
+            let screen_pos = (cell.render_position - camera_pos) * zoom + screen_center;
+            by_color.entry(cell.color).or_default().push((screen_pos, radius));
+        }
+
+        self.ctx.set_line_width(self.cell_style.line_width());
+        for ((r, g, b), pellets) in by_color {
+            self.ctx.begin_path();
+            for (pos, radius) in pellets {
+                self.ctx.move_to((pos.x + radius) as f64, pos.y as f64);
+                self.ctx.arc(pos.x as f64, pos.y as f64, radius as f64, 0.0, TAU).ok();
+            }
+            self.ctx.set_fill_style_str(&format!("rgb({},{},{})", r, g, b));
+            self.ctx.fill();
+            if let Some(stroke) = self.cell_style.stroke_color((r, g, b)) {
+                self.ctx.set_stroke_style_str(&stroke);
+                self.ctx.stroke();
+            }
+        }
+    }
+
+    /// Thin arc just outside one of my cells showing the fraction of its
+    /// remerge countdown still left, starting at 12 o'clock.
+    pub fn draw_merge_arc(&self, cell: &Cell, camera_pos: Vec2, zoom: f32, remaining: f32) {
+        let screen_center = Vec2::new(self.width() / 2.0, self.height() / 2.0);
+        let screen_pos = (cell.render_position - camera_pos) * zoom + screen_center;
+        let radius = cell.render_size * zoom + 4.0;
+        let start = -std::f64::consts::FRAC_PI_2;
+
+        self.ctx.begin_path();
+        self.ctx.arc(
+            screen_pos.x as f64,
+            screen_pos.y as f64,
+            radius as f64,
+            start,
+            start + TAU * remaining as f64,
+        ).ok();
+        self.ctx.set_stroke_style_str("rgba(255,255,255,0.6)");
+        self.ctx.set_line_width(3.0);
+        self.ctx.stroke();
+    }
+
+    /// Draw a server-sent DrawLine between two world points, at `alpha`.
+    pub fn draw_line(&self, from: Vec2, to: Vec2, camera_pos: Vec2, zoom: f32, alpha: f64, dark_theme: bool) {
+        let screen_center = Vec2::new(self.width() / 2.0, self.height() / 2.0);
+        let from = (from - camera_pos) * zoom + screen_center;
+        let to = (to - camera_pos) * zoom + screen_center;
+        self.ctx.begin_path();
+        self.ctx.move_to(from.x as f64, from.y as f64);
+        self.ctx.line_to(to.x as f64, to.y as f64);
+        self.ctx.set_global_alpha(alpha.clamp(0.0, 1.0));
+        self.ctx.set_stroke_style_str(if dark_theme { "#ffffff" } else { "#000000" });
+        self.ctx.set_line_width(4.0);
+        self.ctx.stroke();
+        self.ctx.set_global_alpha(1.0);
+    }
+
+    /// Debug HUD in the bottom-right corner (above the minimap when it is
+    /// shown): one sparkline each for frame time, bytes received and cells
+    /// drawn, labeled only with their max and min.
+    pub fn draw_perf_hud(&self, perf: &PerfHistory, above_minimap: bool) {
+        const WIDTH: f64 = 180.0;
+        const GRAPH_HEIGHT: f64 = 28.0;
+        const GAP: f64 = 6.0;
+        let series = [
+            (&perf.frame_ms, "#ffd54f"),
+            (&perf.bytes, "#4fc3f7"),
+            (&perf.cells, "#81c784"),
+        ];
+        let height = series.len() as f64 * (GRAPH_HEIGHT + GAP) + GAP;
+        let bottom_margin = if above_minimap { MINIMAP_SIZE as f64 + 16.0 } else { 8.0 };
+        let left = self.width() as f64 - WIDTH - 8.0;
+        let top = self.height() as f64 - bottom_margin - height;
+
+        self.ctx.save();
+        self.ctx.set_fill_style_str("rgba(0,0,0,0.5)");
+        self.ctx.fill_rect(left, top, WIDTH, height);
+        self.ctx.set_font("9px monospace");
+        self.ctx.set_text_align("right");
+        self.ctx.set_line_width(1.0);
+        let graph_width = WIDTH - 2.0 * GAP;
+        let step = graph_width / (PERF_SAMPLES - 1) as f64;
+        for (i, (samples, color)) in series.into_iter().enumerate() {
+            let Some((min, max)) = samples.min_max() else { continue };
+            let graph_top = top + GAP + i as f64 * (GRAPH_HEIGHT + GAP);
+            let range = (max - min).max(f32::EPSILON) as f64;
+            // Newest sample on the right edge
+            let x0 = left + GAP + (PERF_SAMPLES - samples.len()) as f64 * step;
+            self.ctx.begin_path();
+            for (j, value) in samples.iter().enumerate() {
+                let x = x0 + j as f64 * step;
+                let y = graph_top + GRAPH_HEIGHT - (value - min) as f64 / range * GRAPH_HEIGHT;
+                if j == 0 {
+                    self.ctx.move_to(x, y);
+                } else {
+                    self.ctx.line_to(x, y);
+                }
             }
+            self.ctx.set_stroke_style_str(color);
+            self.ctx.stroke();
+
+            self.ctx.set_fill_style_str(color);
+            let label_x = left + WIDTH - GAP;
+            self.ctx.set_text_baseline("top");
+            let _ = self.ctx.fill_text(&format!("{}", max.round()), label_x, graph_top);
+            self.ctx.set_text_baseline("bottom");
+            let _ = self.ctx.fill_text(&format!("{}", min.round()), label_x, graph_top + GRAPH_HEIGHT);
         }
+        self.ctx.restore();
     }
 
     #[inline]
@@ -418,13 +802,32 @@ impl Renderer {
         self.ctx.set_fill_style_str(&format!("rgb({},{},{})", r, g, b));
         self.ctx.fill();
         
-        self.ctx.set_stroke_style_str("rgba(0,0,0,0.8)");
-        self.ctx.set_line_width(2.0);
-        self.ctx.stroke();
+        if let Some(stroke) = self.cell_style.stroke_color(color) {
+            self.ctx.set_stroke_style_str(&stroke);
+            self.ctx.set_line_width(self.cell_style.line_width());
+            self.ctx.stroke();
+        }
     }
 
+    /// Draw outlined text centered on `pos`, shrunk (down to
+    /// `NAME_FONT_MIN`) and then elided to fit `max_width` (at most
+    /// `TEXT_MAX_WIDTH`). Glyphs reaching far above or below the line are
+    /// clipped.
     #[inline]
-    fn draw_text_centered(&self, text: &str, pos: Vec2, _max_width: f32, font_size: f32) {
+    fn draw_text_centered(&self, text: &str, pos: Vec2, max_width: f32, font_size: f32) {
+        if text.is_empty() {
+            return;
+        }
+        let max_width = max_width.min(TEXT_MAX_WIDTH);
+
+        // Text width scales with font size, so one measurement at the
+        // requested size predicts every other size
+        self.ctx.set_font(&format!("bold {}px Arial", font_size));
+        let measure = |t: &str, size: f32| {
+            let width = self.ctx.measure_text(t).map(|m| m.width()).unwrap_or(0.0) as f32;
+            width * size / font_size
+        };
+        let (text, font_size) = fit_text(text, font_size, max_width, measure);
         if text.is_empty() {
             return;
         }
@@ -440,12 +843,52 @@ impl Renderer {
         self.ctx.set_shadow_offset_y(0.0);
         
         self.ctx.set_fill_style_str("white");
-        self.ctx.fill_text(text, pos.x as f64, pos.y as f64).ok();
+        let band = (font_size * TEXT_MAX_HEIGHT_RATIO) as f64;
+        let too_tall = self.ctx.measure_text(&text)
+            .map(|m| m.actual_bounding_box_ascent() + m.actual_bounding_box_descent() > band)
+            .unwrap_or(false);
+        if too_tall {
+            self.ctx.save();
+            self.ctx.begin_path();
+            self.ctx.rect(pos.x as f64 - max_width as f64 / 2.0, pos.y as f64 - band / 2.0, max_width as f64, band);
+            self.ctx.clip();
+            self.ctx.fill_text(&text, pos.x as f64, pos.y as f64).ok();
+            self.ctx.restore();
+        } else {
+            self.ctx.fill_text(&text, pos.x as f64, pos.y as f64).ok();
+        }
         
         // Reset shadow
         self.ctx.set_shadow_blur(0.0);
     }
 
+    /// Draw walls and speed pads as translucent rectangles: walls grey,
+    /// pads green when they speed cells up and orange when they slow them.
+    pub fn draw_map_shapes(&self, shapes: &[MapShape], camera_pos: Vec2, zoom: f32) {
+        if shapes.is_empty() {
+            return;
+        }
+        let screen_center = Vec2::new(self.width() / 2.0, self.height() / 2.0);
+        self.ctx.set_global_alpha(0.35);
+        for shape in shapes {
+            let top_left = (Vec2::new(shape.min_x, shape.min_y) - camera_pos) * zoom + screen_center;
+            let bottom_right = (Vec2::new(shape.max_x, shape.max_y) - camera_pos) * zoom + screen_center;
+            let color = match shape.kind {
+                MapShapeKind::Wall => "#606060",
+                MapShapeKind::SpeedPad if shape.speed >= 1.0 => "#30c050",
+                MapShapeKind::SpeedPad => "#e08020",
+            };
+            self.ctx.set_fill_style_str(color);
+            self.ctx.fill_rect(
+                top_left.x as f64,
+                top_left.y as f64,
+                (bottom_right.x - top_left.x) as f64,
+                (bottom_right.y - top_left.y) as f64,
+            );
+        }
+        self.ctx.set_global_alpha(1.0);
+    }
+
     #[inline]
     pub fn draw_border(&self, border: (f32, f32, f32, f32), camera_pos: Vec2, zoom: f32) {
         let (min_x, min_y, max_x, max_y) = border;
@@ -466,6 +909,88 @@ impl Renderer {
             height as f64,
         );
     }
+
+    /// Draw live particles in world space, fading out over their lifetime.
+    pub fn draw_particles(&self, particles: &Particles, camera_pos: Vec2, zoom: f32, now: f64) {
+        if particles.list.is_empty() {
+            return;
+        }
+        let screen_center = Vec2::new(self.width() / 2.0, self.height() / 2.0);
+        for p in &particles.list {
+            let life = ((now - p.born) / PARTICLE_LIFETIME_MS).clamp(0.0, 1.0);
+            let radius = p.radius * zoom;
+            if radius < 0.5 {
+                continue;
+            }
+            let pos = (p.position - camera_pos) * zoom + screen_center;
+            let (r, g, b) = p.color;
+            self.ctx.set_global_alpha(1.0 - life);
+            self.ctx.set_fill_style_str(&format!("rgb({},{},{})", r, g, b));
+            self.ctx.begin_path();
+            self.ctx.arc(pos.x as f64, pos.y as f64, radius as f64, 0.0, TAU).ok();
+            self.ctx.fill();
+        }
+        self.ctx.set_global_alpha(1.0);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Death particles — short bursts in world coordinates when cells are eaten.
+// ---------------------------------------------------------------------------
+
+/// How long a particle lives, in ms.
+const PARTICLE_LIFETIME_MS: f64 = 400.0;
+/// Hard cap on live particles so eating sprees stay cheap.
+const MAX_PARTICLES: usize = 600;
+
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    radius: f32,
+    color: (u8, u8, u8),
+    born: f64,
+}
+
+pub struct Particles {
+    list: Vec<Particle>,
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    /// Spawn 6–12 particles flying out of a cell of the given size.
+    pub fn burst(&mut self, position: Vec2, size: f32, color: (u8, u8, u8), now: f64) {
+        let count = 6 + (Math::random() * 7.0) as usize;
+        for _ in 0..count {
+            if self.list.len() >= MAX_PARTICLES {
+                break;
+            }
+            let angle = Math::random() as f32 * std::f32::consts::TAU;
+            let speed = size * (1.0 + Math::random() as f32 * 2.0);
+            self.list.push(Particle {
+                position,
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                radius: (size * 0.12).clamp(3.0, 30.0),
+                color,
+                born: now,
+            });
+        }
+    }
+
+    /// Advance particles by `dt` seconds and drop expired ones.
+    pub fn update(&mut self, dt: f32, now: f64) {
+        self.list.retain(|p| now - p.born < PARTICLE_LIFETIME_MS);
+        for p in &mut self.list {
+            p.position += p.velocity * dt;
+            p.velocity *= (-6.0 * dt).exp();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -477,6 +1002,7 @@ const MINIMAP_SIZE: u32 = 150;
 pub struct Minimap {
     ctx: CanvasRenderingContext2d,
     canvas: HtmlCanvasElement,
+    dpr: f64,
     // Static layer cache (background, border, sectors, labels)
     static_cache: RefCell<Option<(HtmlCanvasElement, bool)>>, // (canvas, dark_theme)
 }
@@ -491,19 +1017,30 @@ impl Minimap {
             .get_element_by_id("minimapCanvas")
             .ok_or("minimapCanvas not found")?
             .dyn_into::<HtmlCanvasElement>()?;
-        canvas.set_width(MINIMAP_SIZE);
-        canvas.set_height(MINIMAP_SIZE);
-
         let ctx = canvas
             .get_context("2d")?
             .ok_or("Failed to get minimap 2d context")?
             .dyn_into::<CanvasRenderingContext2d>()?;
 
-        Ok(Self {
+        let mut minimap = Self {
             ctx,
             canvas,
+            dpr: 1.0,
             static_cache: RefCell::new(None),
-        })
+        };
+        minimap.resize();
+        Ok(minimap)
+    }
+
+    /// Match the backing store to the device pixel ratio; the CSS size stays
+    /// `MINIMAP_SIZE` and drawing stays in logical pixels.
+    pub fn resize(&mut self) {
+        self.dpr = utils::device_pixel_ratio();
+        let backing = (MINIMAP_SIZE as f64 * self.dpr).round() as u32;
+        self.canvas.set_width(backing);
+        self.canvas.set_height(backing);
+        let _ = self.ctx.set_transform(self.dpr, 0.0, 0.0, self.dpr, 0.0, 0.0);
+        *self.static_cache.borrow_mut() = None;
     }
 
     /// Draw the minimap.
@@ -547,7 +1084,7 @@ impl Minimap {
 
         // Blit static layer
         if let Some((static_canvas, _)) = self.static_cache.borrow().as_ref() {
-            let _ = self.ctx.draw_image_with_html_canvas_element(static_canvas, 0.0, 0.0);
+            let _ = self.ctx.draw_image_with_html_canvas_element_and_dw_and_dh(static_canvas, 0.0, 0.0, size, size);
         }
 
         // Closure: world pos → minimap pixel pos
@@ -566,14 +1103,12 @@ impl Minimap {
         self.ctx.stroke_rect(vx, vy, vx2 - vx, vy2 - vy);
 
         // --- highlight current sector ---
-        let (mx, my) = map(cam_pos.x as f64, cam_pos.y as f64);
-        let sector_w = size / 5.0;
-        let sector_h = size / 5.0;
-        let sector_x = (mx / sector_w).floor().clamp(0.0, 4.0);
-        let sector_y = (my / sector_h).floor().clamp(0.0, 4.0);
+        let (sector_x, sector_y) = sector_index(border, cam_pos);
+        let sector_w = size / SECTOR_COUNT as f64;
+        let sector_h = size / SECTOR_COUNT as f64;
         self.ctx.set_fill_style_str("yellow");
         self.ctx.set_global_alpha(0.3);
-        self.ctx.fill_rect(sector_x * sector_w, sector_y * sector_h, sector_w, sector_h);
+        self.ctx.fill_rect(sector_x as f64 * sector_w, sector_y as f64 * sector_h, sector_w, sector_h);
         self.ctx.set_global_alpha(1.0);
 
         // --- player cells ---
@@ -646,13 +1181,14 @@ impl Minimap {
             canvas
         };
 
-        static_canvas.set_width(MINIMAP_SIZE);
-        static_canvas.set_height(MINIMAP_SIZE);
+        static_canvas.set_width(self.canvas.width());
+        static_canvas.set_height(self.canvas.height());
 
         let static_ctx = static_canvas
             .get_context("2d").unwrap()
             .unwrap()
             .dyn_into::<CanvasRenderingContext2d>().unwrap();
+        let _ = static_ctx.scale(self.dpr, self.dpr);
 
         // --- background ---
         static_ctx.set_fill_style_str(if dark_theme { "rgba(0,0,0,0.7)" } else { "rgba(255,255,255,0.7)" });
@@ -664,10 +1200,8 @@ impl Minimap {
         static_ctx.stroke_rect(0.0, 0.0, size, size);
 
         // --- sector labels ---
-        let sector_names_x = ["A", "B", "C", "D", "E"];
-        let sector_names_y = ["1", "2", "3", "4", "5"];
-        let sector_w = size / 5.0;
-        let sector_h = size / 5.0;
+        let sector_w = size / SECTOR_COUNT as f64;
+        let sector_h = size / SECTOR_COUNT as f64;
         let sector_font = (sector_w.min(sector_h) / 3.0).max(8.0);
 
         // Sector grid lines
@@ -675,7 +1209,7 @@ impl Minimap {
         static_ctx.set_stroke_style_str(grid_color);
         static_ctx.set_line_width(if dark_theme { 1.5 } else { 1.2 });
         static_ctx.begin_path();
-        for i in 1..5 {
+        for i in 1..SECTOR_COUNT {
             let x = i as f64 * sector_w;
             let y = i as f64 * sector_h;
             static_ctx.move_to(x, 0.0);
@@ -690,12 +1224,11 @@ impl Minimap {
         static_ctx.set_text_baseline("middle");
         static_ctx.set_font(&format!("{}px Ubuntu", sector_font.floor()));
 
-        for x in 0..5 {
-            for y in 0..5 {
-                let label = format!("{}{}", sector_names_x[x], sector_names_y[y]);
+        for x in 0..SECTOR_COUNT {
+            for y in 0..SECTOR_COUNT {
                 let lx = (x as f64 + 0.5) * sector_w;
                 let ly = (y as f64 + 0.5) * sector_h;
-                static_ctx.fill_text(&label, lx, ly).ok();
+                static_ctx.fill_text(&sector_label(x, y), lx, ly).ok();
             }
         }
 
@@ -703,3 +1236,188 @@ impl Minimap {
         *self.static_cache.borrow_mut() = Some((static_canvas, dark_theme));
     }
 }
+
+/// CSS size of the teams pie chart (square).
+const TEAM_PIE_SIZE: u32 = 120;
+
+/// Base color of a team: red, green and blue for up to three teams like
+/// the server's Teams mode, otherwise hues spread evenly around the wheel.
+pub fn team_color(index: usize, count: usize) -> (u8, u8, u8) {
+    if count <= 3 {
+        return match index {
+            0 => (255, 0, 0),
+            1 => (0, 255, 0),
+            _ => (0, 0, 255),
+        };
+    }
+    let hue = index as f32 / count as f32 * 6.0;
+    let x = (1.0 - (hue % 2.0 - 1.0).abs()) * 255.0;
+    let x = x.round() as u8;
+    match hue as u32 {
+        0 => (255, x, 0),
+        1 => (x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, x),
+    }
+}
+
+/// Each team's fraction of the total; all zero when nothing is scored yet.
+pub fn team_shares(team_sizes: &[f32]) -> Vec<f64> {
+    let total: f64 = team_sizes.iter().map(|&s| s.max(0.0) as f64).sum();
+    team_sizes.iter()
+        .map(|&s| if total > 0.0 { s.max(0.0) as f64 / total } else { 0.0 })
+        .collect()
+}
+
+/// Donut chart of team sizes for the Teams leaderboard (0x32).
+pub struct TeamPie {
+    ctx: CanvasRenderingContext2d,
+    canvas: HtmlCanvasElement,
+    dpr: f64,
+}
+
+impl TeamPie {
+    pub fn new() -> Result<Self, JsValue> {
+        let document = web_sys::window()
+            .ok_or("No window")?
+            .document()
+            .ok_or("No document")?;
+        let canvas = document
+            .get_element_by_id("teamPieCanvas")
+            .ok_or("teamPieCanvas not found")?
+            .dyn_into::<HtmlCanvasElement>()?;
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or("Failed to get team pie 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let mut pie = Self { ctx, canvas, dpr: 1.0 };
+        pie.resize();
+        Ok(pie)
+    }
+
+    /// Match the backing store to the device pixel ratio; the CSS size stays
+    /// `TEAM_PIE_SIZE`.
+    pub fn resize(&mut self) {
+        self.dpr = utils::device_pixel_ratio();
+        let backing = (TEAM_PIE_SIZE as f64 * self.dpr).round() as u32;
+        self.canvas.set_width(backing);
+        self.canvas.set_height(backing);
+        let _ = self.ctx.set_transform(self.dpr, 0.0, 0.0, self.dpr, 0.0, 0.0);
+    }
+
+    /// Draw one slice per team, clockwise from the top, with its percentage.
+    pub fn draw(&self, team_sizes: &[f32], dark_theme: bool) {
+        let size = TEAM_PIE_SIZE as f64;
+        let center = size / 2.0;
+        let outer = center - 2.0;
+        let inner = outer * 0.55;
+        let label_radius = (outer + inner) / 2.0;
+        self.ctx.clear_rect(0.0, 0.0, size, size);
+
+        // Empty ring until something is scored
+        self.ctx.begin_path();
+        let _ = self.ctx.arc(center, center, label_radius, 0.0, TAU);
+        self.ctx.set_line_width(outer - inner);
+        self.ctx.set_stroke_style_str(if dark_theme { "rgba(255,255,255,0.15)" } else { "rgba(0,0,0,0.1)" });
+        self.ctx.stroke();
+
+        let shares = team_shares(team_sizes);
+        let mut start = -TAU / 4.0;
+        for (i, &share) in shares.iter().enumerate() {
+            if share <= 0.0 {
+                continue;
+            }
+            let end = start + share * TAU;
+            let (r, g, b) = team_color(i, shares.len());
+            self.ctx.begin_path();
+            let _ = self.ctx.arc(center, center, outer, start, end);
+            let _ = self.ctx.arc_with_anticlockwise(center, center, inner, end, start, true);
+            self.ctx.close_path();
+            self.ctx.set_fill_style_str(&format!("rgb({},{},{})", r, g, b));
+            self.ctx.fill();
+            start = end;
+        }
+
+        // Labels after all slices so none is painted over
+        self.ctx.set_font("bold 11px Ubuntu");
+        self.ctx.set_text_align("center");
+        self.ctx.set_text_baseline("middle");
+        self.ctx.set_line_width(3.0);
+        self.ctx.set_stroke_style_str("rgba(0,0,0,0.7)");
+        self.ctx.set_fill_style_str("#FFF");
+        let mut start = -TAU / 4.0;
+        for &share in &shares {
+            let mid = start + share * TAU / 2.0;
+            start += share * TAU;
+            // Too thin to fit a label
+            if share < 0.06 {
+                continue;
+            }
+            let text = format!("{}%", (share * 100.0).round());
+            let (x, y) = (center + label_radius * mid.cos(), center + label_radius * mid.sin());
+            self.ctx.stroke_text(&text, x, y).ok();
+            self.ctx.fill_text(&text, x, y).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_text_shrinks_then_elides() {
+        // Every character is 0.5em wide
+        let measure = |t: &str, size: f32| t.chars().count() as f32 * size * 0.5;
+
+        assert_eq!(name_font_size(5.0), NAME_FONT_MIN);
+        assert_eq!(name_font_size(10_000.0), NAME_FONT_MAX);
+        assert_eq!(name_font_size(101.0), 40.0);
+
+        // Fits as is
+        assert_eq!(fit_text("abcd", 20.0, 100.0, measure), ("abcd".into(), 20.0));
+        // 10 chars at 20px is 100px; shrink to 16px for 80px
+        assert_eq!(fit_text("abcdefghij", 20.0, 80.0, measure), ("abcdefghij".into(), 16.0));
+        // Would need 6px: elide at the minimum size instead (5 chars at 10px = 25px)
+        assert_eq!(fit_text("abcdefghij", 20.0, 25.0, measure), ("abcd\u{2026}".into(), NAME_FONT_MIN));
+        // Not even the ellipsis fits
+        assert_eq!(fit_text("abc", 20.0, 1.0, measure), ("".into(), NAME_FONT_MIN));
+    }
+
+    #[test]
+    fn test_sector_at_matches_drawn_labels() {
+        for border in [(0.0, 0.0, 14142.0, 14142.0), (-7071.0, -7071.0, 7071.0, 7071.0), (-500.0, 200.0, 9500.0, 4200.0)] {
+            for row in 0..SECTOR_COUNT {
+                for col in 0..SECTOR_COUNT {
+                    let center = sector_center(border, col, row);
+                    assert_eq!(sector_at(border, center), sector_label(col, row));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_team_colors_and_shares() {
+        assert_eq!(team_color(0, 3), (255, 0, 0));
+        assert_eq!(team_color(2, 2), (0, 0, 255));
+        // Eight teams all get distinct hues
+        let colors: HashSet<(u8, u8, u8)> = (0..8).map(|i| team_color(i, 8)).collect();
+        assert_eq!(colors.len(), 8);
+
+        assert_eq!(team_shares(&[1.0, 3.0]), vec![0.25, 0.75]);
+        assert_eq!(team_shares(&[0.0, 0.0, 0.0]), vec![0.0; 3]);
+        assert_eq!(team_shares(&[-1.0, 2.0]), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_sector_at_edges() {
+        let border = (-7071.0, -7071.0, 7071.0, 7071.0);
+        assert_eq!(sector_at(border, Vec2::new(-7071.0, -7071.0)), "A1");
+        assert_eq!(sector_at(border, Vec2::new(7071.0, 7071.0)), "E5");
+        assert_eq!(sector_at(border, Vec2::new(-9000.0, 9000.0)), "A5");
+        assert_eq!(sector_at((0.0, 0.0, 0.0, 0.0), Vec2::ZERO), "A1");
+    }
+}
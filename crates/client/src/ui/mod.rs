@@ -1,11 +1,30 @@
 // DOM manipulation, overlays, menus, chat
-use web_sys::{Document, Element, HtmlInputElement};
+use web_sys::{Document, Element, HtmlButtonElement, HtmlInputElement};
 use wasm_bindgen::{JsCast, JsValue};
 
+use crate::game::RosterEntry;
+use crate::input::HelpSection;
+
 pub struct UI {
     document: Document,
 }
 
+/// What the connection-status banner says.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    /// Whole seconds until the next attempt; 0 while it's in flight.
+    Reconnecting { seconds: u32 },
+    /// No further attempt is scheduled.
+    Failed,
+    /// The host page closed the connection on purpose.
+    Disconnected,
+    /// The server closed the connection with a permanent close code, so no
+    /// reconnect is attempted; holds its reason.
+    Rejected(String),
+}
+
 impl UI {
     pub fn new(document: Document) -> Self {
         Self { document }
@@ -15,24 +34,142 @@ impl UI {
         self.document.get_element_by_id(id)
     }
 
-    /// Update the leaderboard DOM list.
-    pub fn update_leaderboard(&self, entries: &[(bool, String)]) {
+    /// Update the leaderboard DOM list. Other players' rows with a known id
+    /// get a `data-player-id` so a click can pick them to spectate.
+    pub fn update_leaderboard(&self, entries: &[(bool, Option<u32>, String)]) {
         let list = match self.get_el("leaderboardList") {
             Some(el) => el,
             None => return,
         };
         let mut html = String::new();
-        for (is_me, name) in entries {
-            let escaped = html_escape(name);
+        for (is_me, player_id, name) in entries {
+            let escaped = name_html(name);
             if *is_me {
-                html.push_str(&format!("<li class=\"my-1 font-bold text-green-400\">{}</li>", escaped));
+                html.push_str(&format!("<li class=\"my-1 truncate font-bold text-green-400\">{}</li>", escaped));
+            } else if let Some(id) = player_id {
+                html.push_str(&format!(
+                    "<li class=\"my-1 truncate cursor-pointer hover:underline\" data-player-id=\"{}\">{}</li>",
+                    id, escaped
+                ));
             } else {
-                html.push_str(&format!("<li class=\"my-1\">{}</li>", escaped));
+                html.push_str(&format!("<li class=\"my-1 truncate\">{}</li>", escaped));
             }
         }
         list.set_inner_html(&html);
     }
 
+    /// Replace the leaderboard with unnumbered lines of text.
+    pub fn update_leaderboard_text(&self, lines: &[String]) {
+        let Some(list) = self.get_el("leaderboardList") else {
+            return;
+        };
+        let html: String = lines.iter()
+            .map(|line| format!("<li class=\"my-1 lb-text\">{}</li>", html_escape(line)))
+            .collect();
+        list.set_inner_html(&html);
+    }
+
+    pub fn set_team_pie_visible(&self, visible: bool) {
+        if let Some(el) = self.get_el("teamPieCanvas") {
+            if visible {
+                el.class_list().remove_1("hidden").ok();
+            } else {
+                el.class_list().add_1("hidden").ok();
+            }
+        }
+    }
+
+    /// Fill and show the full-roster scoreboard overlay.
+    pub fn show_scoreboard(&self, entries: &[RosterEntry]) {
+        if let Some(title) = self.get_el("scoreboardTitle") {
+            title.set_text_content(Some(&format!("Players ({})", entries.len())));
+        }
+        if let Some(rows) = self.get_el("scoreboardRows") {
+            let mut html = String::new();
+            for (rank, entry) in entries.iter().enumerate() {
+                let (r, g, b) = entry.color;
+                html.push_str(&format!(
+                    "<tr><td class=\"pr-3 theme-muted\">{}</td>\
+                     <td class=\"pr-3\"><span style=\"color:rgb({},{},{})\">\u{25CF}</span> {}{}</td>\
+                     <td class=\"text-right\">{}</td></tr>",
+                    rank + 1,
+                    r, g, b,
+                    name_html(&entry.name),
+                    if entry.is_bot { " <span class=\"theme-muted\">(bot)</span>" } else { "" },
+                    entry.mass,
+                ));
+            }
+            rows.set_inner_html(&html);
+        }
+        if let Some(el) = self.get_el("scoreboard") {
+            el.class_list().remove_1("hidden").ok();
+        }
+    }
+
+    pub fn hide_scoreboard(&self) {
+        if let Some(el) = self.get_el("scoreboard") {
+            el.class_list().add_1("hidden").ok();
+        }
+    }
+
+    /// Fill the help overlay with `sections` and show it.
+    pub fn show_help(&self, sections: &[HelpSection]) {
+        if let Some(container) = self.get_el("helpSections") {
+            container.set_inner_html("");
+            for section in sections {
+                if let Some(el) = self.help_section(section) {
+                    container.append_child(&el).ok();
+                }
+            }
+        }
+        if let Some(el) = self.get_el("helpOverlay") {
+            el.class_list().remove_1("hidden").ok();
+        }
+    }
+
+    /// One section of the help overlay: a heading over a table of keys.
+    fn help_section(&self, section: &HelpSection) -> Option<Element> {
+        let div = self.document.create_element("div").ok()?;
+        let title = self.document.create_element("h3").ok()?;
+        title.set_class_name("mb-1.5 text-[11px] uppercase tracking-wider theme-muted");
+        title.set_text_content(Some(section.title));
+        div.append_child(&title).ok()?;
+
+        let table = self.document.create_element("table").ok()?;
+        table.set_class_name("text-sm theme-text");
+        for entry in &section.entries {
+            let row = self.document.create_element("tr").ok()?;
+            let keys = self.document.create_element("td").ok()?;
+            keys.set_class_name("pr-3 py-0.5 whitespace-nowrap align-top");
+            for (i, key) in entry.keys.iter().enumerate() {
+                if i > 0 {
+                    keys.append_with_str_1(" / ").ok()?;
+                }
+                let kbd = self.document.create_element("kbd").ok()?;
+                kbd.set_text_content(Some(key));
+                keys.append_child(&kbd).ok()?;
+            }
+            let action = self.document.create_element("td").ok()?;
+            action.set_class_name("py-0.5");
+            action.set_text_content(Some(entry.action));
+            row.append_child(&keys).ok()?;
+            row.append_child(&action).ok()?;
+            table.append_child(&row).ok()?;
+        }
+        div.append_child(&table).ok()?;
+        Some(div)
+    }
+
+    pub fn hide_help(&self) {
+        if let Some(el) = self.get_el("helpOverlay") {
+            el.class_list().add_1("hidden").ok();
+        }
+    }
+
+    pub fn help_visible(&self) -> bool {
+        self.get_el("helpOverlay").is_some_and(|el| !el.class_list().contains("hidden"))
+    }
+
     /// Append a single chat message to the chat box and auto-scroll.
     pub fn show_chat_message(&self, name: &str, message: &str, color: (u8, u8, u8)) {
         let chat_box = match self.get_el("chatBox") {
@@ -48,7 +185,7 @@ impl UI {
         div.set_inner_html(&format!(
             "<span class=\"theme-text\"><span style=\"color:rgb({},{},{})\"><b>{}</b></span>: {}</span>",
             r, g, b,
-            html_escape(name),
+            name_html(name),
             html_escape(message),
         ));
         chat_box.append_child(&div).ok();
@@ -69,30 +206,135 @@ impl UI {
         }
     }
 
-    /// Show the login overlay (on death or initial load), pre-filling the nick + skin inputs.
+    /// Show the death screen with the life's final stats, and the cause of
+    /// death when the server sent one. The HUD stays up behind it.
+    pub fn show_death_overlay(&self, cause: Option<&str>, mass: f32, seconds_alive: u64, cells_eaten: u32) {
+        if let Some(el) = self.get_el("deathCause") {
+            match cause {
+                Some(text) => {
+                    el.set_text_content(Some(text));
+                    el.class_list().remove_1("hidden").ok();
+                }
+                None => {
+                    el.class_list().add_1("hidden").ok();
+                }
+            }
+        }
+        if let Some(el) = self.get_el("deathMass") {
+            el.set_text_content(Some(&format!("{:.0}", mass)));
+        }
+        if let Some(el) = self.get_el("deathTimeAlive") {
+            el.set_text_content(Some(&format_uptime(seconds_alive)));
+        }
+        if let Some(el) = self.get_el("deathCellsEaten") {
+            el.set_text_content(Some(&cells_eaten.to_string()));
+        }
+        if let Some(el) = self.get_el("deathOverlay") {
+            el.class_list().remove_1("hidden").ok();
+        }
+    }
+
+    pub fn hide_death_overlay(&self) {
+        if let Some(el) = self.get_el("deathOverlay") {
+            el.class_list().add_1("hidden").ok();
+        }
+    }
+
+    /// Show the login overlay (on disconnect, or "change name" from the death
+    /// screen), pre-filling the nick + skin inputs.
     pub fn show_login_overlay(&self, nick: &str, skin: Option<&str>) {
-        // Unhide overlay (remove only "hidden"; preserve all layout classes)
+        self.hide_death_overlay();
+        // Unhide overlay, putting back the layout classes main.js strips when hiding it
         if let Some(overlay) = self.get_el("loginOverlay") {
-            overlay.class_list().remove(&js_sys::Array::of1(&JsValue::from("hidden"))).ok();
+            let classes = overlay.class_list();
+            classes.remove_1("hidden").ok();
+            classes.add_4("flex", "flex-col", "items-center", "justify-center").ok();
+        }
+        if let Some(body) = self.document.body() {
+            body.class_list().add_1("overlay-visible").ok();
+        }
+        self.prefill_login(nick, skin);
+        // Hide game HUD (add "hidden"; preserve all layout classes)
+        for id in &["stats", "leaderboard", "instructions", "chatBox", "chatInputRow", "minimapCanvas", "hudHelpButton"] {
+            if let Some(el) = self.get_el(id) {
+                el.class_list().add(&js_sys::Array::of1(&JsValue::from("hidden"))).ok();
+            }
+        }
+    }
+
+    /// Show the connection-status banner, or hide it with `None`.
+    pub fn set_connection_status(&self, status: Option<&ConnectionStatus>) {
+        let banner = match self.get_el("connectionBanner") {
+            Some(el) => el,
+            None => return,
+        };
+        let Some(status) = status else {
+            banner.class_list().add_1("hidden").ok();
+            return;
+        };
+        let (state, text) = match status {
+            ConnectionStatus::Connecting => ("waiting", "Connecting...".to_string()),
+            ConnectionStatus::Connected => ("connected", "Connected".to_string()),
+            ConnectionStatus::Reconnecting { seconds: 0 } => ("waiting", "Reconnecting...".to_string()),
+            ConnectionStatus::Reconnecting { seconds } => ("waiting", format!("Reconnecting in {}s", seconds)),
+            ConnectionStatus::Failed => ("failed", "Connection failed - reload to retry".to_string()),
+            ConnectionStatus::Disconnected => ("failed", "Disconnected".to_string()),
+            ConnectionStatus::Rejected(reason) => ("failed", format!("Disconnected by server: {}", reason)),
+        };
+        banner.set_attribute("data-status", state).ok();
+        banner.set_text_content(Some(&text));
+        banner.class_list().remove_1("hidden").ok();
+    }
+
+    /// Enable or disable the login overlay's play button and the death
+    /// screen's respawn button, so spawns can't be queued while there is no
+    /// connection to send them on.
+    pub fn set_spawn_enabled(&self, enabled: bool) {
+        if let Some(button) = self.get_el("playButton")
+            && let Ok(button) = button.dyn_into::<HtmlButtonElement>()
+        {
+            button.set_disabled(!enabled);
+            button.set_text_content(Some(if enabled { "Play" } else { "Connecting..." }));
         }
-        // Pre-fill nick
+        if let Some(button) = self.get_el("respawnButton")
+            && let Ok(button) = button.dyn_into::<HtmlButtonElement>()
+        {
+            button.set_disabled(!enabled);
+        }
+    }
+
+    /// Inject the on-screen split / eject buttons used on touch devices.
+    /// Returns `(split, eject)`, or `None` if they couldn't be created.
+    pub fn create_touch_buttons(&self) -> Option<(Element, Element)> {
+        let body = self.document.body()?;
+        let container = self.document.create_element("div").ok()?;
+        container.set_class_name("fixed bottom-6 right-6 z-40 flex gap-4 select-none");
+
+        let split = self.document.create_element("button").ok()?;
+        split.set_class_name("w-16 h-16 rounded-full border theme-control text-sm font-bold");
+        split.set_text_content(Some("Split"));
+        let eject = self.document.create_element("button").ok()?;
+        eject.set_class_name("w-16 h-16 rounded-full border theme-control text-sm font-bold");
+        eject.set_text_content(Some("Eject"));
+
+        container.append_child(&eject).ok()?;
+        container.append_child(&split).ok()?;
+        body.append_child(&container).ok()?;
+        Some((split, eject))
+    }
+
+    /// Pre-fill the nick + skin inputs of the login overlay.
+    pub fn prefill_login(&self, nick: &str, skin: Option<&str>) {
         if let Some(input) = self.get_el("nickInput") {
             if let Ok(input) = input.dyn_into::<HtmlInputElement>() {
                 input.set_value(nick);
             }
         }
-        // Pre-fill skin
         if let Some(input) = self.get_el("skinInput") {
             if let Ok(input) = input.dyn_into::<HtmlInputElement>() {
                 input.set_value(skin.unwrap_or(""));
             }
         }
-        // Hide game HUD (add "hidden"; preserve all layout classes)
-        for id in &["stats", "leaderboard", "instructions", "chatBox", "chatInputRow", "minimapCanvas"] {
-            if let Some(el) = self.get_el(id) {
-                el.class_list().add(&js_sys::Array::of1(&JsValue::from("hidden"))).ok();
-            }
-        }
     }
 
     /// Focus the chat input field
@@ -142,7 +384,8 @@ impl UI {
                 let load = update_val * 2.5;
                 // Format uptime
                 let uptime_str = format_uptime(stats.uptime);
-                el.set_inner_html(&format!("{:.1}% load @ {}", load, uptime_str));
+                let overloaded = if stats.overloaded { " (overloaded)" } else { "" };
+                el.set_inner_html(&format!("{:.1}% load{} @ {}", load, overloaded, uptime_str));
             }
         }
 
@@ -170,6 +413,12 @@ fn format_uptime(total_seconds: u64) -> String {
     }
 }
 
+/// A player name for inline HTML, isolated so right-to-left text can't
+/// reorder what follows it.
+fn name_html(name: &str) -> String {
+    format!("<bdi>{}</bdi>", html_escape(name))
+}
+
 /// Escape HTML special characters to prevent XSS from server-supplied strings.
 fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -14,6 +14,43 @@ pub fn now() -> f64 {
         .now()
 }
 
+/// Device pixels per CSS pixel (2 on most HiDPI screens, 1 if unknown).
+pub fn device_pixel_ratio() -> f64 {
+    web_sys::window()
+        .map(|w| w.device_pixel_ratio())
+        .filter(|ratio| *ratio > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Read a value from `localStorage`, if storage is available and the key is set.
+pub fn load_item(key: &str) -> Option<String> {
+    web_sys::window()?
+        .local_storage()
+        .ok()??
+        .get_item(key)
+        .ok()?
+}
+
+/// Write a value to `localStorage`, silently ignoring unavailable storage.
+pub fn save_item(key: &str, value: &str) {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.set_item(key, value);
+    }
+}
+
+/// Load a JSON value from `localStorage`. Missing, unreadable or corrupted
+/// entries all yield `None` so callers can fall back to defaults.
+pub fn load_json<T: serde::de::DeserializeOwned>(key: &str) -> Option<T> {
+    serde_json::from_str(&load_item(key)?).ok()
+}
+
+/// Store a value as JSON in `localStorage`.
+pub fn save_json<T: serde::Serialize>(key: &str, value: &T) {
+    if let Ok(json) = serde_json::to_string(value) {
+        save_item(key, &json);
+    }
+}
+
 /// Log to browser console
 #[macro_export]
 macro_rules! console_log {
@@ -7,6 +7,8 @@ import init, { GameClientWrapper } from './pkg/client.js';
 let gameClient = null;
 let availableServers = [];
 let selectedServerUrl = null;
+// Only cogar servers are sent cogar's own protocol level
+let selectedServerCogar = false;
 let availableSkins = [];
 let selectedSkin = '';
 
@@ -14,21 +16,33 @@ let selectedSkin = '';
 function getAvailableServers() {
     // Check if cogar injected a direct connection
     if (window.COGAR_CONNECTION) {
-        return [{ url: normalizeUrl(window.COGAR_CONNECTION), name: 'Current Server' }];
+        return [{ url: withToken(normalizeUrl(window.COGAR_CONNECTION)), name: 'Current Server', cogar: true }];
     }
     
     // Check if cigar injected connection options
     if (window.CIGAR_CONNECTIONS && Array.isArray(window.CIGAR_CONNECTIONS) && window.CIGAR_CONNECTIONS.length > 0) {
         return window.CIGAR_CONNECTIONS.map(conn => ({
             url: normalizeUrl(conn.url),
-            name: conn.name || conn.url
+            name: conn.name || conn.url,
+            cogar: conn.cogar === true
         }));
     }
     
     // Fallback to query params or default
     const params = new URLSearchParams(window.location.search);
     const serverUrl = params.get('server') || '/game';
-    return [{ url: normalizeUrl(serverUrl), name: serverUrl }];
+    // The default route is cogar's; ?server= may point anywhere
+    return [{ url: withToken(normalizeUrl(serverUrl)), name: serverUrl, cogar: !params.get('server') }];
+}
+
+// Carry the page's ?token= over to the socket URL for private servers
+// (server.connect_token), unless the URL already has one
+function withToken(url) {
+    const token = new URLSearchParams(window.location.search).get('token');
+    if (!token || /[?&]token=/.test(url)) {
+        return url;
+    }
+    return `${url}${url.includes('?') ? '&' : '?'}token=${encodeURIComponent(token)}`;
 }
 
 // Normalize URL format for WebSocket connection
@@ -65,6 +79,7 @@ function showServerSelection() {
         button.addEventListener('click', () => {
             console.log('Server selected:', server.name, server.url);
             selectedServerUrl = server.url;
+            selectedServerCogar = server.cogar;
             hideServerSelection();
             showLoginOverlay();
             initializeGameClient();
@@ -254,7 +269,7 @@ async function initializeGameClient() {
         console.log('Connecting to server:', selectedServerUrl);
 
         // Create the game client immediately to establish WebSocket connection
-        gameClient = new GameClientWrapper('gameCanvas', selectedServerUrl);
+        gameClient = new GameClientWrapper('gameCanvas', selectedServerUrl, selectedServerCogar);
 
         await waitForWebSocketOpen(() => gameClient?.websocket?.(), 10000);
 
@@ -267,62 +282,6 @@ async function initializeGameClient() {
     }
 }
 
-// Death detection - shows overlay when player dies
-let deathCheckInterval = null;
-let wasAlive = false;
-
-function startDeathDetection() {
-    // Clear any existing interval
-    if (deathCheckInterval) {
-        clearInterval(deathCheckInterval);
-        deathCheckInterval = null;
-    }
-
-    wasAlive = false;
-
-    deathCheckInterval = setInterval(() => {
-        try {
-            if (!gameClient) {
-                return;
-            }
-
-            let cellCount;
-            try {
-                cellCount = gameClient.cell_count();
-            } catch (wasmError) {
-                throw wasmError;
-            }
-            
-            const isAlive = cellCount > 0;
-
-            // Player just spawned
-            if (isAlive && !wasAlive) {
-                wasAlive = true;
-            }
-
-            // Player just died (was alive, now dead)
-            if (!isAlive && wasAlive) {
-                wasAlive = false;
-
-                // Show the login overlay for respawn
-                const loginOverlay = document.getElementById('loginOverlay');
-                const stats = document.getElementById('stats');
-                const leaderboard = document.getElementById('leaderboard');
-
-                loginOverlay.classList.remove('hidden');
-                loginOverlay.classList.add('flex', 'flex-col', 'items-center', 'justify-center');
-                document.body.classList.add('overlay-visible');
-                stats.classList.add('hidden');
-                leaderboard.classList.add('hidden');
-                // Keep chatBox visible so they can see death messages
-            }
-        } catch (error) {
-            // Re-throw to see if this kills the interval
-            throw error;
-        }
-    }, 500); // Check every 500ms
-}
-
 async function run() {
     try {
         // Initialize WASM
@@ -356,6 +315,7 @@ async function run() {
         } else {
             // Single server, connect directly
             selectedServerUrl = availableServers[0].url;
+            selectedServerCogar = availableServers[0].cogar;
             showLoginOverlay(); // Ensure login is visible
             initializeGameClient();
         }
@@ -382,6 +342,7 @@ async function run() {
         const leaderboard = document.getElementById('leaderboard');
         const chatBox = document.getElementById('chatBox');
         const minimapCanvas = document.getElementById('minimapCanvas');
+        const hudHelpButton = document.getElementById('hudHelpButton');
         const showMinimap = document.getElementById('settingShowMinimap');
 
         playButton.addEventListener('click', async () => {
@@ -397,7 +358,7 @@ async function run() {
 
                 // If game client doesn't exist yet (multi-server case), create it
                 if (!gameClient) {
-                    gameClient = new GameClientWrapper('gameCanvas', selectedServerUrl);
+                    gameClient = new GameClientWrapper('gameCanvas', selectedServerUrl, selectedServerCogar);
                 }
 
                 await waitForWebSocketOpen(() => gameClient?.websocket?.(), 5000);
@@ -412,6 +373,7 @@ async function run() {
                 stats.classList.remove('hidden');
                 leaderboard.classList.remove('hidden');
                 chatBox.classList.remove('hidden');
+                hudHelpButton.classList.remove('hidden');
                 if (showMinimap?.checked) {
                     minimapCanvas.classList.remove('hidden');
                 }
@@ -419,9 +381,6 @@ async function run() {
                 // Reset button for respawn
                 playButton.disabled = false;
                 playButton.textContent = 'Play';
-
-                // Start death detection - check if player died and show overlay
-                startDeathDetection();
             } catch (error) {
                 console.error('Failed to start game:', error);
                 alert('Failed to connect to server: ' + error.message);
@@ -442,15 +401,6 @@ async function run() {
             }
         });
 
-        // Help popup toggle
-        const helpBtn = document.getElementById('helpBtn');
-        const helpPopup = document.getElementById('helpPopup');
-        helpBtn.addEventListener('click', (e) => {
-            e.stopPropagation();
-            const isOpen = helpPopup.dataset.open === 'true';
-            helpPopup.dataset.open = isOpen ? 'false' : 'true';
-        });
-
         // Settings popup toggle
         const settingsBtn = document.getElementById('settingsBtn');
         const settingsPopup = document.getElementById('settingsPopup');
@@ -460,11 +410,8 @@ async function run() {
             settingsPopup.dataset.open = isOpen ? 'false' : 'true';
         });
 
-        // Close popups when clicking outside
+        // Close the settings popup when clicking outside
         document.addEventListener('click', (e) => {
-            if (!helpPopup.contains(e.target) && e.target !== helpBtn) {
-                helpPopup.dataset.open = 'false';
-            }
             if (!settingsPopup.contains(e.target) && e.target !== settingsBtn) {
                 settingsPopup.dataset.open = 'false';
             }
@@ -499,6 +446,7 @@ async function run() {
             const chatInputRow = document.getElementById('chatInputRow');
             const minimapCanvas = document.getElementById('minimapCanvas');
 
+            document.getElementById('deathOverlay').classList.add('hidden');
             loginOverlay.classList.remove('hidden');
             loginOverlay.classList.add('flex', 'flex-col', 'items-center', 'justify-center');
             document.body.classList.add('overlay-visible');
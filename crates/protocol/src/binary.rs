@@ -1,19 +1,33 @@
 //! Binary reading and writing utilities for the Ogar protocol.
 //!
-//! All values are little-endian.
+//! Unsuffixed methods are little-endian, the Ogar wire order; `_be` variants
+//! exist for big-endian fields.
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
+use crate::ProtocolError;
+
+/// Longest string, in bytes, a reader accepts unless told otherwise.
+pub const DEFAULT_MAX_STRING_BYTES: usize = 16 * 1024;
+
 /// A reader for parsing binary protocol messages.
 #[derive(Debug)]
 pub struct BinaryReader {
     buf: Bytes,
+    /// Strings longer than this are rejected by the `try_get_string_*` methods.
+    max_string_bytes: usize,
 }
 
 impl BinaryReader {
     /// Create a new reader from raw bytes.
     pub fn new(data: impl Into<Bytes>) -> Self {
-        Self { buf: data.into() }
+        Self { buf: data.into(), max_string_bytes: DEFAULT_MAX_STRING_BYTES }
+    }
+
+    /// Reject strings longer than `max` bytes instead of the default limit.
+    pub fn with_max_string_bytes(mut self, max: usize) -> Self {
+        self.max_string_bytes = max;
+        self
     }
 
     /// Returns remaining bytes.
@@ -67,6 +81,16 @@ impl BinaryReader {
     pub fn get_i16(&mut self) -> i16 {
         self.buf.get_i16_le()
     }
+    
+    /// Safe version that returns None if not enough data
+    #[inline]
+    pub fn try_get_i16(&mut self) -> Option<i16> {
+        if self.buf.remaining() >= 2 {
+            Some(self.buf.get_i16_le())
+        } else {
+            None
+        }
+    }
 
     #[inline]
     pub fn get_u32(&mut self) -> u32 {
@@ -83,6 +107,16 @@ impl BinaryReader {
         }
     }
 
+    /// The next `n` bytes, or None if not enough data.
+    #[inline]
+    pub fn try_get_bytes(&mut self, n: usize) -> Option<Bytes> {
+        if self.buf.remaining() >= n {
+            Some(self.buf.split_to(n))
+        } else {
+            None
+        }
+    }
+
     #[inline]
     pub fn get_i32(&mut self) -> i32 {
         self.buf.get_i32_le()
@@ -128,7 +162,30 @@ impl BinaryReader {
         }
     }
 
-    /// Read a null-terminated UTF-8 string.
+    #[inline]
+    pub fn try_get_u16_be(&mut self) -> Option<u16> {
+        (self.buf.remaining() >= 2).then(|| self.buf.get_u16())
+    }
+
+    #[inline]
+    pub fn try_get_u32_be(&mut self) -> Option<u32> {
+        (self.buf.remaining() >= 4).then(|| self.buf.get_u32())
+    }
+
+    #[inline]
+    pub fn try_get_i32_be(&mut self) -> Option<i32> {
+        (self.buf.remaining() >= 4).then(|| self.buf.get_i32())
+    }
+
+    #[inline]
+    pub fn try_get_f32_be(&mut self) -> Option<f32> {
+        (self.buf.remaining() >= 4).then(|| self.buf.get_f32())
+    }
+
+    /// Read a null-terminated UTF-8 string. Never fails: truncation ends the
+    /// string and invalid bytes are replaced. Prefer
+    /// [`try_get_string_utf8_null`](Self::try_get_string_utf8_null) for
+    /// untrusted input.
     pub fn get_string_utf8(&mut self) -> String {
         let mut bytes = Vec::new();
         while self.buf.has_remaining() {
@@ -141,6 +198,63 @@ impl BinaryReader {
         String::from_utf8_lossy(&bytes).into_owned()
     }
 
+    /// Read a null-terminated UTF-8 string, checked. Like MultiOgar, the end
+    /// of the buffer also ends the string, since stock clients often leave the
+    /// final NUL off; a truncated field therefore shows up on the next read.
+    pub fn try_get_string_utf8_null(&mut self) -> Result<String, ProtocolError> {
+        let len = self.scan_string(1)?;
+        let s = std::str::from_utf8(&self.buf[..len])
+            .map_err(|_| ProtocolError::InvalidString)?
+            .to_string();
+        self.buf.advance((len + 1).min(self.buf.remaining()));
+        Ok(s)
+    }
+
+    /// Read a UTF-8 string prefixed with its length as a u16.
+    pub fn try_get_string_utf8_len(&mut self) -> Result<String, ProtocolError> {
+        let len = self.try_get_u16().ok_or(ProtocolError::UnexpectedEof)? as usize;
+        if len > self.max_string_bytes {
+            return Err(ProtocolError::StringTooLong { len, max: self.max_string_bytes });
+        }
+        if self.buf.remaining() < len {
+            return Err(ProtocolError::UnexpectedEof);
+        }
+        let s = std::str::from_utf8(&self.buf[..len])
+            .map_err(|_| ProtocolError::InvalidString)?
+            .to_string();
+        self.buf.advance(len);
+        Ok(s)
+    }
+
+    /// Read a null-terminated UTF-16 (UCS-2) string, checked. The end of the
+    /// buffer also ends it, as with [`try_get_string_utf8_null`](Self::try_get_string_utf8_null).
+    pub fn try_get_string_unicode_null(&mut self) -> Result<String, ProtocolError> {
+        let len = self.scan_string(2)?;
+        let units: Vec<u16> = self.buf[..len]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let s = String::from_utf16(&units).map_err(|_| ProtocolError::InvalidString)?;
+        self.buf.advance((len + 2).min(self.buf.remaining() & !1));
+        Ok(s)
+    }
+
+    /// Byte length of the string at the cursor, up to its terminator of
+    /// `unit` zero bytes, without scanning past the string limit.
+    fn scan_string(&self, unit: usize) -> Result<usize, ProtocolError> {
+        let max = self.max_string_bytes;
+        let available = self.buf.remaining() - self.buf.remaining() % unit;
+        let scan = available.min(max + unit);
+        let end = self.buf[..scan]
+            .chunks_exact(unit)
+            .position(|c| c.iter().all(|&b| b == 0))
+            .map_or(scan, |i| i * unit);
+        if end > max {
+            return Err(ProtocolError::StringTooLong { len: end, max });
+        }
+        Ok(end)
+    }
+
     /// Read a null-terminated UTF-16 (UCS-2) string.
     pub fn get_string_unicode(&mut self) -> String {
         let mut chars = Vec::new();
@@ -226,12 +340,43 @@ impl BinaryWriter {
         self.buf.put_f64_le(v);
     }
 
+    #[inline]
+    pub fn put_u16_be(&mut self, v: u16) {
+        self.buf.put_u16(v);
+    }
+
+    #[inline]
+    pub fn put_u32_be(&mut self, v: u32) {
+        self.buf.put_u32(v);
+    }
+
+    #[inline]
+    pub fn put_i32_be(&mut self, v: i32) {
+        self.buf.put_i32(v);
+    }
+
+    #[inline]
+    pub fn put_f32_be(&mut self, v: f32) {
+        self.buf.put_f32(v);
+    }
+
     /// Write a null-terminated UTF-8 string.
-    pub fn put_string_utf8(&mut self, s: &str) {
+    pub fn put_string_utf8_null(&mut self, s: &str) {
         self.buf.put_slice(s.as_bytes());
         self.buf.put_u8(0);
     }
 
+    /// Write a UTF-8 string prefixed with its length as a u16. Longer strings
+    /// are cut at the last character boundary that fits.
+    pub fn put_string_utf8_len(&mut self, s: &str) {
+        let mut len = s.len().min(u16::MAX as usize);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf.put_u16_le(len as u16);
+        self.buf.put_slice(&s.as_bytes()[..len]);
+    }
+
     /// Write a null-terminated UTF-16 (UCS-2) string.
     pub fn put_string_unicode(&mut self, s: &str) {
         for c in s.encode_utf16() {
@@ -272,9 +417,36 @@ mod tests {
     #[test]
     fn test_string_utf8() {
         let mut w = BinaryWriter::new();
-        w.put_string_utf8("hello");
+        w.put_string_utf8_null("hello");
         let data = w.finish();
         let mut r = BinaryReader::new(data);
         assert_eq!(r.get_string_utf8(), "hello");
     }
+
+    #[test]
+    fn test_checked_strings() {
+        let mut w = BinaryWriter::new();
+        w.put_string_utf8_null("héllo");
+        w.put_string_utf8_len("wörld");
+        w.put_string_unicode("ünï");
+        w.put_u32_be(7);
+        let mut r = BinaryReader::new(w.finish());
+        assert_eq!(r.try_get_string_utf8_null().unwrap(), "héllo");
+        assert_eq!(r.try_get_string_utf8_len().unwrap(), "wörld");
+        assert_eq!(r.try_get_string_unicode_null().unwrap(), "ünï");
+        assert_eq!(r.try_get_u32_be(), Some(7));
+        assert_eq!(r.remaining(), 0);
+
+        // A missing final NUL ends the string at the end of the buffer
+        let mut r = BinaryReader::new(&b"abc"[..]);
+        assert_eq!(r.try_get_string_utf8_null().unwrap(), "abc");
+
+        // Truncated length-prefixed string, oversized and invalid strings
+        let mut r = BinaryReader::new(&[5u8, 0, b'a'][..]);
+        assert!(matches!(r.try_get_string_utf8_len(), Err(ProtocolError::UnexpectedEof)));
+        let mut r = BinaryReader::new(vec![b'x'; 100]).with_max_string_bytes(10);
+        assert!(matches!(r.try_get_string_utf8_null(), Err(ProtocolError::StringTooLong { max: 10, .. })));
+        let mut r = BinaryReader::new(&[0xFFu8, 0xFE, 0][..]);
+        assert!(matches!(r.try_get_string_utf8_null(), Err(ProtocolError::InvalidString)));
+    }
 }
@@ -0,0 +1,54 @@
+//! WebSocket close codes the server ends a connection with, from the
+//! 4000-4999 range reserved for applications.
+
+/// Why the server closed a connection.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCode {
+    /// Removed by an operator's `/kick`.
+    Kicked = 4000,
+    /// Sent no input for too long.
+    Idle = 4001,
+    /// Sent packets faster than the rate limit allows.
+    PacketFlood = 4002,
+    /// Asked for a protocol version the server doesn't speak.
+    UnsupportedProtocol = 4003,
+    /// Sent a handshake key the server doesn't accept.
+    InvalidHandshakeKey = 4004,
+    /// Connected from a banned address.
+    Banned = 4005,
+}
+
+impl CloseCode {
+    /// The code for a close frame received from a server; `None` for the
+    /// standard codes and any this side doesn't know.
+    pub fn from_u16(code: u16) -> Option<Self> {
+        match code {
+            4000 => Some(CloseCode::Kicked),
+            4001 => Some(CloseCode::Idle),
+            4002 => Some(CloseCode::PacketFlood),
+            4003 => Some(CloseCode::UnsupportedProtocol),
+            4004 => Some(CloseCode::InvalidHandshakeKey),
+            4005 => Some(CloseCode::Banned),
+            _ => None,
+        }
+    }
+
+    /// Whether reconnecting would only be refused or undo the server's
+    /// decision, so a client should stop retrying.
+    pub fn is_permanent(self) -> bool {
+        !matches!(self, CloseCode::PacketFlood)
+    }
+
+    /// Fallback text for a close frame that came without a reason.
+    pub fn description(self) -> &'static str {
+        match self {
+            CloseCode::Kicked => "kicked",
+            CloseCode::Idle => "idle for too long",
+            CloseCode::PacketFlood => "packet flood",
+            CloseCode::UnsupportedProtocol => "unsupported protocol",
+            CloseCode::InvalidHandshakeKey => "invalid handshake key",
+            CloseCode::Banned => "banned",
+        }
+    }
+}
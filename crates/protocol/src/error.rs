@@ -16,4 +16,10 @@ pub enum ProtocolError {
 
     #[error("Invalid handshake key")]
     InvalidHandshakeKey,
+
+    #[error("String of {len} bytes exceeds the {max} byte limit")]
+    StringTooLong { len: usize, max: usize },
+
+    #[error("String is not valid UTF-8/UTF-16")]
+    InvalidString,
 }
@@ -6,10 +6,12 @@
 //! - Shared types (Color, Position, etc.)
 
 mod binary;
+mod close;
 mod error;
 pub mod packets;
 
-pub use binary::{BinaryReader, BinaryWriter};
+pub use binary::{BinaryReader, BinaryWriter, DEFAULT_MAX_STRING_BYTES};
+pub use close::CloseCode;
 pub use error::ProtocolError;
 
 /// RGB color used for cells.
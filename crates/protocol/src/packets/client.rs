@@ -1,6 +1,30 @@
-//! Client -> Server packet parsing.
+//! Client -> Server packet parsing, and building for native clients.
+//!
+//! Client packets are small: the largest legitimate one is a Join or Chat
+//! carrying a [`MAX_CLIENT_STRING_BYTES`] string. Servers should drop frames
+//! larger than [`DEFAULT_MAX_PACKET_BYTES`] (or their configured limit)
+//! before handing them to [`ClientPacket::parse`]. Parsing itself never
+//! panics on malformed input; short or garbled packets come back as errors.
 
-use crate::{BinaryReader, ProtocolError};
+use super::ClientOpcode;
+use crate::{BinaryReader, BinaryWriter, Color, ProtocolError};
+
+/// Longest name or chat message accepted from a client, in bytes.
+pub const MAX_CLIENT_STRING_BYTES: usize = 1024;
+
+/// Default cap on an accepted client frame, in bytes.
+pub const DEFAULT_MAX_PACKET_BYTES: usize = 2048;
+
+/// What color a player's ejected mass should be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EjectColor {
+    /// The color of the cell it came from.
+    #[default]
+    Cell,
+    Solid(Color),
+    /// A new hue for every pellet.
+    Rainbow,
+}
 
 /// Parsed client packet.
 #[derive(Debug, Clone)]
@@ -13,6 +37,8 @@ pub enum ClientPacket {
     Join { name: String },
     /// Spectate mode (0x01).
     Spectate,
+    /// Spectate a specific player (0x02), by leaderboard player id.
+    SpectateTarget { player_id: u32 },
     /// Mouse position (0x10).
     Mouse { x: i32, y: i32 },
     /// Split (0x11).
@@ -29,6 +55,13 @@ pub enum ClientPacket {
     KeyT,
     /// P key (0x19).
     KeyP,
+    /// Canvas size in CSS pixels (0x1A), sent on connect and resize. A cogar
+    /// extension; stock clients never send it.
+    Viewport { width: u16, height: u16 },
+    /// Ejected-mass color (0x1B): a mode byte (0 cell, 1 solid, 2 rainbow)
+    /// and an RGB color that only solid uses. A cogar extension; stock
+    /// clients never send it.
+    EjectColor(EjectColor),
     /// Chat message (0x63).
     Chat { flags: u8, message: String },
     /// Stats request (0xFE with len=1).
@@ -44,8 +77,8 @@ impl ClientPacket {
             return Err(ProtocolError::UnexpectedEof);
         }
 
-        let mut reader = BinaryReader::new(data.to_vec());
-        let opcode = reader.get_u8();
+        let mut reader = BinaryReader::new(data.to_vec()).with_max_string_bytes(MAX_CLIENT_STRING_BYTES);
+        let opcode = reader.try_get_u8().ok_or(ProtocolError::UnexpectedEof)?;
 
         match opcode {
             0xFE => {
@@ -54,7 +87,7 @@ impl ClientPacket {
                     Ok(ClientPacket::StatsRequest)
                 } else if data.len() == 5 {
                     // Protocol version
-                    let version = reader.get_u32();
+                    let version = reader.try_get_u32().ok_or(ProtocolError::UnexpectedEof)?;
                     Ok(ClientPacket::Protocol(version))
                 } else {
                     Err(ProtocolError::InvalidOpcode(opcode))
@@ -64,39 +97,38 @@ impl ClientPacket {
                 if data.len() != 5 {
                     return Err(ProtocolError::InvalidHandshakeKey);
                 }
-                let key = reader.get_u32();
+                let key = reader.try_get_u32().ok_or(ProtocolError::InvalidHandshakeKey)?;
                 Ok(ClientPacket::HandshakeKey(key))
             }
             0x00 => {
                 // Join
                 let name = if protocol > 6 {
-                    reader.get_string_unicode()
+                    reader.try_get_string_unicode_null()?
                 } else {
-                    reader.get_string_utf8()
+                    reader.try_get_string_utf8_null()?
                 };
                 Ok(ClientPacket::Join { name })
             }
             0x01 => Ok(ClientPacket::Spectate),
+            0x02 => {
+                let player_id = reader.try_get_u32().ok_or(ProtocolError::UnexpectedEof)?;
+                Ok(ClientPacket::SpectateTarget { player_id })
+            }
             0x10 => {
                 // Mouse - supports multiple formats
                 let (x, y) = match data.len() {
-                    13 => {
-                        let x = reader.get_i32();
-                        let y = reader.get_i32();
-                        (x, y)
-                    }
-                    9 => {
-                        let x = reader.get_i16() as i32;
-                        let y = reader.get_i16() as i32;
-                        (x, y)
-                    }
-                    21 => {
-                        let x = reader.get_f64() as i32;
-                        let y = reader.get_f64() as i32;
-                        (x, y)
-                    }
+                    13 => (reader.try_get_i32(), reader.try_get_i32()),
+                    9 => (reader.try_get_i16().map(i32::from), reader.try_get_i16().map(i32::from)),
+                    // Float casts saturate, and NaN becomes 0
+                    21 => (
+                        reader.try_get_f64().map(|v| v as i32),
+                        reader.try_get_f64().map(|v| v as i32),
+                    ),
                     _ => return Err(ProtocolError::InvalidOpcode(opcode)),
                 };
+                let (Some(x), Some(y)) = (x, y) else {
+                    return Err(ProtocolError::UnexpectedEof);
+                };
                 Ok(ClientPacket::Mouse { x, y })
             }
             0x11 => Ok(ClientPacket::Split),
@@ -106,22 +138,50 @@ impl ClientPacket {
             0x17 => Ok(ClientPacket::KeyR),
             0x18 => Ok(ClientPacket::KeyT),
             0x19 => Ok(ClientPacket::KeyP),
+            0x1A => {
+                if data.len() != 5 {
+                    return Err(ProtocolError::InvalidOpcode(opcode));
+                }
+                let width = reader.try_get_u16().ok_or(ProtocolError::UnexpectedEof)?;
+                let height = reader.try_get_u16().ok_or(ProtocolError::UnexpectedEof)?;
+                Ok(ClientPacket::Viewport { width, height })
+            }
+            0x1B => {
+                if data.len() != 5 {
+                    return Err(ProtocolError::InvalidOpcode(opcode));
+                }
+                let mode = reader.try_get_u8().ok_or(ProtocolError::UnexpectedEof)?;
+                let (r, g, b) = match (reader.try_get_u8(), reader.try_get_u8(), reader.try_get_u8()) {
+                    (Some(r), Some(g), Some(b)) => (r, g, b),
+                    _ => return Err(ProtocolError::UnexpectedEof),
+                };
+                let color = match mode {
+                    0 => EjectColor::Cell,
+                    1 => EjectColor::Solid(Color::new(r, g, b)),
+                    2 => EjectColor::Rainbow,
+                    _ => return Err(ProtocolError::InvalidOpcode(opcode)),
+                };
+                Ok(ClientPacket::EjectColor(color))
+            }
             0x63 => {
                 // Chat
                 if data.len() < 3 {
                     return Err(ProtocolError::UnexpectedEof);
                 }
-                let flags = reader.get_u8();
+                let flags = reader.try_get_u8().ok_or(ProtocolError::UnexpectedEof)?;
                 // Skip reserved bytes based on flags
                 let rv_len = if flags & 2 != 0 { 4 } else { 0 }
                     + if flags & 4 != 0 { 8 } else { 0 }
                     + if flags & 8 != 0 { 16 } else { 0 };
+                if reader.remaining() < rv_len {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
                 reader.skip(rv_len);
 
                 let message = if protocol < 6 {
-                    reader.get_string_unicode()
+                    reader.try_get_string_unicode_null()?
                 } else {
-                    reader.get_string_utf8()
+                    reader.try_get_string_utf8_null()?
                 };
                 Ok(ClientPacket::Chat { flags, message })
             }
@@ -129,3 +189,163 @@ impl ClientPacket {
         }
     }
 }
+
+/// Build a HandshakeKey packet (0xFF). Protocol 6 and below use key 1.
+pub fn build_handshake_key(key: u32) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(5);
+    w.put_u8(ClientOpcode::HandshakeKey as u8);
+    w.put_u32(key);
+    w
+}
+
+/// Build a Protocol packet (0xFE); the server expects exactly 5 bytes.
+pub fn build_protocol_version(version: u32) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(5);
+    w.put_u8(ClientOpcode::Protocol as u8);
+    w.put_u32(version);
+    w
+}
+
+/// Build a stats request (a lone 0xFE byte).
+pub fn build_stats_request() -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(1);
+    w.put_u8(ClientOpcode::Protocol as u8);
+    w
+}
+
+/// Build a Join packet (0x00). Protocol 7+ sends the name as UTF-16.
+pub fn build_join(protocol: u32, name: &str) -> BinaryWriter {
+    let mut w = BinaryWriter::new();
+    w.put_u8(ClientOpcode::Join as u8);
+    if protocol > 6 {
+        w.put_string_unicode(name);
+    } else {
+        w.put_string_utf8_null(name);
+    }
+    w
+}
+
+/// Build a Mouse packet (0x10) in the 13-byte form, in scrambled world space.
+pub fn build_mouse(x: i32, y: i32) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(13);
+    w.put_u8(ClientOpcode::Mouse as u8);
+    w.put_i32(x);
+    w.put_i32(y);
+    w.put_u32(0);
+    w
+}
+
+/// Build a Viewport packet (0x1A) from the canvas size in CSS pixels.
+pub fn build_viewport(width: u16, height: u16) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(5);
+    w.put_u8(ClientOpcode::Viewport as u8);
+    w.put_u16(width);
+    w.put_u16(height);
+    w
+}
+
+/// Build an EjectColor packet (0x1B).
+pub fn build_eject_color(color: EjectColor) -> BinaryWriter {
+    let (mode, rgb) = match color {
+        EjectColor::Cell => (0, Color::default()),
+        EjectColor::Solid(rgb) => (1, rgb),
+        EjectColor::Rainbow => (2, Color::default()),
+    };
+    let mut w = BinaryWriter::with_capacity(5);
+    w.put_u8(ClientOpcode::EjectColor as u8);
+    w.put_u8(mode);
+    w.put_u8(rgb.r);
+    w.put_u8(rgb.g);
+    w.put_u8(rgb.b);
+    w
+}
+
+/// Build a Chat packet (0x63) with no flags. Protocol 5 and below send the
+/// message as UTF-16.
+pub fn build_chat(protocol: u32, message: &str) -> BinaryWriter {
+    let mut w = BinaryWriter::new();
+    w.put_u8(ClientOpcode::Chat as u8);
+    w.put_u8(0);
+    if protocol < 6 {
+        w.put_string_unicode(message);
+    } else {
+        w.put_string_utf8_null(message);
+    }
+    w
+}
+
+/// Build a packet that is only an opcode (Split, Eject, Spectate, key presses).
+pub fn build_opcode(opcode: ClientOpcode) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(1);
+    w.put_u8(opcode as u8);
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Opcodes the parser knows, plus a tail of arbitrary bytes.
+    fn packet() -> impl Strategy<Value = Vec<u8>> {
+        let opcode = prop_oneof![
+            prop::sample::select(vec![0x00u8, 0x01, 0x02, 0x10, 0x11, 0x12, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x63, 0xFE, 0xFF]),
+            any::<u8>(),
+        ];
+        (opcode, prop::collection::vec(any::<u8>(), 0..DEFAULT_MAX_PACKET_BYTES))
+            .prop_map(|(opcode, tail)| std::iter::once(opcode).chain(tail).collect())
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_never_panics(data in packet(), protocol in 1u32..=crate::packets::COGAR_PROTOCOL) {
+            if let Ok(ClientPacket::Join { name } | ClientPacket::Chat { message: name, .. }) = ClientPacket::parse(&data, protocol) {
+                prop_assert!(name.len() <= MAX_CLIENT_STRING_BYTES);
+            }
+        }
+
+        #[test]
+        fn test_mouse_forms_parse(x in any::<i32>(), y in any::<i32>(), fx in any::<f64>(), fy in any::<f64>()) {
+            let mut data = build_mouse(x, y).finish().to_vec();
+            let parsed = match ClientPacket::parse(&data, 6) {
+                Ok(ClientPacket::Mouse { x, y }) => Some((x, y)),
+                _ => None,
+            };
+            prop_assert_eq!(parsed, Some((x, y)));
+
+            data.truncate(9);
+            prop_assert!(ClientPacket::parse(&data, 6).is_ok());
+
+            let mut w = BinaryWriter::new();
+            w.put_u8(ClientOpcode::Mouse as u8);
+            w.put_f64(fx);
+            w.put_f64(fy);
+            w.put_u32(0);
+            prop_assert!(ClientPacket::parse(w.as_slice(), 6).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_viewport_round_trip() {
+        let data = build_viewport(3440, 1440).finish();
+        assert!(matches!(ClientPacket::parse(&data, 6), Ok(ClientPacket::Viewport { width: 3440, height: 1440 })));
+        assert!(ClientPacket::parse(&data[..3], 6).is_err());
+    }
+
+    #[test]
+    fn test_eject_color_round_trip() {
+        for color in [EjectColor::Cell, EjectColor::Solid(Color::new(12, 200, 99)), EjectColor::Rainbow] {
+            let data = build_eject_color(color).finish();
+            assert!(matches!(ClientPacket::parse(&data, 6), Ok(ClientPacket::EjectColor(c)) if c == color));
+        }
+        assert!(ClientPacket::parse(&[0x1B, 3, 0, 0, 0], 6).is_err());
+        assert!(ClientPacket::parse(&[0x1B, 1, 0, 0], 6).is_err());
+    }
+
+    #[test]
+    fn test_chat_flags_past_end_is_error() {
+        // Flags ask for 28 reserved bytes that aren't there
+        let data = [0x63, 0x0E, b'h', b'i', 0];
+        assert!(matches!(ClientPacket::parse(&data, 11), Err(ProtocolError::UnexpectedEof)));
+    }
+}
@@ -16,6 +16,8 @@ pub enum ClientOpcode {
     Join = 0x00,
     /// Request spectate mode.
     Spectate = 0x01,
+    /// Spectate a specific player.
+    SpectateTarget = 0x02,
     /// Mouse position update.
     Mouse = 0x10,
     /// Split (Space key).
@@ -32,6 +34,10 @@ pub enum ClientOpcode {
     KeyT = 0x18,
     /// P key (minion collect).
     KeyP = 0x19,
+    /// Canvas size, for view culling (cogar extension).
+    Viewport = 0x1A,
+    /// Ejected-mass color (cogar extension).
+    EjectColor = 0x1B,
     /// Chat message.
     Chat = 0x63,
     /// Protocol version handshake.
@@ -52,6 +58,8 @@ pub enum ServerOpcode {
     ClearAll = 0x12,
     /// Clear owned cells.
     ClearOwned = 0x14,
+    /// Draw a line from the player's cells to a point.
+    DrawLine = 0x15,
     /// Add owned node.
     AddNode = 0x20,
     /// Leaderboard (text list).
@@ -64,8 +72,68 @@ pub enum ServerOpcode {
     SetBorder = 0x40,
     /// Xray data (operator only).
     XrayData = 0x50,
+    /// Remerge countdown for owned cells.
+    MergeTimer = 0x51,
+    /// Full player roster (names, mass, color; no positions).
+    Roster = 0x52,
+    /// How the player's last cell was lost.
+    DeathInfo = 0x53,
+    /// Static map objects (walls, speed pads).
+    MapObjects = 0x54,
     /// Chat message.
     ChatMessage = 0x63,
+    /// Several packets in one frame (protocol 11+).
+    Batch = 0x70,
     /// Server stats (ping response).
     ServerStat = 0xFE,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BinaryReader;
+
+    /// Deterministic xorshift so failures reproduce.
+    struct Soup(u64);
+
+    impl Soup {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random bytes, usually led by a real opcode so parsing gets past the first byte.
+        fn packet(&mut self, opcodes: &[u8]) -> Vec<u8> {
+            let len = (self.next() % 48) as usize;
+            let mut data: Vec<u8> = (0..len).map(|_| self.next() as u8).collect();
+            if !data.is_empty() && !self.next().is_multiple_of(4) {
+                data[0] = opcodes[(self.next() % opcodes.len() as u64) as usize];
+            }
+            data
+        }
+    }
+
+    #[test]
+    fn test_parsers_survive_byte_soup() {
+        let client_ops = [0x00, 0x01, 0x02, 0x10, 0x11, 0x12, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1B, 0x63, 0xFE, 0xFF];
+        let server_ops = [0x10, 0x11, 0x12, 0x14, 0x15, 0x20, 0x30, 0x31, 0x32, 0x40, 0x50, 0x51, 0x52, 0x53, 0x54, 0x63, 0x70, 0xFE];
+        let mut soup = Soup(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..20_000 {
+            let data = soup.packet(&client_ops);
+            for protocol in [4, 5, 6, 11] {
+                let _ = ClientPacket::parse(&data, protocol);
+            }
+            let data = soup.packet(&server_ops);
+            for protocol in [5, 6, 11] {
+                let _ = ServerPacket::parse(&data, protocol);
+            }
+
+            let mut reader = BinaryReader::new(data.clone()).with_max_string_bytes(8);
+            let _ = reader.try_get_string_utf8_null();
+            let _ = reader.try_get_string_unicode_null();
+            let _ = reader.try_get_string_utf8_len();
+        }
+    }
+}
@@ -1,6 +1,8 @@
-//! Server -> Client packet building.
+//! Server -> Client packet building, and parsing for native clients.
 
-use crate::{BinaryWriter, Color};
+use bytes::Bytes;
+
+use crate::{BinaryReader, BinaryWriter, Color, ProtocolError};
 
 /// Build a ClearAll packet (0x12).
 pub fn build_clear_all() -> BinaryWriter {
@@ -16,6 +18,26 @@ pub fn build_clear_owned() -> BinaryWriter {
     w
 }
 
+/// Build a DrawLine packet (0x15): a line from the player's cells to
+/// (`x`, `y`), shown briefly.
+///
+/// The point is sent as an `i16` pair like the original, so the scrambled
+/// coordinates wrap; the receiver takes the value nearest its own view.
+pub fn build_draw_line(scramble_x: i32, scramble_y: i32, x: i32, y: i32) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(5);
+    w.put_u8(0x15);
+    w.put_i16(x.wrapping_add(scramble_x) as i16);
+    w.put_i16(y.wrapping_add(scramble_y) as i16);
+    w
+}
+
+/// The coordinate a DrawLine `i16` stands for: of all the values it could
+/// have wrapped from, the one nearest `near` (the receiver's view center).
+pub fn unwrap_draw_line(value: i16, near: f32) -> f32 {
+    let near = near.round() as i32;
+    (near + (value as i32).wrapping_sub(near) as i16 as i32) as f32
+}
+
 /// Build an AddNode packet (0x20).
 pub fn build_add_node(node_id: u32, scramble_id: u32) -> BinaryWriter {
     let mut w = BinaryWriter::with_capacity(5);
@@ -24,7 +46,8 @@ pub fn build_add_node(node_id: u32, scramble_id: u32) -> BinaryWriter {
     w
 }
 
-/// Build a SetBorder packet (0x40).
+/// Build a SetBorder packet (0x40). `unlimited_zoom` trails the server name
+/// as one byte when given; only cogar clients expect it.
 pub fn build_set_border(
     min_x: f64,
     min_y: f64,
@@ -32,15 +55,19 @@ pub fn build_set_border(
     max_y: f64,
     game_type: u32,
     server_name: &str,
+    unlimited_zoom: Option<bool>,
 ) -> BinaryWriter {
-    let mut w = BinaryWriter::with_capacity(33 + server_name.len() + 1);
+    let mut w = BinaryWriter::with_capacity(33 + server_name.len() + 2);
     w.put_u8(0x40);
     w.put_f64(min_x);
     w.put_f64(min_y);
     w.put_f64(max_x);
     w.put_f64(max_y);
     w.put_u32(game_type);
-    w.put_string_utf8(server_name);
+    w.put_string_utf8_null(server_name);
+    if let Some(unlimited_zoom) = unlimited_zoom {
+        w.put_u8(unlimited_zoom as u8);
+    }
     w
 }
 
@@ -80,8 +107,8 @@ pub fn build_chat_message(
     w.put_u8(color.r);
     w.put_u8(color.g);
     w.put_u8(color.b);
-    w.put_string_utf8(name);
-    w.put_string_utf8(message);
+    w.put_string_utf8_null(name);
+    w.put_string_utf8_null(message);
     w
 }
 
@@ -89,18 +116,36 @@ pub fn build_chat_message(
 pub fn build_server_stat(json: &str) -> BinaryWriter {
     let mut w = BinaryWriter::new();
     w.put_u8(0xFE);
-    w.put_string_utf8(json);
+    w.put_string_utf8_null(json);
+    w
+}
+
+/// Build a LeaderboardText packet (0x30): plain lines, shown in place of the
+/// ranked list.
+pub fn build_leaderboard_text(lines: &[String]) -> BinaryWriter {
+    let mut w = BinaryWriter::new();
+    w.put_u8(0x30);
+    w.put_u32(lines.len() as u32);
+    for line in lines {
+        w.put_string_utf8_null(line);
+    }
     w
 }
 
 /// Build a LeaderboardFFA packet (0x31).
-pub fn build_leaderboard_ffa(entries: &[(bool, &str)]) -> BinaryWriter {
+///
+/// Protocol 11+ also carries each entry's player id (after the is-me flag),
+/// which clients can send back in a SpectateTarget packet.
+pub fn build_leaderboard_ffa(protocol: u32, entries: &[(bool, u32, &str)]) -> BinaryWriter {
     let mut w = BinaryWriter::new();
     w.put_u8(0x31);
     w.put_u32(entries.len() as u32);
-    for (is_me, name) in entries {
+    for (is_me, player_id, name) in entries {
         w.put_u32(if *is_me { 1 } else { 0 });
-        w.put_string_utf8(name);
+        if protocol >= 11 {
+            w.put_u32(*player_id);
+        }
+        w.put_string_utf8_null(name);
     }
     w
 }
@@ -126,6 +171,8 @@ pub struct CellFlags {
     pub is_agitated: bool,
     pub is_ejected: bool,
     pub is_food: bool,
+    /// Owned by the receiving client or one of its minions (protocol 13+).
+    pub is_friend: bool,
 }
 
 impl CellFlags {
@@ -160,14 +207,64 @@ impl CellFlags {
     pub fn encode_v11(&self) -> u8 {
         self.encode_v6() // Same encoding
     }
+
+    /// Encode the extended flags byte for protocol 13+: 0x01 food, 0x02 friend.
+    pub fn encode_extended(&self) -> u8 {
+        let mut flags = 0u8;
+        if self.is_food {
+            flags |= 0x01;
+        }
+        if self.is_friend {
+            flags |= 0x02;
+        }
+        flags
+    }
+
+    /// Decode a parsed node's flag bytes. From protocol 11 on, 0x80 only
+    /// announces the extended byte, which then carries food.
+    pub fn decode(flags: u8, ext_flags: u8, protocol: u32) -> Self {
+        Self {
+            is_spiked: flags & 0x01 != 0,
+            is_player: flags & 0x02 != 0,
+            has_skin: flags & 0x04 != 0,
+            has_name: flags & 0x08 != 0,
+            is_agitated: flags & 0x10 != 0,
+            is_ejected: flags & 0x20 != 0,
+            is_food: if protocol >= 11 { ext_flags & 0x01 != 0 } else { flags & 0x80 != 0 },
+            is_friend: ext_flags & 0x02 != 0,
+        }
+    }
+}
+
+/// cogar's own protocol level, above every vanilla one: the 17 layout with
+/// UpdateNodes positions in [`FINE_POSITION_SCALE`]ths of a unit, so slow
+/// small cells don't move in whole-unit steps. Vanilla clients never ask
+/// for it.
+pub const COGAR_PROTOCOL: u32 = 18;
+
+/// Steps per world unit in UpdateNodes positions on [`COGAR_PROTOCOL`].
+pub const FINE_POSITION_SCALE: f32 = 4.0;
+
+/// The UpdateNodes layout written for `protocol`: the newest implemented one
+/// (6, 11, 13, 17 or [`COGAR_PROTOCOL`]) that isn't newer than it. Below 6
+/// the node records are 6's; only the remove count differs.
+pub fn update_nodes_layout(protocol: u32) -> u32 {
+    match protocol {
+        0..=10 => 6,
+        11..=12 => 11,
+        13..=16 => 13,
+        17 => 17,
+        _ => COGAR_PROTOCOL,
+    }
 }
 
 /// Cell data for the UpdateNodes packet.
 #[derive(Debug, Clone)]
 pub struct UpdateCell {
     pub node_id: u32,
-    pub x: i32,
-    pub y: i32,
+    /// World position; layouts before [`COGAR_PROTOCOL`] cut it to whole units.
+    pub x: f32,
+    pub y: f32,
     pub size: u16,
     pub color: Color,
     pub flags: CellFlags,
@@ -176,13 +273,14 @@ pub struct UpdateCell {
 }
 
 /// Eat record (cell was eaten by another).
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EatRecord {
     pub eaten_id: u32,
     pub eater_id: u32,
 }
 
-/// Build an UpdateNodes packet (0x10) - protocol 6-10.
+/// Build an UpdateNodes packet (0x10) in the layout
+/// [`update_nodes_layout`] picks for `protocol`.
 /// 
 /// The packet format is:
 /// - opcode 0x10
@@ -213,7 +311,8 @@ pub fn build_update_nodes(
         w.put_u32(eat.eaten_id ^ scramble_id);
     }
 
-    if protocol < 11 {
+    let layout = update_nodes_layout(protocol);
+    if layout < 11 {
         write_update_nodes_v6(
             &mut w,
             scramble_id,
@@ -225,6 +324,7 @@ pub fn build_update_nodes(
     } else {
         write_update_nodes_v11(
             &mut w,
+            layout,
             scramble_id,
             scramble_x,
             scramble_y,
@@ -262,8 +362,8 @@ fn write_update_nodes_v6(
     // Write updates
     for node in upd_nodes {
         w.put_u32(node.node_id ^ scramble_id);
-        w.put_i32(node.x + scramble_x);
-        w.put_i32(node.y + scramble_y);
+        w.put_i32(node.x as i32 + scramble_x);
+        w.put_i32(node.y as i32 + scramble_y);
         w.put_u16(node.size);
 
         let flags = node.flags.encode_v6();
@@ -275,13 +375,21 @@ fn write_update_nodes_v6(
             w.put_u8(node.color.g);
             w.put_u8(node.color.b);
         }
+
+        // Skin and name only when resent (e.g. after a rename)
+        if flags & 0x04 != 0 {
+            w.put_string_utf8_null(node.skin.as_deref().unwrap_or_default());
+        }
+        if flags & 0x08 != 0 {
+            w.put_string_utf8_null(node.name.as_deref().unwrap_or_default());
+        }
     }
 
     // Write adds
     for node in add_nodes {
         w.put_u32(node.node_id ^ scramble_id);
-        w.put_i32(node.x + scramble_x);
-        w.put_i32(node.y + scramble_y);
+        w.put_i32(node.x as i32 + scramble_x);
+        w.put_i32(node.y as i32 + scramble_y);
         w.put_u16(node.size);
 
         let mut flags = node.flags;
@@ -301,14 +409,14 @@ fn write_update_nodes_v6(
         // Skin
         if f & 0x04 != 0 {
             if let Some(ref skin) = node.skin {
-                w.put_string_utf8(skin);
+                w.put_string_utf8_null(skin);
             }
         }
 
         // Name
         if f & 0x08 != 0 {
             if let Some(ref name) = node.name {
-                w.put_string_utf8(name);
+                w.put_string_utf8_null(name);
             }
         }
     }
@@ -317,9 +425,39 @@ fn write_update_nodes_v6(
     w.put_u32(0);
 }
 
-/// Write update/add nodes for protocol 11+.
+/// Write a node's flags for protocol 11+ and return the first byte. Set 0x80
+/// announces an extended flags byte: on 11 only for food (always 0x01), on 13
+/// whenever [`CellFlags::encode_extended`] has bits, and on 17 always.
+fn put_flags_v11(w: &mut BinaryWriter, layout: u32, flags: &CellFlags) -> u8 {
+    let (mut first, extended) = if layout < 13 {
+        (flags.encode_v11(), 0x01)
+    } else {
+        (flags.encode_v11() & !0x80, flags.encode_extended())
+    };
+    if layout >= 17 || (layout >= 13 && extended != 0) {
+        first |= 0x80;
+    }
+    w.put_u8(first);
+    if first & 0x80 != 0 {
+        w.put_u8(extended);
+    }
+    first
+}
+
+/// Write a node coordinate: whole units, or [`FINE_POSITION_SCALE`]ths of
+/// one on [`COGAR_PROTOCOL`].
+fn put_coordinate(w: &mut BinaryWriter, layout: u32, value: f32, scramble: i32) {
+    if layout >= COGAR_PROTOCOL {
+        w.put_i32(((value + scramble as f32) * FINE_POSITION_SCALE).round() as i32);
+    } else {
+        w.put_i32(value as i32 + scramble);
+    }
+}
+
+/// Write update/add nodes for protocol 11+, in the 11, 13, 17 or 18 layout.
 fn write_update_nodes_v11(
     w: &mut BinaryWriter,
+    layout: u32,
     scramble_id: u32,
     scramble_x: i32,
     scramble_y: i32,
@@ -329,17 +467,11 @@ fn write_update_nodes_v11(
     // Write updates
     for node in upd_nodes {
         w.put_u32(node.node_id ^ scramble_id);
-        w.put_i32(node.x + scramble_x);
-        w.put_i32(node.y + scramble_y);
+        put_coordinate(w, layout, node.x, scramble_x);
+        put_coordinate(w, layout, node.y, scramble_y);
         w.put_u16(node.size);
 
-        let flags = node.flags.encode_v11();
-        w.put_u8(flags);
-
-        // Extended flag for food
-        if flags & 0x80 != 0 {
-            w.put_u8(0x01);
-        }
+        let flags = put_flags_v11(w, layout, &node.flags);
 
         // Color only for player cells
         if flags & 0x02 != 0 {
@@ -347,26 +479,31 @@ fn write_update_nodes_v11(
             w.put_u8(node.color.g);
             w.put_u8(node.color.b);
         }
+
+        // Skin and name only when resent (e.g. after a rename)
+        if flags & 0x04 != 0 {
+            match node.skin.as_deref() {
+                Some(skin) if !skin.is_empty() => w.put_string_utf8_null(&format!("%{}", skin)),
+                _ => w.put_string_utf8_null(""),
+            }
+        }
+        if flags & 0x08 != 0 {
+            w.put_string_utf8_null(node.name.as_deref().unwrap_or_default());
+        }
     }
 
     // Write adds
     for node in add_nodes {
         w.put_u32(node.node_id ^ scramble_id);
-        w.put_i32(node.x + scramble_x);
-        w.put_i32(node.y + scramble_y);
+        put_coordinate(w, layout, node.x, scramble_x);
+        put_coordinate(w, layout, node.y, scramble_y);
         w.put_u16(node.size);
 
         let mut flags = node.flags;
         flags.is_player = true; // Always include color for new nodes
         flags.has_skin = node.skin.is_some();
         flags.has_name = node.name.is_some();
-        let f = flags.encode_v11();
-        w.put_u8(f);
-
-        // Extended flag for food
-        if f & 0x80 != 0 {
-            w.put_u8(0x01);
-        }
+        let f = put_flags_v11(w, layout, &flags);
 
         // Color
         if f & 0x02 != 0 {
@@ -378,14 +515,14 @@ fn write_update_nodes_v11(
         // Skin (protocol 11 uses % prefix)
         if f & 0x04 != 0 {
             if let Some(ref skin) = node.skin {
-                w.put_string_utf8(&format!("%{}", skin));
+                w.put_string_utf8_null(&format!("%{}", skin));
             }
         }
 
         // Name
         if f & 0x08 != 0 {
             if let Some(ref name) = node.name {
-                w.put_string_utf8(name);
+                w.put_string_utf8_null(name);
             }
         }
     }
@@ -394,8 +531,111 @@ fn write_update_nodes_v11(
     w.put_u32(0);
 }
 
+/// A node as read back from an UpdateNodes packet, still in the receiving
+/// client's scrambled space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeUpdate {
+    pub node_id: u32,
+    /// Position in world units; whole numbers below [`COGAR_PROTOCOL`].
+    pub x: f32,
+    pub y: f32,
+    pub size: u16,
+    /// Raw flag bits (see [`CellFlags::encode_v6`]).
+    pub flags: u8,
+    /// Extended flag bits on protocol 11+ (see [`CellFlags::encode_extended`]);
+    /// 0 when absent.
+    pub ext_flags: u8,
+    /// Present when the is-player bit (0x02) is set.
+    pub color: Option<Color>,
+    /// Present when the has-skin bit (0x04) is set; protocol 11+ prefixes `%`.
+    pub skin: Option<String>,
+    /// Present when the has-name bit (0x08) is set.
+    pub name: Option<String>,
+}
+
+/// Decoded UpdateNodes packet (0x10).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpdateNodesPayload {
+    pub eats: Vec<EatRecord>,
+    /// Updated and added nodes; the wire format doesn't tell them apart.
+    pub nodes: Vec<NodeUpdate>,
+    /// Removed node ids, including the eaten ones.
+    pub removed: Vec<u32>,
+}
+
+impl UpdateNodesPayload {
+    /// Parse the body of an UpdateNodes packet, after the opcode byte, as
+    /// written by [`build_update_nodes`] for `protocol`.
+    pub fn parse(reader: &mut BinaryReader, protocol: u32) -> Result<Self, ProtocolError> {
+        let eof = || ProtocolError::UnexpectedEof;
+        let mut payload = Self::default();
+
+        let eat_count = reader.try_get_u16().ok_or_else(eof)?;
+        for _ in 0..eat_count {
+            let eater_id = reader.try_get_u32().ok_or_else(eof)?;
+            let eaten_id = reader.try_get_u32().ok_or_else(eof)?;
+            payload.eats.push(EatRecord { eaten_id, eater_id });
+        }
+
+        let scale = if update_nodes_layout(protocol) >= COGAR_PROTOCOL { FINE_POSITION_SCALE } else { 1.0 };
+        loop {
+            let node_id = reader.try_get_u32().ok_or_else(eof)?;
+            if node_id == 0 {
+                break;
+            }
+            let x = reader.try_get_i32().ok_or_else(eof)? as f32 / scale;
+            let y = reader.try_get_i32().ok_or_else(eof)? as f32 / scale;
+            let size = reader.try_get_u16().ok_or_else(eof)?;
+            let flags = reader.try_get_u8().ok_or_else(eof)?;
+            let ext_flags = if protocol >= 11 && flags & 0x80 != 0 {
+                reader.try_get_u8().ok_or_else(eof)?
+            } else {
+                0
+            };
+            let color = if flags & 0x02 != 0 {
+                let r = reader.try_get_u8().ok_or_else(eof)?;
+                let g = reader.try_get_u8().ok_or_else(eof)?;
+                let b = reader.try_get_u8().ok_or_else(eof)?;
+                Some(Color::new(r, g, b))
+            } else {
+                None
+            };
+            let skin = if flags & 0x04 != 0 { Some(reader.try_get_string_utf8_null()?) } else { None };
+            let name = if flags & 0x08 != 0 { Some(reader.try_get_string_utf8_null()?) } else { None };
+            payload.nodes.push(NodeUpdate { node_id, x, y, size, flags, ext_flags, color, skin, name });
+        }
+
+        let remove_count = if protocol < 6 {
+            reader.try_get_u32().ok_or_else(eof)?
+        } else {
+            reader.try_get_u16().ok_or_else(eof)? as u32
+        };
+        for _ in 0..remove_count {
+            payload.removed.push(reader.try_get_u32().ok_or_else(eof)?);
+        }
+
+        Ok(payload)
+    }
+}
+
+/// Build a MergeTimer packet (0x51).
+///
+/// Tells a player how long each of their cells has left before it may
+/// remerge: `u16 count` then `[u32 node_id, u32 remaining_ms] × count`.
+/// A custom opcode like XRay; stock clients ignore it.
+pub fn build_merge_timer(scramble_id: u32, timers: &[(u32, u32)]) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(3 + timers.len() * 8);
+    w.put_u8(0x51);
+    w.put_u16(timers.len() as u16);
+    for &(node_id, remaining_ms) in timers {
+        w.put_u32(node_id ^ scramble_id);
+        w.put_u32(remaining_ms);
+    }
+    w
+}
+
 /// Player cell data for XRay packet.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct XrayPlayerCell {
     pub node_id: u32,
     pub x: i32,
@@ -433,8 +673,584 @@ pub fn build_xray_data(
         w.put_u8(cell.color.b);
 
         // Player name (UTF-8 string with length prefix)
-        w.put_string_utf8(&cell.name);
+        w.put_string_utf8_null(&cell.name);
     }
 
     w
 }
+
+/// One row of the Roster packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RosterEntry {
+    pub name: String,
+    pub mass: u32,
+    pub color: Color,
+    pub is_bot: bool,
+}
+
+/// Build a Roster packet (0x52).
+///
+/// Every alive player and bot with their mass and color, but no positions,
+/// so unlike XRay it is safe to send to everyone:
+/// `u16 count` then `[u32 mass, u8 r, u8 g, u8 b, u8 flags, string name] × count`,
+/// where flags bit 0 marks bots.
+pub fn build_roster(entries: &[RosterEntry]) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(3 + entries.len() * 24);
+    w.put_u8(0x52);
+    w.put_u16(entries.len() as u16);
+    for entry in entries {
+        w.put_u32(entry.mass);
+        w.put_u8(entry.color.r);
+        w.put_u8(entry.color.g);
+        w.put_u8(entry.color.b);
+        w.put_u8(if entry.is_bot { 1 } else { 0 });
+        w.put_string_utf8_null(&entry.name);
+    }
+    w
+}
+
+/// What ended a player's life, as sent in a DeathInfo packet.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    /// Eaten by another player or bot.
+    Eaten = 0,
+    /// Eaten by a mother cell.
+    MotherCell = 1,
+    /// Removed by an operator's `/kill`.
+    Killed = 2,
+}
+
+impl DeathCause {
+    /// Causes this side doesn't know yet read as a plain kill.
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => DeathCause::MotherCell,
+            2 => DeathCause::Killed,
+            _ => DeathCause::Eaten,
+        }
+    }
+}
+
+/// Build a DeathInfo packet (0x53).
+///
+/// Sent to a player whose last cell is gone:
+/// `u8 cause, u32 killer_mass, string killer_name, u32 killer_id`. The killer
+/// fields are only meaningful for [`DeathCause::Eaten`] and are zero/empty
+/// otherwise. `killer_id` is the player id a SpectateTarget packet takes;
+/// older servers end the packet before it.
+pub fn build_death_info(cause: DeathCause, killer_name: &str, killer_mass: u32, killer_id: u32) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(11 + killer_name.len());
+    w.put_u8(0x53);
+    w.put_u8(cause as u8);
+    w.put_u32(killer_mass);
+    w.put_string_utf8_null(killer_name);
+    w.put_u32(killer_id);
+    w
+}
+
+/// What a map object does, as sent in a MapObjects packet.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapShapeKind {
+    /// Player cells can't enter it.
+    Wall = 0,
+    /// Scales the speed of player cells on it.
+    SpeedPad = 1,
+}
+
+/// One static map object: an axis-aligned rectangle in the receiver's
+/// scrambled space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapShape {
+    pub kind: MapShapeKind,
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+    /// Speed multiplier of a pad; 1 for walls.
+    pub speed: f32,
+}
+
+/// Build a MapObjects packet (0x54).
+///
+/// The map's static objects, sent once after the border:
+/// `u16 count` then `[u8 kind, f32 min_x, f32 min_y, f32 max_x, f32 max_y, f32 speed] × count`.
+/// Unknown kinds are skipped by the parser, so new ones can be added.
+pub fn build_map_objects(scramble_x: i32, scramble_y: i32, shapes: &[MapShape]) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(3 + shapes.len() * 21);
+    w.put_u8(0x54);
+    w.put_u16(shapes.len() as u16);
+    for shape in shapes {
+        w.put_u8(shape.kind as u8);
+        w.put_f32(shape.min_x + scramble_x as f32);
+        w.put_f32(shape.min_y + scramble_y as f32);
+        w.put_f32(shape.max_x + scramble_x as f32);
+        w.put_f32(shape.max_y + scramble_y as f32);
+        w.put_f32(shape.speed);
+    }
+    w
+}
+
+/// One row of a LeaderboardFFA packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardFfaEntry {
+    pub is_me: bool,
+    /// Only sent to protocol 11+.
+    pub player_id: Option<u32>,
+    pub name: String,
+}
+
+/// Decoded LeaderboardFFA packet (0x31).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LeaderboardFfaPayload {
+    pub entries: Vec<LeaderboardFfaEntry>,
+}
+
+/// Decoded SetBorder packet (0x40), in the receiver's scrambled space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBorderPayload {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+    /// Game type and server name trail the border; old servers omit them.
+    pub game_type: Option<u32>,
+    pub server_name: Option<String>,
+    /// Whether the server lets clients zoom out without the mass-based
+    /// limit; only cogar servers send it.
+    pub unlimited_zoom: Option<bool>,
+}
+
+/// Decoded ChatMessage packet (0x63).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessagePayload {
+    pub color: Color,
+    pub name: String,
+    pub message: String,
+    pub is_server: bool,
+    pub is_admin: bool,
+    pub is_mod: bool,
+}
+
+/// Decoded XrayData packet (0x50); ids and positions are still scrambled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XrayPayload {
+    pub cells: Vec<XrayPlayerCell>,
+}
+
+/// Build a Batch packet (0x70): several packets in one WebSocket frame, each
+/// as `u32 length` then its bytes. A custom opcode; only clients that know it
+/// may be sent one.
+pub fn build_batch(packets: &[Bytes]) -> BinaryWriter {
+    let len = packets.iter().map(|p| 4 + p.len()).sum::<usize>();
+    let mut w = BinaryWriter::with_capacity(1 + len);
+    w.put_u8(0x70);
+    for packet in packets {
+        w.put_u32(packet.len() as u32);
+        w.put_slice(packet);
+    }
+    w
+}
+
+/// Parsed server packet, the counterpart of the `build_*` functions above.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerPacket {
+    /// World update (0x10).
+    UpdateNodes(UpdateNodesPayload),
+    /// Spectator camera (0x11).
+    UpdatePosition { x: f32, y: f32, scale: f32 },
+    /// Clear all nodes (0x12).
+    ClearAll,
+    /// Clear owned cells (0x14).
+    ClearOwned,
+    /// Line to a point (0x15), wrapped to `i16`; see [`unwrap_draw_line`].
+    DrawLine { x: i16, y: i16 },
+    /// New owned cell (0x20), id still scrambled.
+    AddNode { node_id: u32 },
+    /// Text leaderboard (0x30).
+    LeaderboardText { lines: Vec<String> },
+    /// FFA leaderboard (0x31).
+    LeaderboardFfa(LeaderboardFfaPayload),
+    /// Teams pie chart (0x32).
+    LeaderboardPie { team_sizes: Vec<f32> },
+    /// World border (0x40).
+    SetBorder(SetBorderPayload),
+    /// Operator xray (0x50).
+    XrayData(XrayPayload),
+    /// Remerge countdowns (0x51) as `(node_id, remaining_ms)`.
+    MergeTimer { timers: Vec<(u32, u32)> },
+    /// Player roster (0x52).
+    Roster { entries: Vec<RosterEntry> },
+    /// Cause of the player's death (0x53); `killer_id` is 0 when there is no
+    /// killer to follow.
+    DeathInfo { cause: DeathCause, killer_name: String, killer_mass: u32, killer_id: u32 },
+    /// Static map objects (0x54).
+    MapObjects { shapes: Vec<MapShape> },
+    /// Chat message (0x63).
+    ChatMessage(ChatMessagePayload),
+    /// Packets sent together (0x70), each still to be parsed.
+    Batch { packets: Vec<Bytes> },
+    /// Server stats JSON (0xFE).
+    ServerStat { json: String },
+}
+
+impl ServerPacket {
+    /// Parse a server packet from raw bytes.
+    ///
+    /// `protocol` is the version the receiving client negotiated, which
+    /// changes the UpdateNodes and LeaderboardFFA layouts.
+    pub fn parse(data: &[u8], protocol: u32) -> Result<Self, ProtocolError> {
+        let eof = || ProtocolError::UnexpectedEof;
+        let mut reader = BinaryReader::new(data.to_vec());
+        let opcode = reader.try_get_u8().ok_or_else(eof)?;
+
+        let packet = match opcode {
+            0x10 => ServerPacket::UpdateNodes(UpdateNodesPayload::parse(&mut reader, protocol)?),
+            0x11 => ServerPacket::UpdatePosition {
+                x: reader.try_get_f32().ok_or_else(eof)?,
+                y: reader.try_get_f32().ok_or_else(eof)?,
+                scale: reader.try_get_f32().ok_or_else(eof)?,
+            },
+            0x12 => ServerPacket::ClearAll,
+            0x14 => ServerPacket::ClearOwned,
+            0x15 => ServerPacket::DrawLine {
+                x: reader.try_get_i16().ok_or_else(eof)?,
+                y: reader.try_get_i16().ok_or_else(eof)?,
+            },
+            0x20 => ServerPacket::AddNode { node_id: reader.try_get_u32().ok_or_else(eof)? },
+            0x30 => {
+                let count = reader.try_get_u32().ok_or_else(eof)?;
+                let mut lines = Vec::new();
+                for _ in 0..count {
+                    // Every line has at least its NUL, so a huge count can't spin on an empty buffer
+                    if reader.remaining() == 0 {
+                        return Err(eof());
+                    }
+                    lines.push(reader.try_get_string_utf8_null()?);
+                }
+                ServerPacket::LeaderboardText { lines }
+            }
+            0x31 => {
+                let count = reader.try_get_u32().ok_or_else(eof)?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let is_me = reader.try_get_u32().ok_or_else(eof)? != 0;
+                    let player_id = if protocol >= 11 {
+                        Some(reader.try_get_u32().ok_or_else(eof)?)
+                    } else {
+                        None
+                    };
+                    let name = reader.try_get_string_utf8_null()?;
+                    entries.push(LeaderboardFfaEntry { is_me, player_id, name });
+                }
+                ServerPacket::LeaderboardFfa(LeaderboardFfaPayload { entries })
+            }
+            0x32 => {
+                let count = reader.try_get_u32().ok_or_else(eof)?;
+                let team_sizes = (0..count)
+                    .map(|_| reader.try_get_f32().ok_or_else(eof))
+                    .collect::<Result<_, _>>()?;
+                ServerPacket::LeaderboardPie { team_sizes }
+            }
+            0x40 => {
+                let min_x = reader.try_get_f64().ok_or_else(eof)?;
+                let min_y = reader.try_get_f64().ok_or_else(eof)?;
+                let max_x = reader.try_get_f64().ok_or_else(eof)?;
+                let max_y = reader.try_get_f64().ok_or_else(eof)?;
+                let game_type = reader.try_get_u32();
+                let server_name = game_type.map(|_| reader.try_get_string_utf8_null()).transpose()?;
+                let unlimited_zoom = reader.try_get_u8().map(|allowed| allowed != 0);
+                ServerPacket::SetBorder(SetBorderPayload { min_x, min_y, max_x, max_y, game_type, server_name, unlimited_zoom })
+            }
+            0x50 => {
+                let count = reader.try_get_u16().ok_or_else(eof)?;
+                let mut cells = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let node_id = reader.try_get_u32().ok_or_else(eof)?;
+                    let x = reader.try_get_i32().ok_or_else(eof)?;
+                    let y = reader.try_get_i32().ok_or_else(eof)?;
+                    let size = reader.try_get_u16().ok_or_else(eof)?;
+                    let r = reader.try_get_u8().ok_or_else(eof)?;
+                    let g = reader.try_get_u8().ok_or_else(eof)?;
+                    let b = reader.try_get_u8().ok_or_else(eof)?;
+                    let name = reader.try_get_string_utf8_null()?;
+                    cells.push(XrayPlayerCell { node_id, x, y, size, color: Color::new(r, g, b), name });
+                }
+                ServerPacket::XrayData(XrayPayload { cells })
+            }
+            0x51 => {
+                let count = reader.try_get_u16().ok_or_else(eof)?;
+                let mut timers = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let node_id = reader.try_get_u32().ok_or_else(eof)?;
+                    let remaining_ms = reader.try_get_u32().ok_or_else(eof)?;
+                    timers.push((node_id, remaining_ms));
+                }
+                ServerPacket::MergeTimer { timers }
+            }
+            0x52 => {
+                let count = reader.try_get_u16().ok_or_else(eof)?;
+                let mut entries = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let mass = reader.try_get_u32().ok_or_else(eof)?;
+                    let r = reader.try_get_u8().ok_or_else(eof)?;
+                    let g = reader.try_get_u8().ok_or_else(eof)?;
+                    let b = reader.try_get_u8().ok_or_else(eof)?;
+                    let flags = reader.try_get_u8().ok_or_else(eof)?;
+                    let name = reader.try_get_string_utf8_null()?;
+                    entries.push(RosterEntry { name, mass, color: Color::new(r, g, b), is_bot: flags & 1 != 0 });
+                }
+                ServerPacket::Roster { entries }
+            }
+            0x53 => ServerPacket::DeathInfo {
+                cause: DeathCause::from_u8(reader.try_get_u8().ok_or_else(eof)?),
+                killer_mass: reader.try_get_u32().ok_or_else(eof)?,
+                killer_name: reader.try_get_string_utf8_null()?,
+                killer_id: reader.try_get_u32().unwrap_or(0),
+            },
+            0x54 => {
+                let count = reader.try_get_u16().ok_or_else(eof)?;
+                let mut shapes = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let kind = reader.try_get_u8().ok_or_else(eof)?;
+                    let min_x = reader.try_get_f32().ok_or_else(eof)?;
+                    let min_y = reader.try_get_f32().ok_or_else(eof)?;
+                    let max_x = reader.try_get_f32().ok_or_else(eof)?;
+                    let max_y = reader.try_get_f32().ok_or_else(eof)?;
+                    let speed = reader.try_get_f32().ok_or_else(eof)?;
+                    let kind = match kind {
+                        0 => MapShapeKind::Wall,
+                        1 => MapShapeKind::SpeedPad,
+                        _ => continue,
+                    };
+                    shapes.push(MapShape { kind, min_x, min_y, max_x, max_y, speed });
+                }
+                ServerPacket::MapObjects { shapes }
+            }
+            0x63 => {
+                let flags = reader.try_get_u8().ok_or_else(eof)?;
+                let r = reader.try_get_u8().ok_or_else(eof)?;
+                let g = reader.try_get_u8().ok_or_else(eof)?;
+                let b = reader.try_get_u8().ok_or_else(eof)?;
+                ServerPacket::ChatMessage(ChatMessagePayload {
+                    color: Color::new(r, g, b),
+                    name: reader.try_get_string_utf8_null()?,
+                    message: reader.try_get_string_utf8_null()?,
+                    is_server: flags & 0x80 != 0,
+                    is_admin: flags & 0x40 != 0,
+                    is_mod: flags & 0x20 != 0,
+                })
+            }
+            0x70 => {
+                let mut packets = Vec::new();
+                while reader.remaining() > 0 {
+                    let len = reader.try_get_u32().ok_or_else(eof)?;
+                    packets.push(reader.try_get_bytes(len as usize).ok_or_else(eof)?);
+                }
+                ServerPacket::Batch { packets }
+            }
+            0xFE => ServerPacket::ServerStat { json: reader.try_get_string_utf8_null()? },
+            _ => return Err(ProtocolError::InvalidOpcode(opcode)),
+        };
+        Ok(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(w: BinaryWriter, protocol: u32) -> ServerPacket {
+        ServerPacket::parse(&w.finish(), protocol).unwrap()
+    }
+
+    fn cell(node_id: u32, name: Option<&str>) -> UpdateCell {
+        UpdateCell {
+            node_id,
+            x: -120.0,
+            y: 340.0,
+            size: 55,
+            color: Color::new(10, 20, 30),
+            flags: CellFlags { is_spiked: node_id == 3, ..CellFlags::default() },
+            skin: name.map(|_| "doge".to_string()),
+            name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_update_nodes_round_trip() {
+        for protocol in [6, 11, 13, 17, COGAR_PROTOCOL] {
+            let (scramble_id, scramble_x, scramble_y) = (0x55, 1000, -2000);
+            let added = [cell(1, Some("Alice"))];
+            let updated = [cell(2, None), cell(3, None)];
+            let eats = [EatRecord { eaten_id: 4, eater_id: 1 }];
+            let w = build_update_nodes(protocol, scramble_id, scramble_x, scramble_y, &added, &updated, &eats, &[5]);
+
+            let ServerPacket::UpdateNodes(payload) = parse(w, protocol) else {
+                panic!("expected UpdateNodes");
+            };
+            assert_eq!(payload.eats, vec![EatRecord { eaten_id: 4 ^ scramble_id, eater_id: 1 ^ scramble_id }]);
+            assert_eq!(payload.removed, vec![4 ^ scramble_id, 5 ^ scramble_id]);
+
+            // Updates come first on the wire, then adds
+            let ids: Vec<u32> = payload.nodes.iter().map(|n| n.node_id ^ scramble_id).collect();
+            assert_eq!(ids, vec![2, 3, 1]);
+            for node in &payload.nodes {
+                assert_eq!((node.x, node.y, node.size), ((-120 + scramble_x) as f32, (340 + scramble_y) as f32, 55));
+            }
+            assert_eq!(payload.nodes[1].flags & 0x01, 0x01);
+            assert_eq!(payload.nodes[0].color, None);
+
+            let add = &payload.nodes[2];
+            assert_eq!(add.color, Some(Color::new(10, 20, 30)));
+            assert_eq!(add.name.as_deref(), Some("Alice"));
+            let skin = if protocol >= 11 { "%doge" } else { "doge" };
+            assert_eq!(add.skin.as_deref(), Some(skin));
+        }
+    }
+
+    #[test]
+    fn test_update_nodes_extended_flags() {
+        let food = UpdateCell { flags: CellFlags { is_food: true, ..CellFlags::default() }, ..cell(1, None) };
+        let friend = UpdateCell { flags: CellFlags { is_friend: true, ..CellFlags::default() }, ..cell(2, None) };
+        let plain = cell(3, None);
+        let flags_for = |protocol: u32| -> Vec<(u8, u8)> {
+            let w = build_update_nodes(protocol, 0, 0, 0, &[], &[food.clone(), friend.clone(), plain.clone()], &[], &[]);
+            let ServerPacket::UpdateNodes(payload) = parse(w, protocol) else {
+                panic!("expected UpdateNodes");
+            };
+            payload.nodes.iter().map(|n| (n.flags & 0x80, n.ext_flags)).collect()
+        };
+
+        // 6 marks food in the flag byte alone; 11 adds a fixed food byte; 13
+        // carries food and friend bits; 17 always sends the byte
+        assert_eq!(flags_for(6), vec![(0x80, 0), (0, 0), (0, 0)]);
+        assert_eq!(flags_for(11), vec![(0x80, 0x01), (0, 0), (0, 0)]);
+        assert_eq!(flags_for(13), vec![(0x80, 0x01), (0x80, 0x02), (0, 0)]);
+        assert_eq!(flags_for(17), vec![(0x80, 0x01), (0x80, 0x02), (0x80, 0)]);
+
+        // Versions in between use the nearest older layout
+        assert_eq!(flags_for(12), flags_for(11));
+        assert_eq!(flags_for(15), flags_for(13));
+        assert_eq!(flags_for(COGAR_PROTOCOL), flags_for(17));
+        assert_eq!([1, 9, 12, 14, 16, 17, 20].map(update_nodes_layout), [6, 6, 11, 13, 13, 17, COGAR_PROTOCOL]);
+        for protocol in [6, 11, 13, 17] {
+            let decoded: Vec<(bool, bool)> = flags_for(protocol).iter()
+                .map(|&(flags, ext)| CellFlags::decode(flags, ext, protocol))
+                .map(|f| (f.is_food, f.is_friend))
+                .collect();
+            let friend = protocol >= 13;
+            assert_eq!(decoded, vec![(true, false), (false, friend), (false, false)]);
+        }
+    }
+
+    #[test]
+    fn test_fine_positions_round_trip() {
+        let drifting = UpdateCell { x: -120.3, y: 340.6, ..cell(1, None) };
+        let position_for = |protocol: u32| {
+            let w = build_update_nodes(protocol, 0, 1000, -2000, &[], std::slice::from_ref(&drifting), &[], &[]);
+            let ServerPacket::UpdateNodes(payload) = parse(w, protocol) else {
+                panic!("expected UpdateNodes");
+            };
+            (payload.nodes[0].x, payload.nodes[0].y)
+        };
+        // Vanilla layouts keep whole units; cogar's gets the nearest quarter
+        assert_eq!(position_for(17), (880.0, -1660.0));
+        assert_eq!(position_for(COGAR_PROTOCOL), (879.75, -1659.5));
+    }
+
+    #[test]
+    fn test_other_packets_round_trip() {
+        let chat = build_chat_message(Color::new(1, 2, 3), "Bob", "hi", true, false, true);
+        assert_eq!(
+            parse(chat, 6),
+            ServerPacket::ChatMessage(ChatMessagePayload {
+                color: Color::new(1, 2, 3),
+                name: "Bob".to_string(),
+                message: "hi".to_string(),
+                is_server: true,
+                is_admin: false,
+                is_mod: true,
+            })
+        );
+
+        let entries = [(true, 7, "me"), (false, 9, "other")];
+        let ServerPacket::LeaderboardFfa(v6) = parse(build_leaderboard_ffa(6, &entries), 6) else {
+            panic!("expected LeaderboardFfa");
+        };
+        assert_eq!(v6.entries[0], LeaderboardFfaEntry { is_me: true, player_id: None, name: "me".to_string() });
+        let ServerPacket::LeaderboardFfa(v11) = parse(build_leaderboard_ffa(11, &entries), 11) else {
+            panic!("expected LeaderboardFfa");
+        };
+        assert_eq!(v11.entries[1].player_id, Some(9));
+
+        let border = build_set_border(-5.0, -6.0, 7.0, 8.0, 0, "Native Ogar", None);
+        let mut payload = SetBorderPayload {
+            min_x: -5.0,
+            min_y: -6.0,
+            max_x: 7.0,
+            max_y: 8.0,
+            game_type: Some(0),
+            server_name: Some("Native Ogar".to_string()),
+            unlimited_zoom: None,
+        };
+        assert_eq!(parse(border, 6), ServerPacket::SetBorder(payload.clone()));
+        payload.unlimited_zoom = Some(true);
+        let border = build_set_border(-5.0, -6.0, 7.0, 8.0, 0, "Native Ogar", Some(true));
+        assert_eq!(parse(border, COGAR_PROTOCOL), ServerPacket::SetBorder(payload));
+
+        let xray_cells = vec![XrayPlayerCell { node_id: 3, x: -50, y: 60, size: 100, color: Color::new(4, 5, 6), name: "X".to_string() }];
+        let ServerPacket::XrayData(xray) = parse(build_xray_data(0, 0, 0, &xray_cells), 6) else {
+            panic!("expected XrayData");
+        };
+        assert_eq!(xray.cells, xray_cells);
+
+        let roster = vec![RosterEntry { name: "bot".to_string(), mass: 1234, color: Color::new(7, 8, 9), is_bot: true }];
+        assert_eq!(parse(build_roster(&roster), 6), ServerPacket::Roster { entries: roster });
+        assert_eq!(parse(build_merge_timer(1, &[(2, 3000)]), 6), ServerPacket::MergeTimer { timers: vec![(3, 3000)] });
+        assert_eq!(
+            parse(build_death_info(DeathCause::Eaten, "bot", 4321, 17), 6),
+            ServerPacket::DeathInfo { cause: DeathCause::Eaten, killer_name: "bot".to_string(), killer_mass: 4321, killer_id: 17 }
+        );
+        assert_eq!(
+            parse(build_death_info(DeathCause::Killed, "", 0, 0), 6),
+            ServerPacket::DeathInfo { cause: DeathCause::Killed, killer_name: String::new(), killer_mass: 0, killer_id: 0 }
+        );
+        let pad = MapShape { kind: MapShapeKind::SpeedPad, min_x: -10.0, min_y: -20.0, max_x: 10.0, max_y: 20.0, speed: 1.5 };
+        assert_eq!(
+            parse(build_map_objects(100, -100, &[pad]), 6),
+            ServerPacket::MapObjects { shapes: vec![MapShape { min_x: 90.0, min_y: -120.0, max_x: 110.0, max_y: -80.0, ..pad }] }
+        );
+        assert_eq!(parse(build_leaderboard_pie(&[0.25, 0.75]), 6), ServerPacket::LeaderboardPie { team_sizes: vec![0.25, 0.75] });
+        let lines = vec!["Round starts in 0:42".to_string(), String::new()];
+        assert_eq!(parse(build_leaderboard_text(&lines), 6), ServerPacket::LeaderboardText { lines });
+        assert_eq!(parse(build_add_node(6, 1), 6), ServerPacket::AddNode { node_id: 7 });
+        // Scrambled past the i16 range, then recovered near the view
+        assert_eq!(parse(build_draw_line(40_000, -3, 500, 20), 6), ServerPacket::DrawLine { x: -25_036, y: 17 });
+        assert_eq!(unwrap_draw_line(-25_036, 40_300.0), 40_500.0);
+        assert_eq!(unwrap_draw_line(17, -100.0), 17.0);
+        assert_eq!(parse(build_server_stat("{}"), 6), ServerPacket::ServerStat { json: "{}".to_string() });
+
+        let inner = vec![build_add_node(6, 1).finish(), build_clear_all().finish()];
+        let ServerPacket::Batch { packets } = parse(build_batch(&inner), 11) else {
+            panic!("expected Batch");
+        };
+        assert_eq!(packets, inner);
+        assert_eq!(ServerPacket::parse(&packets[0], 11).unwrap(), ServerPacket::AddNode { node_id: 7 });
+    }
+
+    #[test]
+    fn test_truncated_packets_are_errors() {
+        let mut data = build_roster(&[RosterEntry { name: String::new(), mass: 1, color: Color::default(), is_bot: false }]).finish().to_vec();
+        data.truncate(5);
+        assert!(matches!(ServerPacket::parse(&data, 6), Err(ProtocolError::UnexpectedEof)));
+        assert!(matches!(ServerPacket::parse(&[0x99], 6), Err(ProtocolError::InvalidOpcode(0x99))));
+        // A text leaderboard claiming far more lines than it carries
+        let mut data = build_leaderboard_text(&[]).finish().to_vec();
+        data[1..5].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(ServerPacket::parse(&data, 6), Err(ProtocolError::UnexpectedEof)));
+        // A batch entry longer than what's left
+        assert!(matches!(ServerPacket::parse(&[0x70, 9, 0, 0, 0, 0x12], 11), Err(ProtocolError::UnexpectedEof)));
+    }
+}
@@ -1,6 +1,8 @@
+use rand::seq::IndexedRandom;
+
 use super::bot_player::Bot;
 use crate::config::Config;
-use crate::world::World;
+use crate::world::{GameRng, World};
 
 /// Bot manager.
 #[derive(Debug, Default)]
@@ -9,25 +11,37 @@ pub struct BotManager {
     pub bots: Vec<Bot>,
     /// Next bot ID counter.
     next_id: u32,
+    /// Skins new bots pick from; empty leaves them without one.
+    skins: Vec<String>,
 }
 
 impl BotManager {
-    /// Create a new bot manager.
-    pub fn new() -> Self {
+    /// Create a new bot manager whose bots wear one of `skins`.
+    pub fn new(skins: Vec<String>) -> Self {
         Self {
             bots: Vec::new(),
             next_id: 1_000_000, // Start bot IDs high to avoid collision with real clients
+            skins,
         }
     }
 
-    /// Add a new bot.
-    pub fn add_bot(&mut self) -> u32 {
+    /// Add a new bot with a random skin from the pool.
+    pub fn add_bot(&mut self, rng: &mut GameRng) -> u32 {
         let id = self.next_id;
         self.next_id += 1;
-        self.bots.push(Bot::new(id));
+        let mut bot = Bot::new(id, rng);
+        bot.skin = self.skins.choose(rng).cloned();
+        self.bots.push(bot);
         id
     }
 
+    /// Put back a bot loaded from a world dump, keeping its ID.
+    pub fn restore_bot(&mut self, bot: Bot) {
+        self.remove_bot(bot.id);
+        self.next_id = self.next_id.max(bot.id.saturating_add(1));
+        self.bots.push(bot);
+    }
+
     /// Remove a bot by ID.
     pub fn remove_bot(&mut self, id: u32) {
         self.bots.retain(|b| b.id != id);
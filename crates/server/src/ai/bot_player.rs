@@ -1,6 +1,6 @@
-use crate::config::Config;
+use crate::config::{BotDifficulty, Config};
 use crate::entity::CellType;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use glam::Vec2;
 use protocol::Color;
 use rand::Rng;
@@ -13,6 +13,10 @@ const BOT_NAMES: &[&str] = &[
     "Roamer", "Wanderer", "Ghost", "Shadow", "Swift", "Tiny", "Big", "Mega",
 ];
 
+/// Ticks a split cell takes to cover most of its boost; a hard bot leads
+/// moving prey by this many ticks of its motion.
+const SPLIT_LEAD_TICKS: f32 = 10.0;
+
 /// A bot player controlled by AI.
 #[derive(Debug)]
 pub struct Bot {
@@ -22,6 +26,8 @@ pub struct Bot {
     pub name: String,
     /// Bot color.
     pub color: Color,
+    /// Local skin name, if any.
+    pub skin: Option<String>,
     /// Current target position.
     pub target: Vec2,
     /// Cell IDs owned by this bot.
@@ -40,19 +46,39 @@ pub struct Bot {
     pub target_pursuit: u32,
     /// ID of the currently pursued target.
     pub split_target_id: Option<u32>,
+    /// Whether the bot has had a cell yet; only respawns are delayed.
+    pub has_spawned: bool,
+    /// Tick a dead bot comes back at, set once its death is noticed.
+    pub respawn_tick: Option<u64>,
+    /// Growth still owed to the cell the bot last spawned with.
+    pub spawn_ramp: Option<SpawnRamp>,
+    /// Largest total mass this life, sampled like a player's.
+    pub peak_mass: f32,
+    /// Other players' cells nearby at the last decision, to tell how they move.
+    seen_positions: HashMap<u32, Vec2>,
+    /// Ticks since `seen_positions` was taken.
+    ticks_since_scan: u32,
+}
+
+/// A freshly respawned bot cell growing to `bots.start_size`.
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnRamp {
+    pub cell: u32,
+    pub ticks_left: u64,
+    pub mass_per_tick: f32,
 }
 
 impl Bot {
     /// Create a new bot with the given ID.
-    pub fn new(id: u32) -> Self {
-        let mut rng = rand::rng();
+    pub fn new(id: u32, rng: &mut GameRng) -> Self {
         let name_idx = rng.random_range(0..BOT_NAMES.len());
         let name = format!("{}{}", BOT_NAMES[name_idx], id % 100);
 
         Self {
             id,
             name,
-            color: World::random_color(),
+            color: World::random_color(rng),
+            skin: None,
             target: Vec2::ZERO,
             cells: Vec::new(),
             decision_cooldown: 0,
@@ -62,6 +88,12 @@ impl Bot {
             split_cooldown: 0,
             target_pursuit: 0,
             split_target_id: None,
+            has_spawned: false,
+            respawn_tick: None,
+            spawn_ramp: None,
+            peak_mass: 0.0,
+            seen_positions: HashMap::new(),
+            ticks_since_scan: 0,
         }
     }
 
@@ -69,6 +101,7 @@ impl Bot {
     pub fn update(&mut self, world: &mut World, config: &Config, team_lookup: &HashMap<u32, u8>) {
         // Reset flags
         self.split_requested = false;
+        self.ticks_since_scan = self.ticks_since_scan.saturating_add(1);
 
         // Decrement split cooldown
         if self.split_cooldown > 0 {
@@ -78,6 +111,7 @@ impl Bot {
         // Check if we need to respawn
         if self.cells.is_empty() {
             self.needs_respawn = true;
+            self.seen_positions.clear();
             return;
         }
 
@@ -114,28 +148,30 @@ impl Bot {
         let mut prey_id: Option<u32> = None;
         let mut prey_size = 0.0;
         let mut prey_pos = Vec2::ZERO;
+        // A cell that could eat one of our halves after a split, close enough to do it
+        let mut threatened = false;
 
-        let merge = config.player.merge_time as f32 <= 0.0;
-        let can_split = (self.cells.len() as f32 * 1.5) < 9.0 && self.split_cooldown == 0;
+        let merge = config.player.recombine_time as f32 <= 0.0;
+        let can_split = config.bots.difficulty != BotDifficulty::Easy
+            && self.cells.len() < config.player.max_cells
+            && self.split_cooldown == 0;
         let split_size_check = my_size / 1.3;
+        let half_size = my_size / std::f32::consts::SQRT_2;
+        let elapsed = self.ticks_since_scan.max(1) as f32;
+        let mut seen_positions = HashMap::with_capacity(self.seen_positions.len());
 
         // Search radius (view box equivalent)
         let search_radius = 2000.0;
-        let nearby = world.find_cells_in_radius(my_pos.x, my_pos.y, search_radius);
+        let nearby = world.find_nearby(my_pos.x, my_pos.y, search_radius);
         let num_view_nodes = nearby.len().max(1) as f32;
 
-        for &check_id in &nearby {
+        for check in &nearby {
+            let check_id = check.id;
             if self.cells.contains(&check_id) {
                 continue;
             }
 
-            let (check_pos, check_size, check_type, check_owner) = match world.get_cell(check_id) {
-                Some(cell) => {
-                    let data = cell.data();
-                    (data.position, data.size, data.cell_type, data.owner_id)
-                }
-                None => continue,
-            };
+            let (check_pos, check_size, check_type, check_owner) = (check.position(), check.size, check.cell_type, check.owner_id);
 
             if check_owner == Some(self.id) {
                 continue;
@@ -151,6 +187,13 @@ impl Bot {
                         }
                     }
 
+                    seen_positions.insert(check_id, check_pos);
+                    if check_size > half_size * 1.15
+                        && check_pos.distance(my_pos) - check_size < self.split_reach(check_size, config)
+                    {
+                        threatened = true;
+                    }
+
                     if my_size > check_size * 1.3 {
                         influence = check_size / num_view_nodes.ln().max(1.0);
                     } else if check_size > my_size * 1.3 {
@@ -195,11 +238,15 @@ impl Bot {
                 if can_split && check_type == CellType::Player && split_size_check > check_size {
                     let min_eat_fraction = if merge { 0.1 } else { 0.4 };
                     if my_size * min_eat_fraction < check_size {
-                        if self.split_kill(my_size, check_size, dist, config) {
-                            if check_size > prey_size {
-                                prey_size = check_size;
-                                prey_id = Some(check_id);
-                                prey_pos = check_pos;
+                        if self.split_reach(my_size, config) >= dist && check_size > prey_size {
+                            prey_size = check_size;
+                            prey_id = Some(check_id);
+                            prey_pos = check_pos;
+                            // Lead the prey by how it moved since the last decision
+                            if config.bots.difficulty == BotDifficulty::Hard
+                                && let Some(&seen) = self.seen_positions.get(&check_id)
+                            {
+                                prey_pos += world.border.delta(seen, check_pos) / elapsed * SPLIT_LEAD_TICKS;
                             }
                         }
                     }
@@ -207,7 +254,10 @@ impl Bot {
             }
         }
 
-        if let Some(id) = prey_id {
+        self.seen_positions = seen_positions;
+        self.ticks_since_scan = 0;
+
+        if let Some(id) = prey_id.filter(|_| !threatened) {
             debug!("Bot {} targeting prey {} (size {}) for split", self.id, id, prey_size);
             self.target = prey_pos;
             self.split_target_id = Some(id);
@@ -219,19 +269,17 @@ impl Bot {
                 result = result.normalize();
                 self.target = my_pos + result * 2000.0;
             } else {
-                let mut rng = rand::rng();
-                let angle = rng.random_range(0.0..std::f32::consts::TAU);
+                let angle = world.rng.random_range(0.0..std::f32::consts::TAU);
                 self.target = my_pos + Vec2::new(angle.cos(), angle.sin()) * 400.0;
             }
         }
 
-        self.target.x = self.target.x.clamp(world.border.min_x, world.border.max_x);
-        self.target.y = self.target.y.clamp(world.border.min_y, world.border.max_y);
+        self.target = world.border.confine(self.target);
     }
 
-    fn split_kill(&self, my_size: f32, _prey_size: f32, dist: f32, config: &Config) -> bool {
-        let speed = (1.3 * config.player.split_speed as f32).max(my_size / 1.4142 * 4.5);
-        speed >= dist
+    /// How far a cell of `size` reaches by splitting.
+    fn split_reach(&self, size: f32, config: &Config) -> f32 {
+        (1.3 * config.player.split_speed as f32).max(size / std::f32::consts::SQRT_2 * 4.5)
     }
 
     fn get_largest_cell(&self, world: &World) -> (Vec2, f32) {
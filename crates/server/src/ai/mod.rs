@@ -1,5 +1,5 @@
 pub mod bot_player;
 pub mod bot_manager;
 
-// pub use bot_player::Bot; // Removed unused
+pub use bot_player::{Bot, SpawnRamp};
 pub use bot_manager::BotManager;
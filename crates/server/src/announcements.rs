@@ -0,0 +1,177 @@
+//! Chat announcements for notable kills, and for players joining and leaving.
+//!
+//! A player who eats several others in quick succession is announced as on a
+//! rampage, and so is anyone who eats a very large player. Kill times are
+//! kept per player and pruned to the streak window; a player's streak ends
+//! when they die, and each player is announced at most once per cooldown so
+//! a feeding frenzy can't flood the chat.
+//!
+//! Join and leave notices are throttled by IP instead, since a reconnect
+//! loop comes back as a new client every time.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+use crate::config::AnnouncementsConfig;
+
+/// What a kill is announced for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Announcement {
+    /// This many kills within the streak window.
+    Rampage { kills: usize },
+    /// The victim had this much mass.
+    BigKill { mass: f32 },
+}
+
+#[derive(Debug, Default)]
+struct KillRecord {
+    kills: VecDeque<u64>,
+    last_announced_ms: Option<u64>,
+}
+
+/// Per-player kill history.
+#[derive(Debug, Default)]
+pub struct KillAnnouncer {
+    players: HashMap<u32, KillRecord>,
+}
+
+impl KillAnnouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log that `killer` ate a player with `victim_mass`, returning what to
+    /// announce, if anything. A rampage wins when both apply.
+    pub fn record_kill(&mut self, config: &AnnouncementsConfig, killer: u32, victim_mass: f32, now_ms: u64) -> Option<Announcement> {
+        let window_ms = (config.streak_window_seconds * 1000.0) as u64;
+        let record = self.players.entry(killer).or_default();
+        while record.kills.front().is_some_and(|&at| now_ms.saturating_sub(at) > window_ms) {
+            record.kills.pop_front();
+        }
+        record.kills.push_back(now_ms);
+
+        let announcement = if config.streak_kills > 0 && record.kills.len() >= config.streak_kills as usize {
+            Announcement::Rampage { kills: record.kills.len() }
+        } else if config.big_kill_mass > 0.0 && f64::from(victim_mass) >= config.big_kill_mass {
+            Announcement::BigKill { mass: victim_mass }
+        } else {
+            return None;
+        };
+        let cooldown_ms = (config.cooldown_seconds * 1000.0) as u64;
+        if record.last_announced_ms.is_some_and(|at| now_ms.saturating_sub(at) < cooldown_ms) {
+            return None;
+        }
+        record.last_announced_ms = Some(now_ms);
+        Some(announcement)
+    }
+
+    /// The player died: their streak is over, the cooldown still runs.
+    pub fn end_streak(&mut self, id: u32) {
+        if let Some(record) = self.players.get_mut(&id) {
+            record.kills.clear();
+        }
+    }
+
+    /// Drop everything about a player that left.
+    pub fn remove_player(&mut self, id: u32) {
+        self.players.remove(&id);
+    }
+}
+
+/// When each IP last had a join or leave notice.
+#[derive(Debug, Default)]
+pub struct PresenceThrottle {
+    last_notice_ms: HashMap<IpAddr, u64>,
+}
+
+impl PresenceThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a notice for `ip` may go out now; if so, the cooldown starts.
+    pub fn try_notice(&mut self, config: &AnnouncementsConfig, ip: IpAddr, now_ms: u64) -> bool {
+        let cooldown_ms = (config.join_leave_cooldown_seconds * 1000.0) as u64;
+        self.last_notice_ms.retain(|_, &mut at| now_ms.saturating_sub(at) < cooldown_ms);
+        if self.last_notice_ms.contains_key(&ip) {
+            return false;
+        }
+        self.last_notice_ms.insert(ip, now_ms);
+        true
+    }
+}
+
+/// Whole mass with thousands separators, e.g. `3,200`.
+pub fn format_mass(mass: f32) -> String {
+    let digits = (mass.max(0.0).round() as u64).to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence_throttle_per_ip() {
+        let config = AnnouncementsConfig { join_leave_cooldown_seconds: 30.0, ..Default::default() };
+        let mut throttle = PresenceThrottle::new();
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+
+        assert!(throttle.try_notice(&config, a, 0));
+        assert!(!throttle.try_notice(&config, a, 1_000));
+        assert!(throttle.try_notice(&config, b, 1_000));
+        assert!(!throttle.try_notice(&config, a, 29_999));
+        assert!(throttle.try_notice(&config, a, 30_000));
+
+        assert_eq!(format_mass(3_200.4), "3,200");
+        assert_eq!(format_mass(999.0), "999");
+        assert_eq!(format_mass(1_234_567.0), "1,234,567");
+    }
+
+    #[test]
+    fn test_streak_window_and_cooldown() {
+        let config = AnnouncementsConfig {
+            streak_kills: 3,
+            streak_window_seconds: 10.0,
+            big_kill_mass: 10_000.0,
+            cooldown_seconds: 30.0,
+            ..Default::default()
+        };
+        let mut announcer = KillAnnouncer::new();
+
+        // Kills spread wider than the window never add up
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 0), None);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 6_000), None);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 12_000), None);
+        // The third within 10s (6s, 12s, 14s) is a rampage
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 14_000), Some(Announcement::Rampage { kills: 3 }));
+        // Further kills are held back by the cooldown
+        assert_eq!(announcer.record_kill(&config, 1, 20_000.0, 15_000), None);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 40_000), None);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 43_000), None);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 44_000), Some(Announcement::Rampage { kills: 3 }));
+
+        // Dying ends the streak
+        announcer.end_streak(1);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 80_000), None);
+        assert_eq!(announcer.record_kill(&config, 1, 100.0, 81_000), None);
+
+        // A single big kill is enough on its own, once per cooldown
+        assert_eq!(announcer.record_kill(&config, 2, 12_000.0, 0), Some(Announcement::BigKill { mass: 12_000.0 }));
+        assert_eq!(announcer.record_kill(&config, 2, 12_000.0, 1_000), None);
+
+        // Both checks can be switched off
+        let off = AnnouncementsConfig { streak_kills: 0, big_kill_mass: 0.0, ..config };
+        for at in 0..5 {
+            assert_eq!(announcer.record_kill(&off, 3, 50_000.0, at), None);
+        }
+    }
+}
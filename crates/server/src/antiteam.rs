@@ -0,0 +1,260 @@
+//! FFA anti-teaming heuristic.
+//!
+//! Every time one player's ejected mass is eaten by another player, the
+//! transfer is logged for both of them. Once a second the game samples
+//! whether each pair with recent transfers is still close together. A pair
+//! that exchanges enough mass *and* spends enough time together within the
+//! sliding window gets a strike each; strikes raise the decay rate until they
+//! expire.
+//!
+//! All bookkeeping is bounded: each player keeps a fixed-size ring of recent
+//! events, and records are dropped on disconnect.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::config::AntiTeamConfig;
+
+/// Events kept per player; the oldest are dropped once full.
+pub const RING_CAPACITY: usize = 64;
+
+/// Something that happened between a player and a partner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PairEvent {
+    /// Ejected mass exchanged, in either direction.
+    Transfer { mass: f32 },
+    /// The pair spent this long close together.
+    Near { duration_ms: u64 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    partner: u32,
+    at_ms: u64,
+    event: PairEvent,
+}
+
+#[derive(Debug, Default)]
+struct PlayerRecord {
+    events: VecDeque<Entry>,
+    strikes: u32,
+    last_strike_ms: u64,
+}
+
+impl PlayerRecord {
+    fn push(&mut self, entry: Entry) {
+        if self.events.len() >= RING_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(entry);
+    }
+
+    /// Drop events that fell out of the window.
+    fn prune(&mut self, now_ms: u64, window_ms: u64) {
+        while self.events.front().is_some_and(|e| now_ms.saturating_sub(e.at_ms) > window_ms) {
+            self.events.pop_front();
+        }
+    }
+}
+
+/// Mass exchanged and time spent together by a pair within the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PairScore {
+    pub mass: f32,
+    pub near_ms: u64,
+}
+
+/// Per-player transfer and proximity history.
+#[derive(Debug, Default)]
+pub struct AntiTeam {
+    players: HashMap<u32, PlayerRecord>,
+}
+
+impl AntiTeam {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log `mass` of `from`'s ejected mass being eaten by `to`.
+    pub fn record_transfer(&mut self, from: u32, to: u32, mass: f32, now_ms: u64) {
+        if from == to {
+            return;
+        }
+        self.push_pair(from, to, PairEvent::Transfer { mass }, now_ms);
+    }
+
+    /// Log that `a` and `b` were close together for `duration_ms`.
+    pub fn record_near(&mut self, a: u32, b: u32, duration_ms: u64, now_ms: u64) {
+        if a == b {
+            return;
+        }
+        self.push_pair(a, b, PairEvent::Near { duration_ms }, now_ms);
+    }
+
+    fn push_pair(&mut self, a: u32, b: u32, event: PairEvent, now_ms: u64) {
+        self.players.entry(a).or_default().push(Entry { partner: b, at_ms: now_ms, event });
+        self.players.entry(b).or_default().push(Entry { partner: a, at_ms: now_ms, event });
+    }
+
+    /// Pairs (lower id first) that exchanged mass within the window, for
+    /// proximity sampling.
+    pub fn active_pairs(&self, now_ms: u64, window_ms: u64) -> Vec<(u32, u32)> {
+        let mut pairs: Vec<(u32, u32)> = self.players.iter()
+            .flat_map(|(&id, record)| {
+                record.events.iter()
+                    .filter(move |e| id < e.partner && now_ms.saturating_sub(e.at_ms) <= window_ms)
+                    .filter(|e| matches!(e.event, PairEvent::Transfer { .. }))
+                    .map(move |e| (id, e.partner))
+            })
+            .collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    /// What `a` and `b` did together within the window, as seen from `a`.
+    pub fn pair_score(&self, a: u32, b: u32, now_ms: u64, window_ms: u64) -> PairScore {
+        let mut score = PairScore::default();
+        let Some(record) = self.players.get(&a) else {
+            return score;
+        };
+        for e in record.events.iter().filter(|e| e.partner == b && now_ms.saturating_sub(e.at_ms) <= window_ms) {
+            match e.event {
+                PairEvent::Transfer { mass } => score.mass += mass,
+                PairEvent::Near { duration_ms } => score.near_ms += duration_ms,
+            }
+        }
+        score
+    }
+
+    /// Prune old events and strike every pair over both thresholds. Returns
+    /// the flagged pairs; their history is cleared so each strike needs a
+    /// fresh window of evidence.
+    pub fn evaluate(&mut self, config: &AntiTeamConfig, now_ms: u64) -> Vec<(u32, u32)> {
+        let window_ms = (config.window_seconds * 1000.0) as u64;
+        let near_ms = (config.proximity_seconds * 1000.0) as u64;
+        let penalty_ms = (config.penalty_seconds * 1000.0) as u64;
+
+        for record in self.players.values_mut() {
+            record.prune(now_ms, window_ms);
+            if record.strikes > 0 && now_ms.saturating_sub(record.last_strike_ms) > penalty_ms {
+                record.strikes = 0;
+            }
+        }
+
+        let flagged: Vec<(u32, u32)> = self.active_pairs(now_ms, window_ms).into_iter()
+            .filter(|&(a, b)| {
+                let score = self.pair_score(a, b, now_ms, window_ms);
+                score.mass as f64 >= config.mass_threshold && score.near_ms >= near_ms
+            })
+            .collect();
+
+        for &(a, b) in &flagged {
+            for (id, partner) in [(a, b), (b, a)] {
+                if let Some(record) = self.players.get_mut(&id) {
+                    record.events.retain(|e| e.partner != partner);
+                    record.strikes = (record.strikes + 1).min(config.max_strikes);
+                    record.last_strike_ms = now_ms;
+                }
+            }
+        }
+
+        // Records with nothing left to track are dropped
+        self.players.retain(|_, r| r.strikes > 0 || !r.events.is_empty());
+        flagged
+    }
+
+    /// Current strikes against a player.
+    pub fn strikes(&self, id: u32) -> u32 {
+        self.players.get(&id).map_or(0, |r| r.strikes)
+    }
+
+    /// Decay rate multiplier for a player: 1.0 plus `penalty_step` per strike.
+    pub fn decay_multiplier(&self, config: &AntiTeamConfig, id: u32) -> f32 {
+        1.0 + self.strikes(id) as f32 * config.penalty_step as f32
+    }
+
+    /// Forget a disconnected player, including their partners' events about them.
+    pub fn remove_player(&mut self, id: u32) {
+        self.players.remove(&id);
+        for record in self.players.values_mut() {
+            record.events.retain(|e| e.partner != id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AntiTeamConfig {
+        AntiTeamConfig {
+            enabled: true,
+            window_seconds: 10.0,
+            mass_threshold: 100.0,
+            proximity_seconds: 3.0,
+            ..AntiTeamConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_transfers_outside_window_are_not_counted() {
+        let mut at = AntiTeam::new();
+        at.record_transfer(1, 2, 60.0, 0);
+        at.record_transfer(2, 1, 60.0, 8_000);
+        assert_eq!(at.pair_score(1, 2, 9_000, 10_000).mass, 120.0);
+        assert_eq!(at.pair_score(2, 1, 12_000, 10_000).mass, 60.0);
+        assert_eq!(at.active_pairs(12_000, 10_000), vec![(1, 2)]);
+        assert!(at.active_pairs(20_000, 10_000).is_empty());
+    }
+
+    #[test]
+    fn test_strike_needs_mass_and_proximity() {
+        let config = config();
+        let mut at = AntiTeam::new();
+        at.record_transfer(1, 2, 150.0, 1_000);
+        // Enough mass, not enough time together
+        at.record_near(1, 2, 1_000, 1_000);
+        assert!(at.evaluate(&config, 2_000).is_empty());
+
+        at.record_near(1, 2, 1_000, 2_000);
+        at.record_near(1, 2, 1_000, 3_000);
+        assert_eq!(at.evaluate(&config, 3_000), vec![(1, 2)]);
+        assert_eq!((at.strikes(1), at.strikes(2)), (1, 1));
+        assert_eq!(at.decay_multiplier(&config, 1), 2.0);
+
+        // Evidence was consumed: no second strike without new transfers
+        assert!(at.evaluate(&config, 4_000).is_empty());
+        assert_eq!(at.strikes(1), 1);
+    }
+
+    #[test]
+    fn test_strikes_cap_and_expire() {
+        let config = AntiTeamConfig { max_strikes: 2, ..config() };
+        let mut at = AntiTeam::new();
+        for round in 0..3 {
+            let now = round * 1_000;
+            at.record_transfer(1, 2, 200.0, now);
+            at.record_near(1, 2, 5_000, now);
+            at.evaluate(&config, now);
+        }
+        assert_eq!(at.strikes(1), 2);
+
+        // Lifted after penalty_seconds without a new strike, then forgotten
+        at.evaluate(&config, 2_000 + (config.penalty_seconds * 1000.0) as u64 + 1);
+        assert_eq!(at.strikes(1), 0);
+        assert!(at.players.is_empty());
+    }
+
+    #[test]
+    fn test_ring_is_bounded_and_pruned_on_disconnect() {
+        let mut at = AntiTeam::new();
+        for i in 0..(RING_CAPACITY as u64 * 2) {
+            at.record_transfer(1, 2, 10.0, i);
+        }
+        assert_eq!(at.players[&1].events.len(), RING_CAPACITY);
+
+        at.record_transfer(3, 1, 10.0, 0);
+        at.remove_player(1);
+        assert!(at.players.values().all(|r| r.events.iter().all(|e| e.partner != 1)));
+    }
+}
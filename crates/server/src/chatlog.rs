@@ -0,0 +1,259 @@
+//! Persistent chat and command log for moderation.
+//!
+//! With `logging.chat_file` set, the game loop hands every chat message and
+//! command to a writer task over a bounded channel; the task does all disk
+//! I/O so a slow disk never stalls a tick. Entries are dropped rather than
+//! queued when the channel is full.
+//!
+//! One line per entry, in UTC:
+//!
+//! ```text
+//! 2026-10-16 18:04:11 [/ffa] chat #12 203.0.113.7 "Bob": gg
+//! 2026-10-16 18:04:12 [/ffa] chat (dropped) #12 203.0.113.7 "Bob": gg
+//! 2026-10-16 18:05:40 [/ffa] command (op) #3 198.51.100.2 "Mod": /kick 12
+//! ```
+//!
+//! The file is renamed to `<file>.<YYYY-MM-DD>` (then `.1`, `.2`, ... for
+//! later files of the same day) when the day changes or once it would grow
+//! past `logging.chat_max_bytes`.
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::LoggingConfig;
+
+/// Entries buffered between the game loop and the writer before they drop.
+pub const CHAT_LOG_CHANNEL_CAPACITY: usize = 1024;
+/// Stands in for the operator password wherever it shows up.
+pub const REDACTED: &str = "[redacted]";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// What kind of line an entry is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatLogKind {
+    /// A chat message that was broadcast.
+    Chat,
+    /// A chat message dropped by the rate limit.
+    Dropped,
+    /// A `/` command, whether or not it succeeded.
+    Command { operator: bool },
+}
+
+/// One chat message or command, as handed to the writer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatLogEntry {
+    /// Unix seconds.
+    pub time: u64,
+    /// Path of the room it was sent in.
+    pub room: String,
+    pub client_id: u32,
+    pub ip: IpAddr,
+    pub name: String,
+    pub kind: ChatLogKind,
+    /// The message or command line, already redacted.
+    pub text: String,
+}
+
+impl ChatLogEntry {
+    /// The entry's line in the log file, newline included.
+    pub fn line(&self) -> String {
+        let (year, month, day) = civil_date(self.time);
+        let secs = self.time % SECONDS_PER_DAY;
+        let kind = match self.kind {
+            ChatLogKind::Chat => "chat",
+            ChatLogKind::Dropped => "chat (dropped)",
+            ChatLogKind::Command { operator: false } => "command",
+            ChatLogKind::Command { operator: true } => "command (op)",
+        };
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} [{}] {} #{} {} \"{}\": {}\n",
+            year, month, day, secs / 3600, secs / 60 % 60, secs % 60,
+            self.room, kind, self.client_id, self.ip, escape(&self.name), escape(&self.text)
+        )
+    }
+}
+
+/// `text` with every occurrence of `password` replaced by [`REDACTED`].
+/// An empty password redacts nothing.
+pub fn redact(text: &str, password: &str) -> String {
+    if password.is_empty() {
+        text.to_string()
+    } else {
+        text.replace(password, REDACTED)
+    }
+}
+
+/// Escape control characters (and quotes) so one entry is always one line
+/// and a name can't forge the fields after it.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_control() || c == '"' || c == '\\' {
+            out.extend(c.escape_debug());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The UTC calendar date of a Unix time, as (year, month, day).
+fn civil_date(secs: u64) -> (i64, u32, u32) {
+    // Howard Hinnant's days-from-civil inverse, on a March-based year
+    let z = (secs / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Start the writer task for `config.chat_file`. The returned sender is fed
+/// from the game loop with `try_send`.
+pub fn spawn_writer(config: &LoggingConfig) -> mpsc::Sender<ChatLogEntry> {
+    let (tx, rx) = mpsc::channel(CHAT_LOG_CHANNEL_CAPACITY);
+    let path = PathBuf::from(&config.chat_file);
+    let max_bytes = config.chat_max_bytes;
+    tokio::spawn(async move {
+        info!("Logging chat to {}", path.display());
+        if let Err(e) = run_writer(rx, &path, max_bytes).await {
+            warn!("Chat log {} stopped: {}", path.display(), e);
+        }
+    });
+    tx
+}
+
+struct LogFile {
+    writer: BufWriter<tokio::fs::File>,
+    len: u64,
+    /// Days since the Unix epoch the file's entries are from.
+    day: u64,
+}
+
+impl LogFile {
+    /// Open `path` for appending. A non-empty file keeps the day it was last
+    /// written, so a restart the next morning still rotates it.
+    async fn open(path: &Path) -> std::io::Result<Self> {
+        let file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+        let metadata = file.metadata().await?;
+        let modified = metadata.modified().ok()
+            .and_then(|at| at.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or_else(crate::highscores::unix_now, |since| since.as_secs());
+        Ok(Self { writer: BufWriter::new(file), len: metadata.len(), day: modified / SECONDS_PER_DAY })
+    }
+}
+
+async fn run_writer(mut rx: mpsc::Receiver<ChatLogEntry>, path: &Path, max_bytes: u64) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = LogFile::open(path).await?;
+
+    while let Some(entry) = rx.recv().await {
+        let line = entry.line();
+        let day = entry.time / SECONDS_PER_DAY;
+        let full = max_bytes > 0 && file.len + line.len() as u64 > max_bytes;
+        if file.len == 0 {
+            file.day = day;
+        } else if day != file.day || full {
+            file.writer.flush().await?;
+            let rotated = rotated_path(path, file.day).await?;
+            tokio::fs::rename(path, &rotated).await?;
+            file = LogFile::open(path).await?;
+            file.day = day;
+        }
+
+        file.writer.write_all(line.as_bytes()).await?;
+        file.len += line.len() as u64;
+        // Flush whenever the game loop isn't waiting on us
+        if rx.is_empty() {
+            file.writer.flush().await?;
+        }
+    }
+
+    file.writer.flush().await
+}
+
+/// First free `<path>.<YYYY-MM-DD>[.n]` for a file from `day`.
+async fn rotated_path(path: &Path, day: u64) -> std::io::Result<PathBuf> {
+    let (year, month, date) = civil_date(day * SECONDS_PER_DAY);
+    let base = format!("{}.{:04}-{:02}-{:02}", path.display(), year, month, date);
+    let mut candidate = PathBuf::from(&base);
+    let mut n = 1;
+    while tokio::fs::try_exists(&candidate).await? {
+        candidate = PathBuf::from(format!("{}.{}", base, n));
+        n += 1;
+    }
+    Ok(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(time: u64, kind: ChatLogKind, text: &str) -> ChatLogEntry {
+        ChatLogEntry {
+            time,
+            room: "/".to_string(),
+            client_id: 7,
+            ip: "203.0.113.7".parse().unwrap(),
+            name: "Bob".to_string(),
+            kind,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_line_format() {
+        // 2026-10-16 18:04:11 UTC
+        let time = 1_792_173_851;
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+        assert_eq!(
+            entry(time, ChatLogKind::Chat, "gg").line(),
+            "2026-10-16 18:04:11 [/] chat #7 203.0.113.7 \"Bob\": gg\n"
+        );
+        assert_eq!(
+            entry(time, ChatLogKind::Command { operator: true }, "/say \"hi\"\nfake line").line(),
+            "2026-10-16 18:04:11 [/] command (op) #7 203.0.113.7 \"Bob\": /say \\\"hi\\\"\\nfake line\n"
+        );
+        assert_eq!(redact("my pw is hunter2", "hunter2"), "my pw is [redacted]");
+        assert_eq!(redact("hunter2", ""), "hunter2");
+    }
+
+    #[tokio::test]
+    async fn test_writer_rotates_daily_and_by_size() {
+        let dir = std::env::temp_dir().join(format!("cogar-chatlog-{}", std::process::id()));
+        let path = dir.join("chat.log");
+        let day = 20_000 * SECONDS_PER_DAY;
+        let line_len = entry(day, ChatLogKind::Chat, "hello").line().len() as u64;
+
+        let (tx, rx) = mpsc::channel(CHAT_LOG_CHANNEL_CAPACITY);
+        let writer_path = path.clone();
+        // Room for two lines per file
+        let writer = tokio::spawn(async move { run_writer(rx, &writer_path, line_len * 2).await });
+        for time in [day, day + 1, day + 2, day + SECONDS_PER_DAY] {
+            tx.send(entry(time, ChatLogKind::Chat, "hello")).await.unwrap();
+        }
+        drop(tx);
+        writer.await.unwrap().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap().lines().count();
+        // Full after two lines, then the next day starts a new file
+        let (year, month, date) = civil_date(day);
+        let first = format!("chat.log.{:04}-{:02}-{:02}", year, month, date);
+        assert_eq!(read(&first), 2);
+        assert_eq!(read(&format!("{}.1", first)), 1);
+        assert_eq!(read("chat.log"), 1);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,15 @@
 //! Server configuration.
 
+use protocol::Color;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 use std::path::Path;
 
+/// Commented default `config.toml`, written on first run.
+///
+/// Kept in sync with [`Config::default`] by a unit test.
+pub const DEFAULT_CONFIG_TEMPLATE: &str = include_str!("config.default.toml");
+
 /// Root configuration structure.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
@@ -12,6 +18,8 @@ pub struct Config {
     #[serde(default)]
     pub border: BorderConfig,
     #[serde(default)]
+    pub map: MapConfig,
+    #[serde(default)]
     pub player: PlayerConfig,
     #[serde(default)]
     pub food: FoodConfig,
@@ -19,6 +27,23 @@ pub struct Config {
     pub virus: VirusConfig,
     #[serde(default)]
     pub eject: EjectConfig,
+    #[serde(default)]
+    pub bots: BotsConfig,
+    #[serde(default)]
+    pub antiteam: AntiTeamConfig,
+    #[serde(default)]
+    pub announcements: AnnouncementsConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    #[serde(default)]
+    pub tracker: TrackerConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Extra worlds hosted on the same listener (empty = one world on every path).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rooms: Vec<RoomConfig>,
 }
 
 impl Config {
@@ -29,23 +54,469 @@ impl Config {
             let contents = std::fs::read_to_string(path)?;
             Ok(toml::from_str(&contents)?)
         } else {
-            info!("No config.toml found, creating default config");
-            let default_config = Self::default();
-            std::fs::write(path, toml::to_string_pretty(&default_config)?)?;
-            Ok(default_config)
+            std::fs::write(path, DEFAULT_CONFIG_TEMPLATE)?;
+            let written = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            info!("No config.toml found, wrote commented defaults to {}", written.display());
+            Ok(Self::default())
+        }
+    }
+
+    /// Check cross-field invariants that serde can't express.
+    ///
+    /// Every problem is collected so operators can fix the whole file in one
+    /// pass instead of restarting once per mistake.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, field: &'static str, message: String| {
+            if !ok {
+                errors.push(ConfigError { field, message });
+            }
+        };
+
+        let s = &self.server;
+        check(
+            (10..=200).contains(&s.tick_interval_ms),
+            "server.tick_interval_ms",
+            format!("must be in 10..=200 (got {})", s.tick_interval_ms),
+        );
+        check(
+            s.max_connections > 0,
+            "server.max_connections",
+            "must be at least 1".to_string(),
+        );
+        check(
+            s.ip_limit > 0,
+            "server.ip_limit",
+            "must be at least 1".to_string(),
+        );
+        check(
+            s.send_timeout > 0,
+            "server.send_timeout",
+            "must be at least 1".to_string(),
+        );
+        check(
+            s.handshake_timeout > 0,
+            "server.handshake_timeout",
+            "must be at least 1".to_string(),
+        );
+        check(
+            s.metrics_port != Some(s.port),
+            "server.metrics_port",
+            format!("must differ from server.port (both {})", s.port),
+        );
+        check(
+            s.max_packet_bytes >= 64,
+            "server.max_packet_bytes",
+            format!("must be at least 64 (got {})", s.max_packet_bytes),
+        );
+        check(
+            s.max_view_width >= 1920 && s.max_view_height >= 1080,
+            "server.max_view_width",
+            format!(
+                "max_view_width and max_view_height must be at least 1920x1080 (got {}x{})",
+                s.max_view_width, s.max_view_height
+            ),
+        );
+        check(
+            s.view_margin.is_finite() && s.view_margin >= 1.0,
+            "server.view_margin",
+            format!("must be at least 1.0 (got {})", s.view_margin),
+        );
+        check(
+            s.scramble_level <= 2,
+            "server.scramble_level",
+            format!("must be 0, 1 or 2 (got {})", s.scramble_level),
+        );
+        check(
+            s.allowed_origins.iter().all(|origin| !origin.is_empty()),
+            "server.allowed_origins",
+            "must not contain empty entries".to_string(),
+        );
+        if let Some(tls) = &s.tls {
+            check(
+                !tls.cert_path.is_empty() && !tls.key_path.is_empty(),
+                "server.tls",
+                "cert_path and key_path must both be set".to_string(),
+            );
+        }
+        let limits = &s.packet_limits;
+        check(
+            limits.split_per_second >= 0.0
+                && limits.eject_per_second >= 0.0
+                && limits.join_per_second >= 0.0
+                && limits.chat_per_second >= 0.0,
+            "server.packet_limits",
+            "split_per_second, eject_per_second, join_per_second and chat_per_second must not be negative".to_string(),
+        );
+        check(
+            limits.abuse_factor >= 1.0 && limits.abuse_seconds > 0,
+            "server.packet_limits",
+            format!(
+                "abuse_factor must be at least 1 and abuse_seconds at least 1 (got {} and {})",
+                limits.abuse_factor, limits.abuse_seconds
+            ),
+        );
+        check(
+            s.gamemode <= 6,
+            "server.gamemode",
+            format!("must be in 0..=6 (got {})", s.gamemode),
+        );
+
+        let p = &self.player;
+        check(
+            p.min_size > 0.0,
+            "player.min_size",
+            format!("must be greater than 0 (got {})", p.min_size),
+        );
+        check(
+            p.min_size <= p.start_size && p.start_size <= p.max_size,
+            "player.start_size",
+            format!(
+                "must be between player.min_size ({}) and player.max_size ({}) (got {})",
+                p.min_size, p.max_size, p.start_size
+            ),
+        );
+        check(
+            p.max_cells > 0,
+            "player.max_cells",
+            "must be at least 1 (16 is typical)".to_string(),
+        );
+        check(
+            p.speed > 0.0,
+            "player.speed",
+            format!("must be greater than 0 (got {})", p.speed),
+        );
+        check(
+            (0.0..1.0).contains(&p.decay_rate),
+            "player.decay_rate",
+            format!("must be in 0.0..1.0 (got {})", p.decay_rate),
+        );
+        check(
+            p.min_decay_size >= p.min_size,
+            "player.min_decay_size",
+            format!(
+                "must be at least player.min_size ({}) (got {})",
+                p.min_size, p.min_decay_size
+            ),
+        );
+        check(
+            (0.0..=2.0).contains(&p.decay_mass_exponent),
+            "player.decay_mass_exponent",
+            format!("must be in 0.0..=2.0 (got {})", p.decay_mass_exponent),
+        );
+        check(
+            p.recombine_time >= 0.0,
+            "player.recombine_time",
+            format!("must not be negative (got {})", p.recombine_time),
+        );
+        check(
+            p.max_nick_length > 0,
+            "player.max_nick_length",
+            "must be at least 1".to_string(),
+        );
+        check(
+            p.reconnect_grace_seconds >= 0.0,
+            "player.reconnect_grace_seconds",
+            format!("must not be negative (got {})", p.reconnect_grace_seconds),
+        );
+
+        let b = &self.border;
+        let min_border = p.start_size * 2.0;
+        check(
+            b.width > min_border && b.height > min_border,
+            "border",
+            format!(
+                "width and height must exceed twice player.start_size ({}) (got {}x{})",
+                min_border, b.width, b.height
+            ),
+        );
+
+        let f = &self.food;
+        check(
+            f.min_size > 0.0 && f.min_size <= f.max_size,
+            "food.min_size",
+            format!(
+                "must be greater than 0 and at most food.max_size ({}) (got {})",
+                f.max_size, f.min_size
+            ),
+        );
+        check(
+            f.min_amount <= f.max_amount,
+            "food.min_amount",
+            format!(
+                "must be at most food.max_amount ({}) (got {})",
+                f.max_amount, f.min_amount
+            ),
+        );
+
+        check(
+            (0.0..=1.0).contains(&f.bonus_chance),
+            "food.bonus_chance",
+            format!("must be in 0.0..=1.0 (got {})", f.bonus_chance),
+        );
+        check(
+            (0.0..=1.0).contains(&f.sector_bias),
+            "food.sector_bias",
+            format!("must be in 0.0..=1.0 (got {})", f.sector_bias),
+        );
+        check(
+            f.bonus_mass_multiplier >= 1.0,
+            "food.bonus_mass_multiplier",
+            format!("must be at least 1.0, 5.0..=10.0 is typical (got {})", f.bonus_mass_multiplier),
+        );
+        for hex in &f.palette {
+            check(
+                parse_hex_color(hex).is_some(),
+                "food.palette",
+                format!("entries must look like \"#rrggbb\" (got {:?})", hex),
+            );
+        }
+
+        let v = &self.virus;
+        check(
+            v.min_size > 0.0 && v.min_size <= v.max_size,
+            "virus.min_size",
+            format!(
+                "must be greater than 0 and at most virus.max_size ({}) (got {})",
+                v.max_size, v.min_size
+            ),
+        );
+        check(
+            v.min_amount <= v.max_amount,
+            "virus.min_amount",
+            format!(
+                "must be at most virus.max_amount ({}) (got {})",
+                v.max_amount, v.min_amount
+            ),
+        );
+        check(
+            v.max_cells > 0,
+            "virus.max_cells",
+            "must be at least 1 (12 is typical)".to_string(),
+        );
+        check(
+            v.split_div > 0.0,
+            "virus.split_div",
+            format!("must be greater than 0 (got {})", v.split_div),
+        );
+        check(
+            v.growth_factor >= 0.0,
+            "virus.growth_factor",
+            format!("must not be negative (got {})", v.growth_factor),
+        );
+
+        let e = &self.eject;
+        check(
+            e.size > 0.0,
+            "eject.size",
+            format!("must be greater than 0 (got {})", e.size),
+        );
+        check(
+            e.size_loss < p.min_eject_size,
+            "eject.size_loss",
+            format!(
+                "must be smaller than player.min_eject_size ({}) or ejecting kills the cell (got {})",
+                p.min_eject_size, e.size_loss
+            ),
+        );
+
+        let b = &self.bots;
+        check(
+            b.minion_start_size > 0.0,
+            "bots.minion_start_size",
+            format!("must be greater than 0 (got {})", b.minion_start_size),
+        );
+        check(
+            b.start_size == 0.0 || b.start_size >= p.min_size,
+            "bots.start_size",
+            format!("must be 0 or at least player.min_size ({}) (got {})", p.min_size, b.start_size),
+        );
+
+        let a = &self.antiteam;
+        check(
+            a.window_seconds > 0.0,
+            "antiteam.window_seconds",
+            format!("must be greater than 0 (got {})", a.window_seconds),
+        );
+        check(
+            a.penalty_step >= 0.0,
+            "antiteam.penalty_step",
+            format!("must not be negative (got {})", a.penalty_step),
+        );
+
+        let n = &self.announcements;
+        check(
+            n.streak_window_seconds > 0.0,
+            "announcements.streak_window_seconds",
+            format!("must be greater than 0 (got {})", n.streak_window_seconds),
+        );
+        check(
+            n.cooldown_seconds >= 0.0,
+            "announcements.cooldown_seconds",
+            format!("must not be negative (got {})", n.cooldown_seconds),
+        );
+        check(
+            n.join_leave_cooldown_seconds >= 0.0,
+            "announcements.join_leave_cooldown_seconds",
+            format!("must not be negative (got {})", n.join_leave_cooldown_seconds),
+        );
+
+        let s = &self.stats;
+        check(
+            s.heatmap_interval_ticks > 0,
+            "stats.heatmap_interval_ticks",
+            "must be at least 1".to_string(),
+        );
+        check(
+            !s.heatmap_enabled || !s.heatmap_file.is_empty(),
+            "stats.heatmap_file",
+            "must be set when stats.heatmap_enabled is true".to_string(),
+        );
+
+        let r = &self.replay;
+        check(
+            r.snapshot_interval_ticks > 0,
+            "replay.snapshot_interval_ticks",
+            "must be at least 1".to_string(),
+        );
+        check(
+            !r.enabled || !r.directory.is_empty(),
+            "replay.directory",
+            "must be set when replay.enabled is true".to_string(),
+        );
+        check(
+            !r.dump_directory.is_empty(),
+            "replay.dump_directory",
+            "must not be empty".to_string(),
+        );
+
+        let t = &self.tracker;
+        check(
+            t.url.is_empty() || t.url.starts_with("http://"),
+            "tracker.url",
+            format!("must be an http:// URL; put a TLS proxy in front for https (got {:?})", t.url),
+        );
+        check(
+            t.interval_seconds >= 5.0,
+            "tracker.interval_seconds",
+            format!("must be at least 5 (got {})", t.interval_seconds),
+        );
+
+        for (i, room) in self.rooms.iter().enumerate() {
+            check(
+                room.path.starts_with('/'),
+                "rooms.path",
+                format!("must start with '/' (got {:?})", room.path),
+            );
+            check(
+                !self.rooms[..i].iter().any(|r| r.path == room.path),
+                "rooms.path",
+                format!("{:?} is used by more than one room", room.path),
+            );
+        }
+
+        // Overrides are checked against the settings they end up combined with
+        if !self.rooms.is_empty() {
+            let base: Vec<&str> = errors.iter().map(|e| e.field).collect();
+            let room_errors: Vec<ConfigError> = self.room_configs().iter()
+                .flat_map(|(path, config)| {
+                    config.validate().err().unwrap_or_default().into_iter()
+                        .filter(|e| !base.contains(&e.field))
+                        .map(move |e| ConfigError {
+                            field: "rooms",
+                            message: format!("room {}: {}", path, e),
+                        })
+                })
+                .collect();
+            errors.extend(room_errors);
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// The effective config of every room, keyed by request path.
+    ///
+    /// Without `[[rooms]]` this is the whole config as a single room on `/`.
+    /// When several rooms share the process, each keeps its own highscore file
+    /// and replay and dump directories so they don't overwrite one another.
+    pub fn room_configs(&self) -> Vec<(String, Config)> {
+        if self.rooms.is_empty() {
+            return vec![("/".to_string(), self.clone())];
         }
+        self.rooms.iter()
+            .map(|room| {
+                let mut config = Config { rooms: Vec::new(), ..self.clone() };
+                if let Some(name) = &room.name {
+                    config.server.name = name.clone();
+                }
+                if let Some(gamemode) = room.gamemode {
+                    config.server.gamemode = gamemode;
+                }
+                if let Some(bots) = room.bots {
+                    config.server.bots = bots;
+                }
+                if let Some(width) = room.border_width {
+                    config.border.width = width;
+                }
+                if let Some(height) = room.border_height {
+                    config.border.height = height;
+                }
+                if self.rooms.len() > 1 {
+                    let slug = room.slug();
+                    for file in [&mut config.stats.highscores_file, &mut config.stats.heatmap_file] {
+                        if !file.is_empty() {
+                            let path = Path::new(file.as_str());
+                            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("stats");
+                            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("json");
+                            *file = path
+                                .with_file_name(format!("{}-{}.{}", stem, slug, ext))
+                                .to_string_lossy()
+                                .into_owned();
+                        }
+                    }
+                    config.replay.directory = Path::new(&config.replay.directory)
+                        .join(&slug)
+                        .to_string_lossy()
+                        .into_owned();
+                    config.replay.dump_directory = Path::new(&config.replay.dump_directory)
+                        .join(&slug)
+                        .to_string_lossy()
+                        .into_owned();
+                }
+                (room.path.clone(), config)
+            })
+            .collect()
     }
 }
 
+/// A single invalid setting found by [`Config::validate`].
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{field}: {message}")]
+pub struct ConfigError {
+    /// Dotted path of the offending field, e.g. `food.min_amount`.
+    pub field: &'static str,
+    /// What is wrong and which values would be accepted.
+    pub message: String,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             server: ServerConfig::default(),
             border: BorderConfig::default(),
+            map: MapConfig::default(),
             player: PlayerConfig::default(),
             food: FoodConfig::default(),
             virus: VirusConfig::default(),
             eject: EjectConfig::default(),
+            bots: BotsConfig::default(),
+            antiteam: AntiTeamConfig::default(),
+            announcements: AnnouncementsConfig::default(),
+            stats: StatsConfig::default(),
+            replay: ReplayConfig::default(),
+            tracker: TrackerConfig::default(),
+            logging: LoggingConfig::default(),
+            rooms: Vec::new(),
         }
     }
 }
@@ -68,9 +539,51 @@ pub struct ServerConfig {
     /// Connections per IP limit.
     #[serde(default = "default_ip_limit")]
     pub ip_limit: usize,
+    /// Seconds without a packet before a living player is warned, then moved
+    /// to spectating and finally disconnected (0 = never). Operators are exempt.
+    #[serde(default = "default_afk_timeout")]
+    pub afk_timeout_seconds: u64,
+    /// Seconds a client's outgoing queue or a single write may stay blocked
+    /// before the client is disconnected.
+    #[serde(default = "default_send_timeout")]
+    pub send_timeout: u64,
+    /// Seconds a connection may take to finish the WebSocket upgrade, and
+    /// then again to finish the protocol handshake, before it is dropped.
+    #[serde(default = "default_handshake_timeout")]
+    pub handshake_timeout: u64,
+    /// Send the packets ready for a cogar-protocol client at once as one
+    /// Batch (0x70) frame. Other clients don't know that opcode.
+    #[serde(default)]
+    pub batch_packets: bool,
+    /// Largest accepted client frame in bytes; bigger ones disconnect the client.
+    #[serde(default = "default_max_packet_bytes")]
+    pub max_packet_bytes: usize,
+    /// Largest canvas size, in CSS pixels, a client's view is culled to.
+    #[serde(default = "default_max_view_width")]
+    pub max_view_width: u16,
+    #[serde(default = "default_max_view_height")]
+    pub max_view_height: u16,
+    /// Multiplier on how far outside a client's view cells are still sent:
+    /// their size plus how far they are about to move.
+    #[serde(default = "default_view_margin")]
+    pub view_margin: f32,
+    /// Let cogar clients that turn on "Unlimited Zoom" zoom out as far as
+    /// the biggest players at any mass. Advertised in SetBorder.
+    #[serde(default)]
+    pub allow_unlimited_zoom: bool,
+    /// Per-client obfuscation against bots: 0=off, 1=node ids, 2=ids and coordinates.
+    #[serde(default = "default_scramble_level")]
+    pub scramble_level: u8,
+    /// Per-client rate limits for action packets.
+    #[serde(default)]
+    pub packet_limits: PacketLimitsConfig,
     /// Game mode (0=FFA, 1=Teams, 2=Experimental, etc.)
     #[serde(default)]
     pub gamemode: u32,
+    /// Kill a living player's cells when an operator moves them to another
+    /// team, so switching can't be used to carry mass across.
+    #[serde(default = "default_team_switch_kills")]
+    pub team_switch_kills: bool,
     /// Server name shown to clients.
     #[serde(default = "default_name")]
     pub name: String,
@@ -89,6 +602,28 @@ pub struct ServerConfig {
     /// Password to toggle operator mode (empty = operator disabled).
     #[serde(default)]
     pub operator_password: String,
+    /// Accept `https://` image URLs as skins instead of only local skin names.
+    #[serde(default)]
+    pub allow_url_skins: bool,
+    /// Port serving Prometheus metrics at `/metrics`; unset disables the exporter.
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    /// Origins browsers may open a socket from, exact or with `*` wildcards
+    /// (e.g. `https://*.example.com`); empty allows every origin.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Token a connection must send as `?token=` on the socket URL; empty
+    /// lets anyone in.
+    #[serde(default)]
+    pub connect_token: String,
+    /// Serve `wss://` directly with this certificate; unset serves plain `ws://`.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Seed for the simulation's random numbers (spawn spots, colors, eject
+    /// spread, bot wandering); unset picks one at startup and logs it. The
+    /// same seed and the same inputs play out the same world.
+    #[serde(default)]
+    pub rng_seed: Option<u64>,
 }
 
 impl Default for ServerConfig {
@@ -99,13 +634,31 @@ impl Default for ServerConfig {
             max_connections: default_max_connections(),
             timeout: default_timeout(),
             ip_limit: default_ip_limit(),
+            afk_timeout_seconds: default_afk_timeout(),
+            send_timeout: default_send_timeout(),
+            handshake_timeout: default_handshake_timeout(),
+            batch_packets: false,
+            max_packet_bytes: default_max_packet_bytes(),
+            max_view_width: default_max_view_width(),
+            max_view_height: default_max_view_height(),
+            view_margin: default_view_margin(),
+            allow_unlimited_zoom: false,
+            scramble_level: default_scramble_level(),
+            packet_limits: PacketLimitsConfig::default(),
             gamemode: 0,
+            team_switch_kills: default_team_switch_kills(),
             name: default_name(),
             tick_interval_ms: default_tick_interval(),
             bots: 0,
             server_minions: 0,
             mobile_physics: default_mobile_physics(),
             operator_password: String::new(),
+            allow_url_skins: false,
+            metrics_port: None,
+            allowed_origins: Vec::new(),
+            connect_token: String::new(),
+            tls: None,
+            rng_seed: None,
         }
     }
 }
@@ -125,16 +678,117 @@ fn default_timeout() -> u64 {
 fn default_ip_limit() -> usize {
     100
 }
+fn default_afk_timeout() -> u64 {
+    600
+}
+fn default_send_timeout() -> u64 {
+    10
+}
+fn default_handshake_timeout() -> u64 {
+    10
+}
+fn default_max_packet_bytes() -> usize {
+    protocol::packets::DEFAULT_MAX_PACKET_BYTES
+}
+fn default_max_view_width() -> u16 {
+    3840
+}
+fn default_max_view_height() -> u16 {
+    2160
+}
+fn default_view_margin() -> f32 {
+    1.0
+}
+fn default_scramble_level() -> u8 {
+    2
+}
 fn default_name() -> String {
     "Native Ogar".to_string()
 }
 fn default_mobile_physics() -> bool {
     true
 }
+fn default_team_switch_kills() -> bool {
+    true
+}
 fn default_tick_interval() -> u64 {
     40
 }
 
+/// Certificate for native TLS, read at startup and again on SIGHUP.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first.
+    pub cert_path: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1).
+    pub key_path: String,
+}
+
+/// Per-client token buckets for split and eject packets. Each bucket holds
+/// one second's worth of packets; excess packets are dropped silently.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PacketLimitsConfig {
+    /// Split packets accepted per second (0 = unlimited).
+    #[serde(default = "default_split_per_second")]
+    pub split_per_second: f64,
+    /// Eject packets accepted per second (0 = unlimited).
+    #[serde(default = "default_eject_per_second")]
+    pub eject_per_second: f64,
+    /// Join packets accepted per second (0 = unlimited).
+    #[serde(default = "default_join_per_second")]
+    pub join_per_second: f64,
+    /// Chat messages accepted per second (0 = unlimited); commands are exempt.
+    #[serde(default = "default_chat_per_second")]
+    pub chat_per_second: f64,
+    /// Spawns per minute from one IP address, across all its connections to
+    /// a room (0 = unlimited). Joins past it don't spawn.
+    #[serde(default = "default_spawns_per_minute_per_ip")]
+    pub spawns_per_minute_per_ip: u32,
+    /// Sending this many times a limit, averaged over `abuse_seconds`,
+    /// disconnects the client.
+    #[serde(default = "default_abuse_factor")]
+    pub abuse_factor: f64,
+    /// Window the abuse rate is averaged over.
+    #[serde(default = "default_abuse_seconds")]
+    pub abuse_seconds: u64,
+}
+
+impl Default for PacketLimitsConfig {
+    fn default() -> Self {
+        Self {
+            split_per_second: default_split_per_second(),
+            eject_per_second: default_eject_per_second(),
+            join_per_second: default_join_per_second(),
+            chat_per_second: default_chat_per_second(),
+            spawns_per_minute_per_ip: default_spawns_per_minute_per_ip(),
+            abuse_factor: default_abuse_factor(),
+            abuse_seconds: default_abuse_seconds(),
+        }
+    }
+}
+
+fn default_split_per_second() -> f64 {
+    20.0
+}
+fn default_eject_per_second() -> f64 {
+    30.0
+}
+fn default_join_per_second() -> f64 {
+    2.0
+}
+fn default_chat_per_second() -> f64 {
+    0.0
+}
+fn default_spawns_per_minute_per_ip() -> u32 {
+    30
+}
+fn default_abuse_factor() -> f64 {
+    10.0
+}
+fn default_abuse_seconds() -> u64 {
+    5
+}
+
 /// World border configuration.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BorderConfig {
@@ -142,6 +796,10 @@ pub struct BorderConfig {
     pub width: f64,
     #[serde(default = "default_border_size")]
     pub height: f64,
+    /// Wrap the map around: cells leaving one edge come back on the
+    /// opposite one, and everything near a seam sees across it.
+    #[serde(default)]
+    pub wrap: bool,
 }
 
 impl Default for BorderConfig {
@@ -149,6 +807,7 @@ impl Default for BorderConfig {
         Self {
             width: default_border_size(),
             height: default_border_size(),
+            wrap: false,
         }
     }
 }
@@ -157,6 +816,14 @@ fn default_border_size() -> f64 {
     14142.0
 }
 
+/// Static map objects configuration.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MapConfig {
+    /// JSON file listing walls and speed pads (empty = open map).
+    #[serde(default)]
+    pub file: String,
+}
+
 /// Player configuration.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PlayerConfig {
@@ -166,6 +833,9 @@ pub struct PlayerConfig {
     pub min_size: f64,
     #[serde(default = "default_player_max_size")]
     pub max_size: f64,
+    /// What happens to a cell that grows past `max_size`.
+    #[serde(default)]
+    pub max_size_behavior: MaxSizeBehavior,
     #[serde(default = "default_player_min_split")]
     pub min_split_size: f64,
     #[serde(default = "default_player_min_eject")]
@@ -176,14 +846,44 @@ pub struct PlayerConfig {
     pub speed: f64,
     #[serde(default = "default_player_decay_rate")]
     pub decay_rate: f64,
-    #[serde(default = "default_player_merge_time")]
-    pub merge_time: f64,
+    /// Cells at or below this size don't decay.
+    #[serde(default = "default_player_min_decay_size")]
+    pub min_decay_size: f64,
+    /// How strongly total owner mass speeds up decay (0 = no speed-up,
+    /// `decay_rate` at any mass).
+    #[serde(default = "default_player_decay_mass_exponent")]
+    pub decay_mass_exponent: f64,
+    /// Base seconds before split cells can remerge; bigger cells add
+    /// `size * 0.02` seconds on top. 0 = instant merge.
+    #[serde(default = "default_player_recombine_time", alias = "merge_time")]
+    pub recombine_time: f64,
     #[serde(default = "default_player_split_speed")]
     pub split_speed: f64,
     #[serde(default)]
     pub minion_same_color: bool,
+    /// Most minions a single player can own at once, across `/minion` and
+    /// `server.server_minions`.
+    #[serde(default = "default_player_max_minions")]
+    pub max_minions: usize,
+    /// Widest nickname in display columns; CJK and emoji count as two.
     #[serde(default = "default_max_nick_length")]
     pub max_nick_length: usize,
+    /// Seconds a disconnected player's cells stay in the world (frozen) so a
+    /// reconnect from the same IP and name can reclaim them. 0 = disabled.
+    #[serde(default)]
+    pub reconnect_grace_seconds: f64,
+}
+
+/// How `player.max_size` is enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxSizeBehavior {
+    /// Split the cell in half in a random direction while its owner has
+    /// fewer than `max_cells` cells; cap it after that.
+    #[default]
+    Split,
+    /// Clamp the cell at `max_size`.
+    Cap,
 }
 
 impl Default for PlayerConfig {
@@ -192,15 +892,20 @@ impl Default for PlayerConfig {
             start_size: default_player_start_size(),
             min_size: default_player_min_size(),
             max_size: default_player_max_size(),
+            max_size_behavior: MaxSizeBehavior::default(),
             min_split_size: default_player_min_split(),
             min_eject_size: default_player_min_eject(),
             max_cells: default_player_max_cells(),
             speed: default_player_speed(),
             decay_rate: default_player_decay_rate(),
-            merge_time: default_player_merge_time(),
+            min_decay_size: default_player_min_decay_size(),
+            decay_mass_exponent: default_player_decay_mass_exponent(),
+            recombine_time: default_player_recombine_time(),
             split_speed: default_player_split_speed(),
             minion_same_color: false,
+            max_minions: default_player_max_minions(),
             max_nick_length: default_max_nick_length(),
+            reconnect_grace_seconds: 0.0,
         }
     }
 }
@@ -229,12 +934,21 @@ fn default_player_speed() -> f64 {
 fn default_player_decay_rate() -> f64 {
     0.002
 }
-fn default_player_merge_time() -> f64 {
+fn default_player_min_decay_size() -> f64 {
+    30.0
+}
+fn default_player_decay_mass_exponent() -> f64 {
+    0.5
+}
+fn default_player_recombine_time() -> f64 {
     30.0
 }
 fn default_player_split_speed() -> f64 {
     780.0
 }
+fn default_player_max_minions() -> usize {
+    20
+}
 fn default_max_nick_length() -> usize {
     30
 }
@@ -252,6 +966,57 @@ pub struct FoodConfig {
     pub max_amount: usize,
     #[serde(default = "default_food_spawn_amount")]
     pub spawn_amount: usize,
+    /// Hex colors (`"#rrggbb"`) food is colored from; empty = random colors.
+    #[serde(default)]
+    pub palette: Vec<String>,
+    /// How pellet sizes are picked between `min_size` and `max_size`.
+    #[serde(default)]
+    pub size_distribution: FoodSizeDistribution,
+    /// Let food slowly grow toward `max_size` over time.
+    #[serde(default)]
+    pub grow_enabled: bool,
+    /// Fraction of spawned food that is golden bonus food (0.0 = disabled).
+    #[serde(default)]
+    pub bonus_chance: f64,
+    /// Mass multiplier awarded for eating bonus food.
+    #[serde(default = "default_food_bonus_mass_multiplier")]
+    pub bonus_mass_multiplier: f64,
+    /// Don't spawn pellets on top of player cells. Costs a spatial query per
+    /// pellet, so it is off by default.
+    #[serde(default)]
+    pub avoid_players: bool,
+    /// How strongly spawns favor sectors with less food than average
+    /// (0.0 = uniform, 1.0 = only below-average sectors).
+    #[serde(default)]
+    pub sector_bias: f64,
+}
+
+impl FoodConfig {
+    /// Parse `palette` into colors, skipping malformed entries.
+    pub fn palette_colors(&self) -> Vec<Color> {
+        self.palette.iter().filter_map(|hex| parse_hex_color(hex)).collect()
+    }
+}
+
+/// Food size distribution between `food.min_size` and `food.max_size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodSizeDistribution {
+    /// Every size is equally likely.
+    #[default]
+    Uniform,
+    /// Small pellets are common, large ones rare.
+    WeightedSmall,
+}
+
+/// Parse a `#rrggbb` (or `rrggbb`) hex string.
+pub(crate) fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::new((value >> 16) as u8, (value >> 8) as u8, value as u8))
 }
 
 impl Default for FoodConfig {
@@ -262,6 +1027,13 @@ impl Default for FoodConfig {
             min_amount: default_food_min_amount(),
             max_amount: default_food_max_amount(),
             spawn_amount: default_food_spawn_amount(),
+            palette: Vec::new(),
+            size_distribution: FoodSizeDistribution::default(),
+            grow_enabled: false,
+            bonus_chance: 0.0,
+            bonus_mass_multiplier: default_food_bonus_mass_multiplier(),
+            avoid_players: false,
+            sector_bias: 0.0,
         }
     }
 }
@@ -278,6 +1050,9 @@ fn default_food_max_amount() -> usize {
 fn default_food_spawn_amount() -> usize {
     30
 }
+fn default_food_bonus_mass_multiplier() -> f64 {
+    7.0
+}
 
 /// Virus configuration.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -301,6 +1076,11 @@ pub struct VirusConfig {
     /// value produces fewer, larger pieces.
     #[serde(default = "default_virus_split_div")]
     pub split_div: f64,
+    /// Multiplier on the mass a virus gains per ejected pellet it eats. A
+    /// virus that grows to `max_size` shoots a new virus along the feed
+    /// direction and shrinks back to `min_size` (JS: virusMaxSize).
+    #[serde(default = "default_virus_growth_factor")]
+    pub growth_factor: f64,
 }
 
 impl Default for VirusConfig {
@@ -313,6 +1093,7 @@ impl Default for VirusConfig {
             eject_speed: default_virus_eject_speed(),
             max_cells: default_virus_max_cells(),
             split_div: default_virus_split_div(),
+            growth_factor: default_virus_growth_factor(),
         }
     }
 }
@@ -338,6 +1119,9 @@ fn default_virus_max_cells() -> usize {
 fn default_virus_split_div() -> f64 {
     36.0
 }
+fn default_virus_growth_factor() -> f64 {
+    1.0
+}
 
 /// Ejected mass configuration.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -350,6 +1134,12 @@ pub struct EjectConfig {
     pub speed: f64,
     #[serde(default = "default_eject_cooldown")]
     pub cooldown: u32,
+    /// Most ejected mass alive at once; the oldest pellets go first. 0 = no limit.
+    #[serde(default)]
+    pub max_amount: usize,
+    /// Ticks an ejected pellet lasts before it despawns. 0 = forever.
+    #[serde(default)]
+    pub lifetime_ticks: u64,
 }
 
 impl Default for EjectConfig {
@@ -359,6 +1149,8 @@ impl Default for EjectConfig {
             size_loss: default_eject_size_loss(),
             speed: default_eject_speed(),
             cooldown: default_eject_cooldown(),
+            max_amount: 0,
+            lifetime_ticks: 0,
         }
     }
 }
@@ -375,3 +1167,450 @@ fn default_eject_speed() -> f64 {
 fn default_eject_cooldown() -> u32 {
     2
 }
+
+/// AI bot and minion configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BotsConfig {
+    /// Size a minion cell (re)spawns with.
+    #[serde(default = "default_bots_minion_start_size")]
+    pub minion_start_size: f64,
+    /// Local skin names (as in the client's `./skins`) bots pick from at random.
+    #[serde(default)]
+    pub skins: Vec<String>,
+    /// Size a bot (re)spawns with; 0 uses `player.start_size`.
+    #[serde(default)]
+    pub start_size: f64,
+    /// Ticks a dead bot waits before respawning. Minions come back at once.
+    #[serde(default)]
+    pub respawn_delay_ticks: u64,
+    /// Ticks a respawned bot takes to grow from `player.min_size` to its
+    /// start size. 0 spawns it at full size.
+    #[serde(default)]
+    pub start_mass_ramp_ticks: u64,
+    /// How aggressively bots hunt.
+    #[serde(default)]
+    pub difficulty: BotDifficulty,
+}
+
+/// Bot hunting skill.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotDifficulty {
+    /// Never split to catch prey.
+    Easy,
+    /// Split at prey when it is safe to.
+    #[default]
+    Normal,
+    /// Split at prey, aiming where it is heading.
+    Hard,
+}
+
+impl Default for BotsConfig {
+    fn default() -> Self {
+        Self {
+            minion_start_size: default_bots_minion_start_size(),
+            skins: Vec::new(),
+            start_size: 0.0,
+            respawn_delay_ticks: 0,
+            start_mass_ramp_ticks: 0,
+            difficulty: BotDifficulty::Normal,
+        }
+    }
+}
+
+fn default_bots_minion_start_size() -> f64 {
+    31.623
+}
+
+/// FFA anti-teaming heuristic: players who keep feeding each other ejected
+/// mass while staying close together get a growing decay penalty.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AntiTeamConfig {
+    /// Turn the heuristic on (FFA-style modes only; Teams is never checked).
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sliding window, in seconds, transfers and proximity are counted over.
+    #[serde(default = "default_antiteam_window_seconds")]
+    pub window_seconds: f64,
+    /// Ejected mass a pair must exchange within the window to be flagged.
+    #[serde(default = "default_antiteam_mass_threshold")]
+    pub mass_threshold: f64,
+    /// Distance between the pair's closest cells that counts as "together".
+    #[serde(default = "default_antiteam_proximity_distance")]
+    pub proximity_distance: f64,
+    /// Seconds the pair must spend together within the window to be flagged.
+    #[serde(default = "default_antiteam_proximity_seconds")]
+    pub proximity_seconds: f64,
+    /// Extra decay multiplier added per strike (1.0 = double decay on the first).
+    #[serde(default = "default_antiteam_penalty_step")]
+    pub penalty_step: f64,
+    /// Most strikes a player can accumulate.
+    #[serde(default = "default_antiteam_max_strikes")]
+    pub max_strikes: u32,
+    /// Seconds without a new strike before the penalty is lifted.
+    #[serde(default = "default_antiteam_penalty_seconds")]
+    pub penalty_seconds: f64,
+}
+
+impl Default for AntiTeamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_seconds: default_antiteam_window_seconds(),
+            mass_threshold: default_antiteam_mass_threshold(),
+            proximity_distance: default_antiteam_proximity_distance(),
+            proximity_seconds: default_antiteam_proximity_seconds(),
+            penalty_step: default_antiteam_penalty_step(),
+            max_strikes: default_antiteam_max_strikes(),
+            penalty_seconds: default_antiteam_penalty_seconds(),
+        }
+    }
+}
+
+/// Server chat lines for kill streaks and big kills.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnnouncementsConfig {
+    #[serde(default = "default_announcements_enabled")]
+    pub enabled: bool,
+    /// Player kills within `streak_window_seconds` that make a rampage (0 = never).
+    #[serde(default = "default_announcements_streak_kills")]
+    pub streak_kills: u32,
+    /// Sliding window, in seconds, a streak's kills are counted over.
+    #[serde(default = "default_announcements_streak_window_seconds")]
+    pub streak_window_seconds: f64,
+    /// Victim mass that makes a single kill worth announcing (0 = never).
+    #[serde(default = "default_announcements_big_kill_mass")]
+    pub big_kill_mass: f64,
+    /// Seconds before the same player can be announced again.
+    #[serde(default = "default_announcements_cooldown_seconds")]
+    pub cooldown_seconds: f64,
+    /// Announce named players joining and leaving.
+    #[serde(default = "default_announcements_join_leave")]
+    pub join_leave: bool,
+    /// Seconds before another join or leave from the same IP is announced.
+    #[serde(default = "default_announcements_join_leave_cooldown_seconds")]
+    pub join_leave_cooldown_seconds: f64,
+}
+
+impl Default for AnnouncementsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_announcements_enabled(),
+            streak_kills: default_announcements_streak_kills(),
+            streak_window_seconds: default_announcements_streak_window_seconds(),
+            big_kill_mass: default_announcements_big_kill_mass(),
+            cooldown_seconds: default_announcements_cooldown_seconds(),
+            join_leave: default_announcements_join_leave(),
+            join_leave_cooldown_seconds: default_announcements_join_leave_cooldown_seconds(),
+        }
+    }
+}
+
+fn default_announcements_enabled() -> bool {
+    true
+}
+fn default_announcements_streak_kills() -> u32 {
+    3
+}
+fn default_announcements_streak_window_seconds() -> f64 {
+    10.0
+}
+fn default_announcements_big_kill_mass() -> f64 {
+    10_000.0
+}
+fn default_announcements_cooldown_seconds() -> f64 {
+    30.0
+}
+fn default_announcements_join_leave() -> bool {
+    true
+}
+fn default_announcements_join_leave_cooldown_seconds() -> f64 {
+    30.0
+}
+
+/// One world hosted alongside others, picked by WebSocket request path.
+/// Unset fields inherit the top-level settings.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoomConfig {
+    /// Request path clients connect to, e.g. `/ffa`.
+    pub path: String,
+    /// Server name shown in this room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Game mode for this room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gamemode: Option<u32>,
+    /// Number of bots to spawn in this room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bots: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_width: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_height: Option<f64>,
+}
+
+impl RoomConfig {
+    /// File-name-safe form of the path (`/ffa` -> `ffa`).
+    fn slug(&self) -> String {
+        let slug: String = self.path.trim_matches('/')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+            .collect();
+        if slug.is_empty() { "root".to_string() } else { slug }
+    }
+}
+
+/// Persistent statistics.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatsConfig {
+    /// JSON file the all-time highscore board is kept in (empty = not saved).
+    #[serde(default = "default_stats_highscores_file")]
+    pub highscores_file: String,
+    /// Scores kept on the all-time board.
+    #[serde(default = "default_stats_highscores_size")]
+    pub highscores_size: usize,
+    /// Count where player cells are on a grid over the map; `/heatmap`
+    /// writes it out.
+    #[serde(default)]
+    pub heatmap_enabled: bool,
+    /// Ticks between heatmap samples.
+    #[serde(default = "default_stats_heatmap_interval_ticks")]
+    pub heatmap_interval_ticks: u64,
+    /// JSON file `/heatmap` and shutdown write the heatmap to.
+    #[serde(default = "default_stats_heatmap_file")]
+    pub heatmap_file: String,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            highscores_file: default_stats_highscores_file(),
+            highscores_size: default_stats_highscores_size(),
+            heatmap_enabled: false,
+            heatmap_interval_ticks: default_stats_heatmap_interval_ticks(),
+            heatmap_file: default_stats_heatmap_file(),
+        }
+    }
+}
+
+/// Game event recording (see `--inspect-replay` to read a recording back).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplayConfig {
+    /// Record eats, spawns, removals and periodic position snapshots.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory recordings are written to; one file per hour or tournament round.
+    #[serde(default = "default_replay_directory")]
+    pub directory: String,
+    /// Ticks between full cell position snapshots.
+    #[serde(default = "default_replay_snapshot_interval_ticks")]
+    pub snapshot_interval_ticks: u64,
+    /// Directory the `/dump` operator command writes world dumps to.
+    #[serde(default = "default_replay_dump_directory")]
+    pub dump_directory: String,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: default_replay_directory(),
+            snapshot_interval_ticks: default_replay_snapshot_interval_ticks(),
+            dump_directory: default_replay_dump_directory(),
+        }
+    }
+}
+
+/// Server-list tracker this server reports itself to.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrackerConfig {
+    /// URL heartbeats are POSTed to (empty = not listed).
+    #[serde(default)]
+    pub url: String,
+    /// Seconds between heartbeats.
+    #[serde(default = "default_tracker_interval_seconds")]
+    pub interval_seconds: f64,
+    /// Name shown in server lists (empty = `server.name`).
+    #[serde(default)]
+    pub public_name: String,
+    /// Free-form region label, e.g. "eu-west".
+    #[serde(default)]
+    pub region: String,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            interval_seconds: default_tracker_interval_seconds(),
+            public_name: String::new(),
+            region: String::new(),
+        }
+    }
+}
+
+fn default_tracker_interval_seconds() -> f64 {
+    60.0
+}
+
+/// Moderation logs written next to the server's console output.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// File every chat message and command is appended to (empty = disabled).
+    #[serde(default)]
+    pub chat_file: String,
+    /// Size in bytes past which the chat log rotates early (0 = daily only).
+    #[serde(default = "default_logging_chat_max_bytes")]
+    pub chat_max_bytes: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            chat_file: String::new(),
+            chat_max_bytes: default_logging_chat_max_bytes(),
+        }
+    }
+}
+
+fn default_logging_chat_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_replay_directory() -> String {
+    "replays".to_string()
+}
+fn default_replay_snapshot_interval_ticks() -> u64 {
+    25
+}
+fn default_replay_dump_directory() -> String {
+    "dumps".to_string()
+}
+
+fn default_stats_highscores_file() -> String {
+    "highscores.json".to_string()
+}
+fn default_stats_highscores_size() -> usize {
+    100
+}
+fn default_stats_heatmap_interval_ticks() -> u64 {
+    25
+}
+fn default_stats_heatmap_file() -> String {
+    "heatmap.json".to_string()
+}
+
+fn default_antiteam_window_seconds() -> f64 {
+    30.0
+}
+fn default_antiteam_mass_threshold() -> f64 {
+    400.0
+}
+fn default_antiteam_proximity_distance() -> f64 {
+    800.0
+}
+fn default_antiteam_proximity_seconds() -> f64 {
+    15.0
+}
+fn default_antiteam_penalty_step() -> f64 {
+    1.0
+}
+fn default_antiteam_max_strikes() -> u32 {
+    5
+}
+fn default_antiteam_penalty_seconds() -> f64 {
+    60.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(config: &Config) -> Vec<&'static str> {
+        config
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[test]
+    fn test_template_matches_defaults() {
+        let template: toml::Value = toml::from_str(DEFAULT_CONFIG_TEMPLATE).unwrap();
+        let defaults = toml::Value::try_from(Config::default()).unwrap();
+        assert_eq!(template, defaults);
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_combinations_are_all_reported() {
+        let mut config = Config::default();
+        config.player.max_cells = 0;
+        config.food.min_amount = 5000;
+        config.server.tick_interval_ms = 5;
+        let fields = fields(&config);
+        assert_eq!(
+            fields,
+            vec!["server.tick_interval_ms", "player.max_cells", "food.min_amount"]
+        );
+    }
+
+    #[test]
+    fn test_border_smaller_than_start_size() {
+        let mut config = Config::default();
+        config.border.width = 40.0;
+        assert_eq!(fields(&config), vec!["border"]);
+    }
+
+    #[test]
+    fn test_eject_loss_exceeds_min_eject_size() {
+        let mut config = Config::default();
+        config.eject.size_loss = 80.0;
+        assert_eq!(fields(&config), vec!["eject.size_loss"]);
+    }
+
+    #[test]
+    fn test_food_palette_parsing() {
+        let mut config = Config::default();
+        config.food.palette = vec!["#ff8000".to_string(), "nope".to_string()];
+        assert_eq!(config.food.palette_colors(), vec![Color::new(255, 128, 0)]);
+        assert_eq!(fields(&config), vec!["food.palette"]);
+    }
+    #[test]
+    fn test_rooms_inherit_and_override() {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            bots = 3
+
+            [[rooms]]
+            path = "/ffa"
+
+            [[rooms]]
+            path = "/teams"
+            gamemode = 1
+            border_width = 40.0
+            "#,
+        )
+        .unwrap();
+        let rooms = config.room_configs();
+        assert_eq!(rooms[0].1.server.bots, 3);
+        assert_eq!(rooms[1].1.server.gamemode, 1);
+        assert_eq!(rooms[0].1.stats.highscores_file, "highscores-ffa.json");
+        assert_eq!(rooms[1].1.stats.highscores_file, "highscores-teams.json");
+        assert_eq!(rooms[1].1.stats.heatmap_file, "heatmap-teams.json");
+
+        // The tiny border is only invalid in the room that sets it
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "rooms");
+        assert!(errors[0].message.starts_with("room /teams: border:"));
+
+        assert_eq!(Config::default().room_configs()[0].0, "/");
+    }
+}
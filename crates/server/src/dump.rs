@@ -0,0 +1,218 @@
+//! Whole-world state dumps for reproducing collision and merge bugs.
+//!
+//! The `/dump` operator command captures every cell plus the client and bot
+//! rosters into a [`WorldDump`], serialized to JSON while the game state is
+//! locked; the file itself is written off the tick. `--load-dump <file>`
+//! starts the first room from such a file. Connections can't be restored, so
+//! the cells of players who were connected come back owned by bots with the
+//! same id, name and color.
+
+use std::path::{Path, PathBuf};
+
+use glam::Vec2;
+use protocol::Color;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::entity::{BoostData, EjectedMass, Food, MotherCell, PlayerCell, Virus};
+use crate::world::CellEntry;
+
+/// Bumped when the layout changes incompatibly.
+pub const DUMP_VERSION: u32 = 1;
+
+/// Everything needed to rebuild a world.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldDump {
+    pub version: u32,
+    /// Unix seconds when the dump was taken.
+    pub created: u64,
+    pub tick: u64,
+    /// Game mode name the world was running.
+    pub gamemode: String,
+    pub border_width: f32,
+    pub border_height: f32,
+    /// Node id the next spawned cell would get.
+    pub next_node_id: u32,
+    /// Every cell, by id.
+    pub cells: Vec<CellDump>,
+    /// Connected players, by id.
+    pub clients: Vec<PlayerDump>,
+    /// Bots and minions, by id.
+    pub bots: Vec<PlayerDump>,
+}
+
+/// One cell and its type-specific state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellDump {
+    pub id: u32,
+    #[serde(flatten)]
+    pub kind: CellKind,
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<u32>,
+    pub color: [u8; 3],
+    pub birth_tick: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boost: Option<BoostDump>,
+    /// In the world's moving list.
+    #[serde(default)]
+    pub moving: bool,
+    #[serde(default)]
+    pub agitated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CellKind {
+    Player { can_remerge: bool, merge_tick: u64 },
+    Food { from_mother: bool, is_bonus: bool },
+    Virus { is_mother_cell: bool },
+    Eject { ejector: Option<u32> },
+    Mother { min_size: f32 },
+}
+
+/// Remaining boost of a moving cell.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoostDump {
+    pub distance: f32,
+    pub dx: f32,
+    pub dy: f32,
+    pub angle: f32,
+}
+
+/// A client or bot and the cells it owns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerDump {
+    pub id: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skin: Option<String>,
+    pub color: [u8; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<u8>,
+    pub cells: Vec<u32>,
+    /// Bots this client controls.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub minions: Vec<u32>,
+}
+
+impl CellDump {
+    pub fn capture(entry: &CellEntry, moving: bool) -> Self {
+        let kind = match entry {
+            CellEntry::Player(c) => CellKind::Player { can_remerge: c.can_remerge, merge_tick: c.merge_tick },
+            CellEntry::Food(c) => CellKind::Food { from_mother: c.from_mother, is_bonus: c.is_bonus },
+            CellEntry::Virus(c) => CellKind::Virus { is_mother_cell: c.is_mother_cell },
+            CellEntry::Eject(c) => CellKind::Eject { ejector: c.ejector },
+            CellEntry::Mother(c) => CellKind::Mother { min_size: c.min_size },
+        };
+        let data = entry.data();
+        Self {
+            id: data.node_id,
+            kind,
+            x: data.position.x,
+            y: data.position.y,
+            size: data.size,
+            owner: data.owner_id,
+            color: color_to_rgb(data.color),
+            birth_tick: data.tick_of_birth,
+            boost: data.boost.map(|b| BoostDump {
+                distance: b.distance,
+                dx: b.direction.x,
+                dy: b.direction.y,
+                angle: b.angle,
+            }),
+            moving,
+            agitated: data.is_agitated,
+        }
+    }
+
+    /// Rebuild the cell. Bonus food keeps its dumped size rather than
+    /// growing again.
+    pub fn restore(&self) -> CellEntry {
+        let position = Vec2::new(self.x, self.y);
+        let mut entry = match self.kind {
+            CellKind::Player { can_remerge, merge_tick } => {
+                let mut cell = PlayerCell::new(self.id, self.owner.unwrap_or(0), position, self.size, self.birth_tick);
+                cell.can_remerge = can_remerge;
+                cell.merge_tick = merge_tick;
+                CellEntry::Player(cell)
+            }
+            CellKind::Food { from_mother, is_bonus } => {
+                let mut cell = Food::new(self.id, position, self.size, self.birth_tick);
+                cell.from_mother = from_mother;
+                cell.is_bonus = is_bonus;
+                CellEntry::Food(cell)
+            }
+            CellKind::Virus { is_mother_cell } => {
+                let mut cell = Virus::new(self.id, position, self.size, self.birth_tick);
+                cell.is_mother_cell = is_mother_cell;
+                CellEntry::Virus(cell)
+            }
+            CellKind::Eject { ejector } => {
+                let mut cell = EjectedMass::new(self.id, position, self.size, self.birth_tick);
+                cell.ejector = ejector;
+                CellEntry::Eject(cell)
+            }
+            CellKind::Mother { min_size } => {
+                let mut cell = MotherCell::new(self.id, position, self.size, self.birth_tick);
+                cell.min_size = min_size;
+                CellEntry::Mother(cell)
+            }
+        };
+        let data = entry.data_mut();
+        data.owner_id = self.owner;
+        data.color = rgb_to_color(self.color);
+        data.is_agitated = self.agitated;
+        data.boost = self.boost.map(|b| BoostData {
+            distance: b.distance,
+            direction: Vec2::new(b.dx, b.dy),
+            angle: b.angle,
+        });
+        entry
+    }
+}
+
+pub fn color_to_rgb(color: Color) -> [u8; 3] {
+    [color.r, color.g, color.b]
+}
+
+pub fn rgb_to_color([r, g, b]: [u8; 3]) -> Color {
+    Color::new(r, g, b)
+}
+
+impl WorldDump {
+    /// Read a dump written by `/dump`.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let dump: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if dump.version != DUMP_VERSION {
+            anyhow::bail!("unsupported dump version {} (expected {})", dump.version, DUMP_VERSION);
+        }
+        Ok(dump)
+    }
+
+    /// Read the dump named by a `--load-dump <file>` command-line argument,
+    /// if there is one.
+    pub fn from_args(args: &[String]) -> anyhow::Result<Option<Self>> {
+        let Some(i) = args.iter().position(|arg| arg == "--load-dump") else {
+            return Ok(None);
+        };
+        let path = args.get(i + 1).ok_or_else(|| anyhow::anyhow!("usage: --load-dump <file>"))?;
+        info!("  World dump: {}", path);
+        Self::read(Path::new(path)).map(Some)
+    }
+
+    /// Where `/dump` writes this dump inside `directory`.
+    pub fn path_in(&self, directory: &Path) -> PathBuf {
+        directory.join(format!("world-{}-{}.json", self.created, self.tick))
+    }
+}
+
+/// Write a serialized dump, creating its directory. Blocking.
+pub fn save(path: &Path, json: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, json)
+}
@@ -3,6 +3,8 @@
 use glam::Vec2;
 use protocol::Color;
 
+use crate::world::WorldBorder;
+
 // Performance: Constants for cell calculations
 const MASS_DIVISOR: f32 = 100.0;  // Mass = radius / 100
 
@@ -140,7 +142,7 @@ impl CellData {
     /// Update boost movement (called each tick).
     /// Returns true if the cell is still boosting.
     /// Matches JS moveCell: speed = boostDistance / 10; boostDistance -= speed;
-    pub fn update_boost(&mut self, border_min: Vec2, border_max: Vec2) -> bool {
+    pub fn update_boost(&mut self, border: &WorldBorder) -> bool {
         if let Some(ref mut boost) = self.boost {
             if boost.distance < 1.0 {
                 boost.distance = 0.0;
@@ -154,7 +156,7 @@ impl CellData {
             self.position += boost.direction * move_dist;
 
             // Check border
-            self.check_border(border_min.x, border_min.y, border_max.x, border_max.y);
+            border.confine_cell(self);
 
             true
         } else {
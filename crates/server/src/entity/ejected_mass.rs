@@ -8,6 +8,8 @@ use protocol::Color;
 #[derive(Debug, Clone)]
 pub struct EjectedMass {
     data: CellData,
+    /// Player (client or bot id) that ejected this mass.
+    pub ejector: Option<u32>,
 }
 
 impl EjectedMass {
@@ -15,7 +17,7 @@ impl EjectedMass {
     pub fn new(node_id: u32, position: Vec2, size: f32, tick: u64) -> Self {
         let mut data = CellData::new(node_id, CellType::EjectedMass, position, size, tick);
         data.spiked = false;
-        Self { data }
+        Self { data, ejector: None }
     }
 
     /// Set the color (usually inherits from ejecting player).
@@ -4,12 +4,20 @@ use super::cell::{Cell, CellData, CellType};
 use glam::Vec2;
 use protocol::Color;
 
+/// Color of golden bonus food.
+pub const BONUS_FOOD_COLOR: Color = Color::new(255, 215, 0);
+
+/// Size multiplier applied to bonus food when spawned.
+pub const BONUS_FOOD_SIZE_MULTIPLIER: f32 = 1.3;
+
 /// A food pellet that can be eaten by players.
 #[derive(Debug, Clone)]
 pub struct Food {
     data: CellData,
     /// Whether this food was spawned by a mother cell.
     pub from_mother: bool,
+    /// Golden bonus food worth extra mass when eaten.
+    pub is_bonus: bool,
 }
 
 impl Food {
@@ -20,9 +28,18 @@ impl Food {
         Self {
             data,
             from_mother: false,
+            is_bonus: false,
         }
     }
 
+    /// Turn this pellet into golden bonus food.
+    pub fn make_bonus(&mut self) {
+        self.is_bonus = true;
+        self.data.color = BONUS_FOOD_COLOR;
+        let size = self.data.size * BONUS_FOOD_SIZE_MULTIPLIER;
+        self.data.set_size(size);
+    }
+
     /// Set the food color.
     pub fn set_color(&mut self, color: Color) {
         self.data.color = color;
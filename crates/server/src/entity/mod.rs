@@ -9,9 +9,9 @@ mod virus;
 mod ejected_mass;
 mod mother_cell;
 
-pub use cell::{Cell, CellType, CellData};
+pub use cell::{BoostData, Cell, CellType, CellData};
 pub use food::Food;
-pub use player_cell::PlayerCell;
+pub use player_cell::{decayed_size, PlayerCell};
 pub use virus::Virus;
 pub use ejected_mass::EjectedMass;
 pub use mother_cell::MotherCell;
@@ -3,6 +3,27 @@
 use super::cell::{Cell, CellData, CellType};
 use glam::Vec2;
 
+/// Owner mass at which decay runs at double the base rate (for exponent > 0).
+pub const DECAY_REFERENCE_MASS: f32 = 10_000.0;
+
+/// Size of a cell after one decay pass.
+///
+/// The cell loses `size * rate * (1 + bonus)` where
+/// `bonus = (owner_mass / DECAY_REFERENCE_MASS) ^ exponent`, so bigger
+/// players shrink faster. An exponent of 0 means no bonus, a flat `rate`.
+/// Never shrinks below `min_decay_size`.
+pub fn decayed_size(size: f32, owner_mass: f32, rate: f32, exponent: f32, min_decay_size: f32) -> f32 {
+    if size <= min_decay_size {
+        return size;
+    }
+    let bonus = if exponent > 0.0 {
+        (owner_mass.max(0.0) / DECAY_REFERENCE_MASS).powf(exponent)
+    } else {
+        0.0
+    };
+    (size - size * rate * (1.0 + bonus)).max(min_decay_size)
+}
+
 /// A cell controlled by a player.
 #[derive(Debug, Clone)]
 pub struct PlayerCell {
@@ -11,6 +32,7 @@ pub struct PlayerCell {
     /// Whether this cell can remerge with siblings.
     pub can_remerge: bool,
     /// Tick when merge becomes possible (0 = immediately).
+    /// Fixed from the cell's size when it last split.
     pub merge_tick: u64,
 }
 
@@ -26,9 +48,18 @@ impl PlayerCell {
         }
     }
 
-    /// Update merge status based on current tick and merge time config.
+    /// Start the recombine timer after a split.
+    ///
+    /// JS: time = playerRecombineTime + size * 0.02 seconds (25 ticks/s), so
+    /// big cells take noticeably longer to merge back.
+    pub fn start_merge_timer(&mut self, current_tick: u64, recombine_time: f32) {
+        let seconds = recombine_time.max(0.0) + self.cell_data.size * 0.02;
+        self.merge_tick = current_tick + (seconds * 25.0) as u64;
+    }
+
+    /// Update merge status based on current tick and recombine time config.
     /// Returns true if the cell can now remerge.
-    pub fn update_merge(&mut self, current_tick: u64, merge_time_base: f32) -> bool {
+    pub fn update_merge(&mut self, current_tick: u64, recombine_time: f32) -> bool {
         let age = current_tick.saturating_sub(self.cell_data.tick_of_birth);
 
         // Can't remerge if too young (splitRestoreTicks = 13)
@@ -37,16 +68,13 @@ impl PlayerCell {
             return false;
         }
 
-        // If no merge time configured, check if done boosting
-        if merge_time_base <= 0.0 {
+        // If no recombine time configured, check if done boosting
+        if recombine_time <= 0.0 {
             self.can_remerge = self.cell_data.boost.map(|b| b.distance < 100.0).unwrap_or(true);
             return self.can_remerge;
         }
 
-        // Calculate merge time based on cell size
-        // JS: time = Math.max(playerMergeTime, cell._size * 0.2) * 25
-        let time = (merge_time_base.max(self.cell_data.size * 0.2) * 25.0) as u64;
-        self.can_remerge = age >= time;
+        self.can_remerge = current_tick >= self.merge_tick;
         self.can_remerge
     }
 
@@ -78,3 +106,55 @@ impl Cell for PlayerCell {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay_math() {
+        // Exponent 0: no mass bonus at any mass, just the base rate
+        assert!((decayed_size(100.0, 100.0, 0.01, 0.0, 30.0) - 99.0).abs() < 1e-4);
+        assert!((decayed_size(100.0, DECAY_REFERENCE_MASS * 10.0, 0.01, 0.0, 30.0) - 99.0).abs() < 1e-4);
+        // Owner at the reference mass doubles the base loss
+        assert!((decayed_size(100.0, DECAY_REFERENCE_MASS, 0.01, 0.5, 30.0) - 98.0).abs() < 1e-4);
+        // Small cells never decay below the floor
+        assert_eq!(decayed_size(30.0, 9.0, 0.5, 0.5, 30.0), 30.0);
+        assert_eq!(decayed_size(40.0, 16.0, 0.9, 0.5, 30.0), 30.0);
+    }
+
+    #[test]
+    fn test_big_split_merges_later() {
+        // Splitting a 2,000-mass cell vs a 100-mass cell in half
+        let big_size = (2_000.0f32 * 100.0 / 2.0).sqrt();
+        let small_size = (100.0f32 * 100.0 / 2.0).sqrt();
+        let mut big = PlayerCell::new(1, 1, Vec2::ZERO, big_size, 0);
+        let mut small = PlayerCell::new(2, 2, Vec2::ZERO, small_size, 0);
+        big.start_merge_timer(0, 30.0);
+        small.start_merge_timer(0, 30.0);
+        assert!(big.merge_tick > small.merge_tick);
+
+        let tick = small.merge_tick;
+        assert!(small.update_merge(tick, 30.0));
+        assert!(!big.update_merge(tick, 30.0));
+        assert!(big.update_merge(big.merge_tick, 30.0));
+    }
+
+    #[test]
+    fn test_zero_recombine_time_merges_after_boost() {
+        let mut cell = PlayerCell::new(1, 1, Vec2::ZERO, 100.0, 0);
+        cell.start_merge_timer(0, 0.0);
+        assert!(!cell.update_merge(5, 0.0)); // split restore ticks still apply
+        assert!(cell.update_merge(13, 0.0));
+    }
+
+    #[test]
+    fn test_decay_is_monotonic_in_mass() {
+        let mut last_loss = 0.0;
+        for mass in [100.0, 1_000.0, 5_000.0, 20_000.0, 100_000.0] {
+            let size = (mass * 100.0f32).sqrt();
+            let loss = size - decayed_size(size, mass, 0.002, 0.5, 30.0);
+            assert!(loss > last_loss, "loss {} at mass {} not above {}", loss, mass, last_loss);
+            last_loss = loss;
+        }
+    }
+}
@@ -1,26 +1,30 @@
 //! Beatdown game mode.
 //!
-//! Kill-based progression mode where players gain speed and view bonuses per kill.
-//! Auto-respawn after death. Leaderboard shows kill count.
+//! Kill-based progression mode where players gain speed and view bonuses for
+//! each kill in their current streak. Streaks reset on death, with milestones
+//! announced in chat. Auto-respawn after death. Leaderboard shows kill count.
 
 use super::GameMode;
 use crate::server::client::Client;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use crate::server::LeaderboardEntry;
 use std::collections::HashMap;
 
+/// Streak lengths announced in chat.
+const STREAK_ANNOUNCEMENTS: [u32; 3] = [3, 5, 10];
+
 /// Beatdown game mode.
 pub struct Beatdown {
     /// Kill count per player.
     kill_count: HashMap<u32, u32>,
     /// Speed bonus per kill.
     speed_bonus_per_kill: f32,
-    /// View range bonus per kill.
+    /// View range bonus per kill, as a fraction of the normal range.
     view_bonus_per_kill: f32,
     /// Max speed bonus.
     max_speed_bonus: f32,
-    /// Max view bonus.
+    /// Max view bonus, as a fraction of the normal range.
     max_view_bonus: f32,
 }
 
@@ -28,16 +32,18 @@ impl Beatdown {
     pub fn new() -> Self {
         Self {
             kill_count: HashMap::new(),
-            speed_bonus_per_kill: 0.05, // 5% speed increase per kill
-            view_bonus_per_kill: 50.0,  // 50 units view range per kill
-            max_speed_bonus: 0.5,       // Max 50% speed bonus
-            max_view_bonus: 500.0,      // Max 500 view range bonus
+            speed_bonus_per_kill: 0.10, // 10% speed increase per kill
+            view_bonus_per_kill: 0.05,  // 5% wider view per kill
+            max_speed_bonus: 1.0,       // Max 100% speed bonus
+            max_view_bonus: 0.5,        // Max 50% wider view
         }
     }
 
-    /// Record a kill for a player.
-    pub fn record_kill(&mut self, killer_id: u32) {
-        *self.kill_count.entry(killer_id).or_insert(0) += 1;
+    /// Record a kill for a player, returning their new streak.
+    pub fn record_kill(&mut self, killer_id: u32) -> u32 {
+        let kills = self.kill_count.entry(killer_id).or_insert(0);
+        *kills += 1;
+        *kills
     }
 
     /// Get kill count for a player.
@@ -84,15 +90,15 @@ impl GameMode for Beatdown {
         6
     }
 
-    fn on_player_join(&self, _client: &mut Client) {
+    fn on_player_join(&self, _client: &mut Client, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Standard FFA join
     }
 
-    fn on_player_spawn(&self, _client: &mut Client) {
+    fn on_player_spawn(&self, _client: &mut Client, _rng: &mut GameRng) {
         // Standard FFA spawn - speed bonus applied elsewhere
     }
 
-    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Standard FFA bot spawn
     }
 
@@ -146,8 +152,16 @@ impl GameMode for Beatdown {
     fn on_tick(&mut self, _game_state: &mut crate::server::game::GameState) {}
 
     fn on_player_death(&mut self, game_state: &mut crate::server::game::GameState, killer_id: u32, victim_id: u32) {
-        self.record_kill(killer_id);
+        let streak = self.record_kill(killer_id);
         self.reset_kills(victim_id);
+
+        if STREAK_ANNOUNCEMENTS.contains(&streak) {
+            let name = game_state.clients.get(&killer_id).map(|c| c.name.clone())
+                .or_else(|| game_state.bots.get_bot(killer_id).map(|b| b.name.clone()))
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "An unnamed cell".to_string());
+            game_state.announce(&format!("{} is on a {}-kill streak!", name, streak));
+        }
         
         // Respawn victim immediately
         if game_state.clients.contains_key(&victim_id) {
@@ -167,4 +181,9 @@ impl GameMode for Beatdown {
     fn get_view_bonus(&self, player_id: u32) -> f32 {
         self.get_view_bonus(player_id)
     }
+
+    fn get_decay_multiplier(&self, _player_id: u32) -> f32 {
+        // Faster decay keeps kill-fed players from snowballing
+        1.5
+    }
 }
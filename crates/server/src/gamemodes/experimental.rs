@@ -1,7 +1,7 @@
 use super::GameMode;
 use crate::server::client::Client;
 use crate::server::LeaderboardEntry;
-use crate::world::{World, CellEntry};
+use crate::world::{World, CellEntry, GameRng};
 use crate::ai::BotManager;
 use crate::entity::{MotherCell, Food, Cell};
 use std::collections::HashMap;
@@ -30,7 +30,7 @@ impl Experimental {
 
         // Try to find a valid position
         // Basic check: just random position within border
-        let pos = world.border.random_position();
+        let pos = world.border.random_position(&mut world.rng);
         
         // Ensure no overlap with existing large cells?
         // JS uses `gameServer.willCollide(pos, 149)`.
@@ -51,15 +51,19 @@ impl GameMode for Experimental {
         2
     }
 
-    fn on_player_join(&self, _client: &mut Client) {
+    fn virus_push_enabled(&self) -> bool {
+        true
+    }
+
+    fn on_player_join(&self, _client: &mut Client, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Standard FFA
     }
 
-    fn on_player_spawn(&self, _client: &mut Client) {
+    fn on_player_spawn(&self, _client: &mut Client, _rng: &mut GameRng) {
         // Standard FFA
     }
 
-    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Standard FFA
     }
 
@@ -87,7 +91,7 @@ impl GameMode for Experimental {
         let food_count = world.food_cells.len(); // Capture before borrow
         
         for id in mother_ids {
-            let mut actions = Vec::new(); // (Position, Size) to spawn food
+            let mut actions = Vec::new(); // (Mother center, distance) to spawn food at
             
             if let Some(CellEntry::Mother(mother)) = world.get_cell_mut(id) {
                  // Logic from JS:
@@ -119,32 +123,32 @@ impl GameMode for Experimental {
                              let new_radius = (radius - 100.0).max(mother.min_size * mother.min_size);
                              mother.data_mut().set_size(new_radius.sqrt());
                              
-                             // Calculate spawn position
-                             let mut rng = rand::rng();
-                             let angle = rng.random_range(0.0..std::f32::consts::TAU);
-                             let dist = mother.data().size; 
-                             let pos_x = mother.data().position.x + dist * angle.sin();
-                             let pos_y = mother.data().position.y + dist * angle.cos();
-                             
-                             actions.push(Vec2::new(pos_x, pos_y));
+                             // Spawn on the rim, at an angle picked once the borrow ends
+                             actions.push((mother.data().position, mother.data().size));
                          }
                      }
                  }
             }
             
+            if !actions.is_empty() {
+                // Shrunk; refresh its QuadTree snapshot
+                world.update_cell_position(id);
+            }
+
             // Execute spawns
-            for pos in actions {
+            for (center, dist) in actions {
+                let angle = world.rng.random_range(0.0..std::f32::consts::TAU);
+                let pos = center + Vec2::new(angle.sin(), angle.cos()) * dist;
                 let id = world.next_id();
                 // Random food size between min and max
-                let mut rng = rand::rng();
-                let size = 10.0 + rng.random::<f32>() * 10.0; // 10-20 range
+                let size = 10.0 + world.rng.random::<f32>() * 10.0; // 10-20 range
                 let mut food = Food::new(id, pos, size, 0);
-                food.set_color(World::random_color());
+                food.set_color(World::random_color(&mut world.rng));
                 food.from_mother = true; // Mark as from mother cell
                 
                 // Apply boost
-                let angle = rng.random_range(0.0..std::f32::consts::TAU);
-                let boost_dist = 32.0 + 42.0 * rng.random::<f32>();
+                let angle = world.rng.random_range(0.0..std::f32::consts::TAU);
+                let boost_dist = 32.0 + 42.0 * world.rng.random::<f32>();
                 food.data_mut().set_boost(boost_dist, angle);
                 
                 world.add_food(food);
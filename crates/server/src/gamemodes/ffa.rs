@@ -1,6 +1,6 @@
 use super::GameMode;
 use crate::server::client::Client;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use crate::server::LeaderboardEntry;
 use std::collections::HashMap;
@@ -17,15 +17,15 @@ impl GameMode for Ffa {
     fn name(&self) -> &str { "FFA" }
     fn id(&self) -> u32 { 0 }
 
-    fn on_player_join(&self, _client: &mut Client) {
+    fn on_player_join(&self, _client: &mut Client, _team_sizes: &[usize], _rng: &mut GameRng) {
         // No special logic for FFA join
     }
 
-    fn on_player_spawn(&self, _client: &mut Client) {
+    fn on_player_spawn(&self, _client: &mut Client, _rng: &mut GameRng) {
         // No special logic for FFA spawn
     }
 
-    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot, _team_sizes: &[usize], _rng: &mut GameRng) {
         // No special logic for FFA bot spawn
     }
 
@@ -6,70 +6,43 @@
 use super::GameMode;
 use super::tournament::{Tournament, TournamentPhase};
 use crate::server::client::Client;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use crate::server::LeaderboardEntry;
 use std::collections::HashMap;
 use glam::Vec2;
 
+/// Number of spawn points around the map.
+const SPAWN_POINTS: usize = 12;
+/// Distance of the spawn points from the border.
+const SPAWN_MARGIN: f32 = 200.0;
+/// Size every tribute spawns at. There are no respawns once the round is
+/// on, so a fresh spawn shouldn't be a single bite.
+const TRIBUTE_START_SIZE: f32 = 60.0;
+
 /// Hunger Games game mode.
 pub struct HungerGames {
     /// Tournament base logic.
     tournament: Tournament,
-    /// Predefined spawn points.
-    spawn_points: Vec<Vec2>,
-    /// Next spawn point index.
-    next_spawn_index: usize,
 }
 
 impl HungerGames {
     pub fn new() -> Self {
         Self {
-            tournament: Tournament::new(),
-            spawn_points: Vec::new(),
-            next_spawn_index: 0,
+            tournament: Tournament { start_size: Some(TRIBUTE_START_SIZE), ..Tournament::new() },
         }
     }
 
-    /// Initialize spawn points around the map border.
-    /// Creates 12 spawn points evenly distributed around the perimeter.
-    pub fn init_spawn_points(&mut self, world: &World) {
-        self.spawn_points.clear();
-
-        let border = &world.border;
-        let width = border.max_x - border.min_x;
-        let height = border.max_y - border.min_y;
-        let center_x = (border.min_x + border.max_x) / 2.0;
-        let center_y = (border.min_y + border.max_y) / 2.0;
-
-        // Create spawn points around the border (similar to JS version)
-        // 12 points evenly distributed
-        let num_points = 12;
-        let margin = 200.0; // Distance from border edge
-
-        for i in 0..num_points {
-            let angle = (i as f32 / num_points as f32) * std::f32::consts::TAU;
-            let radius_x = (width / 2.0) - margin;
-            let radius_y = (height / 2.0) - margin;
-
-            let x = center_x + radius_x * angle.cos();
-            let y = center_y + radius_y * angle.sin();
-
-            self.spawn_points.push(Vec2::new(x, y));
-        }
-
-        self.next_spawn_index = 0;
-    }
-
-    /// Get the next spawn position.
-    pub fn get_spawn_position(&mut self) -> Option<Vec2> {
-        if self.spawn_points.is_empty() {
-            return None;
-        }
-
-        let pos = self.spawn_points[self.next_spawn_index];
-        self.next_spawn_index = (self.next_spawn_index + 1) % self.spawn_points.len();
-        Some(pos)
+    /// The spawn points, evenly spread around an ellipse [`SPAWN_MARGIN`]
+    /// inside the border (similar to the JS version).
+    pub fn spawn_points(world: &World) -> impl Iterator<Item = Vec2> {
+        let border = world.border;
+        let center = Vec2::new(border.min_x + border.max_x, border.min_y + border.max_y) / 2.0;
+        let radius = Vec2::new(border.width, border.height) / 2.0 - SPAWN_MARGIN;
+        (0..SPAWN_POINTS).map(move |i| {
+            let angle = i as f32 / SPAWN_POINTS as f32 * std::f32::consts::TAU;
+            center + radius * Vec2::new(angle.cos(), angle.sin())
+        })
     }
 
     /// Get tournament phase.
@@ -98,15 +71,15 @@ impl GameMode for HungerGames {
         5
     }
 
-    fn on_player_join(&self, _client: &mut Client) {
+    fn on_player_join(&self, _client: &mut Client, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Players will be added as contenders in on_tick
     }
 
-    fn on_player_spawn(&self, _client: &mut Client) {
-        // Spawn position will be set from spawn_points
+    fn on_player_spawn(&self, _client: &mut Client, _rng: &mut GameRng) {
+        // Spawn position comes from get_spawn_position
     }
 
-    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Bots handled same as players
     }
 
@@ -119,13 +92,16 @@ impl GameMode for HungerGames {
         self.tournament.get_leaderboard(world, clients, bots)
     }
 
-    fn on_tick(&mut self, game_state: &mut crate::server::game::GameState) {
-        let world = &mut game_state.world;
-        // Initialize spawn points if needed
-        if self.spawn_points.is_empty() {
-            self.init_spawn_points(world);
+    fn get_text_board(&self, game_state: &crate::server::game::GameState) -> Option<Vec<String>> {
+        if self.tournament.phase == TournamentPhase::Active {
+            let alive = self.tournament.alive_count(&game_state.clients, &game_state.bots);
+            let noun = if alive == 1 { "tribute" } else { "tributes" };
+            return Some(vec![format!("{} {} remaining", alive, noun)]);
         }
+        self.tournament.get_text_board(game_state)
+    }
 
+    fn on_tick(&mut self, game_state: &mut crate::server::game::GameState) {
         // Run tournament logic
         self.tournament.on_tick(game_state);
 
@@ -141,10 +117,62 @@ impl GameMode for HungerGames {
                 }
             }
         }
+    }
+
+    /// Tributes spawn at [`TRIBUTE_START_SIZE`].
+    fn get_spawn_size(&self, player_id: u32) -> Option<f32> {
+        self.tournament.get_spawn_size(player_id)
+    }
 
-        // Reset spawn index when tournament resets
-        if self.tournament.phase == TournamentPhase::Waiting && self.tournament.timer == 0 {
-            self.next_spawn_index = 0;
+    /// The spawn point farthest from every player cell on the map.
+    fn get_spawn_position(&self, world: &World, _player_id: u32) -> Option<Vec2> {
+        let nearest_player = |point: Vec2| {
+            world.player_cells.iter()
+                .filter_map(|&id| world.get_cell(id))
+                .map(|cell| world.border.delta(point, cell.data().position).length() - cell.data().size)
+                .fold(f32::INFINITY, f32::min)
+        };
+        Self::spawn_points(world)
+            .map(|point| (point, nearest_player(point)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(point, _)| point)
+    }
+
+    fn get_decay_multiplier(&self, _player_id: u32) -> f32 {
+        // Arena is closed, so shrink hoarders to force fights
+        2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::entity::PlayerCell;
+    use crate::server::game::GameState;
+
+    #[test]
+    fn test_tributes_spawn_at_the_emptiest_point() {
+        let mut game = GameState::for_tests(Config::default());
+        let tribute = game.add_client("127.0.0.1:1".parse().unwrap());
+        let mut mode = HungerGames::new();
+        assert_eq!(mode.get_spawn_size(tribute), None, "not a tribute yet");
+        mode.tournament.add_contender(tribute);
+        assert_eq!(mode.get_spawn_size(tribute), Some(TRIBUTE_START_SIZE));
+
+        // Occupy every spawn point but one
+        let points: Vec<Vec2> = HungerGames::spawn_points(&game.world).collect();
+        assert_eq!(points.len(), SPAWN_POINTS);
+        for (i, &point) in points.iter().enumerate().filter(|&(i, _)| i != 5) {
+            let id = game.world.next_id();
+            game.world.add_player_cell(PlayerCell::new(id, 100 + i as u32, point, 50.0, 0));
         }
+        assert_eq!(mode.get_spawn_position(&game.world, tribute), Some(points[5]));
+
+        game.gamemode = Box::new(mode);
+        game.spawn_player(tribute);
+        let cell = game.world.get_cell(game.clients[&tribute].cells[0]).unwrap();
+        assert_eq!(cell.data().position, points[5]);
+        assert_eq!(cell.data().size, TRIBUTE_START_SIZE);
     }
 }
@@ -1,8 +1,9 @@
 use crate::server::client::Client;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use crate::server::LeaderboardEntry;
 use std::collections::HashMap;
+use glam::Vec2;
 
 pub mod ffa;
 pub mod teams;
@@ -12,19 +13,32 @@ pub mod tournament;
 pub mod hunger_games;
 pub mod beatdown;
 
+/// A chat command a game mode adds, listed by `/help`.
+#[derive(Debug, Clone, Copy)]
+pub struct ModeCommand {
+    /// Command name without the slash.
+    pub name: &'static str,
+    /// Only listed for operators.
+    pub operator_only: bool,
+}
 
 pub trait GameMode: Send + Sync {
     fn name(&self) -> &str;
     fn id(&self) -> u32;
 
-    fn on_player_join(&self, client: &mut Client);
-    fn on_player_spawn(&self, client: &mut Client);
-    fn on_bot_spawn(&self, bot: &mut crate::ai::bot_player::Bot);
+    /// `team_sizes[t]` counts the players and bots on team `t`.
+    fn on_player_join(&self, client: &mut Client, team_sizes: &[usize], rng: &mut GameRng);
+    fn on_player_spawn(&self, client: &mut Client, rng: &mut GameRng);
+    fn on_bot_spawn(&self, bot: &mut crate::ai::bot_player::Bot, team_sizes: &[usize], rng: &mut GameRng);
 
     fn can_eat(&self, owner_id: u32, other_owner_id: u32, clients: &HashMap<u32, Client>, bots: &BotManager) -> bool;
 
     fn get_leaderboard(&self, world: &World, clients: &HashMap<u32, Client>, bots: &BotManager) -> Vec<LeaderboardEntry>;
 
+    /// Lines shown instead of the ranked leaderboard, e.g. a round countdown.
+    /// Default: none.
+    fn get_text_board(&self, _game_state: &crate::server::game::GameState) -> Option<Vec<String>> { None }
+
     fn on_tick(&mut self, _game_state: &mut crate::server::game::GameState) {}
 
     /// Called when a player/bot is killed. Default: no-op.
@@ -33,8 +47,33 @@ pub trait GameMode: Send + Sync {
     /// Get movement speed multiplier for a player. Default: 1.0.
     fn get_speed_multiplier(&self, _player_id: u32) -> f32 { 1.0 }
 
-    /// Get view range bonus for a player. Default: 0.0.
+    /// Extra view range for a player as a fraction (0.05 = 5% wider). Default: 0.0.
     fn get_view_bonus(&self, _player_id: u32) -> f32 { 0.0 }
+
+    /// Size a player or bot spawns at, instead of `player.start_size` (or
+    /// `bots.start_size`). Default: none.
+    fn get_spawn_size(&self, _player_id: u32) -> Option<f32> { None }
+
+    /// Where a player or bot spawns, instead of a random open position.
+    /// Default: none.
+    fn get_spawn_position(&self, _world: &World, _player_id: u32) -> Option<Vec2> { None }
+
+    /// Get mass decay rate multiplier for a player. Default: 1.0.
+    fn get_decay_multiplier(&self, _player_id: u32) -> f32 { 1.0 }
+
+    /// Number of the current round for round-based modes. Default: 0.
+    fn round(&self) -> u64 { 0 }
+
+    /// Whether a virus still flying after being shot pops any larger player
+    /// cell it touches, not just ones that eat it. Default: false.
+    fn virus_push_enabled(&self) -> bool { false }
+
+    /// Handle a chat command before the built-in ones; `cmd` is lowercase
+    /// without the slash. Returns true if the command was handled. Default: false.
+    fn handle_command(&mut self, _game_state: &mut crate::server::game::GameState, _client_id: u32, _cmd: &str, _args: &str) -> bool { false }
+
+    /// Commands handled by [`GameMode::handle_command`], for `/help`. Default: none.
+    fn commands(&self) -> &'static [ModeCommand] { &[] }
 }
 
 pub fn get_gamemode(id: u32) -> Box<dyn GameMode> {
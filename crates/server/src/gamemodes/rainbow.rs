@@ -1,7 +1,7 @@
 use super::GameMode;
 use crate::server::client::Client;
 use crate::server::LeaderboardEntry;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use protocol::Color;
 use std::collections::HashMap;
@@ -63,15 +63,15 @@ impl GameMode for Rainbow {
         3
     }
 
-    fn on_player_join(&self, _client: &mut Client) {
+    fn on_player_join(&self, _client: &mut Client, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Standard FFA
     }
 
-    fn on_player_spawn(&self, _client: &mut Client) {
+    fn on_player_spawn(&self, _client: &mut Client, _rng: &mut GameRng) {
         // Standard FFA
     }
 
-    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Standard FFA
     }
 
@@ -87,14 +87,15 @@ impl GameMode for Rainbow {
     fn on_tick(&mut self, game_state: &mut crate::server::game::GameState) {
         let world = &mut game_state.world;
         
-        let all_ids: Vec<u32> = world.cells.keys().copied().collect();
+        let mut all_ids: Vec<u32> = world.cells.keys().copied().collect();
+        // Hash order differs between runs; sort so seeded draws land the same
+        all_ids.sort_unstable();
         
         for id in all_ids {
             // Get or init index
             let index = self.cell_indices.entry(id).or_insert_with(|| {
                 use rand::Rng;
-                let mut rng = rand::rng();
-                rng.random_range(0..self.colors.len())
+                world.rng.random_range(0..self.colors.len())
             });
             
             // Update color
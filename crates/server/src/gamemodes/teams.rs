@@ -1,11 +1,14 @@
-use super::GameMode;
+use super::{GameMode, ModeCommand};
 use crate::server::client::Client;
-use crate::world::World;
+use crate::server::game::GameState;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use crate::server::LeaderboardEntry;
 use std::collections::HashMap;
 use rand::Rng;
 
+const TEAM_NAMES: [&str; 3] = ["Red", "Green", "Blue"];
+
 pub struct Teams;
 
 impl Teams {
@@ -13,8 +16,89 @@ impl Teams {
         Self
     }
 
-    fn get_team_color(&self, team: u8) -> protocol::Color {
-        let mut rng = rand::rng();
+    /// One of the smallest teams, picked at random among equals.
+    fn smallest_team(team_sizes: &[usize], rng: &mut GameRng) -> u8 {
+        let size = |team: usize| team_sizes.get(team).copied().unwrap_or(0);
+        let smallest = (0..TEAM_NAMES.len()).map(size).min().unwrap_or(0);
+        let candidates: Vec<usize> = (0..TEAM_NAMES.len()).filter(|&team| size(team) == smallest).collect();
+        candidates[rng.random_range(0..candidates.len())] as u8
+    }
+
+    /// Parse a team as its number or its (case-insensitive) name.
+    fn parse_team(arg: &str) -> Option<u8> {
+        match arg.parse::<usize>() {
+            Ok(team) => (team < TEAM_NAMES.len()).then_some(team as u8),
+            Err(_) => TEAM_NAMES.iter().position(|name| name.eq_ignore_ascii_case(arg)).map(|team| team as u8),
+        }
+    }
+
+    fn sizes_line(team_sizes: &[usize]) -> String {
+        let sizes: Vec<String> = TEAM_NAMES.iter().enumerate()
+            .map(|(team, name)| format!("{} {}", name, team_sizes.get(team).copied().unwrap_or(0)))
+            .collect();
+        sizes.join(", ")
+    }
+
+    /// Put a player or bot on `team` with a freshly fuzzed team color. A
+    /// player still alive loses their cells if `server.team_switch_kills`
+    /// is set; otherwise the cells take the new color.
+    fn move_to_team(&self, game_state: &mut GameState, id: u32, team: u8) {
+        let color = self.get_team_color(team, &mut game_state.world.rng);
+        let cells = if let Some(client) = game_state.clients.get_mut(&id) {
+            client.team = Some(team);
+            client.color = color;
+            if game_state.config.server.team_switch_kills && !client.cells.is_empty() {
+                game_state.kill_client(id);
+                return;
+            }
+            client.cells.clone()
+        } else if let Some(bot) = game_state.bots.get_bot_mut(id) {
+            bot.team = Some(team);
+            bot.color = color;
+            bot.cells.clone()
+        } else {
+            return;
+        };
+        for cell_id in cells {
+            if let Some(cell) = game_state.world.get_cell_mut(cell_id) {
+                cell.data_mut().color = color;
+            }
+        }
+    }
+
+    /// Move members off the biggest team while it has two more than the
+    /// smallest. Only bots and players without cells are moved, so nobody
+    /// loses a life to balancing.
+    fn rebalance(&self, game_state: &mut GameState) {
+        loop {
+            let sizes = game_state.team_sizes();
+            let size = |team: usize| sizes.get(team).copied().unwrap_or(0);
+            let biggest = (0..TEAM_NAMES.len()).max_by_key(|&team| size(team)).unwrap_or(0);
+            let smallest = (0..TEAM_NAMES.len()).min_by_key(|&team| size(team)).unwrap_or(0);
+            if size(biggest) <= size(smallest) + 1 {
+                return;
+            }
+            let team = Some(biggest as u8);
+            let idle_player = game_state.clients.values()
+                .filter(|c| c.team == team && c.cells.is_empty())
+                .map(|c| c.id)
+                .min();
+            let Some(id) = game_state.bots.bots.iter()
+                .filter(|b| b.team == team)
+                .map(|b| b.id)
+                .min()
+                .or(idle_player)
+            else {
+                return;
+            };
+            self.move_to_team(game_state, id, smallest as u8);
+            if game_state.clients.contains_key(&id) {
+                game_state.send_server_message(id, &format!("You were moved to {} to balance the teams.", TEAM_NAMES[smallest]));
+            }
+        }
+    }
+
+    fn get_team_color(&self, team: u8, rng: &mut GameRng) -> protocol::Color {
         let fuzz = 38;
         
         let base_color = match team {
@@ -35,32 +119,28 @@ impl GameMode for Teams {
     fn name(&self) -> &str { "Teams" }
     fn id(&self) -> u32 { 1 }
 
-    fn on_player_join(&self, client: &mut Client) {
+    fn on_player_join(&self, client: &mut Client, team_sizes: &[usize], rng: &mut GameRng) {
         if client.team.is_none() {
-            let mut rng = rand::rng();
-            let team = rng.random_range(0..3);
-            client.team = Some(team);
+            client.team = Some(Self::smallest_team(team_sizes, rng));
         }
         
         if let Some(team) = client.team {
-            client.color = self.get_team_color(team);
+            client.color = self.get_team_color(team, rng);
         }
     }
 
-    fn on_player_spawn(&self, client: &mut Client) {
+    fn on_player_spawn(&self, client: &mut Client, rng: &mut GameRng) {
         if let Some(team) = client.team {
-            client.color = self.get_team_color(team);
+            client.color = self.get_team_color(team, rng);
         }
     }
 
-    fn on_bot_spawn(&self, bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, bot: &mut crate::ai::bot_player::Bot, team_sizes: &[usize], rng: &mut GameRng) {
         if bot.team.is_none() {
-            let mut rng = rand::rng();
-            let team = rng.random_range(0..3);
-            bot.team = Some(team);
+            bot.team = Some(Self::smallest_team(team_sizes, rng));
         }
         if let Some(team) = bot.team {
-            bot.color = self.get_team_color(team);
+            bot.color = self.get_team_color(team, rng);
         }
     }
 
@@ -118,4 +198,96 @@ impl GameMode for Teams {
         }
         entries
     }
+    fn on_tick(&mut self, game_state: &mut GameState) {
+        self.rebalance(game_state);
+    }
+
+    fn commands(&self) -> &'static [ModeCommand] {
+        &[
+            ModeCommand { name: "team", operator_only: false },
+            ModeCommand { name: "setteam", operator_only: true },
+        ]
+    }
+
+    fn handle_command(&mut self, game_state: &mut GameState, client_id: u32, cmd: &str, args: &str) -> bool {
+        match cmd {
+            "team" => {
+                let sizes = Self::sizes_line(&game_state.team_sizes());
+                let message = match game_state.clients.get(&client_id).and_then(|c| c.team) {
+                    Some(team) => format!("You are on {}. Teams: {}", TEAM_NAMES[team as usize % TEAM_NAMES.len()], sizes),
+                    None => format!("You are not on a team yet. Teams: {}", sizes),
+                };
+                game_state.send_server_message(client_id, &message);
+                true
+            }
+            "setteam" => {
+                if !game_state.clients.get(&client_id).is_some_and(|c| c.is_operator) {
+                    game_state.send_server_message(client_id, "Operator only.");
+                    return true;
+                }
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                let (target_id, team) = match parts.as_slice() {
+                    [id, team] => match (id.parse::<u32>(), Self::parse_team(team)) {
+                        (Ok(id), Some(team)) => (id, team),
+                        _ => {
+                            game_state.send_server_message(client_id, "Usage: /setteam <client_id> <0-2|red|green|blue>");
+                            return true;
+                        }
+                    },
+                    _ => {
+                        game_state.send_server_message(client_id, "Usage: /setteam <client_id> <0-2|red|green|blue>");
+                        return true;
+                    }
+                };
+                if !game_state.clients.contains_key(&target_id) {
+                    game_state.send_server_message(client_id, "Client not found.");
+                    return true;
+                }
+                self.move_to_team(game_state, target_id, team);
+                let name = TEAM_NAMES[team as usize];
+                tracing::info!("Teams: Operator {} moved client {} to {}", client_id, target_id, name);
+                game_state.send_server_message(client_id, &format!("Moved client {} to {}.", target_id, name));
+                if target_id != client_id {
+                    game_state.send_server_message(target_id, &format!("An operator moved you to {}.", name));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_setteam_recolors_or_kills() {
+        let mut game = GameState::for_tests(Config::default());
+        let op = game.add_client("127.0.0.1:1".parse().unwrap());
+        let player = game.add_client("127.0.0.1:2".parse().unwrap());
+        game.clients.get_mut(&player).unwrap().team = Some(0);
+        game.spawn_player(player);
+
+        let mut mode = Teams::new();
+        assert!(mode.handle_command(&mut game, player, "team", ""));
+        assert!(mode.handle_command(&mut game, op, "setteam", &format!("{} blue", player)));
+        assert_eq!(game.clients[&player].team, Some(0), "non-operators can't move players");
+
+        game.clients.get_mut(&op).unwrap().is_operator = true;
+        game.config.server.team_switch_kills = false;
+        assert!(mode.handle_command(&mut game, op, "setteam", &format!("{} blue", player)));
+        let client = &game.clients[&player];
+        assert_eq!(client.team, Some(2));
+        let cell = game.world.get_cell(client.cells[0]).unwrap().data();
+        assert_eq!(cell.color, client.color);
+        assert!(cell.color.b >= 255 - 38 && cell.color.r < 38);
+
+        game.config.server.team_switch_kills = true;
+        assert!(mode.handle_command(&mut game, op, "setteam", &format!("{} 1", player)));
+        assert_eq!(game.clients[&player].team, Some(1));
+        assert!(game.clients[&player].cells.is_empty());
+        assert!(!mode.handle_command(&mut game, op, "kick", ""));
+    }
 }
@@ -2,12 +2,13 @@
 //!
 //! Phase-based tournament with waiting lobby, preparation time, and winner declaration.
 
-use super::GameMode;
+use super::{GameMode, ModeCommand};
 use crate::server::client::Client;
-use crate::world::World;
+use crate::world::{GameRng, World};
 use crate::ai::BotManager;
 use crate::server::LeaderboardEntry;
 use std::collections::HashMap;
+use glam::Vec2;
 
 /// Tournament phases.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -55,6 +56,11 @@ pub struct Tournament {
     pub auto_fill: bool,
     /// Target player count for auto-fill.
     pub auto_fill_count: usize,
+    /// Rounds started so far.
+    pub round: u64,
+    /// Size every contender spawns at, bots included, so no one starts
+    /// ahead. `None` keeps the configured start sizes.
+    pub start_size: Option<f32>,
 }
 
 impl Tournament {
@@ -68,6 +74,8 @@ impl Tournament {
             winner_time: 250,  // ~10 seconds
             auto_fill: false,
             auto_fill_count: 5,
+            round: 0,
+            start_size: None,
         }
     }
 
@@ -101,6 +109,24 @@ impl Tournament {
         self.contenders.contains(&id)
     }
 
+    /// Enlist every playing client and every bot that isn't a minion.
+    fn gather_contenders(&mut self, clients: &HashMap<u32, Client>, bots: &BotManager) {
+        for (&id, client) in clients.iter() {
+            if !self.is_contender(id) && !client.is_spectating {
+                self.add_contender(id);
+            }
+        }
+        for bot in &bots.bots {
+            if !self.is_contender(bot.id) {
+                // Skip if this bot is a minion owned by any client
+                let is_minion = clients.values().any(|client| client.minions.contains(&bot.id));
+                if !is_minion {
+                    self.add_contender(bot.id);
+                }
+            }
+        }
+    }
+
     /// Reset tournament to waiting phase.
     pub fn reset(&mut self) {
         self.phase = TournamentPhase::Waiting;
@@ -108,6 +134,22 @@ impl Tournament {
         self.timer = 0;
     }
 
+    /// Name of a contending client or bot.
+    fn contender_name(id: u32, clients: &HashMap<u32, Client>, bots: &BotManager) -> Option<String> {
+        clients.get(&id).map(|c| c.name.clone()).or_else(|| bots.get_bot(id).map(|b| b.name.clone()))
+    }
+
+    /// Spawn point of a contender: contenders are spread evenly around a
+    /// ring halfway between the center and the border, in the order they
+    /// joined.
+    pub fn contender_position(&self, world: &World, id: u32) -> Option<Vec2> {
+        let index = self.contenders.iter().position(|&c| c == id)?;
+        let border = &world.border;
+        let center = Vec2::new(border.min_x + border.max_x, border.min_y + border.max_y) / 2.0;
+        let angle = index as f32 / self.contenders.len() as f32 * std::f32::consts::TAU;
+        Some(center + Vec2::new(angle.cos() * border.width, angle.sin() * border.height) / 4.0)
+    }
+
     /// Get the winner (last alive contender).
     pub fn get_winner(&self, clients: &HashMap<u32, Client>, bots: &BotManager) -> Option<u32> {
         for &id in &self.contenders {
@@ -125,6 +167,12 @@ impl Tournament {
     }
 }
 
+/// `m:ss` for a number of ticks.
+pub(crate) fn countdown(ticks: u64, tick_ms: u64) -> String {
+    let secs = (ticks * tick_ms).div_ceil(1000);
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
 impl Default for Tournament {
     fn default() -> Self {
         Self::new()
@@ -140,15 +188,15 @@ impl GameMode for Tournament {
         4
     }
 
-    fn on_player_join(&self, _client: &mut Client) {
+    fn on_player_join(&self, _client: &mut Client, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Players start as spectators until they become contenders
     }
 
-    fn on_player_spawn(&self, _client: &mut Client) {
+    fn on_player_spawn(&self, _client: &mut Client, _rng: &mut GameRng) {
         // Spawning handled by on_tick based on phase
     }
 
-    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot) {
+    fn on_bot_spawn(&self, _bot: &mut crate::ai::bot_player::Bot, _team_sizes: &[usize], _rng: &mut GameRng) {
         // Bots handled same as players
     }
 
@@ -157,6 +205,69 @@ impl GameMode for Tournament {
         owner_id != other_owner_id
     }
 
+    fn round(&self) -> u64 {
+        self.round
+    }
+
+    fn get_spawn_size(&self, player_id: u32) -> Option<f32> {
+        self.start_size.filter(|_| self.is_contender(player_id))
+    }
+
+    fn get_spawn_position(&self, world: &World, player_id: u32) -> Option<Vec2> {
+        self.contender_position(world, player_id)
+    }
+
+    fn commands(&self) -> &'static [ModeCommand] {
+        &[
+            ModeCommand { name: "players", operator_only: false },
+            ModeCommand { name: "start", operator_only: true },
+        ]
+    }
+
+    fn handle_command(&mut self, game_state: &mut crate::server::game::GameState, client_id: u32, cmd: &str, _args: &str) -> bool {
+        match cmd {
+            "start" => {
+                if !game_state.clients.get(&client_id).is_some_and(|c| c.is_operator) {
+                    game_state.send_server_message(client_id, "Operator only.");
+                } else if self.phase != TournamentPhase::Waiting {
+                    game_state.send_server_message(client_id, "The tournament is already running.");
+                } else {
+                    self.gather_contenders(&game_state.clients, &game_state.bots);
+                    if self.contenders.is_empty() {
+                        game_state.send_server_message(client_id, "No contenders to start with.");
+                    } else {
+                        self.phase = TournamentPhase::Preparing;
+                        self.timer = 0;
+                        tracing::info!("Tournament: Operator {} started the round with {} contenders", client_id, self.contenders.len());
+                        game_state.send_server_message(client_id, &format!("Starting with {} contender(s).", self.contenders.len()));
+                    }
+                }
+                true
+            }
+            "players" => {
+                let lines: Vec<String> = self.contenders.iter()
+                    .map(|&id| {
+                        let (name, alive) = if let Some(c) = game_state.clients.get(&id) {
+                            (c.name.clone(), !c.cells.is_empty())
+                        } else if let Some(b) = game_state.bots.get_bot(id) {
+                            (b.name.clone(), !b.cells.is_empty())
+                        } else {
+                            ("(left)".to_string(), false)
+                        };
+                        let name = if name.is_empty() { "An unnamed cell".to_string() } else { name };
+                        format!("{} {}{}", id, name, if alive { "" } else { " (out)" })
+                    })
+                    .collect();
+                game_state.send_server_message(client_id, &format!("{:?} phase, {} contender(s)", self.phase, lines.len()));
+                for line in lines {
+                    game_state.send_server_message(client_id, &line);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn get_leaderboard(&self, world: &World, clients: &HashMap<u32, Client>, bots: &BotManager) -> Vec<LeaderboardEntry> {
         // Only show contenders on leaderboard
         let mut entries: Vec<LeaderboardEntry> = self.contenders.iter()
@@ -193,6 +304,29 @@ impl GameMode for Tournament {
         entries
     }
 
+    fn get_text_board(&self, game_state: &crate::server::game::GameState) -> Option<Vec<String>> {
+        let tick_ms = game_state.config.server.tick_interval_ms;
+        match self.phase {
+            TournamentPhase::Waiting => Some(vec![
+                "Waiting for players".to_string(),
+                format!("{}/{} contenders", self.contenders.len(), self.min_players),
+            ]),
+            TournamentPhase::Preparing => Some(vec![format!(
+                "Round {} starts in {}",
+                self.round + 1,
+                countdown(self.prepare_time.saturating_sub(self.timer), tick_ms)
+            )]),
+            TournamentPhase::Active => None,
+            TournamentPhase::Winner | TournamentPhase::Timeout => {
+                let headline = self.get_winner(&game_state.clients, &game_state.bots)
+                    .and_then(|id| Self::contender_name(id, &game_state.clients, &game_state.bots))
+                    .map(|name| format!("{} wins!", if name.is_empty() { "An unnamed cell" } else { &name }))
+                    .unwrap_or_else(|| "Round over".to_string());
+                Some(vec![headline, format!("Next round in {}", countdown(self.winner_time.saturating_sub(self.timer), tick_ms))])
+            }
+        }
+    }
+
     fn on_tick(&mut self, game_state: &mut crate::server::game::GameState) {
         let clients = &mut game_state.clients;
         let bots = &mut game_state.bots;
@@ -200,23 +334,8 @@ impl GameMode for Tournament {
 
         match self.phase {
             TournamentPhase::Waiting => {
-                // Add new players as contenders
-                for (&id, client) in clients.iter() {
-                    if !self.is_contender(id) && !client.is_spectating {
-                        self.add_contender(id);
-                    }
-                }
-
-                // Add bots as contenders (but not minions)
-                for bot in &bots.bots {
-                    if !self.is_contender(bot.id) {
-                        // Skip if this bot is a minion owned by any client
-                        let is_minion = clients.values().any(|client| client.minions.contains(&bot.id));
-                        if !is_minion {
-                            self.add_contender(bot.id);
-                        }
-                    }
-                }
+                // Add new players and bots (but not minions) as contenders
+                self.gather_contenders(clients, bots);
 
                 // Check if enough players to start
                 if self.contenders.len() >= self.min_players {
@@ -231,6 +350,7 @@ impl GameMode for Tournament {
                 if self.timer >= self.prepare_time {
                     self.phase = TournamentPhase::Active;
                     self.timer = 0;
+                    self.round += 1;
                     tracing::info!("Tournament: Game started!");
                 }
             }
@@ -244,13 +364,7 @@ impl GameMode for Tournament {
                     self.timer = 0;
                 } else if alive == 1 {
                     if let Some(winner_id) = self.get_winner(clients, bots) {
-                        let winner_name = if let Some(c) = clients.get(&winner_id) {
-                            c.name.clone()
-                        } else if let Some(b) = bots.get_bot(winner_id) {
-                            b.name.clone()
-                        } else {
-                            "Unknown".to_string()
-                        };
+                        let winner_name = Self::contender_name(winner_id, clients, bots).unwrap_or_else(|| "Unknown".to_string());
                         tracing::info!("Tournament: Winner is {}!", winner_name);
                     }
                     self.phase = TournamentPhase::Winner;
@@ -268,3 +382,70 @@ impl GameMode for Tournament {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::server::game::GameState;
+
+    #[test]
+    fn test_start_command_skips_the_wait() {
+        let mut game = GameState::for_tests(Config::default());
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+
+        let mut mode = Tournament::new();
+        assert!(mode.handle_command(&mut game, player, "start", ""));
+        assert_eq!(mode.phase, TournamentPhase::Waiting, "non-operators can't start");
+
+        game.clients.get_mut(&player).unwrap().is_operator = true;
+        assert!(mode.handle_command(&mut game, player, "start", ""));
+        assert_eq!(mode.phase, TournamentPhase::Preparing);
+        assert!(mode.is_contender(player));
+
+        assert!(mode.handle_command(&mut game, player, "players", ""));
+        assert!(!mode.handle_command(&mut game, player, "kick", ""));
+    }
+
+    #[test]
+    fn test_text_board_counts_down() {
+        let game = GameState::for_tests(Config::default());
+
+        let mut mode = Tournament::new();
+        assert_eq!(mode.get_text_board(&game), Some(vec!["Waiting for players".to_string(), "0/2 contenders".to_string()]));
+
+        mode.phase = TournamentPhase::Preparing;
+        mode.prepare_time = 1100;
+        mode.timer = 49;
+        // 1051 ticks of 40ms round up to 43 seconds
+        assert_eq!(mode.get_text_board(&game), Some(vec!["Round 1 starts in 0:43".to_string()]));
+
+        mode.phase = TournamentPhase::Active;
+        assert_eq!(mode.get_text_board(&game), None);
+    }
+
+    #[test]
+    fn test_contenders_spawn_around_a_ring() {
+        let mut game = GameState::for_tests(Config::default());
+        let first = game.add_client("127.0.0.1:1".parse().unwrap());
+        let second = game.add_client("127.0.0.1:2".parse().unwrap());
+
+        let mut mode = Tournament { start_size: Some(80.0), ..Tournament::new() };
+        assert_eq!(mode.get_spawn_position(&game.world, first), None, "spectators spawn anywhere");
+        assert_eq!(mode.get_spawn_size(first), None);
+        mode.add_contender(first);
+        mode.add_contender(second);
+
+        // Opposite sides, a quarter of the map from the center
+        let quarter = game.world.border.width / 4.0;
+        assert_eq!(mode.get_spawn_position(&game.world, first), Some(Vec2::new(quarter, 0.0)));
+        let across = mode.get_spawn_position(&game.world, second).unwrap();
+        assert!((across - Vec2::new(-quarter, 0.0)).length() < 0.01, "{}", across);
+
+        game.gamemode = Box::new(mode);
+        game.spawn_player(second);
+        let cell = game.world.get_cell(game.clients[&second].cells[0]).unwrap();
+        assert_eq!(cell.data().position, across);
+        assert_eq!(cell.data().size, 80.0);
+    }
+}
@@ -0,0 +1,115 @@
+//! Where players spend their time, for tuning virus and food placement.
+//!
+//! With `stats.heatmap_enabled`, every `stats.heatmap_interval_ticks` ticks
+//! the player cells of that tick's world broadcast are counted into a fixed
+//! [`HEATMAP_GRID`]² grid over the border. `/heatmap` and shutdown write the
+//! grid to `stats.heatmap_file` as JSON, one row per grid line from the top
+//! (min y) down, ready to be drawn as an image.
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::WorldBorder;
+
+/// Cells per side of the grid.
+pub const HEATMAP_GRID: usize = 64;
+
+/// Running counts of player cells per grid cell.
+pub struct Heatmap {
+    counts: Box<[u32; HEATMAP_GRID * HEATMAP_GRID]>,
+    samples: u64,
+    interval_ticks: u64,
+}
+
+/// The file `/heatmap` writes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeatmapFile {
+    /// Unix seconds when the file was written.
+    pub created: u64,
+    pub tick: u64,
+    /// Sampling passes counted.
+    pub samples: u64,
+    /// Ticks between sampling passes.
+    pub interval_ticks: u64,
+    pub grid_size: usize,
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+    /// `grid_size` rows of `grid_size` counts.
+    pub counts: Vec<Vec<u32>>,
+}
+
+impl Heatmap {
+    pub fn new(interval_ticks: u64) -> Self {
+        Self {
+            counts: Box::new([0; HEATMAP_GRID * HEATMAP_GRID]),
+            samples: 0,
+            interval_ticks: interval_ticks.max(1),
+        }
+    }
+
+    /// Whether `tick` is a sampling tick.
+    pub fn due(&self, tick: u64) -> bool {
+        tick.is_multiple_of(self.interval_ticks)
+    }
+
+    /// Count one sampling pass over player cell positions. Positions on or
+    /// past the border land in the edge cells.
+    pub fn sample(&mut self, border: &WorldBorder, positions: impl IntoIterator<Item = (f32, f32)>) {
+        let bin = |value: f32, min: f32, size: f32| {
+            let scaled = (value - min) / size * HEATMAP_GRID as f32;
+            (scaled.max(0.0) as usize).min(HEATMAP_GRID - 1)
+        };
+        for (x, y) in positions {
+            let col = bin(x, border.min_x, border.width);
+            let row = bin(y, border.min_y, border.height);
+            self.counts[row * HEATMAP_GRID + col] += 1;
+        }
+        self.samples += 1;
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// The grid as written to disk.
+    pub fn to_file(&self, border: &WorldBorder, tick: u64) -> HeatmapFile {
+        HeatmapFile {
+            created: crate::highscores::unix_now(),
+            tick,
+            samples: self.samples,
+            interval_ticks: self.interval_ticks,
+            grid_size: HEATMAP_GRID,
+            min_x: border.min_x,
+            min_y: border.min_y,
+            max_x: border.max_x,
+            max_y: border.max_y,
+            counts: self.counts.chunks(HEATMAP_GRID).map(<[u32]>::to_vec).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_bins_positions() {
+        let border = WorldBorder::new(6400.0, 6400.0);
+        let mut heatmap = Heatmap::new(5);
+        assert!(heatmap.due(10) && !heatmap.due(11));
+
+        heatmap.sample(&border, [(-3200.0, -3200.0), (-3050.0, -3101.0), (0.0, 0.0), (3200.0, 3200.0), (9999.0, -9999.0)]);
+        heatmap.sample(&border, []);
+        let file = heatmap.to_file(&border, 42);
+        assert_eq!((file.samples, file.interval_ticks, file.tick), (2, 5, 42));
+        assert_eq!(file.counts.len(), HEATMAP_GRID);
+        // 100 units per grid cell
+        assert_eq!(file.counts[0][0], 1);
+        assert_eq!(file.counts[0][1], 1);
+        assert_eq!(file.counts[32][32], 1);
+        assert_eq!(file.counts[63][63], 1);
+        assert_eq!(file.counts[0][63], 1);
+        assert_eq!(file.counts.iter().flatten().sum::<u32>(), 5);
+    }
+}
@@ -0,0 +1,162 @@
+//! All-time highscore board, persisted as JSON across restarts.
+//!
+//! Players' peak masses are submitted when a life ends (death, disconnect or
+//! shutdown); only scores above the current cutoff are kept. The board is
+//! written back on a timer and at shutdown.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// One all-time score.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighscoreEntry {
+    pub name: String,
+    pub mass: u32,
+    /// Game mode name the score was set in.
+    pub gamemode: String,
+    /// Unix seconds when the score was recorded.
+    pub timestamp: u64,
+}
+
+/// The top `capacity` scores, highest first.
+#[derive(Debug, Default)]
+pub struct Highscores {
+    entries: Vec<HighscoreEntry>,
+    capacity: usize,
+    /// Changed since the last flush.
+    dirty: bool,
+}
+
+impl Highscores {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: Vec::new(), capacity, dirty: false }
+    }
+
+    /// Load the board from `path`. A missing file starts empty; an unreadable
+    /// or corrupt one is logged and also starts empty rather than failing.
+    pub fn load(path: &Path, capacity: usize) -> Self {
+        let mut board = Self::new(capacity);
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return board,
+            Err(e) => {
+                warn!("Could not read highscores from {}: {}; starting fresh", path.display(), e);
+                return board;
+            }
+        };
+        match serde_json::from_str::<Vec<HighscoreEntry>>(&contents) {
+            Ok(mut entries) => {
+                entries.sort_by_key(|e| std::cmp::Reverse(e.mass));
+                entries.truncate(capacity);
+                info!("Loaded {} highscore(s) from {}", entries.len(), path.display());
+                board.entries = entries;
+            }
+            Err(e) => warn!("Highscores file {} is corrupt ({}); starting fresh", path.display(), e),
+        }
+        board
+    }
+
+    /// Lowest mass that still makes the board, once it is full.
+    pub fn cutoff(&self) -> Option<u32> {
+        if self.entries.len() < self.capacity {
+            None
+        } else {
+            self.entries.last().map(|e| e.mass)
+        }
+    }
+
+    /// Insert a score if it beats the cutoff. Returns whether it was kept.
+    pub fn submit(&mut self, entry: HighscoreEntry) -> bool {
+        if self.capacity == 0 || entry.mass == 0 || self.cutoff().is_some_and(|cutoff| entry.mass <= cutoff) {
+            return false;
+        }
+        // Ties keep the older score ahead
+        let index = self.entries.partition_point(|e| e.mass >= entry.mass);
+        self.entries.insert(index, entry);
+        self.entries.truncate(self.capacity);
+        self.dirty = true;
+        true
+    }
+
+    /// The best `n` scores.
+    pub fn top(&self, n: usize) -> &[HighscoreEntry] {
+        &self.entries[..n.min(self.entries.len())]
+    }
+
+    /// The all-time record, if any.
+    pub fn leader(&self) -> Option<&HighscoreEntry> {
+        self.entries.first()
+    }
+
+    /// Serialized board if it changed since the last call.
+    pub fn take_dirty_json(&mut self) -> Option<String> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        Some(self.to_json())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// Write a serialized board, via a temporary file so a crash mid-write never
+/// leaves a truncated file behind.
+pub fn save(path: &Path, json: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Current time in Unix seconds.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, mass: u32) -> HighscoreEntry {
+        HighscoreEntry { name: name.to_string(), mass, gamemode: "Free For All".to_string(), timestamp: 0 }
+    }
+
+    #[test]
+    fn test_submit_keeps_top_scores_sorted() {
+        let mut board = Highscores::new(3);
+        assert!(board.submit(entry("a", 100)));
+        assert!(board.submit(entry("b", 300)));
+        assert!(board.submit(entry("c", 200)));
+        assert_eq!(board.cutoff(), Some(100));
+
+        // Below the cutoff, or tying it, is rejected
+        assert!(!board.submit(entry("d", 100)));
+        assert!(board.submit(entry("e", 250)));
+        let names: Vec<&str> = board.top(10).iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "e", "c"]);
+        assert_eq!(board.leader().unwrap().mass, 300);
+    }
+
+    #[test]
+    fn test_round_trip_and_corrupt_file() {
+        let dir = std::env::temp_dir().join(format!("cogar-highscores-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("highscores.json");
+
+        let mut board = Highscores::new(5);
+        board.submit(entry("a", 500));
+        save(&path, &board.take_dirty_json().unwrap()).unwrap();
+        assert!(board.take_dirty_json().is_none());
+        assert_eq!(Highscores::load(&path, 5).top(5), board.top(5));
+
+        std::fs::write(&path, "{not json").unwrap();
+        assert!(Highscores::load(&path, 5).leader().is_none());
+        assert!(Highscores::load(&dir.join("missing.json"), 5).leader().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,17 +1,29 @@
 //! Native Ogar game server library.
 
 pub mod ai;
+pub mod announcements;
+pub mod antiteam;
+pub mod chatlog;
 pub mod collision;
 pub mod config;
+pub mod dump;
 pub mod entity;
 pub mod gamemodes;
+pub mod heatmap;
+pub mod highscores;
+pub mod metrics;
+pub mod names;
+pub mod replay;
 pub mod server;
 pub mod spatial;
+pub mod tls;
+pub mod tracker;
+pub mod upgrade;
 pub mod world;
 
 // Re-export commonly used types
 pub use config::Config;
 pub use server::{
-    run, ChatBroadcast, LeaderboardBroadcast, WorldUpdateBroadcast, TargetedMessage, TargetedMessageType,
+    run, serve, build_view_update, Frame, LagTracker, Outbox, RoomRegistry, ChatBroadcast, LeaderboardBroadcast, WorldUpdateBroadcast, TargetedMessage, TargetedMessageType, TargetedSender,
     ClientViewData, WorldCell
 };
\ No newline at end of file
@@ -1,19 +1,44 @@
 //! Native Ogar game server.
 
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
 mod ai;
+mod announcements;
+mod antiteam;
+mod chatlog;
 mod collision;
 mod config;
+mod dump;
 mod entity;
 mod gamemodes;
+mod heatmap;
+mod highscores;
+mod metrics;
+mod names;
+mod replay;
 mod server;
 mod spatial;
+mod tls;
+mod tracker;
+mod upgrade;
 mod world;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // Dump the effective config before logging is set up so stdout stays clean
+    if std::env::args().any(|arg| arg == "--write-config") {
+        let config = config::Config::load()?;
+        print!("{}", toml::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    // Summarize a replay recording and exit
+    let args: Vec<String> = std::env::args().collect();
+    if replay::ReplayInfo::inspect_from_args(&args)? {
+        return Ok(());
+    }
+
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -25,13 +50,23 @@ async fn main() -> anyhow::Result<()> {
 
     // Load configuration
     let config = config::Config::load()?;
+    if let Err(errors) = config.validate() {
+        error!("Invalid configuration:");
+        for err in &errors {
+            error!("  {}", err);
+        }
+        anyhow::bail!("config.toml has {} invalid setting(s)", errors.len());
+    }
     info!("Loaded configuration");
     info!("  Port: {}", config.server.port);
     info!("  Border: {}x{}", config.border.width, config.border.height);
     info!("  Game mode: {}", config.server.gamemode);
 
+    // Start the first room from a world dump when asked to
+    let dump = dump::WorldDump::from_args(&args)?;
+
     // Start the game server
-    server::run(config).await?;
+    server::run(config, dump).await?;
 
     Ok(())
 }
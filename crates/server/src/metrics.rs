@@ -0,0 +1,429 @@
+//! Prometheus metrics: counters kept by the game loop and connection tasks,
+//! served in the text exposition format when `server.metrics_port` is set.
+
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::server::room::RoomRegistry;
+
+/// Upper bounds of the tick duration histogram buckets, in seconds.
+const TICK_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.02, 0.04, 0.08, 0.16, 0.32, 0.64];
+
+/// Timed sections of a tick.
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Spawn,
+    Ai,
+    Movement,
+    Collision,
+    Decay,
+    Broadcast,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [Phase::Spawn, Phase::Ai, Phase::Movement, Phase::Collision, Phase::Decay, Phase::Broadcast];
+
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Spawn => "spawn",
+            Phase::Ai => "ai",
+            Phase::Movement => "move",
+            Phase::Collision => "collision",
+            Phase::Decay => "decay",
+            Phase::Broadcast => "broadcast",
+        }
+    }
+}
+
+/// Kinds of world entities counted.
+#[derive(Debug, Clone, Copy)]
+pub enum Entity {
+    Player,
+    Food,
+    Virus,
+    Ejected,
+    Mother,
+}
+
+impl Entity {
+    const ALL: [Entity; 5] = [Entity::Player, Entity::Food, Entity::Virus, Entity::Ejected, Entity::Mother];
+
+    fn label(self) -> &'static str {
+        match self {
+            Entity::Player => "player",
+            Entity::Food => "food",
+            Entity::Virus => "virus",
+            Entity::Ejected => "ejected",
+            Entity::Mother => "mother",
+        }
+    }
+}
+
+/// A room's broadcast channels, as seen by connection tasks.
+#[derive(Debug, Clone, Copy)]
+pub enum Channel {
+    Chat,
+    Leaderboard,
+    World,
+    Targeted,
+}
+
+impl Channel {
+    const ALL: [Channel; 4] = [Channel::Chat, Channel::Leaderboard, Channel::World, Channel::Targeted];
+
+    fn label(self) -> &'static str {
+        match self {
+            Channel::Chat => "chat",
+            Channel::Leaderboard => "leaderboard",
+            Channel::World => "world",
+            Channel::Targeted => "targeted",
+        }
+    }
+}
+
+/// Where a connection that never finished its handshake gave up.
+#[derive(Debug, Clone, Copy)]
+pub enum HandshakeStage {
+    /// The WebSocket upgrade.
+    Upgrade,
+    /// The protocol handshake (0xFE / 0xFF).
+    Protocol,
+}
+
+impl HandshakeStage {
+    const ALL: [HandshakeStage; 2] = [HandshakeStage::Upgrade, HandshakeStage::Protocol];
+
+    fn label(self) -> &'static str {
+        match self {
+            HandshakeStage::Upgrade => "websocket",
+            HandshakeStage::Protocol => "protocol",
+        }
+    }
+}
+
+/// Why a WebSocket upgrade was refused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpgradeRejection {
+    /// The Origin header isn't in `server.allowed_origins`.
+    Origin,
+    /// The `?token=` doesn't match `server.connect_token`.
+    Token,
+}
+
+impl UpgradeRejection {
+    const ALL: [UpgradeRejection; 2] = [UpgradeRejection::Origin, UpgradeRejection::Token];
+
+    fn label(self) -> &'static str {
+        match self {
+            UpgradeRejection::Origin => "origin",
+            UpgradeRejection::Token => "token",
+        }
+    }
+}
+
+/// One room's metrics. Everything is a relaxed atomic, so the game loop and
+/// connection tasks update it without locking and a scrape never blocks them.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Ticks per histogram bucket (not cumulative); the last is `+Inf`.
+    tick_buckets: [AtomicU64; TICK_BUCKETS.len() + 1],
+    tick_sum_micros: AtomicU64,
+    phase_micros: [AtomicU64; Phase::ALL.len()],
+    /// Ticks over `server.tick_interval_ms`, by their slowest phase.
+    tick_overruns: [AtomicU64; Phase::ALL.len()],
+    /// 1 while the game loop reports itself overloaded.
+    overloaded: AtomicU64,
+    entities: [AtomicU64; Entity::ALL.len()],
+    clients: AtomicU64,
+    bots: AtomicU64,
+    bytes_sent: AtomicU64,
+    /// Packets waiting in connections' send queues.
+    queued_packets: AtomicU64,
+    slow_clients_dropped: AtomicU64,
+    world_updates_coalesced: AtomicU64,
+    lagged: [AtomicU64; Channel::ALL.len()],
+    handshake_timeouts: [AtomicU64; HandshakeStage::ALL.len()],
+    rejected_upgrades: [AtomicU64; UpgradeRejection::ALL.len()],
+}
+
+impl Metrics {
+    /// Record one tick's total duration and its phase timings.
+    pub fn record_tick(&self, total: Duration, phases: &[(Phase, Duration)]) {
+        let secs = total.as_secs_f64();
+        let bucket = TICK_BUCKETS.iter().position(|&le| secs <= le).unwrap_or(TICK_BUCKETS.len());
+        self.tick_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.tick_sum_micros.fetch_add(total.as_micros() as u64, Ordering::Relaxed);
+        for &(phase, time) in phases {
+            self.phase_micros[phase as usize].fetch_add(time.as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Count a tick that ran over budget, mostly in `phase`.
+    pub fn tick_overrun(&self, phase: Phase) {
+        self.tick_overruns[phase as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_overloaded(&self, overloaded: bool) {
+        self.overloaded.store(overloaded as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_entity_count(&self, entity: Entity, count: usize) {
+        self.entities[entity as usize].store(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_players(&self, clients: usize, bots: usize) {
+        self.clients.store(clients as u64, Ordering::Relaxed);
+        self.bots.store(bots as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn packet_queued(&self) {
+        self.queued_packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn packet_dequeued(&self) {
+        self.queued_packets.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn slow_client_dropped(&self) {
+        self.slow_clients_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn world_updates_coalesced(&self, skipped: u64) {
+        self.world_updates_coalesced.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn handshake_timed_out(&self, stage: HandshakeStage) {
+        self.handshake_timeouts[stage as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn upgrade_rejected(&self, reason: UpgradeRejection) {
+        self.rejected_upgrades[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count the messages a connection missed because it fell behind on `channel`.
+    pub fn observe_recv<T>(&self, channel: Channel, result: &Result<T, RecvError>) {
+        if let Err(RecvError::Lagged(missed)) = result {
+            self.lagged[channel as usize].fetch_add(*missed, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A room's metrics plus what is read at scrape time.
+pub struct RoomSample<'a> {
+    pub path: &'a str,
+    pub metrics: &'a Metrics,
+    /// Messages still queued per channel (`Channel` order), i.e. how far the
+    /// slowest connection is behind.
+    pub queued: [usize; Channel::ALL.len()],
+}
+
+/// Render every room's metrics in the Prometheus text format.
+pub fn render(rooms: &[RoomSample]) -> String {
+    let mut out = String::new();
+    let load = |v: &AtomicU64| v.load(Ordering::Relaxed);
+    let micros = |v: &AtomicU64| load(v) as f64 / 1_000_000.0;
+
+    family(&mut out, "cogar_tick_duration_seconds", "histogram", "Game tick duration.");
+    for room in rooms {
+        let m = room.metrics;
+        let mut cumulative = 0;
+        for (i, le) in TICK_BUCKETS.iter().enumerate() {
+            cumulative += load(&m.tick_buckets[i]);
+            let _ = writeln!(out, "cogar_tick_duration_seconds_bucket{{room=\"{}\",le=\"{}\"}} {}", room.path, le, cumulative);
+        }
+        cumulative += load(&m.tick_buckets[TICK_BUCKETS.len()]);
+        let _ = writeln!(out, "cogar_tick_duration_seconds_bucket{{room=\"{}\",le=\"+Inf\"}} {}", room.path, cumulative);
+        let _ = writeln!(out, "cogar_tick_duration_seconds_sum{{room=\"{}\"}} {}", room.path, micros(&m.tick_sum_micros));
+        let _ = writeln!(out, "cogar_tick_duration_seconds_count{{room=\"{}\"}} {}", room.path, cumulative);
+    }
+
+    family(&mut out, "cogar_tick_phase_seconds_total", "counter", "Time spent in each tick phase.");
+    for room in rooms {
+        for phase in Phase::ALL {
+            let _ = writeln!(out, "cogar_tick_phase_seconds_total{{room=\"{}\",phase=\"{}\"}} {}",
+                room.path, phase.label(), micros(&room.metrics.phase_micros[phase as usize]));
+        }
+    }
+
+    family(&mut out, "cogar_tick_overruns_total", "counter", "Ticks over the tick interval, by their slowest phase.");
+    for room in rooms {
+        for phase in Phase::ALL {
+            let _ = writeln!(out, "cogar_tick_overruns_total{{room=\"{}\",phase=\"{}\"}} {}",
+                room.path, phase.label(), load(&room.metrics.tick_overruns[phase as usize]));
+        }
+    }
+
+    family(&mut out, "cogar_overloaded", "gauge", "1 while the average tick time has been over the tick interval for a while.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_overloaded{{room=\"{}\"}} {}", room.path, load(&room.metrics.overloaded));
+    }
+
+    family(&mut out, "cogar_entities", "gauge", "World entities by type.");
+    for room in rooms {
+        for entity in Entity::ALL {
+            let _ = writeln!(out, "cogar_entities{{room=\"{}\",type=\"{}\"}} {}",
+                room.path, entity.label(), load(&room.metrics.entities[entity as usize]));
+        }
+    }
+
+    family(&mut out, "cogar_connected_clients", "gauge", "Connected clients.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_connected_clients{{room=\"{}\"}} {}", room.path, load(&room.metrics.clients));
+    }
+
+    family(&mut out, "cogar_bots", "gauge", "AI bots.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_bots{{room=\"{}\"}} {}", room.path, load(&room.metrics.bots));
+    }
+
+    family(&mut out, "cogar_sent_bytes_total", "counter", "WebSocket payload bytes sent to clients.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_sent_bytes_total{{room=\"{}\"}} {}", room.path, load(&room.metrics.bytes_sent));
+    }
+
+    family(&mut out, "cogar_send_queue_packets", "gauge", "Packets waiting in connections' send queues.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_send_queue_packets{{room=\"{}\"}} {}", room.path, load(&room.metrics.queued_packets));
+    }
+
+    family(&mut out, "cogar_slow_clients_dropped_total", "counter", "Clients disconnected for a stalled send queue or write.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_slow_clients_dropped_total{{room=\"{}\"}} {}", room.path, load(&room.metrics.slow_clients_dropped));
+    }
+
+    family(&mut out, "cogar_world_updates_coalesced_total", "counter", "World updates skipped because a newer one replaced them before sending.");
+    for room in rooms {
+        let _ = writeln!(out, "cogar_world_updates_coalesced_total{{room=\"{}\"}} {}", room.path, load(&room.metrics.world_updates_coalesced));
+    }
+
+    family(&mut out, "cogar_channel_queued_messages", "gauge", "Broadcast messages not yet received by every connection.");
+    for room in rooms {
+        for channel in Channel::ALL {
+            let _ = writeln!(out, "cogar_channel_queued_messages{{room=\"{}\",channel=\"{}\"}} {}",
+                room.path, channel.label(), room.queued[channel as usize]);
+        }
+    }
+
+    family(&mut out, "cogar_channel_lagged_messages_total", "counter", "Broadcast messages dropped for connections that fell behind.");
+    for room in rooms {
+        for channel in Channel::ALL {
+            let _ = writeln!(out, "cogar_channel_lagged_messages_total{{room=\"{}\",channel=\"{}\"}} {}",
+                room.path, channel.label(), load(&room.metrics.lagged[channel as usize]));
+        }
+    }
+
+    family(&mut out, "cogar_handshake_timeouts_total", "counter", "Connections dropped for not finishing a handshake in time.");
+    for room in rooms {
+        for stage in HandshakeStage::ALL {
+            let _ = writeln!(out, "cogar_handshake_timeouts_total{{room=\"{}\",stage=\"{}\"}} {}",
+                room.path, stage.label(), load(&room.metrics.handshake_timeouts[stage as usize]));
+        }
+    }
+
+    family(&mut out, "cogar_rejected_upgrades_total", "counter", "WebSocket upgrades refused with 403.");
+    for room in rooms {
+        for reason in UpgradeRejection::ALL {
+            let _ = writeln!(out, "cogar_rejected_upgrades_total{{room=\"{}\",reason=\"{}\"}} {}",
+                room.path, reason.label(), load(&room.metrics.rejected_upgrades[reason as usize]));
+        }
+    }
+
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Serve `GET /metrics` for every room on `bind:port` until the process exits.
+pub async fn serve(bind: &str, port: u16, rooms: Arc<RoomRegistry>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind((bind, port)).await?;
+    info!("Metrics on http://{}:{}/metrics", bind, port);
+    loop {
+        let (mut stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Metrics accept failed: {}", e);
+                continue;
+            }
+        };
+        let rooms = Arc::clone(&rooms);
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut request)).await;
+            let Ok(Ok(len)) = read else {
+                return;
+            };
+            let response = if request[..len].starts_with(b"GET /metrics ") {
+                let samples: Vec<RoomSample> = rooms.rooms().iter()
+                    .map(|room| RoomSample {
+                        path: &room.path,
+                        metrics: &room.metrics,
+                        queued: [room.chat_tx.len(), room.lb_tx.len(), room.world_tx.len(), room.targeted_tx.queued()],
+                    })
+                    .collect();
+                let body = render(&samples);
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            };
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                warn!("Failed to send metrics to {}: {}", addr, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_histogram_and_counters() {
+        let metrics = Metrics::default();
+        metrics.record_tick(Duration::from_micros(500), &[(Phase::Ai, Duration::from_micros(200))]);
+        metrics.record_tick(Duration::from_millis(30), &[(Phase::Ai, Duration::from_micros(300))]);
+        metrics.record_tick(Duration::from_secs(1), &[]);
+        metrics.tick_overrun(Phase::Collision);
+        metrics.set_overloaded(true);
+        metrics.set_entity_count(Entity::Food, 42);
+        metrics.add_bytes_sent(1500);
+        metrics.observe_recv::<()>(Channel::World, &Err(RecvError::Lagged(3)));
+        metrics.observe_recv::<()>(Channel::World, &Ok(()));
+
+        let text = render(&[RoomSample { path: "/", metrics: &metrics, queued: [0, 0, 2, 0] }]);
+        for line in [
+            "# TYPE cogar_tick_duration_seconds histogram",
+            "cogar_tick_duration_seconds_bucket{room=\"/\",le=\"0.001\"} 1",
+            "cogar_tick_duration_seconds_bucket{room=\"/\",le=\"0.02\"} 1",
+            "cogar_tick_duration_seconds_bucket{room=\"/\",le=\"0.04\"} 2",
+            "cogar_tick_duration_seconds_bucket{room=\"/\",le=\"+Inf\"} 3",
+            "cogar_tick_duration_seconds_count{room=\"/\"} 3",
+            "cogar_tick_phase_seconds_total{room=\"/\",phase=\"ai\"} 0.0005",
+            "cogar_tick_overruns_total{room=\"/\",phase=\"collision\"} 1",
+            "cogar_overloaded{room=\"/\"} 1",
+            "cogar_entities{room=\"/\",type=\"food\"} 42",
+            "cogar_sent_bytes_total{room=\"/\"} 1500",
+            "cogar_channel_queued_messages{room=\"/\",channel=\"world\"} 2",
+            "cogar_channel_lagged_messages_total{room=\"/\",channel=\"world\"} 3",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in:\n{}", line, text);
+        }
+    }
+}
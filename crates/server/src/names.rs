@@ -0,0 +1,105 @@
+//! Canonical player names.
+//!
+//! Names are shown in the leaderboard, chat and on cells, so anything that
+//! renders outside its own box is taken out when a name is set: bidi
+//! controls (which reverse the text around them), other control and
+//! zero-width characters, and combining marks beyond a couple per base
+//! character (zalgo). Names are NFC-normalized first, so a precomposed and
+//! a decomposed accent give the same name. The length limit is a display
+//! width, where CJK and emoji count as two columns and most combining marks
+//! as none.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use unicode_width::UnicodeWidthChar;
+
+/// Most combining marks kept on one base character.
+const MAX_MARKS_PER_CHAR: usize = 2;
+
+/// Canonical form of `name`, cut to `max_width` display columns.
+pub fn canonical_name(name: &str, max_width: usize) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut width = 0;
+    // Marks on the last base character; `None` until there is one
+    let mut marks: Option<usize> = None;
+    for c in name.nfc() {
+        if is_stripped(c) || (out.is_empty() && c.is_whitespace()) {
+            continue;
+        }
+        // Spacing marks still take a column
+        let char_width = c.width().unwrap_or(0);
+        if is_combining_mark(c) {
+            if let Some(count) = marks.as_mut()
+                && *count < MAX_MARKS_PER_CHAR
+                && width + char_width <= max_width
+            {
+                *count += 1;
+                width += char_width;
+                out.push(c);
+            }
+            continue;
+        }
+        if width + char_width > max_width {
+            break;
+        }
+        width += char_width;
+        if char_width > 0 {
+            marks = Some(0);
+        }
+        out.push(c);
+    }
+    out.trim_end().to_string()
+}
+
+/// Bidi controls, control characters and invisible spacing.
+fn is_stripped(c: char) -> bool {
+    c.is_control()
+        || matches!(c,
+            '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+            | '\u{00AD}' | '\u{200B}' | '\u{2060}' | '\u{FEFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adversarial_names() {
+        let long = "a".repeat(40);
+        let accents = "e\u{301}".repeat(31);
+        let accents_cut = "\u{E9}".repeat(30);
+        let emoji = "\u{1FA70}".repeat(16);
+        let cases: &[(&str, &str)] = &[
+            ("Alice", "Alice"),
+            ("  Bob  ", "Bob"),
+            ("", ""),
+            // Bidi overrides and isolates
+            ("\u{202E}gnp.exe", "gnp.exe"),
+            ("a\u{2066}b\u{2069}c\u{200F}", "abc"),
+            // Controls and invisible characters
+            ("tab\there\n", "tabhere"),
+            ("ab\u{200B}c\u{FEFF}d", "abcd"),
+            ("\u{200B}\u{202E}", ""),
+            ("\u{202E} \u{200B} Bob", "Bob"),
+            // Zalgo: two marks per base character survive
+            ("X\u{301}\u{302}\u{303}\u{304}a\u{330}", "X\u{301}\u{302}a\u{330}"),
+            ("\u{301}\u{302}x", "x"),
+            ("x\u{951}\u{951}\u{951}", "x\u{951}\u{951}"),
+            // NFC: a decomposed accent becomes the precomposed one
+            ("e\u{301}", "\u{E9}"),
+            // Width, not chars
+            (&long, &long[..30]),
+            ("漢字漢字漢字漢字漢字漢字漢字漢字", "漢字漢字漢字漢字漢字漢字漢字漢"),
+            ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaa漢", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaa"),
+            (&accents, &accents_cut),
+            (&emoji, &emoji[..60]),
+            // Emoji with a joiner and variation selector
+            ("👩\u{200D}🚀\u{FE0F}", "👩\u{200D}🚀\u{FE0F}"),
+            // A cut right before a space leaves no trailing space
+            ("aaaaaaaaaaaaaaaaaaaaaaaaaaaaa bbb", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaa"),
+        ];
+        for &(name, expected) in cases {
+            assert_eq!(canonical_name(name, 30), expected, "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Game event recording for moderation and highlight clips.
+//!
+//! The game loop hands a compact description of every tick (eats, spawns,
+//! removals and, every few ticks, a position snapshot) to a recorder task over
+//! a bounded channel; the task does all encoding and disk I/O so a slow disk
+//! never stalls a tick. Frames are dropped rather than queued when the channel
+//! is full.
+//!
+//! File format: a sequence of records, each a little-endian `u32` length
+//! followed by that many bytes of [`BinaryWriter`] payload whose first byte is
+//! the record type:
+//!
+//! - `0x01` header: magic `u32`, version `u8`, tick interval ms `u32`, start
+//!   time (Unix seconds) `u32`, game mode name (string)
+//! - `0x02` player: id `u32`, name (string); repeated on rename
+//! - `0x03` tick: tick `u32`, eats `u32` × (eater `u32`, eaten `u32`), spawns
+//!   `u32` × (id `u32`, cell type `u8`, x `i32`, y `i32`, size `u16`, owner
+//!   `u32`, 0 = none), removals `u32` × id `u32`, snapshot flag `u8`, then if
+//!   set `u32` × (id `u32`, x `i32`, y `i32`, size `u16`)
+//!
+//! Files rotate every hour and at the start of every tournament round; each
+//! new file repeats the header and the known players.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use protocol::{BinaryReader, BinaryWriter};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::ReplayConfig;
+
+/// Identifies a recording; "CGRP" read as little-endian bytes.
+pub const REPLAY_MAGIC: u32 = u32::from_le_bytes(*b"CGRP");
+pub const REPLAY_VERSION: u8 = 1;
+/// Ticks buffered between the game loop and the recorder before frames drop.
+pub const REPLAY_CHANNEL_CAPACITY: usize = 1024;
+/// Wall-clock length of one recording file.
+const ROTATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const RECORD_HEADER: u8 = 0x01;
+const RECORD_PLAYER: u8 = 0x02;
+const RECORD_TICK: u8 = 0x03;
+
+/// A cell that appeared this tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpawnRecord {
+    pub node_id: u32,
+    pub cell_type: u8,
+    pub x: i32,
+    pub y: i32,
+    pub size: u16,
+    pub owner: Option<u32>,
+}
+
+/// A cell position in a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellSnapshot {
+    pub node_id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub size: u16,
+}
+
+/// Everything recorded for one tick.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TickFrame {
+    pub tick: u32,
+    /// (eater, eaten) pairs.
+    pub eats: Vec<(u32, u32)>,
+    pub spawns: Vec<SpawnRecord>,
+    pub removals: Vec<u32>,
+    pub snapshot: Option<Vec<CellSnapshot>>,
+}
+
+/// Messages from the game loop to the recorder task.
+#[derive(Debug, Clone)]
+pub enum ReplayMessage {
+    Tick(TickFrame),
+    /// A player joined or renamed.
+    Player { id: u32, name: String },
+    /// Start a new file (new tournament round).
+    Rotate,
+}
+
+fn encode_header(tick_interval_ms: u64, started: u64, gamemode: &str) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(32);
+    w.put_u8(RECORD_HEADER);
+    w.put_u32(REPLAY_MAGIC);
+    w.put_u8(REPLAY_VERSION);
+    w.put_u32(tick_interval_ms as u32);
+    w.put_u32(started as u32);
+    w.put_string_utf8_null(gamemode);
+    w
+}
+
+fn encode_player(id: u32, name: &str) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(8 + name.len());
+    w.put_u8(RECORD_PLAYER);
+    w.put_u32(id);
+    w.put_string_utf8_null(name);
+    w
+}
+
+fn encode_tick(frame: &TickFrame) -> BinaryWriter {
+    let mut w = BinaryWriter::with_capacity(64);
+    w.put_u8(RECORD_TICK);
+    w.put_u32(frame.tick);
+    w.put_u32(frame.eats.len() as u32);
+    for &(eater, eaten) in &frame.eats {
+        w.put_u32(eater);
+        w.put_u32(eaten);
+    }
+    w.put_u32(frame.spawns.len() as u32);
+    for s in &frame.spawns {
+        w.put_u32(s.node_id);
+        w.put_u8(s.cell_type);
+        w.put_i32(s.x);
+        w.put_i32(s.y);
+        w.put_u16(s.size);
+        w.put_u32(s.owner.unwrap_or(0));
+    }
+    w.put_u32(frame.removals.len() as u32);
+    for &id in &frame.removals {
+        w.put_u32(id);
+    }
+    match &frame.snapshot {
+        Some(cells) => {
+            w.put_u8(1);
+            w.put_u32(cells.len() as u32);
+            for c in cells {
+                w.put_u32(c.node_id);
+                w.put_i32(c.x);
+                w.put_i32(c.y);
+                w.put_u16(c.size);
+            }
+        }
+        None => w.put_u8(0),
+    }
+    w
+}
+
+/// Start the recorder task. The returned sender is fed from the game loop
+/// with `try_send`.
+pub fn spawn_recorder(config: &ReplayConfig, tick_interval_ms: u64, gamemode: String) -> mpsc::Sender<ReplayMessage> {
+    let (tx, rx) = mpsc::channel(REPLAY_CHANNEL_CAPACITY);
+    let directory = PathBuf::from(&config.directory);
+    tokio::spawn(async move {
+        if let Err(e) = run_recorder(rx, directory, tick_interval_ms, gamemode).await {
+            warn!("Replay recording stopped: {}", e);
+        }
+    });
+    tx
+}
+
+struct ReplayFile {
+    writer: BufWriter<tokio::fs::File>,
+    opened: Instant,
+}
+
+async fn run_recorder(
+    mut rx: mpsc::Receiver<ReplayMessage>,
+    directory: PathBuf,
+    tick_interval_ms: u64,
+    gamemode: String,
+) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(&directory).await?;
+    // Known players, repeated at the top of every file
+    let mut players: BTreeMap<u32, String> = BTreeMap::new();
+    let mut file: Option<ReplayFile> = None;
+    // Sequence number keeps files started within the same second apart
+    let mut sequence = 0u32;
+
+    while let Some(message) = rx.recv().await {
+        let rotate = matches!(message, ReplayMessage::Rotate)
+            || file.as_ref().is_some_and(|f| f.opened.elapsed() >= ROTATE_INTERVAL);
+        if rotate && let Some(mut old) = file.take() {
+            old.writer.flush().await?;
+        }
+
+        if file.is_none() {
+            let started = crate::highscores::unix_now();
+            let path = directory.join(format!("replay-{}-{}.cgr", started, sequence));
+            sequence += 1;
+            info!("Recording replay to {}", path.display());
+            let mut writer = BufWriter::new(tokio::fs::File::create(&path).await?);
+            write_record(&mut writer, encode_header(tick_interval_ms, started, &gamemode)).await?;
+            for (&id, name) in &players {
+                write_record(&mut writer, encode_player(id, name)).await?;
+            }
+            file = Some(ReplayFile { writer, opened: Instant::now() });
+        }
+        let Some(current) = file.as_mut() else { continue };
+
+        match message {
+            ReplayMessage::Tick(frame) => write_record(&mut current.writer, encode_tick(&frame)).await?,
+            ReplayMessage::Player { id, name } => {
+                write_record(&mut current.writer, encode_player(id, &name)).await?;
+                players.insert(id, name);
+            }
+            ReplayMessage::Rotate => {}
+        }
+        // Flush whenever the game loop isn't waiting on us
+        if rx.is_empty() {
+            current.writer.flush().await?;
+        }
+    }
+
+    if let Some(mut current) = file {
+        current.writer.flush().await?;
+    }
+    Ok(())
+}
+
+async fn write_record(writer: &mut BufWriter<tokio::fs::File>, record: BinaryWriter) -> std::io::Result<()> {
+    writer.write_all(&(record.len() as u32).to_le_bytes()).await?;
+    writer.write_all(record.as_slice()).await
+}
+
+/// Summary of a recording, for `--inspect-replay`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayInfo {
+    pub gamemode: String,
+    pub tick_interval_ms: u32,
+    /// Unix seconds the file was started.
+    pub started: u32,
+    pub first_tick: Option<u32>,
+    pub last_tick: Option<u32>,
+    /// Tick records in the file.
+    pub tick_count: u32,
+    pub snapshot_count: u32,
+    /// Players by id, latest name.
+    pub players: BTreeMap<u32, String>,
+}
+
+impl ReplayInfo {
+    /// Print the summary of the recording named by a `--inspect-replay
+    /// <file>` command-line argument. Returns whether there was one, in
+    /// which case the binary has nothing else to do.
+    pub fn inspect_from_args(args: &[String]) -> anyhow::Result<bool> {
+        let Some(i) = args.iter().position(|arg| arg == "--inspect-replay") else {
+            return Ok(false);
+        };
+        let path = args.get(i + 1).ok_or_else(|| anyhow::anyhow!("usage: --inspect-replay <file>"))?;
+        print!("{}", Self::read(Path::new(path))?);
+        Ok(true)
+    }
+
+    /// Parse a recording. A truncated trailing record (e.g. after a crash) is
+    /// ignored; anything else malformed is an error.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let mut info = Self::default();
+        let mut rest = data;
+        let mut seen_header = false;
+        while rest.len() >= 4 {
+            let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            if rest.len() - 4 < len {
+                break;
+            }
+            let mut r = BinaryReader::new(rest[4..4 + len].to_vec());
+            rest = &rest[4 + len..];
+
+            match r.try_get_u8() {
+                Some(RECORD_HEADER) => {
+                    let magic = r.try_get_u32();
+                    anyhow::ensure!(magic == Some(REPLAY_MAGIC), "not a replay file");
+                    let version = r.try_get_u8().unwrap_or(0);
+                    anyhow::ensure!(version == REPLAY_VERSION, "unsupported replay version {}", version);
+                    info.tick_interval_ms = r.try_get_u32().unwrap_or(0);
+                    info.started = r.try_get_u32().unwrap_or(0);
+                    info.gamemode = r.try_get_string_utf8_null()?;
+                    seen_header = true;
+                }
+                Some(RECORD_PLAYER) => {
+                    let id = r.try_get_u32().ok_or_else(|| anyhow::anyhow!("truncated player record"))?;
+                    info.players.insert(id, r.try_get_string_utf8_null()?);
+                }
+                Some(RECORD_TICK) => {
+                    let frame = decode_tick(&mut r).ok_or_else(|| anyhow::anyhow!("truncated tick record"))?;
+                    info.first_tick.get_or_insert(frame.tick);
+                    info.last_tick = Some(frame.tick);
+                    info.tick_count += 1;
+                    if frame.snapshot.is_some() {
+                        info.snapshot_count += 1;
+                    }
+                }
+                other => anyhow::bail!("unknown record type {:?}", other),
+            }
+            anyhow::ensure!(seen_header, "missing replay header");
+        }
+        anyhow::ensure!(seen_header, "missing replay header");
+        Ok(info)
+    }
+
+    /// Recorded game time.
+    pub fn duration(&self) -> Duration {
+        match (self.first_tick, self.last_tick) {
+            (Some(first), Some(last)) => {
+                Duration::from_millis((last - first + 1) as u64 * self.tick_interval_ms as u64)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl std::fmt::Display for ReplayInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Game mode: {}", self.gamemode)?;
+        writeln!(f, "Started: {} (Unix time)", self.started)?;
+        writeln!(f, "Duration: {:.1}s", self.duration().as_secs_f64())?;
+        writeln!(f, "Ticks: {} ({} snapshots)", self.tick_count, self.snapshot_count)?;
+        writeln!(f, "Players: {}", self.players.len())?;
+        for (id, name) in &self.players {
+            writeln!(f, "  [{}] {}", id, if name.is_empty() { "An unnamed cell" } else { name })?;
+        }
+        Ok(())
+    }
+}
+
+fn decode_tick(r: &mut BinaryReader) -> Option<TickFrame> {
+    let mut frame = TickFrame { tick: r.try_get_u32()?, ..TickFrame::default() };
+    for _ in 0..r.try_get_u32()? {
+        frame.eats.push((r.try_get_u32()?, r.try_get_u32()?));
+    }
+    for _ in 0..r.try_get_u32()? {
+        frame.spawns.push(SpawnRecord {
+            node_id: r.try_get_u32()?,
+            cell_type: r.try_get_u8()?,
+            x: r.try_get_i32()?,
+            y: r.try_get_i32()?,
+            size: r.try_get_u16()?,
+            owner: Some(r.try_get_u32()?).filter(|&o| o != 0),
+        });
+    }
+    for _ in 0..r.try_get_u32()? {
+        frame.removals.push(r.try_get_u32()?);
+    }
+    if r.try_get_u8()? == 1 {
+        let mut cells = Vec::new();
+        for _ in 0..r.try_get_u32()? {
+            cells.push(CellSnapshot {
+                node_id: r.try_get_u32()?,
+                x: r.try_get_i32()?,
+                y: r.try_get_i32()?,
+                size: r.try_get_u16()?,
+            });
+        }
+        frame.snapshot = Some(cells);
+    }
+    Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framed(records: &[BinaryWriter]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for record in records {
+            out.extend_from_slice(&(record.len() as u32).to_le_bytes());
+            out.extend_from_slice(record.as_slice());
+        }
+        out
+    }
+
+    #[test]
+    fn test_tick_frame_round_trips() {
+        let frame = TickFrame {
+            tick: 42,
+            eats: vec![(1, 2)],
+            spawns: vec![SpawnRecord { node_id: 3, cell_type: 0, x: -10, y: 20, size: 30, owner: Some(7) }],
+            removals: vec![2],
+            snapshot: Some(vec![CellSnapshot { node_id: 3, x: -5, y: 25, size: 31 }]),
+        };
+        let encoded = encode_tick(&frame);
+        let mut r = BinaryReader::new(encoded.as_slice().to_vec());
+        assert_eq!(r.try_get_u8(), Some(RECORD_TICK));
+        assert_eq!(decode_tick(&mut r), Some(frame));
+    }
+
+    #[test]
+    fn test_info_summarizes_recording() {
+        let mut data = framed(&[
+            encode_header(40, 1_700_000_000, "Free For All"),
+            encode_player(1, "Alice"),
+            encode_tick(&TickFrame { tick: 10, ..TickFrame::default() }),
+            encode_player(1, "Alicia"),
+            encode_player(2, "Bob"),
+            encode_tick(&TickFrame { tick: 11, snapshot: Some(Vec::new()), ..TickFrame::default() }),
+            encode_tick(&TickFrame { tick: 12, ..TickFrame::default() }),
+        ]);
+        // A record cut short by a crash is ignored
+        data.extend_from_slice(&100u32.to_le_bytes());
+        data.push(RECORD_TICK);
+
+        let info = ReplayInfo::parse(&data).unwrap();
+        assert_eq!(info.gamemode, "Free For All");
+        assert_eq!(info.tick_count, 3);
+        assert_eq!(info.snapshot_count, 1);
+        assert_eq!(info.duration(), Duration::from_millis(120));
+        assert_eq!(info.players.values().collect::<Vec<_>>(), vec!["Alicia", "Bob"]);
+
+        assert!(ReplayInfo::parse(b"garbage!").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_recorder_output_is_readable() {
+        let dir = std::env::temp_dir().join(format!("cogar-replay-{}", std::process::id()));
+        let config = ReplayConfig {
+            enabled: true,
+            directory: dir.to_string_lossy().into_owned(),
+            ..ReplayConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(REPLAY_CHANNEL_CAPACITY);
+        let recorder = tokio::spawn(run_recorder(rx, PathBuf::from(&config.directory), 40, "Tournament".to_string()));
+        tx.send(ReplayMessage::Player { id: 1, name: "Alice".to_string() }).await.unwrap();
+        tx.send(ReplayMessage::Tick(TickFrame { tick: 1, ..TickFrame::default() })).await.unwrap();
+        tx.send(ReplayMessage::Tick(TickFrame { tick: 2, ..TickFrame::default() })).await.unwrap();
+        drop(tx);
+        recorder.await.unwrap().unwrap();
+
+        let file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let info = ReplayInfo::read(&file).unwrap();
+        assert_eq!((info.gamemode.as_str(), info.tick_count), ("Tournament", 2));
+        assert_eq!(info.players[&1], "Alice");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,43 @@
 //! Client session state.
 
+use protocol::packets::EjectColor;
 use protocol::Color;
 use std::collections::HashSet;
 use std::net::SocketAddr;
 
+use super::ratelimit::PacketLimiter;
+use crate::config::PacketLimitsConfig;
+
+/// View size, in CSS pixels, for clients that never report their canvas.
+pub const DEFAULT_VIEW_WIDTH: f32 = 1920.0;
+pub const DEFAULT_VIEW_HEIGHT: f32 = 1080.0;
+
+/// Coordinate scramble offsets are drawn from `-SCRAMBLE_RANGE..SCRAMBLE_RANGE`,
+/// small enough that scrambled positions stay far from the i32 limits.
+pub const SCRAMBLE_RANGE: i32 = 1 << 15;
+
+/// Centroid of `(x, y, size)` cells weighted by size² (mass), so a big cell
+/// outweighs a cloud of small split pieces. `None` for no cells.
+pub fn weighted_center(cells: &[(f32, f32, f32)]) -> Option<(f32, f32)> {
+    // Offsets from the first cell keep a lone cell's center exact
+    let &(x0, y0, _) = cells.first()?;
+    let (mut dx, mut dy, mut total) = (0.0, 0.0, 0.0);
+    for &(x, y, size) in cells {
+        let mass = size * size;
+        dx += (x - x0) * mass;
+        dy += (y - y0) * mass;
+        total += mass;
+    }
+    if total > 0.0 {
+        Some((x0 + dx / total, y0 + dy / total))
+    } else {
+        // Zero-size cells only: fall back to the plain average
+        let count = cells.len() as f32;
+        let (sx, sy) = cells.iter().fold((0.0, 0.0), |(sx, sy), &(x, y, _)| (sx + x - x0, sy + y - y0));
+        Some((x0 + sx / count, y0 + sy / count))
+    }
+}
+
 /// A connected client session.
 #[derive(Debug)]
 pub struct Client {
@@ -32,10 +66,16 @@ pub struct Client {
     pub scramble_y: i32,
     /// Is spectating.
     pub is_spectating: bool,
+    /// Player (client or bot id) a spectating client's view follows.
+    pub spectate_target: Option<u32>,
     /// Is operator.
     pub is_operator: bool,
     /// Last activity timestamp.
     pub last_activity: std::time::Instant,
+    /// Warned about being idle since the last activity.
+    pub afk_warned: bool,
+    /// Rate limits for split and eject packets.
+    pub packet_limiter: PacketLimiter,
 
     // Viewport state
     /// Center position (average of owned cells, or spectate position).
@@ -43,6 +83,9 @@ pub struct Client {
     pub center_y: f32,
     /// Current zoom scale.
     pub scale: f32,
+    /// Canvas size the client reported, in CSS pixels, capped by the config.
+    pub view_width: f32,
+    pub view_height: f32,
     /// Viewport bounds.
     pub view_min_x: f32,
     pub view_min_y: f32,
@@ -72,6 +115,8 @@ pub struct Client {
     pub minions: Vec<u32>,
     /// Latest minion number assigned (for naming).
     pub latest_minion_id: u16,
+    /// Name given with `/minion name`; minions use the player's name when unset.
+    pub minion_name: Option<String>,
     /// Minion follow mode: follow owner center (true) vs mouse (false).
     pub minion_follow: bool,
     /// One-shot: trigger minion split this tick.
@@ -82,17 +127,36 @@ pub struct Client {
     pub minion_frozen: bool,
     /// Minion collect: seek nearest food.
     pub minion_collect: bool,
+    /// Minion feed (`/minion feed`): eject mass into the owner's largest cell.
+    pub minion_feed: bool,
     /// XRay mode: see all player cells (operator only).
     pub xray_enabled: bool,
     /// Player frozen: main cells stop moving toward mouse (minions unaffected).
     pub frozen: bool,
+    /// Highest total mass of the current life, submitted to the highscores when it ends.
+    pub peak_mass: f32,
+    /// Whether this connection has had its first join (announced or not).
+    pub joined: bool,
+    /// Ejected-mass color picked with a 0x1B; stock clients stay on `Cell`.
+    pub eject_color: EjectColor,
+    /// Pellets ejected in rainbow mode, to step the hue.
+    pub rainbow_ejects: u32,
 }
 
 impl Client {
-    /// Create a new client session.
-    pub fn new(id: u32, addr: SocketAddr) -> Self {
+    /// Create a new client session. `scramble_level` picks what is
+    /// randomized per client: 0 nothing, 1 node ids, 2 ids and coordinates.
+    /// Scrambling draws from the OS rather than the seeded game RNG so a
+    /// known seed doesn't give the offsets away.
+    pub fn new(id: u32, addr: SocketAddr, color: Color, limits: &PacketLimitsConfig, scramble_level: u8) -> Self {
         use rand::Rng;
         let mut rng = rand::rng();
+        let scramble_id = if scramble_level >= 1 { rng.random() } else { 0 };
+        let (scramble_x, scramble_y) = if scramble_level >= 2 {
+            (rng.random_range(-SCRAMBLE_RANGE..SCRAMBLE_RANGE), rng.random_range(-SCRAMBLE_RANGE..SCRAMBLE_RANGE))
+        } else {
+            (0, 0)
+        };
 
         Self {
             id,
@@ -101,23 +165,24 @@ impl Client {
             handshake_complete: false,
             name: String::new(),
             skin: None,
-            color: Color::new(
-                rng.random_range(50..=255),
-                rng.random_range(50..=255),
-                rng.random_range(50..=255),
-            ),
+            color,
             mouse_x: 0,
             mouse_y: 0,
             cells: Vec::new(),
-            scramble_id: rng.random(),
-            scramble_x: rng.random_range(-1000..1000),
-            scramble_y: rng.random_range(-1000..1000),
+            scramble_id,
+            scramble_x,
+            scramble_y,
             is_spectating: false,
+            spectate_target: None,
             is_operator: false,
             last_activity: std::time::Instant::now(),
+            afk_warned: false,
+            packet_limiter: PacketLimiter::new(limits, std::time::Instant::now()),
             center_x: 0.0,
             center_y: 0.0,
             scale: 1.0,
+            view_width: DEFAULT_VIEW_WIDTH,
+            view_height: DEFAULT_VIEW_HEIGHT,
             view_min_x: 0.0,
             view_min_y: 0.0,
             view_max_x: 0.0,
@@ -132,19 +197,32 @@ impl Client {
             minion_control: false,
             minions: Vec::new(),
             latest_minion_id: 0,
+            minion_name: None,
             minion_follow: false,
             minion_split: false,
             minion_eject: false,
             minion_frozen: false,
             minion_collect: false,
+            minion_feed: false,
             xray_enabled: false,
             frozen: false,
+            peak_mass: 0.0,
+            joined: false,
+            eject_color: EjectColor::Cell,
+            rainbow_ejects: 0,
         }
     }
 
     /// Update activity timestamp.
     pub fn touch(&mut self) {
         self.last_activity = std::time::Instant::now();
+        self.afk_warned = false;
+    }
+
+    /// Whether the client negotiated cogar's protocol and understands its
+    /// own opcodes (0x50 and up).
+    pub fn speaks_cogar(&self) -> bool {
+        self.protocol >= protocol::packets::COGAR_PROTOCOL
     }
 
     /// Get the player's total mass.
@@ -153,19 +231,12 @@ impl Client {
         0.0 // Placeholder
     }
 
-    /// Update the center position based on owned cells.
-    pub fn update_center(&mut self, positions: &[(f32, f32)]) {
-        if positions.is_empty() {
-            return;
-        }
-        let mut cx = 0.0;
-        let mut cy = 0.0; 
-        for (x, y) in positions {
-            cx += x;
-            cy += y;
+    /// Center on the mass-weighted centroid of `(x, y, size)` cells.
+    pub fn update_center(&mut self, cells: &[(f32, f32, f32)]) {
+        if let Some((x, y)) = weighted_center(cells) {
+            self.center_x = x;
+            self.center_y = y;
         }
-        self.center_x = cx / positions.len() as f32;
-        self.center_y = cy / positions.len() as f32;
     }
 
     /// Update the viewport based on scale.
@@ -1,29 +1,119 @@
 //! Game state and main loop.
 
-use crate::ai::BotManager;
-use crate::config::Config;
-use crate::entity::{Cell, CellType, PlayerCell};
-use crate::world::{CellEntry, World};
-use protocol::packets::ClientPacket;
+use crate::ai::{Bot, BotManager, SpawnRamp};
+use crate::announcements::{format_mass, Announcement, KillAnnouncer, PresenceThrottle};
+use crate::antiteam::AntiTeam;
+use crate::chatlog::{self, ChatLogEntry, ChatLogKind};
+use crate::config::{Config, MaxSizeBehavior};
+use crate::dump::{self, CellDump, PlayerDump, WorldDump};
+use crate::heatmap::Heatmap;
+use crate::entity::{decayed_size, Cell, CellType, PlayerCell};
+use crate::highscores::{HighscoreEntry, Highscores};
+use crate::metrics::{Entity, Metrics, Phase};
+use crate::replay::{CellSnapshot, ReplayMessage, SpawnRecord, TickFrame};
+use crate::world::{CellEntry, World, WorldMap};
+use protocol::CloseCode;
+use protocol::packets::{ClientPacket, DeathCause, EjectColor};
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{broadcast, RwLock};
-use tokio::time::{interval_at, sleep, Instant, MissedTickBehavior};
-use futures_util::FutureExt;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::time::{interval_at, sleep, Instant, Interval, MissedTickBehavior};
 use tracing::{debug, info, warn};
 use fixedbitset::FixedBitSet;
 
-use super::client::Client;
-use super::{ChatBroadcast, ClientViewData, LeaderboardBroadcast, TargetedMessage, TargetedMessageType, WorldCell, WorldUpdateBroadcast};
+use super::client::{weighted_center, Client};
+use super::ratelimit::{Action, SpawnLimiter, Verdict};
+use super::{ChatBroadcast, ClientViewData, LeaderboardBroadcast, TargetedMessage, TargetedMessageType, TargetedSender, WorldCell, WorldUpdateBroadcast};
+
+/// How far a collecting minion looks for food.
+const MINION_COLLECT_RADIUS: f32 = 500.0;
+/// Ticks between a feeding minion's ejects.
+const MINION_FEED_INTERVAL_TICKS: u64 = 3;
+/// Least time between full player rosters; each rides along with a
+/// leaderboard broadcast.
+const ROSTER_INTERVAL_MS: u64 = 2000;
+/// Most rows in a roster packet; the smallest players are dropped beyond it.
+const ROSTER_MAX_ENTRIES: usize = 128;
+/// How often a changed highscore board is written to disk.
+const HIGHSCORE_FLUSH_INTERVAL_MS: u64 = 60_000;
+/// Radius `/spawn food` scatters pellets over when none is given.
+const SPAWN_FOOD_DEFAULT_RADIUS: f32 = 200.0;
+/// Scores listed by `/top`.
+const TOP_COMMAND_ENTRIES: usize = 10;
+/// Chat color of rampage and big-kill announcements.
+const KILL_ANNOUNCEMENT_COLOR: protocol::Color = protocol::Color::new(255, 170, 0);
+/// Chat color of join and leave notices.
+const PRESENCE_COLOR: protocol::Color = protocol::Color::new(160, 160, 160);
+/// How often the frozen world is re-sent while `/pause` is on.
+const PAUSED_BROADCAST_INTERVAL_MS: u64 = 200;
+/// Time between the idle warning and the switch to spectating.
+const AFK_WARNING_SECS: u64 = 30;
+/// Consecutive ticks the average tick time must be over budget before the
+/// room counts as overloaded.
+const OVERLOAD_STREAK_TICKS: u32 = 10;
+/// Ticks of leaderboard, xray and decay work skipped once overload sets in.
+const OVERLOAD_SHED_TICKS: u64 = 25;
+/// Ticks of a cell's last movement it is sent ahead of reaching a view.
+const VIEW_LEAD_TICKS: f32 = 4.0;
+/// Most of a cell's last movement counted toward its lead; longer jumps are
+/// teleports, not motion that will carry on.
+const MAX_VIEW_LEAD_MOVE: f32 = 100.0;
 
 /// Pending broadcasts to send after releasing the game state lock.
 pub struct PendingBroadcasts {
     pub world_update: Option<WorldUpdateBroadcast>,
     pub leaderboard: Option<LeaderboardBroadcast>,
-    pub xray_messages: Vec<TargetedMessage>,
+    /// XRay messages.
+    pub targeted_messages: Vec<TargetedMessage>,
+    /// Serialized highscore board to write to disk, when it changed.
+    pub highscores: Option<String>,
+    /// World dump requested by `/dump`: file path and JSON.
+    pub dump: Option<(PathBuf, String)>,
+    /// Heatmap requested by `/heatmap`: file path and JSON.
+    pub heatmap: Option<(PathBuf, String)>,
+}
+
+/// A handshake the server refuses. [`GameState::handle_packet`] returns it
+/// so the connection closes its socket with `code` itself.
+#[derive(Debug, thiserror::Error)]
+#[error("{reason}")]
+pub struct HandshakeRejected {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+/// A disconnected player whose cells stay in the world, frozen, until the
+/// reconnect grace period runs out.
+struct ParkedClient {
+    client: Client,
+    /// Tick the connection dropped; the most recent wins a reconnect tie.
+    parked_tick: u64,
+    /// Tick after which the cells are removed.
+    deadline_tick: u64,
+}
+
+/// Game loop side of replay recording.
+struct ReplayFeed {
+    tx: mpsc::Sender<ReplayMessage>,
+    /// Cells present at the last recorded tick, to derive spawns and removals.
+    known_cells: HashSet<u32>,
+    /// Gamemode round of the current file.
+    round: u64,
+    /// Frames dropped because the recorder fell behind.
+    dropped: u64,
+}
+
+/// Game loop side of the chat log.
+struct ChatLogFeed {
+    tx: mpsc::Sender<ChatLogEntry>,
+    /// Path of the room, written on every line.
+    room: String,
+    /// Entries dropped because the writer fell behind.
+    dropped: u64,
 }
 
 /// World border (for protocol compatibility).
@@ -52,18 +142,44 @@ impl Border {
     }
 }
 
+/// Connected players by state, plus the bot count.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerCounts {
+    pub alive: u32,
+    pub dead: u32,
+    pub spectating: u32,
+    pub bots: u32,
+}
+
+impl PlayerCounts {
+    /// Connected players, bots excluded.
+    pub fn total(&self) -> u32 {
+        self.alive + self.dead + self.spectating
+    }
+}
+
 /// Main game state.
 pub struct GameState {
     pub config: Config,
     pub border: Border,
     pub tick_count: u64,
     pub start_time: std::time::Instant,
+    /// Set by `/pause`: ticks leave the world alone until `/resume`.
+    pub paused: bool,
+    /// Ticks run since the last `/pause`.
+    paused_ticks: u64,
 
     // ID counters
     next_client_id: u32,
 
     // Connected clients
     pub clients: HashMap<u32, Client>,
+    // Disconnected clients within the reconnect grace period, matched by IP and name
+    parked_clients: Vec<ParkedClient>,
+    /// Action packets dropped by the per-client rate limits, shown by /status.
+    pub dropped_action_packets: u64,
+    /// Recent spawns per IP, against `packet_limits.spawns_per_minute_per_ip`.
+    spawn_limiter: SpawnLimiter,
 
     // Game world (entities)
     pub world: World,
@@ -81,18 +197,56 @@ pub struct GameState {
     world_tx: broadcast::Sender<WorldUpdateBroadcast>,
 
     // Targeted message channel
-    targeted_tx: broadcast::Sender<TargetedMessage>,
+    targeted_tx: TargetedSender,
 
     // Tick count since last leaderboard update
     last_lb_tick: u64,
+    // Tick of the last decay / anti-team / AFK pass
+    last_decay_tick: u64,
+    // Tick of the last roster send
+    last_roster_tick: u64,
+    // Clients that changed name/skin while alive; their cells resend both next broadcast
+    identity_changed: HashSet<u32>,
+    // FFA anti-teaming transfer/proximity history
+    antiteam: AntiTeam,
+    kill_announcer: KillAnnouncer,
+    // Per-IP throttle on join and leave notices
+    presence: PresenceThrottle,
+    // All-time highscore board and the tick it was last flushed
+    highscores: Highscores,
+    last_highscore_flush_tick: u64,
+    // Replay recorder feed, when recording is enabled
+    replay: Option<ReplayFeed>,
+    // Chat log writer feed, when `logging.chat_file` is set
+    chat_log: Option<ChatLogFeed>,
+    // World dump taken by /dump, written to disk by the game loop
+    pending_dump: Option<(PathBuf, String)>,
+    /// Player positions sampled with `stats.heatmap_enabled`.
+    heatmap: Option<Heatmap>,
+    pending_heatmap: Option<(PathBuf, String)>,
 
     // Track eaten cells this tick: (eaten_id, eater_id)
     eaten_this_tick: Vec<(u32, u32)>,
+    /// Cells removed outside of eating since the last world broadcast (e.g. by `/clear`).
+    removed_cells: Vec<u32>,
     // Track player deaths this tick: (killer_owner, victim_owner)
     deaths_this_tick: Vec<(u32, u32)>,
+    /// Mass eaten off each player who died this tick, in that tick.
+    death_masses: HashMap<u32, f32>,
+    /// Players a mother cell ate this tick: (mother cell, victim).
+    mother_cell_deaths: Vec<(u32, u32)>,
 
     // Average tick duration in milliseconds (exponential moving average).
     pub update_time_avg: f64,
+    /// Ticks in a row the average has been over `server.tick_interval_ms`.
+    overload_streak: u32,
+    /// The average has been over budget for [`OVERLOAD_STREAK_TICKS`] ticks
+    /// and hasn't come back yet; reported in server stats.
+    pub overloaded: bool,
+    /// Leaderboard, xray and decay work is skipped before this tick.
+    shed_until_tick: u64,
+    /// Exported on `server.metrics_port`; shared with the connection tasks.
+    pub metrics: Arc<Metrics>,
 
     // Game mode
     pub gamemode: Box<dyn crate::gamemodes::GameMode>,
@@ -103,12 +257,16 @@ pub struct GameState {
     collision_eat_events: Vec<(u32, u32, f32)>,
     collision_cells_to_remove: FixedBitSet,
     collision_virus_pops: Vec<(u32, u32)>,
-    collision_virus_ate_eject: Vec<u32>,
+    // (virus, direction the eaten pellet was travelling)
+    collision_virus_ate_eject: Vec<(u32, glam::Vec2)>,
 
     // Reusable buffers for movement and broadcast (reduce allocations)
     movement_cell_targets: Vec<(u32, f32, f32, u32)>,
     movement_speed_mults: HashMap<u32, f32>,
     broadcast_world_cells: Vec<WorldCell>,
+    // Cell positions at the last broadcast and the one being built, for leads
+    broadcast_positions: HashMap<u32, glam::Vec2>,
+    broadcast_last_positions: HashMap<u32, glam::Vec2>,
     xray_client_ids: Vec<u32>,
 }
 
@@ -119,27 +277,74 @@ impl GameState {
         chat_tx: broadcast::Sender<ChatBroadcast>,
         lb_tx: broadcast::Sender<LeaderboardBroadcast>,
         world_tx: broadcast::Sender<WorldUpdateBroadcast>,
-        targeted_tx: broadcast::Sender<TargetedMessage>,
+        targeted_tx: TargetedSender,
     ) -> Self {
-        let world = World::new(config.border.width as f32, config.border.height as f32);
+        let mut world = World::new(config.border.width as f32, config.border.height as f32);
+        world.border.wrap = config.border.wrap;
+        world.food_palette = config.food.palette_colors();
+        world.food_size_distribution = config.food.size_distribution;
+        world.food_bonus_chance = config.food.bonus_chance as f32;
+        world.food_avoid_players = config.food.avoid_players;
+        world.food_sector_bias = config.food.sector_bias as f32;
+        if !config.map.file.is_empty() {
+            match WorldMap::load(Path::new(&config.map.file)) {
+                Ok(map) => {
+                    info!("Loaded {} map object(s) from {}", map.objects.len(), config.map.file);
+                    world.map = Arc::new(map);
+                }
+                Err(e) => warn!("Could not load map {}: {}; starting with an open map", config.map.file, e),
+            }
+        }
+        let seed = config.server.rng_seed.unwrap_or_else(rand::random);
+        world.seed(seed);
+        info!("Simulation seed: {}", seed);
 
         Self {
             config: config.clone(),
             border: Border::new(config.border.width, config.border.height),
             tick_count: 0,
+            paused: false,
+            paused_ticks: 0,
             start_time: std::time::Instant::now(),
             next_client_id: 1,
             clients: HashMap::new(),
+            parked_clients: Vec::new(),
+            dropped_action_packets: 0,
+            spawn_limiter: SpawnLimiter::new(),
             world,
-            bots: BotManager::new(),
+            bots: BotManager::new(bot_skins(&config.bots.skins)),
             chat_tx,
             lb_tx,
             world_tx,
             targeted_tx,
             last_lb_tick: 0,
+            last_decay_tick: 0,
+            last_roster_tick: 0,
+            identity_changed: HashSet::new(),
+            antiteam: AntiTeam::new(),
+            kill_announcer: KillAnnouncer::new(),
+            presence: PresenceThrottle::new(),
+            highscores: if config.stats.highscores_file.is_empty() {
+                Highscores::new(config.stats.highscores_size)
+            } else {
+                Highscores::load(Path::new(&config.stats.highscores_file), config.stats.highscores_size)
+            },
+            last_highscore_flush_tick: 0,
+            replay: None,
+            chat_log: None,
+            pending_dump: None,
+            heatmap: config.stats.heatmap_enabled.then(|| Heatmap::new(config.stats.heatmap_interval_ticks)),
+            pending_heatmap: None,
             eaten_this_tick: Vec::new(),
+            removed_cells: Vec::new(),
             deaths_this_tick: Vec::new(),
+            death_masses: HashMap::new(),
+            mother_cell_deaths: Vec::new(),
             update_time_avg: 0.0,
+            overload_streak: 0,
+            overloaded: false,
+            shed_until_tick: 0,
+            metrics: Arc::new(Metrics::default()),
             gamemode: crate::gamemodes::get_gamemode(config.server.gamemode),
             // Pre-allocate reusable buffers based on typical game loads
             // Sized for 128 players with 16 cells each = ~2048 cells
@@ -153,6 +358,8 @@ impl GameState {
             movement_cell_targets: Vec::with_capacity(2048),
             movement_speed_mults: HashMap::with_capacity(128),
             broadcast_world_cells: Vec::with_capacity(5000),
+            broadcast_positions: HashMap::with_capacity(5000),
+            broadcast_last_positions: HashMap::with_capacity(5000),
             xray_client_ids: Vec::with_capacity(16),
         }
     }
@@ -161,35 +368,262 @@ impl GameState {
     pub fn add_client(&mut self, addr: SocketAddr) -> u32 {
         let id = self.next_client_id;
         self.next_client_id += 1;
-        let client = Client::new(id, addr);
+        let color = World::random_color(&mut self.world.rng);
+        let client = Client::new(id, addr, color, &self.config.server.packet_limits, self.config.server.scramble_level);
         self.clients.insert(id, client);
         info!("Client {} connected from {}", id, addr);
         id
     }
 
+    /// Whether the client has finished the 0xFE / 0xFF handshake.
+    pub fn handshake_complete(&self, client_id: u32) -> bool {
+        self.clients.get(&client_id).is_some_and(|c| c.handshake_complete)
+    }
+
+    /// The protocol the client agreed on, once its handshake is complete.
+    pub fn negotiated_protocol(&self, client_id: u32) -> Option<u32> {
+        self.clients.get(&client_id)
+            .filter(|c| c.handshake_complete)
+            .map(|c| c.protocol)
+    }
+
     /// Remove a client.
     pub fn remove_client(&mut self, id: u32) {
-        if let Some(client) = self.clients.remove(&id) {
+        if let Some(mut client) = self.clients.remove(&id) {
             info!("Client {} ({}) disconnected", id, client.addr);
+            self.announce_leave(&client);
+            self.antiteam.remove_player(id);
+            self.kill_announcer.remove_player(id);
+            self.submit_highscore(&client.name, client.peak_mass);
             // Remove all cells owned by this client
             let cell_ids: Vec<u32> = client.cells.clone();
             for cell_id in cell_ids {
                 self.world.remove_cell(cell_id);
             }
-            
-            // Remove all minions owned by this client
-            for minion_id in &client.minions {
-                // First, remove all cells owned by the minion
-                if let Some(bot) = self.bots.get_bot(*minion_id) {
-                    let bot_cells: Vec<u32> = bot.cells.clone();
-                    for cell_id in bot_cells {
-                        self.world.remove_cell(cell_id);
-                    }
+            self.remove_minions(&mut client);
+        }
+    }
+
+    /// Handle a dropped connection. With a reconnect grace period the
+    /// client's cells are parked instead of removed; see [`Self::restore_parked`].
+    pub fn disconnect_client(&mut self, id: u32) {
+        let grace = self.config.player.reconnect_grace_seconds;
+        let parkable = grace > 0.0 && self.clients.get(&id).is_some_and(|c| !c.cells.is_empty());
+        if !parkable {
+            self.remove_client(id);
+            return;
+        }
+        let Some(mut client) = self.clients.remove(&id) else {
+            return;
+        };
+        info!("Client {} ({}) disconnected, keeping {} cell(s) for {}s", id, client.addr, client.cells.len(), grace);
+        self.antiteam.remove_player(id);
+        self.kill_announcer.remove_player(id);
+        // Minions would fall back to the free-roaming bot AI without an owner
+        self.remove_minions(&mut client);
+
+        let grace_ticks = (grace * 1000.0) as u64 / self.config.server.tick_interval_ms.max(1);
+        self.parked_clients.push(ParkedClient {
+            client,
+            parked_tick: self.tick_count,
+            deadline_tick: self.tick_count + grace_ticks,
+        });
+    }
+
+    /// Tell everyone a named player joined, on the connection's first spawn.
+    fn announce_join(&mut self, client_id: u32) {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+        if std::mem::replace(&mut client.joined, true) || client.name.is_empty() {
+            return;
+        }
+        let (name, ip) = (client.name.clone(), client.addr.ip());
+        if self.config.announcements.join_leave
+            && self.presence.try_notice(&self.config.announcements, ip, self.game_time_ms())
+        {
+            self.announce_with_color(&format!("{} joined", name), PRESENCE_COLOR);
+        }
+    }
+
+    /// Tell everyone a named player left while still alive.
+    fn announce_leave(&mut self, client: &Client) {
+        if !self.config.announcements.join_leave || client.name.is_empty() {
+            return;
+        }
+        let mass: f32 = client.cells.iter()
+            .filter_map(|&cell_id| self.world.get_cell(cell_id))
+            .filter(|cell| cell.data().owner_id == Some(client.id))
+            .map(|cell| cell.data().mass)
+            .sum();
+        if mass <= 0.0 || !self.presence.try_notice(&self.config.announcements, client.addr.ip(), self.game_time_ms()) {
+            return;
+        }
+        self.announce_with_color(&format!("{} left the game (mass {})", client.name, format_mass(mass)), PRESENCE_COLOR);
+    }
+
+    /// Remove a client's minions and their cells.
+    fn remove_minions(&mut self, client: &mut Client) {
+        for minion_id in client.minions.drain(..) {
+            // First, remove all cells owned by the minion
+            if let Some(bot) = self.bots.get_bot(minion_id) {
+                let bot_cells: Vec<u32> = bot.cells.clone();
+                for cell_id in bot_cells {
+                    self.world.remove_cell(cell_id);
+                }
+            }
+            // Then remove the minion bot itself
+            self.bots.remove_bot(minion_id);
+        }
+    }
+
+    /// Hand a parked client's surviving cells to `client_id` if it connects
+    /// from the same IP under the same name. Two parked players sharing an IP
+    /// are told apart by name, then the most recent disconnect wins.
+    fn restore_parked(&mut self, client_id: u32, name: &str) -> bool {
+        let Some(ip) = self.clients.get(&client_id).map(|c| c.addr.ip()) else {
+            return false;
+        };
+        let Some(index) = self.parked_clients.iter().enumerate()
+            .filter(|(_, p)| p.client.addr.ip() == ip && p.client.name == name)
+            .max_by_key(|(_, p)| p.parked_tick)
+            .map(|(i, _)| i)
+        else {
+            return false;
+        };
+        let parked = self.parked_clients.swap_remove(index).client;
+
+        // Cells eaten while parked are gone; re-own the survivors
+        let mut cells = Vec::with_capacity(parked.cells.len());
+        for cell_id in parked.cells {
+            if let Some(CellEntry::Player(cell)) = self.world.get_cell_mut(cell_id)
+                && cell.cell_data.owner_id == Some(parked.id)
+            {
+                cell.cell_data.owner_id = Some(client_id);
+                cells.push(cell_id);
+                self.world.update_cell_position(cell_id);
+            }
+        }
+        if cells.is_empty() {
+            return false;
+        }
+
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return false;
+        };
+        client.color = parked.color;
+        client.peak_mass = parked.peak_mass;
+        client.cells = cells;
+        // Picking up where they left off isn't a join
+        client.joined = true;
+        let scramble_id = client.scramble_id;
+        for &node_id in &client.cells {
+            let _ = self.targeted_tx.send(TargetedMessage {
+                client_id,
+                message: TargetedMessageType::AddNode { node_id, scramble_id },
+            });
+        }
+        // Viewers knew these cells under the old owner; resend name and skin
+        self.identity_changed.insert(client_id);
+        info!("Client {} reclaimed {} cell(s) of disconnected client {}", client_id, self.clients[&client_id].cells.len(), parked.id);
+        true
+    }
+
+    /// Remove the cells of parked clients whose grace period ran out.
+    fn expire_parked_clients(&mut self) {
+        let tick = self.tick_count;
+        let (expired, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.parked_clients)
+            .into_iter()
+            .partition(|p| tick > p.deadline_tick);
+        self.parked_clients = kept;
+        for parked in expired {
+            debug!("Reconnect grace for client {} expired", parked.client.id);
+            self.announce_leave(&parked.client);
+            self.submit_highscore(&parked.client.name, parked.client.peak_mass);
+            for cell_id in parked.client.cells {
+                if self.world.get_cell(cell_id).is_some_and(|c| c.data().owner_id == Some(parked.client.id)) {
+                    self.world.remove_cell(cell_id);
+                }
+            }
+        }
+    }
+
+    /// Run an action packet through the client's rate limits. Excess packets
+    /// are dropped silently; sustained abuse disconnects the client.
+    fn allow_action(&mut self, client_id: u32, action: Action) -> bool {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return false;
+        };
+        match client.packet_limiter.check(action, std::time::Instant::now()) {
+            Verdict::Allow => true,
+            Verdict::Drop => {
+                self.dropped_action_packets += 1;
+                false
+            }
+            Verdict::Abuse => {
+                warn!("Client {} ({}) is flooding {:?} packets; disconnecting", client_id, client.addr, action);
+                let _ = self.targeted_tx.send(TargetedMessage {
+                    client_id,
+                    message: TargetedMessageType::Disconnect { code: CloseCode::PacketFlood, reason: "packet flood".to_string() },
+                });
+                false
+            }
+        }
+    }
+
+    /// Warn, then spectate, then disconnect players who stopped sending
+    /// packets: the warning comes after `afk_timeout_seconds` idle,
+    /// spectating [`AFK_WARNING_SECS`] later and the disconnect one more
+    /// timeout after that.
+    fn check_afk_clients(&mut self) {
+        let timeout = self.config.server.afk_timeout_seconds;
+        if timeout == 0 {
+            return;
+        }
+        let warn_after = std::time::Duration::from_secs(timeout);
+        let spectate_after = warn_after + std::time::Duration::from_secs(AFK_WARNING_SECS);
+        let disconnect_after = spectate_after + warn_after;
+
+        let mut warned = Vec::new();
+        let mut spectated = Vec::new();
+        let mut disconnected = Vec::new();
+        for client in self.clients.values_mut() {
+            if !client.handshake_complete || client.is_operator {
+                continue;
+            }
+            let idle = client.last_activity.elapsed();
+            if idle < warn_after {
+                continue;
+            }
+            if client.cells.is_empty() {
+                if idle >= disconnect_after {
+                    disconnected.push(client.id);
                 }
-                // Then remove the minion bot itself
-                self.bots.remove_bot(*minion_id);
+            } else if !client.afk_warned {
+                client.afk_warned = true;
+                warned.push(client.id);
+            } else if idle >= spectate_after {
+                for cell_id in client.cells.drain(..) {
+                    self.world.remove_cell(cell_id);
+                }
+                client.is_spectating = true;
+                spectated.push(client.id);
             }
         }
+
+        for id in warned {
+            self.send_server_message(id, &format!("You are idle. Move within {} seconds or you will be switched to spectating.", AFK_WARNING_SECS));
+        }
+        for id in spectated {
+            info!("Client {} is idle; switched to spectating", id);
+            self.send_server_message(id, "You were idle for too long and are now spectating.");
+        }
+        for id in disconnected {
+            let _ = self.targeted_tx.send(TargetedMessage {
+                client_id: id,
+                message: TargetedMessageType::Disconnect { code: CloseCode::Idle, reason: "idle for too long".to_string() },
+            });
+        }
     }
 
     /// Handle a packet from a client.
@@ -215,6 +649,20 @@ impl GameState {
         } else {
             debug!("Client {} sent {:?}", client_id, packet);
         }
+        let action = match packet {
+            ClientPacket::Split => Some(Action::Split),
+            ClientPacket::Eject => Some(Action::Eject),
+            ClientPacket::Join { .. } => Some(Action::Join),
+            // Commands only answer the sender, so only broadcasts are limited
+            ClientPacket::Chat { ref message, .. } if !message.starts_with('/') => Some(Action::Chat),
+            _ => None,
+        };
+        if let Some(action) = action && !self.allow_action(client_id, action) {
+            if let ClientPacket::Chat { message, .. } = &packet {
+                self.log_chat(client_id, ChatLogKind::Dropped, message);
+            }
+            return Ok(());
+        }
         match packet {
             ClientPacket::Join { name } => {
                 self.handle_join(client_id, name)?;
@@ -224,16 +672,28 @@ impl GameState {
                     client.is_spectating = true;
                 }
             }
+            ClientPacket::SpectateTarget { player_id } => {
+                self.handle_spectate_target(client_id, player_id);
+            }
+            ClientPacket::Viewport { width, height } => {
+                let server = &self.config.server;
+                let (max_w, max_h) = (server.max_view_width, server.max_view_height);
+                if let Some(client) = self.clients.get_mut(&client_id) {
+                    client.view_width = f32::from(width.clamp(1, max_w));
+                    client.view_height = f32::from(height.clamp(1, max_h));
+                }
+            }
+            ClientPacket::EjectColor(color) => self.handle_eject_color(client_id, color),
             ClientPacket::Mouse { x, y } => {
                 if let Some(client) = self.clients.get_mut(&client_id) {
-                    client.mouse_x = x - client.scramble_x;
-                    client.mouse_y = y - client.scramble_y;
+                    client.mouse_x = x.saturating_sub(client.scramble_x);
+                    client.mouse_y = y.saturating_sub(client.scramble_y);
                 }
             }
-            ClientPacket::Split => {
+            ClientPacket::Split if !self.paused => {
                 self.handle_split(client_id);
             }
-            ClientPacket::Eject => {
+            ClientPacket::Eject if !self.paused => {
                 self.handle_eject(client_id);
             }
             ClientPacket::Chat { message, .. } => {
@@ -309,22 +769,28 @@ impl GameState {
             0xFE if data.len() == 5 => {
                 // Protocol version
                 let version = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
-                if !(1..=17).contains(&version) {
+                if !(1..=protocol::packets::COGAR_PROTOCOL).contains(&version) {
                     warn!(
                         "Client {} sent unsupported protocol version {}",
                         client_id, version
                     );
-                    return Err(anyhow::anyhow!("Unsupported protocol"));
+                    let reason = format!("unsupported protocol {}", version);
+                    return Err(HandshakeRejected { code: CloseCode::UnsupportedProtocol, reason }.into());
                 }
                 client.protocol = version;
                 debug!("Client {} using protocol {}", client_id, version);
+                let layout = protocol::packets::update_nodes_layout(version);
+                if layout != version {
+                    debug!("Client {}: no UpdateNodes layout for protocol {}, sending {}'s", client_id, version, layout);
+                }
             }
             0xFF if data.len() == 5 => {
                 // Handshake key
                 let key = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
                 if client.protocol > 6 && key != 0 {
                     warn!("Client {} sent invalid handshake key", client_id);
-                    return Err(anyhow::anyhow!("Invalid handshake key"));
+                    let reason = format!("invalid handshake key {}", key);
+                    return Err(HandshakeRejected { code: CloseCode::InvalidHandshakeKey, reason }.into());
                 }
                 client.handshake_complete = true;
                 info!(
@@ -349,8 +815,19 @@ impl GameState {
                         scramble_y: client.scramble_y,
                         game_type: self.config.server.gamemode,
                         server_name: self.config.server.name.clone(),
+                        unlimited_zoom: client.speaks_cogar().then_some(self.config.server.allow_unlimited_zoom),
                     },
                 });
+                if !self.world.map.is_empty() && client.speaks_cogar() {
+                    let _ = self.targeted_tx.send(TargetedMessage {
+                        client_id,
+                        message: TargetedMessageType::MapObjects {
+                            shapes: self.world.map.objects.iter().map(|object| object.shape()).collect(),
+                            scramble_x: client.scramble_x,
+                            scramble_y: client.scramble_y,
+                        },
+                    });
+                }
             }
             _ => {
                 warn!("Client {} sent unexpected handshake packet", client_id);
@@ -360,26 +837,37 @@ impl GameState {
         Ok(())
     }
 
+    /// Canonicalize a nickname and cut it to `player.max_nick_length` columns.
+    fn clamp_nick(&self, name: &str) -> String {
+        crate::names::canonical_name(name, self.config.player.max_nick_length)
+    }
+
     /// Handle join request.
     fn handle_join(&mut self, client_id: u32, name: String) -> anyhow::Result<()> {
         // Parse name and skin
         let (skin, player_name) = parse_name_and_skin(&name);
-        let player_name: String = player_name
-            .chars()
-            .take(self.config.player.max_nick_length)
-            .collect();
+        let skin = skin.and_then(|s| sanitize_skin(&s, self.config.server.allow_url_skins));
+        let player_name = self.clamp_nick(&player_name);
 
         // Update client
+        let team_sizes = self.team_sizes();
         {
             let client = self
                 .clients
                 .get_mut(&client_id)
                 .ok_or_else(|| anyhow::anyhow!("Client not found"))?;
+            if !client.cells.is_empty() && (client.name != player_name || client.skin != skin) {
+                self.identity_changed.insert(client_id);
+            }
             client.name = player_name.clone();
             client.skin = skin;
+            client.spectate_target = None;
+            if let Some(feed) = &self.replay {
+                let _ = feed.tx.try_send(ReplayMessage::Player { id: client_id, name: player_name.clone() });
+            }
             
             // Let GameMode handle team assignment etc.
-            self.gamemode.on_player_join(client);
+            self.gamemode.on_player_join(client, &team_sizes, &mut self.world.rng);
         }
 
         let team = self.clients.get(&client_id).and_then(|c| c.team);
@@ -399,6 +887,12 @@ impl GameState {
             }
         );
 
+        // Reclaim cells parked by a recent disconnect from the same IP and name
+        let has_own_cells = self.clients.get(&client_id).is_some_and(|c| !c.cells.is_empty());
+        if !has_own_cells {
+            self.restore_parked(client_id, &player_name);
+        }
+
         // Spawn player cell only if they don't already have any
         let has_cells = self.world.cells.values()
             .filter_map(|cell| {
@@ -409,9 +903,18 @@ impl GameState {
                 }
             })
             .any(|owner| owner == client_id);
-        if !has_cells {
-            self.spawn_player(client_id);
+        if has_cells {
+            return Ok(());
+        }
+        let ip = self.clients.get(&client_id).map(|c| c.addr.ip());
+        let per_minute = self.config.server.packet_limits.spawns_per_minute_per_ip;
+        if let Some(ip) = ip && !self.spawn_limiter.try_spawn(ip, per_minute, std::time::Instant::now()) {
+            warn!("Client {} ({}) is over the per-IP spawn limit; not spawning", client_id, ip);
+            self.send_server_message(client_id, "Too many spawns from your address; wait a minute.");
+            return Ok(());
         }
+        self.spawn_player(client_id);
+        self.announce_join(client_id);
 
         // Spawn default minions if configured
         let minion_count = self.config.server.server_minions;
@@ -422,29 +925,14 @@ impl GameState {
         Ok(())
     }
 
-    /// Get fuzzy color for a team (matching Teams.js fuzzColor)
-    #[allow(dead_code)]
-    fn get_team_color(team: u8) -> protocol::Color {
-        let mut rng = rand::rng();
-        let fuzz = 38;
-        
-        let base_color = match team {
-            0 => (255, 0, 0), // Red
-            1 => (0, 255, 0), // Green
-            _ => (0, 0, 255), // Blue
-        };
-
-        let r = (base_color.0 as i32 + rng.random_range(0..fuzz)).clamp(0, 255) as u8;
-        let g = (base_color.1 as i32 + rng.random_range(0..fuzz)).clamp(0, 255) as u8;
-        let b = (base_color.2 as i32 + rng.random_range(0..fuzz)).clamp(0, 255) as u8;
-
-        protocol::Color::new(r, g, b)
-    }
-
     /// Spawn a player cell for the given client.
     pub fn spawn_player(&mut self, client_id: u32) {
-        let start_size = self.config.player.start_size as f32;
-        let position = self.world.border.random_position();
+        let start_size = self.gamemode.get_spawn_size(client_id)
+            .unwrap_or(self.config.player.start_size as f32);
+        let position = match self.gamemode.get_spawn_position(&self.world, client_id) {
+            Some(position) => position,
+            None => self.world.open_position(start_size),
+        };
         let node_id = self.world.next_id();
 
         let mut cell = PlayerCell::new(node_id, client_id, position, start_size, self.tick_count);
@@ -452,7 +940,7 @@ impl GameState {
         // Get client color and scramble_id
         let scramble_id = if let Some(client) = self.clients.get_mut(&client_id) {
             // Let GameMode specialize the client if needed (e.g. refresh fuzzy color)
-            self.gamemode.on_player_spawn(client);
+            self.gamemode.on_player_spawn(client, &mut self.world.rng);
             
             cell.cell_data.color = client.color;
             client.scramble_id
@@ -467,6 +955,12 @@ impl GameState {
             client.cells.push(cell_id);
         }
 
+        // Clear the previous life's cells first, in the same targeted
+        // sequence, so the clear can't land after the new AddNode
+        let _ = self.targeted_tx.send(TargetedMessage {
+            client_id,
+            message: TargetedMessageType::ClearOwned,
+        });
         // Send AddNode packet to tell client which cell is theirs
         let _ = self.targeted_tx.send(TargetedMessage {
             client_id,
@@ -518,6 +1012,7 @@ impl GameState {
         debug!("Client/Bot {} splitting {} cells", client_id, cells_to_split.len());
 
         // Process each cell split
+        let mut timer_cells = Vec::new();
         for cell_id in cells_to_split {
             // Check if still under max cells
             if let Some(client) = self.clients.get(&client_id) {
@@ -536,8 +1031,8 @@ impl GameState {
             };
 
             // Calculate split angle toward mouse
-            let dx = mouse_x as f32 - position.x;
-            let dy = mouse_y as f32 - position.y;
+            let delta = self.world.border.delta(position, glam::Vec2::new(mouse_x, mouse_y));
+            let (dx, dy) = (delta.x, delta.y);
             let angle = if dx * dx + dy * dy < 1.0 {
                 0.0 // No direction, split straight up
             } else {
@@ -552,11 +1047,14 @@ impl GameState {
                 continue;
             }
 
-            // Shrink parent cell
-            if let Some(cell) = self.world.get_cell_mut(cell_id) {
-                cell.data_mut().set_size(new_size);
+            // Shrink parent cell and restart its recombine timer
+            let recombine_time = self.config.player.recombine_time as f32;
+            if let Some(CellEntry::Player(cell)) = self.world.get_cell_mut(cell_id) {
+                cell.cell_data.set_size(new_size);
+                cell.start_merge_timer(self.tick_count, recombine_time);
             }
             self.world.update_cell_position(cell_id);
+            timer_cells.push(cell_id);
 
             // Create new split cell
             let new_id = self.world.next_id();
@@ -568,6 +1066,7 @@ impl GameState {
                 self.tick_count,
             );
             new_cell.cell_data.color = color;
+            new_cell.start_merge_timer(self.tick_count, recombine_time);
 
             // Apply boost in split direction
             // JS: cell.setBoost(this.config.playerSplitSpeed * Math.pow(size, .0122), angle)
@@ -596,7 +1095,10 @@ impl GameState {
                     scramble_id,
                 },
             });
+            timer_cells.push(cell_id);
         }
+
+        self.send_merge_timers(client_id, &timer_cells);
     }
 
     /// Split a player cell into a new cell with a specific mass (used for virus popping).
@@ -655,8 +1157,10 @@ impl GameState {
         if new_parent_size < self.config.player.min_size as f32 {
             return; // JS: if (isNaN(size2) || size2 < playerMinDecay) return
         }
-        if let Some(parent) = self.world.get_cell_mut(parent_cell_id) {
-            parent.data_mut().set_size(new_parent_size);
+        let recombine_time = self.config.player.recombine_time as f32;
+        if let Some(CellEntry::Player(parent)) = self.world.get_cell_mut(parent_cell_id) {
+            parent.cell_data.set_size(new_parent_size);
+            parent.start_merge_timer(self.tick_count, recombine_time);
         }
         self.world.update_cell_position(parent_cell_id);
 
@@ -670,6 +1174,7 @@ impl GameState {
             self.tick_count,
         );
         new_cell.cell_data.color = color;
+        new_cell.start_merge_timer(self.tick_count, recombine_time);
 
         // Apply boost in split direction
         let boost_distance = split_speed * new_size.powf(0.0122);
@@ -697,15 +1202,41 @@ impl GameState {
                 scramble_id,
             },
         });
+        self.send_merge_timers(owner_id, &[parent_cell_id, cell_id]);
+    }
+
+    /// Tell a client how long each of the given cells has left before it may
+    /// remerge. Bots have no connection, and only cogar clients know the
+    /// opcode, so everyone else is skipped.
+    fn send_merge_timers(&self, client_id: u32, cell_ids: &[u32]) {
+        let Some(client) = self.clients.get(&client_id).filter(|c| c.speaks_cogar()) else {
+            return;
+        };
+        let tick_ms = self.config.server.tick_interval_ms;
+        let timers: Vec<(u32, u32)> = cell_ids.iter()
+            .filter_map(|&id| match self.world.get_cell(id) {
+                Some(CellEntry::Player(cell)) => {
+                    let ticks_left = cell.merge_tick.saturating_sub(self.tick_count);
+                    Some((id, (ticks_left * tick_ms).min(u32::MAX as u64) as u32))
+                }
+                _ => None,
+            })
+            .collect();
+        if timers.is_empty() {
+            return;
+        }
+        let _ = self.targeted_tx.send(TargetedMessage {
+            client_id,
+            message: TargetedMessageType::MergeTimer {
+                timers,
+                scramble_id: client.scramble_id,
+            },
+        });
     }
 
     /// Handle eject request (W key).
     fn handle_eject(&mut self, client_id: u32) {
         let eject_cooldown = self.config.eject.cooldown as u64;
-        let min_eject_size = self.config.player.min_eject_size as f32;
-        let eject_size_loss = self.config.eject.size_loss as f32;
-        let eject_size = self.config.eject.size as f32;
-        let eject_speed = self.config.eject.speed as f32;
         let tick_count = self.tick_count;
 
         // Get cells and mouse/target position, with cooldown check for human clients
@@ -729,73 +1260,128 @@ impl GameState {
 
         debug!("Client {} ejecting from {} cells", client_id, cell_ids.len());
 
-        // Process each cell
+        let target = glam::Vec2::new(mouse_x as f32, mouse_y as f32);
         for cell_id in cell_ids {
-            // Get cell data
-            let (cell_pos, cell_size, cell_color) = match self.world.get_cell(cell_id) {
-                Some(cell) => {
-                    let data = cell.data();
-                    (data.position, data.size, data.color)
-                }
-                None => continue,
-            };
+            self.eject_from_cell(client_id, cell_id, target, false);
+        }
+    }
 
-            // Check if cell is big enough to eject
-            if cell_size < min_eject_size {
-                continue;
+    /// Store the player's ejected-mass color. Colors that would vanish into
+    /// either client theme's background fall back to the cell color.
+    fn handle_eject_color(&mut self, client_id: u32, color: EjectColor) {
+        let color = match color {
+            EjectColor::Solid(rgb) if !visible_on_backgrounds(rgb) => {
+                self.send_server_message(client_id, "That eject color is too hard to see; using your cell color.");
+                EjectColor::Cell
             }
+            color => color,
+        };
+        if let Some(client) = self.clients.get_mut(&client_id) {
+            client.eject_color = color;
+        }
+    }
 
-            // Calculate direction toward mouse
-            let dx = mouse_x as f32 - cell_pos.x;
-            let dy = mouse_y as f32 - cell_pos.y;
-            let squared = dx * dx + dy * dy;
-            let (norm_dx, norm_dy) = if squared > 1.0 {
-                let dist = squared.sqrt();
-                (dx / dist, dy / dist)
-            } else {
-                (0.0, 0.0)
-            };
-
-            // Shrink the cell
-            // JS: cell.setSize(Math.sqrt(cell.radius - loss * loss))
-            let cell_radius = cell_size * cell_size;
-            let new_radius = cell_radius - eject_size_loss * eject_size_loss;
-            if new_radius <= 0.0 {
-                continue;
+    /// Color of a pellet ejected by `ejector_id` from a cell of `cell_color`.
+    /// Bots and minions always use the cell color. Rainbow FFA recolors
+    /// every cell each tick, so it overrides this anyway.
+    fn eject_color(&mut self, ejector_id: u32, cell_color: protocol::Color) -> protocol::Color {
+        let Some(client) = self.clients.get_mut(&ejector_id) else {
+            return cell_color;
+        };
+        match client.eject_color {
+            EjectColor::Cell => cell_color,
+            EjectColor::Solid(rgb) => rgb,
+            EjectColor::Rainbow => {
+                client.rainbow_ejects = client.rainbow_ejects.wrapping_add(1);
+                rainbow_color(client.rainbow_ejects)
             }
-            let new_size = new_radius.sqrt();
+        }
+    }
 
-            if let Some(cell) = self.world.get_cell_mut(cell_id) {
-                cell.data_mut().set_size(new_size);
+    /// Eject one pellet from `cell_id` toward `target`. Normally pellets get
+    /// some random spread and fly the full eject distance; with
+    /// `land_on_target` they fly straight and stop at `target`. Returns
+    /// whether the cell was big enough to eject.
+    fn eject_from_cell(&mut self, ejector_id: u32, cell_id: u32, target: glam::Vec2, land_on_target: bool) -> bool {
+        let min_eject_size = self.config.player.min_eject_size as f32;
+        let eject_size_loss = self.config.eject.size_loss as f32;
+        let eject_size = self.config.eject.size as f32;
+        let eject_speed = self.config.eject.speed as f32;
+        let tick_count = self.tick_count;
+
+        // Get cell data
+        let (cell_pos, cell_size, cell_color) = match self.world.get_cell(cell_id) {
+            Some(cell) => {
+                let data = cell.data();
+                (data.position, data.size, data.color)
             }
-            self.world.update_cell_position(cell_id);
+            None => return false,
+        };
 
-            // Spawn position: at the edge of the cell in the eject direction
-            let spawn_pos = glam::Vec2::new(
-                cell_pos.x + norm_dx * new_size,
-                cell_pos.y + norm_dy * new_size,
-            );
+        // Check if cell is big enough to eject
+        if cell_size < min_eject_size {
+            return false;
+        }
 
-            // Calculate eject angle
-            let angle = if norm_dx == 0.0 && norm_dy == 0.0 {
-                std::f32::consts::FRAC_PI_2
-            } else {
-                // Add some random variation
-                let mut rng = rand::rng();
-                let base_angle = norm_dx.atan2(norm_dy);
-                base_angle + rng.random_range(-0.3..0.3)
-            };
+        // Calculate direction toward mouse
+        let delta = self.world.border.delta(cell_pos, target);
+        let (dx, dy) = (delta.x, delta.y);
+        let squared = dx * dx + dy * dy;
+        let (norm_dx, norm_dy) = if squared > 1.0 {
+            let dist = squared.sqrt();
+            (dx / dist, dy / dist)
+        } else {
+            (0.0, 0.0)
+        };
 
-            // Create ejected mass
-            let eject_id = self.world.next_id();
-            let mut eject = crate::entity::EjectedMass::new(eject_id, spawn_pos, eject_size, tick_count);
-            eject.set_color(cell_color);
-            eject.data_mut().set_boost(eject_speed, angle);
+        // Shrink the cell
+        // JS: cell.setSize(Math.sqrt(cell.radius - loss * loss))
+        let cell_radius = cell_size * cell_size;
+        let new_radius = cell_radius - eject_size_loss * eject_size_loss;
+        if new_radius <= 0.0 {
+            return false;
+        }
+        let new_size = new_radius.sqrt();
 
-            // Add to world
-            let new_id = self.world.add_eject(eject);
-            self.world.add_moving(new_id);
+        if let Some(cell) = self.world.get_cell_mut(cell_id) {
+            cell.data_mut().set_size(new_size);
         }
+        self.world.update_cell_position(cell_id);
+
+        // Spawn position: at the edge of the cell in the eject direction
+        let spawn_pos = glam::Vec2::new(
+            cell_pos.x + norm_dx * new_size,
+            cell_pos.y + norm_dy * new_size,
+        );
+
+        // Calculate eject angle
+        let angle = if norm_dx == 0.0 && norm_dy == 0.0 {
+            std::f32::consts::FRAC_PI_2
+        } else if land_on_target {
+            norm_dx.atan2(norm_dy)
+        } else {
+            // Add some random variation
+            let base_angle = norm_dx.atan2(norm_dy);
+            base_angle + self.world.rng.random_range(-0.3..0.3)
+        };
+        // Boost movement decays toward its full distance, so it ends there
+        let distance = if land_on_target {
+            self.world.border.delta(spawn_pos, target).length().min(eject_speed)
+        } else {
+            eject_speed
+        };
+
+        // Create ejected mass
+        let eject_id = self.world.next_id();
+        let mut eject = crate::entity::EjectedMass::new(eject_id, spawn_pos, eject_size, tick_count);
+        eject.set_color(self.eject_color(ejector_id, cell_color));
+        eject.ejector = Some(ejector_id);
+        eject.data_mut().set_boost(distance, angle);
+
+        // Add to world
+        let new_id = self.world.add_eject(eject);
+        self.world.add_moving(new_id);
+        true
     }
 
     /// Handle chat message.
@@ -815,9 +1401,12 @@ impl GameState {
 
         // Check for commands
         if message.starts_with('/') {
+            let operator = client.is_operator;
+            self.log_chat(client_id, ChatLogKind::Command { operator }, &message);
             self.handle_command(client_id, &message)?;
             return Ok(());
         }
+        self.log_chat(client_id, ChatLogKind::Chat, &message);
 
         info!("[Chat] {}: {}", name, message);
 
@@ -832,6 +1421,22 @@ impl GameState {
         Ok(())
     }
 
+    /// Count connected players by state, as reported by the stats packet
+    /// and the tracker heartbeat.
+    pub fn player_counts(&self) -> PlayerCounts {
+        let mut counts = PlayerCounts { bots: self.bots.bots.len() as u32, ..PlayerCounts::default() };
+        for c in self.clients.values() {
+            if c.is_spectating {
+                counts.spectating += 1;
+            } else if c.cells.is_empty() {
+                counts.dead += 1;
+            } else {
+                counts.alive += 1;
+            }
+        }
+        counts
+    }
+
     /// Handle a StatsRequest packet — rate-limited to once per 30 ticks (matches JS).
     fn handle_stats_request(&mut self, client_id: u32) {
         let client = match self.clients.get_mut(&client_id) {
@@ -845,38 +1450,36 @@ impl GameState {
         }
         client.last_stat_tick = self.tick_count;
 
-        // Count player states
-        let mut players_alive = 0u32;
-        let mut players_dead = 0u32;
-        let mut players_spect = 0u32;
-        for c in self.clients.values() {
-            if c.is_spectating {
-                players_spect += 1;
-            } else if c.cells.is_empty() {
-                players_dead += 1;
-            } else {
-                players_alive += 1;
-            }
-        }
-        let players_total = players_alive + players_dead + players_spect;
-        let bots_total = self.bots.bots.len() as u32;
+        let counts = self.player_counts();
 
         let uptime_secs = self.start_time.elapsed().as_secs();
         let update_str = format!("{:.2}", self.update_time_avg);
 
-        // Build JSON matching JS ServerStat output
+        // All-time record holder, or null
+        let highscore = match self.highscores.leader() {
+            Some(e) => format!(
+                r#"{{"name":{},"mass":{}}}"#,
+                serde_json::to_string(&e.name).unwrap_or_default(),
+                e.mass
+            ),
+            None => "null".to_string(),
+        };
+
+        // Build JSON matching JS ServerStat output, plus the highscore record and overload flag
         let json = format!(
-            r#"{{"name":"{}","mode":"{}","uptime":{},"update":"{}","playersTotal":{},"playersAlive":{},"playersDead":{},"playersSpect":{},"botsTotal":{},"playersLimit":{}}}"#,
+            r#"{{"name":"{}","mode":"{}","uptime":{},"update":"{}","playersTotal":{},"playersAlive":{},"playersDead":{},"playersSpect":{},"botsTotal":{},"playersLimit":{},"highscore":{},"overloaded":{}}}"#,
             self.config.server.name,
             self.gamemode.name(),
             uptime_secs,
             update_str,
-            players_total,
-            players_alive,
-            players_dead,
-            players_spect,
-            bots_total,
+            counts.total(),
+            counts.alive,
+            counts.dead,
+            counts.spectating,
+            counts.bots,
             self.config.server.max_connections,
+            highscore,
+            self.overloaded,
         );
 
         let _ = self.targeted_tx.send(TargetedMessage {
@@ -893,13 +1496,28 @@ impl GameState {
 
         let is_op = self.clients.get(&client_id).map_or(false, |c| c.is_operator);
 
+        // The game mode gets first pick; it is swapped out so it can borrow the state
+        let mut gamemode = std::mem::replace(&mut self.gamemode, Box::new(crate::gamemodes::ffa::Ffa::new()));
+        let handled = gamemode.handle_command(self, client_id, &cmd, args);
+        self.gamemode = gamemode;
+        if handled {
+            return Ok(());
+        }
+
         match cmd.as_str() {
             // --- Public commands (no OP required) ---
             "help" => {
                 if is_op {
-                    self.send_server_message(client_id, "Operator commands: /operator, /list, /addbot, /kick, /kill, /killall, /mass, /speed, /freeze, /teleport, /gamemode, /chat, /name, /xray, /status");
+                    self.send_server_message(client_id, "Operator commands: /operator, /list, /addbot, /kick, /kill, /killall, /mass, /merge, /speed, /freeze, /teleport, /line, /spawn, /clear, /food, /virus, /gamemode, /chat, /name, /rename, /color, /minion, /xray, /status, /dump, /heatmap, /pause, /resume, /top");
                 } else {
-                    self.send_server_message(client_id, "Available commands: /help, /name, /operator <password>");
+                    self.send_server_message(client_id, "Available commands: /help, /name, /top, /operator <password>");
+                }
+                let mode_commands: Vec<String> = self.gamemode.commands().iter()
+                    .filter(|c| is_op || !c.operator_only)
+                    .map(|c| format!("/{}", c.name))
+                    .collect();
+                if !mode_commands.is_empty() {
+                    self.send_server_message(client_id, &format!("{} commands: {}", self.gamemode.name(), mode_commands.join(", ")));
                 }
             }
             "name" => {
@@ -916,6 +1534,9 @@ impl GameState {
             "operator" | "op" => {
                 self.handle_cmd_operator(client_id, args);
             }
+            "top" => {
+                self.handle_cmd_top(client_id);
+            }
             // --- Operator commands ---
             "list" => {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
@@ -931,7 +1552,7 @@ impl GameState {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 let count: usize = args.parse().unwrap_or(1);
                 for _ in 0..count.min(10) {
-                    self.bots.add_bot();
+                    self.bots.add_bot(&mut self.world.rng);
                 }
                 self.send_server_message(client_id, &format!("Added {} bot(s)", count.min(10)));
             }
@@ -941,6 +1562,10 @@ impl GameState {
                 if let Ok(target_id) = args.trim().parse::<u32>() {
                     if self.clients.contains_key(&target_id) {
                         self.remove_client(target_id);
+                        let _ = self.targeted_tx.send(TargetedMessage {
+                            client_id: target_id,
+                            message: TargetedMessageType::Disconnect { code: CloseCode::Kicked, reason: "kicked".to_string() },
+                        });
                         self.send_server_message(client_id, &format!("Kicked client {}", target_id));
                     } else {
                         self.send_server_message(client_id, "Client not found.");
@@ -958,17 +1583,14 @@ impl GameState {
                 // Kill all players except self
                 let ids: Vec<u32> = self.clients.keys().filter(|&&id| id != client_id).copied().collect();
                 for target_id in ids {
-                    let cell_ids: Vec<u32> = self.clients.get(&target_id)
-                        .map(|c| c.cells.clone()).unwrap_or_default();
-                    for cell_id in cell_ids {
-                        self.world.remove_cell(cell_id);
-                    }
-                    if let Some(c) = self.clients.get_mut(&target_id) {
-                        c.cells.clear();
-                    }
+                    self.kill_client(target_id);
                 }
                 self.send_server_message(client_id, "All other players killed.");
             }
+            "merge" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_merge(client_id, args);
+            }
             "mass" | "m" => {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 self.handle_cmd_mass(client_id, args);
@@ -993,10 +1615,26 @@ impl GameState {
                     self.send_server_message(client_id, "Frozen.");
                 }
             }
+            "spawn" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_spawn(client_id, args);
+            }
+            "clear" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_clear(client_id, args);
+            }
+            "food" | "foodamount" | "virus" | "virusamount" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_amount(client_id, cmd.starts_with("food"), args);
+            }
             "teleport" | "tp" => {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 self.handle_cmd_teleport(client_id, args);
             }
+            "line" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_line(client_id, args);
+            }
             "gamemode" => {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 if let Ok(mode_id) = args.trim().parse::<u32>() {
@@ -1019,6 +1657,14 @@ impl GameState {
                     });
                 }
             }
+            "rename" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_rename(client_id, args);
+            }
+            "color" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_color(client_id, args);
+            }
             "minion" => {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 self.handle_cmd_minion(client_id, args);
@@ -1027,16 +1673,42 @@ impl GameState {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 self.handle_cmd_xray(client_id);
             }
+            "dump" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_dump(client_id);
+            }
+            "heatmap" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_heatmap(client_id);
+            }
+            "pause" | "resume" => {
+                if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
+                self.handle_cmd_pause(client_id, cmd == "pause");
+            }
             "status" => {
                 if !is_op { self.send_server_message(client_id, "Operator only."); return Ok(()); }
                 let uptime = self.start_time.elapsed().as_secs();
                 let players = self.clients.len();
                 let bots = self.bots.bots.len();
+                let alive_bots = self.bots.bots.iter().filter(|b| !b.cells.is_empty()).count();
                 let cells = self.world.cell_counts();
                 self.send_server_message(client_id, &format!(
-                    "Uptime: {}s | Players: {} | Bots: {} | Food: {} | Viruses: {} | Speed: {}",
-                    uptime, players, bots, cells.food, cells.viruses, self.config.player.speed
+                    "Uptime: {}s | Players: {} | Bots: {} ({} alive, {} waiting) | Food: {} | Viruses: {} | Speed: {} | Bonus food: {:.1}% (x{}) | Dropped action packets: {}",
+                    uptime, players, bots, alive_bots, bots - alive_bots, cells.food, cells.viruses, self.config.player.speed,
+                    self.config.food.bonus_chance * 100.0, self.config.food.bonus_mass_multiplier,
+                    self.dropped_action_packets
                 ));
+                let mut droppers: Vec<(u32, u64)> = self.clients.values()
+                    .filter(|c| c.packet_limiter.dropped > 0)
+                    .map(|c| (c.id, c.packet_limiter.dropped))
+                    .collect();
+                if !droppers.is_empty() {
+                    droppers.sort_by_key(|&(_, dropped)| std::cmp::Reverse(dropped));
+                    let list: Vec<String> = droppers.iter().take(5)
+                        .map(|(id, dropped)| format!("{} ({})", id, dropped))
+                        .collect();
+                    self.send_server_message(client_id, &format!("Most dropped: {}", list.join(", ")));
+                }
             }
             _ => {
                 self.send_server_message(client_id, &format!("Unknown command: /{}. Type /help for help.", cmd));
@@ -1046,14 +1718,277 @@ impl GameState {
         Ok(())
     }
 
-    /// Handle /operator command.
-    fn handle_cmd_operator(&mut self, client_id: u32, args: &str) {
-        let password = &self.config.server.operator_password;
-        if password.is_empty() {
-            self.send_server_message(client_id, "Operator mode is not configured.");
+    /// Handle /pause and /resume: stop or restart the simulation.
+    fn handle_cmd_pause(&mut self, client_id: u32, pause: bool) {
+        if self.paused == pause {
+            let state = if pause { "already paused" } else { "not paused" };
+            self.send_server_message(client_id, &format!("The game is {}.", state));
             return;
         }
-
+        self.paused = pause;
+        self.paused_ticks = 0;
+        let name = self.clients.get(&client_id).map(|c| c.name.clone()).unwrap_or_default();
+        if pause {
+            self.announce("The game is paused.");
+            info!("{} paused the game at tick {}.", name, self.tick_count);
+        } else {
+            self.announce("The game is resumed.");
+            info!("{} resumed the game at tick {}.", name, self.tick_count);
+        }
+    }
+
+    /// Handle /top: list the best all-time scores.
+    fn handle_cmd_top(&mut self, client_id: u32) {
+        let top = self.highscores.top(TOP_COMMAND_ENTRIES);
+        if top.is_empty() {
+            self.send_server_message(client_id, "No highscores yet.");
+            return;
+        }
+        let lines: Vec<String> = top.iter().enumerate()
+            .map(|(i, e)| format!("{}. {} - {} ({})", i + 1, e.name, e.mass, e.gamemode))
+            .collect();
+        self.send_server_message(client_id, "All-time top scores:");
+        for line in lines {
+            self.send_server_message(client_id, &line);
+        }
+    }
+
+    /// Track each living player's peak mass and submit it once their life
+    /// ends. Bots' peaks are kept too, for kill announcements.
+    fn update_peak_masses(&mut self) {
+        let mut ended = Vec::new();
+        for client in self.clients.values_mut() {
+            if client.cells.is_empty() {
+                if client.peak_mass > 0.0 {
+                    ended.push((client.name.clone(), std::mem::take(&mut client.peak_mass)));
+                }
+                continue;
+            }
+            let mass: f32 = client.cells.iter()
+                .filter_map(|&cell_id| self.world.get_cell(cell_id))
+                .map(|cell| cell.data().mass)
+                .sum();
+            client.peak_mass = client.peak_mass.max(mass);
+        }
+        for (name, peak_mass) in ended {
+            self.submit_highscore(&name, peak_mass);
+        }
+        let world = &self.world;
+        for bot in &mut self.bots.bots {
+            let mass: f32 = bot.cells.iter()
+                .filter_map(|&cell_id| world.get_cell(cell_id))
+                .map(|cell| cell.data().mass)
+                .sum();
+            bot.peak_mass = if bot.cells.is_empty() { 0.0 } else { bot.peak_mass.max(mass) };
+        }
+    }
+
+    /// Largest mass a player or bot reached this life, as last sampled.
+    fn life_peak_mass(&self, id: u32) -> f32 {
+        match self.clients.get(&id) {
+            Some(client) => client.peak_mass,
+            None => self.bots.get_bot(id).map_or(0.0, |bot| bot.peak_mass),
+        }
+    }
+
+    /// Offer a finished life's peak mass to the highscore board.
+    fn submit_highscore(&mut self, name: &str, peak_mass: f32) {
+        let entry = HighscoreEntry {
+            name: if name.is_empty() { "An unnamed cell".to_string() } else { name.to_string() },
+            mass: peak_mass as u32,
+            gamemode: self.gamemode.name().to_string(),
+            timestamp: crate::highscores::unix_now(),
+        };
+        if self.highscores.submit(entry) {
+            info!("New highscore: {} with {} mass", name, peak_mass as u32);
+        }
+    }
+
+    /// Submit every current life and write the board to disk. Called at shutdown.
+    pub fn save_highscores(&mut self) {
+        self.update_peak_masses();
+        let lives: Vec<(String, f32)> = self.clients.values_mut()
+            .chain(self.parked_clients.iter_mut().map(|p| &mut p.client))
+            .filter(|c| c.peak_mass > 0.0)
+            .map(|c| (c.name.clone(), std::mem::take(&mut c.peak_mass)))
+            .collect();
+        for (name, peak_mass) in lives {
+            self.submit_highscore(&name, peak_mass);
+        }
+
+        if self.config.stats.highscores_file.is_empty() {
+            return;
+        }
+        if let Some(json) = self.highscores.take_dirty_json() {
+            let path = Path::new(&self.config.stats.highscores_file);
+            match crate::highscores::save(path, &json) {
+                Ok(()) => info!("Saved highscores to {}", path.display()),
+                Err(e) => warn!("Failed to save highscores to {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Capture every cell and the client and bot rosters.
+    pub fn dump_world(&self) -> WorldDump {
+        let mut cells: Vec<CellDump> = self.world.iter_cells()
+            .map(|(&id, entry)| CellDump::capture(entry, self.world.is_moving(id)))
+            .collect();
+        cells.sort_by_key(|c| c.id);
+
+        let mut clients: Vec<PlayerDump> = self.clients.values()
+            .chain(self.parked_clients.iter().map(|p| &p.client))
+            .map(|c| PlayerDump {
+                id: c.id,
+                name: c.name.clone(),
+                skin: c.skin.clone(),
+                color: dump::color_to_rgb(c.color),
+                team: c.team,
+                cells: c.cells.clone(),
+                minions: c.minions.clone(),
+            })
+            .collect();
+        clients.sort_by_key(|p| p.id);
+
+        let mut bots: Vec<PlayerDump> = self.bots.bots.iter()
+            .map(|b| PlayerDump {
+                id: b.id,
+                name: b.name.clone(),
+                skin: b.skin.clone(),
+                color: dump::color_to_rgb(b.color),
+                team: b.team,
+                cells: b.cells.clone(),
+                minions: Vec::new(),
+            })
+            .collect();
+        bots.sort_by_key(|p| p.id);
+
+        WorldDump {
+            version: dump::DUMP_VERSION,
+            created: crate::highscores::unix_now(),
+            tick: self.tick_count,
+            gamemode: self.gamemode.name().to_string(),
+            border_width: self.world.border.width,
+            border_height: self.world.border.height,
+            next_node_id: self.world.next_node_id,
+            cells,
+            clients,
+            bots,
+        }
+    }
+
+    /// Replace the world with a dump. Only meant for a fresh game state: the
+    /// dumped clients' cells are handed to bots with the same id, since
+    /// there is no connection to give them back to.
+    pub fn load_dump(&mut self, dump: &WorldDump) {
+        if dump.gamemode != self.gamemode.name() {
+            warn!("Dump was taken in {}, loading it into {}", dump.gamemode, self.gamemode.name());
+        }
+        let mut world = World::new(dump.border_width, dump.border_height);
+        world.border.wrap = self.world.border.wrap;
+        world.food_palette = std::mem::take(&mut self.world.food_palette);
+        world.food_size_distribution = self.world.food_size_distribution;
+        world.food_bonus_chance = self.world.food_bonus_chance;
+        world.food_avoid_players = self.world.food_avoid_players;
+        world.food_sector_bias = self.world.food_sector_bias;
+        world.map = Arc::clone(&self.world.map);
+        world.rng = self.world.rng.clone();
+        world.next_node_id = dump.next_node_id;
+        for cell in &dump.cells {
+            match cell.restore() {
+                CellEntry::Player(c) => world.add_player_cell(c),
+                CellEntry::Food(c) => world.add_food(c),
+                CellEntry::Virus(c) => world.add_virus(c),
+                CellEntry::Eject(c) => world.add_eject(c),
+                CellEntry::Mother(c) => world.add_mother_cell(c),
+            };
+            if cell.moving {
+                world.add_moving(cell.id);
+            }
+        }
+        self.world = world;
+        self.border = Border::new(dump.border_width as f64, dump.border_height as f64);
+        self.tick_count = dump.tick;
+
+        self.bots = BotManager::new(bot_skins(&self.config.bots.skins));
+        for player in dump.bots.iter().chain(&dump.clients) {
+            let mut bot = Bot::new(player.id, &mut self.world.rng);
+            bot.name = player.name.clone();
+            bot.skin = player.skin.clone();
+            bot.color = dump::rgb_to_color(player.color);
+            bot.team = player.team;
+            bot.cells = player.cells.iter()
+                .copied()
+                .filter(|&id| self.world.get_cell(id).is_some_and(|c| c.data().owner_id == Some(player.id)))
+                .collect();
+            bot.needs_respawn = bot.cells.is_empty();
+            self.bots.restore_bot(bot);
+        }
+        if let Some(max_client) = dump.clients.iter().map(|c| c.id).max() {
+            self.next_client_id = self.next_client_id.max(max_client + 1);
+        }
+        info!(
+            "Loaded world dump from tick {}: {} cells, {} bots, {} former clients",
+            dump.tick, dump.cells.len(), dump.bots.len(), dump.clients.len()
+        );
+    }
+
+    /// Handle /dump: serialize the world now and have the game loop write it.
+    fn handle_cmd_dump(&mut self, client_id: u32) {
+        let dump = self.dump_world();
+        let path = dump.path_in(Path::new(&self.config.replay.dump_directory));
+        match serde_json::to_string(&dump) {
+            Ok(json) => {
+                self.send_server_message(client_id, &format!(
+                    "Dumping {} cells at tick {} to {}", dump.cells.len(), dump.tick, path.display()
+                ));
+                self.pending_dump = Some((path, json));
+            }
+            Err(e) => self.send_server_message(client_id, &format!("Dump failed: {}", e)),
+        }
+    }
+
+    /// Handle /heatmap: serialize the sampled grid and have the game loop write it.
+    fn handle_cmd_heatmap(&mut self, client_id: u32) {
+        let Some(heatmap) = &self.heatmap else {
+            self.send_server_message(client_id, "Heatmap sampling is off (stats.heatmap_enabled).");
+            return;
+        };
+        let file = heatmap.to_file(&self.world.border, self.tick_count);
+        let path = PathBuf::from(&self.config.stats.heatmap_file);
+        match serde_json::to_string(&file) {
+            Ok(json) => {
+                self.send_server_message(client_id, &format!(
+                    "Writing heatmap of {} samples to {}", file.samples, path.display()
+                ));
+                self.pending_heatmap = Some((path, json));
+            }
+            Err(e) => self.send_server_message(client_id, &format!("Heatmap failed: {}", e)),
+        }
+    }
+
+    /// Write the heatmap, if sampled, at shutdown. Blocking.
+    pub fn save_heatmap(&self) {
+        let Some(heatmap) = self.heatmap.as_ref().filter(|h| h.samples() > 0) else {
+            return;
+        };
+        let path = Path::new(&self.config.stats.heatmap_file);
+        let result = serde_json::to_string(&heatmap.to_file(&self.world.border, self.tick_count))
+            .map_err(std::io::Error::other)
+            .and_then(|json| crate::dump::save(path, &json));
+        match result {
+            Ok(()) => info!("Saved heatmap to {}", path.display()),
+            Err(e) => warn!("Failed to save heatmap to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Handle /operator command.
+    fn handle_cmd_operator(&mut self, client_id: u32, args: &str) {
+        let password = &self.config.server.operator_password;
+        if password.is_empty() {
+            self.send_server_message(client_id, "Operator mode is not configured.");
+            return;
+        }
+
         let client = match self.clients.get_mut(&client_id) {
             Some(c) => c,
             None => return,
@@ -1071,6 +2006,61 @@ impl GameState {
         }
     }
 
+    /// Handle /rename: change a player's name, sanitized like a join.
+    fn handle_cmd_rename(&mut self, client_id: u32, args: &str) {
+        let (target_id, name) = match args.trim().split_once(' ') {
+            Some((id, name)) => match id.parse::<u32>() {
+                Ok(id) => (id, self.clamp_nick(name.trim())),
+                Err(_) => {
+                    self.send_server_message(client_id, "Usage: /rename <client_id> <new name>");
+                    return;
+                }
+            },
+            None => {
+                self.send_server_message(client_id, "Usage: /rename <client_id> <new name>");
+                return;
+            }
+        };
+        let Some(target) = self.clients.get_mut(&target_id) else {
+            self.send_server_message(client_id, "Client not found.");
+            return;
+        };
+        let old_name = std::mem::replace(&mut target.name, name.clone());
+        if !target.cells.is_empty() {
+            self.identity_changed.insert(target_id);
+        }
+        if let Some(feed) = &self.replay {
+            let _ = feed.tx.try_send(ReplayMessage::Player { id: target_id, name: name.clone() });
+        }
+        info!("Operator {} renamed client {} from '{}' to '{}'", client_id, target_id, old_name, name);
+        self.send_server_message(client_id, &format!("Renamed client {} to '{}'", target_id, name));
+        if target_id != client_id {
+            self.send_server_message(target_id, &format!("An operator renamed you to '{}'.", name));
+        }
+    }
+
+    /// Handle /color: recolor a player and all of their cells.
+    fn handle_cmd_color(&mut self, client_id: u32, args: &str) {
+        let Some((target_id, color)) = parse_color_args(args) else {
+            self.send_server_message(client_id, "Usage: /color <client_id> <r> <g> <b> or /color <client_id> <#rrggbb>");
+            return;
+        };
+        let Some(target) = self.clients.get_mut(&target_id) else {
+            self.send_server_message(client_id, "Client not found.");
+            return;
+        };
+        target.color = color;
+        for &cell_id in &target.cells {
+            if let Some(cell) = self.world.get_cell_mut(cell_id) {
+                cell.data_mut().color = color;
+            }
+        }
+        self.send_server_message(client_id, &format!("Recolored client {} to #{:02x}{:02x}{:02x}", target_id, color.r, color.g, color.b));
+        if target_id != client_id {
+            self.send_server_message(target_id, "An operator changed your color.");
+        }
+    }
+
     /// Handle /kill command.
     fn handle_cmd_kill(&mut self, client_id: u32, args: &str) {
         let target_id: u32 = match args.trim().parse() {
@@ -1081,22 +2071,71 @@ impl GameState {
             }
         };
 
-        let cell_ids: Vec<u32> = self.clients.get(&target_id)
-            .map(|c| c.cells.clone())
-            .unwrap_or_default();
-
-        if cell_ids.is_empty() {
+        if !self.kill_client(target_id) {
             self.send_server_message(client_id, "Target has no cells.");
             return;
         }
+        self.send_server_message(client_id, &format!("Killed client {}", target_id));
+    }
 
+    /// Remove every cell of a client, telling them they were killed. Returns
+    /// false if they had none.
+    pub(crate) fn kill_client(&mut self, client_id: u32) -> bool {
+        let cell_ids = match self.clients.get_mut(&client_id) {
+            Some(c) if !c.cells.is_empty() => std::mem::take(&mut c.cells),
+            _ => return false,
+        };
         for cell_id in cell_ids {
             self.world.remove_cell(cell_id);
         }
-        if let Some(c) = self.clients.get_mut(&target_id) {
-            c.cells.clear();
+        self.send_death_info(client_id, DeathCause::Killed, String::new(), 0.0, 0);
+        true
+    }
+
+    /// How many players and bots are on each team, indexed by team.
+    pub(crate) fn team_sizes(&self) -> Vec<usize> {
+        let clients = self.clients.values().filter_map(|c| c.team);
+        let bots = self.bots.bots.iter().filter_map(|b| b.team);
+        let mut sizes = Vec::new();
+        for team in clients.chain(bots).map(usize::from) {
+            if sizes.len() <= team {
+                sizes.resize(team + 1, 0);
+            }
+            sizes[team] += 1;
         }
-        self.send_server_message(client_id, &format!("Killed client {}", target_id));
+        sizes
+    }
+
+    /// Handle /merge command — let self (or target) recombine immediately.
+    fn handle_cmd_merge(&mut self, client_id: u32, args: &str) {
+        let target_id = if args.trim().is_empty() {
+            client_id
+        } else {
+            match args.trim().parse::<u32>() {
+                Ok(id) => id,
+                Err(_) => {
+                    self.send_server_message(client_id, "Usage: /merge or /merge <client_id>");
+                    return;
+                }
+            }
+        };
+
+        let cell_ids: Vec<u32> = self.clients.get(&target_id)
+            .map(|c| c.cells.clone())
+            .unwrap_or_default();
+
+        if cell_ids.len() < 2 {
+            self.send_server_message(client_id, "Target has nothing to merge.");
+            return;
+        }
+
+        for cell_id in &cell_ids {
+            if let Some(CellEntry::Player(cell)) = self.world.get_cell_mut(*cell_id) {
+                cell.merge_tick = 0;
+            }
+        }
+        self.send_merge_timers(target_id, &cell_ids);
+        self.send_server_message(client_id, &format!("Merging {} cells", cell_ids.len()));
     }
 
     /// Handle /mass command — set all cells of self (or target) to a given size.
@@ -1148,6 +2187,137 @@ impl GameState {
             self.world.update_cell_position(*cell_id);
         }
         self.send_server_message(client_id, &format!("Set {} cells to mass {}", cell_ids.len(), mass));
+        if new_size > self.config.player.max_size as f32 {
+            self.enforce_max_size();
+            self.send_server_message(client_id, &format!(
+                "Above player.max_size ({}), so the cells were {}",
+                self.config.player.max_size,
+                match self.config.player.max_size_behavior {
+                    MaxSizeBehavior::Split => "split up (capped at player.max_cells)",
+                    MaxSizeBehavior::Cap => "capped",
+                }
+            ));
+        }
+    }
+
+    /// Handle /spawn: place a virus, food or a mother cell, by default at the
+    /// operator's position.
+    fn handle_cmd_spawn(&mut self, client_id: u32, args: &str) {
+        let request = match parse_spawn_args(args) {
+            Ok(request) => request,
+            Err(usage) => {
+                self.send_server_message(client_id, usage);
+                return;
+            }
+        };
+        let here = self.clients.get(&client_id)
+            .map_or(glam::Vec2::ZERO, |c| glam::Vec2::new(c.center_x, c.center_y));
+        let border = self.world.border;
+        let at = |pos: Option<glam::Vec2>| border.clamp(pos.unwrap_or(here));
+        let tick = self.tick_count;
+
+        let mut ids = Vec::new();
+        match request {
+            SpawnRequest::Virus { pos, size } => {
+                if self.world.virus_cells.len() >= self.config.virus.max_amount {
+                    self.send_server_message(client_id, &format!("Virus limit reached ({}); spawning anyway.", self.config.virus.max_amount));
+                }
+                let size = size.unwrap_or(self.config.virus.min_size as f32);
+                let id = self.world.next_id();
+                ids.push(self.world.add_virus(crate::entity::Virus::new(id, at(pos), size, tick)));
+            }
+            SpawnRequest::Food { count, pos, radius } => {
+                let room = self.config.food.max_amount.saturating_sub(self.world.food_cells.len());
+                if count > room {
+                    self.send_server_message(client_id, &format!("Food limit is {}; spawning {} of {}.", self.config.food.max_amount, room, count));
+                }
+                let center = at(pos);
+                let radius = radius.unwrap_or(SPAWN_FOOD_DEFAULT_RADIUS);
+                let size = self.config.food.min_size as f32;
+                for _ in 0..count.min(room) {
+                    let angle = self.world.rng.random_range(0.0..std::f32::consts::TAU);
+                    let distance = radius * self.world.rng.random::<f32>().sqrt();
+                    let pos = border.clamp(center + glam::Vec2::from_angle(angle) * distance);
+                    let food = self.world.new_food(pos, size, tick);
+                    ids.push(self.world.add_food(food));
+                }
+            }
+            SpawnRequest::MotherCell { pos } => {
+                let id = self.world.next_id();
+                ids.push(self.world.add_mother_cell(crate::entity::MotherCell::new(id, at(pos), 0.0, tick)));
+            }
+        }
+
+        let listed: Vec<String> = ids.iter().take(10).map(u32::to_string).collect();
+        let more = if ids.len() > listed.len() { format!(" and {} more", ids.len() - listed.len()) } else { String::new() };
+        self.send_server_message(client_id, &format!("Spawned {} node(s): {}{}", ids.len(), listed.join(", "), more));
+    }
+
+    /// Handle /clear: remove every food pellet, virus or ejected mass.
+    fn handle_cmd_clear(&mut self, client_id: u32, args: &str) {
+        let ids = match args.trim() {
+            "food" => self.world.food_cells.clone(),
+            "viruses" | "virus" => self.world.virus_cells.clone(),
+            "ejected" | "eject" => self.world.eject_cells.clone(),
+            _ => {
+                self.send_server_message(client_id, "Usage: /clear food|viruses|ejected");
+                return;
+            }
+        };
+        for &id in &ids {
+            self.world.remove_cell(id);
+        }
+        let count = ids.len();
+        self.removed_cells.extend(ids);
+        self.send_server_message(client_id, &format!("Removed {} cell(s).", count));
+    }
+
+    /// Handle /food and /virus: change how many pellets or viruses the
+    /// world keeps, removing the oldest ones above the new maximum.
+    fn handle_cmd_amount(&mut self, client_id: u32, food: bool, args: &str) {
+        let (section, usage) = if food { ("food", "/food <min> <max>") } else { ("virus", "/virus <min> <max>") };
+        let (old_min, old_max) = if food {
+            (self.config.food.min_amount, self.config.food.max_amount)
+        } else {
+            (self.config.virus.min_amount, self.config.virus.max_amount)
+        };
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let (min, max) = match parts.as_slice() {
+            [min, max] => match (min.parse::<usize>(), max.parse::<usize>()) {
+                (Ok(min), Ok(max)) => (min, max),
+                _ => {
+                    self.send_server_message(client_id, &format!("Usage: {}", usage));
+                    return;
+                }
+            },
+            _ => {
+                self.send_server_message(client_id, &format!("Current {} amount: {}..{}. Usage: {}", section, old_min, old_max, usage));
+                return;
+            }
+        };
+
+        let mut config = self.config.clone();
+        if food {
+            (config.food.min_amount, config.food.max_amount) = (min, max);
+        } else {
+            (config.virus.min_amount, config.virus.max_amount) = (min, max);
+        }
+        if let Err(errors) = config.validate()
+            && let Some(error) = errors.iter().find(|e| e.field.starts_with(section))
+        {
+            self.send_server_message(client_id, &format!("Invalid {} amount: {}", section, error));
+            return;
+        }
+        self.config = config;
+
+        let ids = if food { self.world.food_cells.clone() } else { self.world.virus_cells.clone() };
+        let removed = self.world.remove_oldest(&ids, max);
+        let count = removed.len();
+        self.removed_cells.extend(removed);
+        self.send_server_message(client_id, &format!(
+            "{} amount changed from {}..{} to {}..{}; removed {} cell(s).",
+            if food { "Food" } else { "Virus" }, old_min, old_max, min, max, count
+        ));
     }
 
     /// Handle /teleport command — move self (or target) to given coordinates.
@@ -1196,12 +2366,61 @@ impl GameState {
         self.send_server_message(client_id, &format!("Teleported client {} to ({}, {})", target_id, x, y));
     }
 
-    /// Handle /minion command — add or remove minions for the operator.
+    /// Handle /line: draw a line from a client's cells to a point on their
+    /// screen for about a second, for pointing things out while debugging.
+    fn handle_cmd_line(&mut self, client_id: u32, args: &str) {
+        let usage = "Usage: /line <x> <y> or /line <id> <x> <y>";
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let parsed = match parts.as_slice() {
+            [x, y] => x.parse::<f32>().ok().zip(y.parse::<f32>().ok()).map(|(x, y)| (client_id, x, y)),
+            [id, x, y] => match (id.parse::<u32>(), x.parse::<f32>(), y.parse::<f32>()) {
+                (Ok(id), Ok(x), Ok(y)) => Some((id, x, y)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some((target_id, x, y)) = parsed else {
+            self.send_server_message(client_id, usage);
+            return;
+        };
+        let Some(target) = self.clients.get(&target_id) else {
+            self.send_server_message(client_id, &format!("Client {} not found.", target_id));
+            return;
+        };
+        let _ = self.targeted_tx.send(TargetedMessage {
+            client_id: target_id,
+            message: TargetedMessageType::DrawLine {
+                x: x as i32,
+                y: y as i32,
+                scramble_x: target.scramble_x,
+                scramble_y: target.scramble_y,
+            },
+        });
+        self.send_server_message(client_id, &format!("Drew a line to ({}, {}) for client {}", x, y, target_id));
+    }
+
+    /// Handle /minion command — add, remove, rename or feed minions for the operator.
     fn handle_cmd_minion(&mut self, client_id: u32, args: &str) {
         let parts: Vec<&str> = args.split_whitespace().collect();
         let action = parts.first().copied().unwrap_or("");
 
-        if action == "remove" || (action.is_empty() && self.clients.get(&client_id).map_or(false, |c| c.minion_control)) {
+        if action == "name" {
+            let name = self.clamp_nick(&parts[1..].join(" "));
+            if name.is_empty() {
+                self.send_server_message(client_id, "Usage: /minion name <text>");
+                return;
+            }
+            let renamed = self.rename_minions(client_id, &name);
+            self.send_server_message(client_id, &format!("Renamed {} minion(s) to '{}'.", renamed, name));
+        } else if action == "feed" {
+            let Some(client) = self.clients.get_mut(&client_id).filter(|c| !c.minions.is_empty()) else {
+                self.send_server_message(client_id, "You have no minions.");
+                return;
+            };
+            client.minion_feed = !client.minion_feed;
+            let state = if client.minion_feed { "on" } else { "off" };
+            self.send_server_message(client_id, &format!("Minion feeding {}.", state));
+        } else if action == "remove" || (action.is_empty() && self.clients.get(&client_id).is_some_and(|c| c.minion_control)) {
             // Remove all minions
             let minion_ids: Vec<u32> = self.clients.get(&client_id)
                 .map(|c| c.minions.clone())
@@ -1223,76 +2442,65 @@ impl GameState {
                 client.minion_follow = false;
                 client.minion_frozen = false;
                 client.minion_collect = false;
+                client.minion_feed = false;
             }
             self.send_server_message(client_id, "Successfully removed your minions.");
         } else {
             // Add minions
             let count: usize = action.parse().unwrap_or(1);
-            let count = count.min(10); // Cap at 10
+            let count = count.min(10); // Cap at 10 per invocation
 
-            let (owner_color, owner_name) = if let Some(client) = self.clients.get(&client_id) {
-                let name = if client.name.is_empty() {
-                    "Player".to_string()
-                } else {
-                    client.name.clone()
-                };
-                (client.color, name)
+            let added = self.add_minions(client_id, count, true);
+            if added == 0 {
+                self.send_server_message(
+                    client_id,
+                    &format!("You already have the maximum of {} minions.", self.config.player.max_minions),
+                );
+            } else if added < count {
+                self.send_server_message(
+                    client_id,
+                    &format!("You gave yourself {} minion(s); the limit is {}.", added, self.config.player.max_minions),
+                );
             } else {
-                (protocol::Color::new(128, 128, 128), "Player".to_string())
-            };
-            
-            let mut added = 0;
-            for _ in 0..count {
-                let minion_id = self.bots.add_bot();
-                
-                // Increment client's minion counter and get number
-                let minion_number = if let Some(client) = self.clients.get_mut(&client_id) {
-                    client.latest_minion_id += 1;
-                    client.latest_minion_id
-                } else {
-                    1
-                };
-                
-                // Set minion bot to use owner's color
-                if let Some(bot) = self.bots.get_bot_mut(minion_id) {
-                    bot.color = owner_color;
-                    bot.name = format!("{} {}", owner_name, minion_number);
-                    bot.needs_respawn = true;
-                }
-                if let Some(client) = self.clients.get_mut(&client_id) {
-                    client.minions.push(minion_id);
-                    client.minion_control = true;
-                }
-                added += 1;
+                self.send_server_message(client_id, &format!("You gave yourself {} minion(s). Use Q/E/R/T/P keys to control them.", added));
             }
-            self.send_server_message(client_id, &format!("You gave yourself {} minion(s). Use Q/E/R/T/P keys to control them.", added));
         }
     }
 
     /// Spawn default minions for a player (called on join if server_minions > 0).
     fn spawn_default_minions(&mut self, client_id: u32, count: usize) {
-        let count = count.min(10); // Cap at 10
+        let same_color = self.config.player.minion_same_color;
+        let added = self.add_minions(client_id, count, same_color);
+        info!("Client {} spawned with {} default minions", client_id, added);
+    }
 
-        // Get owner color and name
-        let (owner_color, owner_name) = if let Some(client) = self.clients.get(&client_id) {
-            let color = if self.config.player.minion_same_color {
-                client.color
-            } else {
-                protocol::Color::new(0, 0, 0) // Will be set per minion below
-            };
-            let name = if client.name.is_empty() {
-                "Player".to_string()
-            } else {
-                client.name.clone()
-            };
-            (color, name)
-        } else {
-            (protocol::Color::new(128, 128, 128), "Player".to_string())
+    /// Name prefix for a client's minions: the `/minion name` override, else
+    /// the player's name.
+    fn minion_base_name(&self, client_id: u32) -> String {
+        match self.clients.get(&client_id) {
+            Some(client) => match &client.minion_name {
+                Some(name) => name.clone(),
+                None if client.name.is_empty() => "Player".to_string(),
+                None => client.name.clone(),
+            },
+            None => "Player".to_string(),
+        }
+    }
+
+    /// Add up to `count` minions for a client without exceeding
+    /// `player.max_minions`. Returns how many were actually added.
+    fn add_minions(&mut self, client_id: u32, count: usize, same_color: bool) -> usize {
+        let Some(client) = self.clients.get(&client_id) else {
+            return 0;
         };
+        let owner_color = client.color;
+        let room = self.config.player.max_minions.saturating_sub(client.minions.len());
+        let count = count.min(room);
+        let base_name = self.minion_base_name(client_id);
 
         for _ in 0..count {
-            let minion_id = self.bots.add_bot();
-            
+            let minion_id = self.bots.add_bot(&mut self.world.rng);
+
             // Increment client's minion counter and get number
             let minion_number = if let Some(client) = self.clients.get_mut(&client_id) {
                 client.latest_minion_id += 1;
@@ -1300,26 +2508,44 @@ impl GameState {
             } else {
                 1
             };
-            
+
             // Configure minion bot
             if let Some(bot) = self.bots.get_bot_mut(minion_id) {
-                if self.config.player.minion_same_color {
-                    bot.color = owner_color;
+                bot.color = if same_color {
+                    owner_color
                 } else {
-                    bot.color = crate::world::World::random_color();
-                }
-                bot.name = format!("{} {}", owner_name, minion_number);
+                    World::random_color(&mut self.world.rng)
+                };
+                bot.name = format!("{} {}", base_name, minion_number);
+                bot.skin = None;
                 bot.needs_respawn = true;
             }
-            
+
             // Add to client's minion list
             if let Some(client) = self.clients.get_mut(&client_id) {
                 client.minions.push(minion_id);
                 client.minion_control = true;
             }
         }
+        count
+    }
+
+    /// Rename all of a client's minions to `name`, renumbering them from 1.
+    /// Returns how many minions were renamed.
+    fn rename_minions(&mut self, client_id: u32, name: &str) -> usize {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return 0;
+        };
+        client.minion_name = Some(name.to_string());
+        client.latest_minion_id = client.minions.len() as u16;
+        let minion_ids = client.minions.clone();
 
-        info!("Client {} spawned with {} default minions", client_id, count);
+        for (i, minion_id) in minion_ids.iter().enumerate() {
+            if let Some(bot) = self.bots.get_bot_mut(*minion_id) {
+                bot.name = format!("{} {}", name, i + 1);
+            }
+        }
+        minion_ids.len()
     }
 
     /// Handle /xray command — toggle XRay mode to see all players.
@@ -1344,8 +2570,23 @@ impl GameState {
         info!("{} {} xray mode.", client_name, status);
     }
 
-    /// Send a server message to a specific client via targeted channel.
-    fn send_server_message(&self, client_id: u32, message: &str) {
+    /// Send a server message to everyone in the room.
+    pub(crate) fn announce(&self, message: &str) {
+        self.announce_with_color(message, protocol::Color::new(255, 0, 0));
+    }
+
+    /// Send a server message to everyone, with `color` instead of the usual red.
+    pub(crate) fn announce_with_color(&self, message: &str, color: protocol::Color) {
+        let _ = self.chat_tx.send(ChatBroadcast {
+            name: "SERVER".to_string(),
+            color,
+            message: message.to_string(),
+            is_server: true,
+        });
+    }
+
+    /// Send a server message to a specific client through its targeted queue.
+    pub(crate) fn send_server_message(&self, client_id: u32, message: &str) {
         let _ = self.targeted_tx.send(TargetedMessage {
             client_id,
             message: TargetedMessageType::ChatMessage {
@@ -1357,17 +2598,94 @@ impl GameState {
         });
     }
 
+    /// Update the exported metrics after a tick.
+    fn record_metrics(&self, total: Duration, phases: [(Phase, Duration); 6]) {
+        self.metrics.record_tick(total, &phases);
+        // Blame a tick over budget on its slowest phase
+        if total > Duration::from_millis(self.config.server.tick_interval_ms)
+            && let Some(&(phase, _)) = phases.iter().max_by_key(|(_, time)| *time)
+        {
+            self.metrics.tick_overrun(phase);
+        }
+        for (entity, count) in [
+            (Entity::Player, self.world.player_cells.len()),
+            (Entity::Food, self.world.food_cells.len()),
+            (Entity::Virus, self.world.virus_cells.len()),
+            (Entity::Ejected, self.world.eject_cells.len()),
+            (Entity::Mother, self.world.mother_cells.len()),
+        ] {
+            self.metrics.set_entity_count(entity, count);
+        }
+        self.metrics.set_players(self.clients.len(), self.bots.bots.len());
+    }
+
+    /// Fold a finished tick's duration into [`Self::update_time_avg`] and
+    /// track overload: once the average has been over `tick_interval_ms` for
+    /// [`OVERLOAD_STREAK_TICKS`] ticks in a row, warn, flag the room as
+    /// overloaded and skip non-essential work for [`OVERLOAD_SHED_TICKS`].
+    /// Each further run of [`OVERLOAD_STREAK_TICKS`] slow ticks extends it.
+    pub fn record_tick_time(&mut self, tick_ms: f64) {
+        // Exponential moving average (weight 0.5, matches typical server stat smoothing)
+        self.update_time_avg = self.update_time_avg * 0.5 + tick_ms * 0.5;
+        let budget = self.config.server.tick_interval_ms as f64;
+        if self.update_time_avg <= budget {
+            if self.overloaded {
+                info!("Tick time back within budget: {:.2}ms average (budget: {:.0}ms)", self.update_time_avg, budget);
+            }
+            self.overload_streak = 0;
+            self.overloaded = false;
+            self.metrics.set_overloaded(false);
+            return;
+        }
+        self.overload_streak += 1;
+        if !self.overload_streak.is_multiple_of(OVERLOAD_STREAK_TICKS) {
+            return;
+        }
+        if self.overload_streak == OVERLOAD_STREAK_TICKS {
+            warn!(
+                "Overloaded: {:.2}ms average tick for {} ticks (budget: {:.0}ms) - {} players, {} cells; \
+                 skipping leaderboard, xray and decay for {} ticks",
+                self.update_time_avg,
+                OVERLOAD_STREAK_TICKS,
+                budget,
+                self.clients.len(),
+                self.world.cells.len(),
+                OVERLOAD_SHED_TICKS
+            );
+            self.overloaded = true;
+            self.metrics.set_overloaded(true);
+        }
+        self.shed_until_tick = self.tick_count + OVERLOAD_SHED_TICKS;
+    }
+
+    /// Whether non-essential work is being skipped to catch up.
+    fn shedding_load(&self) -> bool {
+        self.tick_count < self.shed_until_tick
+    }
+
     /// Run a single game tick and return pending broadcasts.
     pub fn tick(&mut self) -> PendingBroadcasts {
+        if self.paused {
+            return self.paused_tick();
+        }
         let tick_start = std::time::Instant::now();
         
         self.tick_count += 1;
+        self.world.begin_tick(self.tick_count);
         self.eaten_this_tick.clear();
         self.deaths_this_tick.clear();
+        self.death_masses.clear();
+        self.expire_parked_clients();
 
         // Spawn food if needed
         let spawn_start = std::time::Instant::now();
-        self.world.spawn_food(
+        let expired = self.world.expire_ejects(
+            self.tick_count,
+            self.config.eject.lifetime_ticks,
+            self.config.eject.max_amount,
+        );
+        self.removed_cells.extend(expired);
+        self.world.spawn_food(
             self.config.food.min_amount,
             self.config.food.max_amount,
             self.config.food.spawn_amount,
@@ -1375,6 +2693,9 @@ impl GameState {
             self.config.food.max_size as f32,
             self.tick_count,
         );
+        if self.config.food.grow_enabled {
+            self.world.grow_food(self.config.food.max_size as f32, self.tick_count);
+        }
 
         // Spawn viruses if needed
         self.world.spawn_viruses(
@@ -1452,17 +2773,29 @@ impl GameState {
         self.gamemode = gamemode;
         let collision_time = collision_start.elapsed();
 
-        // Cell decay (every 25 ticks)
+        // Cell decay (every 25 ticks, put off while overloaded)
         let decay_start = std::time::Instant::now();
-        if self.tick_count % 25 == 0 {
+        let shedding = self.shedding_load();
+        if !shedding && self.tick_count - self.last_decay_tick >= 25 {
+            self.last_decay_tick = self.tick_count;
+            self.update_antiteam();
             self.update_decay();
+            self.check_afk_clients();
+            self.update_peak_masses();
         }
+        self.record_replay_tick();
         let decay_time = decay_start.elapsed();
 
-        // Prepare leaderboard broadcast (every 25 ticks)
-        let leaderboard_broadcast = if self.tick_count - self.last_lb_tick >= 25 {
+        // Prepare leaderboard broadcast (every 25 ticks, put off while overloaded)
+        let leaderboard_broadcast = if !shedding && self.tick_count - self.last_lb_tick >= 25 {
             self.last_lb_tick = self.tick_count;
-            Some(self.prepare_leaderboard_broadcast())
+            let mut leaderboard = self.prepare_leaderboard_broadcast();
+            let roster_ticks = ROSTER_INTERVAL_MS / self.config.server.tick_interval_ms.max(1);
+            if self.tick_count - self.last_roster_tick >= roster_ticks {
+                self.last_roster_tick = self.tick_count;
+                leaderboard.roster = Some(self.prepare_roster());
+            }
+            Some(leaderboard)
         } else {
             None
         };
@@ -1471,8 +2804,32 @@ impl GameState {
 
         // Prepare world state broadcast
         let broadcast_start = std::time::Instant::now();
-        let (world_broadcast, xray_messages) = self.prepare_world_broadcast();
+        let (world_broadcast, targeted_messages) = self.prepare_world_broadcast();
+        if let Some(heatmap) = &mut self.heatmap
+            && heatmap.due(self.tick_count)
+        {
+            let players = world_broadcast.cells.iter()
+                .filter(|cell| cell.cell_type == CellType::Player as u8)
+                .map(|cell| (cell.x, cell.y));
+            heatmap.sample(&self.world.border, players);
+        }
         let broadcast_time = broadcast_start.elapsed();
+        self.record_metrics(tick_start.elapsed(), [
+            (Phase::Spawn, spawn_time),
+            (Phase::Ai, ai_time),
+            (Phase::Movement, movement_time),
+            (Phase::Collision, collision_time),
+            (Phase::Decay, decay_time),
+            (Phase::Broadcast, broadcast_time),
+        ]);
+
+        let flush_ticks = HIGHSCORE_FLUSH_INTERVAL_MS / self.config.server.tick_interval_ms.max(1);
+        let highscores = if self.tick_count - self.last_highscore_flush_tick >= flush_ticks {
+            self.last_highscore_flush_tick = self.tick_count;
+            self.highscores.take_dirty_json()
+        } else {
+            None
+        };
 
         // Log performance metrics every 400 ticks
         if self.tick_count % 400 == 0 {
@@ -1496,16 +2853,158 @@ impl GameState {
         PendingBroadcasts {
             world_update: Some(world_broadcast),
             leaderboard: leaderboard_broadcast,
-            xray_messages,
+            targeted_messages,
+            highscores,
+            dump: self.pending_dump.take(),
+            heatmap: self.pending_heatmap.take(),
+        }
+    }
+
+    /// A tick while paused: nothing moves, spawns, decays or counts as a tick.
+    /// The unchanged world is re-sent every [`PAUSED_BROADCAST_INTERVAL_MS`]
+    /// so clients keep seeing it, along with anything operators changed.
+    fn paused_tick(&mut self) -> PendingBroadcasts {
+        self.paused_ticks += 1;
+        self.eaten_this_tick.clear();
+        let interval = (PAUSED_BROADCAST_INTERVAL_MS / self.config.server.tick_interval_ms.max(1)).max(1);
+        let (world_update, targeted_messages) = if self.paused_ticks.is_multiple_of(interval) {
+            let (world, targeted) = self.prepare_world_broadcast();
+            (Some(world), targeted)
+        } else {
+            (None, Vec::new())
+        };
+        PendingBroadcasts {
+            world_update,
+            leaderboard: None,
+            targeted_messages,
+            highscores: None,
+            dump: self.pending_dump.take(),
+            heatmap: self.pending_heatmap.take(),
+        }
+    }
+
+    /// Cells owned by a client or bot.
+    fn player_cells(&self, player_id: u32) -> Option<&[u32]> {
+        match self.clients.get(&player_id) {
+            Some(client) => Some(&client.cells),
+            None => self.bots.get_bot(player_id).map(|bot| bot.cells.as_slice()),
+        }
+    }
+
+    /// Make a spectator's view follow another player. Only spectators without
+    /// cells may do this; a target that is no longer alive gets them the
+    /// leader instead.
+    fn handle_spectate_target(&mut self, client_id: u32, target_id: u32) {
+        let Some(client) = self.clients.get(&client_id) else {
+            return;
+        };
+        if target_id == client_id || !client.is_spectating || !client.cells.is_empty() {
+            return;
+        }
+        if self.is_alive(target_id) {
+            self.clients.get_mut(&client_id).unwrap().spectate_target = Some(target_id);
+        } else {
+            // Asked to follow someone who has died since, e.g. the killer
+            // from the death screen
+            self.follow_leader(client_id, target_id);
+        }
+    }
+
+    /// Whether a client or bot has cells on the map.
+    fn is_alive(&self, player_id: u32) -> bool {
+        self.player_cells(player_id).is_some_and(|cells| !cells.is_empty())
+    }
+
+    /// The leaderboard leader other than `except`: the client or bot (not
+    /// minion) with the most mass.
+    fn leader(&self, except: u32) -> Option<u32> {
+        let minions: HashSet<u32> = self.clients.values().flat_map(|c| c.minions.iter().copied()).collect();
+        let bots = self.bots.bots.iter().filter(|bot| !minions.contains(&bot.id));
+        self.clients.iter()
+            .map(|(&id, client)| (id, client.cells.as_slice()))
+            .chain(bots.map(|bot| (bot.id, bot.cells.as_slice())))
+            .filter(|&(id, cells)| id != except && !cells.is_empty())
+            .map(|(id, cells)| {
+                let mass: f32 = cells.iter()
+                    .filter_map(|&cell| self.world.get_cell(cell))
+                    .map(|cell| cell.data().size * cell.data().size)
+                    .sum();
+                (id, mass)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|(id, _)| id)
+    }
+
+    /// Switch a spectator whose target `lost_id` died to the leader, telling
+    /// them so; with nobody alive the view stays where it was.
+    fn follow_leader(&mut self, client_id: u32, lost_id: u32) {
+        let leader = self.leader(client_id).filter(|&leader| leader != lost_id);
+        let message = match leader {
+            Some(leader) => format!(
+                "{} is gone; now following the leader, {}.",
+                self.player_name(lost_id), self.player_name(leader)
+            ),
+            None => format!("{} is gone and nobody else is alive to follow.", self.player_name(lost_id)),
+        };
+        if let Some(client) = self.clients.get_mut(&client_id) {
+            client.spectate_target = leader;
+        }
+        self.send_server_message(client_id, &message);
+    }
+
+    /// Center each following spectator on its target's cells. A target that
+    /// died or left hands the spectator over to the leader; spectators who
+    /// spawned stop following.
+    fn update_spectator_follow(&mut self) {
+        let followers: Vec<(u32, u32)> = self.clients.iter()
+            .filter_map(|(&id, client)| client.spectate_target.map(|target| (id, target)))
+            .collect();
+        for (client_id, mut target_id) in followers {
+            if !self.is_alive(target_id) && self.clients.get(&client_id).is_some_and(|c| c.cells.is_empty()) {
+                self.follow_leader(client_id, target_id);
+                match self.clients.get(&client_id).and_then(|c| c.spectate_target) {
+                    Some(leader) => target_id = leader,
+                    None => continue,
+                }
+            }
+            let cells: Vec<(f32, f32, f32)> = self.player_cells(target_id)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|&id| self.world.get_cell(id))
+                .map(|cell| {
+                    let data = cell.data();
+                    (data.position.x, data.position.y, data.size)
+                })
+                .collect();
+            let Some(client) = self.clients.get_mut(&client_id) else {
+                continue;
+            };
+            if cells.is_empty() || !client.cells.is_empty() {
+                client.spectate_target = None;
+                continue;
+            }
+            client.update_center(&cells);
+            client.update_scale(cells.iter().map(|&(_, _, size)| size).sum());
         }
     }
 
     /// Prepare the world state broadcast data.
     fn prepare_world_broadcast(&mut self) -> (WorldUpdateBroadcast, Vec<TargetedMessage>) {
+        self.update_spectator_follow();
+
         // Build cell list using pooled buffer
         self.broadcast_world_cells.clear();
+        std::mem::swap(&mut self.broadcast_positions, &mut self.broadcast_last_positions);
+        self.broadcast_positions.clear();
         for (&node_id, entry) in self.world.iter_cells() {
             let data = entry.data();
+            self.broadcast_positions.insert(node_id, data.position);
+            // The short way round, so crossing a wrapping seam isn't a jump
+            let moved = self.broadcast_last_positions.get(&node_id)
+                .map_or(0.0, |&last| self.world.border.delta(last, data.position).length())
+                .min(MAX_VIEW_LEAD_MOVE);
+            let boost = data.boost.map_or(0.0, |boost| boost.distance);
+            let is_bonus = matches!(entry, CellEntry::Food(food) if food.is_bonus);
             let (name, skin, owner_id) = if let CellEntry::Player(_p) = entry {
                 let owner_id = data.owner_id;
                 let (name, skin) = if let Some(oid) = owner_id {
@@ -1518,6 +3017,12 @@ impl GameState {
                             },
                             client.skin.clone(),
                         )
+                    } else if let Some(parked) = self.parked_clients.iter().find(|p| p.client.id == oid) {
+                        let client = &parked.client;
+                        (
+                            if client.name.is_empty() { None } else { Some(client.name.clone()) },
+                            client.skin.clone(),
+                        )
                     } else if let Some(bot) = self.bots.get_bot(oid) {
                         // Check if it's a bot/minion
                         (
@@ -1526,7 +3031,7 @@ impl GameState {
                             } else {
                                 Some(bot.name.clone())
                             },
-                            None, // Bots don't have skins
+                            bot.skin.clone(),
                         )
                     } else {
                         (None, None)
@@ -1556,8 +3061,13 @@ impl GameState {
                 name,
                 skin,
                 owner_id,
+                is_bonus,
+                is_boosted: self.world.is_moving(node_id),
+                lead: boost.max(moved * VIEW_LEAD_TICKS),
+                identity_changed: owner_id.is_some_and(|id| self.identity_changed.contains(&id)),
             });
         }
+        self.identity_changed.clear();
 
         // Build per-client data
         let mut client_data = HashMap::new();
@@ -1566,30 +3076,24 @@ impl GameState {
                 continue;
             }
 
-            // Calculate center position from owned cells
-            let (center_x, center_y, total_size) = if client.cells.is_empty() {
-                (client.center_x, client.center_y, 0.0)
-            } else {
-                let mut cx = 0.0;
-                let mut cy = 0.0;
-                let mut total = 0.0;
-                for &cell_id in &client.cells {
-                    if let Some(cell) = self.world.get_cell(cell_id) {
-                        let data = cell.data();
-                        cx += data.position.x;
-                        cy += data.position.y;
-                        total += data.size;
-                    }
-                }
-                let count = client.cells.len() as f32;
-                (cx / count, cy / count, total)
-            };
+            // Center on the owned cells' mass-weighted centroid
+            let cells: Vec<(f32, f32, f32)> = client.cells.iter()
+                .filter_map(|&id| self.world.get_cell(id))
+                .map(|cell| {
+                    let data = cell.data();
+                    (data.position.x, data.position.y, data.size)
+                })
+                .collect();
+            let (center_x, center_y) = weighted_center(&cells).unwrap_or((client.center_x, client.center_y));
+            let total_size: f32 = cells.iter().map(|&(_, _, size)| size).sum();
 
             // Calculate scale based on total size
-            let scale = if total_size <= 0.0 {
+            let scale = if client.spectate_target.is_some() {
+                client.scale
+            } else if total_size <= 0.0 {
                 1.0
             } else {
-                (64.0 / total_size).min(1.0).powf(0.4)
+                (64.0 / total_size).min(1.0).powf(0.4) / (1.0 + self.gamemode.get_view_bonus(client_id))
             };
 
             client_data.insert(
@@ -1598,6 +3102,8 @@ impl GameState {
                     center_x,
                     center_y,
                     scale,
+                    view_width: client.view_width,
+                    view_height: client.view_height,
                     cell_ids: client.cells.clone(),
                     minion_ids: client.minions.clone(),
                     protocol: client.protocol,
@@ -1606,6 +3112,7 @@ impl GameState {
                     scramble_y: client.scramble_y,
                     name: client.name.clone(),
                     skin: client.skin.clone(),
+                    spectating: client.is_spectating && client.cells.is_empty(),
                 },
             );
         }
@@ -1614,12 +3121,13 @@ impl GameState {
         let world_broadcast = WorldUpdateBroadcast {
             cells: self.broadcast_world_cells.clone(),
             eaten: self.eaten_this_tick.clone(),
-            removed: Vec::new(), // TODO: track removed cells
+            removed: std::mem::take(&mut self.removed_cells),
             client_data,
+            view_margin: self.config.server.view_margin,
         };
 
-        // Prepare XRay data for clients that have it enabled
-        let xray_messages = self.prepare_xray_data();
+        // Prepare XRay data for clients that have it enabled, unless overloaded
+        let xray_messages = if self.shedding_load() { Vec::new() } else { self.prepare_xray_data() };
         
         (world_broadcast, xray_messages)
     }
@@ -1740,27 +3248,53 @@ impl GameState {
         messages
     }
 
+    /// Build the roster: every alive player and bot, heaviest first.
+    fn prepare_roster(&self) -> Arc<[protocol::packets::RosterEntry]> {
+        let mass_of = |cells: &[u32]| -> u32 {
+            cells.iter()
+                .filter_map(|&id| self.world.get_cell(id))
+                .map(|cell| cell.data().size * cell.data().size / 100.0)
+                .sum::<f32>() as u32
+        };
+        let players = self.clients.values()
+            .filter(|c| !c.cells.is_empty())
+            .map(|c| protocol::packets::RosterEntry {
+                name: if c.name.is_empty() { "An unnamed cell".to_string() } else { c.name.clone() },
+                mass: mass_of(&c.cells),
+                color: c.color,
+                is_bot: false,
+            });
+        let bots = self.bots.bots.iter()
+            .filter(|b| !b.cells.is_empty())
+            .map(|b| protocol::packets::RosterEntry {
+                name: b.name.clone(),
+                mass: mass_of(&b.cells),
+                color: b.color,
+                is_bot: true,
+            });
+        let mut entries: Vec<_> = players.chain(bots).collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.mass));
+        entries.truncate(ROSTER_MAX_ENTRIES);
+        entries.into()
+    }
+
     /// Prepare the leaderboard broadcast data.
     fn prepare_leaderboard_broadcast(&self) -> LeaderboardBroadcast {
         let entries = self.gamemode.get_leaderboard(&self.world, &self.clients, &self.bots);
         
         LeaderboardBroadcast { 
             entries,
+            text: self.gamemode.get_text_board(self),
             gamemode_id: self.gamemode.id(),
             gamemode_name: self.gamemode.name().to_string(),
+            roster: None,
         }
     }
 
     /// Update cells that are moving (boosted).
     fn update_moving_cells(&mut self) {
-        let border_min = glam::Vec2::new(
-            self.world.border.min_x,
-            self.world.border.min_y,
-        );
-        let border_max = glam::Vec2::new(
-            self.world.border.max_x,
-            self.world.border.max_y,
-        );
+        let border = self.world.border;
+        let map = Arc::clone(&self.world.map);
 
         // Collect cells that stopped moving
         let mut to_remove: Vec<u32> = Vec::new();
@@ -1768,7 +3302,13 @@ impl GameState {
         for i in 0..self.world.moving_cells.len() {
             let cell_id = self.world.moving_cells[i];
             let still_moving = if let Some(cell) = self.world.get_cell_mut(cell_id) {
-                cell.data_mut().update_boost(border_min, border_max)
+                let data = cell.data_mut();
+                let moving = data.update_boost(&border);
+                // Split cells stop at walls; ejected mass flies over them
+                if data.cell_type == CellType::Player {
+                    map.push_out_of_walls(data);
+                }
+                moving
             } else {
                 false
             };
@@ -1789,11 +3329,9 @@ impl GameState {
 
     /// Update player cell movement toward mouse.
     fn update_player_movement(&mut self) {
-        // Copy border values to avoid borrow conflicts
-        let border_min_x = self.world.border.min_x;
-        let border_min_y = self.world.border.min_y;
-        let border_max_x = self.world.border.max_x;
-        let border_max_y = self.world.border.max_y;
+        // Copy the border to avoid borrow conflicts
+        let border = self.world.border;
+        let map = Arc::clone(&self.world.map);
         let speed_config = self.config.player.speed;
 
         // Reuse pooled buffer - clear and rebuild
@@ -1823,19 +3361,20 @@ impl GameState {
             if let Some(cell) = self.world.get_cell_mut(cell_id) {
                 let data = cell.data_mut();
 
-                // Calculate direction to mouse
-                let dx = mouse_x - data.position.x;
-                let dy = mouse_y - data.position.y;
+                // Calculate direction to mouse, the short way around on a wrapping map
+                let delta = border.delta(data.position, glam::Vec2::new(mouse_x, mouse_y));
+                let (dx, dy) = (delta.x, delta.y);
                 let dist = (dx * dx + dy * dy).sqrt();
 
                 if dist < 1.0 {
                     continue;
                 }
 
-                // Calculate speed based on size, with gamemode multiplier
+                // Calculate speed based on size, with gamemode and speed pad multipliers
                 let base_speed = 2.2 * data.size.powf(-0.439) * 40.0;
                 let gm_mult = speed_mults.get(&owner_id).copied().unwrap_or(1.0);
-                let speed = base_speed * (speed_config as f32 / 30.0) * (dist.min(32.0) / 32.0) * gm_mult;
+                let pad_mult = map.speed_multiplier(data.position);
+                let speed = base_speed * (speed_config as f32 / 30.0) * (dist.min(32.0) / 32.0) * gm_mult * pad_mult;
 
                 // Normalize and apply movement
                 let move_x = (dx / dist) * speed;
@@ -1844,8 +3383,10 @@ impl GameState {
                 data.position.x += move_x;
                 data.position.y += move_y;
 
-                // Clamp to border
-                data.check_border(border_min_x, border_min_y, border_max_x, border_max_y);
+                // Keep on the map and out of walls
+                border.confine_cell(data);
+                map.push_out_of_walls(data);
+                self.world.update_cell_position(cell_id);
             }
         }
 
@@ -1880,6 +3421,14 @@ impl GameState {
             }
         }
 
+        // Parked cells keep their owner so they neither eat nor merge with each other
+        for parked in &self.parked_clients {
+            for &cell_id in &parked.client.cells {
+                self.collision_owner_lookup.insert(cell_id, parked.client.id);
+                self.collision_remerge_lookup.insert(cell_id, false);
+            }
+        }
+
         // Add bots to lookups
         for bot in &self.bots.bots {
             for &cell_id in &bot.cells {
@@ -1907,20 +3456,14 @@ impl GameState {
             };
 
             let cell_owner = self.collision_owner_lookup.get(&cell_id).copied();
-            let cell_age = {
-                if let Some(cell) = self.world.get_cell(cell_id) {
-                    self.tick_count.saturating_sub(cell.data().tick_of_birth)
-                } else {
-                    0
-                }
-            };
 
             // Find nearby cells using QuadTree
             // Use a larger radius to ensure we find entities that we might be overlapping with
             let search_radius = (cell_size * 3.0).max(cell_size + 200.0);
-            let nearby = self.world.find_cells_in_radius(cell_pos.x, cell_pos.y, search_radius);
+            let nearby = self.world.find_nearby(cell_pos.x, cell_pos.y, search_radius);
 
-            for &check_id in &nearby {
+            for check in &nearby {
+                let check_id = check.id;
                 if check_id == cell_id {
                     continue;
                 }
@@ -1933,14 +3476,9 @@ impl GameState {
                     continue;
                 }
 
-                let (check_pos, check_size, check_type, check_age) = match self.world.get_cell(check_id) {
-                    Some(c) => {
-                        let data = c.data();
-                        let age = self.tick_count.saturating_sub(data.tick_of_birth);
-                        (data.position, data.size, data.cell_type, age)
-                    }
-                    None => continue,
-                };
+                // The QuadTree snapshot is current: everything that moves,
+                // resizes or re-owns a cell refreshes it
+                let (check_pos, check_size, check_type) = (check.position(), check.size, check.cell_type);
 
                 // Check collision
                 let collision = check_cell_collision(
@@ -1958,17 +3496,17 @@ impl GameState {
 
                 // JS logic: swap so smaller cell is "cell" and larger is "check" (the eater)
                 // This ensures the larger cell always eats the smaller one
-                let (smaller_id, smaller_size, smaller_owner, smaller_age, smaller_type) =
+                let (smaller_id, smaller_size, smaller_owner, smaller_type) =
                     if cell_size > check_size {
-                        (check_id, check_size, self.collision_owner_lookup.get(&check_id).copied(), check_age, check_type)
+                        (check_id, check_size, self.collision_owner_lookup.get(&check_id).copied(), check_type)
                     } else {
-                        (cell_id, cell_size, cell_owner, cell_age, cell_type_val)
+                        (cell_id, cell_size, cell_owner, cell_type_val)
                     };
-                let (larger_id, larger_size, larger_owner, larger_age, larger_type) =
+                let (larger_id, larger_size, larger_owner, larger_type) =
                     if cell_size > check_size {
-                        (cell_id, cell_size, cell_owner, cell_age, cell_type_val)
+                        (cell_id, cell_size, cell_owner, cell_type_val)
                     } else {
-                        (check_id, check_size, self.collision_owner_lookup.get(&check_id).copied(), check_age, check_type)
+                        (check_id, check_size, self.collision_owner_lookup.get(&check_id).copied(), check_type)
                     };
 
                 // Skip if either is already removed
@@ -1979,6 +3517,26 @@ impl GameState {
                     continue;
                 }
 
+                // Pushed virus: a virus still flying from a shot pops any larger
+                // player cell it touches and is used up, without adding mass
+                if smaller_type == CellType::Virus
+                    && larger_type == CellType::Player
+                    && larger_size > smaller_size
+                    && self.world.is_moving(smaller_id)
+                    && self.gamemode.virus_push_enabled()
+                {
+                    if let Some(owner_id) = larger_owner {
+                        self.collision_eat_events.push((larger_id, smaller_id, 0.0));
+                        let idx = smaller_id as usize;
+                        if idx >= self.collision_cells_to_remove.len() {
+                            self.collision_cells_to_remove.grow(idx + 1);
+                        }
+                        self.collision_cells_to_remove.insert(idx);
+                        self.collision_virus_pops.push((owner_id, larger_id));
+                    }
+                    continue;
+                }
+
                 // Check actual overlap threshold
                 // JS resolveCollision: size = check._size - cell._size / div
                 // (check = larger, cell = smaller; applies to ALL cell types)
@@ -1994,7 +3552,7 @@ impl GameState {
                 // Rust increments tick_count at the start of tick(), so freshly spawned
                 // ejects (born at tick N) will have age 1 on their first collision check;
                 // use < 2 to match the one-tick grace window of the JS version.
-                if smaller_type == CellType::EjectedMass && smaller_age < 2 {
+                if smaller_type == CellType::EjectedMass && self.cell_age(smaller_id) < 2 {
                     continue;
                 }
 
@@ -2036,7 +3594,8 @@ impl GameState {
                             // Both cells must be able to remerge AND be old enough
                             let split_restore_ticks = if self.config.server.mobile_physics { 1 } else { 13 };
                             let can_merge = smaller_can_remerge && larger_can_remerge &&
-                                           smaller_age >= split_restore_ticks && larger_age >= split_restore_ticks;
+                                           self.cell_age(smaller_id) >= split_restore_ticks &&
+                                           self.cell_age(larger_id) >= split_restore_ticks;
                             // For equal sizes, use ID as tiebreaker
                             can_merge && (larger_size > smaller_size || (larger_size == smaller_size && larger_id > smaller_id))
                         } else {
@@ -2075,13 +3634,21 @@ impl GameState {
 
                 if can_eat_check {
                     // Larger cell eats smaller cell
-                    let eaten_mass = size_to_mass(smaller_size);
+                    let mut eaten_mass = size_to_mass(smaller_size);
+                    if matches!(self.world.get_cell(smaller_id), Some(CellEntry::Food(food)) if food.is_bonus) {
+                        eaten_mass *= self.config.food.bonus_mass_multiplier as f32;
+                    }
                     self.collision_eat_events.push((larger_id, smaller_id, eaten_mass));
                     let idx = smaller_id as usize;
                     if idx >= self.collision_cells_to_remove.len() {
                         self.collision_cells_to_remove.grow(idx + 1);
                     }
                     self.collision_cells_to_remove.insert(idx);
+
+                    // Another player's ejected mass: log the transfer for anti-teaming
+                    if larger_type == CellType::Player && smaller_type == CellType::EjectedMass {
+                        self.record_feed(smaller_id, larger_owner, eaten_mass);
+                    }
                     
                     // Check if player ate a virus - trigger pop
                     if larger_type == CellType::Player && smaller_type == CellType::Virus {
@@ -2126,9 +3693,10 @@ impl GameState {
                 }
 
                 let search_radius = (cell_size * 3.0).max(cell_size + 200.0);
-                let nearby = self.world.find_cells_in_radius(cell_pos.x, cell_pos.y, search_radius);
+                let nearby = self.world.find_nearby(cell_pos.x, cell_pos.y, search_radius);
 
-                for &check_id in &nearby {
+                for check in &nearby {
+                    let check_id = check.id;
                     let check_id_idx = check_id as usize;
                     let cell_id_idx = cell_id as usize;
                     if check_id == cell_id
@@ -2138,25 +3706,14 @@ impl GameState {
                         continue;
                     }
 
-                    let (check_pos, check_size, check_type, check_age) = match self.world.get_cell(check_id) {
-                        Some(c) => {
-                            let d = c.data();
-                            let age = self.tick_count.saturating_sub(d.tick_of_birth);
-                            (d.position, d.size, d.cell_type, age)
-                        }
-                        None => continue,
-                    };
+                    let (check_pos, check_size, check_type) = (check.position(), check.size, check.cell_type);
 
                     // Identify virus and eject in the pair (either order)
-                    let (virus_id, virus_size, virus_pos, eject_id, eject_size, eject_pos, eject_age) =
+                    let (virus_id, virus_size, virus_pos, eject_id, eject_size, eject_pos) =
                         if cell_type == CellType::Virus && check_type == CellType::EjectedMass {
-                            (cell_id, cell_size, cell_pos, check_id, check_size, check_pos, check_age)
+                            (cell_id, cell_size, cell_pos, check_id, check_size, check_pos)
                         } else if cell_type == CellType::EjectedMass && check_type == CellType::Virus {
-                            (check_id, check_size, check_pos, cell_id, cell_size, cell_pos, {
-                                if let Some(c) = self.world.get_cell(cell_id) {
-                                    self.tick_count.saturating_sub(c.data().tick_of_birth)
-                                } else { 0 }
-                            })
+                            (check_id, check_size, check_pos, cell_id, cell_size, cell_pos)
                         } else {
                             continue; // not a virus-eject pair
                         };
@@ -2167,7 +3724,7 @@ impl GameState {
                     }
 
                     // Ejected-mass age grace window (same as player-cells loop)
-                    if eject_age < 2 {
+                    if self.cell_age(eject_id) < 2 {
                         continue;
                     }
 
@@ -2187,11 +3744,18 @@ impl GameState {
                         continue;
                     }
 
-                    // Virus eats ejected mass – growth uses the same on_eat formula;
-                    // after applying, we check whether the virus hit virusMaxSize
-                    // and needs to shoot (handled after the eat-event loop).
-                    self.collision_eat_events.push((virus_id, eject_id, size_to_mass(eject_size)));
-                    self.collision_virus_ate_eject.push(virus_id);
+                    // Virus eats ejected mass – growth uses the same on_eat formula,
+                    // scaled by virus.growth_factor; after applying, we check whether
+                    // the virus hit virusMaxSize and needs to shoot (handled after the
+                    // eat-event loop). A pellet that stopped counts as fed from where it lies.
+                    let growth = size_to_mass(eject_size) * self.config.virus.growth_factor as f32;
+                    let feed_direction = self.world.get_cell(eject_id)
+                        .and_then(|c| c.data().boost)
+                        .map(|b| b.direction)
+                        .filter(|d| *d != glam::Vec2::ZERO)
+                        .unwrap_or_else(|| (virus_pos - eject_pos).normalize_or_zero());
+                    self.collision_eat_events.push((virus_id, eject_id, growth));
+                    self.collision_virus_ate_eject.push((virus_id, feed_direction));
                     let idx = eject_id as usize;
                     if idx >= self.collision_cells_to_remove.len() {
                         self.collision_cells_to_remove.grow(idx + 1);
@@ -2222,40 +3786,42 @@ impl GameState {
 
         // Virus onEat post-processing: if a virus that ate an eject grew past
         // virusMaxSize, reset it to virusMinSize and shoot a new virus in the
-        // direction the eaten eject was travelling.
+        // direction the eaten eject was travelling. At virus.max_amount the
+        // virus waits at max size instead.
         // JS Virus.onEat: setSize(virusMinSize); shootVirus(this, cell.boostDirection.angle)
         {
             let virus_max_size = self.config.virus.max_size as f32;
             let virus_min_size = self.config.virus.min_size as f32;
             let virus_eject_speed = self.config.virus.eject_speed as f32;
+            let virus_max = self.config.virus.max_amount;
 
-            for &vid in &self.collision_virus_ate_eject {
-                let virus_size = match self.world.get_cell(vid) {
-                    Some(c) => c.data().size,
+            for &(vid, direction) in &self.collision_virus_ate_eject {
+                let (virus_pos, virus_size) = match self.world.get_cell(vid) {
+                    Some(c) => (c.data().position, c.data().size),
                     None => continue,
                 };
                 if virus_size < virus_max_size {
                     continue;
                 }
-                // Reset virus to min size
+                let can_shoot = self.world.virus_cells.len() < virus_max;
+                let new_size = if can_shoot { virus_min_size } else { virus_max_size };
                 if let Some(c) = self.world.get_cell_mut(vid) {
-                    c.data_mut().set_size(virus_min_size);
+                    c.data_mut().set_size(new_size);
                 }
                 self.world.update_cell_position(vid);
+                if !can_shoot {
+                    continue;
+                }
 
-                // Shoot a new virus from this position in a random direction
-                // (JS uses the eaten eject's boostDirection.angle; we use a
-                // random angle here as the eject is already removed and its
-                // boost data is gone).
-                let virus_pos = match self.world.get_cell(vid) {
-                    Some(c) => c.data().position,
-                    None => continue,
+                let direction = if direction == glam::Vec2::ZERO {
+                    let angle = self.world.rng.random_range(0.0..std::f32::consts::TAU);
+                    glam::Vec2::new(angle.sin(), angle.cos())
+                } else {
+                    direction
                 };
-                let mut rng = rand::rng();
-                let angle = rng.random_range(0.0..std::f32::consts::TAU);
                 let new_virus_id = self.world.next_id();
                 let mut new_virus = crate::entity::Virus::new(new_virus_id, virus_pos, virus_min_size, self.tick_count);
-                new_virus.data_mut().set_boost(virus_eject_speed, angle);
+                new_virus.data_mut().set_boost_direction(virus_eject_speed, direction);
                 self.world.add_virus(new_virus);
                 self.world.add_moving(new_virus_id);
             }
@@ -2279,11 +3845,18 @@ impl GameState {
             // Detect deaths: clients/bots that now have zero cells
             // Build victim→killer map from eat_events using owner_lookup
             let mut victim_killer: HashMap<u32, u32> = HashMap::new();
-            for &(eater_id, eaten_id, _) in &self.collision_eat_events {
+            let mut victim_mass: HashMap<u32, f32> = HashMap::new();
+            let mut victim_mother: HashMap<u32, u32> = HashMap::new();
+            for &(eater_id, eaten_id, eaten_mass) in &self.collision_eat_events {
                 let eater_owner = self.collision_owner_lookup.get(&eater_id).copied().unwrap_or(0);
                 let eaten_owner = self.collision_owner_lookup.get(&eaten_id).copied().unwrap_or(0);
                 if eater_owner != 0 && eaten_owner != 0 && eater_owner != eaten_owner {
                     victim_killer.entry(eaten_owner).or_insert(eater_owner);
+                    *victim_mass.entry(eaten_owner).or_default() += eaten_mass;
+                } else if eater_owner == 0 && eaten_owner != 0
+                    && matches!(self.world.get_cell(eater_id), Some(CellEntry::Mother(_)))
+                {
+                    victim_mother.entry(eaten_owner).or_insert(eater_id);
                 }
             }
             for (&victim_id, &killer_id) in &victim_killer {
@@ -2296,6 +3869,15 @@ impl GameState {
                 };
                 if is_dead {
                     self.deaths_this_tick.push((killer_id, victim_id));
+                    self.death_masses.insert(victim_id, victim_mass.get(&victim_id).copied().unwrap_or(0.0));
+                }
+            }
+            // Players whose last cell went to a mother cell, unless someone else ate them too
+            for (&victim_id, &mother_id) in &victim_mother {
+                if !victim_killer.contains_key(&victim_id)
+                    && self.clients.get(&victim_id).is_some_and(|c| c.cells.is_empty())
+                {
+                    self.mother_cell_deaths.push((mother_id, victim_id));
                 }
             }
 
@@ -2308,6 +3890,54 @@ impl GameState {
         // Handle virus pops AFTER eating is done
         let virus_pops = std::mem::take(&mut self.collision_virus_pops);
         self.process_virus_pops(virus_pops);
+
+        self.enforce_max_size();
+    }
+
+    /// Bring player cells grown past `player.max_size` back under it, by
+    /// splitting or capping per `player.max_size_behavior`.
+    fn enforce_max_size(&mut self) {
+        let max_size = self.config.player.max_size as f32;
+        let mut oversized: Vec<(u32, u32)> = self.world.player_cells.iter()
+            .filter_map(|&id| match self.world.get_cell(id) {
+                Some(CellEntry::Player(cell)) if cell.cell_data.size > max_size => {
+                    cell.cell_data.owner_id.map(|owner| (owner, id))
+                }
+                _ => None,
+            })
+            .collect();
+        while let Some((owner_id, cell_id)) = oversized.pop() {
+            let Some(size) = self.world.get_cell(cell_id).map(|c| c.data().size) else {
+                continue;
+            };
+            if size <= max_size {
+                continue;
+            }
+            if self.config.player.max_size_behavior == MaxSizeBehavior::Split {
+                let cells_before = self.player_cells(owner_id).map_or(0, <[u32]>::len);
+                let angle = self.world.rng.random_range(0.0..std::f32::consts::TAU);
+                self.split_player_cell_with_mass(owner_id, cell_id, angle, crate::collision::size_to_mass(size) / 2.0);
+                if let Some(&new_id) = self.player_cells(owner_id)
+                    .filter(|cells| cells.len() > cells_before)
+                    .and_then(|cells| cells.last())
+                {
+                    // Both halves may still be too big
+                    oversized.push((owner_id, cell_id));
+                    oversized.push((owner_id, new_id));
+                    continue;
+                }
+            }
+            if let Some(cell) = self.world.get_cell_mut(cell_id) {
+                cell.data_mut().set_size(max_size);
+            }
+            self.world.update_cell_position(cell_id);
+        }
+    }
+
+    /// Ticks since the cell was born; 0 if it is gone.
+    fn cell_age(&self, cell_id: u32) -> u64 {
+        self.world.get_cell(cell_id)
+            .map_or(0, |cell| self.tick_count.saturating_sub(cell.data().tick_of_birth))
     }
 
     /// Pop a player into multiple cells when they eat a virus.
@@ -2342,7 +3972,7 @@ impl GameState {
 
             // Split the player cell
             for &split_mass in &splits {
-                let angle = rand::rng().random::<f32>() * std::f32::consts::TAU;
+                let angle = self.world.rng.random::<f32>() * std::f32::consts::TAU;
                 self.split_player_cell_with_mass(owner_id, cell_id, angle, split_mass);
             }
         }
@@ -2426,10 +4056,19 @@ impl GameState {
     /// Notify gamemode of player deaths detected this tick.
     fn process_deaths(&mut self) {
         let deaths: Vec<(u32, u32)> = self.deaths_this_tick.drain(..).collect();
+        for &(killer_id, victim_id) in &deaths {
+            self.send_eaten_death_info(killer_id, victim_id);
+        }
+        for (mother_id, victim_id) in std::mem::take(&mut self.mother_cell_deaths) {
+            let mass = self.world.get_cell(mother_id)
+                .map_or(0.0, |cell| crate::collision::size_to_mass(cell.data().size));
+            self.send_death_info(victim_id, DeathCause::MotherCell, String::new(), mass, 0);
+        }
         
         // Temporarily take gamemode ownership to satisfy borrow checker
         let mut gamemode = std::mem::replace(&mut self.gamemode, Box::new(crate::gamemodes::ffa::Ffa::new()));
         
+        let mut kills = Vec::new();
         for (killer_id, victim_id) in deaths {
             // Check if the victim is a minion owned by any player
             let is_minion = self.clients.values().any(|client| client.minions.contains(&victim_id));
@@ -2437,10 +4076,79 @@ impl GameState {
             // Only notify gamemode if victim is not a minion
             if !is_minion {
                 gamemode.on_player_death(self, killer_id, victim_id);
+                kills.push((killer_id, victim_id));
             }
         }
         
         self.gamemode = gamemode;
+        if self.config.announcements.enabled {
+            self.announce_kills(&kills);
+        }
+    }
+
+    /// Announce rampages and big kills among this tick's kills.
+    fn announce_kills(&mut self, kills: &[(u32, u32)]) {
+        let now = self.game_time_ms();
+        for &(killer_id, victim_id) in kills {
+            self.kill_announcer.end_streak(victim_id);
+            // A victim eaten piece by piece over several ticks loses only
+            // the last pieces in the fatal one, so size it by its peak
+            let mass = self.death_masses.get(&victim_id).copied().unwrap_or(0.0)
+                .max(self.life_peak_mass(victim_id));
+            let Some(announcement) = self.kill_announcer.record_kill(&self.config.announcements, killer_id, mass, now) else {
+                continue;
+            };
+            let killer = self.player_name(killer_id);
+            let message = match announcement {
+                Announcement::Rampage { kills } => format!(
+                    "{} is on a rampage! ({} kills in {}s)",
+                    killer, kills, self.config.announcements.streak_window_seconds
+                ),
+                Announcement::BigKill { mass } => format!(
+                    "{} ate {} ({} mass)!",
+                    killer, self.player_name(victim_id), mass.round()
+                ),
+            };
+            self.announce_with_color(&message, KILL_ANNOUNCEMENT_COLOR);
+        }
+    }
+
+    /// Tell a client which player ate their last cell. A minion's kill is
+    /// credited to its owner by name and id; the mass is the minion's own.
+    fn send_eaten_death_info(&self, killer_id: u32, victim_id: u32) {
+        if !self.clients.contains_key(&victim_id) {
+            return;
+        }
+        let owner = self.clients.values().find(|c| c.minions.contains(&killer_id)).map(|c| c.id);
+        let credited = owner.unwrap_or(killer_id);
+        let name = self.player_name(credited);
+        let cells = self.clients.get(&killer_id).map(|c| c.cells.as_slice())
+            .or_else(|| self.bots.get_bot(killer_id).map(|b| b.cells.as_slice()))
+            .unwrap_or_default();
+        let mass = cells.iter()
+            .filter_map(|&id| self.world.get_cell(id))
+            .map(|cell| crate::collision::size_to_mass(cell.data().size))
+            .sum();
+        self.send_death_info(victim_id, DeathCause::Eaten, name, mass, credited);
+    }
+
+    /// Send a DeathInfo packet for the client's death overlay, if it speaks
+    /// cogar's protocol.
+    fn send_death_info(&self, client_id: u32, cause: DeathCause, killer_name: String, killer_mass: f32, killer_id: u32) {
+        if self.clients.get(&client_id).is_some_and(Client::speaks_cogar) {
+            let _ = self.targeted_tx.send(TargetedMessage {
+                client_id,
+                message: TargetedMessageType::DeathInfo { cause, killer_name, killer_mass: killer_mass as u32, killer_id },
+            });
+        }
+    }
+
+    /// Name of a client or bot as shown in chat.
+    fn player_name(&self, id: u32) -> String {
+        self.clients.get(&id).map(|c| c.name.clone())
+            .or_else(|| self.bots.get_bot(id).map(|b| b.name.clone()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "An unnamed cell".to_string())
     }
 
     /// Process rigid collisions (push apart) for same-owner cells that can't merge.
@@ -2448,11 +4156,9 @@ impl GameState {
         let split_restore_ticks = if self.config.server.mobile_physics { 1 } else { 13 };
         let tick = self.tick_count;
 
-        // Cache border values before we start mutating
-        let border_min_x = self.world.border.min_x;
-        let border_min_y = self.world.border.min_y;
-        let border_max_x = self.world.border.max_x;
-        let border_max_y = self.world.border.max_y;
+        // Cache the border before we start mutating
+        let border = self.world.border;
+        let map = Arc::clone(&self.world.map);
 
         // Use index iteration to avoid cloning
         let player_count = self.world.player_cells.len();
@@ -2477,24 +4183,16 @@ impl GameState {
             let cell_can_remerge = self.collision_remerge_lookup.get(&cell_id).copied().unwrap_or(false);
 
             // Find nearby cells
-            let nearby = self.world.find_cells_in_radius(cell_pos.x, cell_pos.y, cell_size * 2.0);
+            let nearby = self.world.find_nearby(cell_pos.x, cell_pos.y, cell_size * 2.0);
 
-            for &check_id in &nearby {
+            for check in &nearby {
+                let check_id = check.id;
                 if check_id <= cell_id {
                     continue; // Avoid duplicate pairs
                 }
 
-                // Re-read check cell position (may have been updated)
-                let (check_pos, check_size, check_mass, check_birth, check_type) = match self.world.get_cell(check_id) {
-                    Some(c) => {
-                        let data = c.data();
-                        (data.position, data.size, data.mass, data.tick_of_birth, data.cell_type)
-                    }
-                    None => continue,
-                };
-
                 // Only apply rigid collision to player cells of same owner
-                if check_type != crate::entity::CellType::Player {
+                if check.cell_type != crate::entity::CellType::Player {
                     continue;
                 }
 
@@ -2503,6 +4201,13 @@ impl GameState {
                     continue;
                 }
 
+                // Snapshot taken after any earlier pushes, so still current
+                let (check_pos, check_size) = (check.position(), check.size);
+                let (check_mass, check_birth) = match self.world.get_cell(check_id) {
+                    Some(c) => (c.data().mass, c.data().tick_of_birth),
+                    None => continue,
+                };
+
                 let check_age = tick.saturating_sub(check_birth);
                 let check_can_remerge = self.collision_remerge_lookup.get(&check_id).copied().unwrap_or(false);
 
@@ -2578,13 +4283,9 @@ impl GameState {
                     let data = cell.data_mut();
                     data.position.x -= push_x * cell_ratio;
                     data.position.y -= push_y * cell_ratio;
-                    // Keep within border
-                    data.check_border(
-                        border_min_x,
-                        border_min_y,
-                        border_max_x,
-                        border_max_y,
-                    );
+                    // Keep within border and out of walls
+                    border.confine_cell(data);
+                    map.push_out_of_walls(data);
                 }
                 self.world.update_cell_position(cell_id);
 
@@ -2593,13 +4294,9 @@ impl GameState {
                     let data = cell.data_mut();
                     data.position.x += push_x * check_ratio;
                     data.position.y += push_y * check_ratio;
-                    // Keep within border
-                    data.check_border(
-                        border_min_x,
-                        border_min_y,
-                        border_max_x,
-                        border_max_y,
-                    );
+                    // Keep within border and out of walls
+                    border.confine_cell(data);
+                    map.push_out_of_walls(data);
                 }
                 self.world.update_cell_position(check_id);
             }
@@ -2609,7 +4306,7 @@ impl GameState {
 
     /// Update merge status for all player cells.
     fn update_merge_status(&mut self) {
-        let merge_time = self.config.player.merge_time;
+        let recombine_time = self.config.player.recombine_time;
         let tick = self.tick_count;
 
         // Update canRemerge for all player cells - iterate by index to avoid clone
@@ -2617,34 +4314,40 @@ impl GameState {
         for i in 0..player_count {
             let cell_id = self.world.player_cells[i];
             if let Some(CellEntry::Player(cell)) = self.world.get_cell_mut(cell_id) {
-                cell.update_merge(tick, merge_time as f32);
+                cell.update_merge(tick, recombine_time as f32);
             }
         }
     }
 
     /// Update cell decay (large cells shrink).
+    ///
+    /// Each pass a cell loses `size * decay_rate * (1 + mass_bonus)`, where the
+    /// bonus grows with the owner's total mass (see [`decayed_size`]) and the
+    /// gamemode can scale the rate per player.
     fn update_decay(&mut self) {
-        let min_decay = self.config.player.min_size as f32;
+        let min_decay = self.config.player.min_decay_size as f32;
         let decay_rate = self.config.player.decay_rate as f32;
-        let decay_factor = 1.0 - decay_rate;
+        let exponent = self.config.player.decay_mass_exponent as f32;
 
         // Collect cells to decay
         let mut decay_updates: Vec<(u32, f32)> = Vec::new();
 
-        // Decay human player cells
-        for (&_client_id, client) in &self.clients {
-            for &cell_id in &client.cells {
+        // Human player cells, then bot cells
+        let owners = self.clients.iter().map(|(&id, client)| (id, &client.cells))
+            .chain(self.bots.bots.iter().map(|bot| (bot.id, &bot.cells)));
+        for (owner_id, cells) in owners {
+            let owner_mass: f32 = cells.iter()
+                .filter_map(|&id| self.world.get_cell(id))
+                .map(|cell| cell.data().mass)
+                .sum();
+            let rate = decay_rate
+                * self.gamemode.get_decay_multiplier(owner_id)
+                * self.antiteam.decay_multiplier(&self.config.antiteam, owner_id);
+
+            for &cell_id in cells {
                 if let Some(cell) = self.world.get_cell(cell_id) {
                     let size = cell.data().size;
-                    if size <= min_decay {
-                        continue;
-                    }
-
-                    // Apply decay: size = sqrt(size^2 * (1 - rate))
-                    // Optimized: sqrt(size^2 * decay) = size * sqrt(decay)
-                    // Pre-compute sqrt(decay) since it's constant per tick
-                    let new_size = size * decay_factor.sqrt();
-                    let new_size = new_size.max(min_decay);
+                    let new_size = decayed_size(size, owner_mass, rate, exponent, min_decay);
 
                     // Only update if change is significant (avoid tiny updates)
                     if size - new_size > 0.01 {
@@ -2654,59 +4357,267 @@ impl GameState {
             }
         }
 
-        // Decay bot cells
-        for bot in &self.bots.bots {
-            for &cell_id in &bot.cells {
-                if let Some(cell) = self.world.get_cell(cell_id) {
-                    let size = cell.data().size;
-                    if size <= min_decay {
-                        continue;
-                    }
+        // Apply decay updates
+        for (cell_id, new_size) in decay_updates {
+            if let Some(cell) = self.world.get_cell_mut(cell_id) {
+                cell.data_mut().set_size(new_size);
+            }
+            self.world.update_cell_position(cell_id);
+        }
+    }
 
-                    let new_size = size * decay_factor.sqrt();
-                    let new_size = new_size.max(min_decay);
+    /// Start logging chat and commands sent in the room at `room` to a chat
+    /// log writer (see [`crate::chatlog::spawn_writer`]).
+    pub fn attach_chat_log(&mut self, tx: mpsc::Sender<ChatLogEntry>, room: String) {
+        self.chat_log = Some(ChatLogFeed { tx, room, dropped: 0 });
+    }
 
-                    if size - new_size > 0.01 {
-                        decay_updates.push((cell_id, new_size));
-                    }
+    /// Hand a chat message or command to the chat log writer without
+    /// waiting on it. The operator password never reaches the log: it is
+    /// redacted wherever it appears, and `/operator` arguments always are.
+    fn log_chat(&mut self, client_id: u32, kind: ChatLogKind, message: &str) {
+        let (Some(feed), Some(client)) = (self.chat_log.as_mut(), self.clients.get(&client_id)) else {
+            return;
+        };
+        let (command, args) = message.split_once(' ').unwrap_or((message, ""));
+        let text = if matches!(kind, ChatLogKind::Command { .. }) && command.eq_ignore_ascii_case("/operator") && !args.is_empty() {
+            format!("{} {}", command, chatlog::REDACTED)
+        } else {
+            chatlog::redact(message, &self.config.server.operator_password)
+        };
+        let entry = ChatLogEntry {
+            time: crate::highscores::unix_now(),
+            room: feed.room.clone(),
+            client_id,
+            ip: client.addr.ip(),
+            name: client.name.clone(),
+            kind,
+            text,
+        };
+        match feed.tx.try_send(entry) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                feed.dropped += 1;
+                if feed.dropped.is_power_of_two() {
+                    warn!("Chat log writer is falling behind; {} entry(s) dropped", feed.dropped);
                 }
             }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                warn!("Chat log writer stopped; chat logging disabled");
+                self.chat_log = None;
+            }
         }
+    }
 
-        // Apply decay updates
-        for (cell_id, new_size) in decay_updates {
-            if let Some(cell) = self.world.get_cell_mut(cell_id) {
-                cell.data_mut().set_size(new_size);
+    /// Start feeding ticks to a replay recorder (see [`crate::replay::spawn_recorder`]).
+    pub fn attach_replay(&mut self, tx: mpsc::Sender<ReplayMessage>) {
+        for (&id, client) in &self.clients {
+            let _ = tx.try_send(ReplayMessage::Player { id, name: client.name.clone() });
+        }
+        self.replay = Some(ReplayFeed {
+            tx,
+            known_cells: HashSet::new(),
+            round: self.gamemode.round(),
+            dropped: 0,
+        });
+    }
+
+    /// Hand this tick's eats, spawns, removals and (periodically) a position
+    /// snapshot to the replay recorder without waiting on it.
+    fn record_replay_tick(&mut self) {
+        let Some(feed) = self.replay.as_mut() else {
+            return;
+        };
+        // A new tournament round starts a new file, which re-lists every cell
+        let round = self.gamemode.round();
+        if round != feed.round {
+            feed.round = round;
+            feed.known_cells.clear();
+            let _ = feed.tx.try_send(ReplayMessage::Rotate);
+        }
+
+        let snapshot_due = self.tick_count.is_multiple_of(self.config.replay.snapshot_interval_ticks.max(1));
+        let mut frame = TickFrame {
+            tick: self.tick_count as u32,
+            eats: self.eaten_this_tick.iter().map(|&(eaten, eater)| (eater, eaten)).collect(),
+            snapshot: snapshot_due.then(Vec::new),
+            ..TickFrame::default()
+        };
+        let mut current = HashSet::with_capacity(feed.known_cells.len());
+        for (&node_id, entry) in self.world.iter_cells() {
+            let data = entry.data();
+            current.insert(node_id);
+            if !feed.known_cells.contains(&node_id) {
+                frame.spawns.push(SpawnRecord {
+                    node_id,
+                    cell_type: data.cell_type as u8,
+                    x: data.position.x as i32,
+                    y: data.position.y as i32,
+                    size: data.size as u16,
+                    owner: data.owner_id,
+                });
             }
-            self.world.update_cell_position(cell_id);
+            // Food never moves; snapshots skip it to stay small
+            if let Some(snapshot) = frame.snapshot.as_mut().filter(|_| data.cell_type != CellType::Food) {
+                snapshot.push(CellSnapshot {
+                    node_id,
+                    x: data.position.x as i32,
+                    y: data.position.y as i32,
+                    size: data.size as u16,
+                });
+            }
+        }
+        frame.removals = feed.known_cells.difference(&current).copied().collect();
+        feed.known_cells = current;
+
+        match feed.tx.try_send(ReplayMessage::Tick(frame)) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                feed.dropped += 1;
+                if feed.dropped.is_power_of_two() {
+                    warn!("Replay recorder is falling behind; {} frame(s) dropped", feed.dropped);
+                }
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                warn!("Replay recorder stopped; recording disabled");
+                self.replay = None;
+            }
+        }
+    }
+
+    /// Whether the anti-teaming heuristic applies: enabled, and not in Teams
+    /// where feeding teammates is the point.
+    fn antiteam_active(&self) -> bool {
+        self.config.antiteam.enabled && self.gamemode.id() != 1
+    }
+
+    /// Game time in milliseconds, for tick-based sliding windows.
+    fn game_time_ms(&self) -> u64 {
+        self.tick_count * self.config.server.tick_interval_ms
+    }
+
+    /// Log a human player eating another human player's ejected mass.
+    fn record_feed(&mut self, eject_id: u32, eater: Option<u32>, mass: f32) {
+        if !self.antiteam_active() {
+            return;
+        }
+        let Some(CellEntry::Eject(eject)) = self.world.get_cell(eject_id) else {
+            return;
+        };
+        let (Some(from), Some(to)) = (eject.ejector, eater) else {
+            return;
+        };
+        if from != to && self.clients.contains_key(&from) && self.clients.contains_key(&to) {
+            let now = self.game_time_ms();
+            self.antiteam.record_transfer(from, to, mass, now);
+        }
+    }
+
+    /// Sample proximity for pairs with recent transfers, then strike and warn
+    /// pairs over the thresholds. Runs once per decay pass.
+    fn update_antiteam(&mut self) {
+        if !self.antiteam_active() {
+            return;
+        }
+        let config = &self.config.antiteam;
+        let now = self.game_time_ms();
+        let window_ms = (config.window_seconds * 1000.0) as u64;
+        let sample_ms = 25 * self.config.server.tick_interval_ms;
+        for (a, b) in self.antiteam.active_pairs(now, window_ms) {
+            if self.cell_gap(a, b).is_some_and(|gap| gap <= config.proximity_distance as f32) {
+                self.antiteam.record_near(a, b, sample_ms, now);
+            }
+        }
+
+        for (a, b) in self.antiteam.evaluate(&self.config.antiteam, now) {
+            for (id, partner) in [(a, b), (b, a)] {
+                let partner_name = self.clients.get(&partner)
+                    .map(|c| if c.name.is_empty() { "An unnamed cell".to_string() } else { c.name.clone() })
+                    .unwrap_or_default();
+                let multiplier = self.antiteam.decay_multiplier(&self.config.antiteam, id);
+                self.send_server_message(
+                    id,
+                    &format!("Teaming with {} detected: your cells now decay {:.0}x faster.", partner_name, multiplier),
+                );
+            }
+            info!("Anti-teaming: clients {} and {} flagged", a, b);
         }
     }
 
+    /// Edge-to-edge distance between the closest cells of two clients.
+    fn cell_gap(&self, a: u32, b: u32) -> Option<f32> {
+        let border = self.world.border;
+        let cells = |id: u32| {
+            self.clients.get(&id).into_iter()
+                .flat_map(|c| c.cells.iter())
+                .filter_map(|&cell_id| self.world.get_cell(cell_id))
+                .map(|cell| (cell.data().position, cell.data().size))
+        };
+        cells(a)
+            .flat_map(|(pa, sa)| cells(b).map(move |(pb, sb)| border.delta(pa, pb).length() - sa - sb))
+            .min_by(f32::total_cmp)
+            .map(|gap| gap.max(0.0))
+    }
+
     /// Process bot respawns.
     fn process_bot_respawns(&mut self) {
-        let start_size = self.config.player.start_size as f32;
+        let bots = &self.config.bots;
+        let default_start_size = if bots.start_size > 0.0 { bots.start_size } else { self.config.player.start_size } as f32;
+        let minion_start_size = bots.minion_start_size as f32;
+        let ramp_ticks = bots.start_mass_ramp_ticks;
+        let min_size = self.config.player.min_size as f32;
+        let delay = bots.respawn_delay_ticks;
         let tick_count = self.tick_count;
 
+        self.grow_respawned_bots();
+
         // Get list of bots that need to respawn
         let respawn_list = self.bots.get_respawn_list();
+        let minion_ids: HashSet<u32> = self.clients.values()
+            .flat_map(|c| c.minions.iter().copied())
+            .collect();
 
         for bot_id in respawn_list {
+            let is_minion = minion_ids.contains(&bot_id);
+            if let Some(bot) = self.bots.get_bot_mut(bot_id) {
+                bot.spawn_ramp = None;
+                if !is_minion && bot.has_spawned && delay > 0 {
+                    let respawn_tick = *bot.respawn_tick.get_or_insert(tick_count + delay);
+                    if tick_count < respawn_tick {
+                        continue;
+                    }
+                }
+            }
+            let start_size = self.gamemode.get_spawn_size(bot_id).unwrap_or(default_start_size);
+            let ramp_from = min_size.min(start_size);
+            let ramped = !is_minion && ramp_ticks > 0 && ramp_from < start_size;
+            let size = if is_minion {
+                minion_start_size
+            } else if ramped {
+                ramp_from
+            } else {
+                start_size
+            };
             // Spawn a cell for this bot
-            let position = self.world.border.random_position();
+            let position = match self.gamemode.get_spawn_position(&self.world, bot_id) {
+                Some(position) => position,
+                None => self.world.open_position(size),
+            };
             let node_id = self.world.next_id();
 
             // Let GameMode handle team assignment if needed
+            let team_sizes = self.team_sizes();
             if let Some(bot) = self.bots.get_bot_mut(bot_id) {
-                self.gamemode.on_bot_spawn(bot);
+                self.gamemode.on_bot_spawn(bot, &team_sizes, &mut self.world.rng);
             }
 
             let (color, team) = if let Some(bot) = self.bots.get_bot(bot_id) {
                 (bot.color, bot.team)
             } else {
-                (crate::world::World::random_color(), None)
+                (World::random_color(&mut self.world.rng), None)
             };
 
-            let mut cell = PlayerCell::new(node_id, bot_id, position, start_size as f32, tick_count);
+            let mut cell = PlayerCell::new(node_id, bot_id, position, size, tick_count);
             cell.cell_data.color = color;
 
             let cell_id = self.world.add_player_cell(cell);
@@ -2715,19 +4626,52 @@ impl GameState {
             if let Some(bot) = self.bots.get_bot_mut(bot_id) {
                 bot.cells.push(cell_id);
                 bot.needs_respawn = false;
+                bot.has_spawned = true;
+                bot.respawn_tick = None;
+                if ramped {
+                    bot.spawn_ramp = Some(SpawnRamp {
+                        cell: cell_id,
+                        ticks_left: ramp_ticks,
+                        mass_per_tick: (crate::collision::size_to_mass(start_size) - crate::collision::size_to_mass(size)) / ramp_ticks as f32,
+                    });
+                }
                 debug!("Bot {} '{}' spawned cell {} in team {:?}", bot_id, bot.name, cell_id, team);
             }
         }
     }
 
+    /// Feed each respawned bot's spawn cell its share of the start mass ramp.
+    /// The ramp ends early if that cell is eaten or merged away.
+    fn grow_respawned_bots(&mut self) {
+        for bot in &mut self.bots.bots {
+            let Some(ramp) = bot.spawn_ramp.as_mut() else { continue };
+            let alive = bot.cells.contains(&ramp.cell);
+            if alive && let Some(cell) = self.world.get_cell_mut(ramp.cell) {
+                let data = cell.data_mut();
+                let mass = crate::collision::size_to_mass(data.size) + ramp.mass_per_tick;
+                data.set_size(crate::collision::mass_to_size(mass));
+                self.world.update_cell_position(ramp.cell);
+                ramp.ticks_left -= 1;
+                if ramp.ticks_left > 0 {
+                    continue;
+                }
+            }
+            bot.spawn_ramp = None;
+        }
+    }
+
     /// Process minion control: apply owner flags to minion bots.
     fn process_minions(&mut self) {
         // Collect minion actions from all clients
         let mut minion_targets: Vec<(u32, glam::Vec2, bool)> = Vec::new(); // (minion_id, target, frozen)
         let mut minion_splits: Vec<(u32, glam::Vec2)> = Vec::new(); // (minion_id, owner_mouse) - need mouse pos for split direction
         let mut minion_ejects: Vec<u32> = Vec::new();
+        let mut minion_feeds: Vec<(u32, glam::Vec2)> = Vec::new(); // (minion_id, owner cell center)
+        let mut fed_owners: Vec<u32> = Vec::new();
+        let min_eject_size = self.config.player.min_eject_size as f32;
+        let feed_tick = self.tick_count.is_multiple_of(MINION_FEED_INTERVAL_TICKS);
 
-        for client in self.clients.values_mut() {
+        for (&client_id, client) in self.clients.iter_mut() {
             if !client.minion_control || client.minions.is_empty() {
                 continue;
             }
@@ -2752,52 +4696,49 @@ impl GameState {
 
             let owner_mouse = glam::Vec2::new(client.mouse_x as f32, client.mouse_y as f32);
 
+            // Feeding goes into the owner's largest cell
+            let owner_largest = client.cells.iter()
+                .filter_map(|&cell_id| self.world.get_cell(cell_id))
+                .map(|cell| (cell.data().position, cell.data().size))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let mut feeding = false;
+
             for &minion_id in &client.minions {
-                if client.minion_frozen {
-                    // Frozen minions don't move — set target to current position
-                    if let Some(bot) = self.bots.get_bot(minion_id) {
-                        if let Some(&cell_id) = bot.cells.first() {
-                            if let Some(cell) = self.world.get_cell(cell_id) {
-                                minion_targets.push((minion_id, cell.data().position, true));
-                                continue;
+                let minion_cell = self.bots.get_bot(minion_id)
+                    .and_then(|bot| bot.cells.first())
+                    .and_then(|&cell_id| self.world.get_cell(cell_id))
+                    .map(|cell| (cell.data().position, cell.data().size));
+
+                // Precedence: frozen > feed > collect > follow > mouse
+                let target = match minion_cell {
+                    Some((pos, _)) if client.minion_frozen => pos,
+                    Some((pos, size)) if client.minion_feed => match owner_largest {
+                        Some((owner_pos, owner_size)) if size >= min_eject_size => {
+                            feeding = true;
+                            // Park touching the owner's cell, on the side we're already on
+                            let offset = self.world.border.delta(owner_pos, pos);
+                            let dir = offset.try_normalize().unwrap_or(glam::Vec2::X);
+                            if feed_tick && offset.length() <= owner_size + size * 2.0 {
+                                minion_feeds.push((minion_id, owner_pos));
                             }
+                            owner_pos + dir * (owner_size + size)
                         }
-                    }
-                }
-
-                if client.minion_collect {
-                    // Seek nearest food/ejected mass
-                    if let Some(bot) = self.bots.get_bot(minion_id) {
-                        if let Some(&cell_id) = bot.cells.first() {
-                            if let Some(cell) = self.world.get_cell(cell_id) {
-                                let pos = cell.data().position;
-                                let nearby = self.world.find_cells_in_radius(pos.x, pos.y, 500.0);
-                                let mut best_target = if client.minion_follow { owner_center } else { owner_mouse };
-                                let mut best_dist = f32::MAX;
-                                for &nid in &nearby {
-                                    if let Some(ncell) = self.world.get_cell(nid) {
-                                        let ndata = ncell.data();
-                                        if ndata.cell_type == crate::entity::CellType::Food || ndata.cell_type == crate::entity::CellType::EjectedMass {
-                                            let dx = ndata.position.x - pos.x;
-                                            let dy = ndata.position.y - pos.y;
-                                            let dist = dx * dx + dy * dy;
-                                            if dist < best_dist {
-                                                best_dist = dist;
-                                                best_target = ndata.position;
-                                            }
-                                        }
-                                    }
-                                }
-                                minion_targets.push((minion_id, best_target, false));
-                                continue;
-                            }
-                        }
-                    }
-                }
+                        _ => owner_center,
+                    },
+                    Some((pos, _)) if client.minion_collect => self.world
+                        .nearest_food(pos, MINION_COLLECT_RADIUS)
+                        .unwrap_or(owner_center),
+                    _ if client.minion_follow => owner_center,
+                    _ => owner_mouse,
+                };
+                minion_targets.push((minion_id, target, client.minion_frozen));
+            }
 
-                // Default: follow center or mouse
-                let target = if client.minion_follow { owner_center } else { owner_mouse };
-                minion_targets.push((minion_id, target, false));
+            // Every minion is down to minimum size: back to following
+            if client.minion_feed && !feeding && !client.minion_frozen && owner_largest.is_some() {
+                client.minion_feed = false;
+                client.minion_follow = true;
+                fed_owners.push(client_id);
             }
 
             // Collect one-shot actions
@@ -2832,15 +4773,24 @@ impl GameState {
         for minion_id in minion_ejects {
             self.handle_eject(minion_id);
         }
+
+        // Feed ejects land on the owner's cell center
+        for (minion_id, owner_pos) in minion_feeds {
+            let cell_id = self.bots.get_bot(minion_id).and_then(|bot| bot.cells.first().copied());
+            if let Some(cell_id) = cell_id {
+                self.eject_from_cell(minion_id, cell_id, owner_pos, true);
+            }
+        }
+        for owner in fed_owners {
+            self.send_server_message(owner, "Your minions are out of mass to feed; they follow you now.");
+        }
     }
 
     /// Update bot cell movement toward their targets.
     fn update_bot_movement(&mut self) {
-        // Copy border values to avoid borrow conflicts
-        let border_min_x = self.world.border.min_x;
-        let border_min_y = self.world.border.min_y;
-        let border_max_x = self.world.border.max_x;
-        let border_max_y = self.world.border.max_y;
+        // Copy the border to avoid borrow conflicts
+        let border = self.world.border;
+        let map = Arc::clone(&self.world.map);
         let speed_config = self.config.player.speed;
 
         // Collect (cell_id, target_x, target_y) tuples - avoids cloning cell vectors
@@ -2858,17 +4808,18 @@ impl GameState {
                 let data = cell.data_mut();
 
                 // Calculate direction to target
-                let dx = target_x - data.position.x;
-                let dy = target_y - data.position.y;
+                let delta = border.delta(data.position, glam::Vec2::new(target_x, target_y));
+                let (dx, dy) = (delta.x, delta.y);
                 let dist = (dx * dx + dy * dy).sqrt();
 
                 if dist < 1.0 {
                     continue;
                 }
 
-                // Calculate speed based on size
+                // Calculate speed based on size, with the speed pad multiplier
                 let base_speed = 2.2 * data.size.powf(-0.439) * 40.0;
-                let speed = base_speed * (speed_config as f32 / 30.0) * (dist.min(32.0) / 32.0);
+                let pad_mult = map.speed_multiplier(data.position);
+                let speed = base_speed * (speed_config as f32 / 30.0) * (dist.min(32.0) / 32.0) * pad_mult;
 
                 // Normalize and apply movement
                 let move_x = (dx / dist) * speed;
@@ -2877,27 +4828,117 @@ impl GameState {
                 data.position.x += move_x;
                 data.position.y += move_y;
 
-                // Clamp to border
-                data.check_border(border_min_x, border_min_y, border_max_x, border_max_y);
+                // Keep on the map and out of walls
+                border.confine_cell(data);
+                map.push_out_of_walls(data);
+                self.world.update_cell_position(cell_id);
             }
         }
     }
 
     /// Spawn initial bots based on config.
     pub fn spawn_bots(&mut self) {
-        let bot_count = self.config.server.bots;
+        // A world loaded from a dump already has its bots
+        let bot_count = self.config.server.bots.saturating_sub(self.bots.bots.len());
         if bot_count == 0 {
             return;
         }
 
         info!("Spawning {} bots", bot_count);
         for _ in 0..bot_count {
-            let bot_id = self.bots.add_bot();
+            let bot_id = self.bots.add_bot(&mut self.world.rng);
             debug!("Added bot {}", bot_id);
         }
     }
 }
 
+/// Parse `/color` arguments: `<id> <r> <g> <b>` or `<id> <#rrggbb>`.
+fn parse_color_args(args: &str) -> Option<(u32, protocol::Color)> {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let target_id = parts.first()?.parse().ok()?;
+    let color = match parts[1..] {
+        [hex] => crate::config::parse_hex_color(hex)?,
+        [r, g, b] => protocol::Color::new(r.parse().ok()?, g.parse().ok()?, b.parse().ok()?),
+        _ => return None,
+    };
+    Some((target_id, color))
+}
+
+/// Background colors of the client's dark and light themes.
+const THEME_BACKGROUNDS: [protocol::Color; 2] = [protocol::Color::new(0x11, 0x11, 0x11), protocol::Color::new(0xF2, 0xF2, 0xF2)];
+/// How far, in the most different channel, a chosen color must be from each background.
+const MIN_BACKGROUND_DIFFERENCE: u8 = 48;
+
+/// Whether `color` stands out from the background on both client themes.
+fn visible_on_backgrounds(color: protocol::Color) -> bool {
+    THEME_BACKGROUNDS.iter().all(|bg| {
+        let difference = color.r.abs_diff(bg.r).max(color.g.abs_diff(bg.g)).max(color.b.abs_diff(bg.b));
+        difference >= MIN_BACKGROUND_DIFFERENCE
+    })
+}
+
+/// Fully saturated color for the `step`th rainbow pellet, 30° of hue apart.
+fn rainbow_color(step: u32) -> protocol::Color {
+    let hue = (step % 12) as f32 * 30.0;
+    let x = 1.0 - ((hue / 60.0) % 2.0 - 1.0).abs();
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    protocol::Color::new((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+}
+
+/// A parsed `/spawn` command.
+#[derive(Debug, Clone, PartialEq)]
+enum SpawnRequest {
+    Virus { pos: Option<glam::Vec2>, size: Option<f32> },
+    Food { count: usize, pos: Option<glam::Vec2>, radius: Option<f32> },
+    MotherCell { pos: Option<glam::Vec2> },
+}
+
+/// Most pellets one `/spawn food` may place.
+const SPAWN_FOOD_MAX_COUNT: usize = 1000;
+
+/// Parse `/spawn` arguments:
+/// `virus [x y] [size]`, `food <count> [x y [radius]]` or `mothercell [x y]`.
+fn parse_spawn_args(args: &str) -> Result<SpawnRequest, &'static str> {
+    const USAGE: &str = "Usage: /spawn virus [x y] [size] | /spawn food <count> [x y [radius]] | /spawn mothercell [x y]";
+    let mut parts = args.split_whitespace();
+    let kind = parts.next().ok_or(USAGE)?;
+    let numbers: Vec<f32> = parts
+        .map(|p| p.parse::<f32>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<_>>()
+        .ok_or(USAGE)?;
+    let point = |x: f32, y: f32| Some(glam::Vec2::new(x, y));
+    let positive = |n: f32| if n > 0.0 { Ok(Some(n)) } else { Err(USAGE) };
+
+    match (kind, numbers.as_slice()) {
+        ("virus", []) => Ok(SpawnRequest::Virus { pos: None, size: None }),
+        ("virus", &[size]) => Ok(SpawnRequest::Virus { pos: None, size: positive(size)? }),
+        ("virus", &[x, y]) => Ok(SpawnRequest::Virus { pos: point(x, y), size: None }),
+        ("virus", &[x, y, size]) => Ok(SpawnRequest::Virus { pos: point(x, y), size: positive(size)? }),
+        ("food", &[count, ref rest @ ..]) => {
+            if count < 1.0 || count.fract() != 0.0 || count as usize > SPAWN_FOOD_MAX_COUNT {
+                return Err(USAGE);
+            }
+            let count = count as usize;
+            match *rest {
+                [] => Ok(SpawnRequest::Food { count, pos: None, radius: None }),
+                [x, y] => Ok(SpawnRequest::Food { count, pos: point(x, y), radius: None }),
+                [x, y, radius] => Ok(SpawnRequest::Food { count, pos: point(x, y), radius: positive(radius)? }),
+                _ => Err(USAGE),
+            }
+        }
+        ("mothercell" | "mother", []) => Ok(SpawnRequest::MotherCell { pos: None }),
+        ("mothercell" | "mother", &[x, y]) => Ok(SpawnRequest::MotherCell { pos: point(x, y) }),
+        _ => Err(USAGE),
+    }
+}
+
 /// Parse player name and skin from the join string.
 /// Format: `{skin}name` or just `name`.
 fn parse_name_and_skin(input: &str) -> (Option<String>, String) {
@@ -2911,12 +4952,73 @@ fn parse_name_and_skin(input: &str) -> (Option<String>, String) {
     (None, input.to_string())
 }
 
+/// Longest accepted local skin name.
+const MAX_SKIN_NAME_LEN: usize = 32;
+/// Longest accepted skin URL.
+const MAX_SKIN_URL_LEN: usize = 256;
+
+/// Validate a requested skin, returning `None` for anything unacceptable.
+///
+/// Local names are limited to a short charset without path separators since
+/// clients turn them into `./skins/{name}.png`. With `allow_urls`, `https://`
+/// URLs are also passed through as long as they contain no whitespace, quotes
+/// or angle brackets.
+fn sanitize_skin(skin: &str, allow_urls: bool) -> Option<String> {
+    let skin = skin.trim();
+    if skin.starts_with("https://") {
+        let ok = allow_urls
+            && skin.len() <= MAX_SKIN_URL_LEN
+            && !skin.chars().any(|c| c.is_whitespace() || c.is_control() || "\"'<>`\\".contains(c));
+        return ok.then(|| skin.to_string());
+    }
+    let name = skin.strip_prefix('%').unwrap_or(skin);
+    let ok = !name.is_empty()
+        && name.chars().count() <= MAX_SKIN_NAME_LEN
+        && !name.contains("..")
+        && name.chars().all(|c| c.is_alphanumeric() || " _-.".contains(c));
+    ok.then(|| name.to_string())
+}
+
+/// The configured bot skins that are valid local skin names; the rest are
+/// dropped with a warning.
+fn bot_skins(skins: &[String]) -> Vec<String> {
+    skins.iter()
+        .filter_map(|skin| {
+            let valid = sanitize_skin(skin, false);
+            if valid.is_none() {
+                warn!("Ignoring invalid bot skin {:?}", skin);
+            }
+            valid
+        })
+        .collect()
+}
+
+/// The game loop's ticker. A tick that runs long delays the ticks after it
+/// rather than being made up back-to-back, so physics never runs a burst of
+/// catch-up steps.
+fn game_ticker(period: Duration) -> Interval {
+    let mut ticker = interval_at(Instant::now() + period, period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker
+}
+
+#[cfg(test)]
+impl GameState {
+    /// A room on `config` for tests, with nobody listening on its channels
+    /// and no highscore file.
+    pub(crate) fn for_tests(mut config: Config) -> Self {
+        config.stats.highscores_file = String::new();
+        let (chat_tx, _) = broadcast::channel(16);
+        let (lb_tx, _) = broadcast::channel(16);
+        let (world_tx, _) = broadcast::channel(16);
+        GameState::new(&config, chat_tx, lb_tx, world_tx, TargetedSender::default())
+    }
+}
+
 /// Run the main game loop.
 pub async fn run_game_loop(state: Arc<RwLock<GameState>>, tick_interval_ms: u64) {
-    let start = Instant::now() + Duration::from_millis(tick_interval_ms);
-    let mut ticker = interval_at(start, Duration::from_millis(tick_interval_ms));
-    // Use Skip to catch up on missed ticks - ensures consistent game speed.
-    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let period = Duration::from_millis(tick_interval_ms);
+    let mut ticker = game_ticker(period);
 
     // Initial spawn
     {
@@ -2962,29 +5064,28 @@ pub async fn run_game_loop(state: Arc<RwLock<GameState>>, tick_interval_ms: u64)
             }
         }
         
-        // Drain any backlog of tick events so we always process the most recent tick.
-        // This keeps user inputs up-to-date when the server falls behind.
-        let mut skipped = 0u32;
-        while ticker.tick().now_or_never().is_some() {
-            skipped += 1;
-        }
-        if skipped > 0 {
-            debug!("Skipped {} ticks to stay current (lag: {:?})", skipped, Instant::now().saturating_duration_since(scheduled));
+        // A late tick runs once and pushes the schedule back instead of being made up
+        let lag = Instant::now().saturating_duration_since(scheduled);
+        if lag > period {
+            debug!("Tick started {:?} late", lag);
         }
         
         // Run tick and extract pending broadcasts
         let broadcasts = {
             let mut game = state.write().await;
             let tick_start = std::time::Instant::now();
+            let paused = game.paused;
             let broadcasts = game.tick();
             let tick_ms = tick_start.elapsed().as_secs_f64() * 1000.0;
             
-            // Exponential moving average (weight 0.5, matches typical server stat smoothing)
-            game.update_time_avg = game.update_time_avg * 0.5 + tick_ms * 0.5;
+            // Paused ticks do no work and would drag the average down
+            if !paused {
+                game.record_tick_time(tick_ms);
+            }
             
             // Warn if tick is too slow (>80% of tick interval = 20ms for 25ms interval)
             let tick_budget = tick_interval_ms as f64 * 0.9;
-            if tick_ms > tick_budget {
+            if !paused && tick_ms > tick_budget {
                 warn!(
                     "Slow tick #{}: {:.3}ms (budget: {:.1}ms) - {} players, {} cells total",
                     game.tick_count,
@@ -2999,10 +5100,35 @@ pub async fn run_game_loop(state: Arc<RwLock<GameState>>, tick_interval_ms: u64)
         }; // Write lock released here
         
         // Clone channel senders once with a single read lock
-        let (world_tx, lb_tx, targeted_tx) = {
+        let (world_tx, lb_tx, targeted_tx, highscores_file) = {
             let game = state.read().await;
-            (game.world_tx.clone(), game.lb_tx.clone(), game.targeted_tx.clone())
+            (game.world_tx.clone(), game.lb_tx.clone(), game.targeted_tx.clone(), game.config.stats.highscores_file.clone())
         }; // Read lock released here
+
+        // Highscore file writes never block the tick
+        if let Some(json) = broadcasts.highscores.filter(|_| !highscores_file.is_empty()) {
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = crate::highscores::save(Path::new(&highscores_file), &json) {
+                    warn!("Failed to save highscores to {}: {}", highscores_file, e);
+                }
+            });
+        }
+        if let Some((path, json)) = broadcasts.dump {
+            tokio::task::spawn_blocking(move || {
+                match crate::dump::save(&path, &json) {
+                    Ok(()) => info!("Wrote world dump to {}", path.display()),
+                    Err(e) => warn!("Failed to write world dump to {}: {}", path.display(), e),
+                }
+            });
+        }
+        if let Some((path, json)) = broadcasts.heatmap {
+            tokio::task::spawn_blocking(move || {
+                match crate::dump::save(&path, &json) {
+                    Ok(()) => info!("Wrote heatmap to {}", path.display()),
+                    Err(e) => warn!("Failed to write heatmap to {}: {}", path.display(), e),
+                }
+            });
+        }
         
         // Send all broadcasts in parallel without any locks
         let _world_task = broadcasts.world_update.map(|world_update| {
@@ -3019,9 +5145,9 @@ pub async fn run_game_loop(state: Arc<RwLock<GameState>>, tick_interval_ms: u64)
             })
         });
         
-        let _xray_task = if !broadcasts.xray_messages.is_empty() {
+        let _targeted_task = if !broadcasts.targeted_messages.is_empty() {
             let tx = targeted_tx.clone();
-            let messages = broadcasts.xray_messages;
+            let messages = broadcasts.targeted_messages;
             Some(tokio::spawn(async move {
                 for message in messages {
                     let _ = tx.send(message);
@@ -3038,8 +5164,1598 @@ pub async fn run_game_loop(state: Arc<RwLock<GameState>>, tick_interval_ms: u64)
         // if let Some(task) = lb_task {
         //     let _ = task.await;
         // }
-        // if let Some(task) = xray_task {
+        // if let Some(task) = targeted_task {
         //     let _ = task.await;
         // }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{EjectedMass, Food};
+    use crate::metrics::Channel;
+    use crate::server::{build_view_update, LagTracker};
+
+    fn test_state() -> GameState {
+        let mut config = Config::default();
+        config.food.min_amount = 0;
+        config.food.max_amount = 0;
+        config.virus.min_amount = 0;
+        config.virus.max_amount = 0;
+        GameState::for_tests(config)
+    }
+
+    #[test]
+    fn test_collecting_minion_targets_food() {
+        let mut game = test_state();
+        let client_id = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_cmd_minion(client_id, "1");
+        let minion_id = game.clients[&client_id].minions[0];
+
+        // First tick respawns the minion somewhere in the world
+        game.tick();
+        let minion_cell = game.bots.get_bot(minion_id).unwrap().cells[0];
+        let minion_pos = game.world.get_cell(minion_cell).unwrap().data().position;
+
+        let food_pos = minion_pos + glam::Vec2::new(80.0, 0.0);
+        let food_id = game.world.next_id();
+        game.world.add_food(Food::new(food_id, food_pos, 10.0, game.tick_count));
+        game.clients.get_mut(&client_id).unwrap().minion_collect = true;
+
+        game.tick();
+        assert_eq!(game.bots.get_bot(minion_id).unwrap().target, food_pos);
+
+        // Frozen takes precedence over collecting
+        game.clients.get_mut(&client_id).unwrap().minion_frozen = true;
+        game.tick();
+        let frozen_pos = game.world.get_cell(minion_cell).unwrap().data().position;
+        assert_eq!(game.bots.get_bot(minion_id).unwrap().target, frozen_pos);
+    }
+
+    #[test]
+    fn test_feeding_minions_grow_owner() {
+        let mut game = test_state();
+        let owner = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(owner);
+        game.handle_cmd_minion(owner, "5");
+        game.tick();
+
+        // A big owner holding still, five fat minions scattered around it
+        let owner_cell = game.clients[&owner].cells[0];
+        let owner_pos = glam::Vec2::ZERO;
+        let cell = game.world.get_cell_mut(owner_cell).unwrap().data_mut();
+        cell.position = owner_pos;
+        cell.set_size(300.0);
+        game.world.update_cell_position(owner_cell);
+        let client = game.clients.get_mut(&owner).unwrap();
+        (client.mouse_x, client.mouse_y) = (0, 0);
+        let minions = client.minions.clone();
+        for (i, &minion_id) in minions.iter().enumerate() {
+            let minion_cell = game.bots.get_bot(minion_id).unwrap().cells[0];
+            let angle = i as f32 * std::f32::consts::TAU / 5.0;
+            let cell = game.world.get_cell_mut(minion_cell).unwrap().data_mut();
+            cell.position = owner_pos + glam::Vec2::from_angle(angle) * 800.0;
+            cell.set_size(150.0);
+            game.world.update_cell_position(minion_cell);
+        }
+        let owner_mass = |game: &GameState| game.world.get_cell(owner_cell).unwrap().data().mass;
+        let before = owner_mass(&game);
+
+        game.handle_cmd_minion(owner, "feed");
+        assert!(game.clients[&owner].minion_feed);
+        for _ in 0..200 {
+            game.tick();
+        }
+        // Most of the minions' 5 * 225 mass ended up in the owner
+        let gained = owner_mass(&game) - before;
+        assert!(gained > 500.0, "owner gained only {}", gained);
+        for &minion_id in &minions {
+            let minion_cell = game.bots.get_bot(minion_id).unwrap().cells[0];
+            assert!(game.world.get_cell(minion_cell).unwrap().data().size < 150.0);
+        }
+        // Drained minions go back to following
+        assert!(!game.clients[&owner].minion_feed && game.clients[&owner].minion_follow);
+    }
+
+    #[test]
+    fn test_join_flood_is_bounded() {
+        let mut game = test_state();
+        game.config.server.packet_limits.join_per_second = 0.0;
+        // Five connections from one address, dying and rejoining nonstop
+        let connect = |game: &mut GameState, port: u16| {
+            let id = game.add_client(format!("10.0.0.1:{}", port).parse().unwrap());
+            let client = game.clients.get_mut(&id).unwrap();
+            client.handshake_complete = true;
+            client.protocol = 6;
+            id
+        };
+        let flooders: Vec<u32> = (0..5).map(|i| connect(&mut game, 1000 + i)).collect();
+        let join = protocol::packets::build_join(6, "spam").finish();
+        let mut spawns = 0;
+        for _ in 0..40 {
+            for &id in &flooders {
+                game.handle_packet(id, &join).unwrap();
+                if !game.clients[&id].cells.is_empty() {
+                    spawns += 1;
+                    game.handle_cmd_kill(id, &id.to_string());
+                }
+            }
+            game.tick();
+        }
+        // The address's spawns for the minute, however many connections share it
+        assert_eq!(spawns, game.config.server.packet_limits.spawns_per_minute_per_ip);
+        assert!(game.world.player_cells.is_empty());
+        // Another address is unaffected
+        let other = game.add_client("10.0.0.2:1000".parse().unwrap());
+        game.handle_join(other, "ok".to_string()).unwrap();
+        assert_eq!(game.clients[&other].cells.len(), 1);
+
+        // With the per-client rate on, a join loop gets disconnected
+        game.config.server.packet_limits.join_per_second = 2.0;
+        let flooder = connect(&mut game, 2000);
+        let mut targeted_rx = game.targeted_tx.register(flooder);
+        for _ in 0..200 {
+            game.handle_packet(flooder, &join).unwrap();
+        }
+        let mut disconnected = false;
+        loop {
+            match targeted_rx.try_recv() {
+                Ok(msg) => disconnected |= msg.client_id == flooder && matches!(msg.message, TargetedMessageType::Disconnect { .. }),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+        assert!(disconnected);
+    }
+
+    #[test]
+    fn test_chat_log_records_chat_commands_and_drops() {
+        let mut game = test_state();
+        game.config.server.operator_password = "hunter2".to_string();
+        // A burst of one chat message; commands don't count
+        game.config.server.packet_limits.chat_per_second = 1.0;
+        let (tx, mut rx) = mpsc::channel(16);
+        game.attach_chat_log(tx, "/ffa".to_string());
+        let id = game.add_client("10.0.0.1:1000".parse().unwrap());
+        let client = game.clients.get_mut(&id).unwrap();
+        client.handshake_complete = true;
+        client.protocol = 6;
+        client.name = "Bob".to_string();
+
+        for message in ["/operator hunter2", "oops hunter2", "too fast"] {
+            game.handle_packet(id, &protocol::packets::build_chat(6, message).finish()).unwrap();
+        }
+        assert!(game.clients[&id].is_operator);
+
+        let entries: Vec<ChatLogEntry> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let logged: Vec<(ChatLogKind, &str)> = entries.iter().map(|e| (e.kind, e.text.as_str())).collect();
+        assert_eq!(logged, vec![
+            (ChatLogKind::Command { operator: false }, "/operator [redacted]"),
+            (ChatLogKind::Chat, "oops [redacted]"),
+            (ChatLogKind::Dropped, "too fast"),
+        ]);
+        assert!(entries.iter().all(|e| e.room == "/ffa" && e.client_id == id && e.name == "Bob"));
+        assert_eq!(entries[0].ip, "10.0.0.1".parse::<std::net::IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_minion_cap_applies_across_invocations() {
+        let mut game = test_state();
+        game.config.player.max_minions = 12;
+        let client_id = game.add_client("127.0.0.1:1".parse().unwrap());
+
+        game.handle_cmd_minion(client_id, "10");
+        game.handle_cmd_minion(client_id, "10");
+        assert_eq!(game.clients[&client_id].minions.len(), 12);
+
+        // At the cap nothing more is added
+        game.spawn_default_minions(client_id, 5);
+        assert_eq!(game.clients[&client_id].minions.len(), 12);
+        assert_eq!(game.bots.bots.len(), 12);
+    }
+
+    #[test]
+    fn test_minion_rename_renumbers() {
+        let mut game = test_state();
+        let client_id = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_cmd_minion(client_id, "3");
+
+        game.handle_cmd_minion(client_id, "name Swarm Unit");
+        let names: Vec<String> = game.clients[&client_id].minions.iter()
+            .map(|id| game.bots.get_bot(*id).unwrap().name.clone())
+            .collect();
+        assert_eq!(names, ["Swarm Unit 1", "Swarm Unit 2", "Swarm Unit 3"]);
+
+        // New minions keep the custom name and continue the numbering
+        game.handle_cmd_minion(client_id, "1");
+        let newest = *game.clients[&client_id].minions.last().unwrap();
+        assert_eq!(game.bots.get_bot(newest).unwrap().name, "Swarm Unit 4");
+    }
+
+    #[test]
+    fn test_sanitize_skin() {
+        assert_eq!(sanitize_skin("doge", false).as_deref(), Some("doge"));
+        assert_eq!(sanitize_skin("%2ch.hk", false).as_deref(), Some("2ch.hk"));
+        assert_eq!(sanitize_skin("../secret", false), None);
+        assert_eq!(sanitize_skin("a/b", false), None);
+        assert_eq!(sanitize_skin("<img>", false), None);
+
+        let url = "https://i.imgur.com/abc.png";
+        assert_eq!(sanitize_skin(url, false), None);
+        assert_eq!(sanitize_skin(url, true).as_deref(), Some(url));
+        assert_eq!(sanitize_skin("http://i.imgur.com/abc.png", true), None);
+        assert_eq!(sanitize_skin("https://x.com/\"onerror=", true), None);
+    }
+
+    #[test]
+    fn test_spectate_target_follows_player() {
+        let mut game = test_state();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        let viewer = game.add_client("127.0.0.1:2".parse().unwrap());
+        game.spawn_player(player);
+
+        // Not spectating yet: ignored
+        game.handle_spectate_target(viewer, player);
+        assert_eq!(game.clients[&viewer].spectate_target, None);
+
+        game.clients.get_mut(&viewer).unwrap().is_spectating = true;
+        game.handle_spectate_target(viewer, player);
+        assert_eq!(game.clients[&viewer].spectate_target, Some(player));
+
+        game.update_spectator_follow();
+        let cell_id = game.clients[&player].cells[0];
+        let pos = game.world.get_cell(cell_id).unwrap().data().position;
+        assert_eq!((game.clients[&viewer].center_x, game.clients[&viewer].center_y), (pos.x, pos.y));
+
+        // Target dies: the viewer is handed to the leader and told so
+        let mut targeted_rx = game.targeted_tx.register(viewer);
+        let small = game.add_client("127.0.0.1:3".parse().unwrap());
+        let leader = game.add_client("127.0.0.1:4".parse().unwrap());
+        game.clients.get_mut(&leader).unwrap().name = "Leader".to_string();
+        game.spawn_player(small);
+        game.spawn_player(leader);
+        let leader_cell = game.clients[&leader].cells[0];
+        game.world.get_cell_mut(leader_cell).unwrap().data_mut().set_size(500.0);
+        game.clients.get_mut(&player).unwrap().cells.clear();
+        game.update_spectator_follow();
+        assert_eq!(game.clients[&viewer].spectate_target, Some(leader));
+        let notice = targeted_rx.try_recv().unwrap();
+        assert!(matches!(notice.message, TargetedMessageType::ChatMessage { ref message, .. } if message.contains("Leader")));
+
+        // Asking for a dead player (a killer who died since) gets the leader too
+        game.clients.get_mut(&viewer).unwrap().spectate_target = None;
+        game.handle_spectate_target(viewer, player);
+        assert_eq!(game.clients[&viewer].spectate_target, Some(leader));
+
+        // With nobody left alive the follow is dropped
+        game.clients.get_mut(&small).unwrap().cells.clear();
+        game.clients.get_mut(&leader).unwrap().cells.clear();
+        game.update_spectator_follow();
+        assert_eq!(game.clients[&viewer].spectate_target, None);
+    }
+
+    #[test]
+    fn test_shot_virus_pops_large_bot_in_experimental() {
+        use crate::entity::{EjectedMass, Virus};
+
+        for (gamemode, pops) in [(0, false), (2, true)] {
+            let mut game = test_state();
+            game.gamemode = crate::gamemodes::get_gamemode(gamemode);
+            game.config.virus.max_amount = 10;
+            let bot_id = game.bots.add_bot(&mut game.world.rng);
+            game.tick();
+            let bot_cell = game.bots.get_bot(bot_id).unwrap().cells[0];
+            // Ejects only become edible after a short grace
+            game.tick_count += 10;
+
+            // Feed a virus until it shoots a new one
+            let virus_pos = glam::Vec2::new(3000.0, 3000.0);
+            let virus_id = game.world.next_id();
+            game.world.add_virus(Virus::new(virus_id, virus_pos, game.config.virus.min_size as f32, 0));
+            let shot = (0..50).find_map(|_| {
+                let eject_id = game.world.next_id();
+                game.world.add_eject(EjectedMass::new(eject_id, virus_pos, 38.0, 0));
+                game.world.add_moving(eject_id);
+                game.process_collisions();
+                game.world.virus_cells.iter().copied().find(|&id| id != virus_id)
+            }).expect("virus never shot");
+            assert!(game.world.is_moving(shot));
+
+            // A large stationary bot cell just touching the shot virus
+            let shot_size = game.world.get_cell(shot).unwrap().data().size;
+            let shot_pos = game.world.get_cell(shot).unwrap().data().position;
+            if let Some(cell) = game.world.get_cell_mut(bot_cell) {
+                let data = cell.data_mut();
+                data.set_size(400.0);
+                data.position = shot_pos + glam::Vec2::new(400.0 + shot_size * 0.5, 0.0);
+            }
+            game.world.update_cell_position(bot_cell);
+            game.process_collisions();
+
+            assert_eq!(game.world.get_cell(shot).is_none(), pops);
+            assert_eq!(game.bots.get_bot(bot_id).unwrap().cells.len() > 1, pops);
+        }
+    }
+
+    #[test]
+    fn test_overgrown_virus_shoots_along_feed_direction() {
+        use crate::entity::{EjectedMass, Virus};
+
+        for (growth_factor, feeds) in [(1.0, 8), (2.0, 4)] {
+            let mut game = test_state();
+            game.config.virus.max_amount = 2;
+            game.config.virus.growth_factor = growth_factor;
+            game.tick_count = 10;
+            let virus_pos = glam::Vec2::new(3000.0, 3000.0);
+            let virus_id = game.world.next_id();
+            game.world.add_virus(Virus::new(virus_id, virus_pos, game.config.virus.min_size as f32, 0));
+
+            let feed = |game: &mut GameState| {
+                let eject_id = game.world.next_id();
+                let mut eject = EjectedMass::new(eject_id, virus_pos, game.config.eject.size as f32, 0);
+                eject.data_mut().set_boost_direction(50.0, glam::Vec2::X);
+                game.world.add_eject(eject);
+                game.world.add_moving(eject_id);
+                game.process_collisions();
+            };
+            let mut sizes = Vec::new();
+            for _ in 0..feeds {
+                feed(&mut game);
+                sizes.push(game.world.get_cell(virus_id).unwrap().data().size);
+            }
+            // Grows with every pellet, then shoots and shrinks on the last one
+            assert!(sizes[..feeds - 1].windows(2).all(|w| w[1] > w[0]));
+            assert_eq!(sizes[feeds - 1], game.config.virus.min_size as f32);
+            let shot = *game.world.virus_cells.iter().find(|&&id| id != virus_id).unwrap();
+            let boost = game.world.get_cell(shot).unwrap().data().boost.unwrap();
+            assert_eq!(boost.direction, glam::Vec2::X);
+
+            // At virus.max_amount pellets aren't eaten and nothing more is shot
+            for _ in 0..feeds {
+                feed(&mut game);
+            }
+            assert_eq!(game.world.virus_cells.len(), 2);
+            assert_eq!(game.world.get_cell(virus_id).unwrap().data().size, game.config.virus.min_size as f32);
+        }
+    }
+
+    #[test]
+    fn test_max_size_splits_or_caps() {
+        let total_mass = |game: &GameState, id: u32| -> f32 {
+            game.clients[&id].cells.iter()
+                .map(|&c| crate::collision::size_to_mass(game.world.get_cell(c).unwrap().data().size))
+                .sum()
+        };
+        for (behavior, max_cells, expected_cells) in [
+            (MaxSizeBehavior::Split, 16, 8),
+            (MaxSizeBehavior::Split, 3, 3),
+            (MaxSizeBehavior::Cap, 16, 1),
+        ] {
+            let mut game = test_state();
+            game.config.player.max_size = 1000.0;
+            game.config.player.max_size_behavior = behavior;
+            game.config.player.max_cells = max_cells;
+            let player = game.add_client("127.0.0.1:1".parse().unwrap());
+            game.spawn_player(player);
+            let cell = game.clients[&player].cells[0];
+            // 2.5x the cap: three rounds of halving get every piece under it
+            game.world.get_cell_mut(cell).unwrap().data_mut().set_size(2500.0);
+            let mass = total_mass(&game, player);
+
+            game.process_collisions();
+            let cells = &game.clients[&player].cells;
+            assert_eq!(cells.len(), expected_cells, "{:?} with max_cells {}", behavior, max_cells);
+            assert!(cells.iter().all(|&c| game.world.get_cell(c).unwrap().data().size <= 1000.0));
+            if expected_cells == 8 {
+                assert!((total_mass(&game, player) - mass).abs() < 1.0);
+            }
+        }
+
+        // /mass is held to the cap too
+        let mut game = test_state();
+        game.config.player.max_size_behavior = MaxSizeBehavior::Cap;
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(player);
+        game.handle_cmd_mass(player, "500000");
+        let cell = game.clients[&player].cells[0];
+        assert_eq!(game.world.get_cell(cell).unwrap().data().size, game.config.player.max_size as f32);
+    }
+
+    #[test]
+    fn test_beatdown_streak_widens_view() {
+        let mut game = test_state();
+        game.gamemode = crate::gamemodes::get_gamemode(6);
+        let mut chat_rx = game.chat_tx.subscribe();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.clients.get_mut(&player).unwrap().handshake_complete = true;
+        game.spawn_player(player);
+        let scale = |game: &mut GameState| game.prepare_world_broadcast().0.client_data[&player].scale;
+        let base = scale(&mut game);
+
+        game.deaths_this_tick.extend([(player, 900), (player, 901), (player, 902)]);
+        game.process_deaths();
+        assert!(scale(&mut game) < base, "a wider view means a smaller scale");
+        assert!((game.gamemode.get_speed_multiplier(player) - 1.3).abs() < 1e-6);
+        assert!(chat_rx.try_recv().unwrap().message.contains("3-kill streak"));
+
+        // Dying ends the streak
+        game.deaths_this_tick.push((900, player));
+        game.process_deaths();
+        assert_eq!(scale(&mut game), base);
+        assert_eq!(game.gamemode.get_speed_multiplier(player), 1.0);
+    }
+
+    #[test]
+    fn test_rename_reaches_every_viewer() {
+        let mut game = test_state();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_join(player, "Alice".to_string()).unwrap();
+        let viewers = [
+            game.add_client("127.0.0.1:2".parse().unwrap()),
+            game.add_client("127.0.0.1:3".parse().unwrap()),
+        ];
+        for &viewer in &viewers {
+            let client = game.clients.get_mut(&viewer).unwrap();
+            client.handshake_complete = true;
+            client.protocol = 6;
+            client.is_spectating = true;
+            client.spectate_target = Some(player);
+        }
+
+        // Both viewers learn the cell under its first name
+        let mut nodes = [HashSet::new(), HashSet::new()];
+        let (world, _) = game.prepare_world_broadcast();
+        for (viewer, known) in viewers.iter().zip(nodes.iter_mut()) {
+            build_view_update(&world, &world.client_data[viewer], known, false);
+            assert!(known.contains(&game.clients[&player].cells[0]));
+        }
+
+        // Renaming while alive resends the name to viewers that already know the cell
+        game.handle_join(player, "Bob".to_string()).unwrap();
+        let (world, _) = game.prepare_world_broadcast();
+        for (viewer, known) in viewers.iter().zip(nodes.iter_mut()) {
+            let packet = build_view_update(&world, &world.client_data[viewer], known, false).finish();
+            assert!(packet.windows(3).any(|w| w == b"Bob"));
+        }
+
+        // Only once: the next broadcast is back to position-only updates
+        let (world, _) = game.prepare_world_broadcast();
+        let packet = build_view_update(&world, &world.client_data[&viewers[0]], &mut nodes[0], false).finish();
+        assert!(!packet.windows(3).any(|w| w == b"Bob"));
+    }
+
+    #[test]
+    fn test_recycled_ids_never_reuse_a_visible_cell() {
+        let mut game = test_state();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_join(player, "Alice".to_string()).unwrap();
+        game.clients.get_mut(&player).unwrap().frozen = true;
+        let center = game.world.get_cell(game.clients[&player].cells[0]).unwrap().data().position;
+        let viewer = game.add_client("127.0.0.1:2".parse().unwrap());
+        let client = game.clients.get_mut(&viewer).unwrap();
+        client.handshake_complete = true;
+        client.protocol = 6;
+        client.is_spectating = true;
+        client.spectate_target = Some(player);
+
+        // Ejects churn next to the player, well inside the viewer's view.
+        // The viewer's connection runs as far behind as the world channel
+        // allows before it would be resynced.
+        let mut known = HashSet::new();
+        let mut in_flight = std::collections::VecDeque::new();
+        let mut ejects = std::collections::VecDeque::new();
+        let mut highest = 0;
+        let mut recycled = 0;
+        for _ in 0..200 {
+            while ejects.len() > 20 {
+                game.world.remove_cell(ejects.pop_front().unwrap());
+            }
+            for i in 0..5 {
+                let id = game.world.next_id();
+                assert!(!known.contains(&id), "id {} handed out while the viewer still sees it", id);
+                if id < highest {
+                    recycled += 1;
+                }
+                highest = highest.max(id);
+                let pos = center + glam::Vec2::new(300.0 + 50.0 * i as f32, 0.0);
+                game.world.add_eject(crate::entity::EjectedMass::new(id, pos, 20.0, game.tick_count));
+                ejects.push_back(id);
+            }
+            in_flight.push_back(game.tick().world_update.unwrap());
+            if in_flight.len() == 5 {
+                let world = in_flight.pop_front().unwrap();
+                build_view_update(&world, &world.client_data[&viewer], &mut known, false);
+            }
+        }
+        assert!(recycled > 500);
+    }
+
+    #[test]
+    fn test_ejects_expire_and_are_capped() {
+        let mut game = test_state();
+        game.config.eject.lifetime_ticks = 50;
+        let food = game.world.next_id();
+        game.world.add_food(Food::new(food, glam::Vec2::new(-5000.0, -5000.0), 10.0, 0));
+        game.tick();
+        let baseline = game.world.cells.len();
+
+        let mut rng = rand::rng();
+        for _ in 0..10_000 {
+            let id = game.world.next_id();
+            let pos = glam::Vec2::new(rng.random_range(-6000.0..6000.0), rng.random_range(-6000.0..6000.0));
+            game.world.add_eject(EjectedMass::new(id, pos, 20.0, game.tick_count));
+        }
+        let mut removed = 0;
+        for _ in 0..50 {
+            removed += game.tick().world_update.unwrap().removed.len();
+        }
+        assert_eq!(game.world.cells.len(), baseline);
+        assert_eq!(removed, 10_000);
+        assert!(game.world.get_cell(food).is_some());
+
+        // Past max_amount the oldest pellets go first
+        game.config.eject.lifetime_ticks = 0;
+        game.config.eject.max_amount = 100;
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            for i in 0..60 {
+                let id = game.world.next_id();
+                game.world.add_eject(EjectedMass::new(id, glam::Vec2::new(-6000.0 + 20.0 * i as f32, 6000.0), 20.0, game.tick_count));
+                ids.push(id);
+            }
+            game.tick();
+        }
+        let mut left = game.world.eject_cells.clone();
+        left.sort_unstable();
+        let mut newest = ids[80..].to_vec();
+        newest.sort_unstable();
+        assert_eq!(left, newest);
+    }
+
+    #[test]
+    fn test_scramble_is_per_client() {
+        let mut game = test_state();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_join(player, "Alice".to_string()).unwrap();
+        let viewers = [
+            game.add_client("127.0.0.1:2".parse().unwrap()),
+            game.add_client("127.0.0.1:3".parse().unwrap()),
+        ];
+        for &viewer in &viewers {
+            let client = game.clients.get_mut(&viewer).unwrap();
+            client.handshake_complete = true;
+            client.protocol = 6;
+            client.is_spectating = true;
+            client.spectate_target = Some(player);
+        }
+        let cell_id = game.clients[&player].cells[0];
+        let position = game.world.get_cell(cell_id).unwrap().data().position;
+
+        let (world, _) = game.prepare_world_broadcast();
+        let mut raw = Vec::new();
+        for viewer in viewers {
+            let client = &game.clients[&viewer];
+            let packet = build_view_update(&world, &world.client_data[&viewer], &mut HashSet::new(), false).finish();
+            let Ok(protocol::packets::ServerPacket::UpdateNodes(update)) = protocol::packets::ServerPacket::parse(&packet, 6) else {
+                panic!("expected UpdateNodes");
+            };
+            let node = update.nodes.iter().find(|n| n.node_id ^ client.scramble_id == cell_id).unwrap();
+            // Unscrambled with the same offsets as the client's border, the cell is where the world has it
+            assert_eq!(node.x as i32 - client.scramble_x, position.x as i32);
+            assert_eq!(node.y as i32 - client.scramble_y, position.y as i32);
+            raw.push((node.node_id, node.x, node.y));
+        }
+        assert_ne!(raw[0], raw[1]);
+
+        let limits = &game.config.server.packet_limits;
+        let addr = "127.0.0.1:4".parse().unwrap();
+        let off = Client::new(9, addr, protocol::Color::new(0, 0, 0), limits, 0);
+        assert_eq!((off.scramble_id, off.scramble_x, off.scramble_y), (0, 0, 0));
+        let ids_only = Client::new(9, addr, protocol::Color::new(0, 0, 0), limits, 1);
+        assert_eq!((ids_only.scramble_x, ids_only.scramble_y), (0, 0));
+    }
+
+    #[test]
+    fn test_lagged_consumer_resyncs_missed_world_updates() {
+        let mut game = test_state();
+        let (world_tx, mut world_rx) = broadcast::channel(2);
+        game.world_tx = world_tx.clone();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_join(player, "Alice".to_string()).unwrap();
+        let viewer = game.add_client("127.0.0.1:2".parse().unwrap());
+        let client = game.clients.get_mut(&viewer).unwrap();
+        client.handshake_complete = true;
+        client.protocol = 6;
+        client.is_spectating = true;
+        client.spectate_target = Some(player);
+        let addr = "127.0.0.1:2".parse().unwrap();
+        let mut lag = LagTracker::default();
+        let mut known = HashSet::new();
+
+        let (world, _) = game.prepare_world_broadcast();
+        world_tx.send(world).unwrap();
+        let world = world_rx.try_recv().unwrap();
+        build_view_update(&world, &world.client_data[&viewer], &mut known, lag.take_resync());
+        assert!(known.contains(&game.clients[&player].cells[0]));
+
+        // The consumer stalls while the rename goes out and the channel overflows
+        game.handle_join(player, "Bob".to_string()).unwrap();
+        for _ in 0..4 {
+            let (world, _) = game.prepare_world_broadcast();
+            world_tx.send(world).unwrap();
+        }
+        let missed = world_rx.try_recv().map_err(|e| match e {
+            broadcast::error::TryRecvError::Lagged(n) => broadcast::error::RecvError::Lagged(n),
+            _ => panic!("expected lag, got {:?}", e),
+        });
+        lag.observe(Channel::World, &missed, &game.metrics, addr);
+        assert_eq!(lag.missed, 2);
+
+        // The next update it gets resends the cell in full, with its new name
+        let world = world_rx.try_recv().unwrap();
+        let packet = build_view_update(&world, &world.client_data[&viewer], &mut known, lag.take_resync()).finish();
+        assert!(packet.windows(3).any(|w| w == b"Bob"));
+        assert!(!lag.take_resync());
+    }
+
+    #[test]
+    fn test_reconnect_within_grace_reclaims_cells() {
+        let mut game = test_state();
+        game.config.player.reconnect_grace_seconds = 5.0;
+        let old = game.add_client("10.0.0.1:1000".parse().unwrap());
+        game.handle_join(old, "Alice".to_string()).unwrap();
+        let cell_id = game.clients[&old].cells[0];
+
+        game.disconnect_client(old);
+        assert!(!game.clients.contains_key(&old));
+        assert!(game.world.get_cell(cell_id).is_some());
+
+        // Same IP, different name: a fresh spawn
+        let other = game.add_client("10.0.0.1:1001".parse().unwrap());
+        game.handle_join(other, "Bob".to_string()).unwrap();
+        assert!(!game.clients[&other].cells.contains(&cell_id));
+
+        // Different IP, same name: also a fresh spawn
+        let stranger = game.add_client("10.0.0.2:1000".parse().unwrap());
+        game.handle_join(stranger, "Alice".to_string()).unwrap();
+        assert!(!game.clients[&stranger].cells.contains(&cell_id));
+
+        let new = game.add_client("10.0.0.1:1002".parse().unwrap());
+        game.handle_join(new, "Alice".to_string()).unwrap();
+        assert_eq!(game.clients[&new].cells, vec![cell_id]);
+        assert_eq!(game.world.get_cell(cell_id).unwrap().data().owner_id, Some(new));
+    }
+
+    #[test]
+    fn test_join_and_leave_are_announced() {
+        let mut game = test_state();
+        let mut chat_rx = game.chat_tx.subscribe();
+        let notices = |chat_rx: &mut broadcast::Receiver<ChatBroadcast>| {
+            std::iter::from_fn(|| chat_rx.try_recv().ok()).map(|c| c.message).collect::<Vec<_>>()
+        };
+
+        let alice = game.add_client("10.0.0.1:1000".parse().unwrap());
+        game.handle_join(alice, "Alice".to_string()).unwrap();
+        assert_eq!(notices(&mut chat_rx), ["Alice joined"]);
+
+        // Minions are bots and never announced; rejoining after death isn't a join
+        game.handle_cmd_minion(alice, "2");
+        game.tick();
+        for cell in std::mem::take(&mut game.clients.get_mut(&alice).unwrap().cells) {
+            game.world.remove_cell(cell);
+        }
+        game.tick_count += 30_000 / game.config.server.tick_interval_ms;
+        game.handle_join(alice, "Alice".to_string()).unwrap();
+        assert!(notices(&mut chat_rx).is_empty());
+
+        let cell = game.clients[&alice].cells[0];
+        game.world.get_cell_mut(cell).unwrap().data_mut().mass = 3_200.0;
+        game.remove_client(alice);
+        assert_eq!(notices(&mut chat_rx), ["Alice left the game (mass 3,200)"]);
+
+        // A reconnect loop from the same IP stays quiet for the cooldown
+        for port in 1001..1005 {
+            let id = game.add_client(format!("10.0.0.1:{}", port).parse().unwrap());
+            game.handle_join(id, "Alice".to_string()).unwrap();
+            game.remove_client(id);
+        }
+        assert!(notices(&mut chat_rx).is_empty());
+
+        // Unnamed and dead players come and go silently
+        let unnamed = game.add_client("10.0.0.2:1000".parse().unwrap());
+        game.handle_join(unnamed, String::new()).unwrap();
+        game.remove_client(unnamed);
+        let spectator = game.add_client("10.0.0.3:1000".parse().unwrap());
+        game.clients.get_mut(&spectator).unwrap().name = "Carol".to_string();
+        game.remove_client(spectator);
+        assert!(notices(&mut chat_rx).is_empty());
+
+        // Switched off, nothing is announced
+        game.config.announcements.join_leave = false;
+        let bob = game.add_client("10.0.0.4:1000".parse().unwrap());
+        game.handle_join(bob, "Bob".to_string()).unwrap();
+        game.remove_client(bob);
+        assert!(notices(&mut chat_rx).is_empty());
+    }
+
+    #[test]
+    fn test_teams_stay_balanced_over_joins_and_leaves() {
+        let mut game = test_state();
+        game.gamemode = crate::gamemodes::get_gamemode(1);
+        let imbalance = |game: &GameState| {
+            let mut sizes = game.team_sizes();
+            sizes.resize(3, 0);
+            sizes.iter().max().unwrap() - sizes.iter().min().unwrap()
+        };
+
+        for _ in 0..6 {
+            game.bots.add_bot(&mut game.world.rng);
+        }
+        game.process_bot_respawns();
+        assert_eq!(game.team_sizes(), [2, 2, 2]);
+        for i in 0..9 {
+            let id = game.add_client(format!("127.0.0.1:{}", 1000 + i).parse().unwrap());
+            game.handle_join(id, format!("p{}", i)).unwrap();
+            assert!(imbalance(&game) <= 1);
+        }
+
+        // Emptying a team of players pulls bots over to it
+        for team in [0, 1] {
+            let leaving: Vec<u32> = game.clients.values().filter(|c| c.team == Some(team)).map(|c| c.id).collect();
+            for id in leaving {
+                game.remove_client(id);
+                game.tick();
+                assert!(imbalance(&game) <= 1, "teams {:?}", game.team_sizes());
+            }
+        }
+        for i in 0..4 {
+            let id = game.add_client(format!("127.0.0.1:{}", 2000 + i).parse().unwrap());
+            game.handle_join(id, format!("q{}", i)).unwrap();
+            assert!(imbalance(&game) <= 1);
+        }
+    }
+
+    #[test]
+    fn test_eject_color_choices() {
+        let mut game = test_state();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(player);
+        game.handle_cmd_mass(player, "2000");
+        let cell_color = game.world.get_cell(game.clients[&player].cells[0]).unwrap().data().color;
+        let eject_colors = |game: &mut GameState, count: usize| -> Vec<protocol::Color> {
+            (0..count)
+                .map(|_| {
+                    let cell_id = game.clients[&player].cells[0];
+                    game.eject_from_cell(player, cell_id, glam::Vec2::ZERO, false);
+                    game.world.get_cell(*game.world.eject_cells.last().unwrap()).unwrap().data().color
+                })
+                .collect()
+        };
+
+        assert_eq!(eject_colors(&mut game, 1), [cell_color]);
+
+        let teal = protocol::Color::new(0, 128, 128);
+        game.handle_eject_color(player, EjectColor::Solid(teal));
+        assert_eq!(eject_colors(&mut game, 2), [teal, teal]);
+
+        // Near-black and near-white would disappear on one of the themes
+        for hidden in [protocol::Color::new(20, 20, 20), protocol::Color::new(250, 250, 250)] {
+            game.handle_eject_color(player, EjectColor::Solid(hidden));
+            assert_eq!(game.clients[&player].eject_color, EjectColor::Cell);
+        }
+
+        game.handle_eject_color(player, EjectColor::Rainbow);
+        let rainbow = eject_colors(&mut game, 3);
+        assert!(rainbow[0] != rainbow[1] && rainbow[1] != rainbow[2]);
+        assert!((0..12).all(|step| visible_on_backgrounds(rainbow_color(step))));
+    }
+
+    #[test]
+    fn test_afk_player_is_warned_spectated_then_disconnected() {
+        let mut game = test_state();
+        game.config.server.afk_timeout_seconds = 60;
+        let id = game.add_client("127.0.0.1:1".parse().unwrap());
+        let mut targeted_rx = game.targeted_tx.register(id);
+        game.clients.get_mut(&id).unwrap().handshake_complete = true;
+        game.handle_join(id, "Idle".to_string()).unwrap();
+        let idle_for = |game: &mut GameState, secs: u64| {
+            let client = game.clients.get_mut(&id).unwrap();
+            client.last_activity = std::time::Instant::now() - std::time::Duration::from_secs(secs);
+        };
+        while targeted_rx.try_recv().is_ok() {}
+
+        idle_for(&mut game, 59);
+        game.check_afk_clients();
+        assert!(!game.clients[&id].afk_warned);
+
+        idle_for(&mut game, 61);
+        game.check_afk_clients();
+        assert!(game.clients[&id].afk_warned);
+        assert!(!game.clients[&id].cells.is_empty());
+        assert!(matches!(targeted_rx.try_recv().unwrap().message, TargetedMessageType::ChatMessage { .. }));
+
+        idle_for(&mut game, 60 + AFK_WARNING_SECS);
+        game.check_afk_clients();
+        assert!(game.clients[&id].cells.is_empty());
+        assert!(game.clients[&id].is_spectating);
+        targeted_rx.try_recv().unwrap();
+
+        idle_for(&mut game, 120 + AFK_WARNING_SECS);
+        game.check_afk_clients();
+        let msg = targeted_rx.try_recv().unwrap();
+        assert_eq!(msg.client_id, id);
+        assert!(matches!(msg.message, TargetedMessageType::Disconnect { .. }));
+
+        // Operators and a disabled timeout are left alone
+        game.clients.get_mut(&id).unwrap().is_operator = true;
+        game.check_afk_clients();
+        game.clients.get_mut(&id).unwrap().is_operator = false;
+        game.config.server.afk_timeout_seconds = 0;
+        game.check_afk_clients();
+        assert!(targeted_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_rename_and_color_commands() {
+        let mut game = test_state();
+        let op = game.add_client("127.0.0.1:1".parse().unwrap());
+        let player = game.add_client("127.0.0.1:2".parse().unwrap());
+        game.handle_join(player, "Rude".to_string()).unwrap();
+        game.config.player.max_nick_length = 5;
+
+        game.handle_cmd_rename(op, &format!("{} Polite Name", player));
+        assert_eq!(game.clients[&player].name, "Polit");
+        assert!(game.identity_changed.contains(&player));
+
+        game.handle_cmd_color(op, &format!("{} #ff8000", player));
+        let orange = protocol::Color::new(255, 128, 0);
+        assert_eq!(game.clients[&player].color, orange);
+        let cell_id = game.clients[&player].cells[0];
+        assert_eq!(game.world.get_cell(cell_id).unwrap().data().color, orange);
+
+        assert_eq!(parse_color_args("7 1 2 3"), Some((7, protocol::Color::new(1, 2, 3))));
+        assert_eq!(parse_color_args("7 1 2 300"), None);
+        assert_eq!(parse_color_args("7"), None);
+    }
+
+    #[test]
+    fn test_parse_spawn_args() {
+        use glam::Vec2;
+        assert_eq!(parse_spawn_args("virus"), Ok(SpawnRequest::Virus { pos: None, size: None }));
+        assert_eq!(parse_spawn_args("virus 150"), Ok(SpawnRequest::Virus { pos: None, size: Some(150.0) }));
+        assert_eq!(parse_spawn_args("virus 10 -20"), Ok(SpawnRequest::Virus { pos: Some(Vec2::new(10.0, -20.0)), size: None }));
+        assert_eq!(parse_spawn_args("virus 10 -20 150"), Ok(SpawnRequest::Virus { pos: Some(Vec2::new(10.0, -20.0)), size: Some(150.0) }));
+        assert_eq!(parse_spawn_args("food 5"), Ok(SpawnRequest::Food { count: 5, pos: None, radius: None }));
+        assert_eq!(parse_spawn_args("food 5 1 2"), Ok(SpawnRequest::Food { count: 5, pos: Some(Vec2::new(1.0, 2.0)), radius: None }));
+        assert_eq!(parse_spawn_args("food 5 1 2 300"), Ok(SpawnRequest::Food { count: 5, pos: Some(Vec2::new(1.0, 2.0)), radius: Some(300.0) }));
+        assert_eq!(parse_spawn_args("mothercell 0 0"), Ok(SpawnRequest::MotherCell { pos: Some(Vec2::ZERO) }));
+
+        for bad in ["", "virus 1 2 3 4", "virus -5", "virus x", "virus nan", "food", "food 0", "food 2.5", "food 5 1", "food 99999", "mothercell 1", "cake"] {
+            assert!(parse_spawn_args(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn test_spawn_and_clear_commands() {
+        let mut game = test_state();
+        game.config.food.max_amount = 3;
+        let op = game.add_client("127.0.0.1:1".parse().unwrap());
+
+        game.handle_cmd_spawn(op, "food 5 100 100 50");
+        assert_eq!(game.world.food_cells.len(), 3);
+        for &id in &game.world.food_cells {
+            assert!(game.world.get_cell(id).unwrap().data().position.distance(glam::Vec2::new(100.0, 100.0)) <= 50.0);
+        }
+        game.handle_cmd_spawn(op, "virus 1e9 0");
+        let virus = game.world.virus_cells[0];
+        assert_eq!(game.world.get_cell(virus).unwrap().data().position.x, game.world.border.max_x);
+
+        game.handle_cmd_clear(op, "food");
+        assert!(game.world.food_cells.is_empty());
+        assert_eq!(game.removed_cells.len(), 3);
+        assert_eq!(game.world.virus_cells, vec![virus]);
+    }
+
+    #[test]
+    fn test_line_command_targets_a_client() {
+        let mut game = test_state();
+        let op = game.add_client("127.0.0.1:1".parse().unwrap());
+        let player = game.add_client("127.0.0.1:2".parse().unwrap());
+        let mut op_rx = game.targeted_tx.register(op);
+        let mut player_rx = game.targeted_tx.register(player);
+
+        game.handle_cmd_line(op, &format!("{} 120 -40", player));
+        let msg = player_rx.try_recv().unwrap();
+        assert_eq!(msg.client_id, player);
+        let client = &game.clients[&player];
+        assert!(matches!(
+            msg.message,
+            TargetedMessageType::DrawLine { x: 120, y: -40, scramble_x, scramble_y }
+                if scramble_x == client.scramble_x && scramble_y == client.scramble_y
+        ));
+        assert_eq!(op_rx.try_recv().unwrap().client_id, op);
+
+        // Bad arguments and unknown clients only get a reply
+        for args in ["", "1", "x 2", "999 1 2"] {
+            game.handle_cmd_line(op, args);
+            let reply = op_rx.try_recv().unwrap();
+            assert_eq!(reply.client_id, op);
+            assert!(matches!(reply.message, TargetedMessageType::ChatMessage { .. }), "{:?}", args);
+        }
+        assert!(player_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_amount_commands_remove_the_oldest_excess() {
+        let mut game = test_state();
+        let op = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.world.spawn_food(4, 4, 4, 10.0, 20.0, 0);
+        game.world.spawn_food(7, 7, 3, 10.0, 20.0, 5);
+        assert_eq!(game.world.food_cells.len(), 7);
+
+        game.handle_cmd_amount(op, true, "0 3");
+        assert_eq!((game.config.food.min_amount, game.config.food.max_amount), (0, 3));
+        assert_eq!(game.world.food_cells.len(), 3);
+        assert!(game.world.food_cells.iter().all(|&id| game.world.get_cell(id).unwrap().data().tick_of_birth == 5));
+        assert_eq!(game.removed_cells.len(), 4);
+
+        // Rejected as a whole when min exceeds max
+        game.handle_cmd_amount(op, false, "9 2");
+        assert_eq!((game.config.virus.min_amount, game.config.virus.max_amount), (0, 0));
+        game.handle_cmd_amount(op, false, "2 9");
+        assert_eq!((game.config.virus.min_amount, game.config.virus.max_amount), (2, 9));
+    }
+
+    #[test]
+    fn test_parked_cells_expire_after_grace() {
+        let mut game = test_state();
+        game.config.player.reconnect_grace_seconds = 1.0;
+        let id = game.add_client("10.0.0.1:1000".parse().unwrap());
+        game.handle_join(id, "Alice".to_string()).unwrap();
+        let cell_id = game.clients[&id].cells[0];
+        game.disconnect_client(id);
+
+        let grace_ticks = 1000 / game.config.server.tick_interval_ms;
+        game.tick_count += grace_ticks;
+        game.expire_parked_clients();
+        assert!(game.world.get_cell(cell_id).is_some());
+
+        game.tick_count += 1;
+        game.expire_parked_clients();
+        assert!(game.world.get_cell(cell_id).is_none());
+
+        // Without a grace period cells go immediately
+        game.config.player.reconnect_grace_seconds = 0.0;
+        let id = game.add_client("10.0.0.1:1001".parse().unwrap());
+        game.handle_join(id, "Alice".to_string()).unwrap();
+        let cell_id = game.clients[&id].cells[0];
+        game.disconnect_client(id);
+        assert!(game.world.get_cell(cell_id).is_none());
+    }
+
+    #[test]
+    fn test_peak_mass_is_submitted_when_life_ends() {
+        let mut game = test_state();
+        let id = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_join(id, "Alice".to_string()).unwrap();
+        let cell_id = game.clients[&id].cells[0];
+        game.world.get_cell_mut(cell_id).unwrap().data_mut().set_size(300.0);
+        game.update_peak_masses();
+
+        // Shrinking doesn't lower the peak, and nothing is recorded while alive
+        game.world.get_cell_mut(cell_id).unwrap().data_mut().set_size(100.0);
+        game.update_peak_masses();
+        assert!(game.highscores.leader().is_none());
+
+        game.clients.get_mut(&id).unwrap().cells.clear();
+        game.update_peak_masses();
+        let leader = game.highscores.leader().unwrap();
+        assert_eq!((leader.name.as_str(), leader.mass), ("Alice", 900));
+        assert_eq!(game.clients[&id].peak_mass, 0.0);
+    }
+
+    #[test]
+    fn test_big_kill_counts_the_victims_peak_mass() {
+        let mut game = test_state();
+        game.config.announcements.enabled = true;
+        game.config.announcements.big_kill_mass = 5_000.0;
+        let mut chat_rx = game.chat_tx.subscribe();
+        let killer = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.clients.get_mut(&killer).unwrap().name = "Killer".to_string();
+        let victim = game.add_client("127.0.0.1:2".parse().unwrap());
+        game.spawn_player(killer);
+        game.spawn_player(victim);
+        for (id, size) in [(killer, 600.0), (victim, 100.0)] {
+            let cell = game.clients[&id].cells[0];
+            let data = game.world.get_cell_mut(cell).unwrap().data_mut();
+            data.set_size(size);
+            data.position = glam::Vec2::new(500.0, 500.0);
+            game.world.update_cell_position(cell);
+        }
+        // Most of the victim was eaten in earlier ticks
+        game.clients.get_mut(&victim).unwrap().peak_mass = 8_000.0;
+
+        game.process_collisions();
+        game.process_deaths();
+        let announced: Vec<String> = std::iter::from_fn(|| chat_rx.try_recv().ok()).map(|c| c.message).collect();
+        assert!(announced.iter().any(|m| m.starts_with("Killer ate") && m.ends_with("(8000 mass)!")), "{:?}", announced);
+    }
+
+    #[test]
+    fn test_roster_lists_players_and_bots_by_mass() {
+        let mut game = test_state();
+        let viewer = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.clients.get_mut(&viewer).unwrap().handshake_complete = true;
+        game.spawn_player(viewer);
+        game.handle_cmd_minion(viewer, "2");
+        game.process_bot_respawns();
+        let minion = game.clients[&viewer].minions[0];
+        let cell_id = game.bots.get_bot(minion).unwrap().cells[0];
+        game.world.get_cell_mut(cell_id).unwrap().data_mut().size = 200.0;
+
+        let entries = game.prepare_roster();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].is_bot);
+        assert_eq!(entries[0].mass, 400);
+        assert!(entries.windows(2).all(|w| w[0].mass >= w[1].mass));
+
+        // One copy rides along with the room-wide leaderboard
+        game.tick_count = 100;
+        let leaderboard = game.tick().leaderboard.expect("leaderboard due");
+        assert!(leaderboard.roster.is_some());
+    }
+
+    #[test]
+    fn test_pause_freezes_the_world() {
+        let mut game = test_state();
+        game.config.food.min_amount = 50;
+        game.config.food.max_amount = 100;
+        game.config.eject.lifetime_ticks = 10;
+        game.config.server.bots = 3;
+        game.spawn_bots();
+        let op = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_join(op, "Alice".to_string()).unwrap();
+        let client = game.clients.get_mut(&op).unwrap();
+        client.is_operator = true;
+        client.handshake_complete = true;
+        client.mouse_x += 1000;
+        let cell = client.cells[0];
+        game.world.get_cell_mut(cell).unwrap().data_mut().set_size(200.0);
+        for _ in 0..5 {
+            game.tick();
+        }
+        game.handle_eject(op);
+
+        let snapshot = |game: &GameState| {
+            let mut cells: Vec<(u32, u32, u32, u32)> = game.world.iter_cells()
+                .map(|(&id, c)| {
+                    let d = c.data();
+                    (id, d.position.x.to_bits(), d.position.y.to_bits(), d.size.to_bits())
+                })
+                .collect();
+            cells.sort_unstable();
+            cells
+        };
+        game.handle_command(op, "/pause").unwrap();
+        assert!(game.paused);
+        let before = snapshot(&game);
+        let tick = game.tick_count;
+        let mut updates = 0;
+        for _ in 0..100 {
+            game.handle_packet(op, &[17]).unwrap();
+            game.handle_packet(op, &[21]).unwrap();
+            let broadcasts = game.tick();
+            assert!(broadcasts.leaderboard.is_none());
+            updates += broadcasts.world_update.is_some() as usize;
+        }
+        assert_eq!(snapshot(&game), before);
+        assert_eq!(game.tick_count, tick);
+        // Re-sent at the reduced rate only
+        assert_eq!(updates, 100 / (PAUSED_BROADCAST_INTERVAL_MS / game.config.server.tick_interval_ms) as usize);
+
+        game.handle_command(op, "/resume").unwrap();
+        game.tick();
+        assert_eq!(game.tick_count, tick + 1);
+        assert_ne!(snapshot(&game), before);
+    }
+
+    #[test]
+    fn test_death_info_names_the_killer() {
+        let mut game = test_state();
+        let owner = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.clients.get_mut(&owner).unwrap().name = "Owner".to_string();
+        game.handle_cmd_minion(owner, "name Helper");
+        game.handle_cmd_minion(owner, "1");
+        let minion = game.clients[&owner].minions[0];
+        game.process_bot_respawns();
+        let victim = game.add_client("127.0.0.1:2".parse().unwrap());
+        game.clients.get_mut(&victim).unwrap().protocol = protocol::packets::COGAR_PROTOCOL;
+        let mut targeted_rx = game.targeted_tx.register(victim);
+        game.spawn_player(victim);
+
+        let place = |game: &mut GameState, cell: u32, size: f32| {
+            let data = game.world.get_cell_mut(cell).unwrap().data_mut();
+            data.set_size(size);
+            data.position = glam::Vec2::new(500.0, 500.0);
+            game.world.update_cell_position(cell);
+        };
+        let mut death_info = |game: &mut GameState| {
+            game.process_collisions();
+            game.process_deaths();
+            std::iter::from_fn(|| targeted_rx.try_recv().ok())
+                .find_map(|msg| match msg.message {
+                    TargetedMessageType::DeathInfo { cause, killer_name, killer_mass, killer_id } => {
+                        Some((cause, killer_name, killer_mass, killer_id))
+                    }
+                    _ => None,
+                })
+        };
+
+        // A minion's kill goes to its owner
+        let minion_cell = game.bots.get_bot(minion).unwrap().cells[0];
+        place(&mut game, minion_cell, 300.0);
+        let victim_cell = game.clients[&victim].cells[0];
+        place(&mut game, victim_cell, 50.0);
+        let (cause, name, mass, killer) = death_info(&mut game).expect("no death info");
+        assert_eq!((cause, name.as_str(), killer), (DeathCause::Eaten, "Owner", owner));
+        assert!(mass >= 900);
+
+        // Mother cells aren't anyone's
+        game.world.remove_cell(minion_cell);
+        game.bots.get_bot_mut(minion).unwrap().cells.clear();
+        game.spawn_player(victim);
+        let victim_cell = game.clients[&victim].cells[0];
+        place(&mut game, victim_cell, 50.0);
+        let mother = game.world.next_id();
+        game.world.add_mother_cell(crate::entity::MotherCell::new(mother, glam::Vec2::new(500.0, 500.0), 200.0, 0));
+        // Its mass includes the victim it just swallowed
+        let (cause, name, mass, killer) = death_info(&mut game).expect("no death info");
+        assert_eq!((cause, name.as_str(), killer), (DeathCause::MotherCell, "", 0));
+        assert_eq!(mass, 425);
+
+        // So is /kill
+        game.world.remove_cell(mother);
+        game.spawn_player(victim);
+        game.handle_cmd_kill(owner, &victim.to_string());
+        assert_eq!(death_info(&mut game), Some((DeathCause::Killed, String::new(), 0, 0)));
+
+        // And /killall, though only to clients that speak cogar's protocol
+        let vanilla = game.add_client("127.0.0.1:3".parse().unwrap());
+        game.clients.get_mut(&vanilla).unwrap().protocol = 6;
+        let mut vanilla_rx = game.targeted_tx.register(vanilla);
+        game.spawn_player(victim);
+        game.spawn_player(vanilla);
+        game.clients.get_mut(&owner).unwrap().is_operator = true;
+        game.handle_command(owner, "/killall").unwrap();
+        assert_eq!(death_info(&mut game), Some((DeathCause::Killed, String::new(), 0, 0)));
+        assert!(game.clients[&vanilla].cells.is_empty());
+        assert!(!std::iter::from_fn(|| vanilla_rx.try_recv().ok())
+            .any(|msg| matches!(msg.message, TargetedMessageType::DeathInfo { .. })));
+    }
+
+    #[test]
+    fn test_bot_respawn_waits_and_ramps_up() {
+        let mut game = test_state();
+        game.config.bots.start_size = 100.0;
+        game.config.bots.respawn_delay_ticks = 10;
+        game.config.bots.start_mass_ramp_ticks = 20;
+        let bot = game.bots.add_bot(&mut game.world.rng);
+        let owner = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(owner);
+        game.handle_cmd_minion(owner, "1");
+        let minion = game.clients[&owner].minions[0];
+        let size_of = |game: &GameState, id: u32| {
+            let cell = game.bots.get_bot(id).unwrap().cells[0];
+            game.world.get_cell(cell).unwrap().data().size
+        };
+
+        // A new bot spawns at once, small, and grows to bots.start_size
+        game.process_bot_respawns();
+        assert_eq!(size_of(&game, bot), game.config.player.min_size as f32);
+        assert_eq!(size_of(&game, minion), game.config.bots.minion_start_size as f32);
+        for _ in 0..20 {
+            game.process_bot_respawns();
+        }
+        assert!((size_of(&game, bot) - 100.0).abs() < 0.01);
+        assert!(game.bots.get_bot(bot).unwrap().spawn_ramp.is_none());
+
+        // Once dead, it waits the delay; the minion doesn't
+        for id in [bot, minion] {
+            let cell = game.bots.get_bot_mut(id).unwrap().cells.pop().unwrap();
+            game.world.remove_cell(cell);
+        }
+        game.process_bot_respawns();
+        assert_eq!(game.bots.get_bot(minion).unwrap().cells.len(), 1);
+        for _ in 0..9 {
+            game.tick_count += 1;
+            game.process_bot_respawns();
+            assert!(game.bots.get_bot(bot).unwrap().cells.is_empty());
+        }
+        game.tick_count += 1;
+        game.process_bot_respawns();
+        assert_eq!(size_of(&game, bot), game.config.player.min_size as f32);
+    }
+
+    #[test]
+    fn test_mode_places_and_sizes_bot_spawns() {
+        let mut game = test_state();
+        game.config.bots.start_mass_ramp_ticks = 0;
+        let bot = game.bots.add_bot(&mut game.world.rng);
+        // FFA leaves both to the config
+        assert_eq!(game.gamemode.get_spawn_size(bot), None);
+        assert_eq!(game.gamemode.get_spawn_position(&game.world, bot), None);
+
+        let mut mode = crate::gamemodes::tournament::Tournament { start_size: Some(70.0), ..Default::default() };
+        mode.add_contender(bot);
+        let ring = mode.contender_position(&game.world, bot).unwrap();
+        game.gamemode = Box::new(mode);
+        game.process_bot_respawns();
+        let cell = game.world.get_cell(game.bots.get_bot(bot).unwrap().cells[0]).unwrap();
+        assert_eq!(cell.data().position, ring);
+        assert_eq!(cell.data().size, 70.0);
+    }
+
+    #[test]
+    fn test_bots_wear_configured_skins() {
+        let mut game = test_state();
+        game.bots = BotManager::new(bot_skins(&["doge".to_string(), "../etc".to_string()]));
+        let bot = game.bots.add_bot(&mut game.world.rng);
+        assert_eq!(game.bots.get_bot(bot).unwrap().skin.as_deref(), Some("doge"));
+
+        // Minions stay plain
+        let owner = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(owner);
+        game.handle_cmd_minion(owner, "1");
+        let minion = game.clients[&owner].minions[0];
+        assert_eq!(game.bots.get_bot(minion).unwrap().skin, None);
+
+        game.process_bot_respawns();
+        let (world, _) = game.prepare_world_broadcast();
+        let bot_cell = game.bots.get_bot(bot).unwrap().cells[0];
+        let cell = world.cells.iter().find(|c| c.node_id == bot_cell).unwrap();
+        assert_eq!(cell.skin.as_deref(), Some("doge"));
+    }
+
+    #[test]
+    fn test_center_is_mass_weighted() {
+        // One 200-size cell and fifteen 20-size pieces 1000 units away
+        let mut cells = vec![(0.0, 0.0, 200.0)];
+        cells.extend((0..15).map(|_| (1000.0, 0.0, 20.0)));
+        let (x, y) = weighted_center(&cells).unwrap();
+        // 15 * 400 / (40000 + 15 * 400) of the way over, not 15/16
+        assert!((x - 1000.0 * 6000.0 / 46000.0).abs() < 0.01);
+        assert_eq!(y, 0.0);
+
+        assert_eq!(weighted_center(&[]), None);
+        assert_eq!(weighted_center(&[(10.0, 20.0, 0.0), (30.0, 40.0, 0.0)]), Some((20.0, 30.0)));
+
+        // The broadcast center follows the big cell
+        let mut game = test_state();
+        let id = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.clients.get_mut(&id).unwrap().handshake_complete = true;
+        game.spawn_player(id);
+        let main = game.clients[&id].cells[0];
+        let main_pos = game.world.get_cell(main).unwrap().data().position;
+        let (world, _) = game.prepare_world_broadcast();
+        let view = &world.client_data[&id];
+        assert_eq!((view.center_x, view.center_y), (main_pos.x, main_pos.y));
+    }
+
+    #[test]
+    fn test_world_dump_round_trip() {
+        let mut game = test_state();
+        game.config.server.bots = 2;
+        game.spawn_bots();
+        game.process_bot_respawns();
+        game.tick_count = 100;
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(player);
+        let cell = game.clients[&player].cells[0];
+        game.world.get_cell_mut(cell).unwrap().data_mut().set_size(400.0);
+        game.handle_split(player);
+        game.handle_eject(player);
+        game.world.spawn_food(20, 20, 20, 10.0, 20.0, 0);
+        game.world.spawn_viruses(3, 3, 100.0, 0);
+        let mother = game.world.next_id();
+        game.world.add_mother_cell(crate::entity::MotherCell::new(mother, glam::Vec2::new(100.0, 100.0), 0.0, 0));
+
+        let dump = game.dump_world();
+        assert_eq!(dump.tick, 100);
+        assert!(dump.cells.iter().any(|c| c.moving && c.boost.is_some()));
+        assert!(dump.cells.iter().any(|c| matches!(c.kind, dump::CellKind::Eject { ejector: Some(id) } if id == player)));
+        let json = serde_json::to_string(&dump).unwrap();
+        let parsed: WorldDump = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, dump);
+
+        let mut loaded = test_state();
+        loaded.load_dump(&parsed);
+        let again = loaded.dump_world();
+        assert_eq!(again.cells, dump.cells);
+        assert_eq!((again.tick, again.next_node_id), (dump.tick, dump.next_node_id));
+        for bot in &dump.bots {
+            assert_eq!(again.bots.iter().find(|b| b.id == bot.id), Some(bot));
+        }
+
+        // The player's cells come back under a bot with the same id
+        let restored = loaded.bots.get_bot(player).unwrap();
+        assert_eq!(restored.cells, dump.clients[0].cells);
+        assert_eq!(restored.cells.len(), 2);
+        assert!(loaded.world.find_nearby(dump.cells[0].x, dump.cells[0].y, 1.0).iter().any(|item| item.id == dump.cells[0].id));
+        assert_ne!(loaded.add_client("127.0.0.1:2".parse().unwrap()), player);
+        assert_eq!(loaded.world.next_id(), dump.next_node_id);
+    }
+
+    #[test]
+    fn test_dump_command_writes_loadable_file() {
+        let dir = std::env::temp_dir().join(format!("cogar-dump-{}", std::process::id()));
+        let mut game = test_state();
+        game.config.replay.dump_directory = dir.to_string_lossy().into_owned();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(player);
+        game.world.spawn_food(10, 10, 10, 10.0, 20.0, 0);
+
+        game.handle_cmd_dump(player);
+        let (path, json) = game.tick().dump.unwrap();
+        assert!(path.starts_with(&dir));
+        assert!(game.tick().dump.is_none());
+        dump::save(&path, &json).unwrap();
+
+        let read = WorldDump::read(&path).unwrap();
+        let mut loaded = test_state();
+        loaded.load_dump(&read);
+        assert_eq!(loaded.dump_world().cells, read.cells);
+        assert_eq!(read.cells.len(), 11);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bot_split_kills_nearby_prey() {
+        use crate::config::BotDifficulty;
+
+        for difficulty in [BotDifficulty::Normal, BotDifficulty::Hard] {
+            let mut game = test_state();
+            game.config.bots.difficulty = difficulty;
+            let bot_id = game.bots.add_bot(&mut game.world.rng);
+            game.process_bot_respawns();
+            let bot_cell = game.bots.get_bot(bot_id).unwrap().cells[0];
+            let data = game.world.get_cell_mut(bot_cell).unwrap().data_mut();
+            data.set_size(300.0);
+            data.position = glam::Vec2::ZERO;
+            game.world.update_cell_position(bot_cell);
+
+            // A dummy that holds still, well out of the bot's own reach
+            let dummy = game.add_client("127.0.0.1:1".parse().unwrap());
+            game.spawn_player(dummy);
+            game.clients.get_mut(&dummy).unwrap().frozen = true;
+            let dummy_cell = game.clients[&dummy].cells[0];
+            let data = game.world.get_cell_mut(dummy_cell).unwrap().data_mut();
+            data.set_size(150.0);
+            data.position = glam::Vec2::new(600.0, 0.0);
+            game.world.update_cell_position(dummy_cell);
+
+            let mut split = false;
+            let eaten = (0..100).any(|_| {
+                game.tick();
+                split |= game.bots.get_bot(bot_id).unwrap().cells.len() > 1;
+                game.clients[&dummy].cells.is_empty()
+            });
+            assert!(eaten, "{:?} bot never caught the dummy", difficulty);
+            assert!(split, "{:?} bot walked instead of splitting", difficulty);
+        }
+    }
+
+    #[test]
+    fn test_wrapping_border_lets_cells_cross_and_eat_over_the_seam() {
+        for wrap in [false, true] {
+            let mut game = test_state();
+            game.world.border.wrap = wrap;
+            let border = game.world.border;
+            let player = game.add_client("127.0.0.1:1".parse().unwrap());
+            game.spawn_player(player);
+            let cell = game.clients[&player].cells[0];
+            let data = game.world.get_cell_mut(cell).unwrap().data_mut();
+            data.set_size(100.0);
+            data.position = glam::Vec2::new(border.max_x - 150.0, 0.0);
+            game.world.update_cell_position(cell);
+            let food = game.world.next_id();
+            game.world.add_food(crate::entity::Food::new(food, glam::Vec2::new(border.min_x + 50.0, 0.0), 10.0, 0));
+
+            // Keep heading right, past the edge
+            let client = game.clients.get_mut(&player).unwrap();
+            client.mouse_x = (border.max_x + 1000.0) as i32;
+            client.mouse_y = 0;
+            for _ in 0..50 {
+                // Crossing the seam doesn't count as a map-wide jump
+                if let Some(update) = game.tick().world_update {
+                    let lead = update.cells.iter().find(|c| c.node_id == cell).unwrap().lead;
+                    assert!(lead < MAX_VIEW_LEAD_MOVE, "lead {}", lead);
+                }
+            }
+
+            let x = game.world.get_cell(cell).unwrap().data().position.x;
+            if wrap {
+                assert!(x < border.min_x + 500.0, "cell at {} never came back on the left", x);
+                assert!(game.world.get_cell(food).is_none());
+            } else {
+                // Held against the wall, half a cell in
+                assert!(x > border.max_x - 60.0 && x < border.max_x, "cell at {}", x);
+                assert!(game.world.get_cell(food).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_heatmap_samples_player_cells() {
+        let mut game = test_state();
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.handle_cmd_heatmap(player);
+        assert!(game.tick().heatmap.is_none());
+
+        let mut config = game.config.clone();
+        config.stats.heatmap_enabled = true;
+        config.stats.heatmap_interval_ticks = 2;
+        config.stats.heatmap_file = "maps/heat.json".to_string();
+        let mut game = GameState::for_tests(config);
+        let player = game.add_client("127.0.0.1:1".parse().unwrap());
+        game.spawn_player(player);
+        game.world.spawn_food(10, 10, 10, 10.0, 20.0, 0);
+        for _ in 0..10 {
+            game.tick();
+        }
+
+        game.handle_cmd_heatmap(player);
+        let (path, json) = game.tick().heatmap.unwrap();
+        assert_eq!(path, PathBuf::from("maps/heat.json"));
+        let file: crate::heatmap::HeatmapFile = serde_json::from_str(&json).unwrap();
+        assert_eq!((file.samples, file.interval_ticks), (5, 2));
+        // One player cell per sample; food isn't counted
+        assert_eq!(file.counts.iter().flatten().sum::<u32>(), 5);
+    }
+
+    #[test]
+    fn test_same_seed_plays_out_the_same() {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        fn world_hash(game: &GameState) -> u64 {
+            let mut cells: Vec<_> = game.world.cells.iter()
+                .map(|(&id, cell)| {
+                    let data = cell.data();
+                    (id, data.position.x.to_bits(), data.position.y.to_bits(), data.size.to_bits(), data.owner_id, (data.color.r, data.color.g, data.color.b))
+                })
+                .collect();
+            cells.sort_unstable();
+            let mut hasher = DefaultHasher::new();
+            cells.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        // Two players circling, splitting, ejecting and rejoining among bots,
+        // food and viruses, hashed every 100 ticks
+        fn play(seed: u64) -> Vec<u64> {
+            let mut config = Config::default();
+            config.server.rng_seed = Some(seed);
+            config.server.bots = 8;
+            config.server.scramble_level = 0;
+            config.server.packet_limits.split_per_second = 0.0;
+            config.server.packet_limits.eject_per_second = 0.0;
+            config.server.packet_limits.join_per_second = 0.0;
+            let mut game = GameState::for_tests(config);
+
+            let players: Vec<u32> = (0..2u16).map(|i| {
+                let id = game.add_client(format!("10.0.0.{}:1000", i + 1).parse().unwrap());
+                let client = game.clients.get_mut(&id).unwrap();
+                client.handshake_complete = true;
+                client.protocol = 6;
+                id
+            }).collect();
+            let join = protocol::packets::build_join(6, "p").finish();
+            let split = protocol::packets::build_opcode(protocol::packets::ClientOpcode::Split).finish();
+            let eject = protocol::packets::build_opcode(protocol::packets::ClientOpcode::Eject).finish();
+
+            let mut hashes = Vec::new();
+            for tick in 0..1000u64 {
+                for (i, &id) in players.iter().enumerate() {
+                    if game.clients[&id].cells.is_empty() {
+                        game.handle_packet(id, &join).unwrap();
+                    }
+                    let angle = tick as f32 / 40.0 + i as f32 * std::f32::consts::PI;
+                    let mouse = protocol::packets::build_mouse((angle.cos() * 1500.0) as i32, (angle.sin() * 1500.0) as i32).finish();
+                    game.handle_packet(id, &mouse).unwrap();
+                    if (tick + i as u64 * 30).is_multiple_of(120) {
+                        game.handle_packet(id, &split).unwrap();
+                    }
+                    if (tick + i as u64).is_multiple_of(15) {
+                        game.handle_packet(id, &eject).unwrap();
+                    }
+                }
+                game.tick();
+                if tick % 100 == 99 {
+                    hashes.push(world_hash(&game));
+                }
+            }
+            assert!(!game.world.virus_cells.is_empty() && !game.world.food_cells.is_empty());
+            hashes
+        }
+
+        let first = play(7);
+        assert_eq!(first, play(7));
+        assert_ne!(first, play(8));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_tick_causes_no_catch_up_burst() {
+        use futures_util::FutureExt;
+
+        let period = Duration::from_millis(40);
+        let mut ticker = game_ticker(period);
+        ticker.tick().await;
+        // A tick that takes three periods
+        sleep(period * 3).await;
+        ticker.tick().await;
+        // The missed ticks aren't made up back-to-back...
+        assert!(ticker.tick().now_or_never().is_none());
+        // ...the next one is a full period after the late one
+        let late = Instant::now();
+        ticker.tick().await;
+        assert_eq!(late.elapsed(), period);
+    }
+
+    #[test]
+    fn test_overload_sheds_work_and_is_reported() {
+        let mut game = test_state();
+        let id = game.add_client("10.0.0.1:1000".parse().unwrap());
+        let mut targeted_rx = game.targeted_tx.register(id);
+        let budget = game.config.server.tick_interval_ms as f64;
+        game.tick_count = 100;
+
+        for _ in 1..OVERLOAD_STREAK_TICKS {
+            game.record_tick_time(budget * 3.0);
+        }
+        assert!(!game.overloaded);
+        game.record_tick_time(budget * 3.0);
+        assert!(game.overloaded);
+
+        // Leaderboard is due but put off
+        assert!(game.tick().leaderboard.is_none());
+        game.handle_stats_request(id);
+        let json = match targeted_rx.try_recv().unwrap().message {
+            TargetedMessageType::ServerStat { json } => json,
+            other => panic!("expected stats, got {:?}", other),
+        };
+        assert!(json.ends_with(r#""overloaded":true}"#), "{}", json);
+
+        // A lasting overload keeps shedding past the first window
+        for _ in 0..OVERLOAD_SHED_TICKS {
+            game.tick_count += 1;
+            game.record_tick_time(budget * 3.0);
+            assert!(game.shedding_load());
+        }
+
+        // Fast ticks bring the average back under budget
+        for _ in 0..3 {
+            game.record_tick_time(0.0);
+        }
+        assert!(!game.overloaded);
+        game.tick_count = game.shed_until_tick;
+        assert!(game.tick().leaderboard.is_some());
+    }
+
+    #[test]
+    fn test_merge_timers_go_only_to_cogar_clients() {
+        let mut game = test_state();
+        for (protocol, expected) in [(6, false), (protocol::packets::COGAR_PROTOCOL, true)] {
+            let player = game.add_client("127.0.0.1:1".parse().unwrap());
+            game.clients.get_mut(&player).unwrap().protocol = protocol;
+            let mut targeted_rx = game.targeted_tx.register(player);
+            game.spawn_player(player);
+            let cell = game.clients[&player].cells[0];
+            game.world.get_cell_mut(cell).unwrap().data_mut().set_size(400.0);
+            game.handle_split(player);
+            assert_eq!(game.clients[&player].cells.len(), 2);
+            let got_timer = std::iter::from_fn(|| targeted_rx.try_recv().ok())
+                .any(|msg| matches!(msg.message, TargetedMessageType::MergeTimer { .. }));
+            assert_eq!(got_timer, expected, "protocol {}", protocol);
+        }
+    }
+}
@@ -1,22 +1,44 @@
 //! Game server implementation.
 
 use crate::config::Config;
-use futures_util::{SinkExt, StreamExt};
+use crate::metrics::{Channel, HandshakeStage, Metrics, UpgradeRejection};
+use crate::tls::{ReloadSignal, TlsAcceptor};
+use crate::upgrade::UpgradePolicy;
+use futures_util::StreamExt;
 use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, SocketAddr};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, RwLock};
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio::sync::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::{accept_hdr_async, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{StatusCode, header::ORIGIN};
 use tracing::{error, info, warn};
 
 pub mod client;
 pub mod game;
+pub mod outbox;
+pub mod ratelimit;
+pub mod room;
+pub mod targeted;
 
 pub use game::{GameState, run_game_loop};
+pub use outbox::{Frame, Outbox};
+pub use room::{RoomReceivers, RoomRegistry};
+pub use targeted::TargetedSender;
 
-use protocol::Color;
+use protocol::{CloseCode, Color};
+
+/// Fraction of the view's half-size a cell the client already has may drift
+/// past its margin before it is deleted, so cells at the edge don't flicker
+/// in and out of the view on alternate ticks.
+pub const VIEW_HYSTERESIS: f32 = 0.1;
 
 /// A chat message to be broadcast to all clients.
 #[derive(Debug, Clone)]
@@ -47,10 +69,15 @@ pub struct LeaderboardEntry {
 pub struct LeaderboardBroadcast {
     /// Sorted list of leaderboard entries (highest score first).
     pub entries: Vec<LeaderboardEntry>,
+    /// Lines from [`GameMode::get_text_board`](crate::gamemodes::GameMode::get_text_board),
+    /// sent in place of the entries when present.
+    pub text: Option<Vec<String>>,
     /// Active gamemode ID.
     pub gamemode_id: u32,
     /// Active gamemode name.
     pub gamemode_name: String,
+    /// Full player roster, attached every few leaderboards.
+    pub roster: Option<Arc<[protocol::packets::RosterEntry]>>,
 }
 
 /// Cell data for world updates.
@@ -65,6 +92,16 @@ pub struct WorldCell {
     pub name: Option<String>,
     pub skin: Option<String>,
     pub owner_id: Option<u32>,
+    /// Golden bonus food (sent to clients as the agitated flag).
+    pub is_bonus: bool,
+    /// Being pushed by a boost: a split, shot or ejection still in flight
+    /// (also sent as the agitated flag).
+    pub is_boosted: bool,
+    /// How far the cell may move before viewers hear of it again: the rest
+    /// of its boost, or a few ticks of its last movement.
+    pub lead: f32,
+    /// The owner changed name or skin this tick; viewers get them again.
+    pub identity_changed: bool,
 }
 
 /// World state update broadcast (sent every tick).
@@ -78,6 +115,8 @@ pub struct WorldUpdateBroadcast {
     pub removed: Vec<u32>,
     /// Per-client data (client_id -> (center_x, center_y, scale, cell_ids)).
     pub client_data: HashMap<u32, ClientViewData>,
+    /// `server.view_margin`, the multiplier on each cell's culling margin.
+    pub view_margin: f32,
 }
 
 /// Per-client view data.
@@ -86,6 +125,9 @@ pub struct ClientViewData {
     pub center_x: f32,
     pub center_y: f32,
     pub scale: f32,
+    /// Client canvas size in CSS pixels; the view covers this divided by `scale`.
+    pub view_width: f32,
+    pub view_height: f32,
     pub cell_ids: Vec<u32>,
     pub minion_ids: Vec<u32>,
     pub protocol: u32,
@@ -94,6 +136,8 @@ pub struct ClientViewData {
     pub scramble_y: i32,
     pub name: String,
     pub skin: Option<String>,
+    /// Spectating; the view center is streamed as UpdatePosition packets.
+    pub spectating: bool,
 }
 
 /// A message targeted at a specific client.
@@ -112,6 +156,8 @@ pub enum TargetedMessageType {
     AddNode { node_id: u32, scramble_id: u32 },
     /// ClearAll packet - sent after handshake.
     ClearAll,
+    /// ClearOwned packet - sent ahead of a respawn's AddNode.
+    ClearOwned,
     /// SetBorder packet - sent after handshake.
     SetBorder {
         min_x: f64,
@@ -122,6 +168,21 @@ pub enum TargetedMessageType {
         scramble_y: i32,
         game_type: u32,
         server_name: String,
+        /// Sent to cogar-protocol clients only.
+        unlimited_zoom: Option<bool>,
+    },
+    /// MapObjects packet - sent after SetBorder to cogar-protocol clients when the map has any.
+    MapObjects {
+        shapes: Arc<[protocol::packets::MapShape]>,
+        scramble_x: i32,
+        scramble_y: i32,
+    },
+    /// DrawLine packet - a line to a world point, from `/line`.
+    DrawLine {
+        x: i32,
+        y: i32,
+        scramble_x: i32,
+        scramble_y: i32,
     },
     /// ServerStat packet - JSON stats response.
     ServerStat { json: String },
@@ -139,6 +200,184 @@ pub enum TargetedMessageType {
         scramble_x: i32,
         scramble_y: i32,
     },
+    /// Remerge countdown for the client's own cells: `(node_id, remaining_ms)`.
+    MergeTimer {
+        timers: Vec<(u32, u32)>,
+        scramble_id: u32,
+    },
+    /// How the client's player died, for the death overlay. `killer_id` is
+    /// the player to spectate afterwards, 0 for none.
+    DeathInfo { cause: protocol::packets::DeathCause, killer_name: String, killer_mass: u32, killer_id: u32 },
+    /// Close the client's connection with a close frame carrying `code`
+    /// and `reason`.
+    Disconnect { code: protocol::CloseCode, reason: String },
+}
+
+/// Build one client's UpdateNodes packet from a world broadcast: cells that
+/// entered its view are added, cells that left are deleted, and `client_nodes`
+/// (what the client has been sent so far) is updated to match. With `resync`,
+/// every visible cell is resent in full as if new, for a client that missed
+/// updates; cells it knew that left the view are still deleted.
+///
+/// A cell is in view while its size plus its lead, times the world's
+/// `view_margin`, reaches the viewport. Cells the client already has stay
+/// until they're [`VIEW_HYSTERESIS`] further out.
+pub fn build_view_update(
+    world: &WorldUpdateBroadcast,
+    client_view: &ClientViewData,
+    client_nodes: &mut HashSet<u32>,
+    resync: bool,
+) -> protocol::BinaryWriter {
+    // Calculate viewport bounds
+    let scale = client_view.scale.max(0.15);
+    let view_half_w = (client_view.view_width / scale) / 2.0;
+    let view_half_h = (client_view.view_height / scale) / 2.0;
+    let view_min_x = client_view.center_x - view_half_w;
+    let view_min_y = client_view.center_y - view_half_h;
+    let view_max_x = client_view.center_x + view_half_w;
+    let view_max_y = client_view.center_y + view_half_h;
+
+    let keep_margin = view_half_w.min(view_half_h) * VIEW_HYSTERESIS;
+
+    // Find cells in viewport
+    let mut view_nodes: HashSet<u32> = HashSet::new();
+    for cell in &world.cells {
+        // Check if cell is in viewport, with a margin for its size and where it's heading
+        let mut margin = (cell.size + cell.lead) * world.view_margin;
+        if client_nodes.contains(&cell.node_id) {
+            margin += keep_margin;
+        }
+        if cell.x + margin >= view_min_x
+            && cell.x - margin <= view_max_x
+            && cell.y + margin >= view_min_y
+            && cell.y - margin <= view_max_y
+        {
+            view_nodes.insert(cell.node_id);
+        }
+    }
+
+    // Also always include own cells
+    for &cell_id in &client_view.cell_ids {
+        view_nodes.insert(cell_id);
+    }
+
+    // Force-include all minion cells (always visible to owner)
+    for &minion_id in &client_view.minion_ids {
+        for cell in &world.cells {
+            if cell.owner_id == Some(minion_id) {
+                view_nodes.insert(cell.node_id);
+            }
+        }
+    }
+
+    // Calculate add/update/delete sets
+    let mut add_nodes = Vec::new();
+    let mut upd_nodes = Vec::new();
+    let mut del_nodes = Vec::new();
+
+    // Nodes to add (in view but not in client_nodes)
+    for cell in &world.cells {
+        if view_nodes.contains(&cell.node_id) {
+            let is_new = resync || !client_nodes.contains(&cell.node_id);
+            // Renamed owners resend name and skin to viewers that already know the cell;
+            // an empty string clears a name or skin that was dropped
+            let (skin, name) = if cell.identity_changed {
+                (Some(cell.skin.clone().unwrap_or_default()), Some(cell.name.clone().unwrap_or_default()))
+            } else if is_new {
+                (cell.skin.clone(), cell.name.clone())
+            } else {
+                (None, None)
+            };
+
+            let update_cell = protocol::packets::UpdateCell {
+                node_id: cell.node_id,
+                x: cell.x,
+                y: cell.y,
+                size: cell.size as u16,
+                color: cell.color,
+                flags: protocol::packets::CellFlags {
+                    is_spiked: cell.cell_type == 2, // Virus
+                    is_player: true, // Always send color (needed for Rainbow mode)
+                    has_skin: skin.is_some(),
+                    has_name: name.is_some(),
+                    is_agitated: cell.is_bonus || cell.is_boosted,
+                    is_ejected: cell.cell_type == 3,
+                    is_food: cell.cell_type == 1,
+                    is_friend: client_view.cell_ids.contains(&cell.node_id)
+                        || cell.owner_id.is_some_and(|owner| client_view.minion_ids.contains(&owner)),
+                },
+                skin,
+                name,
+            };
+
+            if is_new {
+                add_nodes.push(update_cell);
+            } else {
+                upd_nodes.push(update_cell);
+            }
+        }
+    }
+
+    // Nodes to delete (in client_nodes but not in view)
+    for &node_id in client_nodes.iter() {
+        if !view_nodes.contains(&node_id) {
+            del_nodes.push(node_id);
+        }
+    }
+
+    // Build eat records
+    let eat_records: Vec<protocol::packets::EatRecord> = world.eaten.iter()
+        .filter(|(eaten_id, eater_id)| {
+            view_nodes.contains(eaten_id)
+                || view_nodes.contains(eater_id)
+                || client_nodes.contains(eaten_id)
+                || client_nodes.contains(eater_id)
+        })
+        .map(|&(eaten_id, eater_id)| protocol::packets::EatRecord { eaten_id, eater_id })
+        .collect();
+
+    // Update client_nodes
+    *client_nodes = view_nodes;
+
+    protocol::packets::build_update_nodes(
+        client_view.protocol,
+        client_view.scramble_id,
+        client_view.scramble_x,
+        client_view.scramble_y,
+        &add_nodes,
+        &upd_nodes,
+        &eat_records,
+        &del_nodes,
+    )
+}
+
+/// A connection's record of broadcasts it fell behind on.
+#[derive(Debug, Default)]
+pub struct LagTracker {
+    /// Broadcast messages missed over the connection's lifetime.
+    pub missed: u64,
+    /// A world update or targeted message was missed; resend the whole view
+    /// and the client's own cells with the next world update.
+    resync: bool,
+}
+
+impl LagTracker {
+    /// Account for one broadcast receive, counting any lag in `metrics`.
+    pub fn observe<T>(&mut self, channel: Channel, result: &Result<T, RecvError>, metrics: &Metrics, addr: SocketAddr) {
+        metrics.observe_recv(channel, result);
+        if let Err(RecvError::Lagged(missed)) = result {
+            self.missed += missed;
+            warn!("{} fell behind on {:?} broadcasts: {} missed ({} total)", addr, channel, missed, self.missed);
+            if matches!(channel, Channel::World | Channel::Targeted) {
+                self.resync = true;
+            }
+        }
+    }
+
+    /// Whether the next world update must be a full resync; clears the flag.
+    pub fn take_resync(&mut self) -> bool {
+        std::mem::take(&mut self.resync)
+    }
 }
 
 /// Connection tracking state (shared across connection handlers).
@@ -229,10 +468,30 @@ impl ConnectionState {
 }
 
 /// Run the game server.
-pub async fn run(config: Config) -> anyhow::Result<()> {
+pub async fn run(config: Config, dump: Option<crate::dump::WorldDump>) -> anyhow::Result<()> {
     let addr: SocketAddr = format!("{}:{}", config.server.bind, config.server.port).parse()?;
     let listener = TcpListener::bind(&addr).await?;
-    info!("Listening on ws://{}", addr);
+    serve(listener, config, dump, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
+
+/// Run the server on an already bound `listener` until `shutdown` resolves,
+/// then save the highscores and heatmaps and return. [`run`] binds the configured address
+/// and stops on Ctrl+C; tests bind port 0 and stop it themselves.
+pub async fn serve(
+    listener: TcpListener,
+    config: Config,
+    dump: Option<crate::dump::WorldDump>,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let addr = listener.local_addr()?;
+    let mut tls = config.server.tls.as_ref().map(crate::tls::load_acceptor).transpose()?;
+    // Only a TLS listener has certificates to reload on SIGHUP
+    let mut reload = tls.is_some().then(ReloadSignal::new).transpose()?;
+    let scheme = if tls.is_some() { "wss" } else { "ws" };
+    info!("Listening on {}://{}", scheme, addr);
 
     // Connection tracking state
     let conn_state = Arc::new(RwLock::new(ConnectionState::new()));
@@ -243,62 +502,87 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
         state.load_ban_list(Path::new("banlist.txt"));
     }
 
-    // Create broadcast channels for chat messages, leaderboard, world updates, and targeted messages
-    let (chat_tx, _chat_rx) = broadcast::channel::<ChatBroadcast>(100);
-    let (lb_tx, _lb_rx) = broadcast::channel::<LeaderboardBroadcast>(10);
-    let (world_tx, _world_rx) = broadcast::channel::<WorldUpdateBroadcast>(5);
-    let (targeted_tx, _targeted_rx) = broadcast::channel::<TargetedMessage>(100);
-
-    // Shared game state
-    let game_state = Arc::new(RwLock::new(GameState::new(&config, chat_tx.clone(), lb_tx.clone(), world_tx.clone(), targeted_tx.clone())));
-
-    // Start the game loop
-    let game_loop_state = Arc::clone(&game_state);
-    let tick_interval = config.server.tick_interval_ms;
-    tokio::spawn(async move {
-        game::run_game_loop(game_loop_state, tick_interval).await;
-    });
+    // One game world per room, each with its own channels and game loop
+    let rooms = Arc::new(RoomRegistry::start(&config, dump).await);
+    for room in rooms.rooms() {
+        let state = room.game_state.read().await;
+        info!("  Room {}://{}{}: {}", scheme, addr, room.path, state.gamemode.name());
+    }
+    if let Some(port) = config.server.metrics_port {
+        let bind = config.server.bind.clone();
+        let rooms = Arc::clone(&rooms);
+        tokio::spawn(async move {
+            if let Err(e) = crate::metrics::serve(&bind, port, rooms).await {
+                error!("Metrics endpoint failed: {}", e);
+            }
+        });
+    }
+    let tracker = if config.tracker.url.is_empty() {
+        None
+    } else {
+        Some(crate::tracker::spawn(&config, addr, Arc::clone(&rooms))?)
+    };
 
     // Connection limits
     let max_connections = config.server.max_connections;
     let ip_limit = config.server.ip_limit;
+    let handshake_timeout = Duration::from_secs(config.server.handshake_timeout);
+    let policy = Arc::new(UpgradePolicy::new(&config.server));
 
+    tokio::pin!(shutdown);
     loop {
-        let (stream, addr) = listener.accept().await?;
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = async {
+                match reload.as_mut() {
+                    Some(reload) => reload.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                if let (Some(acceptor), Some(tls_config)) = (tls.as_mut(), &config.server.tls) {
+                    crate::tls::reload_acceptor(tls_config, acceptor);
+                }
+                continue;
+            }
+            _ = &mut shutdown => {
+                info!("Shutting down");
+                if let Some(tracker) = &tracker {
+                    tracker.abort();
+                }
+                rooms.save_highscores().await;
+                rooms.save_heatmaps().await;
+                return Ok(());
+            }
+        };
         let ip = addr.ip();
 
-        // Check ban list and connection limits
-        {
+        // Check ban list and connection limits. Banned addresses are still
+        // upgraded, so the client is told why and stops reconnecting; they
+        // count against the limits like anyone else while that happens
+        let banned = {
             let mut state = conn_state.write().await;
 
-            // Check if IP is banned
-            if state.is_banned(&ip) {
-                warn!("Connection rejected (IP banned): {}", addr);
-                continue;
-            }
-
-            // Check connection limits
             if !state.try_add_connection(ip, max_connections, ip_limit) {
                 warn!("Connection rejected (limit reached): {}", addr);
                 continue;
             }
-        }
+            let banned = state.is_banned(&ip);
+            if banned {
+                warn!("Connection rejected (IP banned): {}", addr);
+            }
+            banned
+        };
 
-        let game_state = Arc::clone(&game_state);
+        let rooms = Arc::clone(&rooms);
         let conn_state = Arc::clone(&conn_state);
-        let chat_rx = chat_tx.subscribe();
-        let lb_rx = lb_tx.subscribe();
-        let world_rx = world_tx.subscribe();
-        let targeted_rx = targeted_tx.subscribe();
+        let tls = tls.clone();
+        let policy = Arc::clone(&policy);
 
         tokio::spawn(async move {
-            let result = handle_connection(stream, addr, game_state, chat_rx, lb_rx, world_rx, targeted_rx).await;
+            let result = serve_connection(stream, addr, tls, rooms, &policy, handshake_timeout, banned).await;
 
             // Always remove from connection tracking when done
-            {
-                let mut state = conn_state.write().await;
-                state.remove_connection(addr.ip());
-            }
+            conn_state.write().await.remove_connection(addr.ip());
 
             if let Err(e) = result {
                 error!("Connection error from {}: {}", addr, e);
@@ -307,43 +591,197 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
     }
 }
 
-/// Handle a single WebSocket connection.
-async fn handle_connection(
+/// Handshake callback that records the request path and refuses upgrades
+/// the [`UpgradePolicy`] doesn't allow with 403.
+struct UpgradeRequest<'a> {
+    policy: &'a UpgradePolicy,
+    path: &'a mut String,
+    rejected: &'a mut Option<UpgradeRejection>,
+}
+
+impl Callback for UpgradeRequest<'_> {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        *self.path = request.uri().path().to_string();
+        let origin = request.headers().get(ORIGIN).and_then(|origin| origin.to_str().ok());
+        if let Err(reason) = self.policy.check(origin, request.uri().query()) {
+            *self.rejected = Some(reason);
+            let mut forbidden = ErrorResponse::new(None);
+            *forbidden.status_mut() = StatusCode::FORBIDDEN;
+            return Err(forbidden);
+        }
+        Ok(response)
+    }
+}
+
+/// How a WebSocket upgrade ended.
+enum Upgrade<S> {
+    /// Upgraded, with the request path.
+    Accepted(Box<WebSocketStream<S>>, String),
+    /// Answered with 403.
+    Rejected { path: String, reason: UpgradeRejection },
+    /// The peer didn't finish in time.
+    TimedOut,
+}
+
+/// Complete the WebSocket upgrade, if `policy` lets it in and the peer
+/// finishes it within `timeout`.
+async fn accept_websocket<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    policy: &UpgradePolicy,
+    timeout: Duration,
+) -> anyhow::Result<Upgrade<S>> {
+    let mut path = String::from("/");
+    let mut rejected = None;
+    let request = UpgradeRequest { policy, path: &mut path, rejected: &mut rejected };
+    let Ok(result) = tokio::time::timeout(timeout, accept_hdr_async(stream, request)).await else {
+        return Ok(Upgrade::TimedOut);
+    };
+    match (result, rejected) {
+        (_, Some(reason)) => Ok(Upgrade::Rejected { path, reason }),
+        (Ok(ws_stream), None) => Ok(Upgrade::Accepted(Box::new(ws_stream), path)),
+        (Err(e), None) => Err(e.into()),
+    }
+}
+
+/// Encode an outbox frame as a WebSocket message.
+fn websocket_message(frame: Frame) -> Message {
+    match frame {
+        Frame::Binary(packet) => Message::Binary(packet),
+        Frame::Close { code, reason } => Message::Close(Some(CloseFrame { code: code.into(), reason: reason.into() })),
+    }
+}
+
+/// Handle an accepted TCP connection, starting with the TLS handshake when
+/// TLS is configured. That handshake shares the upgrade's deadline. A
+/// `banned` connection is closed with [`CloseCode::Banned`] once upgraded.
+async fn serve_connection(
     stream: TcpStream,
     addr: SocketAddr,
-    game_state: Arc<RwLock<GameState>>,
-    mut chat_rx: broadcast::Receiver<ChatBroadcast>,
-    mut lb_rx: broadcast::Receiver<LeaderboardBroadcast>,
-    mut world_rx: broadcast::Receiver<WorldUpdateBroadcast>,
-    mut targeted_rx: broadcast::Receiver<TargetedMessage>,
+    tls: Option<TlsAcceptor>,
+    rooms: Arc<RoomRegistry>,
+    policy: &UpgradePolicy,
+    handshake_timeout: Duration,
+    banned: bool,
 ) -> anyhow::Result<()> {
-    let ws_stream = accept_async(stream).await?;
-    info!("New connection from {}", addr);
+    let Some(acceptor) = tls else {
+        return handle_connection(stream, addr, rooms, policy, handshake_timeout, banned).await;
+    };
+    match crate::tls::accept(&acceptor, stream, handshake_timeout).await? {
+        Some(stream) => handle_connection(stream, addr, rooms, policy, handshake_timeout, banned).await,
+        None => {
+            warn!("Dropping {}: no TLS handshake within {:?}", addr, handshake_timeout);
+            rooms.get("/").metrics.handshake_timed_out(HandshakeStage::Upgrade);
+            Ok(())
+        }
+    }
+}
 
-    let (mut write, mut read) = ws_stream.split();
+/// Handle a single WebSocket connection.
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+    stream: S,
+    addr: SocketAddr,
+    rooms: Arc<RoomRegistry>,
+    policy: &UpgradePolicy,
+    handshake_timeout: Duration,
+    banned: bool,
+) -> anyhow::Result<()> {
+    // The request path picks the room
+    let (mut ws_stream, path) = match accept_websocket(stream, policy, handshake_timeout).await? {
+        Upgrade::Accepted(ws_stream, path) => (*ws_stream, path),
+        Upgrade::Rejected { path, reason } => {
+            warn!("Refused upgrade from {} to {}: {:?} check failed", addr, path, reason);
+            rooms.get(&path).metrics.upgrade_rejected(reason);
+            return Ok(());
+        }
+        Upgrade::TimedOut => {
+            warn!("Dropping {}: no WebSocket upgrade within {:?}", addr, handshake_timeout);
+            // No path yet, so it counts against the default room
+            rooms.get("/").metrics.handshake_timed_out(HandshakeStage::Upgrade);
+            return Ok(());
+        }
+    };
+    if banned {
+        let close = CloseFrame { code: (CloseCode::Banned as u16).into(), reason: CloseCode::Banned.description().into() };
+        let _ = tokio::time::timeout(handshake_timeout, ws_stream.close(Some(close))).await;
+        return Ok(());
+    }
+    let room = rooms.get(&path);
+    info!("New connection from {} to room {}", addr, room.path);
+
+    let game_state = Arc::clone(&room.game_state);
+    let metrics = Arc::clone(&room.metrics);
+    let RoomReceivers { mut chat_rx, mut lb_rx, mut world_rx } = room.subscribe();
+
+    let (write, mut read) = ws_stream.split();
 
     // Create client
-    let client_id = {
+    let (client_id, mut targeted_rx, max_packet_bytes, send_timeout, batch_packets) = {
         let mut state = game_state.write().await;
-        state.add_client(addr)
+        let client_id = state.add_client(addr);
+        // Registered under the lock, so nothing addressed to the client is missed
+        let targeted_rx = room.targeted_tx.register(client_id);
+        let server = &state.config.server;
+        (client_id, targeted_rx, server.max_packet_bytes, Duration::from_secs(server.send_timeout), server.batch_packets)
     };
+    let mut outbox = Outbox::spawn(write, websocket_message, client_id, addr, send_timeout, batch_packets, Arc::clone(&metrics));
 
     // Note: ClearAll and SetBorder are sent after handshake completes (packet 255)
 
-    // Track which nodes this client has seen (for delta updates)
-    let mut client_nodes: HashSet<u32> = HashSet::new();
+    // Negotiated protocol, learned from the first world update
+    let mut client_protocol = 0;
+    let mut lag = LagTracker::default();
+    let handshake_deadline = tokio::time::sleep(handshake_timeout);
+    tokio::pin!(handshake_deadline);
+    let mut awaiting_handshake = true;
 
     // Message loop - handle both incoming messages and broadcasts
     loop {
         tokio::select! {
+            // The writer gave up on a stalled or broken socket
+            _ = outbox.closed() => {
+                break;
+            }
+            // Upgraded, but never sent 0xFE / 0xFF
+            _ = &mut handshake_deadline, if awaiting_handshake => {
+                awaiting_handshake = false;
+                if !game_state.read().await.handshake_complete(client_id) {
+                    warn!("Dropping {}: no protocol handshake within {:?}", addr, handshake_timeout);
+                    metrics.handshake_timed_out(HandshakeStage::Protocol);
+                    break;
+                }
+            }
             // Handle incoming WebSocket messages
             msg = read.next() => {
                 match msg {
                     Some(Ok(Message::Binary(data))) => {
-                        let mut state = game_state.write().await;
-                        if let Err(e) = state.handle_packet(client_id, &data) {
+                        if data.len() > max_packet_bytes {
+                            warn!("Disconnecting {}: {}-byte packet exceeds the {}-byte limit", addr, data.len(), max_packet_bytes);
+                            break;
+                        }
+                        let (result, negotiated) = {
+                            let mut state = game_state.write().await;
+                            let result = state.handle_packet(client_id, &data);
+                            (result, state.negotiated_protocol(client_id))
+                        };
+                        if let Err(e) = result {
+                            // A refused handshake closes the socket with its code
+                            if let Some(rejected) = e.downcast_ref::<game::HandshakeRejected>() {
+                                info!("Disconnecting {}: {}", addr, rejected.reason);
+                                let _ = outbox.close(rejected.code as u16, rejected.reason.clone()).await;
+                                break;
+                            }
                             warn!("Packet error from {}: {}", addr, e);
                         }
+                        // The writer learns the protocol before the handshake's replies
+                        if client_protocol == 0
+                            && let Some(protocol) = negotiated
+                        {
+                            client_protocol = protocol;
+                            if let Err(e) = outbox.set_protocol(protocol).await {
+                                warn!("Failed to pass the protocol to the writer for {}: {}", addr, e);
+                                break;
+                            }
+                        }
                     }
                     Some(Ok(Message::Close(_))) => {
                         info!("Client {} disconnected", addr);
@@ -361,6 +799,7 @@ async fn handle_connection(
             }
             // Handle chat broadcasts
             chat_msg = chat_rx.recv() => {
+                lag.observe(Channel::Chat, &chat_msg, &metrics, addr);
                 if let Ok(chat) = chat_msg {
                     let packet = protocol::packets::build_chat_message(
                         chat.color,
@@ -370,7 +809,7 @@ async fn handle_connection(
                         false, // is_admin
                         false, // is_mod
                     );
-                    if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                    if let Err(e) = outbox.send(packet.finish()).await {
                         warn!("Failed to send chat to {}: {}", addr, e);
                         break;
                     }
@@ -378,7 +817,27 @@ async fn handle_connection(
             }
             // Handle leaderboard broadcasts
             lb_msg = lb_rx.recv() => {
+                lag.observe(Channel::Leaderboard, &lb_msg, &metrics, addr);
                 if let Ok(lb) = lb_msg {
+                    // Only cogar clients know the Roster opcode
+                    if let Some(entries) = &lb.roster
+                        && client_protocol >= protocol::packets::COGAR_PROTOCOL
+                    {
+                        let packet = protocol::packets::build_roster(entries);
+                        if let Err(e) = outbox.send(packet.finish()).await {
+                            warn!("Failed to send Roster to {}: {}", addr, e);
+                            break;
+                        }
+                    }
+                    if let Some(lines) = &lb.text {
+                        let packet = protocol::packets::build_leaderboard_text(lines);
+                        if let Err(e) = outbox.send(packet.finish()).await {
+                            warn!("Failed to send text leaderboard to {}: {}", addr, e);
+                            break;
+                        }
+                        // A text board replaces the ranked list
+                        continue;
+                    }
                     match lb.gamemode_id {
                         1 => {
                             // Teams mode (Pie chart)
@@ -386,20 +845,20 @@ async fn handle_connection(
                                 .map(|e| e.score)
                                 .collect();
                             let packet = protocol::packets::build_leaderboard_pie(&team_scores);
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send pie leaderboard to {}: {}", addr, e);
                                 break;
                             }
                         }
                         _ => {
                             // FFA mode
-                            let entries: Vec<(bool, &str)> = lb.entries.iter()
+                            let entries: Vec<(bool, u32, &str)> = lb.entries.iter()
                                 .take(10) // Top 10
-                                .map(|e| (e.client_id == client_id, e.name.as_str()))
+                                .map(|e| (e.client_id == client_id, e.client_id, e.name.as_str()))
                                 .collect();
 
-                            let packet = protocol::packets::build_leaderboard_ffa(&entries);
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            let packet = protocol::packets::build_leaderboard_ffa(client_protocol, &entries);
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send ffa leaderboard to {}: {}", addr, e);
                                 break;
                             }
@@ -409,150 +868,44 @@ async fn handle_connection(
             }
             // Handle world update broadcasts
             world_msg = world_rx.recv() => {
+                lag.observe(Channel::World, &world_msg, &metrics, addr);
                 if let Ok(world) = world_msg {
                     // Get this client's view data
                     let client_view = match world.client_data.get(&client_id) {
                         Some(v) => v,
                         None => continue, // Client not in game yet
                     };
-
-                    // Calculate viewport bounds
-                    let scale = client_view.scale.max(0.15);
-                    let view_half_w = (1920.0 / scale) / 2.0;
-                    let view_half_h = (1080.0 / scale) / 2.0;
-                    let view_min_x = client_view.center_x - view_half_w;
-                    let view_min_y = client_view.center_y - view_half_h;
-                    let view_max_x = client_view.center_x + view_half_w;
-                    let view_max_y = client_view.center_y + view_half_h;
-
-                    // Find cells in viewport
-                    let mut view_nodes: HashSet<u32> = HashSet::new();
-                    for cell in &world.cells {
-                        // Check if cell is in viewport (with some margin for size)
-                        let margin = cell.size;
-                        if cell.x + margin >= view_min_x
-                            && cell.x - margin <= view_max_x
-                            && cell.y + margin >= view_min_y
-                            && cell.y - margin <= view_max_y
-                        {
-                            view_nodes.insert(cell.node_id);
-                        }
-                    }
-
-                    // Also always include own cells
-                    for &cell_id in &client_view.cell_ids {
-                        view_nodes.insert(cell_id);
-                    }
-
-                    // Force-include all minion cells (always visible to owner)
-                    for &minion_id in &client_view.minion_ids {
-                        for cell in &world.cells {
-                            if cell.owner_id == Some(minion_id) {
-                                view_nodes.insert(cell.node_id);
-                            }
-                        }
-                    }
-
-                    // Calculate add/update/delete sets
-                    let mut add_nodes = Vec::new();
-                    let mut upd_nodes = Vec::new();
-                    let mut del_nodes = Vec::new();
-
-                    // Nodes to add (in view but not in client_nodes)
-                    for cell in &world.cells {
-                        if view_nodes.contains(&cell.node_id) {
-                            let is_new = !client_nodes.contains(&cell.node_id);
-
-                            let update_cell = protocol::packets::UpdateCell {
-                                node_id: cell.node_id,
-                                x: cell.x as i32,
-                                y: cell.y as i32,
-                                size: cell.size as u16,
-                                color: cell.color,
-                                flags: protocol::packets::CellFlags {
-                                    is_spiked: cell.cell_type == 2, // Virus
-                                    is_player: true, // Always send color (needed for Rainbow mode)
-                                    has_skin: is_new && cell.skin.is_some(),
-                                    has_name: is_new && cell.name.is_some(),
-                                    is_agitated: false,
-                                    is_ejected: cell.cell_type == 3,
-                                    is_food: cell.cell_type == 1,
-                                },
-                                skin: if is_new { cell.skin.clone() } else { None },
-                                name: if is_new { cell.name.clone() } else { None }, // Send name for all cells when adding
-                            };
-
-                            if is_new {
-                                add_nodes.push(update_cell);
-                            } else {
-                                upd_nodes.push(update_cell);
-                            }
-                        }
-                    }
-
-                    // Nodes to delete (in client_nodes but not in view)
-                    for &node_id in &client_nodes {
-                        if !view_nodes.contains(&node_id) {
-                            del_nodes.push(node_id);
-                        }
-                    }
-
-                    // Build eat records
-                    let eat_records: Vec<protocol::packets::EatRecord> = world.eaten.iter()
-                        .filter(|(eaten_id, eater_id)| {
-                            view_nodes.contains(eaten_id)
-                                || view_nodes.contains(eater_id)
-                                || client_nodes.contains(eaten_id)
-                                || client_nodes.contains(eater_id)
-                        })
-                        .map(|&(eaten_id, eater_id)| protocol::packets::EatRecord { eaten_id, eater_id })
-                        .collect();
-
-                    // Update client_nodes
-                    client_nodes = view_nodes;
-
-                    // Build and send the packet
-                    let packet = protocol::packets::build_update_nodes(
-                        client_view.protocol,
-                        client_view.scramble_id,
-                        client_view.scramble_x,
-                        client_view.scramble_y,
-                        &add_nodes,
-                        &upd_nodes,
-                        &eat_records,
-                        &del_nodes,
-                    );
-
-                    if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
-                        warn!("Failed to send world update to {}: {}", addr, e);
-                        break;
-                    }
+                    client_protocol = client_view.protocol;
+                    outbox.send_world(world, lag.take_resync());
                 }
             }
             // Handle targeted messages (AddNode, etc.)
             targeted_msg = targeted_rx.recv() => {
+                lag.observe(Channel::Targeted, &targeted_msg, &metrics, addr);
                 if let Ok(msg) = targeted_msg {
-                    // Only process messages for this client
-                    if msg.client_id != client_id {
-                        continue;
-                    }
-
                     match msg.message {
                         TargetedMessageType::AddNode { node_id, scramble_id } => {
                             let packet = protocol::packets::build_add_node(node_id, scramble_id);
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send AddNode to {}: {}", addr, e);
                                 break;
                             }
                         }
                         TargetedMessageType::ClearAll => {
                             let packet = protocol::packets::build_clear_all();
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send ClearAll to {}: {}", addr, e);
                                 break;
                             }
                         }
-                        TargetedMessageType::SetBorder { min_x, min_y, max_x, max_y, scramble_x, scramble_y, game_type, server_name } => {
+                        TargetedMessageType::ClearOwned => {
+                            let packet = protocol::packets::build_clear_owned();
+                            if let Err(e) = outbox.send(packet.finish()).await {
+                                warn!("Failed to send ClearOwned to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        TargetedMessageType::SetBorder { min_x, min_y, max_x, max_y, scramble_x, scramble_y, game_type, server_name, unlimited_zoom } => {
                             // Apply scramble to border coordinates (as the JS does)
                             let packet = protocol::packets::build_set_border(
                                 min_x + scramble_x as f64,
@@ -560,16 +913,31 @@ async fn handle_connection(
                                 max_x + scramble_x as f64,
                                 max_y + scramble_y as f64,
                                 game_type,
-                                &server_name
+                                &server_name,
+                                unlimited_zoom,
                             );
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send SetBorder to {}: {}", addr, e);
                                 break;
                             }
                         }
+                        TargetedMessageType::MapObjects { shapes, scramble_x, scramble_y } => {
+                            let packet = protocol::packets::build_map_objects(scramble_x, scramble_y, &shapes);
+                            if let Err(e) = outbox.send(packet.finish()).await {
+                                warn!("Failed to send MapObjects to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        TargetedMessageType::DrawLine { x, y, scramble_x, scramble_y } => {
+                            let packet = protocol::packets::build_draw_line(scramble_x, scramble_y, x, y);
+                            if let Err(e) = outbox.send(packet.finish()).await {
+                                warn!("Failed to send DrawLine to {}: {}", addr, e);
+                                break;
+                            }
+                        }
                         TargetedMessageType::ServerStat { json } => {
                             let packet = protocol::packets::build_server_stat(&json);
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send ServerStat to {}: {}", addr, e);
                                 break;
                             }
@@ -583,7 +951,7 @@ async fn handle_connection(
                                 false,
                                 false,
                             );
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send ChatMessage to {}: {}", addr, e);
                                 break;
                             }
@@ -595,11 +963,30 @@ async fn handle_connection(
                                 scramble_y,
                                 &player_cells,
                             );
-                            if let Err(e) = write.send(Message::Binary(packet.finish().to_vec().into())).await {
+                            if let Err(e) = outbox.send(packet.finish()).await {
                                 warn!("Failed to send XrayData to {}: {}", addr, e);
                                 break;
                             }
                         }
+                        TargetedMessageType::MergeTimer { timers, scramble_id } => {
+                            let packet = protocol::packets::build_merge_timer(scramble_id, &timers);
+                            if let Err(e) = outbox.send(packet.finish()).await {
+                                warn!("Failed to send MergeTimer to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        TargetedMessageType::DeathInfo { cause, killer_name, killer_mass, killer_id } => {
+                            let packet = protocol::packets::build_death_info(cause, &killer_name, killer_mass, killer_id);
+                            if let Err(e) = outbox.send(packet.finish()).await {
+                                warn!("Failed to send DeathInfo to {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        TargetedMessageType::Disconnect { code, reason } => {
+                            info!("Disconnecting {}: {}", addr, reason);
+                            let _ = outbox.close(code as u16, reason).await;
+                            break;
+                        }
                     }
                 }
             }
@@ -609,8 +996,166 @@ async fn handle_connection(
     // Remove client
     {
         let mut state = game_state.write().await;
-        state.remove_client(client_id);
+        state.disconnect_client(client_id);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{render, RoomSample};
+    use tokio::io::AsyncReadExt;
+
+    /// Accept one connection from `connect` and run it through
+    /// [`serve_connection`] with a short handshake deadline, returning the
+    /// room's rendered metrics once the server side has given up.
+    async fn serve_one<F, Fut>(tls: Option<TlsAcceptor>, policy: UpgradePolicy, connect: F) -> String
+    where
+        F: FnOnce(TcpStream) -> Fut,
+        Fut: std::future::Future<Output = ()>,
+    {
+        let rooms = Arc::new(RoomRegistry::start(&Config::default(), None).await);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, addr) = listener.accept().await.unwrap();
+        let server = serve_connection(stream, addr, tls, Arc::clone(&rooms), &policy, Duration::from_millis(100), false);
+        let (result, ()) = tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(server, connect(client)) })
+            .await
+            .expect("connection was never dropped");
+        result.unwrap();
+        let room = rooms.get("/");
+        render(&[RoomSample { path: "/", metrics: &room.metrics, queued: [0; 4] }])
+    }
+
+    #[tokio::test]
+    async fn test_silent_tcp_connection_is_dropped() {
+        let metrics = serve_one(None, UpgradePolicy::default(), |mut client| async move {
+            // Sends nothing; the server closes the socket
+            let mut buf = [0u8; 16];
+            assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+        }).await;
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"websocket\"} 1"));
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"protocol\"} 0"));
+    }
+
+    #[tokio::test]
+    async fn test_upgraded_connection_without_handshake_is_dropped() {
+        let metrics = serve_one(None, UpgradePolicy::default(), |client| async move {
+            let (mut ws, _) = tokio_tungstenite::client_async("ws://localhost/", client).await.unwrap();
+            // Never sends 0xFE / 0xFF; read until the server hangs up
+            while let Some(Ok(_)) = ws.next().await {}
+        }).await;
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"websocket\"} 0"));
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"protocol\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_tls_connection_upgrades() {
+        use tokio_rustls::rustls::pki_types::ServerName;
+        use tokio_rustls::rustls::{ClientConfig, RootCertStore, crypto::ring};
+
+        let dir = std::env::temp_dir().join(format!("cogar-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let self_signed = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let tls_config = crate::config::TlsConfig {
+            cert_path: dir.join("cert.pem").to_string_lossy().into_owned(),
+            key_path: dir.join("key.pem").to_string_lossy().into_owned(),
+        };
+        std::fs::write(&tls_config.cert_path, self_signed.cert.pem()).unwrap();
+        std::fs::write(&tls_config.key_path, self_signed.key_pair.serialize_pem()).unwrap();
+        let acceptor = crate::tls::load_acceptor(&tls_config).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // A client that trusts only the self-signed certificate
+        let mut roots = RootCertStore::empty();
+        roots.add(self_signed.cert.der().clone()).unwrap();
+        let client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+
+        let metrics = serve_one(Some(acceptor), UpgradePolicy::default(), |client| async move {
+            let server_name = ServerName::try_from("localhost").unwrap();
+            let stream = connector.connect(server_name, client).await.unwrap();
+            let (mut ws, _) = tokio_tungstenite::client_async("wss://localhost/", stream).await.unwrap();
+            while let Some(Ok(_)) = ws.next().await {}
+        }).await;
+        // Upgraded over TLS, then dropped for skipping the protocol handshake
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"websocket\"} 0"));
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"protocol\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_protocol_gets_a_close_code() {
+        use futures_util::SinkExt;
+
+        serve_one(None, UpgradePolicy::default(), |client| async move {
+            let (mut ws, _) = tokio_tungstenite::client_async("ws://localhost/", client).await.unwrap();
+            ws.send(Message::Binary(vec![0xFE, 99, 0, 0, 0].into())).await.unwrap();
+            let close = loop {
+                match ws.next().await {
+                    Some(Ok(Message::Close(frame))) => break frame.unwrap(),
+                    Some(Ok(_)) => continue,
+                    other => panic!("expected a close frame, got {:?}", other),
+                }
+            };
+            assert_eq!(u16::from(close.code), CloseCode::UnsupportedProtocol as u16);
+            assert_eq!(close.reason.as_str(), "unsupported protocol 99");
+        }).await;
+    }
+
+    #[tokio::test]
+    async fn test_foreign_origin_is_refused() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let config = crate::config::ServerConfig {
+            allowed_origins: vec!["https://play.example.com".to_string()],
+            ..Default::default()
+        };
+        let metrics = serve_one(None, UpgradePolicy::new(&config), |client| async move {
+            let mut request = "ws://localhost/".into_client_request().unwrap();
+            request.headers_mut().insert(ORIGIN, "https://evil.example".parse().unwrap());
+            match tokio_tungstenite::client_async(request, client).await {
+                Err(tokio_tungstenite::tungstenite::Error::Http(response)) => assert_eq!(response.status(), StatusCode::FORBIDDEN),
+                other => panic!("expected a 403, got {:?}", other.map(|(_, response)| response.status())),
+            }
+        }).await;
+        assert!(metrics.contains("cogar_rejected_upgrades_total{room=\"/\",reason=\"origin\"} 1"));
+        assert!(metrics.contains("cogar_handshake_timeouts_total{room=\"/\",stage=\"websocket\"} 0"));
+    }
+
+    #[test]
+    fn test_view_margin_covers_lead_and_keeps_known_cells() {
+        let cell = |node_id: u32, x: f32, lead: f32| WorldCell {
+            node_id, x, y: 0.0, size: 100.0, color: Color::default(), cell_type: 0,
+            name: None, skin: None, owner_id: None, is_bonus: false, is_boosted: false, lead,
+            identity_changed: false,
+        };
+        let view = ClientViewData {
+            center_x: 0.0, center_y: 0.0, scale: 1.0, view_width: 1920.0, view_height: 1080.0,
+            cell_ids: Vec::new(), minion_ids: Vec::new(), protocol: 6, scramble_id: 0,
+            scramble_x: 0, scramble_y: 0, name: String::new(), skin: None, spectating: false,
+        };
+        let visible = |cells: Vec<WorldCell>, view_margin: f32, known: &[u32]| {
+            let world = WorldUpdateBroadcast {
+                cells, eaten: Vec::new(), removed: Vec::new(), client_data: HashMap::new(), view_margin,
+            };
+            let mut nodes: HashSet<u32> = known.iter().copied().collect();
+            build_view_update(&world, &view, &mut nodes, false);
+            let mut nodes: Vec<u32> = nodes.into_iter().collect();
+            nodes.sort();
+            nodes
+        };
+
+        // 130 units past the right edge (at 960): only a cell heading in is sent
+        assert_eq!(visible(vec![cell(1, 1090.0, 0.0), cell(2, 1090.0, 50.0)], 1.0, &[]), [2]);
+        assert_eq!(visible(vec![cell(1, 1090.0, 0.0)], 2.0, &[]), [1]);
+        // A known cell lingers a little past its margin before it's deleted
+        assert_eq!(visible(vec![cell(1, 1090.0, 0.0)], 1.0, &[1]), [1]);
+        assert_eq!(visible(vec![cell(1, 1200.0, 0.0)], 1.0, &[1]), [] as [u32; 0]);
+    }
+}
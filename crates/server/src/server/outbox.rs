@@ -0,0 +1,470 @@
+//! Per-connection outgoing queue, drained by a writer task so a client with a
+//! full TCP window never stalls its connection's reads.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{Sink, SinkExt};
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, warn};
+
+use super::{build_view_update, WorldUpdateBroadcast};
+use crate::metrics::Metrics;
+
+/// Packets queued per connection before senders start waiting.
+const QUEUE_CAPACITY: usize = 64;
+
+/// Why a packet could not be queued.
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error("send queue full for {0:?}")]
+    Stalled(Duration),
+    #[error("connection closed")]
+    Closed,
+}
+
+/// What the writer hands its sink, through the `to_message` it was spawned with.
+pub enum Frame {
+    Binary(Bytes),
+    Close { code: u16, reason: String },
+}
+
+enum Outgoing {
+    Packet(Bytes),
+    /// The protocol agreed in the handshake.
+    Protocol(u32),
+    Close { code: u16, reason: String },
+}
+
+/// The newest world update for the writer to build the client's view from.
+struct WorldFrame {
+    /// Increments per update; a gap means updates were coalesced.
+    seq: u64,
+    world: WorldUpdateBroadcast,
+    resync: bool,
+}
+
+/// Sending half of a connection's outgoing queue.
+pub struct Outbox {
+    packets: mpsc::Sender<Outgoing>,
+    world: watch::Sender<Option<WorldFrame>>,
+    next_seq: u64,
+    send_timeout: Duration,
+    metrics: Arc<Metrics>,
+}
+
+impl Outbox {
+    /// Spawn the writer task for `sink`. A write that takes longer than
+    /// `send_timeout` ends it, as does a queue that stays full that long.
+    /// With `batch`, packets that are ready together go to cogar-protocol
+    /// clients as one Batch frame.
+    pub fn spawn<S, M>(
+        sink: S,
+        to_message: fn(Frame) -> M,
+        client_id: u32,
+        addr: SocketAddr,
+        send_timeout: Duration,
+        batch: bool,
+        metrics: Arc<Metrics>,
+    ) -> Self
+    where
+        S: Sink<M> + Unpin + Send + 'static,
+        S::Error: Display,
+        M: Send + 'static,
+    {
+        let (packets, packets_rx) = mpsc::channel(QUEUE_CAPACITY);
+        let (world, world_rx) = watch::channel(None);
+        let writer = Writer {
+            sink,
+            to_message,
+            client_id,
+            addr,
+            send_timeout,
+            batch,
+            protocol: 0,
+            metrics: Arc::clone(&metrics),
+            client_nodes: HashSet::new(),
+            last_seq: 0,
+        };
+        tokio::spawn(writer.run(packets_rx, world_rx));
+        Self { packets, world, next_seq: 1, send_timeout, metrics }
+    }
+
+    /// Queue a packet, waiting at most `send_timeout` for room.
+    pub async fn send(&self, packet: Bytes) -> Result<(), SendError> {
+        self.push(Outgoing::Packet(packet)).await
+    }
+
+    /// Replace the pending world update; only the newest is ever written.
+    /// `resync` resends the whole view, for a connection that missed updates.
+    pub fn send_world(&mut self, world: WorldUpdateBroadcast, resync: bool) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.world.send_replace(Some(WorldFrame { seq, world, resync }));
+    }
+
+    /// Tell the writer the protocol agreed in the handshake, so the packets
+    /// queued after it are batched for that protocol.
+    pub async fn set_protocol(&self, protocol: u32) -> Result<(), SendError> {
+        self.push(Outgoing::Protocol(protocol)).await
+    }
+
+    /// Close the WebSocket with `code` and `reason` once the packets queued
+    /// so far are written.
+    pub async fn close(&self, code: u16, reason: String) -> Result<(), SendError> {
+        self.push(Outgoing::Close { code, reason }).await
+    }
+
+    /// Completes when the writer has stopped, e.g. after a stalled write.
+    pub async fn closed(&self) {
+        self.packets.closed().await
+    }
+
+    async fn push(&self, item: Outgoing) -> Result<(), SendError> {
+        // Counted before sending so the writer never dequeues an uncounted packet
+        let is_packet = matches!(item, Outgoing::Packet(_));
+        if is_packet {
+            self.metrics.packet_queued();
+        }
+        let result = self.packets.send_timeout(item, self.send_timeout).await;
+        if result.is_err() && is_packet {
+            self.metrics.packet_dequeued();
+        }
+        match result {
+            Ok(()) => Ok(()),
+            Err(SendTimeoutError::Timeout(_)) => {
+                self.metrics.slow_client_dropped();
+                Err(SendError::Stalled(self.send_timeout))
+            }
+            Err(SendTimeoutError::Closed(_)) => Err(SendError::Closed),
+        }
+    }
+}
+
+struct Writer<S, M> {
+    sink: S,
+    to_message: fn(Frame) -> M,
+    client_id: u32,
+    addr: SocketAddr,
+    send_timeout: Duration,
+    batch: bool,
+    /// Negotiated protocol, from the handshake or the latest world update.
+    protocol: u32,
+    metrics: Arc<Metrics>,
+    /// Nodes the client has been sent (for delta updates).
+    client_nodes: HashSet<u32>,
+    last_seq: u64,
+}
+
+impl<S, M> Writer<S, M>
+where
+    S: Sink<M> + Unpin,
+    S::Error: Display,
+{
+    async fn run(mut self, mut packets: mpsc::Receiver<Outgoing>, mut world: watch::Receiver<Option<WorldFrame>>) {
+        loop {
+            let mut batch = tokio::select! {
+                // Queued packets go first; they may set up state the world update relies on
+                biased;
+                item = packets.recv() => match item {
+                    Some(Outgoing::Packet(packet)) => {
+                        self.metrics.packet_dequeued();
+                        vec![packet]
+                    }
+                    Some(Outgoing::Protocol(protocol)) => {
+                        self.protocol = protocol;
+                        continue;
+                    }
+                    Some(Outgoing::Close { code, reason }) => {
+                        self.close(code, reason).await;
+                        break;
+                    }
+                    None => {
+                        let _ = self.sink.close().await;
+                        break;
+                    }
+                },
+                changed = world.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let frame = world.borrow_and_update();
+                    match frame.as_ref() {
+                        Some(frame) => self.build_world(frame),
+                        None => continue,
+                    }
+                }
+            };
+
+            // Whatever else is ready already goes out in the same frame
+            let mut closing = None;
+            if self.batches() {
+                loop {
+                    match packets.try_recv() {
+                        Ok(Outgoing::Packet(packet)) => {
+                            self.metrics.packet_dequeued();
+                            batch.push(packet);
+                        }
+                        Ok(Outgoing::Protocol(protocol)) => self.protocol = protocol,
+                        Ok(Outgoing::Close { code, reason }) => {
+                            closing = Some((code, reason));
+                            break;
+                        }
+                        Err(_) => break,
+                    }
+                }
+                if closing.is_none() && world.has_changed().unwrap_or(false) {
+                    let frame = world.borrow_and_update();
+                    if let Some(frame) = frame.as_ref() {
+                        batch.extend(self.build_world(frame));
+                    }
+                }
+                if batch.len() > 1 {
+                    batch = vec![protocol::packets::build_batch(&batch).finish()];
+                }
+            }
+
+            if !self.write(batch).await {
+                break;
+            }
+            if let Some((code, reason)) = closing {
+                self.close(code, reason).await;
+                break;
+            }
+        }
+
+        // Whatever is still queued is never written
+        packets.close();
+        while packets.try_recv().is_ok() {
+            self.metrics.packet_dequeued();
+        }
+    }
+
+    /// Whether ready packets are combined into Batch frames.
+    fn batches(&self) -> bool {
+        self.batch && self.protocol >= protocol::packets::COGAR_PROTOCOL
+    }
+
+    /// The packets for one world update: the view delta, plus the client's
+    /// own cells on a resync and its camera when spectating.
+    fn build_world(&mut self, frame: &WorldFrame) -> Vec<Bytes> {
+        let skipped = frame.seq - self.last_seq - 1;
+        self.last_seq = frame.seq;
+        if skipped > 0 {
+            self.metrics.world_updates_coalesced(skipped);
+        }
+        let Some(client_view) = frame.world.client_data.get(&self.client_id) else {
+            return Vec::new();
+        };
+        self.protocol = client_view.protocol;
+        // Skipped updates may have carried name and skin changes
+        let resync = frame.resync || skipped > 0;
+
+        let mut batch = Vec::new();
+        if resync {
+            for &node_id in &client_view.cell_ids {
+                batch.push(protocol::packets::build_add_node(node_id, client_view.scramble_id).finish());
+            }
+        }
+        batch.push(build_view_update(&frame.world, client_view, &mut self.client_nodes, resync).finish());
+        if client_view.spectating {
+            let packet = protocol::packets::build_update_position(
+                client_view.center_x + client_view.scramble_x as f32,
+                client_view.center_y + client_view.scramble_y as f32,
+                client_view.scale,
+            );
+            batch.push(packet.finish());
+        }
+        batch
+    }
+
+    /// Send a close frame, then close the sink.
+    async fn close(&mut self, code: u16, reason: String) {
+        let frame = (self.to_message)(Frame::Close { code, reason });
+        let _ = tokio::time::timeout(self.send_timeout, self.sink.send(frame)).await;
+        let _ = self.sink.close().await;
+    }
+
+    /// Write packets in order; false once the connection is unusable.
+    async fn write(&mut self, batch: Vec<Bytes>) -> bool {
+        for packet in batch {
+            let len = packet.len();
+            match tokio::time::timeout(self.send_timeout, self.sink.send((self.to_message)(Frame::Binary(packet)))).await {
+                Ok(Ok(())) => self.metrics.add_bytes_sent(len),
+                Ok(Err(e)) => {
+                    // Usually the client already closed; the connection task logs that
+                    debug!("Failed to send to {}: {}", self.addr, e);
+                    return false;
+                }
+                Err(_) => {
+                    warn!("Disconnecting {}: a write stalled for {:?}", self.addr, self.send_timeout);
+                    self.metrics.slow_client_dropped();
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{render, RoomSample};
+    use crate::server::ClientViewData;
+    use protocol::packets::COGAR_PROTOCOL;
+    use std::collections::HashMap;
+    use tokio::sync::Semaphore;
+
+    fn world(cell_ids: Vec<u32>) -> WorldUpdateBroadcast {
+        world_for(cell_ids, 6)
+    }
+
+    fn world_for(cell_ids: Vec<u32>, protocol: u32) -> WorldUpdateBroadcast {
+        let view = ClientViewData {
+            center_x: 0.0,
+            center_y: 0.0,
+            scale: 1.0,
+            view_width: 1920.0,
+            view_height: 1080.0,
+            cell_ids,
+            minion_ids: Vec::new(),
+            protocol,
+            scramble_id: 0,
+            scramble_x: 0,
+            scramble_y: 0,
+            name: String::new(),
+            skin: None,
+            spectating: false,
+        };
+        WorldUpdateBroadcast { cells: Vec::new(), eaten: Vec::new(), removed: Vec::new(), client_data: HashMap::from([(1, view)]), view_margin: 1.0 }
+    }
+
+    /// The tests' sinks take packets as-is; a close frame reads as empty.
+    fn packet(frame: Frame) -> Bytes {
+        match frame {
+            Frame::Binary(packet) => packet,
+            Frame::Close { .. } => Bytes::new(),
+        }
+    }
+
+    fn rendered(metrics: &Metrics) -> String {
+        render(&[RoomSample { path: "/", metrics, queued: [0; 4] }])
+    }
+
+    #[tokio::test]
+    async fn test_stalled_writer_coalesces_world_updates_then_disconnects() {
+        // A sink that writes one packet per permit, so the test controls when it stalls
+        let permits = Arc::new(Semaphore::new(0));
+        let (written_tx, mut written) = mpsc::unbounded_channel::<Bytes>();
+        let sink = Box::pin(futures_util::sink::unfold(
+            (Arc::clone(&permits), written_tx),
+            |(permits, written_tx), packet: Bytes| async move {
+                permits.acquire().await.unwrap().forget();
+                written_tx.send(packet).unwrap();
+                Ok::<_, std::convert::Infallible>((permits, written_tx))
+            },
+        ));
+        let metrics = Arc::new(Metrics::default());
+        let mut outbox = Outbox::spawn(sink, packet, 1, "127.0.0.1:1".parse().unwrap(), Duration::from_millis(200), false, Arc::clone(&metrics));
+
+        // The first update blocks in the sink; the next three pile up behind it
+        outbox.send_world(world(vec![7]), false);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        for _ in 0..3 {
+            outbox.send_world(world(vec![7]), false);
+        }
+        permits.add_permits(3);
+        written.recv().await.unwrap();
+
+        // Only the newest is written, preceded by the client's own cell as a resync
+        let add_node = written.recv().await.unwrap();
+        assert_eq!(add_node[0], 0x20);
+        written.recv().await.unwrap();
+        assert!(rendered(&metrics).contains("cogar_world_updates_coalesced_total{room=\"/\"} 2"));
+
+        // Nothing drains the sink any more: the writer gives up and the client is dropped
+        outbox.send_world(world(vec![7]), false);
+        tokio::time::timeout(Duration::from_secs(2), outbox.closed()).await.unwrap();
+        assert!(matches!(outbox.send(Bytes::from_static(b"x")).await, Err(SendError::Closed)));
+        let text = rendered(&metrics);
+        assert!(text.contains("cogar_slow_clients_dropped_total{room=\"/\"} 1"));
+        assert!(text.contains("cogar_send_queue_packets{room=\"/\"} 0"));
+    }
+
+    #[tokio::test]
+    async fn test_ready_packets_are_batched_for_cogar_clients() {
+        let (written_tx, mut written) = mpsc::unbounded_channel::<Bytes>();
+        let sink = Box::pin(futures_util::sink::unfold(written_tx, |written_tx, packet: Bytes| async move {
+            written_tx.send(packet).unwrap();
+            Ok::<_, std::convert::Infallible>(written_tx)
+        }));
+        let metrics = Arc::new(Metrics::default());
+        let mut outbox = Outbox::spawn(sink, packet, 1, "127.0.0.1:1".parse().unwrap(), Duration::from_secs(1), true, metrics);
+
+        // The first world update tells the writer the protocol
+        outbox.send_world(world_for(vec![7], COGAR_PROTOCOL), false);
+        assert_eq!(written.recv().await.unwrap()[0], 0x10);
+
+        // Everything queued before the writer wakes goes out as one frame
+        let chat = Bytes::from_static(&[0x63, 1]);
+        let clear = Bytes::from_static(&[0x12]);
+        outbox.send(chat.clone()).await.unwrap();
+        outbox.send(clear.clone()).await.unwrap();
+        outbox.send_world(world_for(vec![7], COGAR_PROTOCOL), false);
+        let frame = written.recv().await.unwrap();
+        let Ok(protocol::packets::ServerPacket::Batch { packets }) = protocol::packets::ServerPacket::parse(&frame, COGAR_PROTOCOL) else {
+            panic!("expected a batch");
+        };
+        assert_eq!(packets.len(), 3);
+        assert_eq!((&packets[0], &packets[1], packets[2][0]), (&chat, &clear, 0x10));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_replies_are_batched_for_the_negotiated_protocol() {
+        let (written_tx, mut written) = mpsc::unbounded_channel::<Bytes>();
+        let sink = Box::pin(futures_util::sink::unfold(written_tx, |written_tx, packet: Bytes| async move {
+            written_tx.send(packet).unwrap();
+            Ok::<_, std::convert::Infallible>(written_tx)
+        }));
+        let metrics = Arc::new(Metrics::default());
+        let outbox = Outbox::spawn(sink, packet, 1, "127.0.0.1:1".parse().unwrap(), Duration::from_secs(1), true, metrics);
+
+        // No world update yet: the handshake says which protocol to batch for
+        let clear = Bytes::from_static(&[0x12]);
+        let border = Bytes::from_static(&[0x40]);
+        outbox.set_protocol(COGAR_PROTOCOL).await.unwrap();
+        outbox.send(clear.clone()).await.unwrap();
+        outbox.send(border.clone()).await.unwrap();
+        let frame = written.recv().await.unwrap();
+        let Ok(protocol::packets::ServerPacket::Batch { packets }) = protocol::packets::ServerPacket::parse(&frame, COGAR_PROTOCOL) else {
+            panic!("expected a batch");
+        };
+        assert_eq!(packets, vec![clear, border]);
+    }
+
+    #[tokio::test]
+    async fn test_vanilla_protocols_are_never_batched() {
+        let (written_tx, mut written) = mpsc::unbounded_channel::<Bytes>();
+        let sink = Box::pin(futures_util::sink::unfold(written_tx, |written_tx, packet: Bytes| async move {
+            written_tx.send(packet).unwrap();
+            Ok::<_, std::convert::Infallible>(written_tx)
+        }));
+        let metrics = Arc::new(Metrics::default());
+        let outbox = Outbox::spawn(sink, packet, 1, "127.0.0.1:1".parse().unwrap(), Duration::from_secs(1), true, metrics);
+
+        // Third-party protocol 17 clients can't read 0x70
+        let clear = Bytes::from_static(&[0x12]);
+        let border = Bytes::from_static(&[0x40]);
+        outbox.set_protocol(17).await.unwrap();
+        outbox.send(clear.clone()).await.unwrap();
+        outbox.send(border.clone()).await.unwrap();
+        assert_eq!(written.recv().await.unwrap(), clear);
+        assert_eq!(written.recv().await.unwrap(), border);
+    }
+}
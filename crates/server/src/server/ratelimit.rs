@@ -0,0 +1,236 @@
+//! Per-client rate limiting of action packets, and per-IP limiting of spawns.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use crate::config::PacketLimitsConfig;
+
+/// Refills at `rate` tokens per second up to `capacity`; each packet takes one.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket. A `rate` of 0 never runs dry.
+    pub fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        Self { rate, capacity, tokens: capacity, last_refill: now }
+    }
+
+    /// Take a token if one is available.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        if self.rate <= 0.0 {
+            return true;
+        }
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Rate-limited client actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Split,
+    Eject,
+    Join,
+    Chat,
+}
+
+/// What to do with an action packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Drop,
+    /// Far over the limit for a whole abuse window; disconnect the client.
+    Abuse,
+}
+
+/// Action counts within one abuse window.
+#[derive(Debug, Clone)]
+struct AbuseWindow {
+    start: Instant,
+    split: u32,
+    eject: u32,
+    join: u32,
+    chat: u32,
+}
+
+/// A client's buckets, abuse window and drop counter.
+#[derive(Debug, Clone)]
+pub struct PacketLimiter {
+    split: TokenBucket,
+    eject: TokenBucket,
+    join: TokenBucket,
+    chat: TokenBucket,
+    /// Most packets per action allowed in one window before it counts as abuse.
+    abuse_split: f64,
+    abuse_eject: f64,
+    abuse_join: f64,
+    abuse_chat: f64,
+    abuse_window: Duration,
+    window: AbuseWindow,
+    /// Packets dropped over the client's lifetime.
+    pub dropped: u64,
+}
+
+impl PacketLimiter {
+    pub fn new(limits: &PacketLimitsConfig, now: Instant) -> Self {
+        let window_secs = limits.abuse_seconds as f64;
+        Self {
+            split: TokenBucket::new(limits.split_per_second, limits.split_per_second, now),
+            eject: TokenBucket::new(limits.eject_per_second, limits.eject_per_second, now),
+            join: TokenBucket::new(limits.join_per_second, limits.join_per_second, now),
+            chat: TokenBucket::new(limits.chat_per_second, limits.chat_per_second, now),
+            abuse_split: limits.split_per_second * limits.abuse_factor * window_secs,
+            abuse_eject: limits.eject_per_second * limits.abuse_factor * window_secs,
+            abuse_join: limits.join_per_second * limits.abuse_factor * window_secs,
+            abuse_chat: limits.chat_per_second * limits.abuse_factor * window_secs,
+            abuse_window: Duration::from_secs(limits.abuse_seconds),
+            window: AbuseWindow { start: now, split: 0, eject: 0, join: 0, chat: 0 },
+            dropped: 0,
+        }
+    }
+
+    /// Account for one action packet.
+    pub fn check(&mut self, action: Action, now: Instant) -> Verdict {
+        if now.saturating_duration_since(self.window.start) >= self.abuse_window {
+            self.window = AbuseWindow { start: now, split: 0, eject: 0, join: 0, chat: 0 };
+        }
+        let (bucket, count, abuse_limit) = match action {
+            Action::Split => (&mut self.split, &mut self.window.split, self.abuse_split),
+            Action::Eject => (&mut self.eject, &mut self.window.eject, self.abuse_eject),
+            Action::Join => (&mut self.join, &mut self.window.join, self.abuse_join),
+            Action::Chat => (&mut self.chat, &mut self.window.chat, self.abuse_chat),
+        };
+        *count += 1;
+        if abuse_limit > 0.0 && f64::from(*count) > abuse_limit {
+            return Verdict::Abuse;
+        }
+        if bucket.try_take(now) {
+            Verdict::Allow
+        } else {
+            self.dropped += 1;
+            Verdict::Drop
+        }
+    }
+}
+
+/// Recent spawns per IP address, shared by all of an address's connections
+/// so reconnecting doesn't reset the count.
+#[derive(Debug, Default)]
+pub struct SpawnLimiter {
+    spawns: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+impl SpawnLimiter {
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a spawn from `ip` unless it already had `per_minute` in the
+    /// last minute (0 = unlimited).
+    pub fn try_spawn(&mut self, ip: IpAddr, per_minute: u32, now: Instant) -> bool {
+        if per_minute == 0 {
+            return true;
+        }
+        // Forget spawns older than the window, and addresses with none left
+        self.spawns.retain(|_, times| {
+            while times.front().is_some_and(|&at| now.saturating_duration_since(at) >= Self::WINDOW) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = self.spawns.entry(ip).or_default();
+        if times.len() >= per_minute as usize {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> PacketLimitsConfig {
+        PacketLimitsConfig {
+            split_per_second: 10.0,
+            eject_per_second: 0.0,
+            join_per_second: 0.0,
+            chat_per_second: 0.0,
+            spawns_per_minute_per_ip: 0,
+            abuse_factor: 10.0,
+            abuse_seconds: 5,
+        }
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 10.0, start);
+        assert_eq!((0..20).filter(|_| bucket.try_take(start)).count(), 10);
+
+        // 0.5s refills five tokens
+        let later = start + Duration::from_millis(500);
+        assert_eq!((0..20).filter(|_| bucket.try_take(later)).count(), 5);
+
+        // Never more than the capacity, however long the pause
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!((0..20).filter(|_| bucket.try_take(much_later)).count(), 10);
+    }
+
+    #[test]
+    fn test_limiter_drops_excess_and_flags_sustained_abuse() {
+        let start = Instant::now();
+        let mut limiter = PacketLimiter::new(&limits(), start);
+
+        // A 30-packet burst: 10 pass, 20 dropped, not yet abuse
+        let verdicts: Vec<Verdict> = (0..30).map(|_| limiter.check(Action::Split, start)).collect();
+        assert_eq!(verdicts.iter().filter(|v| **v == Verdict::Allow).count(), 10);
+        assert_eq!(limiter.dropped, 20);
+
+        // Unlimited eject is never dropped
+        assert!((0..1000).all(|_| limiter.check(Action::Eject, start) == Verdict::Allow));
+
+        // 100/s (10x the limit) for the rest of the 5s window trips the abuse check
+        let mut verdict = Verdict::Allow;
+        for i in 0..500 {
+            verdict = limiter.check(Action::Split, start + Duration::from_millis(i * 10));
+            if verdict == Verdict::Abuse {
+                break;
+            }
+        }
+        assert_eq!(verdict, Verdict::Abuse);
+
+        // A fresh window starts clean
+        let next_window = start + Duration::from_secs(6);
+        assert_eq!(limiter.check(Action::Split, next_window), Verdict::Allow);
+    }
+
+    #[test]
+    fn test_spawn_limit_is_per_address_and_rolls() {
+        let start = Instant::now();
+        let mut limiter = SpawnLimiter::new();
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        assert_eq!((0..5).filter(|_| limiter.try_spawn(a, 3, start)).count(), 3);
+        assert!(limiter.try_spawn(b, 3, start));
+        assert!(!limiter.try_spawn(a, 3, start + Duration::from_secs(59)));
+        assert!(limiter.try_spawn(a, 3, start + Duration::from_secs(60)));
+        assert!((0..100).all(|_| limiter.try_spawn(a, 0, start)));
+    }
+}
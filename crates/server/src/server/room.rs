@@ -0,0 +1,130 @@
+//! Independent worlds sharing one listener, picked by WebSocket request path.
+
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, RwLock};
+
+use super::{run_game_loop, GameState, ChatBroadcast, LeaderboardBroadcast, TargetedSender, WorldUpdateBroadcast};
+use crate::chatlog::ChatLogEntry;
+use crate::config::Config;
+use crate::dump::WorldDump;
+use crate::metrics::Metrics;
+
+/// One world: its game state, game loop and broadcast channels.
+pub struct Room {
+    /// Request path the room is served on.
+    pub path: String,
+    pub game_state: Arc<RwLock<GameState>>,
+    pub chat_tx: broadcast::Sender<ChatBroadcast>,
+    pub lb_tx: broadcast::Sender<LeaderboardBroadcast>,
+    pub world_tx: broadcast::Sender<WorldUpdateBroadcast>,
+    /// Per-connection queues for messages addressed to one client.
+    pub targeted_tx: TargetedSender,
+    /// The game state's metrics, readable without taking its lock.
+    pub metrics: Arc<Metrics>,
+}
+
+/// Receiving ends of a room's channels for one connection.
+pub struct RoomReceivers {
+    pub chat_rx: broadcast::Receiver<ChatBroadcast>,
+    pub lb_rx: broadcast::Receiver<LeaderboardBroadcast>,
+    pub world_rx: broadcast::Receiver<WorldUpdateBroadcast>,
+}
+
+impl Room {
+    /// Create the room's state and channels and start its game loop, from
+    /// `dump` when given, logging chat to `chat_log` when given.
+    pub async fn start(path: String, config: &Config, dump: Option<&WorldDump>, chat_log: Option<mpsc::Sender<ChatLogEntry>>) -> Self {
+        let (chat_tx, _) = broadcast::channel::<ChatBroadcast>(100);
+        let (lb_tx, _) = broadcast::channel::<LeaderboardBroadcast>(10);
+        let (world_tx, _) = broadcast::channel::<WorldUpdateBroadcast>(5);
+        let targeted_tx = TargetedSender::default();
+
+        let mut state = GameState::new(
+            config,
+            chat_tx.clone(),
+            lb_tx.clone(),
+            world_tx.clone(),
+            targeted_tx.clone(),
+        );
+        if let Some(dump) = dump {
+            state.load_dump(dump);
+        }
+        let metrics = Arc::clone(&state.metrics);
+        let game_state = Arc::new(RwLock::new(state));
+
+        if config.replay.enabled {
+            let mut state = game_state.write().await;
+            let gamemode = state.gamemode.name().to_string();
+            state.attach_replay(crate::replay::spawn_recorder(&config.replay, config.server.tick_interval_ms, gamemode));
+        }
+
+        if let Some(tx) = chat_log {
+            game_state.write().await.attach_chat_log(tx, path.clone());
+        }
+
+        let game_loop_state = Arc::clone(&game_state);
+        let tick_interval = config.server.tick_interval_ms;
+        tokio::spawn(async move {
+            run_game_loop(game_loop_state, tick_interval).await;
+        });
+
+        Self { path, game_state, chat_tx, lb_tx, world_tx, targeted_tx, metrics }
+    }
+
+    /// Subscribe a new connection to the room's broadcasts. Its targeted
+    /// messages come from registering with `targeted_tx` once it has a
+    /// client id.
+    pub fn subscribe(&self) -> RoomReceivers {
+        RoomReceivers {
+            chat_rx: self.chat_tx.subscribe(),
+            lb_rx: self.lb_tx.subscribe(),
+            world_rx: self.world_tx.subscribe(),
+        }
+    }
+}
+
+/// Every room in the process, in config order.
+pub struct RoomRegistry {
+    rooms: Vec<Room>,
+}
+
+impl RoomRegistry {
+    /// Start one room per entry of [`Config::room_configs`]; the first one
+    /// starts from `dump` when given.
+    pub async fn start(config: &Config, dump: Option<WorldDump>) -> Self {
+        // One writer for every room, so rotation has a single owner
+        let chat_log = (!config.logging.chat_file.is_empty()).then(|| crate::chatlog::spawn_writer(&config.logging));
+        let mut rooms = Vec::new();
+        for (i, (path, room_config)) in config.room_configs().into_iter().enumerate() {
+            let dump = dump.as_ref().filter(|_| i == 0);
+            rooms.push(Room::start(path, &room_config, dump, chat_log.clone()).await);
+        }
+        Self { rooms }
+    }
+
+    /// The room served on `path`. Unknown paths go to the first room, so a
+    /// single-room server accepts any path.
+    pub fn get(&self, path: &str) -> &Room {
+        self.rooms.iter()
+            .find(|room| room.path == path)
+            .unwrap_or(&self.rooms[0])
+    }
+
+    pub fn rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+
+    /// Flush every room's highscore board, at shutdown.
+    pub async fn save_highscores(&self) {
+        for room in &self.rooms {
+            room.game_state.write().await.save_highscores();
+        }
+    }
+
+    /// Write every room's sampled heatmap, at shutdown.
+    pub async fn save_heatmaps(&self) {
+        for room in &self.rooms {
+            room.game_state.read().await.save_heatmap();
+        }
+    }
+}
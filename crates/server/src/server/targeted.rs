@@ -0,0 +1,140 @@
+//! Per-connection queues for messages addressed to a single client, so one
+//! client's traffic never crowds another's out of a shared channel.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+use super::TargetedMessage;
+
+/// Messages queued per connection before new ones are dropped.
+const QUEUE_CAPACITY: usize = 128;
+
+struct Queue {
+    tx: mpsc::Sender<TargetedMessage>,
+    /// Messages dropped on a full queue since the receiver last looked.
+    dropped: Arc<AtomicU64>,
+}
+
+/// Routes each targeted message to its client's own bounded queue.
+#[derive(Clone, Default)]
+pub struct TargetedSender {
+    queues: Arc<Mutex<HashMap<u32, Queue>>>,
+}
+
+impl TargetedSender {
+    /// Open the queue for `client_id`, replacing any earlier one.
+    pub fn register(&self, client_id: u32) -> TargetedReceiver {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.queues.lock().unwrap().insert(client_id, Queue { tx, dropped: Arc::clone(&dropped) });
+        TargetedReceiver { client_id, rx, dropped, queues: Arc::clone(&self.queues) }
+    }
+
+    /// Queue `msg` for its client. Returns false when the client has no
+    /// queue, or when it is full and the message was dropped; the receiver
+    /// then sees a lag on its next receive.
+    pub fn send(&self, msg: TargetedMessage) -> bool {
+        let queues = self.queues.lock().unwrap();
+        let Some(queue) = queues.get(&msg.client_id) else {
+            return false;
+        };
+        match queue.tx.try_send(msg) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                queue.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Messages waiting across every connection's queue.
+    pub fn queued(&self) -> usize {
+        self.queues.lock().unwrap()
+            .values()
+            .map(|queue| queue.tx.max_capacity() - queue.tx.capacity())
+            .sum()
+    }
+}
+
+/// One connection's targeted messages. Dropping it closes the queue.
+pub struct TargetedReceiver {
+    client_id: u32,
+    rx: mpsc::Receiver<TargetedMessage>,
+    dropped: Arc<AtomicU64>,
+    queues: Arc<Mutex<HashMap<u32, Queue>>>,
+}
+
+impl TargetedReceiver {
+    /// Wait for the next message. Messages dropped since the last receive
+    /// are reported first as [`RecvError::Lagged`], as a broadcast
+    /// receiver would.
+    pub async fn recv(&mut self) -> Result<TargetedMessage, RecvError> {
+        match self.dropped.swap(0, Ordering::Relaxed) {
+            0 => self.rx.recv().await.ok_or(RecvError::Closed),
+            missed => Err(RecvError::Lagged(missed)),
+        }
+    }
+
+    /// Take the next message without waiting.
+    #[cfg(test)]
+    pub fn try_recv(&mut self) -> Result<TargetedMessage, tokio::sync::broadcast::error::TryRecvError> {
+        use tokio::sync::broadcast::error::TryRecvError;
+        match self.dropped.swap(0, Ordering::Relaxed) {
+            0 => self.rx.try_recv().map_err(|e| match e {
+                mpsc::error::TryRecvError::Empty => TryRecvError::Empty,
+                mpsc::error::TryRecvError::Disconnected => TryRecvError::Closed,
+            }),
+            missed => Err(TryRecvError::Lagged(missed)),
+        }
+    }
+}
+
+impl Drop for TargetedReceiver {
+    fn drop(&mut self) {
+        self.rx.close();
+        // A newer registration for the same id keeps its queue
+        let mut queues = self.queues.lock().unwrap();
+        if queues.get(&self.client_id).is_some_and(|queue| queue.tx.is_closed()) {
+            queues.remove(&self.client_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::TargetedMessageType;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    fn message(client_id: u32) -> TargetedMessage {
+        TargetedMessage { client_id, message: TargetedMessageType::ClearAll }
+    }
+
+    #[test]
+    fn test_full_queue_lags_only_its_own_client() {
+        let sender = TargetedSender::default();
+        let mut slow = sender.register(1);
+        let mut other = sender.register(2);
+
+        for _ in 0..QUEUE_CAPACITY + 3 {
+            sender.send(message(1));
+        }
+        assert!(sender.send(message(2)));
+        assert_eq!(sender.queued(), QUEUE_CAPACITY + 1);
+
+        assert!(matches!(slow.try_recv(), Err(TryRecvError::Lagged(3))));
+        assert_eq!(slow.try_recv().unwrap().client_id, 1);
+        assert_eq!(other.try_recv().unwrap().client_id, 2);
+        assert!(matches!(other.try_recv(), Err(TryRecvError::Empty)));
+
+        // A closed connection's queue is forgotten
+        drop(other);
+        assert!(!sender.send(message(2)));
+        assert_eq!(sender.queues.lock().unwrap().len(), 1);
+    }
+}
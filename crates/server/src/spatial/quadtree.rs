@@ -5,6 +5,10 @@
 
 use std::collections::HashMap;
 
+use glam::Vec2;
+
+use crate::entity::{CellData, CellType};
+
 /// Axis-aligned bounding box.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Bounds {
@@ -64,8 +68,9 @@ impl Bounds {
     }
 }
 
-/// An item stored in the QuadTree.
-#[derive(Debug, Clone)]
+/// An item stored in the QuadTree: a snapshot of the cell, so queries can
+/// be answered without looking each id up in the world.
+#[derive(Debug, Clone, Copy)]
 pub struct QuadItem {
     /// Unique node ID.
     pub id: u32,
@@ -75,6 +80,10 @@ pub struct QuadItem {
     pub y: f32,
     /// Current size (radius).
     pub size: f32,
+    /// Cell type.
+    pub cell_type: CellType,
+    /// Owning player, if any.
+    pub owner_id: Option<u32>,
     /// Bounding box (cached).
     pub bound: Bounds,
 }
@@ -87,10 +96,27 @@ impl QuadItem {
             x,
             y,
             size,
+            cell_type: CellType::default(),
+            owner_id: None,
             bound: Bounds::from_center(x, y, size),
         }
     }
 
+    /// Snapshot a cell's current state.
+    #[inline]
+    pub fn of_cell(data: &CellData) -> Self {
+        Self {
+            cell_type: data.cell_type,
+            owner_id: data.owner_id,
+            ..Self::new(data.node_id, data.position.x, data.position.y, data.size)
+        }
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
     /// Update position and size, recalculating bounds.
     #[inline]
     pub fn update(&mut self, x: f32, y: f32, size: f32) {
@@ -261,6 +287,22 @@ impl QuadTree {
     /// Find all items whose bounds intersect with the given bounds.
     #[inline]
     pub fn find_in_bounds(&mut self, bound: &Bounds) -> Vec<u32> {
+        let mut result = Vec::with_capacity(64);
+        self.for_each_in_bounds(bound, |item| result.push(item.id));
+        result
+    }
+
+    /// Like [`Self::find_in_bounds`], but copying out the items themselves.
+    #[inline]
+    pub fn find_items_in_bounds(&mut self, bound: &Bounds) -> Vec<QuadItem> {
+        let mut result = Vec::with_capacity(64);
+        self.for_each_in_bounds(bound, |item| result.push(*item));
+        result
+    }
+
+    /// Call `f` once for every item whose bounds intersect `bound`.
+    #[inline]
+    fn for_each_in_bounds(&mut self, bound: &Bounds, mut f: impl FnMut(&QuadItem)) {
         self.rebuild_grid();
 
         // Calculate which grid cells to check
@@ -274,9 +316,6 @@ impl QuadTree {
         let min_gy = min_gy.max(0) as usize;
         let max_gy = (max_gy as usize).min(self.grid_size - 1);
 
-        // Pre-allocate for typical result size
-        let mut result = Vec::with_capacity(64);
-        
         // Clear seen bits for IDs we might encounter
         for bits in &mut self.seen_bits {
             *bits = 0;
@@ -302,7 +341,7 @@ impl QuadTree {
                             if let Some(&idx) = self.id_to_index.get(&id) {
                                 let item = unsafe { self.items.get_unchecked(idx) };
                                 if item.bound.intersects(bound) {
-                                    result.push(id);
+                                    f(item);
                                 }
                             }
                         }
@@ -310,8 +349,6 @@ impl QuadTree {
                 }
             }
         }
-
-        result
     }
 
     /// Find all items whose bounds intersect with a circle.
@@ -321,6 +358,13 @@ impl QuadTree {
         self.find_in_bounds(&bound)
     }
 
+    /// Like [`Self::find_in_radius`], but copying out the items themselves.
+    #[inline]
+    pub fn find_items_in_radius(&mut self, cx: f32, cy: f32, radius: f32) -> Vec<QuadItem> {
+        let bound = Bounds::from_center(cx, cy, radius);
+        self.find_items_in_bounds(&bound)
+    }
+
     /// Get an item by ID.
     #[inline]
     pub fn get(&self, id: u32) -> Option<&QuadItem> {
@@ -0,0 +1,85 @@
+//! Native TLS, so browsers on HTTPS pages can connect with `wss://` without
+//! a reverse proxy.
+//!
+//! With `server.tls` set, the certificate chain and key are read into a
+//! rustls acceptor at startup and every accepted TCP stream goes through it
+//! before the WebSocket upgrade. On Unix, SIGHUP reads the files again so a
+//! renewed certificate takes effect without a restart; if that fails the old
+//! one stays in use.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tracing::{info, warn};
+
+pub use tokio_rustls::TlsAcceptor;
+pub use tokio_rustls::server::TlsStream;
+
+use crate::config::TlsConfig;
+
+/// Build an acceptor from the configured PEM files.
+pub fn load_acceptor(config: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("reading certificates from {}", config.cert_path))?;
+    anyhow::ensure!(!certs.is_empty(), "no certificates in {}", config.cert_path);
+    let key = PrivateKeyDer::from_pem_file(&config.key_path)
+        .with_context(|| format!("reading private key from {}", config.key_path))?;
+
+    let server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .with_context(|| format!("{} does not match {}", config.key_path, config.cert_path))?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// Finish the TLS handshake on `stream`, or `Ok(None)` if the peer doesn't
+/// within `timeout`.
+pub async fn accept(acceptor: &TlsAcceptor, stream: TcpStream, timeout: Duration) -> std::io::Result<Option<TlsStream<TcpStream>>> {
+    match tokio::time::timeout(timeout, acceptor.accept(stream)).await {
+        Ok(stream) => stream.map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Re-read the certificate into `acceptor`, keeping the old one on failure.
+pub fn reload_acceptor(config: &TlsConfig, acceptor: &mut TlsAcceptor) {
+    match load_acceptor(config) {
+        Ok(reloaded) => {
+            *acceptor = reloaded;
+            info!("Reloaded TLS certificate from {}", config.cert_path);
+        }
+        Err(e) => warn!("Keeping the old TLS certificate: {:#}", e),
+    }
+}
+
+/// SIGHUP, the certificate reload request. Never fires off Unix.
+pub struct ReloadSignal {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+        })
+    }
+
+    /// Wait for the next reload request.
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        if self.signal.recv().await.is_some() {
+            return;
+        }
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,232 @@
+//! Server-list tracker heartbeat: when `tracker.url` is set, a JSON summary
+//! of the server is POSTed there every `tracker.interval_seconds` so server
+//! browsers can list it.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::config::Config;
+use crate::server::game::PlayerCounts;
+use crate::server::room::RoomRegistry;
+
+/// Wait after the first failed heartbeat; doubles per failure up to the interval.
+const RETRY_MIN: Duration = Duration::from_secs(5);
+/// Deadline for connecting, sending and reading the status line.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and what to report, fixed at startup.
+struct Tracker {
+    host: String,
+    port: u16,
+    path: String,
+    name: String,
+    region: String,
+    /// Public address, or `None` when bound to a wildcard address and the
+    /// tracker should use the connection's source address.
+    address: Option<String>,
+    listen_port: u16,
+    tls: bool,
+    player_limit: usize,
+    interval: Duration,
+    started: Instant,
+}
+
+/// Start heartbeating to `config.tracker.url` for the server listening on
+/// `addr`. Abort the returned task at shutdown.
+pub fn spawn(config: &Config, addr: SocketAddr, rooms: Arc<RoomRegistry>) -> anyhow::Result<JoinHandle<()>> {
+    let (host, port, path) = parse_url(&config.tracker.url)?;
+    let name = if config.tracker.public_name.is_empty() {
+        config.server.name.clone()
+    } else {
+        config.tracker.public_name.clone()
+    };
+    let tracker = Tracker {
+        host,
+        port,
+        path,
+        name,
+        region: config.tracker.region.clone(),
+        address: (!addr.ip().is_unspecified()).then(|| addr.ip().to_string()),
+        listen_port: addr.port(),
+        tls: config.server.tls.is_some(),
+        player_limit: config.server.max_connections,
+        interval: Duration::from_secs_f64(config.tracker.interval_seconds),
+        started: Instant::now(),
+    };
+    Ok(tokio::spawn(tracker.run(rooms)))
+}
+
+impl Tracker {
+    async fn run(self, rooms: Arc<RoomRegistry>) {
+        let mut retry = RETRY_MIN;
+        loop {
+            let body = self.payload(&rooms).await.to_string();
+            let wait = match self.post(&body).await {
+                Ok(()) => {
+                    retry = RETRY_MIN;
+                    self.interval
+                }
+                Err(e) => {
+                    debug!("Tracker heartbeat to {}:{} failed: {:#}", self.host, self.port, e);
+                    let wait = retry.min(self.interval);
+                    retry = (retry * 2).min(self.interval);
+                    wait
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Snapshot every room, counting players the way the stats packet does.
+    async fn payload(&self, rooms: &RoomRegistry) -> Value {
+        let mut total = PlayerCounts::default();
+        let mut room_entries = Vec::new();
+        for room in rooms.rooms() {
+            let state = room.game_state.read().await;
+            let counts = state.player_counts();
+            total.alive += counts.alive;
+            total.dead += counts.dead;
+            total.spectating += counts.spectating;
+            total.bots += counts.bots;
+            room_entries.push(json!({
+                "path": room.path,
+                "gamemode": state.gamemode.name(),
+                "players": counts.total(),
+            }));
+        }
+        let gamemode = room_entries.first().map_or(Value::Null, |room| room["gamemode"].clone());
+        json!({
+            "name": self.name,
+            "region": self.region,
+            "address": self.address,
+            "port": self.listen_port,
+            "tls": self.tls,
+            "gamemode": gamemode,
+            "players": {
+                "total": total.total(),
+                "alive": total.alive,
+                "dead": total.dead,
+                "spectating": total.spectating,
+            },
+            "bots": total.bots,
+            "player_limit": self.player_limit,
+            "uptime": self.started.elapsed().as_secs(),
+            "rooms": room_entries,
+        })
+    }
+
+    /// POST `body` to the tracker, succeeding on any 2xx status.
+    async fn post(&self, body: &str) -> anyhow::Result<()> {
+        let exchange = async {
+            let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+            let request = format!(
+                "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                self.path, self.host, self.port, body.len(), body
+            );
+            stream.write_all(request.as_bytes()).await?;
+            let mut response = [0u8; 64];
+            let mut len = 0;
+            while len < response.len() && !response[..len].contains(&b'\n') {
+                let read = stream.read(&mut response[len..]).await?;
+                if read == 0 {
+                    break;
+                }
+                len += read;
+            }
+            anyhow::Ok(String::from_utf8_lossy(&response[..len]).into_owned())
+        };
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, exchange).await.context("timed out")??;
+        let status = response.lines().next().unwrap_or_default();
+        match status.split(' ').nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => bail!("unexpected response {:?}", status),
+        }
+    }
+}
+
+/// Split an `http://host[:port][/path]` URL into its parts.
+fn parse_url(url: &str) -> anyhow::Result<(String, u16, String)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        bail!("tracker URL must start with http://: {:?}", url);
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().with_context(|| format!("bad tracker port in {:?}", url))?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        bail!("tracker URL has no host: {:?}", url);
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_heartbeat_payload_reaches_tracker() {
+        let tracker = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = Config::default();
+        config.tracker.url = format!("http://{}/announce", tracker.local_addr().unwrap());
+        config.tracker.region = "eu-west".to_string();
+        let rooms = Arc::new(RoomRegistry::start(&config, None).await);
+        let addr: SocketAddr = "0.0.0.0:11443".parse().unwrap();
+        let task = spawn(&config, addr, rooms).unwrap();
+
+        let (mut stream, _) = tokio::time::timeout(Duration::from_secs(5), tracker.accept()).await.unwrap().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        let body = loop {
+            let read = stream.read(&mut buf).await.unwrap();
+            assert!(read > 0, "request ended early");
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length: usize = head.lines()
+                    .find_map(|l| l.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    assert!(head.starts_with("POST /announce HTTP/1.1\r\n"), "{}", head);
+                    break body.to_string();
+                }
+            }
+        };
+        stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+        task.abort();
+
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["name"], config.server.name.as_str());
+        assert_eq!(payload["region"], "eu-west");
+        assert_eq!(payload["address"], Value::Null);
+        assert_eq!(payload["port"], 11443);
+        assert_eq!(payload["tls"], false);
+        assert_eq!(payload["gamemode"], "FFA");
+        assert_eq!(payload["players"], json!({"total": 0, "alive": 0, "dead": 0, "spectating": 0}));
+        assert!(payload["bots"].is_u64());
+        assert_eq!(payload["player_limit"], config.server.max_connections);
+        assert_eq!(payload["rooms"][0]["path"], "/");
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(parse_url("http://example.com").unwrap(), ("example.com".to_string(), 80, "/".to_string()));
+        assert_eq!(parse_url("http://10.0.0.1:8080/api/heartbeat").unwrap(), ("10.0.0.1".to_string(), 8080, "/api/heartbeat".to_string()));
+        assert!(parse_url("https://example.com").is_err());
+        assert!(parse_url("http://:80/").is_err());
+    }
+}
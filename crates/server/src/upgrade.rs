@@ -0,0 +1,151 @@
+//! Who may open a WebSocket.
+//!
+//! Without limits any website can open sockets to the server from its
+//! visitors' browsers. Browsers always send the page's Origin with a
+//! WebSocket upgrade, so `server.allowed_origins` turns those away; requests
+//! without an Origin come from non-browser clients, which could send any
+//! Origin they like, and aren't checked. `server.connect_token` closes the
+//! server to everyone who wasn't given the `?token=` to put on the URL.
+
+use crate::config::ServerConfig;
+use crate::metrics::UpgradeRejection;
+
+/// The upgrade checks from the server config.
+#[derive(Debug, Clone, Default)]
+pub struct UpgradePolicy {
+    /// Lowercased patterns.
+    allowed_origins: Vec<String>,
+    connect_token: String,
+}
+
+impl UpgradePolicy {
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            allowed_origins: config.allowed_origins.iter().map(|origin| origin.to_ascii_lowercase()).collect(),
+            connect_token: config.connect_token.clone(),
+        }
+    }
+
+    /// Check an upgrade request's Origin header and URL query.
+    pub fn check(&self, origin: Option<&str>, query: Option<&str>) -> Result<(), UpgradeRejection> {
+        if let Some(origin) = origin
+            && !self.allowed_origins.is_empty()
+        {
+            let origin = origin.to_ascii_lowercase();
+            if !self.allowed_origins.iter().any(|pattern| glob_match(pattern, &origin)) {
+                return Err(UpgradeRejection::Origin);
+            }
+        }
+        if !self.connect_token.is_empty() {
+            // The web client percent-encodes the token
+            let token = query
+                .into_iter()
+                .flat_map(|query| query.split('&'))
+                .find_map(|pair| pair.strip_prefix("token="))
+                .and_then(percent_decode);
+            if !token.is_some_and(|token| constant_time_eq(token.as_bytes(), self.connect_token.as_bytes())) {
+                return Err(UpgradeRejection::Token);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decode a URL query value: `%XX` escapes and `+` for a space. `None` for a
+/// malformed escape or a result that isn't UTF-8.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        match b {
+            b'%' => {
+                let hex = rest.get(..2).filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+                bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                rest = &rest[2..];
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Compare secrets without stopping at the first difference, so response
+/// times don't tell how much of a guess was right. Only the length leaks.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let suffix = parts.pop().unwrap_or("");
+    for part in parts {
+        match text.find(part) {
+            Some(at) => text = &text[at + part.len()..],
+            None => return false,
+        }
+    }
+    text.len() >= suffix.len() && text.ends_with(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_and_token_checks() {
+        let open = UpgradePolicy::default();
+        assert_eq!(open.check(Some("https://evil.example"), None), Ok(()));
+
+        let config = ServerConfig {
+            allowed_origins: vec!["https://play.example.com".into(), "https://*.Example.org".into()],
+            ..Default::default()
+        };
+        let policy = UpgradePolicy::new(&config);
+        assert_eq!(policy.check(Some("https://play.example.com"), None), Ok(()));
+        assert_eq!(policy.check(Some("HTTPS://PLAY.EXAMPLE.COM"), None), Ok(()));
+        assert_eq!(policy.check(Some("https://a.b.example.org"), None), Ok(()));
+        assert_eq!(policy.check(Some("https://example.org"), None), Err(UpgradeRejection::Origin));
+        assert_eq!(policy.check(Some("https://play.example.com.evil.net"), None), Err(UpgradeRejection::Origin));
+        assert_eq!(policy.check(Some("http://play.example.com"), None), Err(UpgradeRejection::Origin));
+        // Non-browser clients send no Origin
+        assert_eq!(policy.check(None, None), Ok(()));
+
+        let config = ServerConfig { connect_token: "s3cret".into(), ..config };
+        let policy = UpgradePolicy::new(&config);
+        assert_eq!(policy.check(None, Some("token=s3cret")), Ok(()));
+        assert_eq!(policy.check(None, Some("room=2&token=s3cret")), Ok(()));
+        assert_eq!(policy.check(None, Some("token=s3cre")), Err(UpgradeRejection::Token));
+        assert_eq!(policy.check(None, Some("xtoken=s3cret")), Err(UpgradeRejection::Token));
+        assert_eq!(policy.check(None, None), Err(UpgradeRejection::Token));
+        assert_eq!(policy.check(Some("https://evil.example"), Some("token=s3cret")), Err(UpgradeRejection::Origin));
+
+        // Tokens arrive percent-encoded, as encodeURIComponent leaves them
+        let config = ServerConfig { connect_token: "a b&c=d/é".into(), ..config };
+        let policy = UpgradePolicy::new(&config);
+        assert_eq!(policy.check(None, Some("token=a%20b%26c%3Dd%2F%C3%A9")), Ok(()));
+        assert_eq!(policy.check(None, Some("token=a+b%26c%3dd/%c3%a9")), Ok(()));
+        assert_eq!(policy.check(None, Some("token=a%20b&c=d/é")), Err(UpgradeRejection::Token));
+        assert_eq!(policy.check(None, Some("token=a%2")), Err(UpgradeRejection::Token));
+        assert_eq!(policy.check(None, Some("token=a%+20b%26c%3Dd%2F%C3%A9")), Err(UpgradeRejection::Token));
+        assert_eq!(policy.check(None, Some("token=%FF")), Err(UpgradeRejection::Token));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("a*c", "abc"));
+        assert!(glob_match("a*c", "ac"));
+        assert!(!glob_match("a*c", "ab"));
+        assert!(glob_match("a*b*c", "a-b-b-c"));
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+}
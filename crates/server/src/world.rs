@@ -2,18 +2,33 @@
 //!
 //! Manages all cells in the game world.
 
+use crate::config::FoodSizeDistribution;
 use crate::entity::{Cell, CellData, CellType, EjectedMass, Food, PlayerCell, Virus, MotherCell};
 use crate::spatial::{QuadItem, QuadTree};
 use glam::Vec2;
 use protocol::Color;
-use rand::Rng;
-use std::collections::HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The simulation's random number generator. Everything that shapes the
+/// world draws from [`World::rng`], so a seed and the same inputs replay it.
+pub type GameRng = StdRng;
 
 /// The game world containing all cells.
 #[derive(Debug)]
 pub struct World {
-    /// Next node ID to assign.
-    next_node_id: u32,
+    /// Next never-used node ID.
+    pub(crate) next_node_id: u32,
+    /// Current tick, as of [`Self::begin_tick`]; stamps freed ids.
+    tick: u64,
+    /// Ids of removed cells waiting out [`ID_QUARANTINE_TICKS`], oldest first.
+    quarantined_ids: VecDeque<(u32, u64)>,
+    /// Ids of removed cells ready to be handed out again.
+    free_ids: Vec<u32>,
 
     /// All cells by ID.
     pub(crate) cells: HashMap<u32, CellEntry>,
@@ -26,6 +41,10 @@ pub struct World {
     pub virus_cells: Vec<u32>,
     /// Ejected mass (cellType = 3).
     pub eject_cells: Vec<u32>,
+    /// (birth tick, id) of ejected mass, oldest first, for
+    /// [`Self::expire_ejects`]. Entries of pellets already eaten stay until
+    /// they reach the front.
+    eject_births: VecDeque<(u64, u32)>,
     /// Mother cells (cellType = 4).
     pub mother_cells: Vec<u32>,
 
@@ -42,11 +61,39 @@ pub struct World {
 
     /// World border.
     pub border: WorldBorder,
+    /// Walls and speed pads from `map.file`.
+    pub map: Arc<WorldMap>,
 
     /// QuadTree for spatial queries.
     pub quad_tree: QuadTree,
+
+    /// Colors food is sampled from (empty = random colors).
+    pub food_palette: Vec<Color>,
+    /// How food sizes are picked between min and max.
+    pub food_size_distribution: FoodSizeDistribution,
+    /// Chance that a spawned pellet is golden bonus food.
+    pub food_bonus_chance: f32,
+    /// Keep spawned food off player cells.
+    pub food_avoid_players: bool,
+    /// How strongly food spawns favor sparse sectors; see [`FoodSectors`].
+    pub food_sector_bias: f32,
+
+    /// Source of every random choice in the simulation; see [`Self::seed`].
+    pub rng: GameRng,
 }
 
+/// Food grows in slices: each tick touches 1/N of the pellets.
+const FOOD_GROW_SLICES: u64 = 25;
+/// Size a pellet gains each time its slice comes around.
+const FOOD_GROW_STEP: f32 = 0.5;
+/// Random positions tried for a spawn before giving up until the next tick.
+const SPAWN_ATTEMPTS: usize = 10;
+/// Ticks a removed cell's id waits before reuse, so every client has been
+/// told the old cell is gone before one with the same id shows up. World
+/// broadcasts run at most a few ticks behind; a client further behind is
+/// resynced.
+const ID_QUARANTINE_TICKS: u64 = 25;
+
 /// A cell entry in the world.
 #[derive(Debug)]
 pub enum CellEntry {
@@ -102,6 +149,8 @@ pub struct WorldBorder {
     pub max_y: f32,
     pub width: f32,
     pub height: f32,
+    /// Whether the map wraps around (a torus) instead of ending at a wall.
+    pub wrap: bool,
 }
 
 impl WorldBorder {
@@ -115,13 +164,72 @@ impl WorldBorder {
             max_y: half_h,
             width,
             height,
+            wrap: false,
         }
     }
 
+    /// Move `pos` inside the border.
+    #[inline]
+    pub fn clamp(&self, pos: Vec2) -> Vec2 {
+        Vec2::new(pos.x.clamp(self.min_x, self.max_x), pos.y.clamp(self.min_y, self.max_y))
+    }
+
+    /// Bring `pos` back onto the map: across the seam on a wrapping map,
+    /// clamped to the edge otherwise.
+    #[inline]
+    pub fn confine(&self, pos: Vec2) -> Vec2 {
+        if !self.wrap {
+            return self.clamp(pos);
+        }
+        // rem_euclid can round up to the full width; that lands on the far edge
+        Vec2::new(
+            (self.min_x + (pos.x - self.min_x).rem_euclid(self.width)).min(self.max_x),
+            (self.min_y + (pos.y - self.min_y).rem_euclid(self.height)).min(self.max_y),
+        )
+    }
+
+    /// Keep a moved cell on the map. A solid border keeps the whole cell
+    /// inside; a wrapping one only its center, the rest shows past the seam.
+    #[inline]
+    pub fn confine_cell(&self, data: &mut CellData) {
+        if self.wrap {
+            data.position = self.confine(data.position);
+        } else {
+            data.check_border(self.min_x, self.min_y, self.max_x, self.max_y);
+        }
+    }
+
+    /// Shortest offset from `from` to `to`, which on a wrapping map may
+    /// cross a seam.
+    #[inline]
+    pub fn delta(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let mut d = to - from;
+        if self.wrap {
+            d.x -= self.width * (d.x / self.width).round();
+            d.y -= self.height * (d.y / self.height).round();
+        }
+        d
+    }
+
+    /// Shifts that bring the far side of each seam within `radius` of
+    /// `center` next to it, always starting with no shift. Only a wrapping
+    /// map has seams.
+    fn seam_offsets(&self, center: Vec2, radius: f32) -> impl Iterator<Item = Vec2> {
+        let axis = |c: f32, min: f32, max: f32, size: f32| {
+            [
+                Some(0.0),
+                (self.wrap && c + radius > max).then_some(size),
+                (self.wrap && c - radius < min).then_some(-size),
+            ]
+        };
+        let xs = axis(center.x, self.min_x, self.max_x, self.width);
+        let ys = axis(center.y, self.min_y, self.max_y, self.height);
+        ys.into_iter().flatten().flat_map(move |y| xs.into_iter().flatten().map(move |x| Vec2::new(x, y)))
+    }
+
     /// Get a random position within the border.
     #[inline]
-    pub fn random_position(&self) -> Vec2 {
-        let mut rng = rand::rng();
+    pub fn random_position(&self, rng: &mut impl Rng) -> Vec2 {
         Vec2::new(
             rng.random_range(self.min_x..self.max_x),
             rng.random_range(self.min_y..self.max_y),
@@ -129,17 +237,243 @@ impl WorldBorder {
     }
 }
 
+/// Map file layout version this server reads.
+pub const MAP_FILE_VERSION: u32 = 1;
+
+/// What a map object does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapObjectKind {
+    /// Player cells can't enter it.
+    Wall,
+    /// Scales the speed of player cells whose center is on it; below 1 it
+    /// is a slow zone.
+    SpeedPad { multiplier: f32 },
+}
+
+/// A static, axis-aligned rectangle on the map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapObject {
+    pub kind: MapObjectKind,
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl MapObject {
+    fn contains(&self, pos: Vec2) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    /// The object as sent to clients, before scrambling.
+    pub fn shape(&self) -> protocol::packets::MapShape {
+        use protocol::packets::{MapShape, MapShapeKind};
+        let (kind, speed) = match self.kind {
+            MapObjectKind::Wall => (MapShapeKind::Wall, 1.0),
+            MapObjectKind::SpeedPad { multiplier } => (MapShapeKind::SpeedPad, multiplier),
+        };
+        MapShape { kind, min_x: self.min.x, min_y: self.min.y, max_x: self.max.x, max_y: self.max.y, speed }
+    }
+}
+
+/// Just the version, read first so a newer file fails with a clear error.
+#[derive(Deserialize)]
+struct MapFileVersion {
+    version: u32,
+}
+
+/// The `map.file` layout.
+#[derive(Deserialize)]
+struct MapFile {
+    objects: Vec<MapFileObject>,
+}
+
+/// One object in a map file; `x` and `y` are its top-left corner.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MapFileObject {
+    Wall { x: f32, y: f32, width: f32, height: f32 },
+    SpeedPad { x: f32, y: f32, width: f32, height: f32, multiplier: f32 },
+}
+
+/// The map's static objects.
+#[derive(Debug, Default)]
+pub struct WorldMap {
+    pub objects: Vec<MapObject>,
+}
+
+impl WorldMap {
+    /// Read a map file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a map file's JSON.
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let MapFileVersion { version } = serde_json::from_str(json)?;
+        if version != MAP_FILE_VERSION {
+            anyhow::bail!("unsupported map version {} (expected {})", version, MAP_FILE_VERSION);
+        }
+        let file: MapFile = serde_json::from_str(json)?;
+        let mut objects = Vec::with_capacity(file.objects.len());
+        for (i, object) in file.objects.into_iter().enumerate() {
+            let (kind, x, y, width, height) = match object {
+                MapFileObject::Wall { x, y, width, height } => (MapObjectKind::Wall, x, y, width, height),
+                MapFileObject::SpeedPad { x, y, width, height, multiplier } => {
+                    if !(multiplier > 0.0 && multiplier.is_finite()) {
+                        anyhow::bail!("object {}: multiplier must be positive (got {})", i, multiplier);
+                    }
+                    (MapObjectKind::SpeedPad { multiplier }, x, y, width, height)
+                }
+            };
+            if !(width > 0.0 && height > 0.0) {
+                anyhow::bail!("object {}: width and height must be positive (got {}x{})", i, width, height);
+            }
+            objects.push(MapObject { kind, min: Vec2::new(x, y), max: Vec2::new(x + width, y + height) });
+        }
+        Ok(Self { objects })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Combined multiplier of every speed pad under `pos`.
+    pub fn speed_multiplier(&self, pos: Vec2) -> f32 {
+        self.objects.iter()
+            .filter(|object| object.contains(pos))
+            .map(|object| match object.kind {
+                MapObjectKind::SpeedPad { multiplier } => multiplier,
+                MapObjectKind::Wall => 1.0,
+            })
+            .product()
+    }
+
+    /// Whether a cell of `size` at `pos` would be pushed by a wall.
+    pub fn blocks(&self, pos: Vec2, size: f32) -> bool {
+        self.wall_around(pos, size).is_some()
+    }
+
+    /// Push a cell out through the nearest side of any wall it entered.
+    /// Like the border, a wall keeps half the cell's size away. Exits that
+    /// land inside a neighbouring wall are skipped, and the push repeats
+    /// until the cell is clear of every wall.
+    pub fn push_out_of_walls(&self, data: &mut CellData) {
+        for _ in 0..=self.objects.len() {
+            let p = data.position;
+            let Some((min, max)) = self.wall_around(p, data.size) else {
+                return;
+            };
+            let mut exits = [
+                (p.x - min.x, Vec2::new(min.x, p.y)),
+                (max.x - p.x, Vec2::new(max.x, p.y)),
+                (p.y - min.y, Vec2::new(p.x, min.y)),
+                (max.y - p.y, Vec2::new(p.x, max.y)),
+            ];
+            exits.sort_by(|a, b| a.0.total_cmp(&b.0));
+            data.position = exits.iter()
+                .map(|&(_, exit)| exit)
+                .find(|&exit| !self.blocks(exit, data.size))
+                .unwrap_or(exits[0].1);
+        }
+    }
+
+    /// Bounds, grown by half of `size`, of the first wall pushing on `pos`.
+    fn wall_around(&self, pos: Vec2, size: f32) -> Option<(Vec2, Vec2)> {
+        let margin = Vec2::splat(size / 2.0);
+        self.walls()
+            .map(|wall| (wall.min - margin, wall.max + margin))
+            .find(|&(min, max)| pos.cmpgt(min).all() && pos.cmplt(max).all())
+    }
+
+    fn walls(&self) -> impl Iterator<Item = &MapObject> {
+        self.objects.iter().filter(|object| object.kind == MapObjectKind::Wall)
+    }
+}
+
+/// Sectors per side of the grid food spawning is balanced over.
+pub const FOOD_SECTOR_GRID: usize = 16;
+
+/// Spawn weights for a [`FOOD_SECTOR_GRID`]² grid over the border, so food
+/// refills areas that were eaten bare instead of landing uniformly.
+///
+/// A sector's weight mixes a uniform share with its share of the total
+/// deficit below the average pellet count: `(1 - bias) / sectors +
+/// bias * deficit / total_deficit`. With no sector below average the
+/// weights are uniform.
+#[derive(Debug)]
+pub struct FoodSectors {
+    /// Running sum of the weights, one entry per sector, row-major from min y.
+    cumulative: Vec<f32>,
+}
+
+impl FoodSectors {
+    /// Weights from the current pellet positions.
+    pub fn new(border: &WorldBorder, positions: impl IntoIterator<Item = Vec2>, bias: f32) -> Self {
+        let sectors = FOOD_SECTOR_GRID * FOOD_SECTOR_GRID;
+        let mut counts = vec![0u32; sectors];
+        let bin = |value: f32, min: f32, size: f32| {
+            let scaled = (value - min) / size * FOOD_SECTOR_GRID as f32;
+            (scaled.max(0.0) as usize).min(FOOD_SECTOR_GRID - 1)
+        };
+        let mut total = 0u32;
+        for pos in positions {
+            counts[bin(pos.y, border.min_y, border.height) * FOOD_SECTOR_GRID + bin(pos.x, border.min_x, border.width)] += 1;
+            total += 1;
+        }
+
+        let average = total as f32 / sectors as f32;
+        let deficits: Vec<f32> = counts.iter().map(|&count| (average - count as f32).max(0.0)).collect();
+        let total_deficit: f32 = deficits.iter().sum();
+        let bias = if total_deficit > 0.0 { bias.clamp(0.0, 1.0) } else { 0.0 };
+        let mut sum = 0.0;
+        let cumulative = deficits
+            .iter()
+            .map(|deficit| {
+                let share = if total_deficit > 0.0 { deficit / total_deficit } else { 0.0 };
+                sum += (1.0 - bias) / sectors as f32 + bias * share;
+                sum
+            })
+            .collect();
+        Self { cumulative }
+    }
+
+    /// Draw a sector index according to the weights.
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        let target = rng.random_range(0.0..self.total());
+        self.cumulative.partition_point(|&sum| sum <= target).min(self.cumulative.len() - 1)
+    }
+
+    /// A random position inside a sector drawn by [`Self::sample`].
+    pub fn random_position(&self, border: &WorldBorder, rng: &mut impl Rng) -> Vec2 {
+        let sector = self.sample(rng);
+        let (col, row) = (sector % FOOD_SECTOR_GRID, sector / FOOD_SECTOR_GRID);
+        let cell_width = border.width / FOOD_SECTOR_GRID as f32;
+        let cell_height = border.height / FOOD_SECTOR_GRID as f32;
+        Vec2::new(
+            border.min_x + (col as f32 + rng.random::<f32>()) * cell_width,
+            border.min_y + (row as f32 + rng.random::<f32>()) * cell_height,
+        )
+    }
+
+    fn total(&self) -> f32 {
+        self.cumulative.last().copied().unwrap_or(0.0)
+    }
+}
+
 impl World {
-    /// Create a new world with the given border size.
+    /// Create a new world with the given border size and an unseeded RNG.
     pub fn new(width: f32, height: f32) -> Self {
         let border = WorldBorder::new(width, height);
         Self {
             next_node_id: 1,
+            tick: 0,
+            quarantined_ids: VecDeque::with_capacity(1024),
+            free_ids: Vec::with_capacity(1024),
             cells: HashMap::with_capacity(2048),
             player_cells: Vec::with_capacity(256),
             food_cells: Vec::with_capacity(1024),
             virus_cells: Vec::with_capacity(64),
             eject_cells: Vec::with_capacity(256),
+            eject_births: VecDeque::with_capacity(256),
             mother_cells: Vec::with_capacity(16),
             player_pos: HashMap::with_capacity(256),
             food_pos: HashMap::with_capacity(1024),
@@ -150,11 +484,27 @@ impl World {
             moving_cells: Vec::with_capacity(256),
             quad_tree: QuadTree::for_world(border.min_x, border.min_y, border.max_x, border.max_y),
             border,
+            map: Arc::new(WorldMap::default()),
+            food_palette: Vec::new(),
+            food_size_distribution: FoodSizeDistribution::Uniform,
+            food_bonus_chance: 0.0,
+            food_avoid_players: false,
+            food_sector_bias: 0.0,
+            rng: GameRng::from_os_rng(),
         }
     }
 
-    /// Get the next node ID.
+    /// Restart the RNG from `seed`.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = GameRng::seed_from_u64(seed);
+    }
+
+    /// Get the next node ID: a recycled one when any is out of quarantine,
+    /// so ids (and the bitsets indexed by them) stay dense.
     pub fn next_id(&mut self) -> u32 {
+        if let Some(id) = self.free_ids.pop() {
+            return id;
+        }
         let id = self.next_node_id;
         self.next_node_id = self.next_node_id.wrapping_add(1);
         if self.next_node_id == 0 {
@@ -163,6 +513,18 @@ impl World {
         id
     }
 
+    /// Start a tick: ids freed [`ID_QUARANTINE_TICKS`] ago become reusable.
+    pub fn begin_tick(&mut self, tick: u64) {
+        self.tick = tick;
+        while let Some(&(id, freed)) = self.quarantined_ids.front() {
+            if tick.saturating_sub(freed) < ID_QUARANTINE_TICKS {
+                break;
+            }
+            self.quarantined_ids.pop_front();
+            self.free_ids.push(id);
+        }
+    }
+
     /// Get a cell by ID.
     #[inline]
     pub fn get_cell(&self, id: u32) -> Option<&CellEntry> {
@@ -178,8 +540,7 @@ impl World {
     /// Add a player cell to the world.
     pub fn add_player_cell(&mut self, cell: PlayerCell) -> u32 {
         let id = cell.data().node_id;
-        let data = cell.data();
-        self.quad_tree.insert(QuadItem::new(id, data.position.x, data.position.y, data.size));
+        self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         let pos = self.player_cells.len();
         self.player_cells.push(id);
         self.player_pos.insert(id, pos);
@@ -190,8 +551,7 @@ impl World {
     /// Add a food cell to the world.
     pub fn add_food(&mut self, cell: Food) -> u32 {
         let id = cell.data().node_id;
-        let data = cell.data();
-        self.quad_tree.insert(QuadItem::new(id, data.position.x, data.position.y, data.size));
+        self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         let pos = self.food_cells.len();
         self.food_cells.push(id);
         self.food_pos.insert(id, pos);
@@ -202,8 +562,7 @@ impl World {
     /// Add a virus to the world.
     pub fn add_virus(&mut self, cell: Virus) -> u32 {
         let id = cell.data().node_id;
-        let data = cell.data();
-        self.quad_tree.insert(QuadItem::new(id, data.position.x, data.position.y, data.size));
+        self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         let pos = self.virus_cells.len();
         self.virus_cells.push(id);
         self.virus_pos.insert(id, pos);
@@ -214,20 +573,71 @@ impl World {
     /// Add ejected mass to the world.
     pub fn add_eject(&mut self, cell: EjectedMass) -> u32 {
         let id = cell.data().node_id;
-        let data = cell.data();
-        self.quad_tree.insert(QuadItem::new(id, data.position.x, data.position.y, data.size));
+        self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         let pos = self.eject_cells.len();
         self.eject_cells.push(id);
         self.eject_pos.insert(id, pos);
+        self.eject_births.push_back((cell.data().tick_of_birth, id));
         self.cells.insert(id, CellEntry::Eject(cell));
         id
     }
 
+    /// Despawn ejected mass born `lifetime_ticks` or more before `tick`, then
+    /// the oldest pellets beyond `max_amount` (0 disables either limit).
+    /// Only walks the front of the birth queue. Returns the removed ids.
+    pub fn expire_ejects(&mut self, tick: u64, lifetime_ticks: u64, max_amount: usize) -> Vec<u32> {
+        let mut removed = Vec::new();
+        while let Some(&(born, id)) = self.eject_births.front() {
+            let live = self.is_eject_born(id, born);
+            let expired = lifetime_ticks > 0 && tick.saturating_sub(born) >= lifetime_ticks;
+            let over = max_amount > 0 && self.eject_cells.len() > max_amount;
+            if live && !expired && !over {
+                break;
+            }
+            self.eject_births.pop_front();
+            if live {
+                self.remove_cell(id);
+                removed.push(id);
+            }
+        }
+        // Eaten pellets queued behind a live one pile up; drop them once they
+        // outnumber the live entries
+        if self.eject_births.len() > 2 * self.eject_cells.len() + 64 {
+            let mut births = std::mem::take(&mut self.eject_births);
+            births.retain(|&(born, id)| self.is_eject_born(id, born));
+            self.eject_births = births;
+        }
+        removed
+    }
+
+    /// Remove the oldest of `ids` until at most `keep` are left, returning
+    /// the removed ids.
+    pub fn remove_oldest(&mut self, ids: &[u32], keep: usize) -> Vec<u32> {
+        if ids.len() <= keep {
+            return Vec::new();
+        }
+        let mut by_age: Vec<(u64, u32)> = ids.iter()
+            .filter_map(|&id| self.get_cell(id).map(|cell| (cell.data().tick_of_birth, id)))
+            .collect();
+        by_age.sort_unstable();
+        by_age.truncate(ids.len() - keep);
+        let removed: Vec<u32> = by_age.into_iter().map(|(_, id)| id).collect();
+        for &id in &removed {
+            self.remove_cell(id);
+        }
+        removed
+    }
+
+    /// Whether `id` is still the ejected pellet born at `born`, not a cell
+    /// that got its recycled id.
+    fn is_eject_born(&self, id: u32, born: u64) -> bool {
+        matches!(self.cells.get(&id), Some(CellEntry::Eject(c)) if c.data().tick_of_birth == born)
+    }
+
     /// Add a mother cell to the world.
     pub fn add_mother_cell(&mut self, cell: MotherCell) -> u32 {
         let id = cell.data().node_id;
-        let data = cell.data();
-        self.quad_tree.insert(QuadItem::new(id, data.position.x, data.position.y, data.size));
+        self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         let pos = self.mother_cells.len();
         self.mother_cells.push(id);
         self.mother_pos.insert(id, pos);
@@ -244,6 +654,12 @@ impl World {
         }
     }
 
+    /// Whether a cell is in the moving (boosted) list.
+    #[inline]
+    pub fn is_moving(&self, id: u32) -> bool {
+        self.moving_pos.contains_key(&id)
+    }
+
     /// Remove from moving cells list (O(1))
     pub fn remove_from_moving(&mut self, id: u32) {
         if let Some(pos) = self.moving_pos.remove(&id) {
@@ -325,6 +741,7 @@ impl World {
             // Remove from moving list (O(1))
             self.remove_from_moving(id);
 
+            self.quarantined_ids.push_back((id, self.tick));
             Some(entry)
         } else {
             None
@@ -345,8 +762,7 @@ impl World {
 
     /// Generate a random color.
     #[inline]
-    pub fn random_color() -> Color {
-        let mut rng = rand::rng();
+    pub fn random_color(rng: &mut impl Rng) -> Color {
         Color::new(
             rng.random_range(50..=255),
             rng.random_range(50..=255),
@@ -371,21 +787,72 @@ impl World {
             to_spawn
         };
 
-        let mut rng = rand::rng();
+        let sectors = (self.food_sector_bias > 0.0 && count > 0).then(|| {
+            let positions = self.food_cells.iter().filter_map(|id| self.cells.get(id)).map(|entry| entry.data().position);
+            FoodSectors::new(&self.border, positions, self.food_sector_bias)
+        });
+
         for _ in 0..count {
-            let pos = self.border.random_position();
             let size = if max_size > min_size {
-                rng.random_range(min_size..max_size)
+                match self.food_size_distribution {
+                    FoodSizeDistribution::Uniform => self.rng.random_range(min_size..max_size),
+                    FoodSizeDistribution::WeightedSmall => {
+                        let t: f32 = self.rng.random();
+                        min_size + (max_size - min_size) * t * t
+                    }
+                }
             } else {
                 min_size
             };
-            let id = self.next_id();
-            let mut food = Food::new(id, pos, size, tick);
-            food.set_color(Self::random_color());
+            let pos = match (&sectors, self.food_avoid_players) {
+                (Some(sectors), _) => match self.sector_spawn_position(sectors, size) {
+                    Some(pos) => pos,
+                    None => continue,
+                },
+                (None, true) => match self.free_spawn_position(size) {
+                    Some(pos) => pos,
+                    None => continue,
+                },
+                (None, false) => self.open_position(size),
+            };
+            let food = self.new_food(pos, size, tick);
             self.add_food(food);
         }
     }
 
+    /// A pellet at `pos` with a palette color and the usual bonus chance.
+    pub fn new_food(&mut self, pos: Vec2, size: f32, tick: u64) -> Food {
+        let color = if self.food_palette.is_empty() {
+            Self::random_color(&mut self.rng)
+        } else {
+            self.food_palette[self.rng.random_range(0..self.food_palette.len())]
+        };
+        let id = self.next_id();
+        let mut food = Food::new(id, pos, size, tick);
+        food.set_color(color);
+        if self.food_bonus_chance > 0.0 && self.rng.random::<f32>() < self.food_bonus_chance {
+            food.make_bonus();
+        }
+        food
+    }
+
+    /// Grow one slice of the food pellets toward `max_size`.
+    pub fn grow_food(&mut self, max_size: f32, tick: u64) {
+        let offset = (tick % FOOD_GROW_SLICES) as usize;
+        for i in (offset..self.food_cells.len()).step_by(FOOD_GROW_SLICES as usize) {
+            let id = self.food_cells[i];
+            let Some(CellEntry::Food(food)) = self.cells.get_mut(&id) else {
+                continue;
+            };
+            let data = food.data_mut();
+            if data.size >= max_size {
+                continue;
+            }
+            data.set_size((data.size + FOOD_GROW_STEP).min(max_size));
+            self.quad_tree.update(id, data.position.x, data.position.y, data.size);
+        }
+    }
+
     /// Spawn viruses up to the minimum amount.
     pub fn spawn_viruses(&mut self, min_amount: usize, max_amount: usize, min_size: f32, tick: u64) {
         let current = self.virus_cells.len();
@@ -395,7 +862,10 @@ impl World {
 
         let to_spawn = min_amount - current;
         for _ in 0..to_spawn {
-            let pos = self.border.random_position();
+            // Never inside a player that would pop on it (or eat it) at once
+            let Some(pos) = self.free_spawn_position(min_size) else {
+                continue;
+            };
             let id = self.next_id();
             let virus = Virus::new(id, pos, min_size, tick);
             self.add_virus(virus);
@@ -406,6 +876,59 @@ impl World {
         }
     }
 
+    /// A random position where a cell of `size` overlaps no bigger player
+    /// cell or wall, or `None` if [`SPAWN_ATTEMPTS`] tries all land on one.
+    pub fn free_spawn_position(&mut self, size: f32) -> Option<Vec2> {
+        for _ in 0..SPAWN_ATTEMPTS {
+            let pos = self.border.random_position(&mut self.rng);
+            if !self.map.blocks(pos, size) && !self.overlaps_bigger_player(pos, size) {
+                return Some(pos);
+            }
+        }
+        None
+    }
+
+    /// A random position clear of walls for a cell of `size`; after
+    /// [`SPAWN_ATTEMPTS`] misses the last try is used anyway.
+    pub fn open_position(&mut self, size: f32) -> Vec2 {
+        let mut pos = self.border.random_position(&mut self.rng);
+        for _ in 1..SPAWN_ATTEMPTS {
+            if !self.map.blocks(pos, size) {
+                break;
+            }
+            pos = self.border.random_position(&mut self.rng);
+        }
+        pos
+    }
+
+    /// A pellet position in a sector drawn from `sectors`, clear of walls
+    /// and, with `food_avoid_players`, of bigger players. Like
+    /// [`Self::open_position`] the last try is used anyway when only walls
+    /// are avoided; like [`Self::free_spawn_position`] it gives up when
+    /// players must be avoided too.
+    fn sector_spawn_position(&mut self, sectors: &FoodSectors, size: f32) -> Option<Vec2> {
+        for attempt in 1..=SPAWN_ATTEMPTS {
+            let pos = sectors.random_position(&self.border, &mut self.rng);
+            let blocked = self.map.blocks(pos, size)
+                || (self.food_avoid_players && self.overlaps_bigger_player(pos, size));
+            if !blocked || (attempt == SPAWN_ATTEMPTS && !self.food_avoid_players) {
+                return Some(pos);
+            }
+        }
+        None
+    }
+
+    fn overlaps_bigger_player(&mut self, pos: Vec2, size: f32) -> bool {
+        self.find_nearby(pos.x, pos.y, size)
+            .into_iter()
+            .any(|item| {
+                let reach = item.size + size;
+                item.cell_type == CellType::Player
+                    && item.size > size
+                    && item.position().distance_squared(pos) < reach * reach
+            })
+    }
+
     /// Iterate over all cells.
     #[inline]
     pub fn iter_cells(&self) -> impl Iterator<Item = (&u32, &CellEntry)> {
@@ -418,18 +941,56 @@ impl World {
         self.cells.iter_mut()
     }
 
-    /// Find all cells within a radius of a point using the QuadTree.
+    /// Find all cells within a radius of a point using the QuadTree, as
+    /// snapshots of their position, size, type and owner. Look a cell up with
+    /// [`Self::get_cell`] only for anything else, or to change it.
+    ///
+    /// On a wrapping map, cells across a seam are found too, and their
+    /// snapshots are moved next to the point (possibly off the map) so plain
+    /// differences between positions are the shortest way around.
+    #[inline]
+    pub fn find_nearby(&mut self, cx: f32, cy: f32, radius: f32) -> Vec<QuadItem> {
+        let mut items = self.quad_tree.find_items_in_radius(cx, cy, radius);
+        let mut offsets = self.border.seam_offsets(Vec2::new(cx, cy), radius).skip(1).peekable();
+        if offsets.peek().is_none() {
+            return items;
+        }
+        for offset in offsets {
+            let found = self.quad_tree.find_items_in_radius(cx - offset.x, cy - offset.y, radius);
+            items.extend(found.into_iter().map(|mut item| {
+                item.update(item.x + offset.x, item.y + offset.y, item.size);
+                item
+            }));
+        }
+        // A query wider than the map sees some cells from more than one side
+        let mut seen = HashSet::with_capacity(items.len());
+        items.retain(|item| seen.insert(item.id));
+        items
+    }
+
+    /// Ids of the cells within a radius of a point.
+    #[deprecated(note = "use `find_nearby`, which also returns position, size, type and owner")]
     #[inline]
     pub fn find_cells_in_radius(&mut self, cx: f32, cy: f32, radius: f32) -> Vec<u32> {
         self.quad_tree.find_in_radius(cx, cy, radius)
     }
 
-    /// Update a cell's position in the QuadTree.
+    /// Position of the food pellet nearest to `pos` within `radius`, if any.
+    pub fn nearest_food(&mut self, pos: Vec2, radius: f32) -> Option<Vec2> {
+        self.find_nearby(pos.x, pos.y, radius)
+            .into_iter()
+            .filter(|item| item.cell_type == CellType::Food)
+            .map(|item| item.position())
+            .filter(|food_pos| food_pos.distance_squared(pos) <= radius * radius)
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+    }
+
+    /// Refresh a cell's QuadTree snapshot after moving, resizing or
+    /// re-owning it.
     #[inline]
     pub fn update_cell_position(&mut self, id: u32) {
         if let Some(cell) = self.cells.get(&id) {
-            let data = cell.data();
-            self.quad_tree.update(id, data.position.x, data.position.y, data.size);
+            self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         }
     }
 
@@ -437,9 +998,8 @@ impl World {
     #[inline]
     pub fn rebuild_quadtree(&mut self) {
         self.quad_tree.clear();
-        for (&id, cell) in &self.cells {
-            let data = cell.data();
-            self.quad_tree.insert(QuadItem::new(id, data.position.x, data.position.y, data.size));
+        for cell in self.cells.values() {
+            self.quad_tree.insert(QuadItem::of_cell(cell.data()));
         }
     }
 }
@@ -453,3 +1013,227 @@ pub struct CellCounts {
     pub ejected: usize,
     pub total: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viruses_never_spawn_inside_big_players() {
+        let mut world = World::new(10_000.0, 10_000.0);
+        // A stationary cell covering 30% of the map
+        let radius = (0.3 * 10_000.0 * 10_000.0 / std::f32::consts::PI).sqrt();
+        let id = world.next_id();
+        world.add_player_cell(PlayerCell::new(id, 1, Vec2::ZERO, radius, 0));
+        let overlaps = |pos: Vec2, size: f32| pos.length() < radius + size;
+
+        let mut spawned = 0;
+        for tick in 0..1000 {
+            world.spawn_viruses(5, 10, 100.0, tick);
+            for virus in world.virus_cells.clone() {
+                let pos = world.get_cell(virus).unwrap().data().position;
+                assert!(!overlaps(pos, 100.0), "virus spawned inside the player at {}", pos);
+                world.remove_cell(virus);
+                spawned += 1;
+            }
+        }
+        // Rejections only cost retries, not spawns
+        assert!(spawned > 4900);
+
+        // Food does the same when asked to
+        world.food_avoid_players = true;
+        world.spawn_food(2000, 2000, 1000, 10.0, 10.0, 0);
+        assert!(world.food_cells.iter().all(|&id| !overlaps(world.get_cell(id).unwrap().data().position, 10.0)));
+    }
+
+    #[test]
+    fn test_find_nearby_snapshots_follow_updates() {
+        let mut world = World::new(10_000.0, 10_000.0);
+        let id = world.next_id();
+        world.add_player_cell(PlayerCell::new(id, 7, Vec2::ZERO, 50.0, 0));
+        let food = world.next_id();
+        world.add_food(Food::new(food, Vec2::new(30.0, 0.0), 10.0, 0));
+
+        let nearby = world.find_nearby(0.0, 0.0, 100.0);
+        let cell = nearby.iter().find(|item| item.id == id).unwrap();
+        assert_eq!((cell.cell_type, cell.owner_id, cell.size), (CellType::Player, Some(7), 50.0));
+        assert_eq!(nearby.iter().find(|item| item.id == food).unwrap().cell_type, CellType::Food);
+
+        let data = world.get_cell_mut(id).unwrap().data_mut();
+        data.position = Vec2::new(1000.0, 0.0);
+        data.set_size(80.0);
+        data.owner_id = Some(8);
+        world.update_cell_position(id);
+        assert!(world.find_nearby(0.0, 0.0, 100.0).iter().all(|item| item.id != id));
+        let moved = world.find_nearby(1000.0, 0.0, 10.0);
+        assert_eq!(moved.len(), 1);
+        assert_eq!((moved[0].position(), moved[0].size, moved[0].owner_id), (Vec2::new(1000.0, 0.0), 80.0, Some(8)));
+    }
+
+    #[test]
+    fn test_wrapping_border_sees_across_seams() {
+        let mut world = World::new(1000.0, 1000.0);
+        let border = world.border;
+        assert_eq!(border.confine(Vec2::new(520.0, -600.0)), Vec2::new(500.0, -500.0));
+        assert_eq!(border.delta(Vec2::new(490.0, 0.0), Vec2::new(-490.0, 0.0)), Vec2::new(-980.0, 0.0));
+        let id = world.next_id();
+        world.add_food(Food::new(id, Vec2::new(-490.0, -490.0), 10.0, 0));
+        assert!(world.find_nearby(490.0, 490.0, 50.0).is_empty());
+
+        world.border.wrap = true;
+        let border = world.border;
+        assert_eq!(border.confine(Vec2::new(520.0, -600.0)), Vec2::new(-480.0, 400.0));
+        assert_eq!(border.delta(Vec2::new(490.0, 0.0), Vec2::new(-490.0, 0.0)), Vec2::new(20.0, 0.0));
+        // The food in the opposite corner shows up next to the query, once
+        let nearby = world.find_nearby(490.0, 490.0, 50.0);
+        assert_eq!(nearby.len(), 1);
+        assert_eq!(nearby[0].position(), Vec2::new(510.0, 510.0));
+        assert_eq!(world.find_nearby(0.0, 0.0, 2000.0).len(), 1);
+        // The cell itself stays where it is
+        assert_eq!(world.get_cell(id).unwrap().data().position, Vec2::new(-490.0, -490.0));
+    }
+
+    /// Probability of `sector` being drawn.
+    fn weight(sectors: &FoodSectors, sector: usize) -> f32 {
+        let before = if sector == 0 { 0.0 } else { sectors.cumulative[sector - 1] };
+        (sectors.cumulative[sector] - before) / sectors.total()
+    }
+
+    #[test]
+    fn test_food_sector_sampling_matches_weights() {
+        let border = WorldBorder::new(1600.0, 1600.0);
+        // Four pellets in every sector of the right half, none on the left
+        let mut positions = Vec::new();
+        for row in 0..FOOD_SECTOR_GRID {
+            for col in FOOD_SECTOR_GRID / 2..FOOD_SECTOR_GRID {
+                let pos = Vec2::new(border.min_x + col as f32 * 100.0 + 50.0, border.min_y + row as f32 * 100.0 + 50.0);
+                positions.extend([pos; 4]);
+            }
+        }
+        let sectors = FOOD_SECTOR_GRID * FOOD_SECTOR_GRID;
+
+        let uniform = FoodSectors::new(&border, positions.iter().copied(), 0.0);
+        assert!((0..sectors).all(|i| (weight(&uniform, i) - 1.0 / sectors as f32).abs() < 1e-6));
+
+        // Half the weight spread evenly, the other half over the empty left half
+        let sampler = FoodSectors::new(&border, positions.iter().copied(), 0.5);
+        let empty = 0.5 / sectors as f32 + 0.5 / (sectors / 2) as f32;
+        let full = 0.5 / sectors as f32;
+        for i in 0..sectors {
+            let expected = if i % FOOD_SECTOR_GRID < FOOD_SECTOR_GRID / 2 { empty } else { full };
+            assert!((weight(&sampler, i) - expected).abs() < 1e-6, "sector {} weighs {}", i, weight(&sampler, i));
+        }
+
+        let mut rng = GameRng::seed_from_u64(7);
+        let draws = 256_000;
+        let mut hits = vec![0u32; sectors];
+        for _ in 0..draws {
+            hits[sampler.sample(&mut rng)] += 1;
+        }
+        for (i, &count) in hits.iter().enumerate() {
+            let expected = weight(&sampler, i) * draws as f32;
+            let tolerance = 5.0 * expected.sqrt();
+            assert!((count as f32 - expected).abs() < tolerance, "sector {} drawn {} times, expected {}", i, count, expected);
+        }
+
+        // Fully biased spawns only refill the empty half
+        let mut world = World::new(1600.0, 1600.0);
+        world.seed(3);
+        world.food_sector_bias = 1.0;
+        for (tick, pos) in positions.iter().enumerate() {
+            let food = world.new_food(*pos, 10.0, tick as u64);
+            world.add_food(food);
+        }
+        let before: HashSet<u32> = world.food_cells.iter().copied().collect();
+        world.spawn_food(0, 10_000, 100, 10.0, 10.0, 0);
+        let spawned: Vec<Vec2> = world.food_cells.iter()
+            .filter(|id| !before.contains(id))
+            .map(|&id| world.get_cell(id).unwrap().data().position)
+            .collect();
+        assert_eq!(spawned.len(), 100);
+        assert!(spawned.iter().all(|pos| pos.x < 0.0), "{:?}", spawned);
+    }
+
+    #[test]
+    fn test_map_walls_and_pads() {
+        assert!(WorldMap::parse(r#"{"version": 2, "objects": []}"#).is_err());
+        assert!(WorldMap::parse(r#"{"version": 1, "objects": [{"type": "wall", "x": 0, "y": 0, "width": 0, "height": 5}]}"#).is_err());
+        let map = WorldMap::parse(r#"{"version": 1, "objects": [
+            {"type": "wall", "x": 0, "y": 0, "width": 100, "height": 100},
+            {"type": "speed_pad", "x": -300, "y": 0, "width": 100, "height": 100, "multiplier": 2},
+            {"type": "speed_pad", "x": -250, "y": 0, "width": 100, "height": 100, "multiplier": 0.5}
+        ]}"#).unwrap();
+        assert_eq!(map.objects[0], MapObject { kind: MapObjectKind::Wall, min: Vec2::ZERO, max: Vec2::splat(100.0) });
+
+        assert_eq!(map.speed_multiplier(Vec2::new(-280.0, 50.0)), 2.0);
+        assert_eq!(map.speed_multiplier(Vec2::new(-220.0, 50.0)), 1.0);
+        assert_eq!(map.speed_multiplier(Vec2::new(500.0, 50.0)), 1.0);
+
+        // A cell that moved into the wall leaves through the nearest side
+        let mut cell = Food::new(1, Vec2::new(10.0, 40.0), 20.0, 0);
+        assert!(map.blocks(cell.data().position, 20.0));
+        map.push_out_of_walls(cell.data_mut());
+        assert_eq!(cell.data().position, Vec2::new(-10.0, 40.0));
+        assert!(!map.blocks(cell.data().position, 20.0));
+
+        // Touching walls: the nearest exit of one lies inside the other
+        let map = WorldMap::parse(r#"{"version": 1, "objects": [
+            {"type": "wall", "x": 0, "y": 0, "width": 100, "height": 100},
+            {"type": "wall", "x": 100, "y": 0, "width": 100, "height": 100}
+        ]}"#).unwrap();
+        let mut cell = Food::new(1, Vec2::new(95.0, 40.0), 20.0, 0);
+        map.push_out_of_walls(cell.data_mut());
+        assert!(!map.blocks(cell.data().position, 20.0));
+    }
+
+    #[test]
+    fn test_removed_ids_are_recycled_after_quarantine() {
+        let mut world = World::new(1000.0, 1000.0);
+        world.begin_tick(10);
+        let ids: Vec<u32> = (0..3).map(|_| world.next_id()).collect();
+        for &id in &ids {
+            world.add_eject(EjectedMass::new(id, Vec2::ZERO, 10.0, 10));
+        }
+        world.remove_cell(ids[1]);
+
+        for tick in 10..10 + ID_QUARANTINE_TICKS {
+            world.begin_tick(tick);
+            assert_ne!(world.next_id(), ids[1]);
+        }
+        world.begin_tick(10 + ID_QUARANTINE_TICKS);
+        assert_eq!(world.next_id(), ids[1]);
+        // Only once
+        assert!(world.next_id() > ids[2]);
+    }
+
+    /// `cargo test --release -p server -- --ignored bench_eject_churn --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_eject_churn() {
+        let mut world = World::new(14_142.0, 14_142.0);
+        let mut ejects = VecDeque::new();
+        let mut removed = fixedbitset::FixedBitSet::with_capacity(10_000);
+        let mut highest = 0;
+        let ticks = 1000;
+        let start = std::time::Instant::now();
+        for tick in 0..ticks {
+            world.begin_tick(tick);
+            // A tenth of 10k ejected masses replaced every tick
+            for _ in 0..1000 {
+                if ejects.len() == 10_000 {
+                    world.remove_cell(ejects.pop_front().unwrap());
+                }
+                let id = world.next_id();
+                highest = highest.max(id);
+                let pos = world.border.random_position(&mut world.rng);
+                world.add_eject(EjectedMass::new(id, pos, 15.0, tick));
+                ejects.push_back(id);
+            }
+            // What collision detection does with the ids every tick
+            removed.clear();
+            removed.grow(highest as usize + 1);
+            world.find_nearby(0.0, 0.0, 500.0);
+        }
+        println!("eject churn: {:?}/tick, highest id {}", start.elapsed() / ticks as u32, highest);
+    }
+}
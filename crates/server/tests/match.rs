@@ -0,0 +1,185 @@
+//! A scripted match against a real server: two clients connect over
+//! WebSocket, join, move, split, eject and chat, then one eats the other.
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use protocol::packets::{
+    build_chat, build_handshake_key, build_join, build_mouse, build_opcode, build_protocol_version, ChatMessagePayload,
+    ClientOpcode, EatRecord, NodeUpdate, ServerPacket, SetBorderPayload,
+};
+use protocol::BinaryWriter;
+use server::Config;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const PROTOCOL: u32 = 6;
+const OPERATOR_PASSWORD: &str = "scripted";
+/// How long any one expected packet may take to arrive.
+const WAIT: Duration = Duration::from_secs(10);
+
+/// A scripted client and everything the server has told it so far.
+struct Player {
+    name: &'static str,
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    border: Option<SetBorderPayload>,
+    /// Ids of the cells this player owns.
+    owned: HashSet<u32>,
+    /// Last update of every node in view.
+    nodes: HashMap<u32, NodeUpdate>,
+    eats: Vec<EatRecord>,
+    leaderboard: Vec<String>,
+    chat: Vec<ChatMessagePayload>,
+}
+
+impl Player {
+    /// Connect and finish the protocol handshake.
+    async fn connect(addr: SocketAddr, name: &'static str) -> Self {
+        let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/", addr)).await.unwrap();
+        let mut player = Self {
+            name,
+            ws,
+            border: None,
+            owned: HashSet::new(),
+            nodes: HashMap::new(),
+            eats: Vec::new(),
+            leaderboard: Vec::new(),
+            chat: Vec::new(),
+        };
+        player.send(build_protocol_version(PROTOCOL)).await;
+        player.send(build_handshake_key(1)).await;
+        player
+    }
+
+    async fn send(&mut self, packet: BinaryWriter) {
+        self.ws.send(Message::Binary(packet.finish())).await.unwrap();
+    }
+
+    async fn chat(&mut self, message: &str) {
+        self.send(build_chat(PROTOCOL, message)).await;
+    }
+
+    /// Run an operator command and wait for the server's reply.
+    async fn command(&mut self, command: &str, reply: &str) {
+        self.chat(command).await;
+        self.wait_for(reply, |p| p.chat.iter().any(|c| c.is_server && c.message.starts_with(reply))).await;
+        self.chat.clear();
+    }
+
+    /// Read packets until `done` holds, failing the test after [`WAIT`].
+    async fn wait_for(&mut self, what: &str, done: impl Fn(&Self) -> bool) {
+        let name = self.name;
+        let waited = tokio::time::timeout(WAIT, async {
+            while !done(self) {
+                match self.ws.next().await {
+                    Some(Ok(Message::Binary(data))) => self.apply(&data),
+                    Some(Ok(_)) => {}
+                    other => panic!("{} lost the connection: {:?}", name, other),
+                }
+            }
+        })
+        .await;
+        assert!(waited.is_ok(), "{} never saw {}", name, what);
+    }
+
+    fn apply(&mut self, data: &[u8]) {
+        match ServerPacket::parse(data, PROTOCOL).unwrap() {
+            ServerPacket::Batch { packets } => packets.iter().for_each(|packet| self.apply(packet)),
+            ServerPacket::SetBorder(border) => self.border = Some(border),
+            ServerPacket::ClearAll => {
+                self.nodes.clear();
+                self.owned.clear();
+            }
+            ServerPacket::AddNode { node_id } => {
+                self.owned.insert(node_id);
+            }
+            ServerPacket::UpdateNodes(update) => {
+                self.eats.extend(update.eats);
+                for node in update.nodes {
+                    self.nodes.insert(node.node_id, node);
+                }
+                for id in update.removed {
+                    self.nodes.remove(&id);
+                    self.owned.remove(&id);
+                }
+            }
+            ServerPacket::LeaderboardFfa(leaderboard) => {
+                self.leaderboard = leaderboard.entries.into_iter().map(|entry| entry.name).collect();
+            }
+            ServerPacket::ChatMessage(message) => self.chat.push(message),
+            _ => {}
+        }
+    }
+
+    /// Whether one of this player's own cells is in view.
+    fn sees_itself(&self) -> bool {
+        self.owned.iter().any(|id| self.nodes.contains_key(id))
+    }
+}
+
+#[tokio::test]
+async fn test_scripted_match() {
+    let mut config = Config::default();
+    config.server.operator_password = OPERATOR_PASSWORD.to_string();
+    // Unscrambled ids and coordinates, so the script can aim in world space
+    config.server.scramble_level = 0;
+    config.stats.highscores_file = String::new();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = tokio::spawn(server::serve(listener, config, None, async {
+        let _ = stopped.await;
+    }));
+
+    let mut alice = Player::connect(addr, "alice").await;
+    let mut bob = Player::connect(addr, "bob").await;
+    for player in [&mut alice, &mut bob] {
+        player.wait_for("the border", |p| p.border.is_some()).await;
+        let border = player.border.as_ref().unwrap();
+        assert!(border.min_x < 0.0 && border.max_x > 0.0 && border.min_y < 0.0 && border.max_y > 0.0);
+
+        player.send(build_join(PROTOCOL, player.name)).await;
+        player.wait_for("its own cell", Player::sees_itself).await;
+        player.wait_for("itself on the leaderboard", |p| p.leaderboard.iter().any(|name| name == p.name)).await;
+    }
+
+    alice.chat("glhf").await;
+    bob.wait_for("alice's chat", |p| p.chat.iter().any(|c| c.name == "alice" && c.message == "glhf")).await;
+
+    for player in [&mut alice, &mut bob] {
+        player.command(&format!("/op {}", OPERATOR_PASSWORD), "Operator mode enabled").await;
+    }
+
+    // Grow, then split and eject towards the right
+    alice.command("/mass 400", "Set 1 cells to mass 400").await;
+    alice.wait_for("its cell grow", |p| p.owned.iter().any(|id| p.nodes.get(id).is_some_and(|n| n.size >= 150))).await;
+    let (x, y) = {
+        let node = alice.owned.iter().find_map(|id| alice.nodes.get(id)).unwrap();
+        (node.x as i32, node.y as i32)
+    };
+    alice.send(build_mouse(x + 1000, y)).await;
+    alice.send(build_opcode(ClientOpcode::Split)).await;
+    alice.wait_for("a second cell after splitting", |p| p.owned.len() >= 2).await;
+    alice.send(build_opcode(ClientOpcode::Eject)).await;
+    alice.wait_for("its ejected mass", |p| p.nodes.values().any(|n| n.flags & 0x20 != 0)).await;
+
+    // Put both on the same spot; alice's halves are far bigger than bob
+    let prey = bob.owned.clone();
+    assert!(!prey.is_empty());
+    alice.command("/teleport 0 0", "Teleported").await;
+    bob.command("/teleport 0 0", "Teleported").await;
+    alice.send(build_mouse(0, 0)).await;
+    bob.send(build_mouse(0, 0)).await;
+    alice
+        .wait_for("bob being eaten", |p| p.eats.iter().any(|eat| prey.contains(&eat.eaten_id) && p.owned.contains(&eat.eater_id)))
+        .await;
+    bob.wait_for("its cells gone", |p| p.owned.is_empty()).await;
+
+    stop.send(()).unwrap();
+    tokio::time::timeout(WAIT, server).await.unwrap().unwrap().unwrap();
+}